@@ -583,7 +583,11 @@ impl WriteBatch<soliton_panic_merkle_tree> for PanicWriteBatch {
     fn rollback_to_save_point(&mut self) -> Result<()> {
         panic!()
     }
-    fn merge(&mut self, src: Self) {
+    fn iterate<F>(&self, _: F) -> Result<()>
+        where F: FnMut(WriteBatchCommand) -> Result<bool> {
+        panic!()
+    }
+    fn merge(&mut self, src: Self) -> Result<()> {
         panic!()
     }
 }
@@ -608,6 +612,9 @@ impl Mutable for PanicWriteBatch {
     fn delete_range_namespaced(&mut self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
         panic!()
     }
+    fn merge_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()> {
+        panic!()
+    }
 }
 */
 
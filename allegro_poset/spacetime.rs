@@ -482,6 +482,7 @@ pub fn update_attribute_map_from_causetid_triples(attribute_map: &mut AttributeM
                     causetq_TV::Ref(causetids::einsteindb_TYPE_REF)     => { builder.causet_locale_type(ValueType::Ref); },
                     causetq_TV::Ref(causetids::einsteindb_TYPE_STRING)  => { builder.causet_locale_type(ValueType::String); },
                     causetq_TV::Ref(causetids::einsteindb_TYPE_UUID)    => { builder.causet_locale_type(ValueType::Uuid); },
+                    causetq_TV::Ref(causetids::einsteindb_TYPE_JSON)    => { builder.causet_locale_type(ValueType::Json); },
                     _ => bail!(einsteindbErrorKind::BadTopographAssertion(format!("Expected [... :einsteindb/causet_localeType :einsteindb.type/*] but got [... :einsteindb/causet_localeType {:?}] for causetid {} and attribute {}", causet_locale, causetid, attr)))
                 }
             },
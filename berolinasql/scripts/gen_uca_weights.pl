@@ -0,0 +1,155 @@
+#!/usr/bin/env perl
+#
+# Copyright 2021-2023 WHTCORPS INC
+#
+# Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+# this file File except in compliance with the License. You may obtain a copy of the
+# License at http://www.apache.org/licenses/LICENSE-2.0
+# Unless required by applicable law or agreed to in writing, software distributed
+# under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+# CONDITIONS OF ANY KIND, either express or implied. See the License for the
+# specific language governing permissions and limitations under the License.
+#
+# Generates berolinasql/src/uca_weights.rs, the primary weights of the UCA collations,
+# from a DUCET `allkeys.txt`:
+#
+#     perl gen_uca_weights.pl allkeys.txt UCA_4_0_0=4.0,bmp UCA_9_0_0=9.0 > ../src/uca_weights.rs
+#
+# Each NAME=VERSION argument emits a table of the characters encoded in Unicode VERSION;
+# `,bmp` leaves out the supplementary planes, which `utf8mb4_unicode_ci` doesn't weigh.
+# Given the allkeys.txt of VERSION itself (4.0.0 for `utf8mb4_unicode_ci`, 9.0.0 for
+# `utf8mb4_0900_ai_ci`) the filter changes nothing. Given a later one, it drops the
+# characters added since, which then get the implicit weights of unassigned code points
+# as they did in VERSION; characters that a later UCA moved keep their later weights.
+#
+# Only primary weights are kept, ignorable characters have none, and contractions are
+# left out, as MySQL's `_ai_ci` collations compare at the primary level and don't
+# contract in the default (untailored) order. Han and the `@implicitweights` scripts
+# get UCA implicit weights, computed from the ranges each table lists.
+
+use strict;
+use warnings;
+
+use Unicode::UCD qw(prop_invlist search_invlist);
+
+my $allkeys = shift @ARGV or die "usage: $0 allkeys.txt NAME=VERSION[,bmp]...\n";
+@ARGV or die "usage: $0 allkeys.txt NAME=VERSION[,bmp]...\n";
+
+my $ducet_version;
+my %weights;      # code point => [primary weights]
+my @implicit;     # [first, last, base] from @implicitweights
+
+open(my $fh, '<', $allkeys) or die "$allkeys: $!\n";
+while (my $line = <$fh>) {
+    $line =~ s/\s*#.*//;
+    next if $line =~ /^\s*$/;
+    if ($line =~ /^\@version\s+(\S+)/) {
+        $ducet_version = $1;
+        next;
+    }
+    if ($line =~ /^\@implicitweights\s+([0-9A-F]+)\.\.([0-9A-F]+);\s*([0-9A-F]+)/) {
+        push @implicit, [hex($1), hex($2), hex($3)];
+        next;
+    }
+    next if $line =~ /^\@/;
+    my ($chars, $elements) = $line =~ /^([0-9A-F ]+?)\s*;\s*(.*)$/ or die "bad line: $line";
+    my @chars = split ' ', $chars;
+    next if @chars > 1;
+    my @primaries = grep { $_ != 0 } map { hex } $elements =~ /\[[.*]([0-9A-F]+)\./g;
+    $weights{hex($chars[0])} = \@primaries;
+}
+close($fh);
+defined $ducet_version or die "$allkeys has no \@version\n";
+
+my @unified_ideograph = prop_invlist("Unified_Ideograph");
+
+sub in_invlist {
+    my ($invlist, $cp) = @_;
+    my $i = search_invlist($invlist, $cp);
+    return defined($i) && $i % 2 == 0;
+}
+
+# Maximal runs of the code points of [$first, $last] for which $pred holds.
+sub runs {
+    my ($first, $last, $pred) = @_;
+    my @runs;
+    my $start;
+    for my $cp ($first .. $last + 1) {
+        my $holds = $cp <= $last && $pred->($cp);
+        if ($holds && !defined $start) {
+            $start = $cp;
+        } elsif (!$holds && defined $start) {
+            push @runs, [$start, $cp - 1];
+            undef $start;
+        }
+    }
+    return @runs;
+}
+
+sub is_core_han {
+    my ($cp) = @_;
+    return ($cp >= 0x4E00 && $cp <= 0x9FFF) || ($cp >= 0xF900 && $cp <= 0xFAFF);
+}
+
+print <<"HEADER";
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+//! Primary weights of the UCA collations.
+//!
+//! Generated by `berolinasql/scripts/gen_uca_weights.pl` from allkeys-$ducet_version.txt; don't edit.
+
+use super::collation::{
+    UcaImplicit,
+    UcaTable,
+};
+HEADER
+
+for my $arg (@ARGV) {
+    my ($name, $version, $options) = $arg =~ /^(\w+)=([\d.]+)(?:,(\w+))?$/ or die "bad table: $arg\n";
+    my $bmp = defined($options) && $options eq 'bmp';
+    my @present = prop_invlist("Present_In=$version");
+    @present or die "Unicode::UCD doesn't know Unicode $version\n";
+    my $present = sub { in_invlist(\@present, $_[0]) };
+    my $max = $bmp ? 0xFFFF : 0x10FFFF;
+
+    print "\n/// The characters encoded in Unicode $version, from allkeys-$ducet_version.txt.\n";
+    print "pub static $name: UcaTable = UcaTable {\n";
+    print "    weights: &[\n";
+    for my $cp (sort { $a <=> $b } keys %weights) {
+        next if $cp > $max || !$present->($cp);
+        my $ws = join(', ', map { sprintf("0x%04X", $_) } @{$weights{$cp}});
+        printf("        (0x%04X, &[%s]),\n", $cp, $ws);
+    }
+    print "    ],\n";
+    print "    implicit: &[\n";
+    my $han_last = $bmp ? 0xFFFF : 0x3FFFF;
+    for my $core (1, 0) {
+        my $pred = sub {
+            my ($cp) = @_;
+            return !exists($weights{$cp}) && in_invlist(\@unified_ideograph, $cp) && $present->($cp)
+                && (is_core_han($cp) ? 1 : 0) == $core;
+        };
+        for my $run (runs(0x3400, $han_last, $pred)) {
+            printf("        UcaImplicit { first: 0x%04X, last: 0x%04X, base: 0x%04X, relative: false },\n",
+                   $run->[0], $run->[1], $core ? 0xFB40 : 0xFB80);
+        }
+    }
+    for my $range (@implicit) {
+        my ($first, $last, $base) = @$range;
+        next if $first > $max;
+        for my $run (runs($first, $last, $present)) {
+            printf("        UcaImplicit { first: 0x%04X, last: 0x%04X, base: 0x%04X, relative: true },\n",
+                   $run->[0], $run->[1], $base);
+        }
+    }
+    print "    ],\n";
+    print "};\n";
+}
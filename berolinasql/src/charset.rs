@@ -50,6 +50,7 @@ use std::sync::mpsc::RecvTimeoutError;
 use super::{AllegroPoset, Poset};
 use super::{PosetError, PosetErrorKind};
 use super::{PosetNode, PosetNodeId, PosetNodeData};
+use super::collation::Collation;
 
 
 /// A `Sync` implementation for `AllegroPoset`.
@@ -101,10 +102,48 @@ pub enum SyncErrorKind {
 }
 
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Charset {
     UTF8,
     UTF8MB4,
+    LATIN1,
+    GBK,
+    BINARY,
+}
+
+impl Charset {
+    /// Parses a MySQL character set name, case-insensitively.
+    pub fn from_name(name: &str) -> Option<Charset> {
+        match name.to_ascii_lowercase().as_str() {
+            CHARSET_UTF8 => Some(Charset::UTF8),
+            CHARSET_UTF8MB4 => Some(Charset::UTF8MB4),
+            CHARSET_LATIN1 => Some(Charset::LATIN1),
+            CHARSET_GBK => Some(Charset::GBK),
+            CHARSET_BIN => Some(Charset::BINARY),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::UTF8 => CHARSET_UTF8,
+            Charset::UTF8MB4 => CHARSET_UTF8MB4,
+            Charset::LATIN1 => CHARSET_LATIN1,
+            Charset::GBK => CHARSET_GBK,
+            Charset::BINARY => CHARSET_BIN,
+        }
+    }
+
+    /// The collation a column of this charset gets when none is declared.
+    pub fn default_collation(self) -> Collation {
+        match self {
+            Charset::UTF8 => Collation::Utf8GeneralCi,
+            Charset::UTF8MB4 => Collation::Utf8Mb4GeneralCi,
+            Charset::LATIN1 => Collation::Latin1Bin,
+            Charset::GBK => Collation::GbkChineseCi,
+            Charset::BINARY => Collation::Binary,
+        }
+    }
 }
 
 
@@ -149,6 +188,7 @@ pub const CHARSET_MAP: &'static [(&'static str, &'static str)] = &[
     ("utf8", "utf8_bin"),
     ("latin1", "latin1_general_ci"),
     ("latin1", "latin1_bin"),
+    ("gbk", "gbk_chinese_ci"),
     ("binary", "binary"),
 ];
 
@@ -162,6 +202,8 @@ pub const CHARSET_UTF8MB4: &str = "utf8mb4";
 pub const CHARSET_ASCII: &str = "ascii";
 /// `CHARSET_LATIN1` is a single byte charset.
 pub const CHARSET_LATIN1: &str = "latin1";
+/// `CHARSET_GBK` is the double byte simplified Chinese charset.
+pub const CHARSET_GBK: &str = "gbk";
 /// `CHARSET_LATIN1MB4` is a single byte charset.
 ///
 /// It's used for marking latin1 charset.
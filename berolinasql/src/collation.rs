@@ -0,0 +1,362 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+//! Collations of string causet_locales.
+//!
+//! Every collation turns a string into a sort key: a byte string whose `memcmp` order is
+//! the collation's order and whose equality is the collation's equality. Comparisons,
+//! `ORDER BY` and string-valued index soliton_ids all go through the sort key, so a
+//! case-insensitive lookup hits the same index entry that `=` would match.
+//!
+//! PAD SPACE collations ignore trailing spaces, the way MySQL compares `'a'` and `'a  '`
+//! as equal; the `_0900_` and binary collations are NO PAD.
+//!
+//! `utf8mb4_unicode_ci` and `utf8mb4_0900_ai_ci` weigh characters by their primary weights
+//! in the UCA's `allkeys.txt`, version 4.0.0 and 9.0.0 respectively as in MySQL, so both ignore
+//! case and accents. Characters the table doesn't list get the UCA's implicit weights, and
+//! `utf8mb4_unicode_ci` weighs everything outside the BMP as U+FFFD. Contractions, which only
+//! matter to decomposed text in a few scripts, aren't applied. The tables in `uca_weights` are
+//! currently generated from allkeys-13.0.0.txt, limited to the characters each version encodes;
+//! `scripts/gen_uca_weights.pl` regenerates them from the 4.0.0 and 9.0.0 files.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str;
+
+use encoding_rs::GBK;
+
+use super::charset::Charset;
+use super::error::{Error, Result};
+use super::uca_weights::{UCA_4_0_0, UCA_9_0_0};
+
+/// The weight every character outside the BMP gets in `utf8mb4_general_ci` and
+/// `utf8mb4_unicode_ci`, which like MySQL don't tell supplementary characters apart.
+const REPLACEMENT_WEIGHT: u32 = 0xFFFD;
+
+/// The first implicit weight of characters `allkeys.txt` doesn't list and that aren't Han,
+/// before the high bits of the code point are added.
+const UNASSIGNED_IMPLICIT_BASE: u16 = 0xFBC0;
+
+/// Base letters of U+00C0..=U+00FF; `*` marks a character that is a letter of its own.
+const LATIN1_SUPPLEMENT_BASE: &[u8; 64] =
+    b"AAAAAA*CEEEEIIII*NOOOOO**UUUUY**AAAAAA*CEEEEIIII*NOOOOO**UUUUY*Y";
+
+/// Base letters of U+0100..=U+017F (Latin Extended-A); `*` as above.
+const LATIN_EXTENDED_A_BASE: &[u8; 128] = b"AAAAAACCCCCCCCDDDDEEEEEEEEEEGGGGGGGGHHHHIIIIIIIIII**JJKKKLLLLLLLLLLNNNNNNNNNOOOOOO**RRRRRRSSSSSSSSTTTTTTUUUUUUUUUUUUWWYYYZZZZZZS";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Collation {
+    Binary,
+    Utf8Bin,
+    Utf8GeneralCi,
+    Utf8Mb4Bin,
+    Utf8Mb4GeneralCi,
+    Utf8Mb4UnicodeCi,
+    Utf8Mb40900AiCi,
+    Latin1Bin,
+    GbkChineseCi,
+}
+
+impl Collation {
+    /// Parses a MySQL collation name, case-insensitively.
+    pub fn from_name(name: &str) -> Result<Collation> {
+        match name.to_ascii_lowercase().as_str() {
+            "binary" => Ok(Collation::Binary),
+            "utf8_bin" | "utf8mb3_bin" => Ok(Collation::Utf8Bin),
+            "utf8_general_ci" | "utf8mb3_general_ci" => Ok(Collation::Utf8GeneralCi),
+            "utf8mb4_bin" => Ok(Collation::Utf8Mb4Bin),
+            "utf8mb4_general_ci" => Ok(Collation::Utf8Mb4GeneralCi),
+            "utf8mb4_unicode_ci" => Ok(Collation::Utf8Mb4UnicodeCi),
+            "utf8mb4_0900_ai_ci" => Ok(Collation::Utf8Mb40900AiCi),
+            "latin1_bin" => Ok(Collation::Latin1Bin),
+            "gbk_chinese_ci" => Ok(Collation::GbkChineseCi),
+            _ => Err(Error::unknown_collation(name)),
+        }
+    }
+
+    /// Resolves the collation of a column declared with `charset` and, optionally, a
+    /// `COLLATE` clause, failing if the two don't belong together.
+    pub fn for_column(charset: Charset, collation: Option<&str>) -> Result<Collation> {
+        match collation {
+            None => Ok(charset.default_collation()),
+            Some(name) => {
+                let collation = Collation::from_name(name)?;
+                if collation.charset() != charset {
+                    return Err(Error::collation_charset_mismatch(name, charset.name()));
+                }
+                Ok(collation)
+            }
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Collation::Binary => "binary",
+            Collation::Utf8Bin => "utf8_bin",
+            Collation::Utf8GeneralCi => "utf8_general_ci",
+            Collation::Utf8Mb4Bin => "utf8mb4_bin",
+            Collation::Utf8Mb4GeneralCi => "utf8mb4_general_ci",
+            Collation::Utf8Mb4UnicodeCi => "utf8mb4_unicode_ci",
+            Collation::Utf8Mb40900AiCi => "utf8mb4_0900_ai_ci",
+            Collation::Latin1Bin => "latin1_bin",
+            Collation::GbkChineseCi => "gbk_chinese_ci",
+        }
+    }
+
+    pub fn charset(self) -> Charset {
+        match self {
+            Collation::Binary => Charset::BINARY,
+            Collation::Utf8Bin | Collation::Utf8GeneralCi => Charset::UTF8,
+            Collation::Utf8Mb4Bin
+            | Collation::Utf8Mb4GeneralCi
+            | Collation::Utf8Mb4UnicodeCi
+            | Collation::Utf8Mb40900AiCi => Charset::UTF8MB4,
+            Collation::Latin1Bin => Charset::LATIN1,
+            Collation::GbkChineseCi => Charset::GBK,
+        }
+    }
+
+    /// Whether trailing spaces are ignored when comparing.
+    pub fn is_pad_space(self) -> bool {
+        !matches!(self, Collation::Binary | Collation::Utf8Mb40900AiCi)
+    }
+
+    /// Whether strings that differ only in letter case compare equal.
+    pub fn is_case_insensitive(self) -> bool {
+        matches!(
+            self,
+            Collation::Utf8GeneralCi
+                | Collation::Utf8Mb4GeneralCi
+                | Collation::Utf8Mb4UnicodeCi
+                | Collation::Utf8Mb40900AiCi
+                | Collation::GbkChineseCi
+        )
+    }
+
+    /// The sort key of `s`, a string in this collation's charset given as UTF-8 (or raw
+    /// bytes for `binary`).
+    pub fn sort_key(self, s: &[u8]) -> Result<Vec<u8>> {
+        if self == Collation::Binary {
+            return Ok(s.to_vec());
+        }
+        let mut s = str::from_utf8(s)?;
+        if self.is_pad_space() {
+            s = s.trim_end_matches(' ');
+        }
+        let mut key = Vec::with_capacity(s.len() * 2);
+        match self {
+            Collation::Binary => unreachable!(),
+            // UTF-8 byte order is code point order.
+            Collation::Utf8Bin | Collation::Utf8Mb4Bin => key.extend_from_slice(s.as_bytes()),
+            Collation::Utf8GeneralCi | Collation::Utf8Mb4GeneralCi => {
+                for c in s.chars() {
+                    let w = general_ci_weight(c);
+                    key.push((w >> 8) as u8);
+                    key.push(w as u8);
+                }
+            }
+            Collation::Utf8Mb4UnicodeCi => {
+                for c in s.chars() {
+                    if c as u32 > 0xFFFF {
+                        key.extend_from_slice(&(REPLACEMENT_WEIGHT as u16).to_be_bytes());
+                    } else {
+                        UCA_4_0_0.push_weights(&mut key, c);
+                    }
+                }
+            }
+            Collation::Utf8Mb40900AiCi => {
+                for c in s.chars() {
+                    UCA_9_0_0.push_weights(&mut key, c);
+                }
+            }
+            Collation::Latin1Bin => key.extend(s.chars().map(|c| {
+                if (c as u32) <= 0xFF {
+                    c as u8
+                } else {
+                    b'?'
+                }
+            })),
+            Collation::GbkChineseCi => {
+                let mut buf = [0; 4];
+                for c in s.chars() {
+                    if c.is_ascii() {
+                        key.push(c.to_ascii_uppercase() as u8);
+                        continue;
+                    }
+                    let (encoded, _, unmappable) = GBK.encode(c.encode_utf8(&mut buf));
+                    if unmappable {
+                        key.push(b'?');
+                    } else {
+                        key.extend_from_slice(&encoded);
+                    }
+                }
+            }
+        }
+        Ok(key)
+    }
+
+    /// Compares two strings of this collation.
+    pub fn compare(self, a: &[u8], b: &[u8]) -> Result<Ordering> {
+        Ok(self.sort_key(a)?.cmp(&self.sort_key(b)?))
+    }
+}
+
+impl fmt::Display for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The letter `c` is an accented form of, if it is one.
+fn latin_base_letter(c: char) -> Option<char> {
+    let base = match c as u32 {
+        cp @ 0xC0..=0xFF => LATIN1_SUPPLEMENT_BASE[(cp - 0xC0) as usize],
+        cp @ 0x100..=0x17F => LATIN_EXTENDED_A_BASE[(cp - 0x100) as usize],
+        _ => return None,
+    };
+    if base == b'*' {
+        None
+    } else {
+        Some(base as char)
+    }
+}
+
+/// The single-character uppercase of `c`, or `c` itself if uppercasing would expand it.
+fn simple_uppercase(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c,
+    }
+}
+
+/// `utf8mb4_general_ci` weighs each BMP character by its uppercase, accents stripped from
+/// Latin letters; `ß` weighs as `S`.
+fn general_ci_weight(c: char) -> u16 {
+    if c as u32 > 0xFFFF {
+        return REPLACEMENT_WEIGHT as u16;
+    }
+    if c == 'ß' {
+        return 'S' as u16;
+    }
+    match latin_base_letter(c) {
+        Some(base) => base as u16,
+        None => simple_uppercase(c) as u16,
+    }
+}
+
+/// The primary weights of one version of the UCA's `allkeys.txt`; see `uca_weights`.
+pub struct UcaTable {
+    /// The characters it lists and their primary weights, by code point. Ignorable characters
+    /// have none, and some have several.
+    pub weights: &'static [(u32, &'static [u16])],
+    /// The ranges of unlisted characters whose implicit weights have their own base.
+    pub implicit: &'static [UcaImplicit],
+}
+
+/// A range of characters weighed by implicit weights with a base of their own, like Han.
+pub struct UcaImplicit {
+    pub first: u32,
+    pub last: u32,
+    pub base: u16,
+    /// Whether the second weight counts from `first` rather than being the code point's low
+    /// bits, as for Tangut.
+    pub relative: bool,
+}
+
+impl UcaTable {
+    /// Appends the primary weights of `c`, two bytes each.
+    fn push_weights(&self, key: &mut Vec<u8>, c: char) {
+        let cp = c as u32;
+        if let Ok(i) = self.weights.binary_search_by_key(&cp, |&(cp, _)| cp) {
+            for w in self.weights[i].1 {
+                key.extend_from_slice(&w.to_be_bytes());
+            }
+            return;
+        }
+        let (first, second) = match self.implicit.iter().find(|r| r.first <= cp && cp <= r.last) {
+            Some(r) if r.relative => (r.base, cp - r.first),
+            Some(r) => (r.base + (cp >> 15) as u16, cp & 0x7FFF),
+            None => (UNASSIGNED_IMPLICIT_BASE + (cp >> 15) as u16, cp & 0x7FFF),
+        };
+        key.extend_from_slice(&first.to_be_bytes());
+        key.extend_from_slice(&((second | 0x8000) as u16).to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collation_compare() {
+        use std::cmp::Ordering::*;
+
+        let test_cases = vec![
+            ("binary", "a", "A", Greater),
+            ("binary", "a", "a ", Less),
+            ("utf8mb4_bin", "a", "A", Greater),
+            ("utf8mb4_bin", "a", "a  ", Equal),
+            ("utf8mb4_general_ci", "a", "A", Equal),
+            ("utf8mb4_general_ci", "résumé", "RESUME", Equal),
+            ("utf8mb4_general_ci", "ß", "s", Equal),
+            ("utf8mb4_general_ci", "a", "a  ", Equal),
+            ("utf8mb4_general_ci", "a", "_", Less),
+            ("utf8mb4_general_ci", "😀", "😃", Equal),
+            ("utf8mb4_unicode_ci", "ß", "ss", Equal),
+            ("utf8mb4_unicode_ci", "Æon", "aeon", Equal),
+            ("utf8mb4_unicode_ci", "_", "-", Less),
+            ("utf8mb4_unicode_ci", "_", "0", Less),
+            ("utf8mb4_unicode_ci", "9", "a", Less),
+            ("utf8mb4_unicode_ci", "Þ", "z", Greater),
+            ("utf8mb4_unicode_ci", "a\u{1}b", "ab", Equal),
+            ("utf8mb4_unicode_ci", "a", "a  ", Equal),
+            ("utf8mb4_unicode_ci", "😀", "😃", Equal),
+            // U+20B9 INDIAN RUPEE SIGN is newer than Unicode 4.0, so it gets an implicit weight.
+            ("utf8mb4_unicode_ci", "₹", "z", Greater),
+            ("utf8mb4_unicode_ci", "z", "一", Less),
+            ("utf8mb4_unicode_ci", "一", "丁", Less),
+            ("utf8mb4_0900_ai_ci", "Ångström", "angstrom", Equal),
+            ("utf8mb4_0900_ai_ci", "ß", "ss", Equal),
+            ("utf8mb4_0900_ai_ci", "_", "-", Less),
+            ("utf8mb4_0900_ai_ci", "Þ", "z", Greater),
+            ("utf8mb4_0900_ai_ci", "₹", "0", Less),
+            ("utf8mb4_0900_ai_ci", "a", "a ", Less),
+            ("utf8mb4_0900_ai_ci", "😀", "😃", Less),
+            ("utf8mb4_0900_ai_ci", "一", "丁", Less),
+            ("latin1_bin", "a", "b", Less),
+            ("latin1_bin", "é", "z", Greater),
+            ("latin1_bin", "a", "a ", Equal),
+            ("gbk_chinese_ci", "abc", "ABC", Equal),
+            // 啊 (B0A1) sorts before 阿 (B0A2), both after ASCII.
+            ("gbk_chinese_ci", "啊", "阿", Less),
+            ("gbk_chinese_ci", "z", "啊", Less),
+        ];
+        for (i, (name, a, b, expected)) in test_cases.into_iter().enumerate() {
+            let collation = Collation::from_name(name).unwrap();
+            let got = collation.compare(a.as_bytes(), b.as_bytes()).unwrap();
+            assert_eq!(got, expected, "#{} expect {:?}", i, expected);
+        }
+    }
+
+    #[test]
+    fn test_collation_for_column() {
+        assert_eq!(
+            Collation::for_column(Charset::UTF8MB4, None).unwrap(),
+            Collation::Utf8Mb4GeneralCi
+        );
+        assert_eq!(
+            Collation::for_column(Charset::UTF8MB4, Some("UTF8MB4_0900_AI_CI")).unwrap(),
+            Collation::Utf8Mb40900AiCi
+        );
+        assert!(Collation::for_column(Charset::LATIN1, Some("utf8mb4_bin")).is_err());
+        assert!(Collation::from_name("klingon_ci").is_err());
+    }
+}
@@ -74,6 +74,7 @@ use std::thread;
 use std::time::Duration;
 use std::time::Instant;
 use std::sync::mpsc::TryRecvError;
+use crate::collation::Collation;
 use std::sync::mpsc::RecvTimeoutError;
 
  #[derive(Debug)]
@@ -137,6 +138,16 @@ pub struct SyncError {
              x.partial_cmp(&y)
          }
      }
+
+     /// Compares two strings by the sort keys `collation` gives them, so that e.g. `'a'`
+     /// and `'A '` are equal under `utf8mb4_general_ci`.
+     fn compare_str_with_collation(
+         x: &[u8],
+         y: &[u8],
+         collation: Collation,
+     ) -> crate::error::Result<Ordering> {
+         collation.compare(x, y)
+     }
  }
 
 impl<'a> JsonRef<'a> {
@@ -13,6 +13,9 @@ use std::{i8, i16, i32, i64, i128};
 use std::{usize, isize};
 use std::{f32, f64};
 use std::{self, mem};
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{One, Signed, ToPrimitive, Zero};
 use std::{num, fmt};
 use std::{str};
 use std::{i32, i64};
@@ -1890,6 +1893,314 @@ impl Decimal {
     }
 }
 
+/// Guard digits the power, root and logarithm functions carry past the requested fraction,
+/// so that the final rounding sees correctly rounded digits.
+const MATH_GUARD_DIGITS: u32 = 10;
+
+/// `exp(x)` overflows a decimal from here on: `ln(10^81)` is about 186.5.
+const MATH_MAX_EXP_ARG: i64 = 187;
+
+/// Integer powers whose mantissa would grow past this many digits are computed through
+/// `exp(n * ln(x))` instead of exactly.
+const MATH_MAX_EXACT_POW_DIGITS: u64 = 4096;
+
+fn ten_pow_big(n: u32) -> BigInt {
+    BigInt::from(10u32).pow(n)
+}
+
+/// Rescales the fixed-point mantissa `m` from `from` to `to` fraction digits, truncating.
+fn rescale(m: &BigInt, from: u32, to: u32) -> BigInt {
+    if to >= from {
+        m * ten_pow_big(to - from)
+    } else {
+        m / ten_pow_big(from - to)
+    }
+}
+
+/// `ln((1 + z) / (1 - z)) = 2 * atanh(z)` in fixed point scaled by `one`; it converges
+/// quickly for the `|z| <= 1/3` it is used with.
+fn ln_series(z: &BigInt, one: &BigInt) -> BigInt {
+    let z2 = z * z / one;
+    let mut term = z.clone();
+    let mut sum = BigInt::zero();
+    let mut n = 1u32;
+    while !term.is_zero() {
+        sum += &term / n;
+        term = &term * &z2 / one;
+        n += 2;
+    }
+    sum * 2u32
+}
+
+/// `ln(m / 10^scale)` at `w` fraction digits, for a positive `m`.
+fn ln_fixed(m: &BigInt, scale: u32, w: u32) -> BigInt {
+    let ws = cmp::max(w, scale) + MATH_GUARD_DIGITS;
+    let one = ten_pow_big(ws);
+    let x = rescale(m, scale, ws);
+    // x = y * 2^k with 1 <= y < 2, so that ln(x) = ln(y) + k * ln(2).
+    let mut k = x.bits() as i64 - one.bits() as i64;
+    let y = loop {
+        let y = if k >= 0 {
+            &x >> k as usize
+        } else {
+            &x << (-k) as usize
+        };
+        if y >= &one << 1 {
+            k += 1;
+        } else if y < one {
+            k -= 1;
+        } else {
+            break y;
+        }
+    };
+    let ln_y = ln_series(&((&y - &one) * &one / (&y + &one)), &one);
+    let ln_2 = ln_series(&(&one / 3u32), &one);
+    rescale(&(ln_y + ln_2 * k), ws, w)
+}
+
+/// `exp(t / 10^scale)` at `w` fraction digits.
+fn exp_fixed(t: &BigInt, scale: u32, w: u32) -> BigInt {
+    let ws = w + MATH_GUARD_DIGITS;
+    let one = ten_pow_big(ws);
+    let half = &one / 2u32;
+    // Halve the argument below 1/2 for the Taylor series, then square the sum back up.
+    let mut r = rescale(t, scale, ws);
+    let mut halvings = 0;
+    while r.abs() > half {
+        r /= 2u32;
+        halvings += 1;
+    }
+    let mut sum = one.clone();
+    let mut term = one.clone();
+    let mut k = 1u32;
+    loop {
+        term = &term * &r / &one / k;
+        if term.is_zero() {
+            break;
+        }
+        sum += &term;
+        k += 1;
+    }
+    for _ in 0..halvings {
+        sum = &sum * &sum / &one;
+    }
+    rescale(&sum, ws, w)
+}
+
+/// `exp(t / 10^scale)` rounded to `frac` digits.
+fn exp_decimal(t: &BigInt, scale: u32, frac: u8, round_mode: RoundMode) -> Res<Decimal> {
+    if t.is_zero() {
+        return Decimal::from_fixed(BigInt::one(), 0, frac, round_mode, true);
+    }
+    let w = u32::from(frac) + MATH_GUARD_DIGITS;
+    let t_int = (t / ten_pow_big(scale)).to_i64().unwrap_or(if t.is_negative() {
+        i64::MIN
+    } else {
+        i64::MAX
+    });
+    if t_int >= MATH_MAX_EXP_ARG {
+        return Res::OverCausetxctx(max_or_min_dec(false, WORD_BUF_LEN * DIGITS_PER_WORD, 0));
+    }
+    // Below ln(10^-w) nothing of the result survives the guard digits.
+    if t_int < -(i64::from(w) * 23 / 10 + 1) {
+        return Decimal::from_fixed(BigInt::zero(), w, frac, round_mode, false);
+    }
+    // A result with n integer digits needs n more working digits for the same fraction.
+    let int_digits = if t_int > 0 { (t_int * 4343 / 10000 + 1) as u32 } else { 0 };
+    let w = w + int_digits;
+    Decimal::from_fixed(exp_fixed(t, scale, w), w, frac, round_mode, false)
+}
+
+/// Power, root, logarithm and exponential functions.
+///
+/// They work on the exact causet_locale in arbitrary-precision fixed point, carrying guard
+/// digits past `frac`, and round the result to `frac` fraction digits (at most
+/// `MAX_FRACTION`) with `round_mode` once at the end. `None` means the result is undefined
+/// and the SQL function returns NULL.
+impl Decimal {
+    /// The decimal as a mantissa and the number of fraction digits it is scaled by, without
+    /// trailing fraction zeroes.
+    fn to_fixed(&self) -> (BigInt, u32) {
+        let int_word_cnt = word_cnt!(self.int_cnt) as usize;
+        let frac_word_cnt = word_cnt!(self.frac_cnt) as usize;
+        let mut m = BigInt::zero();
+        for &word in &self.word_buf[..int_word_cnt + frac_word_cnt] {
+            m = m * WORD_BASE + word;
+        }
+        let mut scale = frac_word_cnt as u32 * u32::from(DIGITS_PER_WORD);
+        while scale > 0 && (&m % 10u32).is_zero() {
+            m /= 10u32;
+            scale -= 1;
+        }
+        if self.negative {
+            m = -m;
+        }
+        (m, scale)
+    }
+
+    /// Rounds the fixed-point `m / 10^scale` to `frac` fraction digits. Unless `exact`, the
+    /// true causet_locale is only known to lie close to `m`, and `Ceiling` rounds it up even if
+    /// the dropped digits are all zero.
+    fn from_fixed(
+        m: BigInt,
+        scale: u32,
+        frac: u8,
+        round_mode: RoundMode,
+        exact: bool,
+    ) -> Res<Decimal> {
+        let frac = cmp::min(frac, MAX_FRACTION);
+        let negative = m.is_negative();
+        let mut m = m.abs();
+        let target = u32::from(frac);
+        if scale > target {
+            let divisor = ten_pow_big(scale - target);
+            let (q, r) = m.div_rem(&divisor);
+            let round_up = match round_mode {
+                RoundMode::Truncate => false,
+                RoundMode::Ceiling => !exact || !r.is_zero(),
+                RoundMode::HalfEven => &r * 2u32 >= divisor,
+            };
+            m = if round_up { q + 1u32 } else { q };
+        } else {
+            m *= ten_pow_big(target - scale);
+            if !exact && matches!(round_mode, RoundMode::Ceiling) {
+                m += 1u32;
+            }
+        }
+        let mut digits = m.to_string();
+        if digits.len() <= frac as usize {
+            digits = "0".repeat(frac as usize + 1 - digits.len()) + &digits;
+        }
+        let (int_part, frac_part) = digits.split_at(digits.len() - frac as usize);
+        if int_part.len() > (WORD_BUF_LEN * DIGITS_PER_WORD) as usize {
+            return Res::OverCausetxctx(max_or_min_dec(
+                negative,
+                WORD_BUF_LEN * DIGITS_PER_WORD,
+                0,
+            ));
+        }
+        let mut s = String::with_capacity(digits.len() + 2);
+        if negative && !m.is_zero() {
+            s.push('-');
+        }
+        s.push_str(int_part);
+        if frac > 0 {
+            s.push('.');
+            s.push_str(frac_part);
+        }
+        // A string of digits is always a well-formed decimal.
+        let mut res = Decimal::from_bytes(s.as_bytes()).unwrap();
+        res.result_frac_cnt = frac;
+        res
+    }
+
+    /// `pow` raises the decimal to `exp`. Integer exponents are computed exactly before the
+    /// rounding; other exponents go through `exp(exp * ln(self))` and need a non-negative
+    /// base. Zero to a negative power and a negative base to a fractional power are undefined.
+    pub fn pow(&self, exp: &Decimal, frac: u8, round_mode: RoundMode) -> Option<Res<Decimal>> {
+        if exp.is_zero() {
+            return Some(Decimal::from_fixed(BigInt::one(), 0, frac, round_mode, true));
+        }
+        if self.is_zero() {
+            if exp.negative {
+                return None;
+            }
+            return Some(Decimal::from_fixed(BigInt::zero(), 0, frac, round_mode, true));
+        }
+        let (m, scale) = self.to_fixed();
+        let (e, e_scale) = exp.to_fixed();
+        let integral = e_scale == 0;
+        if !integral && self.negative {
+            return None;
+        }
+        if integral {
+            // A mantissa of b bits raised to n has fewer than n * b / 3 decimal digits.
+            let n = e
+                .abs()
+                .to_u32()
+                .filter(|&n| u64::from(n) * m.bits() / 3 <= MATH_MAX_EXACT_POW_DIGITS);
+            if let Some(n) = n {
+                let power = m.pow(n);
+                let power_scale = scale * n;
+                if !e.is_negative() {
+                    return Some(Decimal::from_fixed(power, power_scale, frac, round_mode, true));
+                }
+                let w = u32::from(frac) + MATH_GUARD_DIGITS;
+                let (q, r) = ten_pow_big(power_scale + w).div_rem(&power);
+                return Some(Decimal::from_fixed(q, w, frac, round_mode, r.is_zero()));
+            }
+        }
+        // The error of ln(|self|) is multiplied by the exponent, so it needs as many more
+        // digits as the exponent has integer digits.
+        let e_int_digits = (&e / ten_pow_big(e_scale)).abs().to_string().len() as u32;
+        let w = u32::from(frac) + MATH_GUARD_DIGITS + e_int_digits;
+        let odd = integral && e.is_odd();
+        let t = e * ln_fixed(&m.abs(), scale, w);
+        let res = exp_decimal(&t, e_scale + w, frac, round_mode);
+        if self.negative && odd {
+            Some(res.map(|d| -d))
+        } else {
+            Some(res)
+        }
+    }
+
+    /// `sqrt` returns the square root of the decimal, which must not be negative.
+    pub fn sqrt(&self, frac: u8, round_mode: RoundMode) -> Option<Res<Decimal>> {
+        if self.negative && !self.is_zero() {
+            return None;
+        }
+        let (m, scale) = self.to_fixed();
+        // sqrt(m / 10^scale) at w fraction digits is isqrt(m * 10^(2w - scale)).
+        let w = cmp::max(u32::from(frac) + MATH_GUARD_DIGITS, (scale + 1) / 2);
+        let n = m * ten_pow_big(2 * w - scale);
+        let root = n.sqrt();
+        let exact = &root * &root == n;
+        Some(Decimal::from_fixed(root, w, frac, round_mode, exact))
+    }
+
+    /// `ln` returns the natural logarithm of the decimal, which must be positive.
+    pub fn ln(&self, frac: u8, round_mode: RoundMode) -> Option<Res<Decimal>> {
+        if self.negative || self.is_zero() {
+            return None;
+        }
+        let (m, scale) = self.to_fixed();
+        if scale == 0 && m.is_one() {
+            return Some(Decimal::from_fixed(BigInt::zero(), 0, frac, round_mode, true));
+        }
+        let w = u32::from(frac) + MATH_GUARD_DIGITS;
+        Some(Decimal::from_fixed(ln_fixed(&m, scale, w), w, frac, round_mode, false))
+    }
+
+    /// `log10` returns the base-10 logarithm of the decimal, which must be positive. Powers
+    /// of ten have exact logarithms.
+    pub fn log10(&self, frac: u8, round_mode: RoundMode) -> Option<Res<Decimal>> {
+        if self.negative || self.is_zero() {
+            return None;
+        }
+        let (m, scale) = self.to_fixed();
+        let (mut significand, mut zeroes) = (m.clone(), 0i64);
+        while (&significand % 10u32).is_zero() {
+            significand /= 10u32;
+            zeroes += 1;
+        }
+        if significand.is_one() {
+            let log = BigInt::from(zeroes - i64::from(scale));
+            return Some(Decimal::from_fixed(log, 0, frac, round_mode, true));
+        }
+        let w = u32::from(frac) + MATH_GUARD_DIGITS;
+        let ws = w + MATH_GUARD_DIGITS;
+        let ln_10 = ln_fixed(&BigInt::from(10u32), 0, ws);
+        let log = ln_fixed(&m, scale, ws) * ten_pow_big(ws) / ln_10;
+        Some(Decimal::from_fixed(log, ws, frac, round_mode, false))
+    }
+
+    /// `exp` returns e raised to the decimal.
+    pub fn exp(&self, frac: u8, round_mode: RoundMode) -> Option<Res<Decimal>> {
+        let (t, scale) = self.to_fixed();
+        Some(exp_decimal(&t, scale, frac, round_mode))
+    }
+}
+
 macro_rules! enable_conv_for_int {
     ($s:ty, $t:ty) => {
         impl From<$s> for Decimal {
@@ -3953,4 +4264,83 @@ mod tests {
             .into_result_impl(&mut ctx, None, Some(error))
             .is_ok());
     }
+
+    #[test]
+    fn test_pow() {
+        let cases = vec![
+            ("2", "10", 0, RoundMode::HalfEven, Some(Res::Ok("1024"))),
+            ("2", "-2", 4, RoundMode::HalfEven, Some(Res::Ok("0.2500"))),
+            ("-2", "3", 0, RoundMode::HalfEven, Some(Res::Ok("-8"))),
+            ("-2", "-3", 4, RoundMode::HalfEven, Some(Res::Ok("-0.1250"))),
+            ("1.5", "2", 2, RoundMode::HalfEven, Some(Res::Ok("2.25"))),
+            ("1.5", "2", 1, RoundMode::HalfEven, Some(Res::Ok("2.3"))),
+            ("1.5", "2", 1, RoundMode::Truncate, Some(Res::Ok("2.2"))),
+            ("2", "0.5", 10, RoundMode::HalfEven, Some(Res::Ok("1.4142135624"))),
+            ("1.0001", "10000", 6, RoundMode::HalfEven, Some(Res::Ok("2.718146"))),
+            ("0", "0", 0, RoundMode::HalfEven, Some(Res::Ok("1"))),
+            ("0", "3", 2, RoundMode::HalfEven, Some(Res::Ok("0.00"))),
+            ("0", "-1", 0, RoundMode::HalfEven, None),
+            ("-8", "0.5", 4, RoundMode::HalfEven, None),
+        ];
+        for (base, exp, frac, round_mode, expected) in cases {
+            let base: Decimal = base.parse().unwrap();
+            let exp: Decimal = exp.parse().unwrap();
+            let got = base
+                .pow(&exp, frac, round_mode)
+                .map(|res| res.map(|d| d.to_string()));
+            let expected = expected.map(|res| res.map(|s| s.to_owned()));
+            assert_eq!(got, expected, "pow({}, {})", base, exp);
+        }
+
+        let ten: Decimal = "10".parse().unwrap();
+        let hundred: Decimal = "100".parse().unwrap();
+        assert!(ten.pow(&hundred, 0, RoundMode::HalfEven).unwrap().is_overCausetxctx());
+    }
+
+    #[test]
+    fn test_sqrt() {
+        let cases = vec![
+            ("4", 0, RoundMode::HalfEven, Some("2")),
+            ("2", 10, RoundMode::HalfEven, Some("1.4142135624")),
+            ("2", 10, RoundMode::Truncate, Some("1.4142135623")),
+            ("2", 10, RoundMode::Ceiling, Some("1.4142135624")),
+            ("0.0001", 4, RoundMode::Ceiling, Some("0.0100")),
+            ("0", 2, RoundMode::HalfEven, Some("0.00")),
+            ("-1", 2, RoundMode::HalfEven, None),
+        ];
+        for (input, frac, round_mode, expected) in cases {
+            let dec: Decimal = input.parse().unwrap();
+            let got = dec.sqrt(frac, round_mode).map(|res| res.unwrap().to_string());
+            assert_eq!(got, expected.map(|s| s.to_owned()), "sqrt({})", input);
+        }
+    }
+
+    #[test]
+    fn test_ln_log10_exp() {
+        type MathFn = fn(&Decimal, u8, RoundMode) -> Option<Res<Decimal>>;
+        let cases: Vec<(&str, MathFn, &str, u8, Option<&str>)> = vec![
+            ("ln", Decimal::ln, "2", 10, Some("0.6931471806")),
+            ("ln", Decimal::ln, "2.5", 8, Some("0.91629073")),
+            ("ln", Decimal::ln, "1", 4, Some("0.0000")),
+            ("ln", Decimal::ln, "0.000000000000000000000000000001", 6, Some("-69.077553")),
+            ("ln", Decimal::ln, "0", 4, None),
+            ("ln", Decimal::ln, "-1", 4, None),
+            ("log10", Decimal::log10, "1000", 2, Some("3.00")),
+            ("log10", Decimal::log10, "0.001", 2, Some("-3.00")),
+            ("log10", Decimal::log10, "2", 10, Some("0.3010299957")),
+            ("log10", Decimal::log10, "-10", 2, None),
+            ("exp", Decimal::exp, "1", 10, Some("2.7182818285")),
+            ("exp", Decimal::exp, "-1", 10, Some("0.3678794412")),
+            ("exp", Decimal::exp, "0", 2, Some("1.00")),
+            ("exp", Decimal::exp, "-100", 4, Some("0.0000")),
+        ];
+        for (name, f, input, frac, expected) in cases {
+            let dec: Decimal = input.parse().unwrap();
+            let got = f(&dec, frac, RoundMode::HalfEven).map(|res| res.unwrap().to_string());
+            assert_eq!(got, expected.map(|s| s.to_owned()), "{}({})", name, input);
+        }
+
+        let big: Decimal = "200".parse().unwrap();
+        assert!(big.exp(0, RoundMode::HalfEven).unwrap().is_overCausetxctx());
+    }
 }
@@ -0,0 +1,666 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+//! Changing the store with SQL.
+//!
+//! `INSERT`, `UPDATE` and `DELETE` against a vocabulary table become one transaction of
+//! `:einsteindb/add` and `:einsteindb/retract` terms run through `Conn::transact`:
+//!
+//! - `INSERT` makes a new causet per row (or asserts onto the causet named by `id`), skipping
+//!   NULLs.
+//! - `UPDATE` finds the matching causets with the same compiler `SELECT` uses, retracts the
+//!   causet_locales being replaced and asserts the new ones. `SET col = NULL` just retracts.
+//! - `DELETE` retracts every causet_locale the table's attributes have on the matching causets.
+//!   Attributes belonging to other vocabularies are left alone.
+//!
+//! Each of these keeps a collated column's sort key attribute (see `select`) in step with the
+//! column's causet_locales.
+//!
+//! A table whose attribute is `:einsteindb.unique/idcauset` uses it as its primary key: an
+//! `INSERT` must supply it and may not reuse one held by another causet, and an `UPDATE` may not
+//! give two causets the same key. The affected-row count is the number of causets that changed,
+//! as MySQL reports it.
+//!
+//! The transactor checks JSON causet_locales against the schema stored on their attribute with
+//! `:einsteindb/jsonSchema`, whatever wrote them. An attribute with a JSON Schema attached to
+//! the catalog (see `Catalog::attach_stored_json_schemas`) is checked here first as well, so the
+//! error names the column and the row rather than the transaction.
+
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+
+use causetq::{
+    Causetid,
+    causetq_TV,
+};
+use einstein_db::conn::Conn;
+use einstein_ml::Keyword;
+use einstein_ml::query::{
+    FnArg,
+    NonIntegerConstant,
+};
+
+use crate::error::{
+    Error,
+    Result,
+};
+use einstein_json::Json;
+use crate::parser::{
+    parse_sql,
+    ColumnRef,
+    Delete,
+    Expr,
+    Insert,
+    Literal,
+    Select,
+    SelectItem,
+    Statement,
+    TableFactor,
+    Update,
+};
+use crate::select::{
+    compile_select,
+    constant,
+    execute_select,
+    relation,
+    sort_key_text,
+    store_error,
+    write_arg,
+    Catalog,
+    SqlColumn,
+    SqlTable,
+    ID_COLUMN,
+};
+use crate::table::Table;
+
+/// What running a statement produced.
+#[derive(Debug)]
+pub enum Outcome {
+    Rows(Table),
+    Affected(usize),
+}
+
+fn render(arg: &FnArg) -> String {
+    let mut out = String::new();
+    write_arg(&mut out, arg);
+    out
+}
+
+/// The EDN for a causet_locale read back from the store.
+fn render_causet_locale(v: &causetq_TV) -> String {
+    render(&match v {
+        &causetq_TV::Ref(e) => FnArg::CausetidOrInteger(e),
+        &causetq_TV::Long(l) => FnArg::CausetidOrInteger(l),
+        &causetq_TV::Keyword(ref k) => FnArg::SolitonidOrKeyword((**k).clone()),
+        &causetq_TV::Boolean(b) => FnArg::Constant(NonIntegerConstant::Boolean(b)),
+        &causetq_TV::Double(d) => FnArg::Constant(NonIntegerConstant::Float(d)),
+        &causetq_TV::Instant(i) => FnArg::Constant(NonIntegerConstant::Instant(i)),
+        &causetq_TV::String(ref s) => FnArg::Constant(NonIntegerConstant::Text(s.clone())),
+        &causetq_TV::Uuid(u) => FnArg::Constant(NonIntegerConstant::Uuid(u)),
+        &causetq_TV::Json(ref j) => FnArg::Constant(NonIntegerConstant::Text(j.to_string().into())),
+    })
+}
+
+fn term(add: bool, causet: &str, attribute: &Keyword, causet_locale: &str) -> String {
+    format!("[:einsteindb/{} {} {} {}]", if add { "add" } else { "retract" }, causet, attribute, causet_locale)
+}
+
+/// The rendered sort key a collated string `column` indexes `causet_locale` under.
+fn sort_key(column: &SqlColumn, causet_locale: &str) -> Result<Option<String>> {
+    match column.collated() {
+        Some((collation, _)) => {
+            let key = sort_key_text(causet_locale, collation)?;
+            Ok(Some(render(&FnArg::Constant(NonIntegerConstant::from(key.as_str())))))
+        },
+        None => Ok(None),
+    }
+}
+
+/// The rendered sort keys of the causet_locales `column` holds.
+fn sort_keys(column: &SqlColumn, causet_locales: &[causetq_TV]) -> Result<BTreeSet<String>> {
+    let mut keys = BTreeSet::new();
+    for v in causet_locales {
+        if let &causetq_TV::String(ref s) = v {
+            keys.extend(sort_key(column, s)?);
+        }
+    }
+    Ok(keys)
+}
+
+fn primary_key_name(table: &SqlTable) -> String {
+    format!("{}.PRIMARY", table.name)
+}
+
+fn literal(expr: &Expr) -> Result<&Literal> {
+    match expr {
+        &Expr::Literal(ref lit) => Ok(lit),
+        other => Err(Error::not_supported_yet(format!("'{}' as a causet_locale", other))),
+    }
+}
+
+/// Reject JSON text that doesn't satisfy the schema attached to `column`'s attribute, if any.
+fn check_json_schema(catalog: &Catalog, column: &SqlColumn, lit: &Literal) -> Result<()> {
+    let schema = match catalog.json_schema(&column.attribute) {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+    let text = match lit {
+        &Literal::String(ref text) => text,
+        other => return Err(Error::truncated_wrong_val("JSON", other)),
+    };
+    let doc = text.parse::<Json>().map_err(Error::invalid_json_text)?;
+    match schema.validate(doc.as_ref())? {
+        Some(failure) => Err(Error::json_schema_violation(&column.name, failure.reason())),
+        None => Ok(()),
+    }
+}
+
+/// One `VALUES` tuple of an `INSERT`.
+#[derive(Clone, Debug, PartialEq)]
+struct InsertRow {
+    /// Set when the row names its causet with `id`.
+    causet: Option<Causetid>,
+    /// The rendered primary key, if the table has one.
+    key: Option<String>,
+    /// Column index and rendered causet_locale for each non-NULL causet_locale.
+    causet_locales: Vec<(usize, String)>,
+    /// Column index and rendered sort key for each causet_locale of a collated column.
+    sort_keys: Vec<(usize, String)>,
+}
+
+fn plan_insert<'c>(catalog: &'c Catalog, insert: &Insert) -> Result<(&'c SqlTable, Vec<InsertRow>)> {
+    let table = catalog.table(&insert.table).ok_or_else(|| Error::no_such_table(&insert.table))?;
+
+    // `None` stands for the `id` column.
+    let columns: Vec<Option<usize>> = match insert.columns {
+        None => (0..table.columns.len()).map(Some).collect(),
+        Some(ref names) => {
+            let mut seen = BTreeSet::new();
+            let mut columns = vec![];
+            for name in names {
+                if !seen.insert(name.to_lowercase()) {
+                    return Err(Error::field_specified_twice(name));
+                }
+                if name.eq_ignore_ascii_case(ID_COLUMN) {
+                    columns.push(None);
+                } else {
+                    let i = table.column_index(name).ok_or_else(|| Error::bad_field(name, "field list"))?;
+                    columns.push(Some(i));
+                }
+            }
+            columns
+        },
+    };
+
+    let primary_key = table.primary_key();
+    let mut rows = vec![];
+    for (n, values) in insert.rows.iter().enumerate() {
+        if values.len() != columns.len() {
+            return Err(Error::wrong_value_count_on_row(n + 1));
+        }
+        let mut row = InsertRow { causet: None, key: None, causet_locales: vec![], sort_keys: vec![] };
+        for (column, expr) in columns.iter().zip(values.iter()) {
+            let lit = literal(expr)?;
+            match (column, lit) {
+                (&None, &Literal::Null) => {},
+                (&None, &Literal::Integer(e)) if e >= 0 => row.causet = Some(e),
+                (&None, lit) => return Err(Error::truncated_wrong_val("Ref", lit)),
+                (&Some(_), &Literal::Null) => {},
+                (&Some(i), lit) => {
+                    check_json_schema(catalog, &table.columns[i], lit)?;
+                    let rendered = render(&constant(table.columns[i].causet_locale_type, lit)?);
+                    if Some(i) == primary_key {
+                        row.key = Some(rendered.clone());
+                    }
+                    if let &Literal::String(ref s) = lit {
+                        if let Some(key) = sort_key(&table.columns[i], s)? {
+                            row.sort_keys.push((i, key));
+                        }
+                    }
+                    row.causet_locales.push((i, rendered));
+                },
+            }
+        }
+        if let Some(i) = primary_key {
+            if row.key.is_none() {
+                return Err(Error::no_default_for_field(&table.columns[i].name));
+            }
+        }
+        if row.causet_locales.is_empty() {
+            return Err(Error::not_supported_yet("a row with no causet_locales"));
+        }
+        rows.push(row);
+    }
+    Ok((table, rows))
+}
+
+fn insert_terms(table: &SqlTable, rows: &[InsertRow]) -> Vec<String> {
+    let mut terms = vec![];
+    for (n, row) in rows.iter().enumerate() {
+        let causet = match row.causet {
+            Some(e) => e.to_string(),
+            None => format!("\"row{}\"", n),
+        };
+        for &(i, ref v) in row.causet_locales.iter() {
+            terms.push(term(true, &causet, &table.columns[i].attribute, v));
+        }
+        for &(i, ref k) in row.sort_keys.iter() {
+            let (_, sort_key) = table.columns[i].collated().unwrap();
+            terms.push(term(true, &causet, sort_key, k));
+        }
+    }
+    terms
+}
+
+/// The causet holding `key` for the primary key `column`, if any.
+fn key_owner(conn: &Conn, sqlite: &rusqlite::Connection, column: &SqlColumn, key: &str) -> Result<Option<Causetid>> {
+    let query = format!("[:find ?e :where [?e {} {}]]", column.attribute, key);
+    let output = conn.q_once(conn, &query, sqlite, None).map_err(store_error)?;
+    Ok(relation(output.results, 1)?
+        .into_iter()
+        .filter_map(|row| match row[0] {
+            causetq_TV::Ref(e) => Some(e),
+            _ => None,
+        })
+        .next())
+}
+
+/// The causets `WHERE` selects from `table`, using the `SELECT` compiler.
+fn matching_causets(conn: &Conn,
+                    sqlite: &rusqlite::Connection,
+                    catalog: &Catalog,
+                    table: &TableFactor,
+                    selection: &Option<Expr>) -> Result<Vec<Causetid>> {
+    let select = Select {
+        distinct: true,
+        projection: vec![SelectItem::Expr {
+            expr: Expr::Column(ColumnRef { table: Some(table.binding_name().to_string()), column: ID_COLUMN.to_string() }),
+            alias: None,
+        }],
+        from: vec![table.clone()],
+        joins: vec![],
+        selection: selection.clone(),
+        group_by: vec![],
+        order_by: vec![],
+        limit: None,
+    };
+    let compiled = compile_select(catalog, &select)?;
+    let output = conn.q_once(conn, &compiled.datalog(), sqlite, None).map_err(store_error)?;
+    Ok(relation(output.results, 1)?
+        .into_iter()
+        .filter_map(|row| match row[0] {
+            causetq_TV::Ref(e) => Some(e),
+            _ => None,
+        })
+        .collect())
+}
+
+/// The causet_locales `column` has on each of `causets`.
+fn current_causet_locales(conn: &Conn,
+                          sqlite: &rusqlite::Connection,
+                          causets: &[Causetid],
+                          column: &SqlColumn) -> Result<BTreeMap<Causetid, Vec<causetq_TV>>> {
+    let mut current: BTreeMap<Causetid, Vec<causetq_TV>> = BTreeMap::new();
+    if causets.is_empty() {
+        return Ok(current);
+    }
+    let causets = causets.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(" ");
+    let query = format!("[:find ?e ?v :where [(ground [{}]) [?e ...]] [?e {} ?v]]", causets, column.attribute);
+    let output = conn.q_once(conn, &query, sqlite, None).map_err(store_error)?;
+    for mut row in relation(output.results, 2)? {
+        let v = row.pop().unwrap();
+        if let causetq_TV::Ref(e) = row[0] {
+            current.entry(e).or_insert_with(Vec::new).push(v);
+        }
+    }
+    Ok(current)
+}
+
+/// Resolve `SET` assignments to column indexes, rendered causet_locales and, for a collated
+/// column, rendered sort keys; `None` is NULL.
+fn plan_update(catalog: &Catalog, table: &SqlTable, update: &Update) -> Result<Vec<(usize, Option<String>, Option<String>)>> {
+    let primary_key = table.primary_key();
+    let mut seen = BTreeSet::new();
+    let mut assignments = vec![];
+    for &(ref name, ref expr) in update.assignments.iter() {
+        if name.eq_ignore_ascii_case(ID_COLUMN) {
+            return Err(Error::not_supported_yet("changing a row's id"));
+        }
+        let i = table.column_index(name).ok_or_else(|| Error::bad_field(name, "field list"))?;
+        if !seen.insert(i) {
+            return Err(Error::field_specified_twice(name));
+        }
+        let (causet_locale, key) = match literal(expr)? {
+            &Literal::Null if Some(i) == primary_key => return Err(Error::bad_null(name)),
+            &Literal::Null => (None, None),
+            lit => {
+                check_json_schema(catalog, &table.columns[i], lit)?;
+                let rendered = render(&constant(table.columns[i].causet_locale_type, lit)?);
+                let key = match lit {
+                    &Literal::String(ref s) => sort_key(&table.columns[i], s)?,
+                    _ => None,
+                };
+                (Some(rendered), key)
+            },
+        };
+        assignments.push((i, causet_locale, key));
+    }
+    Ok(assignments)
+}
+
+/// The terms that move each causet from its `current` causet_locales (one map per assignment) to
+/// the assigned ones, and how many causets that changes.
+fn update_terms(table: &SqlTable,
+                assignments: &[(usize, Option<String>, Option<String>)],
+                causets: &[Causetid],
+                current: &[BTreeMap<Causetid, Vec<causetq_TV>>]) -> Result<(Vec<String>, usize)> {
+    let mut terms = vec![];
+    let mut affected = 0;
+    for &e in causets {
+        let causet = e.to_string();
+        let before = terms.len();
+        for (&(i, ref causet_locale, ref key), current) in assignments.iter().zip(current.iter()) {
+            let column = &table.columns[i];
+            let attribute = &column.attribute;
+            let causet_locales = current.get(&e).map_or(&[][..], |vs| &vs[..]);
+            let existing: Vec<String> = causet_locales.iter().map(render_causet_locale).collect();
+            if causet_locale.is_some() && existing.len() == 1 && existing.get(0) == causet_locale.as_ref() {
+                continue;
+            }
+            for v in existing.iter().filter(|v| Some(*v) != causet_locale.as_ref()) {
+                terms.push(term(false, &causet, attribute, v));
+            }
+            if let &Some(ref v) = causet_locale {
+                if !existing.contains(v) {
+                    terms.push(term(true, &causet, attribute, v));
+                }
+            }
+            // Strings that collate equal share a sort key, which then stays.
+            if let Some((_, sort_key)) = column.collated() {
+                let existing = sort_keys(column, causet_locales)?;
+                for k in existing.iter().filter(|k| Some(*k) != key.as_ref()) {
+                    terms.push(term(false, &causet, sort_key, k));
+                }
+                if let &Some(ref k) = key {
+                    if !existing.contains(k) {
+                        terms.push(term(true, &causet, sort_key, k));
+                    }
+                }
+            }
+        }
+        if terms.len() > before {
+            affected += 1;
+        }
+    }
+    Ok((terms, affected))
+}
+
+/// Retract every causet_locale in `current` (one map per column of `table`), and the sort keys
+/// of collated ones.
+fn delete_terms(table: &SqlTable, causets: &[Causetid], current: &[BTreeMap<Causetid, Vec<causetq_TV>>]) -> Result<(Vec<String>, usize)> {
+    let mut terms = vec![];
+    let mut affected = 0;
+    for &e in causets {
+        let causet = e.to_string();
+        let before = terms.len();
+        for (column, current) in table.columns.iter().zip(current.iter()) {
+            let causet_locales = current.get(&e).map_or(&[][..], |vs| &vs[..]);
+            for v in causet_locales {
+                terms.push(term(false, &causet, &column.attribute, &render_causet_locale(v)));
+            }
+            if let Some((_, sort_key)) = column.collated() {
+                for k in sort_keys(column, causet_locales)? {
+                    terms.push(term(false, &causet, sort_key, &k));
+                }
+            }
+        }
+        if terms.len() > before {
+            affected += 1;
+        }
+    }
+    Ok((terms, affected))
+}
+
+fn transact_terms(conn: &mut Conn, sqlite: &mut rusqlite::Connection, terms: &[String]) -> Result<()> {
+    if terms.is_empty() {
+        return Ok(());
+    }
+    let transaction = format!("[{}]", terms.join(" "));
+    conn.transact(sqlite, transaction.as_str()).map_err(store_error)?;
+    Ok(())
+}
+
+pub fn execute_insert(conn: &mut Conn, sqlite: &mut rusqlite::Connection, catalog: &Catalog, insert: &Insert) -> Result<usize> {
+    let (table, rows) = plan_insert(catalog, insert)?;
+
+    if let Some(i) = table.primary_key() {
+        let column = &table.columns[i];
+        let mut keys = BTreeSet::new();
+        for row in rows.iter() {
+            let key = row.key.as_ref().unwrap();
+            if !keys.insert(key.clone()) {
+                return Err(Error::dup_entry(key, primary_key_name(table)));
+            }
+            if let Some(owner) = key_owner(conn, sqlite, column, key)? {
+                if row.causet != Some(owner) {
+                    return Err(Error::dup_entry(key, primary_key_name(table)));
+                }
+            }
+        }
+    }
+
+    transact_terms(conn, sqlite, &insert_terms(table, &rows))?;
+    Ok(rows.len())
+}
+
+pub fn execute_update(conn: &mut Conn, sqlite: &mut rusqlite::Connection, catalog: &Catalog, update: &Update) -> Result<usize> {
+    let table = catalog.table(&update.table.name).ok_or_else(|| Error::no_such_table(&update.table.name))?;
+    let assignments = plan_update(catalog, table, update)?;
+    let causets = matching_causets(conn, sqlite, catalog, &update.table, &update.selection)?;
+    if causets.is_empty() {
+        return Ok(0);
+    }
+
+    if let Some(pk) = table.primary_key() {
+        if let Some(&(_, Some(ref key), _)) = assignments.iter().find(|&&(i, _, _)| i == pk) {
+            if causets.len() > 1 {
+                return Err(Error::dup_entry(key, primary_key_name(table)));
+            }
+            if let Some(owner) = key_owner(conn, sqlite, &table.columns[pk], key)? {
+                if owner != causets[0] {
+                    return Err(Error::dup_entry(key, primary_key_name(table)));
+                }
+            }
+        }
+    }
+
+    let mut current = vec![];
+    for &(i, _, _) in assignments.iter() {
+        current.push(current_causet_locales(conn, sqlite, &causets, &table.columns[i])?);
+    }
+    let (terms, affected) = update_terms(table, &assignments, &causets, &current)?;
+    transact_terms(conn, sqlite, &terms)?;
+    Ok(affected)
+}
+
+pub fn execute_delete(conn: &mut Conn, sqlite: &mut rusqlite::Connection, catalog: &Catalog, delete: &Delete) -> Result<usize> {
+    let table = catalog.table(&delete.table.name).ok_or_else(|| Error::no_such_table(&delete.table.name))?;
+    let causets = matching_causets(conn, sqlite, catalog, &delete.table, &delete.selection)?;
+
+    let mut current = vec![];
+    for column in table.columns.iter() {
+        current.push(current_causet_locales(conn, sqlite, &causets, column)?);
+    }
+    let (terms, affected) = delete_terms(table, &causets, &current)?;
+    transact_terms(conn, sqlite, &terms)?;
+    Ok(affected)
+}
+
+/// Parse and run one SQL statement.
+pub fn execute(conn: &mut Conn, sqlite: &mut rusqlite::Connection, catalog: &Catalog, sql: &str) -> Result<Outcome> {
+    match parse_sql(sql)? {
+        Statement::Select(ref select) => execute_select(conn, sqlite, catalog, select).map(Outcome::Rows),
+        Statement::Insert(ref insert) => execute_insert(conn, sqlite, catalog, insert).map(Outcome::Affected),
+        Statement::Update(ref update) => execute_update(conn, sqlite, catalog, update).map(Outcome::Affected),
+        Statement::Delete(ref delete) => execute_delete(conn, sqlite, catalog, delete).map(Outcome::Affected),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::{
+        ERR_BAD_NULL,
+        ERR_FIELD_SPECIFIED_TWICE,
+        ERR_INVALID_JSON_TEXT,
+        ERR_JSON_SCHEMA_VALIDATION,
+        ERR_NO_DEFAULT_FOR_FIELD,
+        ERR_WRONG_VALUE_COUNT_ON_ROW,
+    };
+    use einstein_json::json::json_schema::JsonSchema;
+    use crate::select::tests::catalog;
+
+    fn insert(sql: &str) -> Insert {
+        match parse_sql(sql).expect("parsed") {
+            Statement::Insert(insert) => insert,
+            other => panic!("expected an insert, got {:?}", other),
+        }
+    }
+
+    fn update(sql: &str) -> Update {
+        match parse_sql(sql).expect("parsed") {
+            Statement::Update(update) => update,
+            other => panic!("expected an update, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_insert_terms() {
+        let catalog = catalog();
+        let (table, rows) = plan_insert(&catalog, &insert("INSERT INTO person (name, email, age) VALUES \
+                                                           ('Ann', 'ann@example.com', NULL), \
+                                                           ('Bob \"B\"', 'bob@example.com', 30)")).unwrap();
+        assert_eq!(rows[0].key, Some("\"ann@example.com\"".to_string()));
+        assert_eq!(insert_terms(table, &rows), vec![
+            "[:einsteindb/add \"row0\" :person/name \"Ann\"]",
+            "[:einsteindb/add \"row0\" :person/email \"ann@example.com\"]",
+            "[:einsteindb/add \"row1\" :person/name \"Bob \\\"B\\\"\"]",
+            "[:einsteindb/add \"row1\" :person/email \"bob@example.com\"]",
+            "[:einsteindb/add \"row1\" :person/age 30]",
+        ]);
+
+        // A collated column's causet_locale comes with its sort key.
+        let (table, rows) = plan_insert(&catalog, &insert("INSERT INTO person (email, city) VALUES ('cy@example.com', 'Paris')")).unwrap();
+        assert_eq!(insert_terms(table, &rows), vec![
+            "[:einsteindb/add \"row0\" :person/email \"cy@example.com\"]",
+            "[:einsteindb/add \"row0\" :person/city \"Paris\"]",
+            "[:einsteindb/add \"row0\" :person.sort_key/city \"010050004100520049FF0053000000000000F9\"]",
+        ]);
+
+        // An explicit id asserts onto an existing causet; a table without a key needs none.
+        let (table, rows) = plan_insert(&catalog, &insert("INSERT INTO purchase (id, tag) VALUES (65536, ':tag/gift')")).unwrap();
+        assert_eq!(insert_terms(table, &rows), vec!["[:einsteindb/add 65536 :purchase/tag :tag/gift]"]);
+    }
+
+    #[test]
+    fn test_insert_errors() {
+        let catalog = catalog();
+        let code = |sql: &str| plan_insert(&catalog, &insert(sql)).unwrap_err().code();
+        assert_eq!(code("INSERT INTO person (name) VALUES ('Ann')"), ERR_NO_DEFAULT_FOR_FIELD);
+        assert_eq!(code("INSERT INTO person (email, name) VALUES ('a@b.c')"), ERR_WRONG_VALUE_COUNT_ON_ROW);
+        assert_eq!(code("INSERT INTO person (email, EMAIL) VALUES ('a@b.c', 'a@b.c')"), ERR_FIELD_SPECIFIED_TWICE);
+    }
+
+    #[test]
+    fn test_update_terms() {
+        let catalog = catalog();
+        let table = catalog.table("person").unwrap();
+        let assignments = plan_update(&catalog, table, &update("UPDATE person SET age = 31, name = NULL WHERE age = 30")).unwrap();
+        assert_eq!(assignments, vec![(1, Some("31".to_string()), None), (0, None, None)]);
+
+        let mut ages = BTreeMap::new();
+        ages.insert(1, vec![causetq_TV::Long(30)]);
+        ages.insert(2, vec![causetq_TV::Long(31)]);
+        let mut names = BTreeMap::new();
+        names.insert(1, vec![causetq_TV::typed_string("Ann")]);
+
+        // Causet 2 already has age 31 and no name, so only causet 1 changes.
+        let (terms, affected) = update_terms(table, &assignments, &[1, 2], &[ages, names]).unwrap();
+        assert_eq!(terms, vec![
+            "[:einsteindb/retract 1 :person/age 30]",
+            "[:einsteindb/add 1 :person/age 31]",
+            "[:einsteindb/retract 1 :person/name \"Ann\"]",
+        ]);
+        assert_eq!(affected, 1);
+
+        // 'paris' and 'PARIS' share a sort key, so only the causet_locale changes; 'Lyon' gets its own.
+        let assignments = plan_update(&catalog, table, &update("UPDATE person SET city = 'PARIS'")).unwrap();
+        let mut cities = BTreeMap::new();
+        cities.insert(1, vec![causetq_TV::typed_string("paris")]);
+        cities.insert(2, vec![causetq_TV::typed_string("Lyon")]);
+        let (terms, affected) = update_terms(table, &assignments, &[1, 2], &[cities]).unwrap();
+        assert_eq!(terms, vec![
+            "[:einsteindb/retract 1 :person/city \"paris\"]",
+            "[:einsteindb/add 1 :person/city \"PARIS\"]",
+            "[:einsteindb/retract 2 :person/city \"Lyon\"]",
+            "[:einsteindb/add 2 :person/city \"PARIS\"]",
+            "[:einsteindb/retract 2 :person.sort_key/city \"01004C0059004F004EFF0000000000000000F7\"]",
+            "[:einsteindb/add 2 :person.sort_key/city \"010050004100520049FF0053000000000000F9\"]",
+        ]);
+        assert_eq!(affected, 2);
+
+        assert_eq!(plan_update(&catalog, table, &update("UPDATE person SET email = NULL")).unwrap_err().code(), ERR_BAD_NULL);
+    }
+
+    #[test]
+    fn test_json_schema_checks() {
+        let mut catalog = catalog();
+        let schema: Json = r#"{"type": "string", "minLength": 2}"#.parse().unwrap();
+        catalog.attach_json_schema(Keyword::namespaced("person", "city"),
+                                   JsonSchema::new(schema.as_ref()).unwrap());
+
+        let insert_code = |sql: &str| plan_insert(&catalog, &insert(sql)).map(|_| ()).map_err(|e| e.code());
+        assert_eq!(insert_code("INSERT INTO person (email, city) VALUES ('a@b.c', '\"Berlin\"')"), Ok(()));
+        assert_eq!(insert_code("INSERT INTO person (email, city) VALUES ('a@b.c', NULL)"), Ok(()));
+        assert_eq!(insert_code("INSERT INTO person (email, city) VALUES ('a@b.c', '\"B\"')"), Err(ERR_JSON_SCHEMA_VALIDATION));
+        assert_eq!(insert_code("INSERT INTO person (email, city) VALUES ('a@b.c', 'Berlin')"), Err(ERR_INVALID_JSON_TEXT));
+
+        let table = catalog.table("person").unwrap();
+        let code = plan_update(&catalog, table, &update("UPDATE person SET city = '7'")).unwrap_err().code();
+        assert_eq!(code, ERR_JSON_SCHEMA_VALIDATION);
+    }
+
+    #[test]
+    fn test_delete_terms() {
+        let catalog = catalog();
+        let table = catalog.table("purchase").unwrap();
+        let mut tags = BTreeMap::new();
+        tags.insert(7, vec![causetq_TV::Keyword(Keyword::namespaced("tag", "a").into()),
+                            causetq_TV::Keyword(Keyword::namespaced("tag", "b").into())]);
+        let (terms, affected) = delete_terms(table, &[7, 8], &[BTreeMap::new(), BTreeMap::new(), tags]).unwrap();
+        assert_eq!(terms, vec![
+            "[:einsteindb/retract 7 :purchase/tag :tag/a]",
+            "[:einsteindb/retract 7 :purchase/tag :tag/b]",
+        ]);
+        assert_eq!(affected, 1);
+
+        let table = catalog.table("person").unwrap();
+        let mut cities = BTreeMap::new();
+        cities.insert(3, vec![causetq_TV::typed_string("Paris")]);
+        let (terms, _) = delete_terms(table, &[3], &[BTreeMap::new(), BTreeMap::new(), cities, BTreeMap::new()]).unwrap();
+        assert_eq!(terms, vec![
+            "[:einsteindb/retract 3 :person/city \"Paris\"]",
+            "[:einsteindb/retract 3 :person.sort_key/city \"010050004100520049FF0053000000000000F9\"]",
+        ]);
+    }
+}
@@ -191,6 +191,8 @@ use std::ops::{Add, Sub, Mul, Div};
 use std::str::FromStr;
 use std::string::FromUtf8Error;
 
+use crate::time::{check_fsp, Time, TimeType, DEFAULT_FSP, MAX_FSP};
+
 
 
 #[]
@@ -377,9 +379,9 @@ mod parser {
     ) -> IResult<&'a str, Duration, ()> {
         let (rest, digits) = digit1(input)?;
         if digits.len() == 12 || digits.len() == 14 {
-            let datetime = DateTime::parse_datetime(ctx, input, fsp as i8, true)
+            let datetime = Time::parse(input, TimeType::DateTime, fsp as i8)
                 .map_err(|_| nom::Err::Error(()))?;
-            return Ok(("", datetime.convert(ctx).map_err(|_| nom::Err::Error(()))?));
+            return Ok(("", datetime.to_duration().map_err(|_| nom::Err::Error(()))?));
         }
         let (rest, _) = anysep(rest)?;
         let (rest, _) = digit1(rest)?;
@@ -395,9 +397,9 @@ mod parser {
             return Err(nom::Err::Error(()));
         }
 
-        let datetime = DateTime::parse_datetime(ctx, input, fsp as i8, true)
+        let datetime = Time::parse(input, TimeType::DateTime, fsp as i8)
             .map_err(|_| nom::Err::Error(()))?;
-        Ok(("", datetime.convert(ctx).map_err(|_| nom::Err::Error(()))?))
+        Ok(("", datetime.to_duration().map_err(|_| nom::Err::Error(()))?))
     }
 
     fn anysep(input: &str) -> IResult<&str, char, ()> {
@@ -743,8 +745,8 @@ impl Duration {
 
         let nanos = check_nanos_with_ctx(ctx, nanos)?;
         if n >= 10000000000 {
-            if let Ok(t) = DateTime::parse_from_i64(ctx, n, TimeType::DateTime, fsp) {
-                return t.convert(ctx);
+            if let Ok(t) = Time::parse_from_i64(n, TimeType::DateTime, fsp as i8) {
+                return t.to_duration();
             }
         }
         ctx.handle_overCausetxctx_err(Error::overCausetxctx("Duration", n))?;
@@ -1209,8 +1211,8 @@ mod tests {
             ("0000-00-00 00:00:00", 6, "000000"),
         ];
         for (s, fsp, expect) in cases {
-            let t = DateTime::parse_datetime(&mut ctx, s, fsp, true).unwrap();
-            let du: Duration = t.convert(&mut ctx).unwrap();
+            let t = Time::parse(s, TimeType::DateTime, fsp).unwrap();
+            let du = t.to_duration().unwrap();
             let get: Decimal = du.convert(&mut ctx).unwrap();
             assert_eq!(
                 get,
@@ -1234,8 +1236,8 @@ mod tests {
         ];
         let mut ctx = EvalContext::default();
         for (s, fsp, expect) in cases {
-            let t = DateTime::parse_datetime(&mut ctx, s, fsp, true).unwrap();
-            let du: Duration = t.convert(&mut ctx).unwrap();
+            let t = Time::parse(s, TimeType::DateTime, fsp).unwrap();
+            let du = t.to_duration().unwrap();
             let get: f64 = du.convert(&mut ctx).unwrap();
             assert!(
                 (expect - get).abs() < EPSILON,
@@ -96,6 +96,7 @@ impl fmt::Display for BerolinaSqlError {
      }
  }
 
+ pub const ERR_TOO_BIG_PRECISION: i32 = 1426;
  pub const ERR_M_BIGGER_THAN_D: i32 = 1427;
  pub const ERR_UNCAUSET_LOCALE_NUCLEON: i32 = 1105;
  pub const ERR_REGEXP: i32 = 1139;
@@ -108,6 +109,25 @@ impl fmt::Display for BerolinaSqlError {
 pub const ERR_DATA_TOO_LONG: i32 = 1406;
 pub const ERR_INCORRECT_PARAMETERS: i32 = 1583;
 pub const ERR_DATA_OUT_OF_RANGE: i32 = 1690;
+pub const ERR_BAD_NULL: i32 = 1048;
+pub const ERR_UNKNOWN_COLLATION: i32 = 1273;
+pub const ERR_COLLATION_CHARSET_MISMATCH: i32 = 1253;
+pub const ERR_NON_UNIQ: i32 = 1052;
+pub const ERR_BAD_FIELD: i32 = 1054;
+pub const ERR_WRONG_FIELD_WITH_GROUP: i32 = 1055;
+pub const ERR_PARSE: i32 = 1064;
+pub const ERR_DUP_ENTRY: i32 = 1062;
+pub const ERR_NONUNIQ_TABLE: i32 = 1066;
+pub const ERR_FIELD_SPECIFIED_TWICE: i32 = 1110;
+pub const ERR_WRONG_VALUE_COUNT_ON_ROW: i32 = 1136;
+pub const ERR_NO_SUCH_TABLE: i32 = 1146;
+pub const ERR_NOT_SUPPORTED_YET: i32 = 1235;
+pub const ERR_NO_DEFAULT_FOR_FIELD: i32 = 1364;
+pub const ERR_DUP_FIELDNAME: i32 = 1060;
+pub const ERR_INVALID_JSON_TEXT: i32 = 3140;
+pub const ERR_JT_VALUE_OUT_OF_RANGE: i32 = 3664;
+pub const ERR_JT_MISSING_VALUE: i32 = 3665;
+pub const ERR_JSON_SCHEMA_VALIDATION: i32 = 3934;
 
 quick_error! {
     #[derive(Debug)]
@@ -160,6 +180,14 @@ impl Error {
         Error::Eval(msg, ERR_M_BIGGER_THAN_D)
     }
 
+    pub fn too_big_precision(precision: i8, max: i8) -> Error {
+        let msg = format!(
+            "Too big precision {} specified. Maximum is {}.",
+            precision, max
+        );
+        Error::Eval(msg, ERR_TOO_BIG_PRECISION)
+    }
+
     pub fn cast_neg_int_as_unsigned() -> Error {
         let msg = "Cast to unsigned converted negative integer to it's positive complement";
         Error::Eval(msg.into(), ERR_UNCAUSET_LOCALE_NUCLEON)
@@ -189,6 +217,104 @@ impl Error {
         }
     }
 
+    pub fn sql_syntax(err: impl Display) -> Error {
+        let msg = format!("You have an error in your SQL syntax: {}", err);
+        Error::Eval(msg, ERR_PARSE)
+    }
+
+    pub fn no_such_table(table: impl Display) -> Error {
+        let msg = format!("Table '{}' doesn't exist", table);
+        Error::Eval(msg, ERR_NO_SUCH_TABLE)
+    }
+
+    pub fn nonunique_table(table: impl Display) -> Error {
+        let msg = format!("Not unique table/alias: '{}'", table);
+        Error::Eval(msg, ERR_NONUNIQ_TABLE)
+    }
+
+    pub fn bad_field(column: impl Display, clause: &str) -> Error {
+        let msg = format!("Unknown column '{}' in '{}'", column, clause);
+        Error::Eval(msg, ERR_BAD_FIELD)
+    }
+
+    pub fn ambiguous_field(column: impl Display, clause: &str) -> Error {
+        let msg = format!("Column '{}' in {} is ambiguous", column, clause);
+        Error::Eval(msg, ERR_NON_UNIQ)
+    }
+
+    pub fn wrong_field_with_group(column: impl Display) -> Error {
+        let msg = format!("Expression '{}' is not in GROUP BY clause and contains nonaggregated column", column);
+        Error::Eval(msg, ERR_WRONG_FIELD_WITH_GROUP)
+    }
+
+    pub fn dup_entry(causet_locale: impl Display, key: impl Display) -> Error {
+        let msg = format!("Duplicate entry '{}' for key '{}'", causet_locale, key);
+        Error::Eval(msg, ERR_DUP_ENTRY)
+    }
+
+    pub fn bad_null(column: impl Display) -> Error {
+        let msg = format!("Column '{}' cannot be null", column);
+        Error::Eval(msg, ERR_BAD_NULL)
+    }
+
+    pub fn no_default_for_field(column: impl Display) -> Error {
+        let msg = format!("Field '{}' doesn't have a default causet_locale", column);
+        Error::Eval(msg, ERR_NO_DEFAULT_FOR_FIELD)
+    }
+
+    pub fn field_specified_twice(column: impl Display) -> Error {
+        let msg = format!("Column '{}' specified twice", column);
+        Error::Eval(msg, ERR_FIELD_SPECIFIED_TWICE)
+    }
+
+    pub fn wrong_value_count_on_row(row: usize) -> Error {
+        let msg = format!("Column count doesn't match causet_locale count at row {}", row);
+        Error::Eval(msg, ERR_WRONG_VALUE_COUNT_ON_ROW)
+    }
+
+    pub fn dup_fieldname(column: impl Display) -> Error {
+        let msg = format!("Duplicate column name '{}'", column);
+        Error::Eval(msg, ERR_DUP_FIELDNAME)
+    }
+
+    pub fn invalid_json_text(err: impl Display) -> Error {
+        let msg = format!("Invalid JSON text: {}", err);
+        Error::Eval(msg, ERR_INVALID_JSON_TEXT)
+    }
+
+    pub fn jt_value_out_of_range(column: impl Display) -> Error {
+        let msg = format!("Value is out of range for JSON_TABLE's column '{}'", column);
+        Error::Eval(msg, ERR_JT_VALUE_OUT_OF_RANGE)
+    }
+
+    pub fn jt_missing_value(column: impl Display) -> Error {
+        let msg = format!("Missing value for JSON_TABLE column '{}'", column);
+        Error::Eval(msg, ERR_JT_MISSING_VALUE)
+    }
+
+    pub fn json_schema_violation(column: impl Display, reason: impl Display) -> Error {
+        let msg = format!("Column '{}' violates its JSON Schema: {}", column, reason);
+        Error::Eval(msg, ERR_JSON_SCHEMA_VALIDATION)
+    }
+
+    pub fn unknown_collation(collation: impl Display) -> Error {
+        let msg = format!("Unknown collation: '{}'", collation);
+        Error::Eval(msg, ERR_UNKNOWN_COLLATION)
+    }
+
+    pub fn collation_charset_mismatch(collation: impl Display, charset: impl Display) -> Error {
+        let msg = format!(
+            "COLLATION '{}' is not valid for CHARACTER SET '{}'",
+            collation, charset
+        );
+        Error::Eval(msg, ERR_COLLATION_CHARSET_MISMATCH)
+    }
+
+    pub fn not_supported_yet(what: impl Display) -> Error {
+        let msg = format!("This version doesn't yet support '{}'", what);
+        Error::Eval(msg, ERR_NOT_SUPPORTED_YET)
+    }
+
     pub fn code(&self) -> i32 {
         match *self {
             Error::Eval(_, code) => code,
@@ -272,6 +398,15 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<einstein_json::Error> for Error {
+    fn from(err: einstein_json::Error) -> Error {
+        match err {
+            einstein_json::Error::Eval(msg, code) => Error::Eval(msg, code),
+            err => box_err!("json: {}", err),
+        }
+    }
+}
+
 impl From<RegexpError> for Error {
     fn from(err: RegexpError) -> Error {
         let msg = format!("Got error '{:.64}' from regexp", err);
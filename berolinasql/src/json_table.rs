@@ -0,0 +1,428 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+//! `JSON_TABLE`: unnesting a JSON document into rows.
+//!
+//! The row path is matched against the document and every match becomes a row (or several,
+//! when there are `NESTED PATH` columns). Each column then takes its causet_locale from its own
+//! path, relative to the match:
+//!
+//!   * `FOR ORDINALITY` counts the matches of the enclosing path, from 1;
+//!   * `EXISTS PATH` is 1 if the path matches anything and 0 otherwise;
+//!   * `PATH` converts the single matched causet_locale to the column type. A path that matches
+//!     nothing is handled by `ON EMPTY`; one that matches several causet_locales, or a causet_locale
+//!     that can't be converted, by `ON ERROR`. Both default to `NULL`;
+//!   * `NESTED PATH` joins the rows of its own columns to the enclosing row. A nested path
+//!     that matches nothing still yields the enclosing row, with `NULL` for its columns, and
+//!     sibling nested paths yield their rows one after the other rather than a product.
+
+use std::collections::BTreeSet;
+
+use einstein_json::{Json, JsonRef, JsonType};
+use einstein_json::json::json_extract::extract_json;
+use einstein_json::json::path_expr::{parse_json_local_path_expr, LocalPathExpression};
+use crate::error::{Error, Result};
+use crate::parser::{parse_json_table, JsonTableColumnDef, JsonTableExpr, JsonTableOnResponse};
+use crate::table::{Column, Row, Table};
+use crate::value::{Value, ValueType};
+
+/// The name a `JSON_TABLE` without an alias gets.
+pub const JSON_TABLE_DEFAULT_NAME: &str = "json_table";
+
+/// The column types `JSON_TABLE` can produce. `JSON` columns hold the matched causet_locale's
+/// JSON text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JsonTableColumnType {
+    Long,
+    Double,
+    String,
+    Boolean,
+    Json,
+}
+
+impl JsonTableColumnType {
+    pub fn from_sql(data_type: &str) -> Result<JsonTableColumnType> {
+        match data_type {
+            "INT" | "INTEGER" | "BIGINT" | "MEDIUMINT" | "SMALLINT" | "TINYINT" => Ok(JsonTableColumnType::Long),
+            "DOUBLE" | "FLOAT" | "REAL" | "DECIMAL" | "NUMERIC" => Ok(JsonTableColumnType::Double),
+            "CHAR" | "VARCHAR" | "TEXT" => Ok(JsonTableColumnType::String),
+            "BOOL" | "BOOLEAN" => Ok(JsonTableColumnType::Boolean),
+            "JSON" => Ok(JsonTableColumnType::Json),
+            other => Err(Error::not_supported_yet(format!("JSON_TABLE columns of type {}", other))),
+        }
+    }
+
+    pub fn causet_locale_type(self) -> ValueType {
+        match self {
+            JsonTableColumnType::Long => ValueType::Long,
+            JsonTableColumnType::Double => ValueType::Double,
+            JsonTableColumnType::String | JsonTableColumnType::Json => ValueType::String,
+            JsonTableColumnType::Boolean => ValueType::Boolean,
+        }
+    }
+
+    /// Convert a matched causet_locale, or return `None` if it doesn't fit this type. JSON `null`
+    /// converts to SQL `NULL`.
+    /// The causet_locale of an `EXISTS PATH` column: 1 or 0, as this type.
+    fn exists(self, exists: bool) -> Value {
+        let x = if exists { 1 } else { 0 };
+        match self {
+            JsonTableColumnType::Long => Value::Long(x),
+            JsonTableColumnType::Double => Value::Double((x as f64).into()),
+            JsonTableColumnType::String | JsonTableColumnType::Json => Value::typed_string(x.to_string()),
+            JsonTableColumnType::Boolean => Value::Boolean(exists),
+        }
+    }
+
+    fn convert(self, j: JsonRef<'_>) -> Option<Option<Value>> {
+        if j.get_type() == JsonType::Literal && j.get_literal().is_none() {
+            return Some(None);
+        }
+        let v = match (self, j.get_type()) {
+            (JsonTableColumnType::Json, _) => Value::typed_string(j.to_owned().to_string()),
+            (_, JsonType::Object) | (_, JsonType::Array) => return None,
+
+            (JsonTableColumnType::Long, JsonType::I64) => Value::Long(j.get_i64()),
+            (JsonTableColumnType::Long, JsonType::U64) if j.get_u64() <= i64::MAX as u64 => Value::Long(j.get_u64() as i64),
+            (JsonTableColumnType::Long, JsonType::Double) => {
+                let d = j.get_double();
+                if d.fract() != 0.0 || d < i64::MIN as f64 || d > i64::MAX as f64 {
+                    return None;
+                }
+                Value::Long(d as i64)
+            },
+            (JsonTableColumnType::Long, JsonType::String) => Value::Long(j.get_str().ok()?.trim().parse().ok()?),
+            (JsonTableColumnType::Long, JsonType::Literal) => Value::Long(if j.get_literal()? { 1 } else { 0 }),
+            (JsonTableColumnType::Long, _) => return None,
+
+            (JsonTableColumnType::Double, JsonType::I64) => Value::Double((j.get_i64() as f64).into()),
+            (JsonTableColumnType::Double, JsonType::U64) => Value::Double((j.get_u64() as f64).into()),
+            (JsonTableColumnType::Double, JsonType::Double) => Value::Double(j.get_double().into()),
+            (JsonTableColumnType::Double, JsonType::String) => {
+                let d: f64 = j.get_str().ok()?.trim().parse().ok()?;
+                Value::Double(d.into())
+            },
+            (JsonTableColumnType::Double, JsonType::Literal) => Value::Double(if j.get_literal()? { 1.0 } else { 0.0 }.into()),
+
+            (JsonTableColumnType::String, JsonType::String) => Value::typed_string(j.get_str().ok()?),
+            (JsonTableColumnType::String, _) => Value::typed_string(j.to_owned().to_string()),
+
+            (JsonTableColumnType::Boolean, JsonType::Literal) => Value::Boolean(j.get_literal()?),
+            (JsonTableColumnType::Boolean, JsonType::I64) => Value::Boolean(j.get_i64() != 0),
+            (JsonTableColumnType::Boolean, JsonType::U64) => Value::Boolean(j.get_u64() != 0),
+            (JsonTableColumnType::Boolean, JsonType::Double) => Value::Boolean(j.get_double() != 0.0),
+            (JsonTableColumnType::Boolean, _) => return None,
+        };
+        Some(Some(v))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OnResponse {
+    Null,
+    Error,
+    Default(Json),
+}
+
+impl OnResponse {
+    fn from_sql(response: &JsonTableOnResponse) -> Result<OnResponse> {
+        Ok(match response {
+            &JsonTableOnResponse::Null => OnResponse::Null,
+            &JsonTableOnResponse::Error => OnResponse::Error,
+            &JsonTableOnResponse::Default(ref text) =>
+                OnResponse::Default(text.parse::<Json>().map_err(Error::invalid_json_text)?),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum JsonTableColumn {
+    Ordinality {
+        slot: usize,
+    },
+    Path {
+        slot: usize,
+        name: String,
+        column_type: JsonTableColumnType,
+        path: LocalPathExpression,
+        on_empty: OnResponse,
+        on_error: OnResponse,
+    },
+    Exists {
+        slot: usize,
+        column_type: JsonTableColumnType,
+        path: LocalPathExpression,
+    },
+    Nested(JsonTableScope),
+}
+
+/// A path and the columns that take their causet_locales from each of its matches.
+#[derive(Clone, Debug)]
+pub struct JsonTableScope {
+    pub path: LocalPathExpression,
+    pub columns: Vec<JsonTableColumn>,
+}
+
+/// A `JSON_TABLE`, with its paths parsed and its columns laid out.
+#[derive(Clone, Debug)]
+pub struct JsonTable {
+    pub name: String,
+    /// Output columns, in definition order; nested columns are flattened into place.
+    pub columns: Vec<Column>,
+    pub scope: JsonTableScope,
+}
+
+impl JsonTable {
+    pub fn new(expr: &JsonTableExpr) -> Result<JsonTable> {
+        let mut columns = vec![];
+        let mut names = BTreeSet::new();
+        let scope = JsonTableScope {
+            path: parse_json_local_path_expr(&expr.path)?,
+            columns: JsonTable::lay_out(&expr.columns, &mut columns, &mut names)?,
+        };
+        Ok(JsonTable {
+            name: expr.alias.clone().unwrap_or_else(|| JSON_TABLE_DEFAULT_NAME.to_string()),
+            columns,
+            scope,
+        })
+    }
+
+    fn lay_out(defs: &[JsonTableColumnDef],
+               columns: &mut Vec<Column>,
+               names: &mut BTreeSet<String>) -> Result<Vec<JsonTableColumn>> {
+        let mut out = Vec::with_capacity(defs.len());
+        for def in defs {
+            let (name, causet_locale_type) = match def {
+                &JsonTableColumnDef::Nested { ref path, columns: ref nested } => {
+                    out.push(JsonTableColumn::Nested(JsonTableScope {
+                        path: parse_json_local_path_expr(path)?,
+                        columns: JsonTable::lay_out(nested, columns, names)?,
+                    }));
+                    continue;
+                },
+                &JsonTableColumnDef::Ordinality { ref name } => {
+                    out.push(JsonTableColumn::Ordinality { slot: columns.len() });
+                    (name, ValueType::Long)
+                },
+                &JsonTableColumnDef::Exists { ref name, ref data_type, ref path } => {
+                    let column_type = JsonTableColumnType::from_sql(data_type)?;
+                    out.push(JsonTableColumn::Exists { slot: columns.len(), column_type, path: parse_json_local_path_expr(path)? });
+                    (name, column_type.causet_locale_type())
+                },
+                &JsonTableColumnDef::Path { ref name, ref data_type, ref path, ref on_empty, ref on_error } => {
+                    let column_type = JsonTableColumnType::from_sql(data_type)?;
+                    out.push(JsonTableColumn::Path {
+                        slot: columns.len(),
+                        name: name.clone(),
+                        column_type,
+                        path: parse_json_local_path_expr(path)?,
+                        on_empty: OnResponse::from_sql(on_empty)?,
+                        on_error: OnResponse::from_sql(on_error)?,
+                    });
+                    (name, column_type.causet_locale_type())
+                },
+            };
+            // Column names are case-insensitive.
+            if !names.insert(name.to_lowercase()) {
+                return Err(Error::dup_fieldname(name));
+            }
+            columns.push(Column::new(name.clone(), causet_locale_type));
+        }
+        Ok(out)
+    }
+
+    /// Unnest `doc` into rows.
+    pub fn evaluate(&self, doc: JsonRef<'_>) -> Result<Table> {
+        let width = self.columns.len();
+        let mut rows = vec![];
+        for (i, m) in extract_json(doc, &self.scope.path.legs)?.into_iter().enumerate() {
+            rows.extend(scope_rows(&self.scope, m, i + 1, width)?);
+        }
+        let columns = self.columns.iter().map(|c| Column::new(c.name.clone(), c.value_type)).collect();
+        Ok(Table::new(self.name.clone(), columns, rows.into_iter().map(Row::new).collect()))
+    }
+}
+
+/// The rows one match of `scope` yields.
+fn scope_rows(scope: &JsonTableScope, m: JsonRef<'_>, ordinality: usize, width: usize) -> Result<Vec<Vec<Option<Value>>>> {
+    let mut base = vec![None; width];
+    let mut nested = vec![];
+    for column in scope.columns.iter() {
+        match column {
+            &JsonTableColumn::Ordinality { slot } => base[slot] = Some(Value::Long(ordinality as i64)),
+            &JsonTableColumn::Exists { slot, column_type, ref path } => {
+                let exists = !extract_json(m, &path.legs)?.is_empty();
+                base[slot] = Some(column_type.exists(exists));
+            },
+            &JsonTableColumn::Path { slot, ref name, column_type, ref path, ref on_empty, ref on_error } => {
+                let found = extract_json(m, &path.legs)?;
+                base[slot] = match found.len() {
+                    0 => respond(on_empty, column_type, name, Error::jt_missing_value(name))?,
+                    1 => match column_type.convert(found[0]) {
+                        Some(v) => v,
+                        None => respond(on_error, column_type, name, Error::jt_value_out_of_range(name))?,
+                    },
+                    _ => respond(on_error, column_type, name, Error::jt_value_out_of_range(name))?,
+                };
+            },
+            &JsonTableColumn::Nested(ref scope) => nested.push(scope),
+        }
+    }
+
+    let mut rows = vec![];
+    for scope in nested {
+        for (i, nm) in extract_json(m, &scope.path.legs)?.into_iter().enumerate() {
+            for row in scope_rows(scope, nm, i + 1, width)? {
+                let mut merged = base.clone();
+                for (slot, v) in row.into_iter().enumerate() {
+                    if v.is_some() {
+                        merged[slot] = v;
+                    }
+                }
+                rows.push(merged);
+            }
+        }
+    }
+    if rows.is_empty() {
+        rows.push(base);
+    }
+    Ok(rows)
+}
+
+fn respond(response: &OnResponse, column_type: JsonTableColumnType, name: &str, err: Error) -> Result<Option<Value>> {
+    match response {
+        &OnResponse::Null => Ok(None),
+        &OnResponse::Error => Err(err),
+        &OnResponse::Default(ref j) => column_type.convert(j.as_ref()).ok_or_else(|| Error::jt_value_out_of_range(name)),
+    }
+}
+
+/// Parse and evaluate a `JSON_TABLE(...)` table function.
+pub fn json_table(sql: &str) -> Result<Table> {
+    let expr = parse_json_table(sql)?;
+    let doc = expr.doc.parse::<Json>().map_err(Error::invalid_json_text)?;
+    JsonTable::new(&expr)?.evaluate(doc.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::error::{
+        ERR_DUP_FIELDNAME,
+        ERR_JT_MISSING_VALUE,
+        ERR_JT_VALUE_OUT_OF_RANGE,
+    };
+
+    fn rows(table: &Table) -> Vec<Vec<Option<Value>>> {
+        table.rows.iter().map(|r| r.values.clone()).collect()
+    }
+
+    fn long(x: i64) -> Option<Value> {
+        Some(Value::Long(x))
+    }
+
+    fn string(s: &str) -> Option<Value> {
+        Some(Value::typed_string(s))
+    }
+
+    #[test]
+    fn test_json_table_columns() {
+        let table = json_table(r#"JSON_TABLE('[{"a": 1, "b": "x"}, {"a": "2", "c": true}, {"a": [3]}, {"a": null}]', '$[*]'
+                                  COLUMNS (n FOR ORDINALITY,
+                                           a INT PATH '$.a',
+                                           b VARCHAR(10) PATH '$.b' DEFAULT '"none"' ON EMPTY,
+                                           has_c INT EXISTS PATH '$.c',
+                                           j JSON PATH '$.a')) AS t"#).unwrap();
+        assert_eq!(table.name, "t");
+        assert_eq!(table.columns.iter().map(|c| (c.name.as_str(), c.value_type)).collect::<Vec<_>>(),
+                   vec![("n", ValueType::Long), ("a", ValueType::Long), ("b", ValueType::String),
+                        ("has_c", ValueType::Long), ("j", ValueType::String)]);
+        assert_eq!(rows(&table), vec![
+            vec![long(1), long(1), string("x"), long(0), string("1")],
+            vec![long(2), long(2), string("none"), long(1), string("\"2\"")],
+            // An array can't be an INT: NULL ON ERROR.
+            vec![long(3), None, string("none"), long(0), string("[3]")],
+            vec![long(4), None, string("none"), long(0), None],
+        ]);
+    }
+
+    #[test]
+    fn test_json_table_exists_types() {
+        let table = json_table(r#"JSON_TABLE('[{"a": 1}, {}]', '$[*]'
+                                  COLUMNS (s VARCHAR(10) EXISTS PATH '$.a',
+                                           d DOUBLE EXISTS PATH '$.a',
+                                           b BOOLEAN EXISTS PATH '$.a'))"#).unwrap();
+        assert_eq!(table.columns.iter().map(|c| c.value_type).collect::<Vec<_>>(),
+                   vec![ValueType::String, ValueType::Double, ValueType::Boolean]);
+        assert_eq!(rows(&table), vec![
+            vec![string("1"), Some(Value::Double(1.0.into())), Some(Value::Boolean(true))],
+            vec![string("0"), Some(Value::Double(0.0.into())), Some(Value::Boolean(false))],
+        ]);
+    }
+
+    #[test]
+    fn test_json_table_nested() {
+        let table = json_table(r#"JSON_TABLE('[{"a": 1, "b": [10, 20], "c": ["x"]}, {"a": 2, "b": []}]', '$[*]'
+                                  COLUMNS (a INT PATH '$.a',
+                                           NESTED PATH '$.b[*]' COLUMNS (bn FOR ORDINALITY, b INT PATH '$'),
+                                           NESTED PATH '$.c[*]' COLUMNS (c VARCHAR(5) PATH '$')))"#).unwrap();
+        assert_eq!(table.name, JSON_TABLE_DEFAULT_NAME);
+        assert_eq!(rows(&table), vec![
+            vec![long(1), long(1), long(10), None],
+            vec![long(1), long(2), long(20), None],
+            vec![long(1), None, None, string("x")],
+            vec![long(2), None, None, None],
+        ]);
+
+        // Nested paths nest.
+        let table = json_table(r#"JSON_TABLE('{"o": [{"i": [1, 2]}, {"i": [3]}]}', '$'
+                                  COLUMNS (NESTED PATH '$.o[*]' COLUMNS (
+                                             o FOR ORDINALITY,
+                                             NESTED PATH '$.i[*]' COLUMNS (i INT PATH '$'))))"#).unwrap();
+        assert_eq!(rows(&table), vec![
+            vec![long(1), long(1)],
+            vec![long(1), long(2)],
+            vec![long(2), long(3)],
+        ]);
+
+        // A row path that matches nothing yields no rows.
+        let table = json_table("JSON_TABLE('{}', '$.x[*]' COLUMNS (a INT PATH '$'))").unwrap();
+        assert!(table.rows.is_empty());
+    }
+
+    #[test]
+    fn test_json_table_on_empty_and_error() {
+        let table = json_table(r#"JSON_TABLE('[{"a": "x"}, {}]', '$[*]'
+                                  COLUMNS (a INT PATH '$.a' DEFAULT '-1' ON EMPTY DEFAULT '-2' ON ERROR,
+                                           w INT PATH '$.*' DEFAULT '0' ON ERROR))"#).unwrap();
+        assert_eq!(rows(&table), vec![
+            vec![long(-2), long(0)],
+            vec![long(-1), None],
+        ]);
+
+        let err = json_table("JSON_TABLE('[{}]', '$[*]' COLUMNS (a INT PATH '$.a' ERROR ON EMPTY))").unwrap_err();
+        assert_eq!(err.code(), ERR_JT_MISSING_VALUE);
+        let err = json_table(r#"JSON_TABLE('[{"a": "x"}]', '$[*]' COLUMNS (a INT PATH '$.a' ERROR ON ERROR))"#).unwrap_err();
+        assert_eq!(err.code(), ERR_JT_VALUE_OUT_OF_RANGE);
+        // A default that doesn't fit the column is an error too.
+        let err = json_table(r#"JSON_TABLE('[{}]', '$[*]' COLUMNS (a INT PATH '$.a' DEFAULT '"x"' ON EMPTY))"#).unwrap_err();
+        assert_eq!(err.code(), ERR_JT_VALUE_OUT_OF_RANGE);
+    }
+
+    #[test]
+    fn test_json_table_errors() {
+        let err = json_table("JSON_TABLE('[]', '$' COLUMNS (a INT PATH '$', A INT PATH '$'))").unwrap_err();
+        assert_eq!(err.code(), ERR_DUP_FIELDNAME);
+        assert!(json_table("JSON_TABLE('[', '$' COLUMNS (a INT PATH '$'))").is_err());
+        assert!(json_table("JSON_TABLE('[]', 'a' COLUMNS (a INT PATH '$'))").is_err());
+        assert!(json_table("JSON_TABLE('[]', '$' COLUMNS (a BLOB PATH '$'))").is_err());
+    }
+}
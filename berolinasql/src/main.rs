@@ -1,12 +1,18 @@
 mod parser;
+mod select;
+mod dml;
+mod value;
 
 mod table;
 mod decimal;
+mod duration;
+mod time;
 mod error;
-mod binary;
 mod comparison;
-mod json_type;
-mod json_modify;
+mod charset;
+mod collation;
+mod uca_weights;
+mod json_table;
 mod overflow;
 
 
@@ -101,10 +107,10 @@ pub struct Error {
 
 
 pub enum Sql {
-    Select(Sql),
-    Insert(Sql),
-    Update(Sql),
-    Delete(Sql),
+    Select(parser::Select),
+    Insert(parser::Insert),
+    Update(parser::Update),
+    Delete(parser::Delete),
     Create(Sql),
     Drop(Sql),
     Alter(Sql),
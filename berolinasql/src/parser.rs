@@ -0,0 +1,1189 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+//! A small SQL parser.
+//!
+//! We only accept the dialect that `select` can compile to Datalog: a `SELECT` over one or more
+//! tables, inner joins, a conjunctive `WHERE`, `GROUP BY` with aggregates, `ORDER BY` and
+//! `LIMIT`; the single-table `INSERT`, `UPDATE` and `DELETE` that `dml` turns into
+//! transactions; and the `JSON_TABLE` table function that `json_table` evaluates. Keywords are case-insensitive; identifiers may be quoted with backticks or double
+//! quotes. Anything else is a `ParserError` carrying the byte offset it was found at.
+
+use std::fmt;
+
+use crate::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Select(Select),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Select {
+    pub distinct: bool,
+    pub projection: Vec<SelectItem>,
+    /// The comma-separated tables after `FROM`. Never empty.
+    pub from: Vec<TableFactor>,
+    pub joins: Vec<Join>,
+    pub selection: Option<Expr>,
+    pub group_by: Vec<ColumnRef>,
+    pub order_by: Vec<OrderByExpr>,
+    pub limit: Option<u64>,
+}
+
+/// `INSERT INTO table [(column, ...)] VALUES (expr, ...), ...`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    /// `None` means every column of the table, in order.
+    pub columns: Option<Vec<String>>,
+    pub rows: Vec<Vec<Expr>>,
+}
+
+/// `UPDATE table [alias] SET column = expr, ... [WHERE expr]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Update {
+    pub table: TableFactor,
+    pub assignments: Vec<(String, Expr)>,
+    pub selection: Option<Expr>,
+}
+
+/// `DELETE FROM table [alias] [WHERE expr]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Delete {
+    pub table: TableFactor,
+    pub selection: Option<Expr>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableFactor {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl TableFactor {
+    /// The name columns of this table are qualified with.
+    pub fn binding_name(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+}
+
+/// `[INNER] JOIN table ON constraint`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Join {
+    pub table: TableFactor,
+    pub constraint: Expr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SelectItem {
+    /// `*`.
+    Wildcard,
+    /// `t.*`.
+    QualifiedWildcard(String),
+    Expr {
+        expr: Expr,
+        alias: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ColumnRef {
+    pub table: Option<String>,
+    pub column: String,
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.table {
+            Some(ref table) => write!(f, "{}.{}", table, self.column),
+            None => write!(f, "{}", self.column),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Boolean(bool),
+    Null,
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Literal::Integer(i) => write!(f, "{}", i),
+            &Literal::Float(x) => write!(f, "{}", x),
+            &Literal::String(ref s) => write!(f, "'{}'", s.replace('\'', "''")),
+            &Literal::Boolean(b) => write!(f, "{}", if b { "TRUE" } else { "FALSE" }),
+            &Literal::Null => write!(f, "NULL"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    And,
+    Or,
+}
+
+impl BinaryOperator {
+    /// The operator that gives the same answer with its operands swapped.
+    pub fn flipped(self) -> BinaryOperator {
+        match self {
+            BinaryOperator::Lt => BinaryOperator::Gt,
+            BinaryOperator::LtEq => BinaryOperator::GtEq,
+            BinaryOperator::Gt => BinaryOperator::Lt,
+            BinaryOperator::GtEq => BinaryOperator::LtEq,
+            op => op,
+        }
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Eq => "=",
+            BinaryOperator::NotEq => "!=",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::LtEq => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::GtEq => ">=",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Column(ColumnRef),
+    Literal(Literal),
+    /// Only valid as the argument of `COUNT(*)`.
+    Wildcard,
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOperator,
+        right: Box<Expr>,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    InList {
+        expr: Box<Expr>,
+        list: Vec<Expr>,
+        negated: bool,
+    },
+    /// An aggregate call such as `COUNT(*)` or `MAX(age)`. The name is lowercased.
+    Function {
+        name: String,
+        args: Vec<Expr>,
+    },
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &Expr::Column(ref c) => write!(f, "{}", c),
+            &Expr::Literal(ref l) => write!(f, "{}", l),
+            &Expr::Wildcard => write!(f, "*"),
+            &Expr::Binary { ref left, op, ref right } => write!(f, "{} {} {}", left, op, right),
+            &Expr::IsNull { ref expr, negated } =>
+                write!(f, "{} IS {}NULL", expr, if negated { "NOT " } else { "" }),
+            &Expr::InList { ref expr, ref list, negated } => {
+                write!(f, "{} {}IN (", expr, if negated { "NOT " } else { "" })?;
+                for (i, e) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                write!(f, ")")
+            },
+            &Expr::Function { ref name, ref args } => {
+                write!(f, "{}(", name)?;
+                for (i, e) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", e)?;
+                }
+                write!(f, ")")
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OrderByExpr {
+    pub column: ColumnRef,
+    pub asc: bool,
+}
+
+/// `JSON_TABLE('doc', 'path' COLUMNS (...)) [AS alias]`. The document, paths and defaults are
+/// kept as text; `json_table` parses them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonTableExpr {
+    pub doc: String,
+    pub path: String,
+    pub columns: Vec<JsonTableColumnDef>,
+    pub alias: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonTableColumnDef {
+    /// `name FOR ORDINALITY`
+    Ordinality { name: String },
+    /// `name type PATH 'path' [on_empty] [on_error]`
+    Path {
+        name: String,
+        data_type: String,
+        path: String,
+        on_empty: JsonTableOnResponse,
+        on_error: JsonTableOnResponse,
+    },
+    /// `name type EXISTS PATH 'path'`
+    Exists { name: String, data_type: String, path: String },
+    /// `NESTED [PATH] 'path' COLUMNS (...)`
+    Nested { path: String, columns: Vec<JsonTableColumnDef> },
+}
+
+/// What a `JSON_TABLE` column yields when its path matches nothing (`ON EMPTY`) or its causet_locale
+/// can't be converted (`ON ERROR`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonTableOnResponse {
+    Null,
+    Error,
+    /// `DEFAULT 'json'`
+    Default(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParserError {
+    pub message: String,
+    /// Byte offset into the statement.
+    pub position: usize,
+}
+
+impl ParserError {
+    fn new<S: Into<String>>(message: S, position: usize) -> ParserError {
+        ParserError {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(err: ParserError) -> Error {
+        Error::sql_syntax(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A bare word: a keyword or an unquoted identifier.
+    Word(String),
+    QuotedIdent(String),
+    Number(String),
+    Str(String),
+    Comma,
+    Dot,
+    Star,
+    LParen,
+    RParen,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    Minus,
+    Semicolon,
+    Eof,
+}
+
+fn tokenize(sql: &str) -> Result<Vec<(Token, usize)>, ParserError> {
+    let bytes = sql.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = bytes[i];
+        let start = i;
+        match c {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                i += 1;
+                continue;
+            },
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                // A comment runs to the end of the line.
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            },
+            b',' => { tokens.push((Token::Comma, start)); i += 1; },
+            b'.' if !bytes.get(i + 1).map_or(false, u8::is_ascii_digit) => { tokens.push((Token::Dot, start)); i += 1; },
+            b'*' => { tokens.push((Token::Star, start)); i += 1; },
+            b'(' => { tokens.push((Token::LParen, start)); i += 1; },
+            b')' => { tokens.push((Token::RParen, start)); i += 1; },
+            b';' => { tokens.push((Token::Semicolon, start)); i += 1; },
+            b'-' => { tokens.push((Token::Minus, start)); i += 1; },
+            b'=' => { tokens.push((Token::Eq, start)); i += 1; },
+            b'!' if bytes.get(i + 1) == Some(&b'=') => { tokens.push((Token::NotEq, start)); i += 2; },
+            b'<' => match bytes.get(i + 1) {
+                Some(&b'=') => { tokens.push((Token::LtEq, start)); i += 2; },
+                Some(&b'>') => { tokens.push((Token::NotEq, start)); i += 2; },
+                _ => { tokens.push((Token::Lt, start)); i += 1; },
+            },
+            b'>' => match bytes.get(i + 1) {
+                Some(&b'=') => { tokens.push((Token::GtEq, start)); i += 2; },
+                _ => { tokens.push((Token::Gt, start)); i += 1; },
+            },
+            b'\'' | b'"' | b'`' => {
+                // A doubled quote inside the literal stands for itself.
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    let rest = &sql[i..];
+                    let ch = match rest.chars().next() {
+                        Some(ch) => ch,
+                        None => return Err(ParserError::new("unterminated quoted string", start)),
+                    };
+                    i += ch.len_utf8();
+                    if ch as u32 == c as u32 {
+                        if bytes.get(i) == Some(&c) {
+                            s.push(ch);
+                            i += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    s.push(ch);
+                }
+                if c == b'\'' {
+                    tokens.push((Token::Str(s), start));
+                } else if s.is_empty() {
+                    return Err(ParserError::new("empty quoted identifier", start));
+                } else {
+                    tokens.push((Token::QuotedIdent(s), start));
+                }
+            },
+            b'0'..=b'9' | b'.' => {
+                while i < bytes.len() && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+                    i += 1;
+                    if i < bytes.len() && (bytes[i] == b'+' || bytes[i] == b'-') {
+                        i += 1;
+                    }
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                tokens.push((Token::Number(sql[start..i].to_string()), start));
+            },
+            c if c == b'_' || c.is_ascii_alphabetic() => {
+                while i < bytes.len() && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push((Token::Word(sql[start..i].to_string()), start));
+            },
+            _ => {
+                let ch = sql[i..].chars().next().unwrap_or('?');
+                return Err(ParserError::new(format!("unexpected character '{}'", ch), start));
+            },
+        }
+    }
+    tokens.push((Token::Eof, sql.len()));
+    Ok(tokens)
+}
+
+/// Words that can't be used as unquoted identifiers, because they end the clause before them.
+const RESERVED: &[&str] = &[
+    "SELECT", "DISTINCT", "FROM", "WHERE", "JOIN", "INNER", "LEFT", "RIGHT", "OUTER", "CROSS",
+    "ON", "AS", "AND", "OR", "NOT", "IN", "IS", "NULL", "GROUP", "ORDER", "BY", "ASC", "DESC",
+    "LIMIT", "TRUE", "FALSE", "INSERT", "INTO", "VALUES", "UPDATE", "SET", "DELETE",
+];
+
+const AGGREGATES: &[&str] = &["count", "sum", "min", "max", "avg"];
+
+pub struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+}
+
+impl Parser {
+    pub fn new(sql: &str) -> Result<Parser, ParserError> {
+        Ok(Parser {
+            tokens: tokenize(sql)?,
+            index: 0,
+        })
+    }
+
+    /// Parse exactly one statement, optionally followed by a semicolon.
+    pub fn parse_statement(&mut self) -> Result<Statement, ParserError> {
+        let statement = if self.peek_keyword("SELECT") {
+            Statement::Select(self.parse_select()?)
+        } else if self.peek_keyword("INSERT") {
+            Statement::Insert(self.parse_insert()?)
+        } else if self.peek_keyword("UPDATE") {
+            Statement::Update(self.parse_update()?)
+        } else if self.peek_keyword("DELETE") {
+            Statement::Delete(self.parse_delete()?)
+        } else {
+            return Err(self.expected("a statement"));
+        };
+        self.consume(&Token::Semicolon);
+        if self.peek() != &Token::Eof {
+            return Err(self.expected("end of statement"));
+        }
+        Ok(statement)
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].0
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.index].1
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.index].0.clone();
+        if token != Token::Eof {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn expected(&self, what: &str) -> ParserError {
+        let found = match self.peek() {
+            &Token::Eof => "end of input".to_string(),
+            &Token::Word(ref w) => format!("'{}'", w),
+            &Token::QuotedIdent(ref w) => format!("identifier '{}'", w),
+            &Token::Number(ref n) => format!("'{}'", n),
+            &Token::Str(ref s) => format!("string '{}'", s),
+            t => format!("{:?}", t),
+        };
+        ParserError::new(format!("expected {}, found {}", what, found), self.position())
+    }
+
+    fn consume(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token, what: &str) -> Result<(), ParserError> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(self.expected(what))
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            &Token::Word(ref w) => w.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParserError> {
+        if self.consume_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.expected(keyword))
+        }
+    }
+
+    fn unsupported(&self, what: &str) -> ParserError {
+        ParserError::new(format!("{} is not supported", what), self.position())
+    }
+
+    fn parse_identifier(&mut self) -> Result<String, ParserError> {
+        match self.peek().clone() {
+            Token::QuotedIdent(s) => {
+                self.next();
+                Ok(s)
+            },
+            Token::Word(ref w) if !RESERVED.iter().any(|r| w.eq_ignore_ascii_case(r)) => {
+                self.next();
+                Ok(w.clone())
+            },
+            _ => Err(self.expected("an identifier")),
+        }
+    }
+
+    fn parse_optional_alias(&mut self) -> Result<Option<String>, ParserError> {
+        if self.consume_keyword("AS") {
+            return self.parse_identifier().map(Some);
+        }
+        match self.peek() {
+            &Token::QuotedIdent(_) => self.parse_identifier().map(Some),
+            &Token::Word(ref w) if !RESERVED.iter().any(|r| w.eq_ignore_ascii_case(r)) =>
+                self.parse_identifier().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn parse_select(&mut self) -> Result<Select, ParserError> {
+        self.expect_keyword("SELECT")?;
+        let distinct = self.consume_keyword("DISTINCT");
+
+        let mut projection = vec![self.parse_select_item()?];
+        while self.consume(&Token::Comma) {
+            projection.push(self.parse_select_item()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let mut from = vec![self.parse_table_factor()?];
+        while self.consume(&Token::Comma) {
+            from.push(self.parse_table_factor()?);
+        }
+
+        let mut joins = vec![];
+        loop {
+            if self.peek_keyword("LEFT") || self.peek_keyword("RIGHT") || self.peek_keyword("OUTER") {
+                return Err(self.unsupported("an outer join"));
+            }
+            if self.consume(&Token::Comma) {
+                from.push(self.parse_table_factor()?);
+                continue;
+            }
+            if self.consume_keyword("CROSS") {
+                self.expect_keyword("JOIN")?;
+                from.push(self.parse_table_factor()?);
+                continue;
+            }
+            if self.consume_keyword("INNER") {
+                self.expect_keyword("JOIN")?;
+            } else if !self.consume_keyword("JOIN") {
+                break;
+            }
+            let table = self.parse_table_factor()?;
+            self.expect_keyword("ON")?;
+            let constraint = self.parse_expr()?;
+            joins.push(Join { table, constraint });
+        }
+
+        let selection = if self.consume_keyword("WHERE") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+
+        let mut group_by = vec![];
+        if self.consume_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            group_by.push(self.parse_column_ref()?);
+            while self.consume(&Token::Comma) {
+                group_by.push(self.parse_column_ref()?);
+            }
+        }
+        if self.peek_keyword("HAVING") {
+            return Err(self.unsupported("HAVING"));
+        }
+
+        let mut order_by = vec![];
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let column = self.parse_column_ref()?;
+                let asc = if self.consume_keyword("DESC") {
+                    false
+                } else {
+                    self.consume_keyword("ASC");
+                    true
+                };
+                order_by.push(OrderByExpr { column, asc });
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let limit = if self.consume_keyword("LIMIT") {
+            match self.next() {
+                Token::Number(ref n) => match n.parse::<u64>() {
+                    Ok(n) => Some(n),
+                    Err(_) => return Err(ParserError::new(format!("invalid LIMIT '{}'", n), self.tokens[self.index - 1].1)),
+                },
+                _ => {
+                    self.index -= 1;
+                    return Err(self.expected("a row count"));
+                },
+            }
+        } else {
+            None
+        };
+
+        Ok(Select {
+            distinct,
+            projection,
+            from,
+            joins,
+            selection,
+            group_by,
+            order_by,
+            limit,
+        })
+    }
+
+    fn parse_insert(&mut self) -> Result<Insert, ParserError> {
+        self.expect_keyword("INSERT")?;
+        self.expect_keyword("INTO")?;
+        let table = self.parse_identifier()?;
+
+        let columns = if self.consume(&Token::LParen) {
+            let mut columns = vec![self.parse_identifier()?];
+            while self.consume(&Token::Comma) {
+                columns.push(self.parse_identifier()?);
+            }
+            self.expect(&Token::RParen, "')'")?;
+            Some(columns)
+        } else {
+            None
+        };
+
+        self.expect_keyword("VALUES")?;
+        let mut rows = vec![];
+        loop {
+            self.expect(&Token::LParen, "'('")?;
+            let mut row = vec![self.parse_primary()?];
+            while self.consume(&Token::Comma) {
+                row.push(self.parse_primary()?);
+            }
+            self.expect(&Token::RParen, "')'")?;
+            rows.push(row);
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        Ok(Insert { table, columns, rows })
+    }
+
+    fn parse_update(&mut self) -> Result<Update, ParserError> {
+        self.expect_keyword("UPDATE")?;
+        let table = self.parse_table_factor()?;
+        self.expect_keyword("SET")?;
+        let mut assignments = vec![];
+        loop {
+            let column = self.parse_column_ref()?;
+            if column.table.as_ref().map_or(false, |t| !t.eq_ignore_ascii_case(table.binding_name())) {
+                return Err(ParserError::new(format!("unknown table in '{}'", column), self.position()));
+            }
+            self.expect(&Token::Eq, "'='")?;
+            assignments.push((column.column, self.parse_primary()?));
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        let selection = if self.consume_keyword("WHERE") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(Update { table, assignments, selection })
+    }
+
+    fn parse_delete(&mut self) -> Result<Delete, ParserError> {
+        self.expect_keyword("DELETE")?;
+        self.expect_keyword("FROM")?;
+        let table = self.parse_table_factor()?;
+        let selection = if self.consume_keyword("WHERE") {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(Delete { table, selection })
+    }
+
+    fn parse_select_item(&mut self) -> Result<SelectItem, ParserError> {
+        if self.consume(&Token::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        // `t.*` needs two tokens of lookahead.
+        if let (Some(&(Token::Dot, _)), Some(&(Token::Star, _))) = (self.tokens.get(self.index + 1), self.tokens.get(self.index + 2)) {
+            let table = self.parse_identifier()?;
+            self.index += 2;
+            return Ok(SelectItem::QualifiedWildcard(table));
+        }
+        let expr = self.parse_expr()?;
+        let alias = self.parse_optional_alias()?;
+        Ok(SelectItem::Expr { expr, alias })
+    }
+
+    fn parse_table_factor(&mut self) -> Result<TableFactor, ParserError> {
+        if self.peek() == &Token::LParen {
+            return Err(self.unsupported("a subquery"));
+        }
+        let name = self.parse_identifier()?;
+        let alias = self.parse_optional_alias()?;
+        Ok(TableFactor { name, alias })
+    }
+
+    fn parse_string(&mut self, what: &str) -> Result<String, ParserError> {
+        match self.peek().clone() {
+            Token::Str(s) => {
+                self.next();
+                Ok(s)
+            },
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    /// `JSON_TABLE '(' 'doc' ',' 'path' columns ')' [alias]`
+    pub fn parse_json_table(&mut self) -> Result<JsonTableExpr, ParserError> {
+        self.expect_keyword("JSON_TABLE")?;
+        self.expect(&Token::LParen, "'('")?;
+        let doc = self.parse_string("a JSON document string")?;
+        self.expect(&Token::Comma, "','")?;
+        let path = self.parse_string("a JSON path string")?;
+        let columns = self.parse_json_table_columns()?;
+        self.expect(&Token::RParen, "')'")?;
+        let alias = self.parse_optional_alias()?;
+        Ok(JsonTableExpr { doc, path, columns, alias })
+    }
+
+    /// `COLUMNS '(' column (',' column)* ')'`
+    fn parse_json_table_columns(&mut self) -> Result<Vec<JsonTableColumnDef>, ParserError> {
+        self.expect_keyword("COLUMNS")?;
+        self.expect(&Token::LParen, "'('")?;
+        let mut columns = vec![self.parse_json_table_column()?];
+        while self.consume(&Token::Comma) {
+            columns.push(self.parse_json_table_column()?);
+        }
+        self.expect(&Token::RParen, "')'")?;
+        Ok(columns)
+    }
+
+    fn parse_json_table_column(&mut self) -> Result<JsonTableColumnDef, ParserError> {
+        if self.consume_keyword("NESTED") {
+            self.consume_keyword("PATH");
+            let path = self.parse_string("a JSON path string")?;
+            let columns = self.parse_json_table_columns()?;
+            return Ok(JsonTableColumnDef::Nested { path, columns });
+        }
+        let name = self.parse_identifier()?;
+        if self.consume_keyword("FOR") {
+            self.expect_keyword("ORDINALITY")?;
+            return Ok(JsonTableColumnDef::Ordinality { name });
+        }
+        let data_type = self.parse_data_type()?;
+        if self.consume_keyword("EXISTS") {
+            self.expect_keyword("PATH")?;
+            let path = self.parse_string("a JSON path string")?;
+            return Ok(JsonTableColumnDef::Exists { name, data_type, path });
+        }
+        self.expect_keyword("PATH")?;
+        let path = self.parse_string("a JSON path string")?;
+        let mut on_empty = JsonTableOnResponse::Null;
+        let mut on_error = JsonTableOnResponse::Null;
+        let mut seen_error = false;
+        while self.peek_keyword("NULL") || self.peek_keyword("ERROR") || self.peek_keyword("DEFAULT") {
+            let response = self.parse_json_table_on_response()?;
+            self.expect_keyword("ON")?;
+            // ON EMPTY has to come before ON ERROR.
+            if !seen_error && self.consume_keyword("EMPTY") {
+                on_empty = response;
+            } else if !seen_error && self.consume_keyword("ERROR") {
+                on_error = response;
+                seen_error = true;
+            } else {
+                return Err(self.expected(if seen_error { "end of column" } else { "EMPTY or ERROR" }));
+            }
+        }
+        Ok(JsonTableColumnDef::Path { name, data_type, path, on_empty, on_error })
+    }
+
+    fn parse_json_table_on_response(&mut self) -> Result<JsonTableOnResponse, ParserError> {
+        if self.consume_keyword("NULL") {
+            Ok(JsonTableOnResponse::Null)
+        } else if self.consume_keyword("ERROR") {
+            Ok(JsonTableOnResponse::Error)
+        } else {
+            self.expect_keyword("DEFAULT")?;
+            self.parse_string("a JSON string").map(JsonTableOnResponse::Default)
+        }
+    }
+
+    /// A type name with an optional length or precision, e.g. `VARCHAR(100)` or
+    /// `DECIMAL(10, 2)`. Only the name matters to us, so that's all we keep, uppercased.
+    fn parse_data_type(&mut self) -> Result<String, ParserError> {
+        let name = match self.peek().clone() {
+            Token::Word(w) => w.to_uppercase(),
+            _ => return Err(self.expected("a data type")),
+        };
+        self.next();
+        if self.consume(&Token::LParen) {
+            loop {
+                match self.peek() {
+                    &Token::Number(_) => { self.next(); },
+                    _ => return Err(self.expected("a length")),
+                }
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(&Token::RParen, "')'")?;
+        }
+        Ok(name)
+    }
+
+    fn parse_column_ref(&mut self) -> Result<ColumnRef, ParserError> {
+        let first = self.parse_identifier()?;
+        if self.consume(&Token::Dot) {
+            let column = self.parse_identifier()?;
+            Ok(ColumnRef { table: Some(first), column })
+        } else {
+            Ok(ColumnRef { table: None, column: first })
+        }
+    }
+
+    /// `expr := and (OR and)*`
+    pub fn parse_expr(&mut self) -> Result<Expr, ParserError> {
+        let mut left = self.parse_and()?;
+        while self.consume_keyword("OR") {
+            let right = self.parse_and()?;
+            left = Expr::Binary { left: Box::new(left), op: BinaryOperator::Or, right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParserError> {
+        let mut left = self.parse_comparison()?;
+        while self.consume_keyword("AND") {
+            let right = self.parse_comparison()?;
+            left = Expr::Binary { left: Box::new(left), op: BinaryOperator::And, right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ParserError> {
+        if self.peek_keyword("NOT") {
+            return Err(self.unsupported("NOT"));
+        }
+        let left = self.parse_primary()?;
+
+        if self.consume_keyword("IS") {
+            let negated = self.consume_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull { expr: Box::new(left), negated });
+        }
+
+        let negated = self.consume_keyword("NOT");
+        if negated || self.peek_keyword("IN") {
+            self.expect_keyword("IN")?;
+            self.expect(&Token::LParen, "'('")?;
+            let mut list = vec![self.parse_primary()?];
+            while self.consume(&Token::Comma) {
+                list.push(self.parse_primary()?);
+            }
+            self.expect(&Token::RParen, "')'")?;
+            return Ok(Expr::InList { expr: Box::new(left), list, negated });
+        }
+
+        let op = match self.peek() {
+            &Token::Eq => BinaryOperator::Eq,
+            &Token::NotEq => BinaryOperator::NotEq,
+            &Token::Lt => BinaryOperator::Lt,
+            &Token::LtEq => BinaryOperator::LtEq,
+            &Token::Gt => BinaryOperator::Gt,
+            &Token::GtEq => BinaryOperator::GtEq,
+            _ => return Ok(left),
+        };
+        self.next();
+        let right = self.parse_primary()?;
+        Ok(Expr::Binary { left: Box::new(left), op, right: Box::new(right) })
+    }
+
+    fn parse_number(&self, text: &str, negative: bool, position: usize) -> Result<Literal, ParserError> {
+        let text = if negative { format!("-{}", text) } else { text.to_string() };
+        if let Ok(i) = text.parse::<i64>() {
+            return Ok(Literal::Integer(i));
+        }
+        text.parse::<f64>()
+            .map(Literal::Float)
+            .map_err(|_| ParserError::new(format!("invalid number '{}'", text), position))
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParserError> {
+        let position = self.position();
+        match self.peek().clone() {
+            Token::LParen => {
+                self.next();
+                let expr = self.parse_expr()?;
+                self.expect(&Token::RParen, "')'")?;
+                Ok(expr)
+            },
+            Token::Number(n) => {
+                self.next();
+                self.parse_number(&n, false, position).map(Expr::Literal)
+            },
+            Token::Minus => {
+                self.next();
+                match self.next() {
+                    Token::Number(n) => self.parse_number(&n, true, position).map(Expr::Literal),
+                    _ => Err(ParserError::new("expected a number after '-'", position)),
+                }
+            },
+            Token::Str(s) => {
+                self.next();
+                Ok(Expr::Literal(Literal::String(s)))
+            },
+            Token::Word(ref w) if w.eq_ignore_ascii_case("NULL") => {
+                self.next();
+                Ok(Expr::Literal(Literal::Null))
+            },
+            Token::Word(ref w) if w.eq_ignore_ascii_case("TRUE") => {
+                self.next();
+                Ok(Expr::Literal(Literal::Boolean(true)))
+            },
+            Token::Word(ref w) if w.eq_ignore_ascii_case("FALSE") => {
+                self.next();
+                Ok(Expr::Literal(Literal::Boolean(false)))
+            },
+            Token::Word(ref w) if self.tokens.get(self.index + 1).map(|t| &t.0) == Some(&Token::LParen) => {
+                let name = w.to_lowercase();
+                if !AGGREGATES.contains(&name.as_str()) {
+                    return Err(ParserError::new(format!("unknown function '{}'", w), position));
+                }
+                self.index += 2;
+                let args = if self.consume(&Token::Star) {
+                    if name != "count" {
+                        return Err(ParserError::new(format!("{}(*) is not valid", name), position));
+                    }
+                    vec![Expr::Wildcard]
+                } else {
+                    if self.peek_keyword("DISTINCT") {
+                        return Err(self.unsupported("DISTINCT in an aggregate"));
+                    }
+                    vec![Expr::Column(self.parse_column_ref()?)]
+                };
+                self.expect(&Token::RParen, "')'")?;
+                Ok(Expr::Function { name, args })
+            },
+            _ => self.parse_column_ref().map(Expr::Column),
+        }
+    }
+}
+
+/// Parse a single SQL statement.
+pub fn parse_sql(sql: &str) -> Result<Statement, ParserError> {
+    Parser::new(sql)?.parse_statement()
+}
+
+/// Parse a `JSON_TABLE` table function on its own.
+pub fn parse_json_table(sql: &str) -> Result<JsonTableExpr, ParserError> {
+    let mut parser = Parser::new(sql)?;
+    let expr = parser.parse_json_table()?;
+    parser.consume(&Token::Semicolon);
+    if parser.peek() != &Token::Eof {
+        return Err(parser.expected("end of JSON_TABLE"));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn select(sql: &str) -> Select {
+        match parse_sql(sql).expect("parsed") {
+            Statement::Select(select) => select,
+            other => panic!("expected a select, got {:?}", other),
+        }
+    }
+
+    fn column(table: Option<&str>, column: &str) -> ColumnRef {
+        ColumnRef {
+            table: table.map(|t| t.to_string()),
+            column: column.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_simple_select() {
+        let s = select("select name, p.age AS years FROM person p WHERE age >= 18 limit 10;");
+        assert!(!s.distinct);
+        assert_eq!(s.projection, vec![
+            SelectItem::Expr { expr: Expr::Column(column(None, "name")), alias: None },
+            SelectItem::Expr { expr: Expr::Column(column(Some("p"), "age")), alias: Some("years".to_string()) },
+        ]);
+        assert_eq!(s.from, vec![TableFactor { name: "person".to_string(), alias: Some("p".to_string()) }]);
+        assert_eq!(s.selection, Some(Expr::Binary {
+            left: Box::new(Expr::Column(column(None, "age"))),
+            op: BinaryOperator::GtEq,
+            right: Box::new(Expr::Literal(Literal::Integer(18))),
+        }));
+        assert_eq!(s.limit, Some(10));
+    }
+
+    #[test]
+    fn test_parse_joins_and_wildcards() {
+        let s = select("SELECT p.*, `order`.total FROM person p JOIN `order` ON `order`.buyer = p.id, city");
+        assert_eq!(s.projection[0], SelectItem::QualifiedWildcard("p".to_string()));
+        assert_eq!(s.from.len(), 2);
+        assert_eq!(s.from[1].binding_name(), "city");
+        assert_eq!(s.joins.len(), 1);
+        assert_eq!(s.joins[0].table.binding_name(), "order");
+        assert_eq!(s.joins[0].constraint.to_string(), "order.buyer = p.id");
+    }
+
+    #[test]
+    fn test_parse_predicates() {
+        let s = select("SELECT * FROM t WHERE a IN (1, -2.5, 'x''y') AND b IS NOT NULL AND c NOT IN (TRUE) AND d <> NULL");
+        assert_eq!(s.selection.unwrap().to_string(),
+                   "a IN (1, -2.5, 'x''y') AND b IS NOT NULL AND c NOT IN (TRUE) AND d != NULL");
+    }
+
+    #[test]
+    fn test_parse_aggregates() {
+        let s = select("SELECT city, COUNT(*), max(age) FROM person GROUP BY city ORDER BY city DESC, age");
+        assert_eq!(s.projection[1], SelectItem::Expr { expr: Expr::Function { name: "count".to_string(), args: vec![Expr::Wildcard] }, alias: None });
+        assert_eq!(s.group_by, vec![column(None, "city")]);
+        assert_eq!(s.order_by, vec![
+            OrderByExpr { column: column(None, "city"), asc: false },
+            OrderByExpr { column: column(None, "age"), asc: true },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_sql("SELECT FROM t").unwrap_err();
+        assert_eq!(err.position, 7);
+
+        assert!(parse_sql("SELECT a FROM t LEFT JOIN u ON t.a = u.a").unwrap_err().message.contains("not supported"));
+        assert!(parse_sql("SELECT a FROM t WHERE a = 'open").unwrap_err().message.contains("unterminated"));
+        assert!(parse_sql("SELECT frob(a) FROM t").unwrap_err().message.contains("unknown function"));
+        assert!(parse_sql("SELECT a FROM t extra junk").is_err());
+        assert!(parse_sql("DROP TABLE t").is_err());
+    }
+
+    #[test]
+    fn test_parse_dml() {
+        assert_eq!(parse_sql("INSERT INTO person (name, age) VALUES ('a', 1), ('b', NULL)").unwrap(),
+                   Statement::Insert(Insert {
+                       table: "person".to_string(),
+                       columns: Some(vec!["name".to_string(), "age".to_string()]),
+                       rows: vec![
+                           vec![Expr::Literal(Literal::String("a".to_string())), Expr::Literal(Literal::Integer(1))],
+                           vec![Expr::Literal(Literal::String("b".to_string())), Expr::Literal(Literal::Null)],
+                       ],
+                   }));
+
+        match parse_sql("UPDATE person p SET p.age = 2, name = 'c' WHERE id = 65536").unwrap() {
+            Statement::Update(update) => {
+                assert_eq!(update.table.binding_name(), "p");
+                assert_eq!(update.assignments, vec![
+                    ("age".to_string(), Expr::Literal(Literal::Integer(2))),
+                    ("name".to_string(), Expr::Literal(Literal::String("c".to_string()))),
+                ]);
+                assert_eq!(update.selection.unwrap().to_string(), "id = 65536");
+            },
+            other => panic!("expected an update, got {:?}", other),
+        }
+
+        assert_eq!(parse_sql("DELETE FROM person").unwrap(),
+                   Statement::Delete(Delete {
+                       table: TableFactor { name: "person".to_string(), alias: None },
+                       selection: None,
+                   }));
+
+        assert!(parse_sql("UPDATE person SET other.age = 2").is_err());
+        assert!(parse_sql("INSERT INTO person VALUES").is_err());
+    }
+
+    #[test]
+    fn test_parse_json_table() {
+        let expr = parse_json_table("JSON_TABLE('[{\"a\": 1}]', '$[*]' COLUMNS (
+                                         id FOR ORDINALITY,
+                                         a INT PATH '$.a' DEFAULT '0' ON EMPTY ERROR ON ERROR,
+                                         b VARCHAR(10) PATH '$.b' NULL ON ERROR,
+                                         has_c INT EXISTS PATH '$.c',
+                                         NESTED PATH '$.d[*]' COLUMNS (d DECIMAL(10, 2) PATH '$'))) AS jt").unwrap();
+        assert_eq!(expr, JsonTableExpr {
+            doc: "[{\"a\": 1}]".to_string(),
+            path: "$[*]".to_string(),
+            columns: vec![
+                JsonTableColumnDef::Ordinality { name: "id".to_string() },
+                JsonTableColumnDef::Path {
+                    name: "a".to_string(),
+                    data_type: "INT".to_string(),
+                    path: "$.a".to_string(),
+                    on_empty: JsonTableOnResponse::Default("0".to_string()),
+                    on_error: JsonTableOnResponse::Error,
+                },
+                JsonTableColumnDef::Path {
+                    name: "b".to_string(),
+                    data_type: "VARCHAR".to_string(),
+                    path: "$.b".to_string(),
+                    on_empty: JsonTableOnResponse::Null,
+                    on_error: JsonTableOnResponse::Null,
+                },
+                JsonTableColumnDef::Exists {
+                    name: "has_c".to_string(),
+                    data_type: "INT".to_string(),
+                    path: "$.c".to_string(),
+                },
+                JsonTableColumnDef::Nested {
+                    path: "$.d[*]".to_string(),
+                    columns: vec![JsonTableColumnDef::Path {
+                        name: "d".to_string(),
+                        data_type: "DECIMAL".to_string(),
+                        path: "$".to_string(),
+                        on_empty: JsonTableOnResponse::Null,
+                        on_error: JsonTableOnResponse::Null,
+                    }],
+                },
+            ],
+            alias: Some("jt".to_string()),
+        });
+
+        // ON EMPTY must come first.
+        assert!(parse_json_table("JSON_TABLE('[]', '$' COLUMNS (a INT PATH '$' NULL ON ERROR NULL ON EMPTY))").is_err());
+        assert!(parse_json_table("JSON_TABLE('[]', '$' COLUMNS ())").is_err());
+        assert!(parse_json_table("JSON_TABLE('[]', '$' COLUMNS (a INT))").is_err());
+        assert!(parse_json_table("JSON_TABLE('[]' COLUMNS (a INT PATH '$'))").is_err());
+    }
+}
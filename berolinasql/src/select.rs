@@ -0,0 +1,1165 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+//! Compiling SQL `SELECT` to Datalog over the datom store.
+//!
+//! Each SQL table is a vocabulary: its columns are the vocabulary's attributes, named by the
+//! attribute's name (or `namespace_name` when two attributes share a name), plus an implicit `id`
+//! column holding the causet itself. A row is a causet with at least one of the table's
+//! attributes.
+//!
+//! Every table in `FROM` gets a variable for its causet, and every column we touch adds a
+//! `[?t :attr ?v]` pattern. Equi-joins unify variables, so `ON o.buyer = p.id` becomes
+//! `[?o :purchase/buyer ?p]`. `WHERE` comparisons become predicates, `IN` becomes `ground`,
+//! and `IS NULL` becomes `not`. Unprojected table variables go in `:with`, so duplicate rows are
+//! kept the way SQL keeps them unless `DISTINCT` is given.
+//!
+//! Because each column is a pattern, a row that lacks an attribute the query mentions is
+//! omitted rather than returned with a NULL: there are no outer joins here.
+//!
+//! The store compares strings by code point. A string column can have a `Collation` instead,
+//! together with an indexed string attribute that holds each causet_locale's sort key (see
+//! `sort_key_text`) and that `dml` writes alongside it. `WHERE` comparisons of such a column
+//! with a literal, and `ORDER BY` on it, go to the sort key, so the store seeks and orders by
+//! the collation. Joins and comparisons between two columns still go by code point.
+
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use std::fmt::Write;
+
+use causetq::{
+    causetq_TV,
+    causetq_VT,
+};
+use causetq::attribute::Unique;
+use einstein_db::{
+    Attribute,
+    HasTopograph,
+    Topograph,
+};
+use einstein_db::conn::Conn;
+use einstein_ml::{
+    Keyword,
+    PlainShelling,
+};
+use einstein_ml::query::{
+    Aggregate,
+    Binding as FnBinding,
+    Direction,
+    Element,
+    FindSpec,
+    FnArg,
+    Limit,
+    NonIntegerConstant,
+    NotJoin,
+    OrJoin,
+    OrWhereClause,
+    ParsedQuery,
+    Partition,
+    Pattern,
+    PatternNonValuePlace,
+    PatternValuePlace,
+    Predicate,
+    QueryFunction,
+    SrcVar,
+    UnifyVars,
+    Variable,
+    WhereClause,
+    WhereFn,
+};
+use einsteindb_transaction::query::QueryResults;
+use fdb_traits::vocabulary::Vocabularies;
+
+use crate::collation::Collation;
+use crate::error::{
+    Error,
+    Result,
+    ERR_UNCAUSET_LOCALE_NUCLEON,
+};
+use crate::expr::EvalContext;
+use einstein_json::Json;
+use einstein_json::json::json_schema::JsonSchema;
+use crate::parser::{
+    parse_sql,
+    BinaryOperator,
+    ColumnRef,
+    Expr,
+    Literal,
+    Select,
+    SelectItem,
+    Statement,
+};
+use crate::table::{
+    encode_collated_index_causet_locale,
+    Column,
+    Row,
+    Table,
+};
+
+/// The column every table has: the causet a row describes.
+pub const ID_COLUMN: &str = "id";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SqlColumn {
+    pub name: String,
+    pub attribute: Keyword,
+    pub causet_locale_type: causetq_VT,
+    pub multival: bool,
+    pub unique: Option<Unique>,
+    /// How `WHERE` and `ORDER BY` compare the column's strings, and the `:einsteindb/index`ed
+    /// string attribute holding their sort keys; `None` is code point order.
+    pub collation: Option<(Collation, Keyword)>,
+}
+
+impl SqlColumn {
+    pub fn new<S: Into<String>>(name: S, attribute: Keyword, causet_locale_type: causetq_VT) -> SqlColumn {
+        SqlColumn {
+            name: name.into(),
+            attribute,
+            causet_locale_type,
+            multival: false,
+            unique: None,
+            collation: None,
+        }
+    }
+
+    /// The collation and sort key attribute of a string column that has them.
+    pub fn collated(&self) -> Option<(Collation, &Keyword)> {
+        match self.collation {
+            Some((collation, ref sort_key)) if self.causet_locale_type == causetq_VT::String => Some((collation, sort_key)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SqlTable {
+    pub name: String,
+    pub columns: Vec<SqlColumn>,
+}
+
+impl SqlTable {
+    pub fn new<S: Into<String>>(name: S) -> SqlTable {
+        SqlTable {
+            name: name.into(),
+            columns: vec![],
+        }
+    }
+
+    pub fn add_column(&mut self, column: SqlColumn) {
+        self.columns.push(column);
+    }
+
+    /// Column names are case-insensitive, like MySQL's.
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn column(&self, name: &str) -> Option<&SqlColumn> {
+        self.column_index(name).map(|i| &self.columns[i])
+    }
+
+    /// The first `:einsteindb.unique/idcauset` attribute is the table's primary key: it names at
+    /// most one causet, so `INSERT` treats a second row with the same key as a duplicate.
+    pub fn primary_key(&self) -> Option<usize> {
+        self.columns.iter().position(|c| c.unique == Some(Unique::Idcauset))
+    }
+
+    pub fn has_primary_key(&self) -> bool {
+        self.primary_key().is_some()
+    }
+}
+
+/// Turn a keyword component into something usable as an unquoted SQL identifier.
+fn sql_name(s: &str) -> String {
+    s.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect()
+}
+
+/// The tables SQL can see.
+#[derive(Clone, Debug, Default)]
+pub struct Catalog {
+    tables: BTreeMap<String, SqlTable>,
+    /// Checked against every causet_locale `INSERT` or `UPDATE` gives these attributes, before
+    /// the transactor checks the schemas stored on them.
+    json_schemas: BTreeMap<Keyword, JsonSchema>,
+}
+
+impl Catalog {
+    pub fn new() -> Catalog {
+        Catalog::default()
+    }
+
+    /// One table per vocabulary, named after the vocabulary's keyword.
+    pub fn from_vocabularies(vocabularies: &Vocabularies, topograph: &Topograph) -> Catalog {
+        let mut catalog = Catalog::new();
+        for (name, vocabulary) in vocabularies.iter() {
+            let attributes: Vec<(Keyword, &Attribute)> =
+                vocabulary.attributes()
+                          .iter()
+                          .filter_map(|&(causetid, ref attribute)| {
+                              topograph.get_solitonid(causetid).map(|k| (k.clone(), attribute))
+                          })
+                          .collect();
+
+            let mut table = SqlTable::new(sql_name(name.name()));
+            for &(ref solitonid, attribute) in attributes.iter() {
+                let short = sql_name(solitonid.name());
+                let clashes = short.eq_ignore_ascii_case(ID_COLUMN) ||
+                              attributes.iter().filter(|&&(ref k, _)| sql_name(k.name()).eq_ignore_ascii_case(&short)).count() > 1;
+                let column_name = if clashes {
+                    format!("{}_{}", sql_name(solitonid.namespace().unwrap_or("")), short)
+                } else {
+                    short
+                };
+                let mut column = SqlColumn::new(column_name, solitonid.clone(), attribute.causet_locale_type);
+                column.multival = attribute.multival;
+                column.unique = attribute.unique.clone();
+                table.add_column(column);
+            }
+            catalog.add_table(table);
+        }
+        catalog
+    }
+
+    pub fn add_table(&mut self, table: SqlTable) {
+        self.tables.insert(table.name.to_lowercase(), table);
+    }
+
+    /// Table names are case-insensitive.
+    pub fn table(&self, name: &str) -> Option<&SqlTable> {
+        self.tables.get(&name.to_lowercase())
+    }
+
+    /// Require the JSON text written to `attribute` to satisfy `schema`.
+    pub fn attach_json_schema(&mut self, attribute: Keyword, schema: JsonSchema) {
+        self.json_schemas.insert(attribute, schema);
+    }
+
+    pub fn json_schema(&self, attribute: &Keyword) -> Option<&JsonSchema> {
+        self.json_schemas.get(attribute)
+    }
+
+    /// Attach the schemas stored on attributes with `:einsteindb/jsonSchema`, so that a violation
+    /// is reported against its column rather than failing the whole transaction.
+    pub fn attach_stored_json_schemas(&mut self, conn: &Conn, sqlite: &rusqlite::Connection) -> Result<()> {
+        let datalog = "[:find ?a ?schema :where [?attribute :einsteindb/jsonSchema ?schema] [?attribute :einsteindb/solitonid ?a]]";
+        let output = conn.q_once(conn, datalog, sqlite, None).map_err(store_error)?;
+        for row in relation(output.results, 2)? {
+            if let (&causetq_TV::Keyword(ref attribute), &causetq_TV::String(ref text)) = (&row[0], &row[1]) {
+                let schema = text.parse::<Json>().map_err(Error::invalid_json_text)?;
+                self.attach_json_schema(attribute.as_ref().clone(), JsonSchema::new(schema.as_ref())?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A place a column reference can resolve to: the causet of the `n`th table in `FROM`, or one of
+/// its columns. A collated column's sort keys have a slot of their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Slot {
+    Causet(usize),
+    Column(usize, usize),
+    SortKey(usize, usize),
+}
+
+struct TableBinding<'c> {
+    name: String,
+    table: &'c SqlTable,
+    var: Variable,
+}
+
+fn variable(name: &str) -> Variable {
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect();
+    Variable::from_valid_name(&format!("?{}", name))
+}
+
+fn pattern(causet: &Variable, attribute: &Keyword, causet_locale: PatternValuePlace) -> WhereClause {
+    WhereClause::Pattern(Pattern {
+        source: None,
+        causet: PatternNonValuePlace::Variable(causet.clone()),
+        attribute: PatternNonValuePlace::Solitonid(attribute.clone().into()),
+        causet_locale,
+        tx: PatternNonValuePlace::Placeholder,
+    })
+}
+
+fn parse_keyword(s: &str) -> Option<Keyword> {
+    let s = s.strip_prefix(':')?;
+    match s.find('/') {
+        Some(i) if i > 0 && i + 1 < s.len() => Some(Keyword::namespaced(&s[..i], &s[i + 1..])),
+        None if !s.is_empty() => Some(Keyword::plain(s)),
+        _ => None,
+    }
+}
+
+/// The Datalog constant a SQL literal stands for when compared to a column of type `t`.
+pub(crate) fn constant(t: causetq_VT, literal: &Literal) -> Result<FnArg> {
+    let wrong = || Error::truncated_wrong_val(format!("{:?}", t), literal);
+    Ok(match (t, literal) {
+        (_, &Literal::Null) =>
+            return Err(Error::not_supported_yet("comparison with NULL; use IS NULL")),
+        (causetq_VT::Ref, &Literal::Integer(i)) if i >= 0 => FnArg::CausetidOrInteger(i),
+        (causetq_VT::Ref, &Literal::String(ref s)) =>
+            FnArg::SolitonidOrKeyword(parse_keyword(s).ok_or_else(wrong)?),
+        (causetq_VT::Long, &Literal::Integer(i)) => FnArg::CausetidOrInteger(i),
+        (causetq_VT::Double, &Literal::Integer(i)) => FnArg::Constant(NonIntegerConstant::Float((i as f64).into())),
+        (causetq_VT::Double, &Literal::Float(x)) => FnArg::Constant(NonIntegerConstant::Float(x.into())),
+        (causetq_VT::Boolean, &Literal::Boolean(b)) => FnArg::Constant(NonIntegerConstant::Boolean(b)),
+        (causetq_VT::Boolean, &Literal::Integer(i)) if i == 0 || i == 1 => FnArg::Constant(NonIntegerConstant::Boolean(i == 1)),
+        (causetq_VT::String, &Literal::String(ref s)) => FnArg::Constant(NonIntegerConstant::from(s.as_str())),
+        (causetq_VT::Keyword, &Literal::String(ref s)) =>
+            FnArg::SolitonidOrKeyword(parse_keyword(s).ok_or_else(wrong)?),
+        (causetq_VT::Instant, &Literal::String(ref s)) => {
+            let instant = chrono::DateTime::parse_from_rfc3339(s).map_err(|_| wrong())?;
+            FnArg::Constant(NonIntegerConstant::Instant(instant.with_timezone(&chrono::Utc)))
+        },
+        (causetq_VT::Uuid, &Literal::String(ref s)) =>
+            FnArg::Constant(NonIntegerConstant::Uuid(uuid::Uuid::parse_str(s).map_err(|_| wrong())?)),
+        // JSON is written as its text, which the transactor parses.
+        (causetq_VT::Json, &Literal::String(ref s)) => {
+            s.parse::<Json>().map_err(Error::invalid_json_text)?;
+            FnArg::Constant(NonIntegerConstant::from(s.as_str()))
+        },
+        _ => return Err(wrong()),
+    })
+}
+
+fn causet_locale_place(arg: &FnArg) -> PatternValuePlace {
+    match arg {
+        &FnArg::CausetidOrInteger(i) => PatternValuePlace::CausetidOrInteger(i),
+        &FnArg::SolitonidOrKeyword(ref k) => PatternValuePlace::from(k.clone()),
+        &FnArg::Constant(ref c) => PatternValuePlace::Constant(c.clone()),
+        _ => unreachable!("constants only"),
+    }
+}
+
+/// Flatten a conjunction. We can't express `OR` across tables yet.
+fn conjuncts<'e>(expr: &'e Expr, out: &mut Vec<&'e Expr>) -> Result<()> {
+    match expr {
+        &Expr::Binary { ref left, op: BinaryOperator::And, ref right } => {
+            conjuncts(left, out)?;
+            conjuncts(right, out)
+        },
+        &Expr::Binary { op: BinaryOperator::Or, .. } => Err(Error::not_supported_yet("OR")),
+        e => {
+            out.push(e);
+            Ok(())
+        },
+    }
+}
+
+/// A `SELECT`, compiled.
+#[derive(Clone, Debug)]
+pub struct CompiledSelect {
+    /// The name the result table gets.
+    pub name: String,
+    /// Result column headers and types, in projection order.
+    pub columns: Vec<(String, causetq_VT)>,
+    pub query: ParsedQuery,
+}
+
+impl CompiledSelect {
+    /// The query as Datalog text, ready for `Conn::q_once`.
+    pub fn datalog(&self) -> String {
+        to_datalog(&self.query)
+    }
+
+    /// Shape query results as rows of this select.
+    pub fn into_table(self, results: QueryResults) -> Result<Table> {
+        let rows = relation(results, self.columns.len())?
+            .into_iter()
+            .map(|values| Row::new(values.into_iter().map(Some).collect()))
+            .collect();
+        let columns = self.columns.into_iter().map(|(name, t)| Column::new(name, t)).collect();
+        Ok(Table::new(self.name, columns, rows))
+    }
+}
+
+/// The rows of a relation `width` causet_locales wide.
+pub(crate) fn relation(results: QueryResults, width: usize) -> Result<Vec<Vec<causetq_TV>>> {
+    let rel = match results {
+        QueryResults::Rel(rel) => rel,
+        _ => return Err(Error::Eval("expected a relation".into(), ERR_UNCAUSET_LOCALE_NUCLEON)),
+    };
+    if rel.width != width {
+        return Err(Error::Eval(format!("expected {} columns, got {}", width, rel.width), ERR_UNCAUSET_LOCALE_NUCLEON));
+    }
+
+    let mut rows = Vec::with_capacity(rel.values.len() / width.max(1));
+    for chunk in rel.values.chunks(width) {
+        let values = chunk.iter()
+                          .cloned()
+                          .map(|binding| binding.into_scalar()
+                                                .ok_or_else(|| Error::Eval("expected a scalar".into(), ERR_UNCAUSET_LOCALE_NUCLEON)))
+                          .collect::<Result<Vec<causetq_TV>>>()?;
+        rows.push(values);
+    }
+    Ok(rows)
+}
+
+/// The text a collated column's sort key attribute holds for `causet_locale`: the index
+/// soliton_id `encode_collated_index_causet_locale` gives it, in hex, so that the store's code
+/// point order on the text is the byte order of the soliton_id.
+pub(crate) fn sort_key_text(causet_locale: &str, collation: Collation) -> Result<String> {
+    let encoded = encode_collated_index_causet_locale(&mut EvalContext::default(), causet_locale.as_bytes(), collation)?;
+    let mut text = String::with_capacity(encoded.len() * 2);
+    for b in encoded {
+        write!(text, "{:02X}", b).unwrap();
+    }
+    Ok(text)
+}
+
+/// Errors from the store keep their message.
+pub(crate) fn store_error(e: impl std::fmt::Display) -> Error {
+    Error::Eval(e.to_string(), ERR_UNCAUSET_LOCALE_NUCLEON)
+}
+
+struct Compiler<'c> {
+    tables: Vec<TableBinding<'c>>,
+    /// Union-find over slots that equi-joins say are equal.
+    parent: BTreeMap<Slot, Slot>,
+    /// Column slots that have a `[?t :attr ?v]` pattern.
+    materialized: BTreeSet<Slot>,
+    clauses: Vec<WhereClause>,
+    /// `col = constant` conditions. They are patterns with a constant causet_locale; if the column's
+    /// variable is bound too, it is also grounded to the constant so that it can't range over
+    /// the attribute's other causet_locales.
+    grounds: Vec<(Slot, FnArg)>,
+}
+
+impl<'c> Compiler<'c> {
+    fn new(catalog: &'c Catalog, select: &Select) -> Result<Compiler<'c>> {
+        let mut tables: Vec<TableBinding<'c>> = vec![];
+        let factors = select.from.iter().chain(select.joins.iter().map(|j| &j.table));
+        for factor in factors {
+            let table = catalog.table(&factor.name).ok_or_else(|| Error::no_such_table(&factor.name))?;
+            let name = factor.binding_name().to_string();
+            if tables.iter().any(|t| t.name.eq_ignore_ascii_case(&name)) {
+                return Err(Error::nonunique_table(name));
+            }
+            let var = variable(&name);
+            tables.push(TableBinding { name, table, var });
+        }
+        Ok(Compiler {
+            tables,
+            parent: BTreeMap::new(),
+            materialized: BTreeSet::new(),
+            clauses: vec![],
+            grounds: vec![],
+        })
+    }
+
+    fn binding(&self, name: &str) -> Option<usize> {
+        self.tables.iter().position(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    fn resolve(&self, column: &ColumnRef, clause: &str) -> Result<Slot> {
+        let in_table = |b: usize| -> Option<Slot> {
+            if column.column.eq_ignore_ascii_case(ID_COLUMN) {
+                Some(Slot::Causet(b))
+            } else {
+                self.tables[b].table.column_index(&column.column).map(|i| Slot::Column(b, i))
+            }
+        };
+
+        if let Some(ref table) = column.table {
+            return self.binding(table)
+                       .and_then(in_table)
+                       .ok_or_else(|| Error::bad_field(column, clause));
+        }
+        let mut found = (0..self.tables.len()).filter_map(in_table);
+        match (found.next(), found.next()) {
+            (Some(slot), None) => Ok(slot),
+            (Some(_), Some(_)) => Err(Error::ambiguous_field(column, clause)),
+            (None, _) => Err(Error::bad_field(column, clause)),
+        }
+    }
+
+    fn find(&self, mut slot: Slot) -> Slot {
+        while let Some(&parent) = self.parent.get(&slot) {
+            slot = parent;
+        }
+        slot
+    }
+
+    /// Causets are preferred as representatives so that joins on `id` put the other table's
+    /// variable straight into the causet_locale place.
+    fn union(&mut self, a: Slot, b: Slot) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return;
+        }
+        match rb {
+            Slot::Causet(_) => self.parent.insert(ra, rb),
+            Slot::Column(..) | Slot::SortKey(..) => self.parent.insert(rb, ra),
+        };
+    }
+
+    fn slot_type(&self, slot: Slot) -> causetq_VT {
+        match slot {
+            Slot::Causet(_) => causetq_VT::Ref,
+            Slot::Column(b, i) => self.tables[b].table.columns[i].causet_locale_type,
+            Slot::SortKey(..) => causetq_VT::String,
+        }
+    }
+
+    fn column(&self, slot: Slot) -> Option<&'c SqlColumn> {
+        match slot {
+            Slot::Causet(_) | Slot::SortKey(..) => None,
+            Slot::Column(b, i) => Some(&self.tables[b].table.columns[i]),
+        }
+    }
+
+    /// The attribute holding a slot's causet_locales.
+    fn attribute(&self, slot: Slot) -> Option<&'c Keyword> {
+        match slot {
+            Slot::Causet(_) => None,
+            Slot::Column(b, i) => Some(&self.tables[b].table.columns[i].attribute),
+            Slot::SortKey(b, i) => self.tables[b].table.columns[i].collated().map(|(_, sort_key)| sort_key),
+        }
+    }
+
+    /// The collation a slot's strings are compared by, if it isn't code point order.
+    fn collation(&self, slot: Slot) -> Option<Collation> {
+        self.column(slot).and_then(|c| c.collated()).map(|(collation, _)| collation)
+    }
+
+    /// The slot to compare with the constant `arg` in place of `slot`: a collated column is
+    /// compared by its sort key, which the store indexes.
+    fn comparand(&self, slot: Slot, arg: FnArg) -> Result<(Slot, FnArg)> {
+        match (slot, self.collation(slot), arg) {
+            (Slot::Column(b, i), Some(collation), FnArg::Constant(NonIntegerConstant::Text(ref s))) => {
+                let key = sort_key_text(s, collation)?;
+                Ok((Slot::SortKey(b, i), FnArg::Constant(NonIntegerConstant::from(key.as_str()))))
+            },
+            (_, _, arg) => Ok((slot, arg)),
+        }
+    }
+
+    fn var(&self, slot: Slot) -> Variable {
+        match self.find(slot) {
+            Slot::Causet(b) => self.tables[b].var.clone(),
+            Slot::Column(b, i) => variable(&format!("{}_{}", self.tables[b].name, self.tables[b].table.columns[i].name)),
+            Slot::SortKey(b, i) => variable(&format!("{}_{}_sort_key", self.tables[b].name, self.tables[b].table.columns[i].name)),
+        }
+    }
+
+    /// The variable holding a slot's causet_locale, adding the pattern that binds it if needed.
+    fn materialize(&mut self, slot: Slot) -> Variable {
+        let var = self.var(slot);
+        if let Slot::Column(b, _) | Slot::SortKey(b, _) = slot {
+            if self.materialized.insert(slot) {
+                let attribute = self.attribute(slot).unwrap();
+                let clause = pattern(&self.tables[b].var, attribute, PatternValuePlace::Variable(var.clone()));
+                self.clauses.push(clause);
+            }
+        }
+        var
+    }
+
+    fn compile_conditions(&mut self, conditions: &[(&Expr, &str)]) -> Result<()> {
+        // Equi-joins first, so that every later reference to a joined column uses the shared
+        // variable.
+        let mut joined = vec![];
+        for &(expr, clause) in conditions {
+            if let &Expr::Binary { ref left, op: BinaryOperator::Eq, ref right } = expr {
+                if let (&Expr::Column(ref l), &Expr::Column(ref r)) = (&**left, &**right) {
+                    let (l, r) = (self.resolve(l, clause)?, self.resolve(r, clause)?);
+                    self.union(l, r);
+                    joined.push(l);
+                    joined.push(r);
+                }
+            }
+        }
+        for slot in joined {
+            self.materialize(slot);
+        }
+
+        for &(expr, clause) in conditions {
+            self.compile_condition(expr, clause)?;
+        }
+        Ok(())
+    }
+
+    fn compile_condition(&mut self, expr: &Expr, clause: &str) -> Result<()> {
+        match expr {
+            &Expr::Binary { ref left, op, ref right } => {
+                let (column, op, literal) = match (&**left, &**right) {
+                    (&Expr::Column(_), &Expr::Column(_)) if op == BinaryOperator::Eq => return Ok(()),
+                    (&Expr::Column(ref l), &Expr::Column(ref r)) => {
+                        let l = self.resolve(l, clause)?;
+                        let r = self.resolve(r, clause)?;
+                        let args = vec![FnArg::Variable(self.materialize(l)), FnArg::Variable(self.materialize(r))];
+                        self.clauses.push(WhereClause::Pred(Predicate { operator: PlainShelling::plain(op.to_string()), args }));
+                        return Ok(());
+                    },
+                    (&Expr::Column(ref c), &Expr::Literal(ref lit)) => (c, op, lit),
+                    (&Expr::Literal(ref lit), &Expr::Column(ref c)) => (c, op.flipped(), lit),
+                    _ => return Err(Error::not_supported_yet(expr)),
+                };
+                let slot = self.resolve(column, clause)?;
+                let (slot, arg) = self.comparand(slot, constant(self.slot_type(slot), literal)?)?;
+                match (op, slot) {
+                    (BinaryOperator::Eq, Slot::Column(b, _)) | (BinaryOperator::Eq, Slot::SortKey(b, _)) => {
+                        let attribute = self.attribute(slot).unwrap();
+                        let clause = pattern(&self.tables[b].var, attribute, causet_locale_place(&arg));
+                        self.clauses.push(clause);
+                        self.grounds.push((slot, arg));
+                    },
+                    (BinaryOperator::Eq, Slot::Causet(_)) => {
+                        let var = self.materialize(slot);
+                        self.clauses.push(ground(arg, FnBinding::BindScalar(var)));
+                    },
+                    _ => {
+                        let args = vec![FnArg::Variable(self.materialize(slot)), arg];
+                        self.clauses.push(WhereClause::Pred(Predicate { operator: PlainShelling::plain(op.to_string()), args }));
+                    },
+                }
+            },
+            &Expr::InList { expr: ref inner, ref list, negated } => {
+                let slot = match &**inner {
+                    &Expr::Column(ref c) => self.resolve(c, clause)?,
+                    _ => return Err(Error::not_supported_yet(expr)),
+                };
+                let t = self.slot_type(slot);
+                let mut compared = slot;
+                let mut args = vec![];
+                for item in list {
+                    match item {
+                        &Expr::Literal(ref lit) => {
+                            let (s, arg) = self.comparand(slot, constant(t, lit)?)?;
+                            compared = s;
+                            args.push(arg);
+                        },
+                        _ => return Err(Error::not_supported_yet(expr)),
+                    }
+                }
+                let var = self.materialize(compared);
+                if negated {
+                    for arg in args {
+                        let args = vec![FnArg::Variable(var.clone()), arg];
+                        self.clauses.push(WhereClause::Pred(Predicate { operator: PlainShelling::plain("!="), args }));
+                    }
+                } else {
+                    self.clauses.push(ground(FnArg::Vector(args), FnBinding::BindColl(var)));
+                }
+            },
+            &Expr::IsNull { expr: ref inner, negated } => {
+                let slot = match &**inner {
+                    &Expr::Column(ref c) => self.resolve(c, clause)?,
+                    _ => return Err(Error::not_supported_yet(expr)),
+                };
+                let (b, i) = match slot {
+                    Slot::Causet(_) if negated => return Ok(()),
+                    Slot::Causet(_) => return Err(Error::not_supported_yet(expr)),
+                    Slot::Column(b, i) | Slot::SortKey(b, i) => (b, i),
+                };
+                let attribute = &self.tables[b].table.columns[i].attribute;
+                let clause = pattern(&self.tables[b].var, attribute, PatternValuePlace::Placeholder);
+                if negated {
+                    self.clauses.push(clause);
+                } else {
+                    self.clauses.push(WhereClause::NotJoin(NotJoin::new(UnifyVars::Implicit, vec![clause])));
+                }
+            },
+            _ => return Err(Error::not_supported_yet(expr)),
+        }
+        Ok(())
+    }
+
+    /// Make sure each table's variable only ranges over causets that are rows of that table.
+    fn constrain_membership(&mut self) -> Result<()> {
+        for b in 0..self.tables.len() {
+            let var = &self.tables[b].var;
+            let constrained = self.clauses.iter().any(|clause| match clause {
+                &WhereClause::Pattern(ref p) => p.causet == PatternNonValuePlace::Variable(var.clone()),
+                _ => false,
+            });
+            if constrained {
+                continue;
+            }
+            let table = self.tables[b].table;
+            let mut arms: Vec<WhereClause> =
+                table.columns
+                     .iter()
+                     .map(|c| pattern(var, &c.attribute, PatternValuePlace::Placeholder))
+                     .collect();
+            let clause = match arms.len() {
+                0 => return Err(Error::not_supported_yet(format!("table '{}' with no columns", table.name))),
+                1 => arms.remove(0),
+                _ => WhereClause::OrJoin(OrJoin::new(UnifyVars::Implicit, arms.into_iter().map(OrWhereClause::Clause).collect())),
+            };
+            self.clauses.push(clause);
+        }
+        Ok(())
+    }
+}
+
+fn ground(arg: FnArg, binding: FnBinding) -> WhereClause {
+    WhereClause::WhereFn(WhereFn {
+        operator: PlainShelling::plain("ground"),
+        args: vec![arg],
+        binding,
+    })
+}
+
+/// Compile `select` against the tables in `catalog`.
+pub fn compile_select(catalog: &Catalog, select: &Select) -> Result<CompiledSelect> {
+    let mut compiler = Compiler::new(catalog, select)?;
+
+    let mut conditions: Vec<(&Expr, &str)> = vec![];
+    for join in select.joins.iter() {
+        let mut exprs = vec![];
+        conjuncts(&join.constraint, &mut exprs)?;
+        conditions.extend(exprs.into_iter().map(|e| (e, "on clause")));
+    }
+    if let Some(ref selection) = select.selection {
+        let mut exprs = vec![];
+        conjuncts(selection, &mut exprs)?;
+        conditions.extend(exprs.into_iter().map(|e| (e, "where clause")));
+    }
+    compiler.compile_conditions(&conditions)?;
+
+    // Projection.
+    let mut elements: Vec<Element> = vec![];
+    let mut columns: Vec<(String, causetq_VT)> = vec![];
+    let mut grouped: Vec<(Variable, String)> = vec![];
+    let mut aggregated = false;
+    let mut mentioned: BTreeSet<Variable> = BTreeSet::new();
+
+    for item in select.projection.iter() {
+        let (slots, alias): (Vec<Slot>, Option<&String>) = match item {
+            &SelectItem::Wildcard => {
+                let slots = (0..compiler.tables.len()).flat_map(|b| {
+                    let width = compiler.tables[b].table.columns.len();
+                    Some(Slot::Causet(b)).into_iter().chain((0..width).map(move |i| Slot::Column(b, i)))
+                }).collect();
+                (slots, None)
+            },
+            &SelectItem::QualifiedWildcard(ref table) => {
+                let b = compiler.binding(table).ok_or_else(|| Error::no_such_table(table))?;
+                let width = compiler.tables[b].table.columns.len();
+                (Some(Slot::Causet(b)).into_iter().chain((0..width).map(|i| Slot::Column(b, i))).collect(), None)
+            },
+            &SelectItem::Expr { expr: Expr::Column(ref c), ref alias } =>
+                (vec![compiler.resolve(c, "field list")?], alias.as_ref()),
+            &SelectItem::Expr { expr: Expr::Function { ref name, ref args }, ref alias } => {
+                let (var, t) = match args.get(0) {
+                    Some(&Expr::Wildcard) => (compiler.tables[0].var.clone(), causetq_VT::Ref),
+                    Some(&Expr::Column(ref c)) => {
+                        let slot = compiler.resolve(c, "field list")?;
+                        (compiler.materialize(slot), compiler.slot_type(slot))
+                    },
+                    _ => return Err(Error::not_supported_yet(item_text(item))),
+                };
+                let t = match name.as_str() {
+                    "count" => causetq_VT::Long,
+                    "avg" => causetq_VT::Double,
+                    _ => t,
+                };
+                mentioned.insert(var.clone());
+                elements.push(Element::Aggregate(Aggregate {
+                    func: QueryFunction(PlainShelling::plain(name)),
+                    args: vec![FnArg::Variable(var)],
+                }));
+                let header = alias.clone().unwrap_or_else(|| item_text(item));
+                columns.push((header, t));
+                aggregated = true;
+                continue;
+            },
+            _ => return Err(Error::not_supported_yet(item_text(item))),
+        };
+
+        for slot in slots {
+            let var = compiler.materialize(slot);
+            let header = match (alias, slot) {
+                (Some(alias), _) => alias.clone(),
+                (None, Slot::Causet(_)) => ID_COLUMN.to_string(),
+                (None, Slot::Column(..)) => compiler.column(slot).unwrap().name.clone(),
+            };
+            mentioned.insert(var.clone());
+            grouped.push((var.clone(), header.clone()));
+            columns.push((header, compiler.slot_type(slot)));
+            elements.push(Element::Variable(var));
+        }
+    }
+
+    // Datalog groups aggregates by every projected variable, so GROUP BY must name exactly those.
+    if aggregated || !select.group_by.is_empty() {
+        let mut group_vars = BTreeSet::new();
+        for column in select.group_by.iter() {
+            let slot = compiler.resolve(column, "group statement")?;
+            let var = compiler.var(slot);
+            if !grouped.iter().any(|&(ref v, _)| v == &var) {
+                return Err(Error::not_supported_yet(format!("GROUP BY {} without selecting it", column)));
+            }
+            group_vars.insert(var);
+        }
+        if let Some(&(_, ref header)) = grouped.iter().find(|&&(ref v, _)| !group_vars.contains(v)) {
+            return Err(Error::wrong_field_with_group(header));
+        }
+    }
+
+    // A collated column is ordered by its sort key.
+    let mut order = vec![];
+    for o in select.order_by.iter() {
+        let slot = compiler.resolve(&o.column, "order clause")?;
+        let slot = match (slot, compiler.collation(slot)) {
+            (Slot::Column(b, i), Some(_)) => Slot::SortKey(b, i),
+            _ => slot,
+        };
+        let var = compiler.materialize(slot);
+        order.push(Partition(if o.asc { Direction::Ascending } else { Direction::Descending }, var));
+    }
+
+    for (slot, arg) in std::mem::replace(&mut compiler.grounds, vec![]) {
+        if compiler.materialized.contains(&slot) {
+            let var = compiler.var(slot);
+            compiler.clauses.push(ground(arg, FnBinding::BindScalar(var)));
+        }
+    }
+    compiler.constrain_membership()?;
+
+    // A row is a combination of causets, so keep the ones we don't project in `:with`. Without
+    // an aggregate, DISTINCT and GROUP BY collapse duplicates, which is Datalog's default.
+    let with = if aggregated || (!select.distinct && select.group_by.is_empty()) {
+        compiler.tables.iter().map(|t| t.var.clone()).filter(|v| !mentioned.contains(v)).collect()
+    } else {
+        vec![]
+    };
+
+    let name = compiler.tables.iter().map(|t| t.table.name.as_str()).collect::<Vec<_>>().join(",");
+    let query = ParsedQuery {
+        find_spec: FindSpec::FindRel(elements),
+        default_source: SrcVar::DefaultSrc,
+        with,
+        in_vars: vec![],
+        in_sources: BTreeSet::new(),
+        limit: select.limit.map_or(Limit::None, Limit::Fixed),
+        where_clauses: compiler.clauses,
+        order: if order.is_empty() { None } else { Some(order) },
+    };
+    Ok(CompiledSelect { name, columns, query })
+}
+
+fn item_text(item: &SelectItem) -> String {
+    match item {
+        &SelectItem::Wildcard => "*".to_string(),
+        &SelectItem::QualifiedWildcard(ref t) => format!("{}.*", t),
+        &SelectItem::Expr { ref expr, .. } => expr.to_string(),
+    }
+}
+
+fn write_constant(out: &mut String, c: &NonIntegerConstant) {
+    match c {
+        &NonIntegerConstant::Boolean(b) => write!(out, "{}", b),
+        &NonIntegerConstant::BigInteger(ref i) => write!(out, "{}N", i),
+        &NonIntegerConstant::Float(ref f) => write!(out, "{:?}", f.into_inner()),
+        &NonIntegerConstant::Text(ref s) => write!(out, "\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+        &NonIntegerConstant::Instant(ref i) => write!(out, "#inst \"{}\"", i.to_rfc3339()),
+        &NonIntegerConstant::Uuid(ref u) => write!(out, "#uuid \"{}\"", u.hyphenated()),
+    }.unwrap();
+}
+
+pub(crate) fn write_arg(out: &mut String, arg: &FnArg) {
+    match arg {
+        &FnArg::Constant(ref c) => write_constant(out, c),
+        &FnArg::Vector(ref args) => {
+            out.push('[');
+            for (i, a) in args.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_arg(out, a);
+            }
+            out.push(']');
+        },
+        other => write!(out, "{}", other).unwrap(),
+    }
+}
+
+fn write_non_value_place(out: &mut String, place: &PatternNonValuePlace) {
+    match place {
+        &PatternNonValuePlace::Placeholder => out.push('_'),
+        &PatternNonValuePlace::Variable(ref v) => write!(out, "{}", v).unwrap(),
+        &PatternNonValuePlace::Causetid(e) => write!(out, "{}", e).unwrap(),
+        &PatternNonValuePlace::Solitonid(ref k) => write!(out, "{}", k).unwrap(),
+    }
+}
+
+fn write_clause(out: &mut String, clause: &WhereClause) {
+    match clause {
+        &WhereClause::Pattern(ref p) => {
+            out.push('[');
+            write_non_value_place(out, &p.causet);
+            out.push(' ');
+            write_non_value_place(out, &p.attribute);
+            out.push(' ');
+            match p.causet_locale {
+                PatternValuePlace::Placeholder => out.push('_'),
+                PatternValuePlace::Variable(ref v) => write!(out, "{}", v).unwrap(),
+                PatternValuePlace::CausetidOrInteger(i) => write!(out, "{}", i).unwrap(),
+                PatternValuePlace::SolitonidOrKeyword(ref k) => write!(out, "{}", k).unwrap(),
+                PatternValuePlace::Constant(ref c) => write_constant(out, c),
+            }
+            out.push(']');
+        },
+        &WhereClause::Pred(ref p) => {
+            write!(out, "[({}", p.operator).unwrap();
+            for arg in p.args.iter() {
+                out.push(' ');
+                write_arg(out, arg);
+            }
+            out.push_str(")]");
+        },
+        &WhereClause::WhereFn(ref f) => {
+            write!(out, "[({}", f.operator).unwrap();
+            for arg in f.args.iter() {
+                out.push(' ');
+                write_arg(out, arg);
+            }
+            out.push_str(") ");
+            match f.binding {
+                FnBinding::BindScalar(ref v) => write!(out, "{}", v).unwrap(),
+                FnBinding::BindColl(ref v) => write!(out, "[{} ...]", v).unwrap(),
+                ref other => unreachable!("we don't compile {:?}", other),
+            }
+            out.push(']');
+        },
+        &WhereClause::NotJoin(ref n) => {
+            out.push_str("(not");
+            for c in n.clauses.iter() {
+                out.push(' ');
+                write_clause(out, c);
+            }
+            out.push(')');
+        },
+        &WhereClause::OrJoin(ref o) => {
+            out.push_str("(or");
+            for arm in o.clauses.iter() {
+                out.push(' ');
+                match arm {
+                    &OrWhereClause::Clause(ref c) => write_clause(out, c),
+                    &OrWhereClause::And(ref cs) => {
+                        out.push_str("(and");
+                        for c in cs.iter() {
+                            out.push(' ');
+                            write_clause(out, c);
+                        }
+                        out.push(')');
+                    },
+                }
+            }
+            out.push(')');
+        },
+        other => unreachable!("we don't compile {:?}", other),
+    }
+}
+
+/// Render a query we compiled back to Datalog text.
+pub fn to_datalog(query: &ParsedQuery) -> String {
+    let mut out = String::from("[:find");
+    for element in query.find_spec.columns() {
+        out.push(' ');
+        match element {
+            &Element::Aggregate(ref agg) => {
+                write!(out, "({}", agg.func).unwrap();
+                for arg in agg.args.iter() {
+                    out.push(' ');
+                    write_arg(&mut out, arg);
+                }
+                out.push(')');
+            },
+            other => write!(out, "{}", other).unwrap(),
+        }
+    }
+    if !query.with.is_empty() {
+        out.push_str(" :with");
+        for v in query.with.iter() {
+            write!(out, " {}", v).unwrap();
+        }
+    }
+    out.push_str(" :where");
+    for clause in query.where_clauses.iter() {
+        out.push(' ');
+        write_clause(&mut out, clause);
+    }
+    if let Some(ref order) = query.order {
+        out.push_str(" :order");
+        for &Partition(ref direction, ref v) in order.iter() {
+            match direction {
+                &Direction::Ascending => write!(out, " {}", v),
+                &Direction::Descending => write!(out, " (desc {})", v),
+            }.unwrap();
+        }
+    }
+    if let Limit::Fixed(n) = query.limit {
+        write!(out, " :limit {}", n).unwrap();
+    }
+    out.push(']');
+    out
+}
+
+/// Run a `SELECT` against the store.
+pub fn execute_select(conn: &Conn, sqlite: &rusqlite::Connection, catalog: &Catalog, select: &Select) -> Result<Table> {
+    let compiled = compile_select(catalog, select)?;
+    let output = conn.q_once(conn, &compiled.datalog(), sqlite, None).map_err(store_error)?;
+    compiled.into_table(output.results)
+}
+
+/// Parse and run one `SELECT`. Statements that change the store go through `dml::execute`.
+pub fn query(conn: &Conn, sqlite: &rusqlite::Connection, catalog: &Catalog, sql: &str) -> Result<Table> {
+    match parse_sql(sql)? {
+        Statement::Select(ref select) => execute_select(conn, sqlite, catalog, select),
+        _ => Err(Error::not_supported_yet("a statement other than SELECT in a query")),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use crate::error::{
+        ERR_BAD_FIELD,
+        ERR_NO_SUCH_TABLE,
+        ERR_NON_UNIQ,
+        ERR_NOT_SUPPORTED_YET,
+        ERR_TRUNCATE_WRONG_VALUE,
+        ERR_WRONG_FIELD_WITH_GROUP,
+    };
+
+    pub(crate) fn catalog() -> Catalog {
+        let mut person = SqlTable::new("person");
+        person.add_column(SqlColumn::new("name", Keyword::namespaced("person", "name"), causetq_VT::String));
+        person.add_column(SqlColumn::new("age", Keyword::namespaced("person", "age"), causetq_VT::Long));
+        let mut city = SqlColumn::new("city", Keyword::namespaced("person", "city"), causetq_VT::String);
+        city.collation = Some((Collation::Utf8Mb4GeneralCi, Keyword::namespaced("person.sort_key", "city")));
+        person.add_column(city);
+        let mut email = SqlColumn::new("email", Keyword::namespaced("person", "email"), causetq_VT::String);
+        email.unique = Some(Unique::Idcauset);
+        person.add_column(email);
+
+        let mut purchase = SqlTable::new("purchase");
+        purchase.add_column(SqlColumn::new("buyer", Keyword::namespaced("purchase", "buyer"), causetq_VT::Ref));
+        purchase.add_column(SqlColumn::new("total", Keyword::namespaced("purchase", "total"), causetq_VT::Double));
+        let mut tags = SqlColumn::new("tag", Keyword::namespaced("purchase", "tag"), causetq_VT::Keyword);
+        tags.multival = true;
+        purchase.add_column(tags);
+
+        let mut pet = SqlTable::new("pet");
+        pet.add_column(SqlColumn::new("name", Keyword::namespaced("pet", "name"), causetq_VT::String));
+
+        let mut catalog = Catalog::new();
+        catalog.add_table(person);
+        catalog.add_table(purchase);
+        catalog.add_table(pet);
+        catalog
+    }
+
+    fn datalog(sql: &str) -> String {
+        match parse_sql(sql).expect("parsed") {
+            Statement::Select(ref select) => compile_select(&catalog(), select).expect("compiled").datalog(),
+            other => panic!("expected a select, got {:?}", other),
+        }
+    }
+
+    fn error_code(sql: &str) -> i32 {
+        match parse_sql(sql).expect("parsed") {
+            Statement::Select(ref select) => compile_select(&catalog(), select).expect_err("error").code(),
+            other => panic!("expected a select, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_compile_filter_order_limit() {
+        assert_eq!(datalog("SELECT name FROM person WHERE age > 21 ORDER BY name LIMIT 5"),
+                   "[:find ?person_name :with ?person :where \
+                    [?person :person/age ?person_age] [(> ?person_age 21)] \
+                    [?person :person/name ?person_name] \
+                    :order ?person_name :limit 5]");
+    }
+
+    #[test]
+    fn test_compile_join() {
+        assert_eq!(datalog("SELECT p.name, o.total FROM person p JOIN purchase o ON o.buyer = p.id WHERE p.name = 'Alice'"),
+                   "[:find ?p_name ?o_total :with ?p ?o :where \
+                    [?o :purchase/buyer ?p] [?p :person/name \"Alice\"] \
+                    [?p :person/name ?p_name] [?o :purchase/total ?o_total] \
+                    [(ground \"Alice\") ?p_name]]");
+    }
+
+    #[test]
+    fn test_compile_aggregate() {
+        assert_eq!(datalog("SELECT city, COUNT(*) FROM person GROUP BY city"),
+                   "[:find ?person_city (count ?person) :where [?person :person/city ?person_city]]");
+        assert_eq!(datalog("SELECT SUM(total) FROM purchase"),
+                   "[:find (sum ?purchase_total) :with ?purchase :where [?purchase :purchase/total ?purchase_total]]");
+    }
+
+    #[test]
+    fn test_compile_null_and_in() {
+        assert_eq!(datalog("SELECT DISTINCT id FROM person WHERE email IS NULL AND name IN ('a', 'b')"),
+                   "[:find ?person :where (not [?person :person/email _]) \
+                    [?person :person/name ?person_name] [(ground [\"a\" \"b\"]) [?person_name ...]]]");
+    }
+
+    #[test]
+    fn test_compile_membership() {
+        // Nothing but `id` is mentioned, so the table's attributes decide what a row is.
+        assert_eq!(datalog("SELECT id FROM purchase"),
+                   "[:find ?purchase :where (or [?purchase :purchase/buyer _] [?purchase :purchase/total _] \
+                    [?purchase :purchase/tag _])]");
+    }
+
+    #[test]
+    fn test_sort_key_text() {
+        let ci = Collation::Utf8Mb4GeneralCi;
+        let paris = sort_key_text("paris", ci).unwrap();
+        assert_eq!(paris, "010050004100520049FF0053000000000000F9");
+        assert_eq!(sort_key_text("PARIS  ", ci).unwrap(), paris);
+        assert!(sort_key_text("apple", ci).unwrap() < sort_key_text("Zebra", ci).unwrap());
+        assert!(sort_key_text("paris", ci).unwrap() < sort_key_text("parisian", ci).unwrap());
+    }
+
+    #[test]
+    fn test_compile_collated() {
+        // `city` is utf8mb4_general_ci, so comparisons and the ordering go to its sort key.
+        assert_eq!(datalog("SELECT name FROM person WHERE city = 'Paris' ORDER BY city LIMIT 2"),
+                   "[:find ?person_name :with ?person :where \
+                    [?person :person.sort_key/city \"010050004100520049FF0053000000000000F9\"] \
+                    [?person :person/name ?person_name] \
+                    [?person :person.sort_key/city ?person_city_sort_key] \
+                    [(ground \"010050004100520049FF0053000000000000F9\") ?person_city_sort_key] \
+                    :order ?person_city_sort_key :limit 2]");
+        assert_eq!(datalog("SELECT DISTINCT name FROM person WHERE city IN ('paris', 'Lyon')"),
+                   "[:find ?person_name :where \
+                    [?person :person.sort_key/city ?person_city_sort_key] \
+                    [(ground [\"010050004100520049FF0053000000000000F9\" \"01004C0059004F004EFF0000000000000000F7\"]) \
+                    [?person_city_sort_key ...]] \
+                    [?person :person/name ?person_name]]");
+        assert_eq!(datalog("SELECT COUNT(*) FROM person WHERE city > 'LONDON'"),
+                   "[:find (count ?person) :where \
+                    [?person :person.sort_key/city ?person_city_sort_key] \
+                    [(> ?person_city_sort_key \"01004C004F004E0044FF004F004E00000000FB\")]]");
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert_eq!(error_code("SELECT name FROM nowhere"), ERR_NO_SUCH_TABLE);
+        assert_eq!(error_code("SELECT height FROM person"), ERR_BAD_FIELD);
+        assert_eq!(error_code("SELECT name FROM person, pet"), ERR_NON_UNIQ);
+        assert_eq!(error_code("SELECT name FROM person WHERE age = 'old'"), ERR_TRUNCATE_WRONG_VALUE);
+        assert_eq!(error_code("SELECT name FROM person WHERE age = 1 OR age = 2"), ERR_NOT_SUPPORTED_YET);
+        assert_eq!(error_code("SELECT name, COUNT(*) FROM person"), ERR_WRONG_FIELD_WITH_GROUP);
+    }
+}
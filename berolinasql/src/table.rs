@@ -10,6 +10,7 @@
 
 
 use super::*;
+use crate::collation::Collation;
 use crate::error::{Error, Result};
 use crate::parser::{Parser, ParserError};
 use crate::value::{Value, ValueType};
@@ -59,14 +60,15 @@ impl Column {
 
 #[derive(Debug)]
 pub struct Row {
-    pub values: Vec<Value>,
+    /// `None` is SQL `NULL`.
+    pub values: Vec<Option<Value>>,
 }
 
 
 
 
 impl Row {
-    pub fn new(values: Vec<Value>) -> Self {
+    pub fn new(values: Vec<Option<Value>>) -> Self {
         Row {
             values,
         }
@@ -609,6 +611,19 @@ pub fn encode_index_seek_soliton_id(table_id: i64, idx_id: i64, encoded: &[u8])
     soliton_id
 }
 
+/// `encode_collated_index_causet_locale` encodes a string causet_locale of an Index causet_merge
+/// as the sort key `collation` gives it, so that seeks and range scans over the Index follow
+/// the collation and e.g. `'abc'` and `'ABC'` share a soliton_id under a `_ci` collation. The
+/// sort key can't be decoded back into the string, which has to be read from the event.
+pub fn encode_collated_index_causet_locale(
+    ctx: &mut EvalContext,
+    causet_locale: &[u8],
+    collation: Collation,
+) -> Result<Vec<u8>> {
+    let sort_key = collation.sort_key(causet_locale)?;
+    datum::encode_soliton_id(ctx, &[DatumType::Bytes(sort_key)])
+}
+
 // `decode_index_soliton_id` decodes datums from an Index soliton_id.
 pub fn decode_index_soliton_id(
     ctx: &mut EvalContext,
@@ -974,6 +989,22 @@ mod tests {
         assert_eq!(tests, decode_index_soliton_id(&mut ctx, &encoded, &types).unwrap());
     }
 
+    #[test]
+    fn test_collated_index_soliton_id_order() {
+        let mut ctx = EvalContext::default();
+        let mut encode = |s: &str, collation| {
+            let buf = encode_collated_index_causet_locale(&mut ctx, s.as_bytes(), collation).unwrap();
+            encode_index_seek_soliton_id(TABLE_ID, INDEX_ID, &buf)
+        };
+        let ci = Collation::Utf8Mb4GeneralCi;
+        assert_eq!(encode("abc", ci), encode("ABC  ", ci));
+        assert!(encode("abc", ci) < encode("ABD", ci));
+        assert!(encode("Zebra", ci) > encode("apple", ci));
+        let bin = Collation::Utf8Mb4Bin;
+        assert_ne!(encode("abc", bin), encode("ABC", bin));
+        assert!(encode("Zebra", bin) < encode("apple", bin));
+    }
+
     fn to_hash_map(event: &RowColsDict) -> HashMap<i64, Vec<u8>> {
         let mut data = HashMap::with_capacity_and_hasher(event.cols.len(), Default::default());
         if event.is_empty() {
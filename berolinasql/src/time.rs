@@ -0,0 +1,1744 @@
+// Copyright 2022 EinsteinDB Project Authors. Licensed under Apache-2.0.
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! MyBerolinaSQL `DATE`, `DATETIME` and `TIMESTAMP` causet_locales.
+//!
+//! A `Time` is packed into a u64 the way MEDB's `CoreTime` is, so that the packed causet_locales
+//! order like the times they hold once the low four bits are dropped:
+//!
+//! ```text
+//! | year: 14 | month: 4 | day: 5 | hour: 5 | minute: 6 | second: 6 | micro: 20 | fsp_tt: 4 |
+//! ```
+//!
+//! `fsp_tt` is `0b1110` for a `DATE` and `fsp << 1 | is_timestamp` otherwise.
+//!
+//! A `TIMESTAMP` is held in the session time zone like the other types; `to_utc` and
+//! `from_utc` convert it for storage, and `convert_tz` implements `CONVERT_TZ`.
+
+use std::cmp::{self, Ordering};
+use std::fmt::{self, Display, Formatter, Write};
+use std::hash::{Hash, Hasher};
+
+use chrono::{
+    Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset,
+    TimeZone as ChronoTimeZone, Timelike,
+};
+use chrono_tz::Tz;
+
+use super::duration::{Duration, MICROS_PER_SEC, NANOS_PER_MICRO};
+use super::error::{Error, Result};
+
+pub const UNSPECIFIED_FSP: i8 = -1;
+pub const MIN_FSP: i8 = 0;
+pub const MAX_FSP: i8 = 6;
+pub const DEFAULT_FSP: i8 = 0;
+
+const YEAR_OFFSET: u32 = 50;
+const MONTH_OFFSET: u32 = 46;
+const DAY_OFFSET: u32 = 41;
+const HOUR_OFFSET: u32 = 36;
+const MINUTE_OFFSET: u32 = 30;
+const SECOND_OFFSET: u32 = 24;
+const MICRO_OFFSET: u32 = 4;
+
+const YEAR_BITS: u32 = 14;
+const MONTH_BITS: u32 = 4;
+const DAY_BITS: u32 = 5;
+const HOUR_BITS: u32 = 5;
+const MINUTE_BITS: u32 = 6;
+const SECOND_BITS: u32 = 6;
+const MICRO_BITS: u32 = 20;
+
+const DATE_FSP_TT: u64 = 0b1110;
+const FSP_TT_MASK: u64 = 0b1111;
+
+const MIN_YEAR: i32 = 1;
+const MAX_YEAR: i32 = 9999;
+
+/// Two-digit years below this are in the 2000s, the others in the 1900s.
+const YY_PART_YEAR: u32 = 70;
+
+/// The range of a `TIMESTAMP`, in seconds since the epoch.
+const MIN_TIMESTAMP: i64 = 1;
+const MAX_TIMESTAMP: i64 = (1 << 31) - 1;
+
+/// MyBerolinaSQL accepts UTC offsets from -13:59 to +14:00.
+const MIN_UTC_OFFSET_SECS: i32 = -(13 * 3600 + 59 * 60);
+const MAX_UTC_OFFSET_SECS: i32 = 14 * 3600;
+
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const ABBR_MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+/// Weekday names, Monday first as chrono numbers them.
+const WEEKDAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const ABBR_WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Week mode flags of `WEEK()`; see `calc_week`.
+const WEEK_MONDAY_FIRST: u32 = 1;
+const WEEK_YEAR: u32 = 2;
+const WEEK_FIRST_WEEKDAY: u32 = 4;
+
+/// Checks a fractional seconds precision, with `UNSPECIFIED_FSP` meaning `DEFAULT_FSP`.
+pub fn check_fsp(fsp: i8) -> Result<u8> {
+    if fsp == UNSPECIFIED_FSP {
+        return Ok(DEFAULT_FSP as u8);
+    }
+    if !(MIN_FSP..=MAX_FSP).contains(&fsp) {
+        return Err(Error::too_big_precision(fsp, MAX_FSP));
+    }
+    Ok(fsp as u8)
+}
+
+fn is_leap_year(year: u32) -> bool {
+    NaiveDate::from_ymd_opt(year as i32, 2, 29).is_some()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn days_in_year(year: i32) -> i64 {
+    if is_leap_year(year as u32) {
+        366
+    } else {
+        365
+    }
+}
+
+/// Completes a two-digit year.
+fn adjust_year(year: u32) -> u32 {
+    if year < YY_PART_YEAR {
+        year + 2000
+    } else {
+        year + 1900
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeType {
+    Date,
+    DateTime,
+    Timestamp,
+}
+
+/// The parts of a `Time`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeArgs {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub micro: u32,
+}
+
+impl TimeArgs {
+    /// Whether the parts form a real date and time, or the zero date `0000-00-00`.
+    fn is_valid(&self) -> bool {
+        let zero_date = (self.year, self.month, self.day) == (0, 0, 0);
+        let date_valid = zero_date
+            || (self.year <= MAX_YEAR as u32
+                && (1..=12).contains(&self.month)
+                && self.day >= 1
+                && self.day <= days_in_month(self.year, self.month));
+        date_valid
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+            && self.micro < MICROS_PER_SEC as u32
+    }
+}
+
+impl Display for TimeArgs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second, self.micro
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Time(u64);
+
+impl Time {
+    fn pack(args: &TimeArgs, fsp: u8, time_type: TimeType) -> Time {
+        let fsp_tt = match time_type {
+            TimeType::Date => DATE_FSP_TT,
+            TimeType::DateTime => u64::from(fsp) << 1,
+            TimeType::Timestamp => u64::from(fsp) << 1 | 1,
+        };
+        Time(
+            u64::from(args.year) << YEAR_OFFSET
+                | u64::from(args.month) << MONTH_OFFSET
+                | u64::from(args.day) << DAY_OFFSET
+                | u64::from(args.hour) << HOUR_OFFSET
+                | u64::from(args.minute) << MINUTE_OFFSET
+                | u64::from(args.second) << SECOND_OFFSET
+                | u64::from(args.micro) << MICRO_OFFSET
+                | fsp_tt,
+        )
+    }
+
+    #[inline]
+    fn field(self, offset: u32, bits: u32) -> u32 {
+        ((self.0 >> offset) & ((1 << bits) - 1)) as u32
+    }
+
+    /// Builds a time from its parts, rounding the microseconds to `fsp`. A `DATE` drops the
+    /// time of day.
+    pub fn new(args: TimeArgs, fsp: i8, time_type: TimeType) -> Result<Time> {
+        let fsp = check_fsp(fsp)?;
+        let args = if time_type == TimeType::Date {
+            TimeArgs {
+                hour: 0,
+                minute: 0,
+                second: 0,
+                micro: 0,
+                ..args
+            }
+        } else {
+            args
+        };
+        if !args.is_valid() {
+            return Err(Error::incorrect_datetime_causet_locale(args));
+        }
+        Time::pack(&args, MAX_FSP as u8, time_type).round_frac(fsp as i8)
+    }
+
+    /// The zero time `0000-00-00 00:00:00`.
+    pub fn zero(time_type: TimeType, fsp: i8) -> Result<Time> {
+        let fsp = check_fsp(fsp)?;
+        Ok(Time::pack(&TimeArgs::default(), fsp, time_type))
+    }
+
+    #[inline]
+    pub fn year(self) -> u32 {
+        self.field(YEAR_OFFSET, YEAR_BITS)
+    }
+
+    #[inline]
+    pub fn month(self) -> u32 {
+        self.field(MONTH_OFFSET, MONTH_BITS)
+    }
+
+    #[inline]
+    pub fn day(self) -> u32 {
+        self.field(DAY_OFFSET, DAY_BITS)
+    }
+
+    #[inline]
+    pub fn hour(self) -> u32 {
+        self.field(HOUR_OFFSET, HOUR_BITS)
+    }
+
+    #[inline]
+    pub fn minute(self) -> u32 {
+        self.field(MINUTE_OFFSET, MINUTE_BITS)
+    }
+
+    #[inline]
+    pub fn second(self) -> u32 {
+        self.field(SECOND_OFFSET, SECOND_BITS)
+    }
+
+    #[inline]
+    pub fn micro(self) -> u32 {
+        self.field(MICRO_OFFSET, MICRO_BITS)
+    }
+
+    #[inline]
+    pub fn fsp(self) -> u8 {
+        match self.0 & FSP_TT_MASK {
+            DATE_FSP_TT => 0,
+            fsp_tt => (fsp_tt >> 1) as u8,
+        }
+    }
+
+    #[inline]
+    pub fn get_time_type(self) -> TimeType {
+        match self.0 & FSP_TT_MASK {
+            DATE_FSP_TT => TimeType::Date,
+            fsp_tt if fsp_tt & 1 == 1 => TimeType::Timestamp,
+            _ => TimeType::DateTime,
+        }
+    }
+
+    /// Whether this is `0000-00-00 00:00:00`.
+    #[inline]
+    pub fn is_zero(self) -> bool {
+        self.0 >> MICRO_OFFSET == 0
+    }
+
+    pub fn parts(self) -> TimeArgs {
+        TimeArgs {
+            year: self.year(),
+            month: self.month(),
+            day: self.day(),
+            hour: self.hour(),
+            minute: self.minute(),
+            second: self.second(),
+            micro: self.micro(),
+        }
+    }
+
+    fn to_naive(self) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(self.year() as i32, self.month(), self.day())?.and_hms_micro_opt(
+            self.hour(),
+            self.minute(),
+            self.second(),
+            self.micro(),
+        )
+    }
+
+    fn from_naive(dt: NaiveDateTime, fsp: u8, time_type: TimeType) -> Option<Time> {
+        if !(MIN_YEAR..=MAX_YEAR).contains(&dt.year()) {
+            return None;
+        }
+        let args = TimeArgs {
+            year: dt.year() as u32,
+            month: dt.month(),
+            day: dt.day(),
+            hour: dt.hour(),
+            minute: dt.minute(),
+            second: dt.second(),
+            micro: dt.nanosecond() / NANOS_PER_MICRO as u32,
+        };
+        let args = if time_type == TimeType::Date {
+            TimeArgs {
+                hour: 0,
+                minute: 0,
+                second: 0,
+                micro: 0,
+                ..args
+            }
+        } else {
+            args
+        };
+        Some(Time::pack(&args, fsp, time_type))
+    }
+
+    /// Rounds the fractional seconds half up to `fsp` digits, carrying into the seconds.
+    pub fn round_frac(self, fsp: i8) -> Result<Time> {
+        let fsp = check_fsp(fsp)?;
+        let time_type = self.get_time_type();
+        if time_type == TimeType::Date {
+            return Ok(self);
+        }
+        let mut args = self.parts();
+        let step = 10u32.pow(MAX_FSP as u32 - u32::from(fsp));
+        let micro = (args.micro + step / 2) / step * step;
+        if micro < MICROS_PER_SEC as u32 {
+            args.micro = micro;
+            return Ok(Time::pack(&args, fsp, time_type));
+        }
+        args.micro = 0;
+        Time::pack(&args, fsp, time_type)
+            .checked_add_micros(MICROS_PER_SEC)
+            .ok_or_else(|| Error::incorrect_datetime_causet_locale(self))
+    }
+
+    fn checked_add_micros(self, micros: i64) -> Option<Time> {
+        let dt = self
+            .to_naive()?
+            .checked_add_signed(chrono::Duration::microseconds(micros))?;
+        Time::from_naive(dt, self.fsp(), self.get_time_type())
+    }
+
+    /// Parses a time in any of the MyBerolinaSQL formats: delimited `YYYY-MM-DD HH:MM:SS.ffffff`
+    /// with any punctuation as delimiters and `T` or spaces before the time, or undelimited
+    /// `YYYYMMDDHHMMSS`, `YYMMDDHHMMSS`, `YYMMDDHHMM`, `YYYYMMDD` and `YYMMDD`. Two-digit
+    /// years are completed to 1970-2069. The fraction is rounded to `fsp` digits.
+    pub fn parse(input: &str, time_type: TimeType, fsp: i8) -> Result<Time> {
+        let fsp = check_fsp(fsp)?;
+        let err = || Error::incorrect_datetime_causet_locale(input);
+        let trimmed = input.trim();
+        // Runs of digits, each with the run of separators after it.
+        let (mut groups, mut seps) = (vec![], vec![]);
+        let bytes = trimmed.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let start = i;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            if start == i {
+                return Err(err());
+            }
+            groups.push(&trimmed[start..i]);
+            let sep_start = i;
+            while i < bytes.len() && !bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            let sep = &trimmed[sep_start..i];
+            if sep != "T"
+                && !sep
+                    .bytes()
+                    .all(|b| b.is_ascii_punctuation() || b.is_ascii_whitespace())
+            {
+                return Err(err());
+            }
+            seps.push(sep);
+        }
+        if groups.is_empty() || seps.last().is_some_and(|sep| !sep.is_empty()) {
+            return Err(err());
+        }
+
+        let number = |s: &str| -> Result<u32> {
+            if s.len() > 9 {
+                return Err(err());
+            }
+            s.parse().map_err(|_| err())
+        };
+        let mut args = TimeArgs::default();
+        let fraction;
+        if groups.len() == 1 || (groups.len() == 2 && seps[0] == ".") {
+            let digits = groups[0];
+            fraction = groups.get(1).copied();
+            let (year_len, fields) = match digits.len() {
+                14 => (4, 6),
+                12 => (2, 6),
+                10 => (2, 5),
+                8 => (4, 3),
+                6 => (2, 3),
+                _ => return Err(err()),
+            };
+            let mut rest = &digits[year_len..];
+            args.year = number(&digits[..year_len])?;
+            if year_len == 2 {
+                args.year = adjust_year(args.year);
+            }
+            for field in [
+                &mut args.month,
+                &mut args.day,
+                &mut args.hour,
+                &mut args.minute,
+                &mut args.second,
+            ]
+            .into_iter()
+            .take(fields - 1)
+            {
+                *field = number(&rest[..2])?;
+                rest = &rest[2..];
+            }
+        } else {
+            if groups.len() < 3 || groups.len() > 7 {
+                return Err(err());
+            }
+            fraction = if groups.len() == 7 {
+                if seps[5] != "." {
+                    return Err(err());
+                }
+                Some(groups[6])
+            } else {
+                None
+            };
+            args.year = number(groups[0])?;
+            if groups[0].len() <= 2 {
+                args.year = adjust_year(args.year);
+            }
+            args.month = number(groups[1])?;
+            args.day = number(groups[2])?;
+            let time_fields = [&mut args.hour, &mut args.minute, &mut args.second];
+            for (field, group) in time_fields.into_iter().zip(groups[3..].iter().take(3)) {
+                *field = number(group)?;
+            }
+        }
+
+        let mut carry = false;
+        if let Some(fraction) = fraction {
+            // Round the fraction, read as nanoseconds, half up to fsp digits.
+            let digits = &fraction[..cmp::min(fraction.len(), 9)];
+            let nanos = number(digits)? * 10u32.pow(9 - digits.len() as u32);
+            let step = 10u32.pow(9 - u32::from(fsp));
+            let rounded =
+                (u64::from(nanos) + u64::from(step / 2)) / u64::from(step) * u64::from(step);
+            carry = rounded >= 1_000_000_000;
+            args.micro = ((rounded % 1_000_000_000) / NANOS_PER_MICRO as u64) as u32;
+        }
+        let t = Time::new(args, fsp as i8, time_type).map_err(|_| err())?;
+        if carry && time_type != TimeType::Date {
+            return t.checked_add_micros(MICROS_PER_SEC).ok_or_else(err);
+        }
+        Ok(t)
+    }
+
+    /// Reads a number such as `20210102101112` or `210102` as a time, the way MyBerolinaSQL
+    /// does when a number is used where a time is expected.
+    pub fn parse_from_i64(n: i64, time_type: TimeType, fsp: i8) -> Result<Time> {
+        let err = || Error::incorrect_datetime_causet_locale(n);
+        if n == 0 {
+            return Time::zero(time_type, fsp);
+        }
+        let yy = i64::from(YY_PART_YEAR);
+        let n = match n {
+            n if n >= 10_000_101_000_000 => {
+                if n > 99_999_999_999_999 {
+                    return Err(err());
+                }
+                n
+            }
+            n if n < 101 => return Err(err()),
+            n if n <= (yy - 1) * 10_000 + 1231 => (n + 20_000_000) * 1_000_000,
+            n if n < yy * 10_000 + 101 => return Err(err()),
+            n if n <= 991_231 => (n + 19_000_000) * 1_000_000,
+            n if n < 10_000_101 => return Err(err()),
+            n if n <= 99_991_231 => n * 1_000_000,
+            n if n < 101_000_000 => return Err(err()),
+            n if n <= (yy - 1) * 10_000_000_000 + 1_231_235_959 => n + 20_000_000_000_000,
+            n if n < yy * 10_000_000_000 + 101_000_000 => return Err(err()),
+            n if n <= 991_231_235_959 => n + 19_000_000_000_000,
+            _ => return Err(err()),
+        };
+        let ymd = n / 1_000_000;
+        let hms = n % 1_000_000;
+        let args = TimeArgs {
+            year: (ymd / 10_000) as u32,
+            month: (ymd / 100 % 100) as u32,
+            day: (ymd % 100) as u32,
+            hour: (hms / 10_000) as u32,
+            minute: (hms / 100 % 100) as u32,
+            second: (hms % 100) as u32,
+            micro: 0,
+        };
+        Time::new(args, fsp, time_type).map_err(|_| err())
+    }
+
+    /// The MyBerolinaSQL packed form of the time, as the chunk and binlog codecs use it.
+    pub fn to_packed_u64(self) -> u64 {
+        let ymd =
+            (u64::from(self.year()) * 13 + u64::from(self.month())) << 5 | u64::from(self.day());
+        let hms =
+            u64::from(self.hour()) << 12 | u64::from(self.minute()) << 6 | u64::from(self.second());
+        (ymd << 17 | hms) << 24 | u64::from(self.micro())
+    }
+
+    pub fn from_packed_u64(packed: u64, time_type: TimeType, fsp: i8) -> Result<Time> {
+        let ymdhms = packed >> 24;
+        let (ymd, hms) = (ymdhms >> 17, ymdhms & ((1 << 17) - 1));
+        let ym = ymd >> 5;
+        let args = TimeArgs {
+            year: (ym / 13) as u32,
+            month: (ym % 13) as u32,
+            day: (ymd & 31) as u32,
+            hour: (hms >> 12) as u32,
+            minute: (hms >> 6 & 63) as u32,
+            second: (hms & 63) as u32,
+            micro: (packed & ((1 << 24) - 1)) as u32,
+        };
+        Time::new(args, fsp, time_type)
+    }
+
+    /// The time of day as a `Duration`.
+    pub fn to_duration(self) -> Result<Duration> {
+        Duration::new_from_parts(
+            false,
+            self.hour(),
+            self.minute(),
+            self.second(),
+            self.micro() * NANOS_PER_MICRO as u32,
+            self.fsp() as i8,
+        )
+    }
+
+    /// `DATE_ADD`: the time `interval` later, or `None` if that is outside `0001-01-01` to
+    /// `9999-12-31` or this is a zero date. Adding months keeps the day of the month, but no
+    /// later than the month's last day. A `DATE` becomes a `DATETIME` when the interval has
+    /// a time part, and a `TIMESTAMP` always does.
+    pub fn checked_add_interval(self, interval: &Interval) -> Option<Time> {
+        let mut dt = self.to_naive()?;
+        if interval.months != 0 {
+            let months = i64::from(dt.year()) * 12 + i64::from(dt.month0()) + interval.months;
+            let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+            if !(i64::from(MIN_YEAR)..=i64::from(MAX_YEAR)).contains(&year) {
+                return None;
+            }
+            let day = cmp::min(dt.day(), days_in_month(year as u32, month));
+            dt = NaiveDate::from_ymd_opt(year as i32, month, day)?.and_time(dt.time());
+        }
+        let dt = dt.checked_add_signed(chrono::Duration::microseconds(interval.micros))?;
+        let time_type = match self.get_time_type() {
+            TimeType::Date if !interval.time_part => TimeType::Date,
+            _ => TimeType::DateTime,
+        };
+        Time::from_naive(dt, cmp::max(self.fsp(), interval.fsp), time_type)
+    }
+
+    /// `DATE_SUB`: the time `interval` earlier; see `checked_add_interval`.
+    pub fn checked_sub_interval(self, interval: &Interval) -> Option<Time> {
+        self.checked_add_interval(&-*interval)
+    }
+
+    pub fn checked_add_duration(self, duration: Duration) -> Option<Time> {
+        self.checked_add_interval(&Interval::from_duration(duration))
+    }
+
+    pub fn checked_sub_duration(self, duration: Duration) -> Option<Time> {
+        self.checked_sub_interval(&Interval::from_duration(duration))
+    }
+
+    fn date(self) -> Result<NaiveDate> {
+        NaiveDate::from_ymd_opt(self.year() as i32, self.month(), self.day())
+            .ok_or_else(|| Error::incorrect_datetime_causet_locale(self))
+    }
+
+    /// `DATE_FORMAT`: formats the time by MyBerolinaSQL's `%` specifiers. An unknown specifier
+    /// stands for its own character.
+    pub fn date_format(self, format: &str) -> Result<String> {
+        let mut out = String::with_capacity(format.len() * 2);
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            if let Some(spec) = chars.next() {
+                self.write_format_spec(spec, &mut out)?;
+            }
+        }
+        Ok(out)
+    }
+
+    fn write_format_spec(self, spec: char, out: &mut String) -> Result<()> {
+        let hour12 = match self.hour() % 12 {
+            0 => 12,
+            h => h,
+        };
+        let am_pm = if self.hour() < 12 { "AM" } else { "PM" };
+        let month_idx = (self.month() as usize).checked_sub(1);
+        let month_name = |names: &[&'static str; 12]| {
+            month_idx
+                .and_then(|i| names.get(i).copied())
+                .ok_or_else(|| Error::incorrect_datetime_causet_locale(self))
+        };
+        // Writing to a String can't fail.
+        match spec {
+            'b' => out.push_str(month_name(&ABBR_MONTH_NAMES)?),
+            'M' => out.push_str(month_name(&MONTH_NAMES)?),
+            'c' => write!(out, "{}", self.month()).unwrap(),
+            'm' => write!(out, "{:02}", self.month()).unwrap(),
+            'D' => {
+                let suffix = match self.day() {
+                    11..=13 => "th",
+                    d if d % 10 == 1 => "st",
+                    d if d % 10 == 2 => "nd",
+                    d if d % 10 == 3 => "rd",
+                    _ => "th",
+                };
+                write!(out, "{}{}", self.day(), suffix).unwrap()
+            }
+            'd' => write!(out, "{:02}", self.day()).unwrap(),
+            'e' => write!(out, "{}", self.day()).unwrap(),
+            'j' => write!(out, "{:03}", self.date()?.ordinal()).unwrap(),
+            'H' => write!(out, "{:02}", self.hour()).unwrap(),
+            'k' => write!(out, "{}", self.hour()).unwrap(),
+            'h' | 'I' => write!(out, "{:02}", hour12).unwrap(),
+            'l' => write!(out, "{}", hour12).unwrap(),
+            'i' => write!(out, "{:02}", self.minute()).unwrap(),
+            'S' | 's' => write!(out, "{:02}", self.second()).unwrap(),
+            'f' => write!(out, "{:06}", self.micro()).unwrap(),
+            'p' => out.push_str(am_pm),
+            'r' => write!(
+                out,
+                "{:02}:{:02}:{:02} {}",
+                hour12,
+                self.minute(),
+                self.second(),
+                am_pm
+            )
+            .unwrap(),
+            'T' => write!(
+                out,
+                "{:02}:{:02}:{:02}",
+                self.hour(),
+                self.minute(),
+                self.second()
+            )
+            .unwrap(),
+            'W' => {
+                let weekday = self.date()?.weekday().num_days_from_monday();
+                out.push_str(WEEKDAY_NAMES[weekday as usize])
+            }
+            'a' => {
+                let weekday = self.date()?.weekday().num_days_from_monday();
+                out.push_str(ABBR_WEEKDAY_NAMES[weekday as usize])
+            }
+            'w' => write!(out, "{}", self.date()?.weekday().num_days_from_sunday()).unwrap(),
+            'U' => write!(out, "{:02}", calc_week(self.date()?, 0).1).unwrap(),
+            'u' => write!(out, "{:02}", calc_week(self.date()?, 1).1).unwrap(),
+            'V' => write!(out, "{:02}", calc_week(self.date()?, 2).1).unwrap(),
+            'v' => write!(out, "{:02}", calc_week(self.date()?, 3).1).unwrap(),
+            'X' => write!(out, "{:04}", calc_week(self.date()?, 2).0).unwrap(),
+            'x' => write!(out, "{:04}", calc_week(self.date()?, 3).0).unwrap(),
+            'Y' => write!(out, "{:04}", self.year()).unwrap(),
+            'y' => write!(out, "{:02}", self.year() % 100).unwrap(),
+            other => out.push(other),
+        }
+        Ok(())
+    }
+
+    /// `STR_TO_DATE`: parses `input` by a `DATE_FORMAT` format. The result is a `DATETIME` if
+    /// the format has a time part (with fsp 6 if it reads `%f`) and a `DATE` otherwise; `None`
+    /// if the input doesn't match or isn't a valid time.
+    pub fn str_to_date(input: &str, format: &str) -> Option<Time> {
+        let mut parser = StrToDate {
+            rest: input,
+            args: TimeArgs::default(),
+            day_of_year: None,
+            pm: None,
+            twelve_hour: false,
+            has_time: false,
+            has_frac: false,
+        };
+        parser.parse(format)?;
+        parser.finish()
+    }
+
+    /// `CONVERT_TZ`: reads the time in `from` and returns it as the wall-clock time in `to`.
+    /// A `DATE` is taken to be its midnight and becomes a `DATETIME`.
+    pub fn convert_tz(self, from: &TimeZone, to: &TimeZone) -> Result<Time> {
+        if self.is_zero() {
+            return Ok(self);
+        }
+        let err = || Error::incorrect_datetime_causet_locale(self);
+        let local = self.to_naive().ok_or_else(err)?;
+        let converted = to.utc_to_local(from.local_to_utc(local));
+        let time_type = match self.get_time_type() {
+            TimeType::Date => TimeType::DateTime,
+            time_type => time_type,
+        };
+        Time::from_naive(converted, self.fsp(), time_type).ok_or_else(err)
+    }
+
+    /// Converts a time read in the session time zone `tz` to UTC for storage. A `TIMESTAMP`
+    /// must lie within `1970-01-01 00:00:01` and `2038-01-19 03:14:07` UTC.
+    pub fn to_utc(self, tz: &TimeZone) -> Result<Time> {
+        let utc = self.convert_tz(tz, &TimeZone::utc())?;
+        if self.get_time_type() == TimeType::Timestamp && !utc.is_zero() {
+            let secs = utc
+                .to_unix_micros(&TimeZone::utc())?
+                .div_euclid(MICROS_PER_SEC);
+            if !(MIN_TIMESTAMP..=MAX_TIMESTAMP).contains(&secs) {
+                return Err(Error::incorrect_datetime_causet_locale(self));
+            }
+        }
+        Ok(utc)
+    }
+
+    /// Converts a stored UTC time to the session time zone `tz`.
+    pub fn from_utc(self, tz: &TimeZone) -> Result<Time> {
+        self.convert_tz(&TimeZone::utc(), tz)
+    }
+
+    /// `FROM_UNIXTIME`: the `DATETIME` in `tz` that is `micros` after the epoch.
+    pub fn from_unix_micros(micros: i64, tz: &TimeZone, fsp: i8) -> Result<Time> {
+        let err = || Error::incorrect_datetime_causet_locale(micros);
+        let utc = unix_epoch()
+            .checked_add_signed(chrono::Duration::microseconds(micros))
+            .ok_or_else(err)?;
+        Time::from_naive(tz.utc_to_local(utc), MAX_FSP as u8, TimeType::DateTime)
+            .ok_or_else(err)?
+            .round_frac(fsp)
+    }
+
+    /// `UNIX_TIMESTAMP`: the microseconds since the epoch of the time read in `tz`.
+    pub fn to_unix_micros(self, tz: &TimeZone) -> Result<i64> {
+        let local = self
+            .to_naive()
+            .ok_or_else(|| Error::incorrect_datetime_causet_locale(self))?;
+        let since_epoch = tz.local_to_utc(local) - unix_epoch();
+        since_epoch
+            .num_microseconds()
+            .ok_or_else(|| Error::incorrect_datetime_causet_locale(self))
+    }
+}
+
+fn unix_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(1970, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// The `(year, week)` of `date` by the `WEEK()` `mode`.
+///
+/// Port from MyBerolinaSQL's `calc_week`.
+fn calc_week(date: NaiveDate, mode: u32) -> (i32, u32) {
+    let mut mode = mode & 7;
+    if mode & WEEK_MONDAY_FIRST == 0 {
+        mode ^= WEEK_FIRST_WEEKDAY;
+    }
+    let monday_first = mode & WEEK_MONDAY_FIRST != 0;
+    let mut week_year = mode & WEEK_YEAR != 0;
+    let first_weekday = mode & WEEK_FIRST_WEEKDAY != 0;
+
+    let mut year = date.year();
+    let day_nr = i64::from(date.num_days_from_ce());
+    let jan_1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+    let mut first_day_nr = i64::from(jan_1.num_days_from_ce());
+    let mut weekday = i64::from(if monday_first {
+        jan_1.weekday().num_days_from_monday()
+    } else {
+        jan_1.weekday().num_days_from_sunday()
+    });
+
+    if date.month() == 1 && i64::from(date.day()) <= 7 - weekday {
+        if !week_year && ((first_weekday && weekday != 0) || (!first_weekday && weekday >= 4)) {
+            return (year, 0);
+        }
+        week_year = true;
+        year -= 1;
+        let days = days_in_year(year);
+        first_day_nr -= days;
+        weekday = (weekday + 53 * 7 - days) % 7;
+    }
+
+    let days = if (first_weekday && weekday != 0) || (!first_weekday && weekday >= 4) {
+        day_nr - (first_day_nr + (7 - weekday))
+    } else {
+        day_nr - (first_day_nr - weekday)
+    };
+
+    if week_year && days >= 52 * 7 {
+        weekday = (weekday + days_in_year(year)) % 7;
+        if (!first_weekday && weekday < 4) || (first_weekday && weekday == 0) {
+            return (year + 1, 1);
+        }
+    }
+    (year, (days / 7 + 1) as u32)
+}
+
+/// The state of `STR_TO_DATE` as it walks the format.
+struct StrToDate<'a> {
+    rest: &'a str,
+    args: TimeArgs,
+    day_of_year: Option<u32>,
+    pm: Option<bool>,
+    twelve_hour: bool,
+    has_time: bool,
+    has_frac: bool,
+}
+
+impl<'a> StrToDate<'a> {
+    /// Reads at most `max_digits` digits, returning the number and how many digits it had.
+    fn number(&mut self, max_digits: usize) -> Option<(u32, usize)> {
+        self.rest = self.rest.trim_start();
+        let len = self
+            .rest
+            .bytes()
+            .take(max_digits)
+            .take_while(u8::is_ascii_digit)
+            .count();
+        if len == 0 {
+            return None;
+        }
+        let (digits, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        digits.parse().ok().map(|n| (n, len))
+    }
+
+    /// Reads one of `names`, case-insensitively, returning its index.
+    fn name(&mut self, names: &[&str]) -> Option<usize> {
+        self.rest = self.rest.trim_start();
+        let idx = names.iter().position(|name| {
+            self.rest
+                .get(..name.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+        })?;
+        self.rest = &self.rest[names[idx].len()..];
+        Some(idx)
+    }
+
+    fn literal(&mut self, c: char) -> Option<()> {
+        if c.is_whitespace() {
+            self.rest = self.rest.trim_start();
+            return Some(());
+        }
+        self.rest = self.rest.strip_prefix(c)?;
+        Some(())
+    }
+
+    fn parse(&mut self, format: &str) -> Option<()> {
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                self.literal(c)?;
+                continue;
+            }
+            let spec = match chars.next() {
+                Some(spec) => spec,
+                None => return self.literal('%'),
+            };
+            match spec {
+                'Y' => {
+                    let (year, len) = self.number(4)?;
+                    self.args.year = if len <= 2 { adjust_year(year) } else { year };
+                }
+                'y' => self.args.year = adjust_year(self.number(2)?.0),
+                'm' | 'c' => self.args.month = self.number(2)?.0,
+                'M' => self.args.month = self.name(&MONTH_NAMES)? as u32 + 1,
+                'b' => self.args.month = self.name(&ABBR_MONTH_NAMES)? as u32 + 1,
+                'd' | 'e' => self.args.day = self.number(2)?.0,
+                'D' => {
+                    self.args.day = self.number(2)?.0;
+                    self.name(&["st", "nd", "rd", "th"])?;
+                }
+                'j' => self.day_of_year = Some(self.number(3)?.0),
+                'H' | 'k' => {
+                    self.args.hour = self.number(2)?.0;
+                    self.has_time = true;
+                }
+                'h' | 'I' | 'l' => {
+                    self.args.hour = self.number(2)?.0;
+                    self.twelve_hour = true;
+                    self.has_time = true;
+                }
+                'i' => {
+                    self.args.minute = self.number(2)?.0;
+                    self.has_time = true;
+                }
+                'S' | 's' => {
+                    self.args.second = self.number(2)?.0;
+                    self.has_time = true;
+                }
+                'f' => {
+                    let (frac, len) = self.number(6)?;
+                    self.args.micro = frac * 10u32.pow(6 - len as u32);
+                    self.has_time = true;
+                    self.has_frac = true;
+                }
+                'p' => self.pm = Some(self.name(&["AM", "PM"])? == 1),
+                'r' => self.parse("%I:%i:%S %p")?,
+                'T' => self.parse("%H:%i:%S")?,
+                'W' => {
+                    self.name(&WEEKDAY_NAMES)?;
+                }
+                'a' => {
+                    self.name(&ABBR_WEEKDAY_NAMES)?;
+                }
+                other => self.literal(other)?,
+            }
+        }
+        Some(())
+    }
+
+    fn finish(mut self) -> Option<Time> {
+        if self.twelve_hour {
+            if !(1..=12).contains(&self.args.hour) {
+                return None;
+            }
+            self.args.hour %= 12;
+            if self.pm == Some(true) {
+                self.args.hour += 12;
+            }
+        }
+        if let Some(day_of_year) = self.day_of_year {
+            let date = NaiveDate::from_yo_opt(self.args.year as i32, day_of_year)?;
+            self.args.month = date.month();
+            self.args.day = date.day();
+        }
+        let (time_type, fsp) = match (self.has_time, self.has_frac) {
+            (false, _) => (TimeType::Date, 0),
+            (true, false) => (TimeType::DateTime, 0),
+            (true, true) => (TimeType::DateTime, MAX_FSP),
+        };
+        Time::new(self.args, fsp, time_type).ok()
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}",
+            self.year(),
+            self.month(),
+            self.day()
+        )?;
+        if self.get_time_type() == TimeType::Date {
+            return Ok(());
+        }
+        write!(
+            f,
+            " {:02}:{:02}:{:02}",
+            self.hour(),
+            self.minute(),
+            self.second()
+        )?;
+        let fsp = u32::from(self.fsp());
+        if fsp > 0 {
+            let frac = self.micro() / 10u32.pow(MAX_FSP as u32 - fsp);
+            write!(f, ".{:0width$}", frac, width = fsp as usize)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Time {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}({})", self.get_time_type(), self)
+    }
+}
+
+/// Times compare by the instant they hold, whatever their fsp and type.
+impl PartialEq for Time {
+    fn eq(&self, other: &Time) -> bool {
+        self.0 >> MICRO_OFFSET == other.0 >> MICRO_OFFSET
+    }
+}
+
+impl Eq for Time {}
+
+impl PartialOrd for Time {
+    fn partial_cmp(&self, other: &Time) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Time {
+    fn cmp(&self, other: &Time) -> Ordering {
+        (self.0 >> MICRO_OFFSET).cmp(&(other.0 >> MICRO_OFFSET))
+    }
+}
+
+impl Hash for Time {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 >> MICRO_OFFSET).hash(state)
+    }
+}
+
+/// A time zone: a tz database zone such as `Europe/Berlin`, or a fixed offset from UTC.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeZone {
+    Named(Tz),
+    Offset(FixedOffset),
+}
+
+impl TimeZone {
+    pub fn utc() -> TimeZone {
+        TimeZone::Offset(FixedOffset::east_opt(0).unwrap())
+    }
+
+    /// Parses a tz database zone name or an offset such as `+05:30`, failing with
+    /// `Error::invalid_timezone` for anything else.
+    pub fn from_name(name: &str) -> Result<TimeZone> {
+        let trimmed = name.trim();
+        if let Some(offset) = parse_utc_offset(trimmed) {
+            return Ok(TimeZone::Offset(offset));
+        }
+        trimmed
+            .parse::<Tz>()
+            .map(TimeZone::Named)
+            .map_err(|_| Error::invalid_timezone(name))
+    }
+
+    /// Converts a wall-clock time of this zone to UTC. An ambiguous time, repeated when the
+    /// clocks go back, is taken as its earlier instant; a time skipped when they go forward
+    /// is read with the offset from before the skip, which moves it past the skip.
+    fn local_to_utc(&self, local: NaiveDateTime) -> NaiveDateTime {
+        let offset = match self {
+            TimeZone::Offset(offset) => *offset,
+            TimeZone::Named(tz) => match tz.offset_from_local_datetime(&local) {
+                LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => offset.fix(),
+                LocalResult::None => tz
+                    .offset_from_utc_datetime(&(local - chrono::Duration::days(1)))
+                    .fix(),
+            },
+        };
+        local - chrono::Duration::seconds(i64::from(offset.local_minus_utc()))
+    }
+
+    fn utc_to_local(&self, utc: NaiveDateTime) -> NaiveDateTime {
+        let offset = match self {
+            TimeZone::Offset(offset) => *offset,
+            TimeZone::Named(tz) => tz.offset_from_utc_datetime(&utc).fix(),
+        };
+        utc + chrono::Duration::seconds(i64::from(offset.local_minus_utc()))
+    }
+}
+
+fn parse_utc_offset(s: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let (hours, minutes): (i32, i32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if minutes >= 60 {
+        return None;
+    }
+    let secs = sign * (hours * 3600 + minutes * 60);
+    if !(MIN_UTC_OFFSET_SECS..=MAX_UTC_OFFSET_SECS).contains(&secs) {
+        return None;
+    }
+    FixedOffset::east_opt(secs)
+}
+
+/// The units of an `INTERVAL`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntervalUnit {
+    Microsecond,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+    SecondMicrosecond,
+    MinuteMicrosecond,
+    MinuteSecond,
+    HourMicrosecond,
+    HourSecond,
+    HourMinute,
+    DayMicrosecond,
+    DaySecond,
+    DayMinute,
+    DayHour,
+    YearMonth,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IntervalPart {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    Microsecond,
+}
+
+impl IntervalUnit {
+    pub fn from_name(name: &str) -> Result<IntervalUnit> {
+        let unit = match name.trim().to_ascii_uppercase().as_str() {
+            "MICROSECOND" => IntervalUnit::Microsecond,
+            "SECOND" => IntervalUnit::Second,
+            "MINUTE" => IntervalUnit::Minute,
+            "HOUR" => IntervalUnit::Hour,
+            "DAY" => IntervalUnit::Day,
+            "WEEK" => IntervalUnit::Week,
+            "MONTH" => IntervalUnit::Month,
+            "QUARTER" => IntervalUnit::Quarter,
+            "YEAR" => IntervalUnit::Year,
+            "SECOND_MICROSECOND" => IntervalUnit::SecondMicrosecond,
+            "MINUTE_MICROSECOND" => IntervalUnit::MinuteMicrosecond,
+            "MINUTE_SECOND" => IntervalUnit::MinuteSecond,
+            "HOUR_MICROSECOND" => IntervalUnit::HourMicrosecond,
+            "HOUR_SECOND" => IntervalUnit::HourSecond,
+            "HOUR_MINUTE" => IntervalUnit::HourMinute,
+            "DAY_MICROSECOND" => IntervalUnit::DayMicrosecond,
+            "DAY_SECOND" => IntervalUnit::DaySecond,
+            "DAY_MINUTE" => IntervalUnit::DayMinute,
+            "DAY_HOUR" => IntervalUnit::DayHour,
+            "YEAR_MONTH" => IntervalUnit::YearMonth,
+            _ => return Err(Error::sql_syntax(format!("unknown INTERVAL unit {}", name))),
+        };
+        Ok(unit)
+    }
+
+    /// The parts a causet_locale of this unit lists, most significant first.
+    fn parts(self) -> &'static [IntervalPart] {
+        use self::IntervalPart::*;
+        match self {
+            IntervalUnit::Microsecond => &[Microsecond],
+            IntervalUnit::Second => &[Second],
+            IntervalUnit::Minute => &[Minute],
+            IntervalUnit::Hour => &[Hour],
+            IntervalUnit::Day | IntervalUnit::Week => &[Day],
+            IntervalUnit::Month | IntervalUnit::Quarter => &[Month],
+            IntervalUnit::Year => &[Year],
+            IntervalUnit::SecondMicrosecond => &[Second, Microsecond],
+            IntervalUnit::MinuteMicrosecond => &[Minute, Second, Microsecond],
+            IntervalUnit::MinuteSecond => &[Minute, Second],
+            IntervalUnit::HourMicrosecond => &[Hour, Minute, Second, Microsecond],
+            IntervalUnit::HourSecond => &[Hour, Minute, Second],
+            IntervalUnit::HourMinute => &[Hour, Minute],
+            IntervalUnit::DayMicrosecond => &[Day, Hour, Minute, Second, Microsecond],
+            IntervalUnit::DaySecond => &[Day, Hour, Minute, Second],
+            IntervalUnit::DayMinute => &[Day, Hour, Minute],
+            IntervalUnit::DayHour => &[Day, Hour],
+            IntervalUnit::YearMonth => &[Year, Month],
+        }
+    }
+
+    fn multiplier(self) -> i64 {
+        match self {
+            IntervalUnit::Week => 7,
+            IntervalUnit::Quarter => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// The `INTERVAL` operand of `DATE_ADD` and `DATE_SUB`, as months and microseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Interval {
+    months: i64,
+    micros: i64,
+    fsp: u8,
+    /// Whether the unit has a time part, which turns a `DATE` into a `DATETIME`.
+    time_part: bool,
+}
+
+impl Interval {
+    /// Parses the causet_locale of an `INTERVAL expr unit`. A composite unit such as
+    /// `DAY_SECOND` takes its parts separated by any non-digits, e.g. `'1 02:03:04'`; fewer
+    /// causet_locales than parts fill the least significant ones, and a microsecond part is read
+    /// as a fraction, so `'1.5' SECOND_MICROSECOND` is one and a half seconds. `SECOND` alone
+    /// takes a fraction too; the other single units read the integer the causet_locale starts with.
+    pub fn parse(expr: &str, unit: IntervalUnit) -> Result<Interval> {
+        let err = || Error::invalid_time_format(expr);
+        let trimmed = expr.trim();
+        let (negative, body) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let parts = unit.parts();
+        let groups: Vec<&str> = body
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|g| !g.is_empty())
+            .collect();
+        if groups.is_empty() || !body.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(err());
+        }
+        let parse_group = |g: &str| -> Result<i64> { g.parse().map_err(|_| err()) };
+        let mut fsp = 0;
+        let causet_locales: Vec<(IntervalPart, i64)> = if parts.len() == 1 {
+            let whole = parse_group(groups[0])?;
+            if parts[0] == IntervalPart::Second && body[groups[0].len()..].starts_with('.') {
+                let frac = groups.get(1).copied().unwrap_or("");
+                let frac = &frac[..cmp::min(frac.len(), MAX_FSP as usize)];
+                fsp = frac.len() as u8;
+                let micros = if frac.is_empty() {
+                    0
+                } else {
+                    parse_group(frac)? * 10i64.pow(MAX_FSP as u32 - frac.len() as u32)
+                };
+                vec![
+                    (IntervalPart::Second, whole),
+                    (IntervalPart::Microsecond, micros),
+                ]
+            } else {
+                vec![(parts[0], whole)]
+            }
+        } else {
+            if groups.len() > parts.len() {
+                return Err(err());
+            }
+            let offset = parts.len() - groups.len();
+            groups
+                .iter()
+                .enumerate()
+                .map(|(i, g)| {
+                    let part = parts[offset + i];
+                    if part == IntervalPart::Microsecond {
+                        fsp = MAX_FSP as u8;
+                        let digits = &g[..cmp::min(g.len(), MAX_FSP as usize)];
+                        let scale = 10i64.pow(MAX_FSP as u32 - digits.len() as u32);
+                        Ok((part, parse_group(digits)? * scale))
+                    } else {
+                        Ok((part, parse_group(g)?))
+                    }
+                })
+                .collect::<Result<_>>()?
+        };
+        if parts.contains(&IntervalPart::Microsecond) {
+            fsp = MAX_FSP as u8;
+        }
+
+        let (mut months, mut micros) = (0i64, 0i64);
+        for (part, causet_locale) in causet_locales {
+            let causet_locale = causet_locale
+                .checked_mul(unit.multiplier())
+                .ok_or_else(err)?;
+            let (total, factor) = match part {
+                IntervalPart::Year => (&mut months, 12),
+                IntervalPart::Month => (&mut months, 1),
+                IntervalPart::Day => (&mut micros, 86_400 * MICROS_PER_SEC),
+                IntervalPart::Hour => (&mut micros, 3_600 * MICROS_PER_SEC),
+                IntervalPart::Minute => (&mut micros, 60 * MICROS_PER_SEC),
+                IntervalPart::Second => (&mut micros, MICROS_PER_SEC),
+                IntervalPart::Microsecond => (&mut micros, 1),
+            };
+            *total = causet_locale
+                .checked_mul(factor)
+                .and_then(|v| total.checked_add(v))
+                .ok_or_else(err)?;
+        }
+        let time_part = parts.iter().any(|part| {
+            !matches!(
+                part,
+                IntervalPart::Year | IntervalPart::Month | IntervalPart::Day
+            )
+        });
+        let interval = Interval {
+            months,
+            micros,
+            fsp,
+            time_part,
+        };
+        Ok(if negative { -interval } else { interval })
+    }
+
+    pub fn from_duration(duration: Duration) -> Interval {
+        Interval {
+            months: 0,
+            micros: duration.to_micros(),
+            fsp: duration.fsp(),
+            time_part: true,
+        }
+    }
+}
+
+impl std::ops::Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval {
+            months: -self.months,
+            micros: -self.micros,
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ERR_UNCAUSET_LOCALE_NUCLEON_TIMEZONE;
+
+    #[test]
+    fn test_parse() {
+        let cases = vec![
+            (
+                "2021-01-02 10:11:12",
+                TimeType::DateTime,
+                0,
+                Some("2021-01-02 10:11:12"),
+            ),
+            (
+                "2021-01-02T10:11:12.123456",
+                TimeType::DateTime,
+                6,
+                Some("2021-01-02 10:11:12.123456"),
+            ),
+            (
+                "2021-01-02 10:11:12.9999995",
+                TimeType::DateTime,
+                6,
+                Some("2021-01-02 10:11:13.000000"),
+            ),
+            (
+                "2021-01-02 10:11:12.5",
+                TimeType::DateTime,
+                0,
+                Some("2021-01-02 10:11:13"),
+            ),
+            (
+                "2021-12-31 23:59:59.5",
+                TimeType::DateTime,
+                0,
+                Some("2022-01-01 00:00:00"),
+            ),
+            (
+                "21-1-2 3:4:5",
+                TimeType::DateTime,
+                0,
+                Some("2021-01-02 03:04:05"),
+            ),
+            ("99/12/31", TimeType::Date, 0, Some("1999-12-31")),
+            ("2021.01.02", TimeType::Date, 0, Some("2021-01-02")),
+            (
+                "20210102101112",
+                TimeType::DateTime,
+                0,
+                Some("2021-01-02 10:11:12"),
+            ),
+            (
+                "210102101112",
+                TimeType::DateTime,
+                0,
+                Some("2021-01-02 10:11:12"),
+            ),
+            ("20210102", TimeType::Date, 0, Some("2021-01-02")),
+            ("700102", TimeType::Date, 0, Some("1970-01-02")),
+            (
+                "20210102101112.25",
+                TimeType::DateTime,
+                2,
+                Some("2021-01-02 10:11:12.25"),
+            ),
+            ("2021-01-02 10:11:12", TimeType::Date, 0, Some("2021-01-02")),
+            (
+                "2021-01-02 10:11:12",
+                TimeType::Timestamp,
+                0,
+                Some("2021-01-02 10:11:12"),
+            ),
+            (
+                "0000-00-00 00:00:00",
+                TimeType::DateTime,
+                0,
+                Some("0000-00-00 00:00:00"),
+            ),
+            ("2020-02-29", TimeType::Date, 0, Some("2020-02-29")),
+            ("2021-02-29", TimeType::Date, 0, None),
+            ("2021-13-01", TimeType::Date, 0, None),
+            ("2021-01-02 24:00:00", TimeType::DateTime, 0, None),
+            ("2021-01-02x", TimeType::Date, 0, None),
+            ("2021-01", TimeType::Date, 0, None),
+            ("2021010", TimeType::Date, 0, None),
+            ("abc", TimeType::Date, 0, None),
+            ("", TimeType::Date, 0, None),
+        ];
+        for (i, (input, time_type, fsp, expected)) in cases.into_iter().enumerate() {
+            let got = Time::parse(input, time_type, fsp).map(|t| t.to_string());
+            match expected {
+                Some(expected) => {
+                    assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected)
+                }
+                None => assert!(got.is_err(), "#{} expect error but got {:?}", i, got),
+            }
+        }
+
+        let a = Time::parse("2021-01-02 10:11:12", TimeType::DateTime, 0).unwrap();
+        let b = Time::parse("2021-01-02 10:11:12.000", TimeType::DateTime, 3).unwrap();
+        let c = Time::parse("2021-01-02 10:11:12.001", TimeType::DateTime, 3).unwrap();
+        assert_eq!(a, b);
+        assert!(b < c);
+        assert_eq!(c.fsp(), 3);
+        assert_eq!(c.get_time_type(), TimeType::DateTime);
+        assert!(Time::parse("2021-01-02", TimeType::Date, 7).is_err());
+    }
+
+    #[test]
+    fn test_parse_from_i64() {
+        let cases = vec![
+            (20210102101112, Some("2021-01-02 10:11:12")),
+            (210102101112, Some("2021-01-02 10:11:12")),
+            (991231235959, Some("1999-12-31 23:59:59")),
+            (20210102, Some("2021-01-02 00:00:00")),
+            (210102, Some("2021-01-02 00:00:00")),
+            (991231, Some("1999-12-31 00:00:00")),
+            (0, Some("0000-00-00 00:00:00")),
+            (100, None),
+            (691232, None),
+            (-1, None),
+        ];
+        for (i, (n, expected)) in cases.into_iter().enumerate() {
+            let got = Time::parse_from_i64(n, TimeType::DateTime, 0).map(|t| t.to_string());
+            match expected {
+                Some(expected) => {
+                    assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected)
+                }
+                None => assert!(got.is_err(), "#{} expect error but got {:?}", i, got),
+            }
+        }
+    }
+
+    #[test]
+    fn test_packed_u64() {
+        for s in &[
+            "2021-01-02 10:11:12.123456",
+            "0000-00-00 00:00:00.000000",
+            "9999-12-31 23:59:59.999999",
+        ] {
+            let t = Time::parse(s, TimeType::DateTime, 6).unwrap();
+            let packed = t.to_packed_u64();
+            let got = Time::from_packed_u64(packed, TimeType::DateTime, 6).unwrap();
+            assert_eq!(got.to_string(), *s);
+        }
+        let t = Time::parse("2021-01-02 10:11:12.5", TimeType::DateTime, 1).unwrap();
+        assert_eq!(
+            t.to_duration().unwrap(),
+            Duration::new_from_parts(false, 10, 11, 12, 500_000_000, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_add_interval() {
+        let cases = vec![
+            ("2021-01-31", "1", "MONTH", true, Some("2021-02-28")),
+            ("2020-01-31", "1", "MONTH", true, Some("2020-02-29")),
+            ("2021-01-31", "-1", "YEAR", true, Some("2020-01-31")),
+            ("2021-01-02", "1", "DAY", true, Some("2021-01-03")),
+            ("2021-01-02", "1", "HOUR", true, Some("2021-01-02 01:00:00")),
+            ("2021-01-02", "2-3", "YEAR_MONTH", true, Some("2023-04-02")),
+            ("2021-01-02", "1", "QUARTER", true, Some("2021-04-02")),
+            ("2021-01-02", "2", "WEEK", true, Some("2021-01-16")),
+            ("2021-03-01", "1", "DAY", false, Some("2021-02-28")),
+            (
+                "2021-12-31 23:59:59",
+                "1 1:1:1",
+                "DAY_SECOND",
+                true,
+                Some("2022-01-02 01:01:00"),
+            ),
+            (
+                "2021-01-02 00:00:00",
+                "1:2",
+                "DAY_SECOND",
+                true,
+                Some("2021-01-02 00:01:02"),
+            ),
+            (
+                "2021-01-02 00:00:00",
+                "1.5",
+                "SECOND_MICROSECOND",
+                true,
+                Some("2021-01-02 00:00:01.500000"),
+            ),
+            (
+                "2021-01-02 00:00:00",
+                "1.5",
+                "SECOND",
+                true,
+                Some("2021-01-02 00:00:01.5"),
+            ),
+            (
+                "2021-01-02 00:00:00",
+                "-1.5",
+                "SECOND",
+                true,
+                Some("2021-01-01 23:59:58.5"),
+            ),
+            (
+                "2021-01-02 00:00:00",
+                "1 2",
+                "HOUR_MINUTE",
+                false,
+                Some("2021-01-01 22:58:00"),
+            ),
+            ("9999-12-31", "1", "DAY", true, None),
+            ("0001-01-01", "1", "DAY", false, None),
+            ("0000-00-00", "1", "DAY", true, None),
+        ];
+        for (i, (t, expr, unit, add, expected)) in cases.into_iter().enumerate() {
+            let time_type = if t.len() > 10 {
+                TimeType::DateTime
+            } else {
+                TimeType::Date
+            };
+            let t = Time::parse(t, time_type, 0).unwrap();
+            let interval = Interval::parse(expr, IntervalUnit::from_name(unit).unwrap()).unwrap();
+            let got = if add {
+                t.checked_add_interval(&interval)
+            } else {
+                t.checked_sub_interval(&interval)
+            };
+            let got = got.map(|t| t.to_string());
+            assert_eq!(got.as_deref(), expected, "#{} expect {:?}", i, expected);
+        }
+
+        assert!(Interval::parse("1:2:3", IntervalUnit::HourMinute).is_err());
+        assert!(Interval::parse("x", IntervalUnit::Day).is_err());
+        assert!(IntervalUnit::from_name("FORTNIGHT").is_err());
+
+        let t = Time::parse("2021-01-02", TimeType::Date, 0).unwrap();
+        let d = Duration::new_from_parts(false, 1, 30, 0, 0, 0).unwrap();
+        assert_eq!(
+            t.checked_add_duration(d).unwrap().to_string(),
+            "2021-01-02 01:30:00"
+        );
+        assert_eq!(
+            t.checked_sub_duration(d).unwrap().to_string(),
+            "2021-01-01 22:30:00"
+        );
+    }
+
+    #[test]
+    fn test_date_format() {
+        let t = Time::parse("2021-01-03 13:04:05.012345", TimeType::DateTime, 6).unwrap();
+        let cases = vec![
+            ("%Y-%m-%d %H:%i:%s.%f", "2021-01-03 13:04:05.012345"),
+            ("%W %M %D %y", "Sunday January 3rd 21"),
+            ("%a %b %e %c %j", "Sun Jan 3 1 003"),
+            ("%h:%i %p %l %k %I", "01:04 PM 1 13 01"),
+            ("%r %T", "01:04:05 PM 13:04:05"),
+            ("%U %u %V %v %X %x %w", "01 00 01 53 2021 2020 0"),
+            ("%% %Q", "% Q"),
+        ];
+        for (i, (format, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(
+                t.date_format(format).unwrap(),
+                expected,
+                "#{} expect {:?}",
+                i,
+                expected
+            );
+        }
+
+        let t = Time::parse("2021-12-11", TimeType::Date, 0).unwrap();
+        assert_eq!(t.date_format("%D %H:%i").unwrap(), "11th 00:00");
+        let zero = Time::zero(TimeType::Date, 0).unwrap();
+        assert!(zero.date_format("%W").is_err());
+    }
+
+    #[test]
+    fn test_str_to_date() {
+        let cases = vec![
+            (
+                "2021-01-03 13:04:05",
+                "%Y-%m-%d %H:%i:%s",
+                Some("2021-01-03 13:04:05"),
+            ),
+            ("03/01/21", "%d/%m/%y", Some("2021-01-03")),
+            ("January 3rd, 2021", "%M %D, %Y", Some("2021-01-03")),
+            (
+                "Sun Jan 3 2021 1:04:05 PM",
+                "%a %b %e %Y %r",
+                Some("2021-01-03 13:04:05"),
+            ),
+            (
+                "12:00:00 am 2021-01-03",
+                "%r %Y-%m-%d",
+                Some("2021-01-03 00:00:00"),
+            ),
+            (
+                "2021-01-03 13:04:05.5",
+                "%Y-%m-%d %T.%f",
+                Some("2021-01-03 13:04:05.500000"),
+            ),
+            ("2021-034", "%Y-%j", Some("2021-02-03")),
+            ("2021-02-30", "%Y-%m-%d", None),
+            ("2021/01/03", "%Y-%m-%d", None),
+            ("2021-01-03 13:04", "%Y-%m-%d %h:%i", None),
+            ("Someday", "%W", None),
+        ];
+        for (i, (input, format, expected)) in cases.into_iter().enumerate() {
+            let got = Time::str_to_date(input, format).map(|t| t.to_string());
+            assert_eq!(got.as_deref(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+
+    #[test]
+    fn test_convert_tz() {
+        let cases = vec![
+            (
+                "2021-01-01 12:00:00",
+                "+00:00",
+                "+08:00",
+                "2021-01-01 20:00:00",
+            ),
+            (
+                "2021-01-01 00:30:00",
+                "+05:30",
+                "-01:00",
+                "2020-12-31 18:00:00",
+            ),
+            (
+                "2021-01-01 12:00:00",
+                "UTC",
+                "America/New_York",
+                "2021-01-01 07:00:00",
+            ),
+            (
+                "2021-07-01 12:00:00",
+                "UTC",
+                "America/New_York",
+                "2021-07-01 08:00:00",
+            ),
+            // Skipped by the clocks going forward.
+            (
+                "2021-03-14 02:30:00",
+                "America/New_York",
+                "UTC",
+                "2021-03-14 07:30:00",
+            ),
+            // Repeated by the clocks going back.
+            (
+                "2021-11-07 01:30:00",
+                "America/New_York",
+                "UTC",
+                "2021-11-07 05:30:00",
+            ),
+            (
+                "2021-01-01 08:00:00",
+                "Asia/Shanghai",
+                "Europe/London",
+                "2021-01-01 00:00:00",
+            ),
+        ];
+        for (i, (t, from, to, expected)) in cases.into_iter().enumerate() {
+            let t = Time::parse(t, TimeType::DateTime, 0).unwrap();
+            let from = TimeZone::from_name(from).unwrap();
+            let to = TimeZone::from_name(to).unwrap();
+            let got = t.convert_tz(&from, &to).unwrap().to_string();
+            assert_eq!(got, expected, "#{} expect {:?}", i, expected);
+        }
+
+        for name in &["Mars/Olympus_Mons", "+14:01", "+08:60", ""] {
+            let err = TimeZone::from_name(name).unwrap_err();
+            assert_eq!(err.code(), ERR_UNCAUSET_LOCALE_NUCLEON_TIMEZONE, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_range() {
+        let utc = TimeZone::utc();
+        let cases = vec![
+            ("2038-01-19 03:14:07", "UTC", true),
+            ("2038-01-19 03:14:08", "UTC", false),
+            ("1970-01-01 00:00:00", "UTC", false),
+            ("1970-01-01 00:00:01", "UTC", true),
+            ("1970-01-01 01:00:01", "+01:00", true),
+            ("0000-00-00 00:00:00", "UTC", true),
+        ];
+        for (i, (t, tz, ok)) in cases.into_iter().enumerate() {
+            let t = Time::parse(t, TimeType::Timestamp, 0).unwrap();
+            let tz = TimeZone::from_name(tz).unwrap();
+            let got = t.to_utc(&tz);
+            assert_eq!(got.is_ok(), ok, "#{} expect ok {} but got {:?}", i, ok, got);
+            if let Ok(stored) = got {
+                assert_eq!(stored.from_utc(&tz).unwrap(), t);
+            }
+        }
+
+        let shanghai = TimeZone::from_name("Asia/Shanghai").unwrap();
+        let t = Time::from_unix_micros(1_500_000, &shanghai, 1).unwrap();
+        assert_eq!(t.to_string(), "1970-01-01 08:00:01.5");
+        assert_eq!(t.to_unix_micros(&shanghai).unwrap(), 1_500_000);
+        assert_eq!(
+            Time::from_unix_micros(0, &utc, 0).unwrap().to_string(),
+            "1970-01-01 00:00:00"
+        );
+    }
+}
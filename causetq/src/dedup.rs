@@ -324,10 +324,13 @@ pub const TX0: i64 = 0x10000000;
 pub const USER0: i64 = 0x10000;
 
 // Corresponds to the version of the :einsteindb.topograph/core vocabulary.
-pub const CORE_SCHEMA_VERSION: u32 = 1;
+//
+// 1: the initial core vocabulary.
+// 2: adds :einsteindb/ttl, :einsteindb.type/json and :einsteindb/jsonSchema.
+pub const CORE_SCHEMA_VERSION: u32 = 2;
 
 lazy_static! {
-    static ref EINSTEIN_DB__solitonidS: [(shellings::Keyword, i64); 40] = {
+    static ref EINSTEIN_DB__solitonidS: [(shellings::Keyword, i64); 43] = {
             [(ns_soliton_idword!("einsteindb", "solitonid"),             causetids::EINSTEINDB_solitonid),
              (ns_soliton_idword!("einsteindb.part", "einsteindb"),           causetids::EINSTEINDB_PART_EINSTEINDB),
              (ns_soliton_idword!("einsteindb", "txInstant"),         causetids::EINSTEINDB_TX_INSTANT),
@@ -341,6 +344,8 @@ lazy_static! {
              (ns_soliton_idword!("einsteindb", "Index"),             causetids::EINSTEINDB_INDEX),
              (ns_soliton_idword!("einsteindb", "fulltext"),          causetids::EINSTEINDB_FULLTEXT),
              (ns_soliton_idword!("einsteindb", "noHistory"),         causetids::EINSTEINDB_NO_HISTORY),
+             (ns_soliton_idword!("einsteindb", "ttl"),               causetids::EINSTEINDB_TTL),
+             (ns_soliton_idword!("einsteindb", "jsonSchema"),        causetids::EINSTEINDB_JSON_SCHEMA),
              (ns_soliton_idword!("einsteindb", "add"),               causetids::EINSTEINDB_ADD),
              (ns_soliton_idword!("einsteindb", "retract"),           causetids::EINSTEINDB_RETRACT),
              (ns_soliton_idword!("einsteindb.part", "user"),         causetids::EINSTEINDB_PART_USER),
@@ -360,6 +365,7 @@ lazy_static! {
              (ns_soliton_idword!("einsteindb.type", "boolean"),      causetids::EINSTEINDB_TYPE_BOOLEAN),
              (ns_soliton_idword!("einsteindb.type", "instant"),      causetids::EINSTEINDB_TYPE_INSTANT),
              (ns_soliton_idword!("einsteindb.type", "bytes"),        causetids::EINSTEINDB_TYPE_BYTES),
+             (ns_soliton_idword!("einsteindb.type", "json"),         causetids::EINSTEINDB_TYPE_JSON),
              (ns_soliton_idword!("einsteindb.cardinality", "one"),   causetids::EINSTEINDB_CARDINALITY_ONE),
              (ns_soliton_idword!("einsteindb.cardinality", "many"),  causetids::EINSTEINDB_CARDINALITY_MANY),
              (ns_soliton_idword!("einsteindb.unique", "causet_locale"),      causetids::EINSTEINDB_UNIQUE_VALUE),
@@ -372,13 +378,14 @@ lazy_static! {
     };
 
     pub static ref EINSTEIN_DB__PARTS: [(shellings::Keyword, i64, i64, i64, bool); 3] = {
-            [(ns_soliton_idword!("einsteindb.part", "einsteindb"), 0, USER0 - 1, (1 + EINSTEIN_DB__solitonidS.len()) as i64, false),
+            // Solitonids aren't numbered densely, so allocation starts past the highest one.
+            [(ns_soliton_idword!("einsteindb.part", "einsteindb"), 0, USER0 - 1, 1 + EINSTEIN_DB__solitonidS.iter().map(|&(_, e)| e).max().unwrap(), false),
              (ns_soliton_idword!("einsteindb.part", "user"), USER0, TX0 - 1, USER0, true),
              (ns_soliton_idword!("einsteindb.part", "tx"), TX0, i64::max_causet_locale(), TX0, false),
         ]
     };
 
-    static ref EINSTEIN_DB__CORE_SCHEMA: [(shellings::Keyword); 16] = {
+    static ref EINSTEIN_DB__CORE_SCHEMA: [(shellings::Keyword); 18] = {
             [(ns_soliton_idword!("einsteindb", "solitonid")),
              (ns_soliton_idword!("einsteindb.install", "partition")),
              (ns_soliton_idword!("einsteindb.install", "causet_localeType")),
@@ -392,12 +399,23 @@ lazy_static! {
              (ns_soliton_idword!("einsteindb", "Index")),
              (ns_soliton_idword!("einsteindb", "fulltext")),
              (ns_soliton_idword!("einsteindb", "noHistory")),
+             (ns_soliton_idword!("einsteindb", "ttl")),
+             (ns_soliton_idword!("einsteindb", "jsonSchema")),
              (ns_soliton_idword!("einsteindb.alter", "attribute")),
              (ns_soliton_idword!("einsteindb.topograph", "version")),
              (ns_soliton_idword!("einsteindb.topograph", "attribute")),
         ]
     };
 
+    /// The core solitonids added since version 1 of the core vocabulary, with the version that added
+    /// each.  `core_schema_upgrade_causets` installs them in stores created at an older version.
+    static ref EINSTEIN_DB__CORE_SCHEMA_ADDITIONS: [(u32, shellings::Keyword); 3] = {
+            [(2, ns_soliton_idword!("einsteindb", "ttl")),
+             (2, ns_soliton_idword!("einsteindb.type", "json")),
+             (2, ns_soliton_idword!("einsteindb", "jsonSchema")),
+        ]
+    };
+
     static ref EINSTEIN_DB__SYMBOLIC_SCHEMA: Value = {
         let s = r#"
 {:einsteindb/solitonid             {:einsteindb/causet_localeType   :einsteindb.type/soliton_idword
@@ -432,6 +450,12 @@ lazy_static! {
                         :einsteindb/cardinality :einsteindb.cardinality/one}
  :einsteindb/noHistory         {:einsteindb/causet_localeType   :einsteindb.type/boolean
                         :einsteindb/cardinality :einsteindb.cardinality/one}
+ ;; Seconds until datoms expire; see `einstein_db::ttl`.
+ :einsteindb/ttl               {:einsteindb/causet_localeType   :einsteindb.type/long
+                        :einsteindb/cardinality :einsteindb.cardinality/one}
+ ;; The JSON Schema of a JSON attribute; see `einstein_db::json_schemas`.
+ :einsteindb/jsonSchema        {:einsteindb/causet_localeType   :einsteindb.type/string
+                        :einsteindb/cardinality :einsteindb.cardinality/one}
  :einsteindb.alter/attribute   {:einsteindb/causet_localeType   :einsteindb.type/ref
                         :einsteindb/cardinality :einsteindb.cardinality/many}
  :einsteindb.topograph/version    {:einsteindb/causet_localeType   :einsteindb.type/long
@@ -592,6 +616,44 @@ pub(crate) fn bootstrap_causets() -> Vec<causet<einstein_ml::ValueAndSpan>> {
     return bootstrap_causets;
 }
 
+/// The causets that bring the core vocabulary of a store from `version` up to
+/// `CORE_SCHEMA_VERSION`: the solitonids added since, the definitions of those that are
+/// attributes, and the new version.  They name core solitonids, so they are transacted against
+/// `bootstrap_topograph()`.
+pub(crate) fn core_schema_upgrade_causets(version: u32) -> Vec<causet<einstein_ml::ValueAndSpan>> {
+    let added: Vec<&shellings::Keyword> = EINSTEIN_DB__CORE_SCHEMA_ADDITIONS.iter()
+        .filter(|&&(added_in, _)| added_in > version)
+        .map(|&(_, ref solitonid)| solitonid)
+        .collect();
+    let solitonids: Vec<(shellings::Keyword, i64)> = EINSTEIN_DB__solitonidS.iter()
+        .filter(|&&(ref solitonid, _)| added.contains(&solitonid))
+        .cloned()
+        .collect();
+    let attributes: Vec<shellings::Keyword> = EINSTEIN_DB__CORE_SCHEMA.iter()
+        .filter(|solitonid| added.contains(solitonid))
+        .cloned()
+        .collect();
+    let shellingic_topograph = match *EINSTEIN_DB__SYMBOLIC_SCHEMA {
+        Value::Map(ref m) => Value::Map(m.iter()
+            .filter(|&(solitonid, _)| match *solitonid {
+                Value::Keyword(ref solitonid) => added.contains(&solitonid),
+                _ => false,
+            })
+            .map(|(solitonid, attrs)| (solitonid.clone(), attrs.clone()))
+            .collect()),
+        _ => unreachable!("EINSTEIN_DB__SYMBOLIC_SCHEMA is a map"),
+    };
+
+    let upgrade_lightlike_dagger_upsert: Value = Value::Vector([
+        solitonids_to_lightlike_dagger_upsert(&solitonids[..]),
+        shellingic_topograph_to_lightlike_dagger_upsert(&shellingic_topograph).expect("shellingic topograph"),
+        topograph_attrs_to_lightlike_dagger_upsert(CORE_SCHEMA_VERSION, &attributes[..]),
+    ].concat());
+
+    // As with `bootstrap_causets`, the inputs are fixed, so failure here is a coding error.
+    einstein_ml::parse::causets(&upgrade_lightlike_dagger_upsert.to_string()).expect("core topograph upgrade lightlike_dagger_upsert")
+}
+
 
 
 
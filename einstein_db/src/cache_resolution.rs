@@ -0,0 +1,566 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#![allow(dead_code)]
+
+//! Answering patterns from the reverse attribute caches.
+//!
+//! A pattern like `[?e :person/email "x"]` over an attribute that is cached in reverse can be
+//! answered with a single map lookup: the cache maps each causet_locale straight to its causet(s).
+//! The algebrizer replaces each such pattern with a `ground` of the causets it matches, so the
+//! query planner never joins `causets` for it; `ReverseCacheResolver::substitute_patterns` does
+//! the rewriting.
+//!
+//! The causet_locale place may also be an `:in` variable, in which case it can be bound to a
+//! collection of causet_locales; the causets for each are unioned. Patterns that share `?e` are
+//! intersected. Anything we can't answer exactly -- other clause types, projected causet_locale
+//! variables, ordering, aggregates, causet_locales whose reverse entries were evicted -- falls back
+//! to the SQL path by returning `None`, or by being left as it is.
+
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use std::rc::Rc;
+
+use causetq::{
+    Binding,
+    Causetid,
+    causetq_TV,
+    causetq_VT,
+};
+use einstein_json::Json;
+use einstein_ml;
+use einstein_ml::{
+    Keyword,
+    PlainShelling,
+};
+use einstein_ml::query::{
+    Binding as FnBinding,
+    Element,
+    FindSpec,
+    FnArg,
+    Limit,
+    NonIntegerConstant,
+    ParsedQuery,
+    Pattern,
+    PatternNonValuePlace,
+    PatternValuePlace,
+    SrcVar,
+    TypeAnnotation,
+    Variable,
+    WhereClause,
+    WhereFn,
+};
+use einsteindb_core::{
+    CachedAttributes,
+    HasTopograph,
+    Topograph,
+};
+use einsteindb_core::cache::SQLiteAttributeCache;
+use einsteindb_transaction::query::{
+    QueryOutput,
+    QueryResults,
+    RelResult,
+};
+use public_traits::errors::Result;
+
+/// Convert a constant in the causet_locale place of a pattern to a causet_locale of type `causet_locale_type`.
+/// Returns `None` if the constant can't be a causet_locale of that type; the SQL path reports that
+/// (or returns nothing) with the usual semantics.
+pub fn constant_causet_locale(topograph: &Topograph, causet_locale_type: causetq_VT, place: &PatternValuePlace) -> Option<causetq_TV> {
+    match (causet_locale_type, place) {
+        (causetq_VT::Ref, &PatternValuePlace::CausetidOrInteger(x)) if x >= 0 => Some(causetq_TV::Ref(x)),
+        (causetq_VT::Ref, &PatternValuePlace::SolitonidOrKeyword(ref k)) =>
+            topograph.get_causetid(k).map(|e| causetq_TV::Ref(e.0)),
+        (causetq_VT::Long, &PatternValuePlace::CausetidOrInteger(x)) => Some(causetq_TV::Long(x)),
+        (causetq_VT::Double, &PatternValuePlace::CausetidOrInteger(x)) => Some(causetq_TV::Double((x as f64).into())),
+        (causetq_VT::Keyword, &PatternValuePlace::SolitonidOrKeyword(ref k)) => Some(causetq_TV::Keyword(k.clone())),
+        (causetq_VT::Boolean, &PatternValuePlace::Constant(NonIntegerConstant::Boolean(x))) => Some(causetq_TV::Boolean(x)),
+        (causetq_VT::Double, &PatternValuePlace::Constant(NonIntegerConstant::Float(x))) => Some(causetq_TV::Double(x)),
+        (causetq_VT::String, &PatternValuePlace::Constant(NonIntegerConstant::Text(ref s))) => Some(causetq_TV::String(s.clone())),
+        (causetq_VT::Instant, &PatternValuePlace::Constant(NonIntegerConstant::Instant(x))) => Some(causetq_TV::Instant(x)),
+        (causetq_VT::Uuid, &PatternValuePlace::Constant(NonIntegerConstant::Uuid(x))) => Some(causetq_TV::Uuid(x)),
+        (causetq_VT::Json, &PatternValuePlace::Constant(NonIntegerConstant::Text(ref s))) =>
+            s.parse::<Json>().ok().map(|j| causetq_TV::Json(j.into())),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ReverseCacheResolver<'s, 'c> {
+    topograph: &'s Topograph,
+    cache: &'c SQLiteAttributeCache,
+}
+
+impl<'s, 'c> ReverseCacheResolver<'s, 'c> {
+    pub fn new(topograph: &'s Topograph, cache: &'c SQLiteAttributeCache) -> ReverseCacheResolver<'s, 'c> {
+        ReverseCacheResolver {
+            topograph,
+            cache,
+        }
+    }
+
+    /// The attribute of `pattern`, if it is cached in reverse.
+    fn attribute(&self, place: &PatternNonValuePlace) -> Option<Causetid> {
+        let a = match place {
+            &PatternNonValuePlace::Causetid(a) => a,
+            &PatternNonValuePlace::Solitonid(ref k) => self.topograph.get_causetid(k)?.0,
+            _ => return None,
+        };
+        if self.cache.is_attribute_cached_reverse(a) {
+            Some(a)
+        } else {
+            None
+        }
+    }
+
+    /// The causet_locales the causet_locale place of a pattern stands for.
+    fn causet_locales(&self, causet_locale_type: causetq_VT, place: &PatternValuePlace, inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Option<Vec<causetq_TV>> {
+        match place {
+            &PatternValuePlace::Variable(ref var) => {
+                let bound = inputs.get(var)?;
+                // A causet_locale of the wrong type matches nothing; let SQL decide whether that's
+                // an error.
+                if bound.iter().any(|v| v.causet_locale_type() != causet_locale_type) {
+                    return None;
+                }
+                Some(bound.clone())
+            },
+            &PatternValuePlace::Placeholder => None,
+            constant => constant_causet_locale(self.topograph, causet_locale_type, constant).map(|v| vec![v]),
+        }
+    }
+
+    /// Resolve `[?e :attr v]` to the causets that have `v` for `:attr`, without touching SQL.
+    pub fn resolve_pattern(&self, pattern: &Pattern, inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Option<(Variable, BTreeSet<Causetid>)> {
+        match pattern.source {
+            None | Some(SrcVar::DefaultSrc) => {},
+            _ => return None,
+        }
+        if pattern.tx != PatternNonValuePlace::Placeholder {
+            return None;
+        }
+        let var = match pattern.causet {
+            PatternNonValuePlace::Variable(ref var) => var.clone(),
+            _ => return None,
+        };
+
+        let a = self.attribute(&pattern.attribute)?;
+        let attribute = self.topograph.attribute_for_causetid(a)?;
+        let causet_locales = self.causet_locales(attribute.causet_locale_type, &pattern.causet_locale, inputs)?;
+
+        // A miss is only authoritative for causet_locales that haven't lost entries to eviction.
+        if causet_locales.iter().any(|v| self.cache.is_causet_locale_evicted(a, v)) {
+            return None;
+        }
+
+        let mut causets = BTreeSet::new();
+        for v in causet_locales.iter() {
+            if attribute.unique.is_some() {
+                causets.extend(self.cache.get_causetid_for_causet_locale(a, v));
+            } else if let Some(es) = self.cache.get_causetids_for_causet_locale(a, v) {
+                causets.extend(es.iter().cloned());
+            }
+        }
+        Some((var, causets))
+    }
+
+    /// Resolve every clause, or none: each variable maps to the causets that satisfy all of the
+    /// patterns that mention it.
+    pub fn resolve_clauses(&self, clauses: &[WhereClause], inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Option<BTreeMap<Variable, BTreeSet<Causetid>>> {
+        let mut resolved: BTreeMap<Variable, BTreeSet<Causetid>> = BTreeMap::new();
+        for clause in clauses {
+            let (var, causets) = match clause {
+                &WhereClause::Pattern(ref pattern) => self.resolve_pattern(pattern, inputs)?,
+                _ => return None,
+            };
+            let existing = resolved.remove(&var);
+            let causets = match existing {
+                Some(existing) => existing.intersection(&causets).cloned().collect(),
+                None => causets,
+            };
+            resolved.insert(var, causets);
+        }
+        Some(resolved)
+    }
+
+    /// Replace each pattern the reverse caches can resolve with a `ground` of the causets it
+    /// matches, typed as refs. `inputs` are the causet_locales bound to `:in` variables; a pattern
+    /// over an unbound variable, or that can't be resolved exactly, is left for SQL.
+    pub fn substitute_patterns(&self, clauses: Vec<WhereClause>, inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Vec<WhereClause> {
+        let mut substituted = Vec::with_capacity(clauses.len());
+        for clause in clauses {
+            let resolved = match clause {
+                WhereClause::Pattern(ref pattern) => self.resolve_pattern(pattern, inputs),
+                _ => None,
+            };
+            match resolved {
+                Some((var, causets)) => {
+                    substituted.push(WhereClause::TypeAnnotation(TypeAnnotation {
+                        causet_locale_type: Keyword::namespaced("einsteindb.type", "ref"),
+                        variable: var.clone(),
+                    }));
+                    substituted.push(WhereClause::WhereFn(WhereFn {
+                        operator: PlainShelling::plain("ground"),
+                        args: vec![FnArg::Vector(causets.into_iter().map(FnArg::CausetidOrInteger).collect())],
+                        binding: FnBinding::BindColl(var),
+                    }));
+                },
+                None => substituted.push(clause),
+            }
+        }
+        substituted
+    }
+
+    /// Answer `query` entirely from the reverse caches, or return `None` if it needs SQL.
+    pub fn answer(&self, query: &ParsedQuery, inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Option<QueryOutput> {
+        if query.order.is_some() || !query.with.is_empty() || !query.in_sources.is_empty() {
+            return None;
+        }
+        if query.in_vars.iter().any(|var| !inputs.contains_soliton_id(var)) {
+            return None;
+        }
+        let limit = match query.limit {
+            Limit::None => None,
+            Limit::Fixed(n) => Some(n as usize),
+            Limit::Variable(_) => return None,
+        };
+
+        let resolved = self.resolve_clauses(&query.where_clauses, inputs)?;
+
+        // Only the causets we resolved can be projected.
+        let mut columns: Vec<&BTreeSet<Causetid>> = vec![];
+        for element in query.find_spec.columns() {
+            match element {
+                &Element::Variable(ref var) => columns.push(resolved.get(var)?),
+                _ => return None,
+            }
+        }
+
+        // Patterns on distinct variables don't constrain each other, so the relation is the
+        // product of the columns. An empty column empties the whole result.
+        let mut rows: Vec<Vec<Causetid>> = vec![vec![]];
+        for column in columns.iter() {
+            rows = rows.into_iter()
+                       .flat_map(|row| column.iter().map(move |e| {
+                           let mut row = row.clone();
+                           row.push(*e);
+                           row
+                       }))
+                       .collect();
+        }
+        if let Some(limit) = limit {
+            rows.truncate(limit);
+        }
+
+        let bind = |e: Causetid| -> Binding { causetq_TV::Ref(e).into() };
+        let results = match query.find_spec {
+            FindSpec::FindScalar(_) =>
+                QueryResults::Scalar(rows.into_iter().next().map(|row| bind(row[0]))),
+            FindSpec::FindColl(_) =>
+                QueryResults::Coll(rows.into_iter().map(|row| bind(row[0])).collect()),
+            FindSpec::FindTuple(_) =>
+                QueryResults::Tuple(rows.into_iter().next().map(|row| row.into_iter().map(bind).collect())),
+            FindSpec::FindRel(ref elements) =>
+                QueryResults::Rel(RelResult {
+                    width: elements.len(),
+                    values: rows.into_iter().flat_map(|row| row.into_iter()).map(bind).collect(),
+                }),
+        };
+
+        Some(QueryOutput {
+            spec: Rc::new(query.find_spec.clone()),
+            results,
+        })
+    }
+}
+
+/// Parse `query` and try to answer it from the reverse caches. A query that doesn't parse is
+/// left for the SQL path to report.
+pub fn answer_from_reverse_cache(topograph: &Topograph,
+                                 cache: &SQLiteAttributeCache,
+                                 query: &str,
+                                 inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Result<Option<QueryOutput>> {
+    if !cache.has_cached_attributes() {
+        return Ok(None);
+    }
+    let parsed = match einstein_ml::parse::query(query) {
+        Ok(parsed) => parsed,
+        Err(_) => return Ok(None),
+    };
+    Ok(ReverseCacheResolver::new(topograph, cache).answer(&parsed, inputs))
+}
+
+/// The [a e] entries that `query` names with a constant causet and attribute and that have been
+/// evicted from the lightlike caches. The general query path takes a cache miss to mean "no
+/// causet_locale", so these are read back from the store before it runs.
+pub fn evicted_entries_named_by(topograph: &Topograph, cache: &SQLiteAttributeCache, query: &str) -> Vec<(Causetid, Causetid)> {
+    let parsed = match einstein_ml::parse::query(query) {
+        Ok(parsed) => parsed,
+        Err(_) => return vec![],
+    };
+    parsed.where_clauses.iter()
+          .filter_map(|clause| match clause {
+              &WhereClause::Pattern(ref pattern) => {
+                  let e = match pattern.causet {
+                      PatternNonValuePlace::Causetid(e) => e,
+                      _ => return None,
+                  };
+                  let a = match pattern.attribute {
+                      PatternNonValuePlace::Causetid(a) => a,
+                      PatternNonValuePlace::Solitonid(ref k) => topograph.get_causetid(k)?.0,
+                      _ => return None,
+                  };
+                  Some((a, e))
+              },
+              _ => None,
+          })
+          .filter(|&(a, e)| cache.is_causet_evicted(a, e))
+          .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::conn::Conn;
+    use einsteindb_core::cache::AttributeCacheBudget;
+    use einsteindb_core::einsteindb;
+    use einsteindb_core::Keyword;
+    use einsteindb_transaction::{
+        CacheAction,
+        CacheDirection,
+    };
+    use einsteindb_transaction::query::QueryInputs;
+    use rusqlite;
+
+    #[test]
+    fn test_constant_causet_locale() {
+        let topograph = Topograph::default();
+
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::String, &PatternValuePlace::Constant("x".into())),
+                   Some(causetq_TV::typed_string("x")));
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Long, &PatternValuePlace::CausetidOrInteger(-5)),
+                   Some(causetq_TV::Long(-5)));
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Ref, &PatternValuePlace::CausetidOrInteger(65536)),
+                   Some(causetq_TV::Ref(65536)));
+
+        // Negative integers can't be causets, and strings can't be longs.
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Ref, &PatternValuePlace::CausetidOrInteger(-1)), None);
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Long, &PatternValuePlace::Constant("x".into())), None);
+
+        // JSON is written as its text; text that isn't JSON matches nothing.
+        let json: Json = r#"{"a": 1}"#.parse().unwrap();
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Json, &PatternValuePlace::Constant(r#"{"a":1}"#.into())),
+                   Some(causetq_TV::Json(json.into())));
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Json, &PatternValuePlace::Constant("{".into())), None);
+
+        // An unknown solitonid doesn't name a causet.
+        let unknown = PatternValuePlace::from(Keyword::namespaced("foo", "bar"));
+        assert_eq!(constant_causet_locale(&topograph, causetq_VT::Ref, &unknown), None);
+    }
+
+    /// People with a unique email and a non-unique team, both cached in reverse.
+    fn people() -> (rusqlite::Connection, Conn, BTreeMap<&'static str, Causetid>) {
+        let mut sqlite = einsteindb::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+        conn.transact(&mut sqlite, r#"[
+            {:einsteindb/solitonid :person/email
+             :einsteindb/causet_localeType :einsteindb.type/string
+             :einsteindb/unique :einsteindb.unique/causet_locale
+             :einsteindb/Index true
+             :einsteindb/cardinality :einsteindb.cardinality/one}
+            {:einsteindb/solitonid :person/team
+             :einsteindb/causet_localeType :einsteindb.type/long
+             :einsteindb/cardinality :einsteindb.cardinality/many}
+            {:einsteindb/solitonid :person/name
+             :einsteindb/causet_localeType :einsteindb.type/string
+             :einsteindb/cardinality :einsteindb.cardinality/one}]"#).expect("topograph");
+        let report = conn.transact(&mut sqlite, r#"[
+            {:einsteindb/id "ann" :person/email "ann@x" :person/team [1 2] :person/name "Ann"}
+            {:einsteindb/id "bob" :person/email "bob@x" :person/team 1 :person/name "Bob"}
+            {:einsteindb/id "cat" :person/email "cat@x" :person/team 3 :person/name "Cat"}]"#).expect("people");
+
+        let mut people = BTreeMap::new();
+        for name in &["ann", "bob", "cat"] {
+            people.insert(*name, report.tempids[*name]);
+        }
+
+        let topograph = conn.current_schema();
+        for attribute in &[Keyword::namespaced("person", "email"), Keyword::namespaced("person", "team")] {
+            conn.cache(&mut sqlite, &topograph, attribute, CacheDirection::Reverse, CacheAction::Register).expect("cached");
+        }
+        (sqlite, conn, people)
+    }
+
+    /// The rows of `results`, in an order that doesn't depend on how they were found.
+    fn rows(results: QueryResults) -> BTreeSet<Vec<Causetid>> {
+        let causetid = |binding: Binding| match binding {
+            Binding::Scalar(causetq_TV::Ref(e)) => e,
+            other => panic!("expected a causet, got {:?}", other),
+        };
+        match results {
+            QueryResults::Scalar(binding) => binding.into_iter().map(|b| vec![causetid(b)]).collect(),
+            QueryResults::Tuple(bindings) => bindings.into_iter().map(|bs| bs.into_iter().map(causetid).collect()).collect(),
+            QueryResults::Coll(bindings) => bindings.into_iter().map(|b| vec![causetid(b)]).collect(),
+            QueryResults::Rel(rel) => {
+                let width = rel.width;
+                let values: Vec<Causetid> = rel.values.into_iter().map(causetid).collect();
+                values.chunks(width).map(|row| row.to_vec()).collect()
+            },
+        }
+    }
+
+    /// Answer `query` from the reverse caches, and check that SQL agrees.
+    fn assert_cache_matches_sql(sqlite: &rusqlite::Connection, conn: &Conn, query: &str) -> BTreeSet<Vec<Causetid>> {
+        let topograph = conn.current_schema();
+        let cache = conn.current_cache();
+        let cached = answer_from_reverse_cache(&topograph, &cache, query, &BTreeMap::new())
+            .expect("answered")
+            .expect("answered from the cache");
+        let uncached = conn.q_uncached(conn, sqlite, query, None).expect("answered by SQL");
+        let cached = rows(cached.results);
+        assert_eq!(cached, rows(uncached.results), "for {}", query);
+
+        // `q_once` grounds the patterns from the cache, and gives the same answer.
+        let once = conn.q_once(sqlite, query, None).expect("answered");
+        assert_eq!(rows(once.results), cached, "for {}", query);
+        cached
+    }
+
+    #[test]
+    fn test_reverse_cache_matches_sql() {
+        let (sqlite, conn, people) = people();
+        let (ann, bob, cat) = (people["ann"], people["bob"], people["cat"]);
+
+        // Unique attributes.
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, r#"[:find ?e . :where [?e :person/email "bob@x"]]"#),
+                   vec![vec![bob]].into_iter().collect());
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, r#"[:find ?e . :where [?e :person/email "nobody@x"]]"#),
+                   BTreeSet::new());
+
+        // Non-unique attributes, and several causet_locales of a multi-valued attribute.
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, "[:find [?e ...] :where [?e :person/team 1]]"),
+                   vec![vec![ann], vec![bob]].into_iter().collect());
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, "[:find [?e ...] :where [?e :person/team 2]]"),
+                   vec![vec![ann]].into_iter().collect());
+
+        // Patterns on the same variable intersect.
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, r#"[:find [?e ...] :where [?e :person/team 1] [?e :person/email "ann@x"]]"#),
+                   vec![vec![ann]].into_iter().collect());
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, r#"[:find [?e ...] :where [?e :person/team 3] [?e :person/email "ann@x"]]"#),
+                   BTreeSet::new());
+
+        // Patterns on different variables multiply.
+        assert_eq!(assert_cache_matches_sql(&sqlite, &conn, "[:find ?e ?f :where [?e :person/team 1] [?f :person/team 3]]"),
+                   vec![vec![ann, cat], vec![bob, cat]].into_iter().collect());
+    }
+
+    #[test]
+    fn test_resolve_clauses_with_inputs() {
+        let (sqlite, conn, people) = people();
+        let topograph = conn.current_schema();
+        let cache = conn.current_cache();
+        let resolver = ReverseCacheResolver::new(&topograph, &cache);
+
+        let query = "[:find [?e ...] :in ?team :where [?e :person/team ?team]]";
+        let parsed = einstein_ml::parse::query(query).expect("parsed");
+        let team = Variable::from_valid_name("?team");
+
+        // A collection of causet_locales is the union of what SQL finds for each of them.
+        let mut inputs = BTreeMap::new();
+        inputs.insert(team.clone(), vec![causetq_TV::Long(2), causetq_TV::Long(3)]);
+        let resolved = resolver.resolve_clauses(&parsed.where_clauses, &inputs).expect("resolved");
+
+        let mut from_sql = BTreeSet::new();
+        for v in &[2, 3] {
+            let uncached = conn.q_uncached(&conn, &sqlite, format!("[:find [?e ...] :where [?e :person/team {}]]", v).as_str(), None)
+                               .expect("answered by SQL");
+            from_sql.extend(rows(uncached.results).into_iter().map(|row| row[0]));
+        }
+        assert_eq!(resolved[&Variable::from_valid_name("?e")], from_sql);
+        assert_eq!(from_sql, vec![people["ann"], people["cat"]].into_iter().collect());
+
+        let answered = conn.q_reverse_cached(query, &inputs).expect("answered").expect("answered from the cache");
+        assert_eq!(rows(answered.results).into_iter().map(|row| row[0]).collect::<BTreeSet<_>>(), from_sql);
+
+        // An input of the wrong type is left for SQL.
+        inputs.insert(team, vec![causetq_TV::typed_string("2")]);
+        assert!(resolver.resolve_clauses(&parsed.where_clauses, &inputs).is_none());
+    }
+
+    #[test]
+    fn test_substitute_patterns_bound_through_in() {
+        let (sqlite, conn, people) = people();
+        let topograph = conn.current_schema();
+        let cache = conn.current_cache();
+        let resolver = ReverseCacheResolver::new(&topograph, &cache);
+
+        let query = "[:find [?e ...] :in ?team :where [?e :person/team ?team] [?e :person/name _]]";
+        let parsed = einstein_ml::parse::query(query).expect("parsed");
+        let (e, team) = (Variable::from_valid_name("?e"), Variable::from_valid_name("?team"));
+
+        // Unbound, the pattern is left for SQL, as is the one over an uncached attribute.
+        assert_eq!(resolver.substitute_patterns(parsed.where_clauses.clone(), &BTreeMap::new()), parsed.where_clauses);
+
+        // Bound through `:in`, it becomes a `ground` of the causets the cache names.
+        let mut inputs = BTreeMap::new();
+        inputs.insert(team.clone(), vec![causetq_TV::Long(1)]);
+        let substituted = resolver.substitute_patterns(parsed.where_clauses.clone(), &inputs);
+        assert_eq!(substituted.len(), 3);
+        assert_eq!(substituted[0], WhereClause::TypeAnnotation(TypeAnnotation {
+            causet_locale_type: Keyword::namespaced("einsteindb.type", "ref"),
+            variable: e.clone(),
+        }));
+        assert_eq!(substituted[1], WhereClause::WhereFn(WhereFn {
+            operator: PlainShelling::plain("ground"),
+            args: vec![FnArg::Vector(vec![FnArg::CausetidOrInteger(people["ann"]), FnArg::CausetidOrInteger(people["bob"])])],
+            binding: FnBinding::BindColl(e),
+        }));
+        assert_eq!(substituted[2], parsed.where_clauses[1]);
+
+        // `q_once` binds `:in` through `QueryInputs`, and agrees with SQL.
+        let inputs = || QueryInputs::with_causet_locale_sequence(vec![(team.clone(), causetq_TV::Long(1).into())]);
+        let once = rows(conn.q_once(&sqlite, query, inputs()).expect("answered").results);
+        let uncached = rows(conn.q_uncached(&conn, &sqlite, query, inputs()).expect("answered by SQL").results);
+        assert_eq!(once, uncached);
+        assert_eq!(once, vec![vec![people["ann"]], vec![people["bob"]]].into_iter().collect());
+    }
+
+    #[test]
+    fn test_not_reverse_complete_falls_back_to_sql() {
+        let (mut sqlite, mut conn, people) = people();
+        let query = "[:find [?e ...] :where [?e :person/team 1]]";
+        let uncached = rows(conn.q_uncached(&conn, &sqlite, query, None).expect("answered by SQL").results);
+
+        // An attribute that's only cached lightlike isn't answered from the reverse caches.
+        let topograph = conn.current_schema();
+        conn.cache(&mut sqlite, &topograph, &Keyword::namespaced("person", "name"), CacheDirection::Lightlike, CacheAction::Register).expect("cached");
+        let name_query = r#"[:find ?e . :where [?e :person/name "Cat"]]"#;
+        assert!(answer_from_reverse_cache(&topograph, &conn.current_cache(), name_query, &BTreeMap::new()).expect("answered").is_none());
+        assert_eq!(rows(conn.q_once(&sqlite, name_query, None).expect("answered").results),
+                   vec![vec![people["cat"]]].into_iter().collect());
+
+        // Once a causet_locale's entries have been evicted, a miss in the reverse cache no longer
+        // means there's no such causet, so neither the resolver nor `q_once` trusts it.
+        conn.set_cache_budget(AttributeCacheBudget { per_attribute: Some(1), total: None });
+        let cache = conn.current_cache();
+        let team = topograph.get_causetid(&Keyword::namespaced("person", "team")).expect("team").0;
+        assert!(cache.is_attribute_cached_reverse(team));
+        assert!(cache.is_causet_locale_evicted(team, &causetq_TV::Long(1)));
+
+        let parsed = einstein_ml::parse::query(query).expect("parsed");
+        assert!(ReverseCacheResolver::new(&topograph, &cache).resolve_clauses(&parsed.where_clauses, &BTreeMap::new()).is_none());
+        assert!(answer_from_reverse_cache(&topograph, &cache, query, &BTreeMap::new()).expect("answered").is_none());
+        assert_eq!(rows(conn.q_once(&sqlite, query, None).expect("answered").results), uncached);
+        assert_eq!(uncached, vec![vec![people["ann"]], vec![people["bob"]]].into_iter().collect());
+    }
+}
@@ -70,6 +70,17 @@ pub const EINSTEINDB_SCHEMA_CORE_VALUE_TYPE: Causetid = 42;
 pub const EINSTEINDB_SCHEMA_CORE_CARDINALITY: Causetid = 43;
 pub const EINSTEINDB_SCHEMA_CORE_UNIQUE: Causetid = 44;
 
+// Added for datom expiry. `:einsteindb/ttl` asserted on an attribute is the default TTL for that
+// attribute; asserted on any other causet it is the TTL of that causet's datoms, in seconds.
+pub const EINSTEINDB_TTL: Causetid = 45;
+
+// Added for JSON causet_locales: `:einsteindb.type/json`, stored as einstein_json binary JSON.
+pub const EINSTEINDB_TYPE_JSON: Causetid = 46;
+
+// Added for JSON causet_locales: `:einsteindb/jsonSchema` asserted on a JSON attribute is the text
+// of the JSON Schema its causet_locales must conform to.
+pub const EINSTEINDB_JSON_SCHEMA: Causetid = 47;
+
 /// Return `false` if the given attribute will not change the spacetime: recognized solitonids, topograph,
 /// partitions in the partition map.
 pub fn might_update_spacetime(attribute: Causetid) -> bool {
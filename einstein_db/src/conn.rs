@@ -46,10 +46,14 @@ use einsteindb_core::{
     TxObserver,
 };
 use einsteindb_core::cache::{
+    AttributeCacheBudget,
+    AttributeCacheStats,
+    CacheSnapshotOrigin,
     InProgressSQLiteAttributeCache,
     SQLiteAttributeCache,
 };
 use einsteindb_core::einsteindb;
+use einstein_ml::query::Variable;
 use einsteindb_query_pull::{
     pull_attributes_for_causet,
     pull_attributes_for_causets,
@@ -87,6 +91,13 @@ use std::sync::{
     Mutex,
 };
 
+use crate::causetids;
+use crate::cache_resolution::{
+    answer_from_reverse_cache,
+    evicted_entries_named_by,
+};
+use crate::json_query::answer_json_query;
+
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CausetLocaleNucleonCausetid {
@@ -297,12 +308,35 @@ impl Conn {
         let mut spacetime = conn.spacetime.lock().unwrap();
         let query_inputs = inputs.into();
 
-        // Doesn't clone, unlike `current_schema`.
-        let causet_locale_nucleon = CausetLocaleNucleon::new(&*spacetime.schema, Some(&spacetime.attribute_cache));
+        // The general query path doesn't know `json-extract`.
+        if let Some(output) = answer_json_query(&*spacetime.schema, sqlite, query)? {
+            return Ok(output);
+        }
+
+        // The general query path trusts cache misses, so read back the evicted entries it names.
+        {
+            let spacetime = &mut *spacetime;
+            for (a, e) in evicted_entries_named_by(&*spacetime.schema, &spacetime.attribute_cache, query) {
+                spacetime.attribute_cache.fetch_if_evicted(&*spacetime.schema, sqlite, a, e)?;
+            }
+        }
+
+        // Doesn't clone, unlike `current_schema`. The algebrizer answers what it can of the
+        // query from the reverse caches.
+        let causet_locale_nucleon = CausetLocaleNucleon::with_reverse_cache(&*spacetime.schema, &spacetime.attribute_cache);
         q_once(sqlite,
                causet_locale_nucleon,
                query,
-               inputs)
+               query_inputs)
+    }
+
+    /// Answer `query` from the reverse attribute caches alone, binding each `:in` variable to
+    /// one or more causet_locales. Returns `None` if the query can't be answered without SQL.
+    pub fn q_reverse_cached(&self,
+                            query: &str,
+                            inputs: &BTreeMap<Variable, Vec<causetq_TV>>) -> Result<Option<QueryOutput>> {
+        let spacetime = self.spacetime.lock().unwrap();
+        answer_from_reverse_cache(&*spacetime.schema, &spacetime.attribute_cache, query, inputs)
     }
 
     /// Query the einsteindb store, using the given connection and the current spacetime,
@@ -317,6 +351,9 @@ impl Conn {
         where T: Into<Option<QueryInputs>> {
 
         let spacetime = self.spacetime.lock().unwrap();
+        if let Some(output) = answer_json_query(&*spacetime.schema, sqlite, query)? {
+            return Ok(output);
+        }
         q_uncached(sqlite,
                      &spacetime.schema,
 
@@ -415,7 +452,8 @@ impl Conn {
                                        SQLite: &rusqlite::Connection,
                                        causet: Causetid,
                                        attribute: &einstein_ml::Keyword) -> Result<Vec<causetq_TV>> {
-        let spacetime = self.spacetime.lock().unwrap();
+        let mut spacetime = self.spacetime.lock().unwrap();
+        Conn::fetch_evicted(&mut *spacetime, SQLite, causet, attribute)?;
         let CausetLocaleNucleon = CausetLocaleNucleon::new(&*spacetime.schema, Some(&spacetime.attribute_cache));
         lookup_causet_locales_for_attribute(SQLite, CausetLocaleNucleon, causet, attribute)
     }
@@ -424,11 +462,22 @@ impl Conn {
                                       SQLite: &rusqlite::Connection,
                                       causet: Causetid,
                                       attribute: &einstein_ml::Keyword) -> Result<Option<causetq_TV>> {
-        let spacetime = self.spacetime.lock().unwrap();
+        let mut spacetime = self.spacetime.lock().unwrap();
+        Conn::fetch_evicted(&mut *spacetime, SQLite, causet, attribute)?;
         let CausetLocaleNucleon = CausetLocaleNucleon::new(&*spacetime.schema, Some(&spacetime.attribute_cache));
         lookup_causet_locale_for_attribute(SQLite, CausetLocaleNucleon, causet, attribute)
     }
 
+    /// Read `causet`'s causet_locales for `attribute` back into the attribute cache if they were
+    /// evicted, so that a lookup through the cache doesn't take the eviction for a miss.
+    fn fetch_evicted(spacetime: &mut Spacetime, SQLite: &rusqlite::Connection, causet: Causetid, attribute: &einstein_ml::Keyword) -> Result<()> {
+        let a = match spacetime.schema.get_causetid(attribute) {
+            Some(a) => a.0,
+            None => return Ok(()),
+        };
+        spacetime.attribute_cache.fetch_if_evicted(&*spacetime.schema, SQLite, a, causet).map_err(|e| e.into())
+    }
+
     /// Take a sqlite transaction.
     fn begin_transaction_with_behavior<'m, 'conn>(&'m mut self, SQLite: &'conn mut rusqlite::Connection, behavior: TransactionBehavior) -> Result<InProgress<'m, 'conn>> {
         let tx = SQLite.transaction_with_behavior(behavior)?;
@@ -535,6 +584,75 @@ impl Conn {
         }
     }
 
+    /// Where the attribute cache snapshot of the store at `store_path` is kept: beside the store,
+    /// with a `.cache` suffix. In-memory stores have nowhere to keep one.
+    pub fn cache_snapshot_path(store_path: &str) -> Option<::std::path::PathBuf> {
+        if store_path.is_empty() {
+            None
+        } else {
+            Some(::std::path::PathBuf::from(format!("{}.cache", store_path)))
+        }
+    }
+
+    /// Identify the store behind `sqlite`, as of `last_tx`, for keying attribute cache snapshots.
+    fn cache_snapshot_origin(sqlite: &rusqlite::Connection, last_tx: Causetid) -> Result<CacheSnapshotOrigin> {
+        // The instant of the first transaction, which bootstrapped the store.
+        let store: i64 = sqlite.query_row("SELECT v FROM causets WHERE a = ? ORDER BY e ASC LIMIT 1",
+                                          &[&causetids::EINSTEINDB_TX_INSTANT],
+                                          |event| event.get(0))?;
+        let topograph: String = sqlite.query_row(r#"
+            SELECT COALESCE(group_concat(r, ';'), '')
+            FROM (SELECT e || ' ' || a || ' ' || causet_locale_type_tag || ' ' || hex(v) AS r
+                  FROM topograph
+                  ORDER BY e, a, causet_locale_type_tag, v)"#,
+                                                 &[],
+                                                 |event| event.get(0))?;
+        Ok(CacheSnapshotOrigin {
+            store,
+            topograph,
+            last_tx,
+        })
+    }
+
+    /// Write the current attribute cache to `path`, so that a later `warm_cache` of the same store
+    /// can skip rebuilding it from the store.
+    pub fn write_cache_snapshot<P>(&self, sqlite: &rusqlite::Connection, path: P) -> Result<()> where P: AsRef<::std::path::Path> {
+        // Take the cache and the last tx together, so the snapshot is consistent.
+        let spacetime = self.spacetime.lock().unwrap();
+        let last_tx = spacetime.partition_map[":einsteindb.part/tx"].next_causetid() - 1;
+        let origin = Conn::cache_snapshot_origin(sqlite, last_tx)?;
+        spacetime.attribute_cache.write_snapshot(path, &origin).map_err(|e| e.into())
+    }
+
+    /// Replace the attribute cache with the snapshot at `path`, brought up to date with the
+    /// transactions committed since it was written.
+    ///
+    /// Returns `false`, leaving the cache untouched, if there is no usable snapshot: snapshots of
+    /// other stores, or of this store under another topograph, are not used.
+    pub fn warm_cache<P>(&mut self, sqlite: &rusqlite::Connection, path: P) -> Result<bool> where P: AsRef<::std::path::Path> {
+        let mut spacetime = self.spacetime.lock().unwrap();
+        let last_tx = spacetime.partition_map[":einsteindb.part/tx"].next_causetid() - 1;
+        let store = Conn::cache_snapshot_origin(sqlite, last_tx)?;
+        match SQLiteAttributeCache::read_snapshot(&spacetime.schema, sqlite, path, &store)? {
+            Some(mut cache) => {
+                cache.set_budget(spacetime.attribute_cache.budget());
+                spacetime.attribute_cache = cache;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    /// Bound the memory used by the attribute cache. Entries over budget are evicted and, when
+    /// looked up again, read back from the store.
+    pub fn set_cache_budget(&mut self, budget: AttributeCacheBudget) {
+        self.spacetime.lock().unwrap().attribute_cache.set_budget(budget);
+    }
+
+    pub fn cache_stats(&self) -> AttributeCacheStats {
+        self.spacetime.lock().unwrap().attribute_cache.stats()
+    }
+
     pub fn register_observer(&mut self, soliton_id: String, observer: Arc<TxObserver>) {
         self.tx_observer_service.lock().unwrap().register(soliton_id, observer);
     }
@@ -563,6 +681,7 @@ mod tests {
 
     use super::*;
 
+    extern crate tempfile;
     extern crate time;
 
     #[test]
@@ -940,4 +1059,255 @@ mod tests {
         assert!(conn.current_cache().is_attribute_cached_lightlike(einsteindb_solitonid));
         assert!(conn.current_cache().is_attribute_cached_lightlike(einsteindb_type));
     }
+
+    fn evicted_store() -> (rusqlite::Connection, Conn, Causetid, Vec<(Causetid, i64)>) {
+        let mut SQLite = einsteindb::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut SQLite).unwrap();
+        conn.transact(&mut SQLite, r#"[
+            {  :einsteindb/solitonid       :foo/bar
+               :einsteindb/causet_localeType   :einsteindb.type/long
+               :einsteindb/cardinality :einsteindb.cardinality/one }]"#).expect("transaction expected to succeed");
+        let bar = conn.current_schema().get_causetid(&kw!(:foo/bar)).expect("foo/bar").0;
+
+        let mut causets = vec![];
+        for v in 0..10 {
+            let report = conn.transact(&mut SQLite, format!(r#"[[:einsteindb/add "e" :foo/bar {}]]"#, v).as_str()).expect("transaction expected to succeed");
+            causets.push((report.tempids["e"], v));
+        }
+
+        let schema = conn.current_schema();
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Register).expect("registered");
+        assert!(conn.current_cache().is_attribute_cached_lightlike(bar));
+
+        // No entry fits in a byte, so every one is evicted.
+        conn.set_cache_budget(AttributeCacheBudget { per_attribute: Some(1), total: None });
+        assert_eq!(conn.cache_stats().evictions, causets.len());
+        (SQLite, conn, bar, causets)
+    }
+
+    #[test]
+    fn test_lookup_after_eviction() {
+        let (SQLite, conn, bar, causets) = evicted_store();
+
+        // The attribute is still cached, but misses on the evicted causets aren't authoritative...
+        let cache = conn.current_cache();
+        assert!(cache.is_attribute_cached_lightlike(bar));
+        assert!(causets.iter().all(|&(e, _)| cache.is_causet_evicted(bar, e)));
+
+        // ... so lookups and queries read those causets from the store instead.
+        for &(e, v) in causets.iter() {
+            assert_eq!(conn.lookup_causet_locale_for_attribute(&SQLite, e, &kw!(:foo/bar)).expect("lookup"),
+                       Some(causetq_TV::Long(v)));
+        }
+        let (e, _) = causets[3];
+        let found = conn.q_once(&SQLite, format!("[:find ?v . :where [{} :foo/bar ?v]]", e).as_str(), None)
+                        .into_scalar_result()
+                        .expect("query");
+        assert_eq!(found, Some(causetq_TV::Long(3).into()));
+    }
+
+    #[test]
+    fn test_fetch_after_eviction() {
+        let (SQLite, conn, bar, causets) = evicted_store();
+        let schema = conn.current_schema();
+        let mut cache = conn.current_cache();
+
+        // The budget can't hold even the fetched entry; it is pinned while it is read.
+        for &(e, v) in causets.iter() {
+            assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, e), None);
+            assert_eq!(cache.get_causet_locale_for_causetid_or_fetch(&schema, &SQLite, bar, e).expect("fetched"),
+                       Some(causetq_TV::Long(v)));
+        }
+        assert!(cache.stats().fallbacks >= causets.len());
+
+        // Causets without a causet_locale are still misses.
+        assert_eq!(cache.get_causet_locale_for_causetid_or_fetch(&schema, &SQLite, bar, bar).expect("fetched"), None);
+    }
+
+    fn snapshot_store(dir: &::std::path::Path) -> (String, rusqlite::Connection, Conn, Causetid) {
+        let path = dir.join("store.einsteindb").to_str().expect("utf-8 path").to_string();
+        let mut SQLite = einsteindb::new_connection(&path).unwrap();
+        let mut conn = Conn::connect(&mut SQLite).unwrap();
+        conn.transact(&mut SQLite, r#"[
+            {  :einsteindb/solitonid       :foo/bar
+               :einsteindb/causet_localeType   :einsteindb.type/long
+               :einsteindb/cardinality :einsteindb.cardinality/one }]"#).expect("transaction expected to succeed");
+        let bar = conn.current_schema().get_causetid(&kw!(:foo/bar)).expect("foo/bar").0;
+        (path, SQLite, conn, bar)
+    }
+
+    #[test]
+    fn test_cache_snapshot_round_trip() {
+        let dir = tempfile::TempDir::new().expect("tempdir");
+        let (path, mut SQLite, mut conn, bar) = snapshot_store(dir.path());
+        let report = conn.transact(&mut SQLite, r#"[[:einsteindb/add "a" :foo/bar 1]
+                                                    [:einsteindb/add "b" :foo/bar 2]]"#).expect("transaction expected to succeed");
+        let (a, b) = (report.tempids["a"], report.tempids["b"]);
+        let schema = conn.current_schema();
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Both, CacheAction::Register).expect("registered");
+
+        let snapshot = Conn::cache_snapshot_path(&path).expect("snapshot path");
+        conn.write_cache_snapshot(&SQLite, &snapshot).expect("written");
+
+        let mut reopened_sqlite = einsteindb::new_connection(&path).unwrap();
+        let mut reopened = Conn::connect(&mut reopened_sqlite).unwrap();
+        assert!(!reopened.current_cache().is_attribute_cached_lightlike(bar));
+        assert_eq!(reopened.warm_cache(&reopened_sqlite, &snapshot).expect("warmed"), true);
+
+        let schema = reopened.current_schema();
+        let cache = reopened.current_cache();
+        assert!(cache.is_attribute_cached_lightlike(bar));
+        assert!(cache.is_attribute_cached_reverse(bar));
+        assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, a), Some(&causetq_TV::Long(1)));
+        assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, b), Some(&causetq_TV::Long(2)));
+        assert_eq!(cache.get_causetid_for_causet_locale(bar, &causetq_TV::Long(2)), Some(b));
+    }
+
+    #[test]
+    fn test_cache_snapshot_replays_later_transactions() {
+        let dir = tempfile::TempDir::new().expect("tempdir");
+        let (path, mut SQLite, mut conn, bar) = snapshot_store(dir.path());
+        let report = conn.transact(&mut SQLite, r#"[[:einsteindb/add "a" :foo/bar 1]
+                                                    [:einsteindb/add "b" :foo/bar 2]]"#).expect("transaction expected to succeed");
+        let (a, b) = (report.tempids["a"], report.tempids["b"]);
+        let schema = conn.current_schema();
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Register).expect("registered");
+
+        let snapshot = Conn::cache_snapshot_path(&path).expect("snapshot path");
+        conn.write_cache_snapshot(&SQLite, &snapshot).expect("written");
+
+        // Change the store after the snapshot was written: a new causet, a changed causet_locale
+        // and a retraction.
+        let report = conn.transact(&mut SQLite, format!(r#"[[:einsteindb/add "c" :foo/bar 3]
+                                                           [:einsteindb/add {} :foo/bar 10]
+                                                           [:einsteindb/retract {} :foo/bar 2]]"#, a, b).as_str()).expect("transaction expected to succeed");
+        let c = report.tempids["c"];
+
+        let mut reopened_sqlite = einsteindb::new_connection(&path).unwrap();
+        let mut reopened = Conn::connect(&mut reopened_sqlite).unwrap();
+        assert_eq!(reopened.warm_cache(&reopened_sqlite, &snapshot).expect("warmed"), true);
+
+        let schema = reopened.current_schema();
+        let cache = reopened.current_cache();
+        assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, a), Some(&causetq_TV::Long(10)));
+        assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, b), None);
+        assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, c), Some(&causetq_TV::Long(3)));
+    }
+
+    #[test]
+    fn test_cache_snapshot_keeps_evictions() {
+        let dir = tempfile::TempDir::new().expect("tempdir");
+        let (path, mut SQLite, mut conn, bar) = snapshot_store(dir.path());
+        let mut causets = vec![];
+        for v in 0..10 {
+            let report = conn.transact(&mut SQLite, format!(r#"[[:einsteindb/add "e" :foo/bar {}]]"#, v).as_str()).expect("transaction expected to succeed");
+            causets.push((report.tempids["e"], v));
+        }
+        let schema = conn.current_schema();
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Both, CacheAction::Register).expect("registered");
+
+        // Room for about half of the causets.
+        conn.set_cache_budget(AttributeCacheBudget { per_attribute: Some(500), total: None });
+        let cache = conn.current_cache();
+        let (evicted, kept): (Vec<(Causetid, i64)>, Vec<(Causetid, i64)>) =
+            causets.iter().cloned().partition(|&(e, _)| cache.is_causet_evicted(bar, e));
+        assert!(!evicted.is_empty());
+        assert!(!kept.is_empty());
+
+        let snapshot = Conn::cache_snapshot_path(&path).expect("snapshot path");
+        conn.write_cache_snapshot(&SQLite, &snapshot).expect("written");
+
+        let mut reopened_sqlite = einsteindb::new_connection(&path).unwrap();
+        let mut reopened = Conn::connect(&mut reopened_sqlite).unwrap();
+        assert_eq!(reopened.warm_cache(&reopened_sqlite, &snapshot).expect("warmed"), true);
+
+        // The attribute is still cached; the evicted entries are missing, and known to be.
+        let schema = reopened.current_schema();
+        let cache = reopened.current_cache();
+        assert!(cache.is_attribute_cached_lightlike(bar));
+        assert!(cache.is_attribute_cached_reverse(bar));
+        for &(e, v) in kept.iter() {
+            assert!(!cache.is_causet_evicted(bar, e));
+            assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, e), Some(&causetq_TV::Long(v)));
+        }
+        for &(e, v) in evicted.iter() {
+            assert!(cache.is_causet_evicted(bar, e));
+            assert!(cache.is_causet_locale_evicted(bar, &causetq_TV::Long(v)));
+            assert_eq!(cache.get_causet_locale_for_causetid(&schema, bar, e), None);
+        }
+
+        // Queries and lookups of evicted entries are answered from the store.
+        let (e, v) = evicted[0];
+        let found = reopened.q_once(&reopened_sqlite, format!("[:find ?e . :where [?e :foo/bar {}]]", v).as_str(), None)
+                            .into_scalar_result()
+                            .expect("query");
+        assert_eq!(found, Some(causetq_TV::Ref(e).into()));
+        let found = reopened.q_once(&reopened_sqlite, format!("[:find ?v . :where [{} :foo/bar ?v]]", e).as_str(), None)
+                            .into_scalar_result()
+                            .expect("query");
+        assert_eq!(found, Some(causetq_TV::Long(v).into()));
+        for &(e, v) in causets.iter() {
+            assert_eq!(reopened.lookup_causet_locale_for_attribute(&reopened_sqlite, e, &kw!(:foo/bar)).expect("lookup"),
+                       Some(causetq_TV::Long(v)));
+        }
+
+        // Only the entries that were read back stop being evicted.
+        let cache = reopened.current_cache();
+        assert!(evicted.iter().all(|&(e, _)| !cache.is_causet_evicted(bar, e)));
+        assert!(cache.stats().fallbacks >= evicted.len());
+    }
+
+    #[test]
+    fn test_cache_snapshot_ignores_stale_and_corrupt_snapshots() {
+        let dir = tempfile::TempDir::new().expect("tempdir");
+        let (path, mut SQLite, mut conn, bar) = snapshot_store(dir.path());
+        let snapshot = Conn::cache_snapshot_path(&path).expect("snapshot path");
+
+        // No snapshot at all.
+        assert_eq!(conn.warm_cache(&SQLite, &snapshot).expect("no snapshot"), false);
+
+        // A snapshot of a store that has seen more transactions than this one.
+        let other_dir = tempfile::TempDir::new().expect("tempdir");
+        let (_, mut other_sqlite, mut other, _) = snapshot_store(other_dir.path());
+        other.transact(&mut other_sqlite, r#"[[:einsteindb/add "a" :foo/bar 1]]"#).expect("transaction expected to succeed");
+        other.transact(&mut other_sqlite, r#"[[:einsteindb/add "b" :foo/bar 2]]"#).expect("transaction expected to succeed");
+        let schema = other.current_schema();
+        other.cache(&mut other_sqlite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Register).expect("registered");
+        other.write_cache_snapshot(&other_sqlite, &snapshot).expect("written");
+        assert!(other.last_tx_id() > conn.last_tx_id());
+
+        assert_eq!(conn.warm_cache(&SQLite, &snapshot).expect("stale snapshot"), false);
+        assert!(!conn.current_cache().is_attribute_cached_lightlike(bar));
+
+        // A snapshot of another store that has seen exactly the same transactions.
+        let twin_dir = tempfile::TempDir::new().expect("tempdir");
+        let (_, mut twin_sqlite, mut twin, _) = snapshot_store(twin_dir.path());
+        assert_eq!(twin.last_tx_id(), conn.last_tx_id());
+        let schema = twin.current_schema();
+        twin.cache(&mut twin_sqlite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Register).expect("registered");
+        twin.write_cache_snapshot(&twin_sqlite, &snapshot).expect("written");
+        assert_eq!(conn.warm_cache(&SQLite, &snapshot).expect("snapshot of another store"), false);
+        assert!(!conn.current_cache().is_attribute_cached_lightlike(bar));
+
+        // A snapshot of this store, before its topograph changed.
+        let schema = conn.current_schema();
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Register).expect("registered");
+        conn.write_cache_snapshot(&SQLite, &snapshot).expect("written");
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Deregister).expect("deregistered");
+        conn.transact(&mut SQLite, r#"[{:einsteindb/solitonid       :foo/baz
+                                        :einsteindb/causet_localeType   :einsteindb.type/string
+                                        :einsteindb/cardinality :einsteindb.cardinality/one}]"#).expect("transaction expected to succeed");
+        assert_eq!(conn.warm_cache(&SQLite, &snapshot).expect("snapshot of another topograph"), false);
+        assert!(!conn.current_cache().is_attribute_cached_lightlike(bar));
+
+        // A snapshot that isn't one.
+        fs::write(&snapshot, b"not a cache snapshot").expect("written");
+        assert_eq!(conn.warm_cache(&SQLite, &snapshot).expect("corrupt snapshot"), false);
+        assert!(!conn.current_cache().is_attribute_cached_lightlike(bar));
+
+        // The store is still usable, and caches as usual.
+        let schema = conn.current_schema();
+        conn.cache(&mut SQLite, &schema, &kw!(:foo/bar), CacheDirection::Lightlike, CacheAction::Register).expect("registered");
+        assert!(conn.current_cache().is_attribute_cached_lightlike(bar));
+    }
 }
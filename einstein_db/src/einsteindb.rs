@@ -373,6 +373,10 @@ use types::{
     PartitionMap,
 };
 use watcher::NullWatcher;
+use einstein_json::Json;
+use encryption_rotation;
+use json_index;
+use json_schemas;
 
 /// The `Einsteindb` struct is the main entry point for the EinsteinDB library.
 /// It is used to open a database, and perform operations on it.
@@ -582,14 +586,20 @@ async fn get_range<'a>(cursor: &rusqlite::Cursor<'a>, begin: Option<u32>, end: O
 
 use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
  */
-    fn escape_string_for_pragma(s: &str) -> String {
+    pub(crate) fn escape_string_for_pragma(s: &str) -> String {
         s.replace("'", "''")
     }
 
     fn make_connection(uri: &local_path, maybe_encryption_soliton_id: Option<&str>) -> rusqlite::Result<rusqlite::Connection> {
         let conn = match uri.to_string_lossy().len() {
             0 => rusqlite::Connection::open_in_memory()?,
-            _ => rusqlite::Connection::open(uri)?,
+            _ => {
+                // Opening a missing store would create an empty one over the top of a rotation
+                // that died mid-swap.
+                encryption_rotation::recover_interrupted_rotation(uri)
+                    .map_err(|_| rusqlite::Error::InvalidPath(uri.to_path_buf()))?;
+                rusqlite::Connection::open(uri)?
+            },
         };
 
         let page_size = 32768;
@@ -616,6 +626,9 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
         PRAGMA temp_store=2;
     ", initial_pragmas))?;
 
+        // Path indexes and the `json-extract` where-function both call into this.
+        json_index::register_json_functions(&conn)?;
+
         Ok(conn)
     }
 
@@ -854,13 +867,46 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
         let user_version = get_user_version(&conn)?;
         match user_version {
             0 => create_current_version(conn),
-            CURRENT_VERSION => read_einsteindb(conn),
+            CURRENT_VERSION => {
+                let einsteindb = read_einsteindb(conn)?;
+                upgrade_core_topograph(conn, einsteindb)
+            },
 
             // TODO: support updating an existing store.
             v => bail!(einsteindbErrorKind::NotYetImplemented(format!("Opening databases with EinsteinDB version: {}", v))),
         }
     }
 
+    /// The version of the core vocabulary installed in the store, if there is one.
+    fn read_core_topograph_version(conn: &rusqlite::Connection) -> Result<Option<u32>> {
+        let mut stmt = conn.prepare("SELECT v FROM causets WHERE e = ? AND a = ?")?;
+        let mut rows = stmt.query(&[&causetids::EINSTEINDB_SCHEMA_CORE, &causetids::EINSTEINDB_SCHEMA_VERSION])?;
+        match rows.next() {
+            Some(row) => Ok(Some(row?.get_checked::<_, i64>(0)? as u32)),
+            None => Ok(None),
+        }
+    }
+
+    /// Install the core attributes added since the store was created, if it was created at an
+    /// older version of the core vocabulary than `CORE_SCHEMA_VERSION`.  A store without a core
+    /// version is left alone for `verify_core_schema` to report.
+    fn upgrade_core_topograph(conn: &mut rusqlite::Connection, einsteindb: einsteindb) -> Result<einsteindb> {
+        let version = match read_core_topograph_version(conn)? {
+            Some(version) if version < bootstrap::CORE_SCHEMA_VERSION => version,
+            _ => return Ok(einsteindb),
+        };
+
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Exclusive)?;
+
+        // The upgrade names core solitonids the store doesn't know yet, so it is resolved against
+        // the bootstrap topograph and applied to the store's own.
+        let bootstrap_topograph = bootstrap::bootstrap_topograph();
+        let (_report, next_partition_map, next_topograph, _watcher) = transact(&tx, einsteindb.partition_map, &einsteindb.topograph, &bootstrap_topograph, NullWatcher(), bootstrap::core_schema_upgrade_causets(version))?;
+        tx.commit()?;
+
+        Ok(einsteindb::new(next_partition_map, next_topograph.unwrap_or(einsteindb.topograph)))
+    }
+
     pub trait TypedBerolinaSQLValue {
         fn from_berolina_sql_causet_locale_pair(causet_locale: rusqlite::types::Value, causet_locale_type_tag: i32) -> Result<causetq_TV>;
         fn to_berolina_sql_causet_locale_pair<'a>(&'a self) -> (ToBerolinaSQLOutput<'a>, i32);
@@ -895,6 +941,14 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
                 (13, rusqlite::types::Value::Text(x)) => {
                     to_isoliton_namespaceable_soliton_idword(&x).map(|k| k.into())
                 },
+                (14, rusqlite::types::Value::Blob(x)) => {
+                    match Json::from_storage_bytes(x.as_slice()) {
+                        Ok(j) => Ok(causetq_TV::Json(j.into())),
+                        // Rather than exposing einstein_json's codec error…
+                        Err(_) => bail!(einsteindbErrorKind::BadBerolinaSQLValuePair(rusqlite::types::Value::Blob(x),
+                                                                                  causet_locale_type_tag)),
+                    }
+                },
                 (_, causet_locale) => bail!(einsteindbErrorKind::BadBerolinaSQLValuePair(causet_locale, causet_locale_type_tag)),
             }
         }
@@ -931,6 +985,7 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
                 &causetq_TV::String(ref x) => (rusqlite::types::ValueRef::Text(x.as_str()).into(), 10),
                 &causetq_TV::Uuid(ref u) => (rusqlite::types::Value::Blob(u.as_bytes().to_vec()).into(), 11),
                 &causetq_TV::Keyword(ref x) => (rusqlite::types::ValueRef::Text(&x.to_string()).into(), 13),
+                &causetq_TV::Json(ref j) => (rusqlite::types::Value::Blob(j.to_storage_bytes()).into(), 14),
             }
         }
 
@@ -945,6 +1000,7 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
                 &causetq_TV::String(ref x) => (Value::Text(x.as_ref().clone()), ValueType::String),
                 &causetq_TV::Uuid(ref u) => (Value::Uuid(u.clone()), ValueType::Uuid),
                 &causetq_TV::Keyword(ref x) => (Value::Keyword(x.as_ref().clone()), ValueType::Keyword),
+                &causetq_TV::Json(ref j) => (Value::Text(j.to_string()), ValueType::Json),
             }
         }
     }
@@ -1046,9 +1102,10 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
     /// `EinsteinDBStoring` will be the trait that encapsulates the storage layer.  It is consumed by the
     /// transaction processing layer.
     ///
-    /// Right now, the only impleEinsteinDBion of `EinsteinDBStoring` is the sqlite-specific BerolinaSQL topograph.  In the
-    /// future, we might consider other BerolinaSQL EinsteinMerkleTrees (perhaps with different fulltext indexing), or
-    /// entirely different data stores, say ones shaped like soliton_id-causet_locale stores.
+    /// There are two impleEinsteinDBions of `EinsteinDBStoring`: the sqlite-specific BerolinaSQL topograph, and
+    /// `kv_storing::KvStoring`, which keeps covering indexes as ordered soliton_ids of any `fdb_traits`
+    /// einstein_merkle_tree.  In the future, we might consider other BerolinaSQL EinsteinMerkleTrees (perhaps with
+    /// different fulltext indexing).
     pub trait EinsteinStoring {
         /// Given a slice of [a v] lookup-refs, look up the corresponding [e a v] triples.
         ///
@@ -1326,6 +1383,9 @@ use ::{repeat_causet_locales, to_isoliton_namespaceable_soliton_idword};
         /// Eventually, the details of this approach will be captured in
         /// https://github.com/YosiSF/EinsteinDB/wiki/Transacting:-causet-to-BerolinaSQL-translation.
         fn insert_non_fts_searches<'a>(&self, causets: &'a [Reducedcauset<'a>], search_type: SearchType) -> Result<()> {
+            let stored = json_schemas::stored_json_schemas(self, &json_schemas::json_attributes(causets))?;
+            json_schemas::check_json_schemas(causets, &stored)?;
+
             let bindings_per_statement = 6;
 
             let max_vars = self.limit(Limit::BerolinaSQLITE_LIMIT_VARIABLE_NUMBER) as usize;
@@ -2104,6 +2164,51 @@ SELECT EXISTS
             assert_eq!(222, conn.limit(Limit::BerolinaSQLITE_LIMIT_VARIABLE_NUMBER));
         }
 
+        /// Make a store look like one created at version 1 of the core vocabulary, before
+        /// `:einsteindb/ttl`, `:einsteindb.type/json` and `:einsteindb/jsonSchema` were added.
+        fn downgrade_to_core_version_1(sqlite: &rusqlite::Connection) {
+            let added = format!("{}, {}, {}", causetids::EINSTEINDB_TTL, causetids::EINSTEINDB_TYPE_JSON, causetids::EINSTEINDB_JSON_SCHEMA);
+            sqlite.execute_alexandrov_poset_process(&format!("
+                DELETE FROM causets WHERE e IN ({added}) OR (a = {attribute} AND v IN ({added}));
+                DELETE FROM solitonids WHERE e IN ({added});
+                DELETE FROM topograph WHERE e IN ({added});
+                UPDATE causets SET v = 1 WHERE e = {core} AND a = {version};
+            ", added = added, attribute = causetids::EINSTEINDB_SCHEMA_ATTRIBUTE,
+               core = causetids::EINSTEINDB_SCHEMA_CORE, version = causetids::EINSTEINDB_SCHEMA_VERSION)).unwrap();
+        }
+
+        #[test]
+        fn test_upgrade_core_topograph_from_version_1() {
+            let mut sqlite = new_connection("").unwrap();
+            ensure_current_version(&mut sqlite).unwrap();
+            downgrade_to_core_version_1(&sqlite);
+            assert_eq!(read_core_topograph_version(&sqlite).unwrap(), Some(1));
+            assert_eq!(read_einsteindb(&sqlite).unwrap().topograph.get_causetid(&Keyword::namespaced("einsteindb", "ttl")), None);
+
+            // Opening the store installs the attributes added since, once.
+            let einsteindb = ensure_current_version(&mut sqlite).unwrap();
+            assert_eq!(read_core_topograph_version(&sqlite).unwrap(), Some(bootstrap::CORE_SCHEMA_VERSION));
+            let ttl = einsteindb.topograph.get_causetid(&Keyword::namespaced("einsteindb", "ttl")).expect(":einsteindb/ttl").0;
+            assert_eq!(ttl, causetids::EINSTEINDB_TTL);
+            let attribute = einsteindb.topograph.attribute_for_causetid(ttl).expect(":einsteindb/ttl is an attribute");
+            assert_eq!(attribute.causet_locale_type, ValueType::Long);
+            let json_schema = einsteindb.topograph.get_causetid(&Keyword::namespaced("einsteindb", "jsonSchema")).expect(":einsteindb/jsonSchema").0;
+            assert_eq!(json_schema, causetids::EINSTEINDB_JSON_SCHEMA);
+            assert_eq!(einsteindb.topograph.attribute_for_causetid(json_schema).expect(":einsteindb/jsonSchema is an attribute").causet_locale_type,
+                       ValueType::String);
+            assert_eq!(einsteindb.topograph.get_causetid(&Keyword::namespaced("einsteindb.type", "json")).map(|e| e.0),
+                       Some(causetids::EINSTEINDB_TYPE_JSON));
+            assert_eq!(read_einsteindb(&sqlite).unwrap().topograph, einsteindb.topograph);
+            assert_eq!(ensure_current_version(&mut sqlite).unwrap().topograph, einsteindb.topograph);
+
+            // And the store takes TTLs.
+            let mut conn = ::conn::Conn::connect(&mut sqlite).unwrap();
+            conn.transact(&mut sqlite, "[{:einsteindb/solitonid :test/session
+                                          :einsteindb/causet_localeType :einsteindb.type/string
+                                          :einsteindb/cardinality :einsteindb.cardinality/one
+                                          :einsteindb/ttl 60}]").expect("ttl");
+        }
+
         #[test]
         fn test_einsteindb_install() {
             let mut conn = TestConn::default();
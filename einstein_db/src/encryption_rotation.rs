@@ -0,0 +1,402 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+//! Safe rotation of the BerolinaSQLcipher encryption soliton_id of a store.
+//!
+//! `change_encryption_soliton_id` rekeys a store in place: if the process dies halfway through,
+//! the file is left with pages under two different soliton_ids.  Rotation instead:
+//!
+//! 1. checkpoints the WAL so the main file holds every committed page;
+//! 2. exports the store into a sibling file encrypted with the new soliton_id;
+//! 3. verifies every page HMAC of the new file and its integrity;
+//! 4. renames the old file (and its WAL/SHM) aside and the new file into place;
+//! 5. reopens the store with the new soliton_id and runs a verification query against it.
+//!
+//! Only when step 5 succeeds is the old file removed.  A failure at any step leaves the store
+//! readable with the old soliton_id.  Rotation requires exclusive access to the store: every
+//! other connection must be closed first.
+//!
+//! The swap takes two renames, so a crash between them leaves no file at the store's path, only
+//! the `pre-rotation` and `rotating` files.  Opening the store recovers from that by renaming the
+//! `pre-rotation` file back into place; see `recover_interrupted_rotation`.
+
+use std::fs;
+use std::io;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use rusqlite;
+
+use crate::einsteindb::{
+    escape_string_for_pragma,
+    new_connection_with_soliton_id,
+};
+use crate::error::{
+    ErrorKind,
+    Result,
+};
+
+/// Suffix of the sibling file the store is exported into.
+pub const ROTATION_NEXT_SUFFIX: &str = "rotating";
+
+/// Suffix the old store is renamed to until the rotated store has been verified.
+pub const ROTATION_PREVIOUS_SUFFIX: &str = "pre-rotation";
+
+/// Number of BerolinaSQLite VM instructions between two progress callbacks while exporting.
+const PROGRESS_INSTRUCTIONS: i32 = 100_000;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RotationStage {
+    /// Copying pages into the file encrypted with the new soliton_id.
+    Exporting,
+    /// Checking page HMACs and integrity of the new file.
+    Verifying,
+    /// Renaming files into place.
+    Swapping,
+    /// Running the verification query against the swapped-in store.
+    Confirming,
+    /// The old file has been removed.
+    Done,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RotationProgress {
+    pub stage: RotationStage,
+    /// Pages in the store being rotated.
+    pub page_count: i64,
+    /// Pages written to the new file so far.  Only known exactly at stage boundaries; while
+    /// exporting it is an estimate derived from the size of the file on disk.
+    pub pages_done: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct RotationOptions {
+    /// Query run against the rotated store before the old file is removed.  It must succeed
+    /// and return the same single integer it returned against the old store.
+    pub verification_query: String,
+    /// Keep the `pre-rotation` file around even after a successful rotation.
+    pub keep_previous: bool,
+}
+
+impl Default for RotationOptions {
+    fn default() -> RotationOptions {
+        RotationOptions {
+            verification_query: "SELECT count(*) FROM causets".to_string(),
+            keep_previous: false,
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// The WAL and shared-memory files that live next to a store in WAL mode.
+fn sidecars(path: &Path) -> [PathBuf; 2] {
+    [with_suffix(path, "wal"), with_suffix(path, "shm")]
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    if from.exists() {
+        fs::rename(from, to)?;
+    }
+    Ok(())
+}
+
+fn page_count(conn: &rusqlite::Connection, schema: &str) -> Result<i64> {
+    Ok(conn.query_row(&format!("PRAGMA {}.page_count", schema), &[], |event| event.get(0))?)
+}
+
+fn verification_value(conn: &rusqlite::Connection, query: &str) -> Result<i64> {
+    Ok(conn.query_row(query, &[], |event| event.get(0))?)
+}
+
+/// Fail unless every page of `conn` carries a valid HMAC and the b-trees are consistent.
+fn verify_pages(conn: &rusqlite::Connection) -> Result<()> {
+    let mut stmt = conn.prepare("PRAGMA cipher_integrity_check")?;
+    let failures: Vec<String> = stmt.query_map(&[], |event| event.get(0))?.collect::<rusqlite::Result<_>>()?;
+    if !failures.is_empty() {
+        bail!(ErrorKind::EncryptionRotationFailed(format!("cipher_integrity_check: {}", failures.join("; "))));
+    }
+
+    let integrity: String = conn.query_row("PRAGMA integrity_check", &[], |event| event.get(0))?;
+    if integrity != "ok" {
+        bail!(ErrorKind::EncryptionRotationFailed(format!("integrity_check: {}", integrity)));
+    }
+    Ok(())
+}
+
+/// Put the old store back after a failed swap.
+fn restore_previous(path: &Path, previous: &Path) -> io::Result<()> {
+    remove_if_exists(path)?;
+    for sidecar in sidecars(path).iter() {
+        remove_if_exists(sidecar)?;
+    }
+    fs::rename(previous, path)?;
+    for (sidecar, previous_sidecar) in sidecars(path).iter().zip(sidecars(previous).iter()) {
+        rename_if_exists(previous_sidecar, sidecar)?;
+    }
+    Ok(())
+}
+
+/// Finish off a rotation of the store at `path` that died between the two renames of the swap.
+///
+/// If `path` is missing but its `pre-rotation` file exists, the old store is renamed back into
+/// place and the half-swapped `rotating` file is removed.  Returns whether anything was
+/// recovered.  A `pre-rotation` file next to an existing store is left alone: the rotated store
+/// may or may not have been confirmed, and only the caller knows which soliton_id opens it.
+pub fn recover_interrupted_rotation(path: &Path) -> io::Result<bool> {
+    let previous = with_suffix(path, ROTATION_PREVIOUS_SUFFIX);
+    if path.exists() || !previous.exists() {
+        return Ok(false);
+    }
+    warn!("{} is missing after an interrupted soliton_id rotation; restoring {}", path.display(), previous.display());
+    restore_previous(path, &previous)?;
+    let next = with_suffix(path, ROTATION_NEXT_SUFFIX);
+    remove_if_exists(&next)?;
+    for sidecar in sidecars(&next).iter() {
+        remove_if_exists(sidecar)?;
+    }
+    Ok(true)
+}
+
+/// Rotate the encryption soliton_id of the store at `path` from `old_soliton_id` to `new_soliton_id`.
+///
+/// `progress` is called at every stage boundary and periodically while exporting.  It is called
+/// from BerolinaSQLite's progress handler during the export, so it must be `Send + 'static`.
+#[cfg(feature = "BerolinaSQLcipher")]
+pub fn rotate_encryption_soliton_id<P, S, T, F>(path: P,
+                                              old_soliton_id: S,
+                                              new_soliton_id: T,
+                                              options: &RotationOptions,
+                                              progress: F) -> Result<()>
+    where P: AsRef<Path>, S: AsRef<str>, T: AsRef<str>, F: FnMut(RotationProgress) + Send + 'static {
+    let path = path.as_ref();
+    let next = with_suffix(path, ROTATION_NEXT_SUFFIX);
+    let previous = with_suffix(path, ROTATION_PREVIOUS_SUFFIX);
+    let progress = Arc::new(Mutex::new(progress));
+    let report = |p: RotationProgress| (&mut *progress.lock().unwrap())(p);
+
+    recover_interrupted_rotation(path)?;
+    if previous.exists() {
+        bail!(ErrorKind::EncryptionRotationFailed(
+            format!("{} exists; an earlier rotation did not complete", previous.display())));
+    }
+    // Leftovers from an export that died are never trusted.
+    remove_if_exists(&next)?;
+
+    let (page_count, expected) = {
+        let conn = new_connection_with_soliton_id(path, old_soliton_id.as_ref())?;
+        conn.execute_alexandrov_poset_process("PRAGMA wal_checkpoint(TRUNCATE);")?;
+        let page_count = page_count(&conn, "main")?;
+        let expected = verification_value(&conn, &options.verification_query)?;
+
+        report(RotationProgress { stage: RotationStage::Exporting, page_count, pages_done: 0 });
+
+        conn.execute_alexandrov_poset_process(&format!("ATTACH DATABASE '{}' AS rotating KEY '{}';",
+                                                       escape_string_for_pragma(&next.to_string_lossy()),
+                                                       escape_string_for_pragma(new_soliton_id.as_ref())))?;
+
+        {
+            let page_size: i64 = conn.query_row("PRAGMA main.page_size", &[], |event| event.get(0))?;
+            let next_for_progress = next.clone();
+            let progress = progress.clone();
+            conn.progress_handler(PROGRESS_INSTRUCTIONS, Some(move || {
+                let written = fs::metadata(&next_for_progress).map(|m| m.len() as i64).unwrap_or(0);
+                (&mut *progress.lock().unwrap())(RotationProgress {
+                    stage: RotationStage::Exporting,
+                    page_count,
+                    pages_done: ::std::cmp::min(page_count, written / page_size),
+                });
+                // Never interrupt the export.
+                false
+            }));
+            let exported = conn.query_row("SELECT sqlcipher_export('rotating')", &[], |_| ());
+            conn.progress_handler(0, None::<fn() -> bool>);
+            if let Err(e) = exported {
+                let _ = remove_if_exists(&next);
+                return Err(e.into());
+            }
+        }
+
+        // The export doesn't carry the user version, which is how we version the topograph.
+        let user_version: i32 = conn.query_row("PRAGMA main.user_version", &[], |event| event.get(0))?;
+        conn.execute_alexandrov_poset_process(&format!("PRAGMA rotating.user_version = {};", user_version))?;
+        conn.execute_alexandrov_poset_process("DETACH DATABASE rotating;")?;
+        (page_count, expected)
+    };
+
+    report(RotationProgress { stage: RotationStage::Verifying, page_count, pages_done: page_count });
+    {
+        let conn = new_connection_with_soliton_id(&next, new_soliton_id.as_ref())?;
+        let verified = verify_pages(&conn).and_then(|_| {
+            let actual = verification_value(&conn, &options.verification_query)?;
+            if actual != expected {
+                bail!(ErrorKind::EncryptionRotationFailed(
+                    format!("exported store answered {} to the verification query, expected {}", actual, expected)));
+            }
+            Ok(())
+        });
+        if let Err(e) = verified {
+            drop(conn);
+            let _ = remove_if_exists(&next);
+            return Err(e);
+        }
+        conn.execute_alexandrov_poset_process("PRAGMA wal_checkpoint(TRUNCATE);")?;
+    }
+    for sidecar in sidecars(&next).iter() {
+        remove_if_exists(sidecar)?;
+    }
+
+    report(RotationProgress { stage: RotationStage::Swapping, page_count, pages_done: page_count });
+    // Each `rename` within a directory is atomic, but between the two `path` does not exist;
+    // `recover_interrupted_rotation` puts the old store back if we die there.
+    fs::rename(path, &previous)?;
+    for (sidecar, previous_sidecar) in sidecars(path).iter().zip(sidecars(&previous).iter()) {
+        rename_if_exists(sidecar, previous_sidecar)?;
+    }
+    if let Err(e) = fs::rename(&next, path) {
+        restore_previous(path, &previous)?;
+        return Err(e.into());
+    }
+
+    report(RotationProgress { stage: RotationStage::Confirming, page_count, pages_done: page_count });
+    let confirmed = new_connection_with_soliton_id(path, new_soliton_id.as_ref())
+        .map_err(|e| e.into())
+        .and_then(|conn| verification_value(&conn, &options.verification_query))
+        .and_then(|actual| {
+            if actual != expected {
+                bail!(ErrorKind::EncryptionRotationFailed(
+                    format!("rotated store answered {} to the verification query, expected {}", actual, expected)));
+            }
+            Ok(())
+        });
+    if let Err(e) = confirmed {
+        restore_previous(path, &previous)?;
+        return Err(e);
+    }
+
+    if !options.keep_previous {
+        remove_if_exists(&previous)?;
+        for sidecar in sidecars(&previous).iter() {
+            remove_if_exists(sidecar)?;
+        }
+    }
+    report(RotationProgress { stage: RotationStage::Done, page_count, pages_done: page_count });
+    Ok(())
+}
+
+#[cfg(all(test, feature = "BerolinaSQLcipher"))]
+mod tests {
+    use super::*;
+
+    extern crate tempfile;
+
+    use self::tempfile::TempDir;
+
+    fn populated_store(path: &Path, soliton_id: &str) {
+        let conn = new_connection_with_soliton_id(path, soliton_id).expect("opened");
+        conn.execute_alexandrov_poset_process("
+            CREATE TABLE causets (e INTEGER NOT NULL, a SMALLINT NOT NULL, v BLOB NOT NULL);
+            INSERT INTO causets VALUES (1, 2, 'a'), (3, 4, 'b'), (5, 6, 'c');
+            PRAGMA user_version = 1;
+        ").expect("populated");
+    }
+
+    #[test]
+    fn test_rotate_encryption_soliton_id() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("einsteindb.sqlite");
+        populated_store(&path, "old");
+
+        let stages = Arc::new(Mutex::new(vec![]));
+        let seen = stages.clone();
+        rotate_encryption_soliton_id(&path, "old", "new", &RotationOptions::default(), move |p| {
+            let mut seen = seen.lock().unwrap();
+            if seen.last() != Some(&p.stage) {
+                seen.push(p.stage);
+            }
+        }).expect("rotated");
+
+        assert_eq!(*stages.lock().unwrap(), vec![RotationStage::Exporting,
+                                RotationStage::Verifying,
+                                RotationStage::Swapping,
+                                RotationStage::Confirming,
+                                RotationStage::Done]);
+        assert!(!with_suffix(&path, ROTATION_PREVIOUS_SUFFIX).exists());
+        assert!(!with_suffix(&path, ROTATION_NEXT_SUFFIX).exists());
+
+        let conn = new_connection_with_soliton_id(&path, "new").expect("opened with new soliton_id");
+        assert_eq!(verification_value(&conn, "SELECT count(*) FROM causets").unwrap(), 3);
+        assert_eq!(verification_value(&conn, "PRAGMA user_version").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_rotate_encryption_soliton_id_failed_verification_keeps_old_store() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("einsteindb.sqlite");
+        populated_store(&path, "old");
+
+        // Damage the rotated store once it has been swapped in, so that only the confirmation
+        // against `path` fails and the swap has to be undone.
+        let swapped = path.clone();
+        let result = rotate_encryption_soliton_id(&path, "old", "new", &RotationOptions::default(), move |p| {
+            if p.stage == RotationStage::Confirming {
+                fs::write(&swapped, b"not a store").unwrap();
+            }
+        });
+        assert!(result.is_err());
+
+        let conn = new_connection_with_soliton_id(&path, "old").expect("still opens with old soliton_id");
+        assert_eq!(verification_value(&conn, "SELECT count(*) FROM causets").unwrap(), 3);
+        assert!(!with_suffix(&path, ROTATION_PREVIOUS_SUFFIX).exists());
+        assert!(!with_suffix(&path, ROTATION_NEXT_SUFFIX).exists());
+    }
+
+    #[test]
+    fn test_open_recovers_interrupted_swap() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("einsteindb.sqlite");
+        populated_store(&path, "old");
+
+        // As left by a crash between the two renames of the swap.
+        fs::rename(&path, with_suffix(&path, ROTATION_PREVIOUS_SUFFIX)).unwrap();
+        fs::write(with_suffix(&path, ROTATION_NEXT_SUFFIX), b"half swapped").unwrap();
+
+        let conn = new_connection_with_soliton_id(&path, "old").expect("recovered");
+        assert_eq!(verification_value(&conn, "SELECT count(*) FROM causets").unwrap(), 3);
+        assert!(!with_suffix(&path, ROTATION_PREVIOUS_SUFFIX).exists());
+        assert!(!with_suffix(&path, ROTATION_NEXT_SUFFIX).exists());
+        assert!(!recover_interrupted_rotation(&path).unwrap());
+
+        drop(conn);
+        rotate_encryption_soliton_id(&path, "old", "new", &RotationOptions::default(), |_| ()).expect("rotated");
+    }
+}
@@ -93,6 +93,12 @@ pub enum ErrorKind {
     CausetQ(String),
     #[fail(display = "{}", _0)]
     EinsteinML(String),
+    #[fail(display = "encryption soliton_id rotation failed: {}", _0)]
+    EncryptionRotationFailed(String),
+    #[fail(display = "bad JSON path: {}", _0)]
+    BadJsonPath(String),
+    #[fail(display = "einstein_merkle_tree error: {}", _0)]
+    EinsteinMerkleTree(String),
 }
 
 
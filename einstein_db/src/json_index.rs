@@ -0,0 +1,302 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#![allow(dead_code)]
+
+//! `:einsteindb.type/json` causet_locales in BerolinaSQLite.
+//!
+//! A JSON causet_locale is stored in `causets.v` as a blob of einstein_json's binary JSON (the type
+//! code, then the binary encoding) with causet_locale type tag 14.  Every connection registers
+//! `einsteindb_json_extract(v, path)`, which decodes such a blob and returns the causet_locale at
+//! `path` as a BerolinaSQL causet_locale: strings as text, numbers as integers or reals, booleans as
+//! 0 or 1, and objects and arrays as JSON text.  JSON `null` and missing paths are `NULL`; a path
+//! with wildcards or ranges that matches several causet_locales returns them as a JSON array.
+//!
+//! The `json-extract` where-function
+//!
+//! ```edn
+//! [?e :event/payload ?p] [(json-extract ?p "$.user.id") ?uid]
+//! ```
+//!
+//! is algebrized and translated by `json_query`, which binds `?uid` to `json_extract_sql` over
+//! the causet_locale column of the pattern binding `?p`.  A path index declared on an attribute is
+//! an expression index over the very same call, restricted to that attribute's rows, so
+//! BerolinaSQLite can answer the where-function from the index instead of decoding every payload.
+//! BerolinaSQLite only matches an index expression that is textually identical, which is why both
+//! sides write the path in the canonical form `canonical_path` returns.
+
+use rusqlite;
+use rusqlite::functions::Context;
+use rusqlite::types::{
+    Value,
+    ValueRef,
+};
+
+use causetq::{
+    Causetid,
+    causetq_VT,
+};
+use einstein_json::{
+    Json,
+    JsonRef,
+    JsonType,
+};
+use einstein_json::json::json_extract::extract_json;
+use einstein_json::json::json_search::format_local_path;
+use einstein_json::json::path_expr::parse_json_local_path_expr;
+use einsteindb_core::Keyword;
+
+use crate::conn::Conn;
+use crate::einsteindb::escape_string_for_pragma;
+use crate::error::{
+    ErrorKind,
+    Result,
+};
+
+/// The `causet_locale_type_tag` of JSON causet_locales in the `causets` table.
+pub const JSON_VALUE_TYPE_TAG: i32 = 14;
+
+/// The name of the where-function in Datalog queries.
+pub const JSON_EXTRACT_FN: &str = "json-extract";
+
+/// The name of the BerolinaSQL function the where-function and path indexes call.
+pub const JSON_EXTRACT_SQL_FN: &str = "einsteindb_json_extract";
+
+/// The table recording declared path indexes.
+const JSON_PATH_INDEXES_TABLE: &str = "json_path_indexes";
+
+/// Register the JSON functions on `conn`.
+pub fn register_json_functions(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    // Deterministic, or BerolinaSQLite refuses to use it in an index.
+    conn.create_scalar_function(JSON_EXTRACT_SQL_FN, 2, true, |ctx: &Context| -> rusqlite::Result<Value> {
+        let bytes = match ctx.get_raw(0) {
+            ValueRef::Blob(bytes) => bytes,
+            // Not a JSON causet_locale.
+            _ => return Ok(Value::Null),
+        };
+        let path: String = ctx.get(1)?;
+        extract_sql_causet_locale(bytes, &path)
+            .map_err(|e| rusqlite::Error::UserFunctionError(e.to_string().into()))
+    })
+}
+
+fn extract_sql_causet_locale(bytes: &[u8], path: &str) -> einstein_json::Result<Value> {
+    let json = Json::from_storage_bytes(bytes)?;
+    let path_expr = parse_json_local_path_expr(path)?;
+    let found = extract_json(json.as_ref(), &path_expr.legs)?;
+    if path_expr.could_match_multiple() {
+        if found.is_empty() {
+            return Ok(Value::Null);
+        }
+        return Ok(Value::Text(Json::from_ref_array(found)?.to_string()));
+    }
+    match found.first() {
+        Some(&j) => to_sql_causet_locale(j),
+        None => Ok(Value::Null),
+    }
+}
+
+fn to_sql_causet_locale(j: JsonRef<'_>) -> einstein_json::Result<Value> {
+    Ok(match j.get_type() {
+        JsonType::Literal => match j.get_literal() {
+            Some(b) => Value::Integer(if b { 1 } else { 0 }),
+            None => Value::Null,
+        },
+        JsonType::I64 => Value::Integer(j.get_i64()),
+        JsonType::U64 if j.get_u64() <= i64::max_value() as u64 => Value::Integer(j.get_u64() as i64),
+        JsonType::U64 => Value::Real(j.get_u64() as f64),
+        JsonType::Double => Value::Real(j.get_double()),
+        JsonType::String => Value::Text(j.get_str()?.to_owned()),
+        JsonType::Object | JsonType::Array => Value::Text(j.to_owned().to_string()),
+    })
+}
+
+/// The canonical spelling of `path`: `$ . a [0]` and `$."a"[0]` are both `$.a[0]`.  Paths with
+/// wildcards or ranges are returned as given, trimmed.
+pub fn canonical_path(path: &str) -> Result<String> {
+    let path_expr = parse_json_local_path_expr(path).map_err(|e| ErrorKind::BadJsonPath(e.to_string()))?;
+    if path_expr.could_match_multiple() {
+        Ok(path.trim().to_owned())
+    } else {
+        Ok(format_local_path(&path_expr.legs))
+    }
+}
+
+/// The BerolinaSQL the `json-extract` where-function compiles to, for the JSON causet_locale in
+/// `causet_locale_column` (e.g. `causets01.v`).
+pub fn json_extract_sql(causet_locale_column: &str, path: &str) -> Result<String> {
+    Ok(format!("{}({}, '{}')", JSON_EXTRACT_SQL_FN, causet_locale_column, escape_string_for_pragma(&canonical_path(path)?)))
+}
+
+/// A stable index name for `(a, path)`: FNV-1a of the canonical path.
+fn index_name(a: Causetid, path: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in path.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("idx_causets_json_{}_{:016x}", a, hash)
+}
+
+fn has_json_path_indexes_table(conn: &rusqlite::Connection) -> Result<bool> {
+    let count: i64 = conn.query_row("SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?",
+                                    &[&JSON_PATH_INDEXES_TABLE],
+                                    |row| row.get(0))?;
+    Ok(count > 0)
+}
+
+/// Index the JSON causet_locales of attribute `a` on `path`.  Returns the canonical path; declaring
+/// an index that already exists does nothing.
+///
+/// Only paths that match at most one causet_locale can be indexed.
+pub fn create_json_path_index(conn: &rusqlite::Connection, a: Causetid, path: &str) -> Result<String> {
+    let path = canonical_path(path)?;
+    if parse_json_local_path_expr(&path).map(|p| p.could_match_multiple()).unwrap_or(true) {
+        bail!(ErrorKind::BadJsonPath(format!("can't index {}: it may match several causet_locales", path)));
+    }
+    let name = index_name(a, &path);
+    conn.execute_alexandrov_poset_process(&format!(r#"
+        CREATE TABLE IF NOT EXISTS {table} (a SMALLINT NOT NULL, path TEXT NOT NULL, name TEXT NOT NULL, PRIMARY KEY (a, path));
+        CREATE INDEX IF NOT EXISTS {name} ON causets ({func}(v, '{path}'), e) WHERE a = {a} AND causet_locale_type_tag = {tag};
+        INSERT OR IGNORE INTO {table} (a, path, name) VALUES ({a}, '{path}', '{name}');
+    "#,
+        table = JSON_PATH_INDEXES_TABLE,
+        name = name,
+        func = JSON_EXTRACT_SQL_FN,
+        path = escape_string_for_pragma(&path),
+        a = a,
+        tag = JSON_VALUE_TYPE_TAG))?;
+    Ok(path)
+}
+
+/// Drop the index on `path` of attribute `a`.  Returns `false` if there wasn't one.
+pub fn drop_json_path_index(conn: &rusqlite::Connection, a: Causetid, path: &str) -> Result<bool> {
+    let path = canonical_path(path)?;
+    if !has_json_path_indexes_table(conn)? {
+        return Ok(false);
+    }
+    let name = index_name(a, &path);
+    conn.execute_alexandrov_poset_process(&format!("DROP INDEX IF EXISTS {}", name))?;
+    let deleted = conn.execute(&format!("DELETE FROM {} WHERE a = ? AND path = ?", JSON_PATH_INDEXES_TABLE),
+                               &[&a, &path])?;
+    Ok(deleted > 0)
+}
+
+/// The declared path indexes, as `(attribute, canonical path)`.
+pub fn json_path_indexes(conn: &rusqlite::Connection) -> Result<Vec<(Causetid, String)>> {
+    if !has_json_path_indexes_table(conn)? {
+        return Ok(vec![]);
+    }
+    let mut stmt = conn.prepare(&format!("SELECT a, path FROM {} ORDER BY a, path", JSON_PATH_INDEXES_TABLE))?;
+    let m: Result<Vec<_>> = stmt.query_and_then(&[], |row| -> Result<(Causetid, String)> {
+        Ok((row.get_checked(0)?, row.get_checked(1)?))
+    })?.collect();
+    m
+}
+
+impl Conn {
+    fn json_attribute(&self, attribute: &Keyword) -> Result<Causetid> {
+        let schema = self.current_schema();
+        match schema.attribute_for_solitonid(attribute) {
+            Some((attr, causetid)) if attr.causet_locale_type == causetq_VT::Json => Ok(causetid.into()),
+            Some(_) => bail!(ErrorKind::BadJsonPath(format!("{} is not a :einsteindb.type/json attribute", attribute))),
+            None => bail!(ErrorKind::BadJsonPath(format!("unknown attribute {}", attribute))),
+        }
+    }
+
+    /// Index the causet_locales of the JSON attribute `attribute` on `path`.
+    pub fn create_json_path_index(&self, sqlite: &rusqlite::Connection, attribute: &Keyword, path: &str) -> Result<String> {
+        create_json_path_index(sqlite, self.json_attribute(attribute)?, path)
+    }
+
+    pub fn drop_json_path_index(&self, sqlite: &rusqlite::Connection, attribute: &Keyword, path: &str) -> Result<bool> {
+        drop_json_path_index(sqlite, self.json_attribute(attribute)?, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::einsteindb::new_connection;
+
+    fn store() -> rusqlite::Connection {
+        let conn = new_connection("").expect("opened");
+        conn.execute_alexandrov_poset_process("
+            CREATE TABLE causets (e INTEGER NOT NULL, a SMALLINT NOT NULL, v BLOB NOT NULL,
+                                  causet_locale_type_tag SMALLINT NOT NULL);
+        ").expect("created");
+        let payloads = vec![
+            (1, 100, r#"{"user": {"id": 7, "name": "ann"}, "tags": ["a", "b"]}"#),
+            (2, 100, r#"{"user": {"id": 8, "admin": true}}"#),
+            (3, 100, r#"{"user": null}"#),
+        ];
+        for (e, a, text) in payloads {
+            let json: Json = text.parse().unwrap();
+            conn.execute("INSERT INTO causets VALUES (?, ?, ?, ?)",
+                         &[&e, &a, &json.to_storage_bytes(), &JSON_VALUE_TYPE_TAG]).expect("inserted");
+        }
+        // Not JSON: the function ignores it.
+        conn.execute("INSERT INTO causets VALUES (4, 101, 'text', 10)", &[]).expect("inserted");
+        conn
+    }
+
+    fn extract(conn: &rusqlite::Connection, e: i64, path: &str) -> Value {
+        let sql = format!("SELECT {} FROM causets WHERE e = ?", json_extract_sql("v", path).unwrap());
+        conn.query_row(&sql, &[&e], |row| row.get(0)).expect("extracted")
+    }
+
+    #[test]
+    fn test_canonical_path() {
+        assert_eq!(canonical_path("$ . user . id").unwrap(), "$.user.id");
+        assert_eq!(canonical_path(r#"$."user"[0]"#).unwrap(), "$.user[0]");
+        assert_eq!(canonical_path(r#"$."a b""#).unwrap(), r#"$."a b""#);
+        assert_eq!(canonical_path(" $[*] ").unwrap(), "$[*]");
+        assert!(canonical_path("user.id").is_err());
+    }
+
+    #[test]
+    fn test_json_extract_function() {
+        let conn = store();
+        assert_eq!(extract(&conn, 1, "$.user.id"), Value::Integer(7));
+        assert_eq!(extract(&conn, 1, "$.user.name"), Value::Text("ann".to_string()));
+        assert_eq!(extract(&conn, 1, "$.user"), Value::Text(r#"{"id": 7, "name": "ann"}"#.to_string()));
+        assert_eq!(extract(&conn, 1, "$.tags[*]"), Value::Text(r#"["a", "b"]"#.to_string()));
+        assert_eq!(extract(&conn, 2, "$.user.admin"), Value::Integer(1));
+        assert_eq!(extract(&conn, 2, "$.user.name"), Value::Null);
+        assert_eq!(extract(&conn, 3, "$.user"), Value::Null);
+        assert_eq!(extract(&conn, 4, "$.user"), Value::Null);
+    }
+
+    #[test]
+    fn test_json_path_index() {
+        let conn = store();
+        assert_eq!(create_json_path_index(&conn, 100, "$ . user . id").unwrap(), "$.user.id");
+        // Declaring it again is harmless.
+        create_json_path_index(&conn, 100, "$.user.id").unwrap();
+        assert!(create_json_path_index(&conn, 100, "$.tags[*]").is_err());
+        assert_eq!(json_path_indexes(&conn).unwrap(), vec![(100, "$.user.id".to_string())]);
+
+        // The where-function's BerolinaSQL is answered from the index.
+        let sql = format!("EXPLAIN QUERY PLAN SELECT e FROM causets WHERE a = 100 AND causet_locale_type_tag = {} AND {} = 8",
+                          JSON_VALUE_TYPE_TAG, json_extract_sql("v", "$.user.id").unwrap());
+        let plan: Vec<String> = {
+            let mut stmt = conn.prepare(&sql).unwrap();
+            let rows = stmt.query_map(&[], |row| row.get::<_, String>(3)).unwrap();
+            rows.map(|r| r.unwrap()).collect()
+        };
+        let name = index_name(100, "$.user.id");
+        assert!(plan.iter().any(|step| step.contains(&name)), "expected {} in {:?}", name, plan);
+
+        assert!(drop_json_path_index(&conn, 100, "$.user.id").unwrap());
+        assert!(!drop_json_path_index(&conn, 100, "$.user.id").unwrap());
+        assert!(json_path_indexes(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,555 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#![allow(dead_code)]
+
+//! Algebrizing and translating queries that use the `json-extract` where-function.
+//!
+//! ```edn
+//! [:find ?e ?uid
+//!  :where [?e :event/payload ?p]
+//!         [(json-extract ?p "$.user.id") ?uid]
+//!         [(> ?uid 7)]]
+//! ```
+//!
+//! Each pattern becomes an alias of `causets`, each variable the column (or expression) that
+//! first binds it, and `json-extract` binds its output variable to `json_extract_sql` over the
+//! column of its JSON argument -- the very expression a path index on that attribute is declared
+//! over, so BerolinaSQLite answers it from the index.  Rows where the path is missing or `null`
+//! don't bind the output variable, and so don't match.
+//!
+//! Besides `json-extract`, such queries may hold patterns without a transaction or source, and
+//! the comparison predicates `=`, `!=`, `<`, `<=`, `>` and `>=`.  `Conn::q_once` hands every
+//! query calling `json-extract` to this module; other queries never come here.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use causetq::{
+    Binding,
+    Causetid,
+    causetq_TV,
+    causetq_VT,
+};
+use einstein_ml;
+use einstein_ml::query::{
+    Binding as FnBinding,
+    Direction,
+    Element,
+    FindSpec,
+    FnArg,
+    Limit,
+    NonIntegerConstant,
+    ParsedQuery,
+    Pattern,
+    PatternNonValuePlace,
+    PatternValuePlace,
+    Predicate,
+    SrcVar,
+    Variable,
+    WhereClause,
+    WhereFn,
+};
+use einsteindb_core::{
+    HasTopograph,
+    Topograph,
+};
+use einsteindb_transaction::query::{
+    QueryOutput,
+    QueryResults,
+    RelResult,
+};
+use public_traits::errors::{
+    einsteindbErrorKind,
+    Result,
+};
+use rusqlite;
+use rusqlite::types::{
+    ToSql,
+    ToSqlOutput,
+    Value,
+};
+
+use crate::cache_resolution::constant_causet_locale;
+use crate::einsteindb::TypedBerolinaSQLValue;
+use crate::json_index::{
+    json_extract_sql,
+    JSON_EXTRACT_FN,
+    JSON_VALUE_TYPE_TAG,
+};
+
+/// What a variable is bound to.
+#[derive(Clone, Debug, PartialEq)]
+enum Bound {
+    /// The causet column of a pattern.
+    Causet(String),
+    /// The causet_locale column of a pattern, with its type tag column.
+    CausetLocale {
+        column: String,
+        tag: String,
+        causet_locale_type: causetq_VT,
+    },
+    /// The output of `json-extract`.
+    Extracted(String),
+}
+
+impl Bound {
+    fn expression(&self) -> &str {
+        match self {
+            &Bound::Causet(ref e) | &Bound::Extracted(ref e) => e,
+            &Bound::CausetLocale { ref column, .. } => column,
+        }
+    }
+
+    /// The BerolinaSQL columns projecting this binding takes.
+    fn width(&self) -> usize {
+        match self {
+            &Bound::CausetLocale { .. } => 2,
+            _ => 1,
+        }
+    }
+}
+
+fn unsupported<T>(what: &str) -> Result<T> {
+    bail!(einsteindbErrorKind::NotYetImplemented(format!("{} in a query using {}", what, JSON_EXTRACT_FN)))
+}
+
+/// A query using `json-extract`, translated to BerolinaSQL.
+#[derive(Debug)]
+pub struct JsonQuery {
+    spec: FindSpec,
+    sql: String,
+    params: Vec<Value>,
+    /// The binding of each projected variable, in `:find` order.
+    projected: Vec<Bound>,
+}
+
+struct JsonQueryAlgebrizer<'s> {
+    topograph: &'s Topograph,
+    from: Vec<String>,
+    constraints: Vec<String>,
+    params: Vec<Value>,
+    bindings: BTreeMap<Variable, Bound>,
+}
+
+impl<'s> JsonQueryAlgebrizer<'s> {
+    fn new(topograph: &'s Topograph) -> JsonQueryAlgebrizer<'s> {
+        JsonQueryAlgebrizer {
+            topograph,
+            from: vec![],
+            constraints: vec![],
+            params: vec![],
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    fn param(&mut self, v: Value) -> String {
+        self.params.push(v);
+        "?".to_string()
+    }
+
+    /// Bind `var` to `bound`, or constrain it to equal what already binds it.
+    fn bind(&mut self, var: &Variable, bound: Bound) {
+        match self.bindings.get(var).cloned() {
+            None => {
+                self.bindings.insert(var.clone(), bound);
+            },
+            Some(existing) => {
+                self.constraints.push(format!("{} = {}", existing.expression(), bound.expression()));
+                if let (&Bound::CausetLocale { tag: ref t0, .. }, &Bound::CausetLocale { tag: ref t1, .. }) = (&existing, &bound) {
+                    self.constraints.push(format!("{} = {}", t0, t1));
+                }
+            },
+        }
+    }
+
+    fn causetid(&self, place: &PatternNonValuePlace) -> Result<Option<Causetid>> {
+        match place {
+            &PatternNonValuePlace::Causetid(e) => Ok(Some(e)),
+            &PatternNonValuePlace::Solitonid(ref k) => match self.topograph.get_causetid(k) {
+                Some(e) => Ok(Some(e.0)),
+                None => bail!(einsteindbErrorKind::UnrecognizedSolitonid(k.to_string())),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    fn apply_pattern(&mut self, pattern: &Pattern) -> Result<()> {
+        match pattern.source {
+            None | Some(SrcVar::DefaultSrc) => {},
+            _ => return unsupported("a pattern with a source"),
+        }
+        if pattern.tx != PatternNonValuePlace::Placeholder {
+            return unsupported("a pattern with a transaction");
+        }
+        let a = match self.causetid(&pattern.attribute)? {
+            Some(a) => a,
+            None => return unsupported("a pattern without a constant attribute"),
+        };
+        let causet_locale_type = match self.topograph.attribute_for_causetid(a) {
+            Some(attribute) => attribute.causet_locale_type,
+            None => bail!(einsteindbErrorKind::UnrecognizedCausetid(a)),
+        };
+
+        let alias = format!("causets{:02}", self.from.len());
+        self.from.push(format!("causets AS {}", alias));
+        self.constraints.push(format!("{}.a = {}", alias, a));
+        if causet_locale_type == causetq_VT::Json {
+            // Always true, but it is the condition of every path index on the attribute.
+            self.constraints.push(format!("{}.causet_locale_type_tag = {}", alias, JSON_VALUE_TYPE_TAG));
+        }
+
+        match pattern.causet {
+            PatternNonValuePlace::Variable(ref var) => self.bind(var, Bound::Causet(format!("{}.e", alias))),
+            PatternNonValuePlace::Placeholder => {},
+            ref place => {
+                let e = self.causetid(place)?.expect("a constant causet");
+                self.constraints.push(format!("{}.e = {}", alias, e));
+            },
+        }
+
+        match pattern.causet_locale {
+            PatternValuePlace::Variable(ref var) => self.bind(var, Bound::CausetLocale {
+                column: format!("{}.v", alias),
+                tag: format!("{}.causet_locale_type_tag", alias),
+                causet_locale_type,
+            }),
+            PatternValuePlace::Placeholder => {},
+            ref place => {
+                let v = match constant_causet_locale(self.topograph, causet_locale_type, place) {
+                    Some(v) => v,
+                    None => bail!(einsteindbErrorKind::BadValuePair(format!("{:?}", place), causet_locale_type)),
+                };
+                let (v, tag) = v.to_berolina_sql_causet_locale_pair();
+                let v = match v {
+                    ToSqlOutput::Owned(v) => v,
+                    ToSqlOutput::Borrowed(v) => v.into(),
+                    _ => return unsupported("this constant"),
+                };
+                let p = self.param(v);
+                self.constraints.push(format!("{}.v = {}", alias, p));
+                self.constraints.push(format!("{}.causet_locale_type_tag = {}", alias, tag));
+            },
+        }
+        Ok(())
+    }
+
+    fn apply_json_extract(&mut self, where_fn: &WhereFn) -> Result<()> {
+        let (doc, path) = match where_fn.args.as_slice() {
+            &[FnArg::Variable(ref doc), FnArg::Constant(NonIntegerConstant::Text(ref path))] => (doc, path),
+            _ => return unsupported("json-extract without a variable and a path string"),
+        };
+        let column = match self.bindings.get(doc) {
+            Some(&Bound::CausetLocale { ref column, causet_locale_type: causetq_VT::Json, .. }) => column.clone(),
+            _ => return unsupported("json-extract of a variable no :einsteindb.type/json pattern binds"),
+        };
+        let out = match where_fn.binding {
+            FnBinding::BindScalar(ref out) => out,
+            _ => return unsupported("json-extract bound to anything but a scalar"),
+        };
+        let expression = json_extract_sql(&column, path)
+            .map_err(|e| einsteindbErrorKind::BadValuePair(format!("{} ({})", path, e), causetq_VT::String))?;
+        self.constraints.push(format!("{} IS NOT NULL", expression));
+        self.bind(out, Bound::Extracted(expression));
+        Ok(())
+    }
+
+    fn apply_predicate(&mut self, predicate: &Predicate) -> Result<()> {
+        let op = match predicate.operator.0.as_str() {
+            "=" => "=",
+            "!=" => "<>",
+            "<" => "<",
+            "<=" => "<=",
+            ">" => ">",
+            ">=" => ">=",
+            _ => return unsupported("a predicate other than a comparison"),
+        };
+        if predicate.args.len() != 2 {
+            return unsupported("a comparison of other than two arguments");
+        }
+        let mut operands = vec![];
+        for arg in predicate.args.iter() {
+            operands.push(match arg {
+                &FnArg::Variable(ref var) => match self.bindings.get(var) {
+                    Some(bound) => bound.expression().to_string(),
+                    None => return unsupported("a comparison of a variable no earlier clause binds"),
+                },
+                &FnArg::CausetidOrInteger(x) => self.param(Value::Integer(x)),
+                &FnArg::Constant(NonIntegerConstant::Float(x)) => self.param(Value::Real(x.into_inner())),
+                &FnArg::Constant(NonIntegerConstant::Text(ref s)) => self.param(Value::Text(s.to_string())),
+                &FnArg::Constant(NonIntegerConstant::Boolean(b)) => self.param(Value::Integer(if b { 1 } else { 0 })),
+                _ => return unsupported("this comparison argument"),
+            });
+        }
+        self.constraints.push(format!("{} {} {}", operands[0], op, operands[1]));
+        Ok(())
+    }
+
+    fn projected(&self, var: &Variable) -> Result<Bound> {
+        match self.bindings.get(var) {
+            Some(bound) => Ok(bound.clone()),
+            None => unsupported(&format!("projecting {:?}, which no clause binds,", var)),
+        }
+    }
+
+    fn algebrize(mut self, query: &ParsedQuery) -> Result<JsonQuery> {
+        if !query.with.is_empty() || !query.in_vars.is_empty() || !query.in_sources.is_empty() {
+            return unsupported(":with or :in");
+        }
+        for clause in query.where_clauses.iter() {
+            match clause {
+                &WhereClause::Pattern(ref pattern) => self.apply_pattern(pattern)?,
+                &WhereClause::WhereFn(ref f) if f.operator.0 == JSON_EXTRACT_FN => self.apply_json_extract(f)?,
+                &WhereClause::Pred(ref p) => self.apply_predicate(p)?,
+                _ => return unsupported("a clause other than patterns and comparisons"),
+            }
+        }
+        if self.from.is_empty() {
+            return unsupported("a query without patterns");
+        }
+
+        let mut projected = vec![];
+        let mut columns = vec![];
+        for element in query.find_spec.columns() {
+            let bound = match element {
+                &Element::Variable(ref var) => self.projected(var)?,
+                _ => return unsupported("projecting anything but variables"),
+            };
+            columns.push(bound.expression().to_string());
+            if let Bound::CausetLocale { ref tag, .. } = bound {
+                columns.push(tag.clone());
+            }
+            projected.push(bound);
+        }
+
+        let mut sql = format!("SELECT DISTINCT {} FROM {} WHERE {}",
+                              columns.join(", "), self.from.join(", "), self.constraints.join(" AND "));
+        if let Some(ref order) = query.order {
+            let mut terms = vec![];
+            for &einstein_ml::query::Partition(ref direction, ref var) in order.iter() {
+                let bound = self.projected(var)?;
+                terms.push(format!("{} {}", bound.expression(), match direction {
+                    &Direction::Ascending => "ASC",
+                    &Direction::Descending => "DESC",
+                }));
+            }
+            sql.push_str(&format!(" ORDER BY {}", terms.join(", ")));
+        }
+        let limit = match (&query.find_spec, &query.limit) {
+            (&FindSpec::FindScalar(_), _) | (&FindSpec::FindTuple(_), _) => Some(1),
+            (_, &Limit::Fixed(n)) => Some(n),
+            (_, &Limit::None) => None,
+            (_, &Limit::Variable(_)) => return unsupported("a variable :limit"),
+        };
+        if let Some(limit) = limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        Ok(JsonQuery {
+            spec: query.find_spec.clone(),
+            sql,
+            params: self.params,
+            projected,
+        })
+    }
+}
+
+/// Whether `query` calls the `json-extract` where-function.
+pub fn uses_json_extract(query: &ParsedQuery) -> bool {
+    query.where_clauses.iter().any(|clause| match clause {
+        &WhereClause::WhereFn(ref f) => f.operator.0 == JSON_EXTRACT_FN,
+        _ => false,
+    })
+}
+
+/// Algebrize and translate `query`, which uses `json-extract`.
+pub fn algebrize_json_query(topograph: &Topograph, query: &ParsedQuery) -> Result<JsonQuery> {
+    JsonQueryAlgebrizer::new(topograph).algebrize(query)
+}
+
+/// The causet_locale `json-extract` gave, as BerolinaSQLite returned it.
+fn extracted_causet_locale(v: Value) -> Option<causetq_TV> {
+    match v {
+        Value::Integer(x) => Some(causetq_TV::Long(x)),
+        Value::Real(x) => Some(causetq_TV::Double(x.into())),
+        Value::Text(s) => Some(causetq_TV::typed_string(&s)),
+        _ => None,
+    }
+}
+
+impl JsonQuery {
+    /// The BerolinaSQL the query translated to.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    pub fn params(&self) -> &[Value] {
+        &self.params
+    }
+
+    fn rows(&self, sqlite: &rusqlite::Connection) -> Result<Vec<Vec<Binding>>> {
+        let params: Vec<&ToSql> = self.params.iter().map(|p| p as &ToSql).collect();
+        let mut stmt = sqlite.prepare(&self.sql)?;
+        let mut rows = stmt.query(&params)?;
+        let mut results = vec![];
+        while let Some(row) = rows.next() {
+            let row = row?;
+            let mut i = 0;
+            let mut bindings = Vec::with_capacity(self.projected.len());
+            for bound in self.projected.iter() {
+                let v: Value = row.get_checked(i)?;
+                let causet_locale = match bound {
+                    &Bound::Causet(_) => match v {
+                        Value::Integer(e) => causetq_TV::Ref(e),
+                        v => bail!(einsteindbErrorKind::BadBerolinaSQLValuePair(v, 0)),
+                    },
+                    &Bound::CausetLocale { .. } => {
+                        let tag: i32 = row.get_checked(i + 1)?;
+                        causetq_TV::from_berolina_sql_causet_locale_pair(v, tag)?
+                    },
+                    &Bound::Extracted(_) => match extracted_causet_locale(v) {
+                        Some(causet_locale) => causet_locale,
+                        None => unreachable!("json-extract only binds non-null scalars"),
+                    },
+                };
+                bindings.push(causet_locale.into());
+                i += bound.width();
+            }
+            results.push(bindings);
+        }
+        Ok(results)
+    }
+
+    /// Run the query against `sqlite`.
+    pub fn run(&self, sqlite: &rusqlite::Connection) -> Result<QueryOutput> {
+        let rows = self.rows(sqlite)?;
+        let results = match self.spec {
+            FindSpec::FindScalar(_) =>
+                QueryResults::Scalar(rows.into_iter().next().map(|mut row| row.remove(0))),
+            FindSpec::FindColl(_) =>
+                QueryResults::Coll(rows.into_iter().map(|mut row| row.remove(0)).collect()),
+            FindSpec::FindTuple(_) =>
+                QueryResults::Tuple(rows.into_iter().next()),
+            FindSpec::FindRel(ref elements) =>
+                QueryResults::Rel(RelResult {
+                    width: elements.len(),
+                    values: rows.into_iter().flat_map(|row| row.into_iter()).collect(),
+                }),
+        };
+        Ok(QueryOutput {
+            spec: Rc::new(self.spec.clone()),
+            results,
+        })
+    }
+}
+
+/// Answer `query` if it uses `json-extract`, or return `None` to leave it to the general query
+/// path.  A query that doesn't parse is left for the general path to report.
+pub fn answer_json_query(topograph: &Topograph, sqlite: &rusqlite::Connection, query: &str) -> Result<Option<QueryOutput>> {
+    let parsed = match einstein_ml::parse::query(query) {
+        Ok(parsed) => parsed,
+        Err(_) => return Ok(None),
+    };
+    if !uses_json_extract(&parsed) {
+        return Ok(None);
+    }
+    algebrize_json_query(topograph, &parsed)?.run(sqlite).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeSet;
+
+    use einsteindb_core::Keyword;
+
+    use crate::conn::Conn;
+    use crate::einsteindb;
+
+    fn events() -> (rusqlite::Connection, Conn, BTreeMap<String, Causetid>) {
+        let mut sqlite = einsteindb::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+        conn.transact(&mut sqlite, r#"[
+            {:einsteindb/solitonid :event/payload
+             :einsteindb/causet_localeType :einsteindb.type/json
+             :einsteindb/cardinality :einsteindb.cardinality/one}
+            {:einsteindb/solitonid :event/kind
+             :einsteindb/causet_localeType :einsteindb.type/soliton_idword
+             :einsteindb/cardinality :einsteindb.cardinality/one}
+        ]"#).expect("topograph");
+        let report = conn.transact(&mut sqlite, r#"[
+            {:einsteindb/id "ann" :event/kind :kind/login :event/payload "{\"user\": {\"id\": 7, \"name\": \"ann\"}}"}
+            {:einsteindb/id "bob" :event/kind :kind/login :event/payload "{\"user\": {\"id\": 8}}"}
+            {:einsteindb/id "cat" :event/kind :kind/logout :event/payload "{\"user\": {\"id\": 9}}"}
+            {:einsteindb/id "anon" :event/kind :kind/login :event/payload "{\"user\": null}"}
+        ]"#).expect("events");
+        (sqlite, conn, report.tempids)
+    }
+
+    fn rows(output: QueryOutput) -> BTreeSet<Vec<causetq_TV>> {
+        match output.results {
+            QueryResults::Rel(rel) => rel.values
+                                         .chunks(rel.width)
+                                         .map(|row| row.iter().map(|b| b.clone().into_scalar().expect("scalar")).collect())
+                                         .collect(),
+            QueryResults::Coll(values) => values.into_iter().map(|b| vec![b.into_scalar().expect("scalar")]).collect(),
+            other => panic!("expected a relation or a collection, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_extract_query() {
+        let (sqlite, conn, events) = events();
+        let query = r#"[:find ?e ?uid :where [?e :event/payload ?p] [(json-extract ?p "$.user.id") ?uid]]"#;
+        let expected: BTreeSet<Vec<causetq_TV>> = vec![
+            vec![causetq_TV::Ref(events["ann"]), causetq_TV::Long(7)],
+            vec![causetq_TV::Ref(events["bob"]), causetq_TV::Long(8)],
+            vec![causetq_TV::Ref(events["cat"]), causetq_TV::Long(9)],
+        ].into_iter().collect();
+        assert_eq!(rows(conn.q_once(&conn, query, &sqlite, None).expect("answered")), expected);
+
+        // Joined with other patterns and filtered by a comparison.
+        let query = r#"[:find [?e ...] :where [?e :event/kind :kind/login]
+                                            [?e :event/payload ?p]
+                                            [(json-extract ?p "$ . user . id") ?uid]
+                                            [(> ?uid 7)]]"#;
+        assert_eq!(rows(conn.q_once(&conn, query, &sqlite, None).expect("answered")),
+                   vec![vec![causetq_TV::Ref(events["bob"])]].into_iter().collect());
+
+        // Strings come back as strings; a missing path binds nothing.
+        let query = r#"[:find ?name . :where [?e :event/payload ?p] [(json-extract ?p "$.user.name") ?name]]"#;
+        let output = conn.q_once(&conn, query, &sqlite, None).expect("answered");
+        assert_eq!(output.results, QueryResults::Scalar(Some(causetq_TV::typed_string("ann").into())));
+
+        // Calling it on anything but a JSON causet_locale is refused.
+        let query = r#"[:find ?x :where [?e :event/kind ?k] [(json-extract ?k "$.a") ?x]]"#;
+        assert!(conn.q_once(&conn, query, &sqlite, None).is_err());
+    }
+
+    #[test]
+    fn test_json_extract_query_uses_path_index() {
+        let (sqlite, conn, _) = events();
+        let index = conn.create_json_path_index(&sqlite, &Keyword::namespaced("event", "payload"), "$.user.id")
+                        .expect("indexed");
+        assert_eq!(index, "$.user.id");
+
+        let parsed = einstein_ml::parse::query(r#"[:find ?e :where [?e :event/payload ?p]
+                                                                  [(json-extract ?p "$.user.id") ?uid]
+                                                                  [(= ?uid 8)]]"#).expect("parsed");
+        let translated = algebrize_json_query(&conn.current_schema(), &parsed).expect("algebrized");
+        assert!(translated.sql().contains(&json_extract_sql("causets00.v", "$.user.id").unwrap()), "{}", translated.sql());
+
+        let params: Vec<&ToSql> = translated.params().iter().map(|p| p as &ToSql).collect();
+        let mut stmt = sqlite.prepare(&format!("EXPLAIN QUERY PLAN {}", translated.sql())).unwrap();
+        let plan: Vec<String> = stmt.query_map(&params, |row| row.get::<_, String>(3)).unwrap().map(|r| r.unwrap()).collect();
+        assert!(plan.iter().any(|step| step.contains("idx_causets_json_")), "expected the path index in {:?}", plan);
+    }
+}
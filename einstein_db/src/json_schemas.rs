@@ -0,0 +1,180 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! JSON Schemas of `:einsteindb.type/json` attributes.
+//!
+//! A schema is attached to an attribute by asserting its text on the attribute:
+//!
+//! ```edn
+//! [{:einsteindb/solitonid :event/payload
+//!   :einsteindb/causet_localeType :einsteindb.type/json
+//!   :einsteindb/cardinality :einsteindb.cardinality/one
+//!   :einsteindb/jsonSchema "{\"type\": \"object\", \"required\": [\"user\"]}"}]
+//! ```
+//!
+//! The transactor checks every JSON causet_locale asserted on such an attribute against the
+//! schema, whichever front-end the transaction came from, and fails the transaction on the first
+//! causet_locale that doesn't conform.  Causet_locales are checked against the schema their
+//! attribute had when the transaction began; a schema asserted in a transaction applies from the
+//! next one on, and causet_locales stored before it are not checked again.
+
+use std::collections::BTreeMap;
+
+use causetq::{
+    Causetid,
+    causetq_TV,
+    causetq_VT,
+};
+use public_traits::errors::{
+    einsteindbErrorKind,
+    Result,
+};
+use einstein_json::Json;
+use einstein_json::json::json_schema::JsonSchema;
+
+use rusqlite;
+
+use crate::causetids;
+use crate::einsteindb::Reducedcauset;
+
+fn compile(schema: &str) -> Result<JsonSchema> {
+    schema.parse::<Json>()
+        .map_err(|e| e.to_string())
+        .and_then(|json| JsonSchema::new(json.as_ref()).map_err(|e| e.to_string()))
+        .map_err(|e| einsteindbErrorKind::BadTopographAssertion(format!(":einsteindb/jsonSchema {:?} is not a JSON Schema: {}", schema, e)).into())
+}
+
+/// The attributes that `causets` assert JSON causet_locales of, whose stored schemas
+/// `check_json_schemas` needs.
+pub fn json_attributes<'a>(causets: &'a [Reducedcauset<'a>]) -> Vec<Causetid> {
+    let mut attributes: Vec<Causetid> = causets.iter()
+        .filter_map(|&(_, a, _, ref v, added)| match *v {
+            causetq_TV::Json(_) if added => Some(a),
+            _ => None,
+        })
+        .collect();
+    attributes.sort();
+    attributes.dedup();
+    attributes
+}
+
+/// The schema text stored on each of `attributes` that has one, in the BerolinaSQL store.
+pub fn stored_json_schemas(conn: &rusqlite::Connection, attributes: &[Causetid]) -> Result<BTreeMap<Causetid, String>> {
+    let mut stored = BTreeMap::new();
+    if attributes.is_empty() {
+        return Ok(stored);
+    }
+    let mut stmt = conn.prepare_cached("SELECT v FROM causets WHERE e = ? AND a = ?")?;
+    for &a in attributes {
+        let mut rows = stmt.query(&[&a, &causetids::EINSTEINDB_JSON_SCHEMA])?;
+        if let Some(row) = rows.next() {
+            stored.insert(a, row?.get_checked(0)?);
+        }
+    }
+    Ok(stored)
+}
+
+/// Check the JSON causet_locales asserted by `causets` against the schemas of their attributes,
+/// and the schemas asserted by `causets` for being JSON Schemas at all.  `stored` holds the
+/// schema text stored on each attribute `json_attributes` returned that has one.
+pub fn check_json_schemas<'a>(causets: &'a [Reducedcauset<'a>], stored: &BTreeMap<Causetid, String>) -> Result<()> {
+    for &(e, a, _, ref v, added) in causets {
+        if a != causetids::EINSTEINDB_JSON_SCHEMA || !added {
+            continue;
+        }
+        match *v {
+            causetq_TV::String(ref s) => {
+                compile(s)?;
+            },
+            _ => bail!(einsteindbErrorKind::BadTopographAssertion(format!(":einsteindb/jsonSchema of {} is not a string", e))),
+        }
+    }
+
+    // Each schema is compiled once per call, however many causet_locales it checks.
+    let mut schemas: BTreeMap<Causetid, JsonSchema> = BTreeMap::new();
+    for (&a, text) in stored {
+        schemas.insert(a, compile(text)?);
+    }
+
+    for &(_, a, _, ref v, added) in causets {
+        let doc = match *v {
+            causetq_TV::Json(ref doc) if added => doc,
+            _ => continue,
+        };
+        if let Some(schema) = schemas.get(&a) {
+            let failure = schema.validate(Json::as_ref(doc))
+                .map_err(|e| einsteindbErrorKind::BadValuePair(format!("{} ({})", doc, e), causetq_VT::Json))?;
+            if let Some(failure) = failure {
+                bail!(einsteindbErrorKind::BadValuePair(format!("{} ({})", doc, failure.reason()), causetq_VT::Json));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::conn::Conn;
+    use crate::einsteindb;
+
+    fn payload_store() -> (rusqlite::Connection, Conn) {
+        let mut sqlite = einsteindb::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+        conn.transact(&mut sqlite, r#"[
+            {:einsteindb/solitonid :event/payload
+             :einsteindb/causet_localeType :einsteindb.type/json
+             :einsteindb/cardinality :einsteindb.cardinality/many
+             :einsteindb/jsonSchema "{\"type\": \"object\", \"required\": [\"user\"], \"properties\": {\"user\": {\"type\": \"integer\", \"minimum\": 1}}}"}
+        ]"#).expect("schema");
+        (sqlite, conn)
+    }
+
+    #[test]
+    fn test_conforming_causet_locales_transact() {
+        let (mut sqlite, mut conn) = payload_store();
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add "e" :event/payload "{\"user\": 7}"]]"#)
+            .expect("conforming causet_locale transacted");
+    }
+
+    #[test]
+    fn test_nonconforming_causet_locales_fail() {
+        let (mut sqlite, mut conn) = payload_store();
+        let err = conn.transact(&mut sqlite, r#"[[:einsteindb/add "e" :event/payload "{\"user\": 0}"]]"#)
+            .expect_err("minimum");
+        assert!(err.to_string().contains("minimum"), "{}", err);
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add "e" :event/payload "{\"name\": \"ann\"}"]]"#)
+            .expect_err("required");
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add "e" :event/payload "[1, 2]"]]"#)
+            .expect_err("type");
+    }
+
+    #[test]
+    fn test_replaced_schema_applies_from_next_transaction() {
+        let (mut sqlite, mut conn) = payload_store();
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add :event/payload :einsteindb/jsonSchema "{\"type\": \"array\"}"]
+                                       [:einsteindb/add "e" :event/payload "{\"user\": 7}"]]"#)
+            .expect("the old schema still applies");
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add "e" :event/payload "[1, 2]"]]"#)
+            .expect("the new schema applies");
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add "e" :event/payload "{\"user\": 7}"]]"#)
+            .expect_err("the old schema no longer does");
+    }
+
+    #[test]
+    fn test_malformed_schema_fails() {
+        let (mut sqlite, mut conn) = payload_store();
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add :event/payload :einsteindb/jsonSchema "{\"pattern\": \"(\"}"]]"#)
+            .expect_err("bad pattern");
+        conn.transact(&mut sqlite, r#"[[:einsteindb/add :event/payload :einsteindb/jsonSchema "not json"]]"#)
+            .expect_err("not JSON");
+    }
+}
@@ -0,0 +1,780 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#![allow(dead_code)]
+
+//! `EinsteinStoring` on top of a soliton_id-causet_locale einstein_merkle_tree.
+//!
+//! Every datom is written under up to four soliton_ids of the default causet_merge family, one
+//! per covering index, so each index is a plain ordered range scan:
+//!
+//! ```text
+//! EAVT  'e' e a v  -> tx flags
+//! AEVT  'a' a e v  -> tx
+//! AVET  'v' a v e  -> tx      only for attributes flagged :einsteindb/index or :einsteindb/unique
+//! VAET  'r' v a e  -> tx      only for :einsteindb.type/ref attributes
+//! log   't' tx e a v added -> (empty)
+//! ```
+//!
+//! Causetids are big-endian with the sign bit flipped, and causet_locales are a type tag followed
+//! by an order-preserving encoding, so soliton_id order is causet order.  Strings, keywords and
+//! JSON are escaped and terminated, which keeps every component self-delimiting.
+//!
+//! The transaction protocol mirrors the BerolinaSQL store: searches are collected by
+//! `insert_non_fts_searches`, resolved against EAVT by `materialize_einstdb_causet`, which also
+//! checks AVET so that no two causets share a causet_locale of a `:einsteindb/unique`
+//! attribute, and every resulting index and log write is applied as a single write
+//! alexandrov_poset_process by `commit_einstdb_causet`, so a transaction is either entirely
+//! visible or not at all.  Fulltext
+//! causet_locales are stored inline as strings; there is no fulltext index to search them by.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use fdb_traits::{
+    Iterable,
+    KvEinsteinMerkleTree,
+    Mutable,
+    Peekable,
+    WriteBatch,
+    WriteBatchExt,
+};
+use causetq::{
+    Attribute,
+    AttributeBitFlags,
+    Causetid,
+    causetq_TV,
+    FromMicros,
+    ToMicros,
+};
+use einstein_json::Json;
+use uuid::Uuid;
+
+use crate::causetids;
+use crate::einsteindb::{
+    EinsteinStoring,
+    Reducedcauset,
+    SearchType,
+};
+use crate::error::{
+    ErrorKind,
+    Result,
+};
+use crate::json_schemas;
+use crate::types::{
+    AVMap,
+    AVPair,
+};
+use crate::to_isoliton_namespaceable_soliton_idword;
+
+const EAVT: u8 = b'e';
+const AEVT: u8 = b'a';
+const AVET: u8 = b'v';
+const VAET: u8 = b'r';
+const LOG: u8 = b't';
+
+// Type tags of encoded causet_locales.  Unlike the BerolinaSQL `causet_locale_type_tag`, longs and
+// doubles get tags of their own, since nothing else tells their encodings apart.
+const TAG_REF: u8 = 0;
+const TAG_BOOLEAN: u8 = 1;
+const TAG_INSTANT: u8 = 4;
+const TAG_LONG: u8 = 5;
+const TAG_DOUBLE: u8 = 6;
+const TAG_STRING: u8 = 10;
+const TAG_UUID: u8 = 11;
+const TAG_KEYWORD: u8 = 13;
+const TAG_JSON: u8 = 14;
+
+fn store_error<E: ToString>(e: E) -> ErrorKind {
+    ErrorKind::EinsteinMerkleTree(e.to_string())
+}
+
+fn corrupt(what: &str) -> ErrorKind {
+    ErrorKind::EinsteinMerkleTree(format!("corrupt datom soliton_id: {}", what))
+}
+
+fn put_i64(buf: &mut Vec<u8>, x: i64) {
+    buf.extend_from_slice(&((x as u64) ^ (1 << 63)).to_be_bytes());
+}
+
+fn get_i64(bytes: &[u8]) -> Result<(i64, &[u8])> {
+    if bytes.len() < 8 {
+        bail!(corrupt("truncated integer"));
+    }
+    let mut b = [0; 8];
+    b.copy_from_slice(&bytes[..8]);
+    Ok(((u64::from_be_bytes(b) ^ (1 << 63)) as i64, &bytes[8..]))
+}
+
+/// Append `bytes` with every `0x00` escaped as `0x00 0xff`, terminated by `0x00 0x01`.
+fn put_escaped(buf: &mut Vec<u8>, bytes: &[u8]) {
+    for &b in bytes {
+        buf.push(b);
+        if b == 0 {
+            buf.push(0xff);
+        }
+    }
+    buf.extend_from_slice(&[0, 1]);
+}
+
+fn get_escaped(bytes: &[u8]) -> Result<(Vec<u8>, &[u8])> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != 0 {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(0xff) => out.push(0),
+            Some(1) => return Ok((out, &bytes[i + 2..])),
+            _ => bail!(corrupt("bad escape")),
+        }
+        i += 2;
+    }
+    bail!(corrupt("unterminated bytes"))
+}
+
+/// Append the order-preserving encoding of `v`.
+pub fn encode_causet_locale(buf: &mut Vec<u8>, v: &causetq_TV) {
+    match v {
+        &causetq_TV::Ref(x) => {
+            buf.push(TAG_REF);
+            put_i64(buf, x);
+        },
+        &causetq_TV::Boolean(x) => {
+            buf.push(TAG_BOOLEAN);
+            buf.push(x as u8);
+        },
+        &causetq_TV::Instant(x) => {
+            buf.push(TAG_INSTANT);
+            put_i64(buf, x.to_micros());
+        },
+        &causetq_TV::Long(x) => {
+            buf.push(TAG_LONG);
+            put_i64(buf, x);
+        },
+        &causetq_TV::Double(x) => {
+            // Flip every bit of negative numbers and only the sign of the others, so that the
+            // bit patterns sort like the numbers.
+            let bits = x.into_inner().to_bits();
+            let bits = if bits >> 63 == 1 { !bits } else { bits ^ (1 << 63) };
+            buf.push(TAG_DOUBLE);
+            buf.extend_from_slice(&bits.to_be_bytes());
+        },
+        &causetq_TV::String(ref x) => {
+            buf.push(TAG_STRING);
+            put_escaped(buf, x.as_bytes());
+        },
+        &causetq_TV::Uuid(ref u) => {
+            buf.push(TAG_UUID);
+            buf.extend_from_slice(u.as_bytes());
+        },
+        &causetq_TV::Keyword(ref x) => {
+            buf.push(TAG_KEYWORD);
+            put_escaped(buf, x.to_string().as_bytes());
+        },
+        &causetq_TV::Json(ref j) => {
+            buf.push(TAG_JSON);
+            put_escaped(buf, &j.to_storage_bytes());
+        },
+    }
+}
+
+/// Decode a causet_locale written by `encode_causet_locale`, returning it and the bytes after it.
+pub fn decode_causet_locale(bytes: &[u8]) -> Result<(causetq_TV, &[u8])> {
+    let (&tag, rest) = match bytes.split_first() {
+        Some(split) => split,
+        None => bail!(corrupt("missing causet_locale")),
+    };
+    match tag {
+        TAG_REF => get_i64(rest).map(|(x, rest)| (causetq_TV::Ref(x), rest)),
+        TAG_BOOLEAN => match rest.split_first() {
+            Some((&b, rest)) => Ok((causetq_TV::Boolean(b != 0), rest)),
+            None => bail!(corrupt("truncated boolean")),
+        },
+        TAG_INSTANT => get_i64(rest).map(|(x, rest)| (causetq_TV::Instant(DateTime::<Utc>::from_micros(x)), rest)),
+        TAG_LONG => get_i64(rest).map(|(x, rest)| (causetq_TV::Long(x), rest)),
+        TAG_DOUBLE => {
+            if rest.len() < 8 {
+                bail!(corrupt("truncated double"));
+            }
+            let mut b = [0; 8];
+            b.copy_from_slice(&rest[..8]);
+            let bits = u64::from_be_bytes(b);
+            let bits = if bits >> 63 == 1 { bits ^ (1 << 63) } else { !bits };
+            Ok((causetq_TV::Double(f64::from_bits(bits).into()), &rest[8..]))
+        },
+        TAG_STRING => {
+            let (s, rest) = get_escaped(rest)?;
+            let s = String::from_utf8(s).map_err(|_| corrupt("string is not UTF-8"))?;
+            Ok((s.into(), rest))
+        },
+        TAG_UUID => {
+            if rest.len() < 16 {
+                bail!(corrupt("truncated uuid"));
+            }
+            let u = Uuid::from_bytes(&rest[..16]).map_err(|_| corrupt("bad uuid"))?;
+            Ok((causetq_TV::Uuid(u), &rest[16..]))
+        },
+        TAG_KEYWORD => {
+            let (s, rest) = get_escaped(rest)?;
+            let s = String::from_utf8(s).map_err(|_| corrupt("keyword is not UTF-8"))?;
+            Ok((to_isoliton_namespaceable_soliton_idword(&s)?.into(), rest))
+        },
+        TAG_JSON => {
+            let (j, rest) = get_escaped(rest)?;
+            let j = Json::from_storage_bytes(&j).map_err(|_| corrupt("bad JSON"))?;
+            Ok((causetq_TV::Json(j.into()), rest))
+        },
+        _ => bail!(corrupt("unknown causet_locale tag")),
+    }
+}
+
+fn eavt_key(e: Causetid, a: Causetid, v: &causetq_TV) -> Vec<u8> {
+    let mut k = vec![EAVT];
+    put_i64(&mut k, e);
+    put_i64(&mut k, a);
+    encode_causet_locale(&mut k, v);
+    k
+}
+
+fn aevt_key(e: Causetid, a: Causetid, v: &causetq_TV) -> Vec<u8> {
+    let mut k = vec![AEVT];
+    put_i64(&mut k, a);
+    put_i64(&mut k, e);
+    encode_causet_locale(&mut k, v);
+    k
+}
+
+fn avet_prefix(a: Causetid, v: &causetq_TV) -> Vec<u8> {
+    let mut k = vec![AVET];
+    put_i64(&mut k, a);
+    encode_causet_locale(&mut k, v);
+    k
+}
+
+fn avet_key(e: Causetid, a: Causetid, v: &causetq_TV) -> Vec<u8> {
+    let mut k = avet_prefix(a, v);
+    put_i64(&mut k, e);
+    k
+}
+
+fn vaet_key(e: Causetid, a: Causetid, v: &causetq_TV) -> Vec<u8> {
+    let mut k = vec![VAET];
+    encode_causet_locale(&mut k, v);
+    put_i64(&mut k, a);
+    put_i64(&mut k, e);
+    k
+}
+
+fn log_prefix(tx: Causetid) -> Vec<u8> {
+    let mut k = vec![LOG];
+    put_i64(&mut k, tx);
+    k
+}
+
+fn log_key(tx: Causetid, e: Causetid, a: Causetid, v: &causetq_TV, added: bool) -> Vec<u8> {
+    let mut k = log_prefix(tx);
+    put_i64(&mut k, e);
+    put_i64(&mut k, a);
+    encode_causet_locale(&mut k, v);
+    k.push(added as u8);
+    k
+}
+
+/// The first soliton_id after every soliton_id starting with `prefix`.
+fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    // Only possible for an all-0xff prefix, which we never build.
+    Vec::new()
+}
+
+fn unique_conflict(a: Causetid, v: &causetq_TV, held_by: Causetid, e: Causetid) -> ErrorKind {
+    ErrorKind::Causet(format!("causet_locale {:?} of unique attribute {} is held by {}, not {}", v, a, held_by, e))
+}
+
+/// A search collected by `insert_non_fts_searches`: (e a v added flags).
+struct Search {
+    e: Causetid,
+    a: Causetid,
+    v: causetq_TV,
+    added: bool,
+    flags: u8,
+    search_type: SearchType,
+}
+
+/// A search together with what it found in EAVT: the causet_locale `v` for an exact search, any
+/// causet_locale of `[e a]` for an inexact one.
+struct SearchResult {
+    search: Search,
+    existing: Option<causetq_TV>,
+}
+
+impl SearchResult {
+    /// Whether this search asserts a datom that is not there yet.
+    fn adds(&self) -> bool {
+        self.search.added && self.existing.as_ref() != Some(&self.search.v)
+    }
+
+    /// The causet_locale this search retracts, either explicitly or by replacing it under
+    /// :einsteindb.cardinality/one.
+    fn retracts(&self) -> Option<&causetq_TV> {
+        let existing = self.existing.as_ref()?;
+        let replaced = self.search.search_type == SearchType::Inexact && existing != &self.search.v;
+        if !self.search.added || replaced {
+            Some(existing)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Default)]
+struct TxState {
+    searches: Vec<Search>,
+    results: Vec<SearchResult>,
+}
+
+/// An `EinsteinStoring` keeping the datoms in an einstein_merkle_tree.
+pub struct KvStoring<E> {
+    einstein_merkle_tree: E,
+    tx: Mutex<TxState>,
+}
+
+impl<E: KvEinsteinMerkleTree> KvStoring<E> {
+    pub fn new(einstein_merkle_tree: E) -> KvStoring<E> {
+        KvStoring {
+            einstein_merkle_tree,
+            tx: Mutex::new(TxState::default()),
+        }
+    }
+
+    pub fn einstein_merkle_tree(&self) -> &E {
+        &self.einstein_merkle_tree
+    }
+
+    fn scan_prefix<F>(&self, prefix: &[u8], mut f: F) -> Result<()>
+        where F: FnMut(&[u8], &[u8]) -> Result<bool> {
+        let mut inner = None;
+        self.einstein_merkle_tree
+            .scan(prefix, &prefix_end(prefix), true, |k, v| match f(&k[prefix.len()..], v) {
+                Ok(more) => Ok(more),
+                Err(e) => {
+                    inner = Some(e);
+                    Ok(false)
+                },
+            })
+            .map_err(store_error)?;
+        match inner {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// The datoms of `e`, as (a v tx), in attribute then causet_locale order.
+    pub fn causets_for(&self, e: Causetid) -> Result<Vec<(Causetid, causetq_TV, Causetid)>> {
+        let mut prefix = vec![EAVT];
+        put_i64(&mut prefix, e);
+        let mut causets = Vec::new();
+        self.scan_prefix(&prefix, |k, v| {
+            let (a, k) = get_i64(k)?;
+            let (causet_locale, _) = decode_causet_locale(k)?;
+            let (tx, _) = get_i64(v)?;
+            causets.push((a, causet_locale, tx));
+            Ok(true)
+        })?;
+        Ok(causets)
+    }
+
+    /// The causets that `a` points at `v`, through VAET, in attribute then causet order.
+    pub fn referencing(&self, v: Causetid) -> Result<Vec<(Causetid, Causetid)>> {
+        let mut prefix = vec![VAET];
+        encode_causet_locale(&mut prefix, &causetq_TV::Ref(v));
+        let mut causets = Vec::new();
+        self.scan_prefix(&prefix, |k, _| {
+            let (a, k) = get_i64(k)?;
+            let (e, _) = get_i64(k)?;
+            causets.push((a, e));
+            Ok(true)
+        })?;
+        Ok(causets)
+    }
+
+    /// The [e a v added] datoms of transaction `tx`, as the transaction log has them.
+    pub fn transaction(&self, tx: Causetid) -> Result<Vec<(Causetid, Causetid, causetq_TV, bool)>> {
+        let mut causets = Vec::new();
+        self.scan_prefix(&log_prefix(tx), |k, _| {
+            let (e, k) = get_i64(k)?;
+            let (a, k) = get_i64(k)?;
+            let (v, k) = decode_causet_locale(k)?;
+            causets.push((e, a, v, k == [1]));
+            Ok(true)
+        })?;
+        Ok(causets)
+    }
+
+    /// Look `[e a]` (or `[e a v]`, when `v` is given) up in EAVT.
+    fn find(&self, e: Causetid, a: Causetid, v: Option<&causetq_TV>) -> Result<Option<causetq_TV>> {
+        if let Some(v) = v {
+            let found = self.einstein_merkle_tree.get_value(&eavt_key(e, a, v)).map_err(store_error)?;
+            return Ok(found.map(|_| v.clone()));
+        }
+        let mut prefix = vec![EAVT];
+        put_i64(&mut prefix, e);
+        put_i64(&mut prefix, a);
+        let mut found = None;
+        self.scan_prefix(&prefix, |k, _| {
+            found = Some(decode_causet_locale(k)?.0);
+            Ok(false)
+        })?;
+        Ok(found)
+    }
+
+    /// Fail if `results` would give two causets the same causet_locale of a `:einsteindb/unique`
+    /// attribute, which the BerolinaSQL store refuses with its unique index on AVET.  A causet_locale
+    /// that moves to another causet is fine as long as the transaction retracts it from the first.
+    fn check_unique(&self, results: &[SearchResult]) -> Result<()> {
+        let mut asserted: BTreeMap<(Causetid, &causetq_TV), Causetid> = BTreeMap::new();
+        let unique_adds = results.iter().filter(|r| r.search.flags & AttributeBitFlags::UniqueValue as u8 != 0 && r.adds());
+        for r in unique_adds {
+            let (e, a, v) = (r.search.e, r.search.a, &r.search.v);
+            if let Some(&other) = asserted.get(&(a, v)) {
+                if other != e {
+                    bail!(unique_conflict(a, v, other, e));
+                }
+                continue;
+            }
+            asserted.insert((a, v), e);
+
+            let mut holder = None;
+            self.scan_prefix(&avet_prefix(a, v), |k, _| {
+                let (other, _) = get_i64(k)?;
+                let retracted = results.iter().any(|r| r.search.e == other && r.search.a == a && r.retracts() == Some(v));
+                if other != e && !retracted {
+                    holder = Some(other);
+                    return Ok(false);
+                }
+                Ok(true)
+            })?;
+            if let Some(other) = holder {
+                bail!(unique_conflict(a, v, other, e));
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_searches<'a>(&self, causets: &'a [Reducedcauset<'a>], search_type: SearchType, fulltext: bool) -> Result<()> {
+        let mut state = self.tx.lock().unwrap();
+        for &(e, a, attribute, ref v, added) in causets {
+            if fulltext {
+                if let causetq_TV::String(_) = *v {} else {
+                    bail!(ErrorKind::EinsteinMerkleTree(format!("fulltext causet_locale of [{} {}] is not a string", e, a)));
+                }
+            }
+            // The BerolinaSQL store enforces this with a unique index on its inexact searches.
+            if search_type == SearchType::Inexact && added &&
+                state.searches.iter().any(|s| s.search_type == SearchType::Inexact && s.added && s.e == e && s.a == a) {
+                bail!(ErrorKind::EinsteinMerkleTree(format!("[{} {}] asserted twice under :einsteindb.cardinality/one", e, a)));
+            }
+            state.searches.push(Search {
+                e,
+                a,
+                v: v.clone(),
+                added,
+                flags: attribute.flags(),
+                search_type: search_type.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<E: KvEinsteinMerkleTree> EinsteinStoring for KvStoring<E> {
+    fn resolve_avs<'a>(&self, avs: &'a [&'a AVPair]) -> Result<AVMap<'a>> {
+        let mut m = AVMap::default();
+        for &av in avs {
+            let &(a, ref v) = av;
+            let mut found = None;
+            self.scan_prefix(&avet_prefix(a, v), |k, _| {
+                found = Some(get_i64(k)?.0);
+                Ok(false)
+            })?;
+            if let Some(e) = found {
+                m.insert(av, e);
+            }
+        }
+        Ok(m)
+    }
+
+    fn begin_tx_application(&self) -> Result<()> {
+        *self.tx.lock().unwrap() = TxState::default();
+        Ok(())
+    }
+
+    fn insert_non_fts_searches<'a>(&self, causets: &'a [Reducedcauset], search_type: SearchType) -> Result<()> {
+        let mut stored = BTreeMap::new();
+        for a in json_schemas::json_attributes(causets) {
+            if let Some(causetq_TV::String(schema)) = self.find(a, causetids::EINSTEINDB_JSON_SCHEMA, None)? {
+                stored.insert(a, schema.to_string());
+            }
+        }
+        json_schemas::check_json_schemas(causets, &stored).map_err(|e| ErrorKind::Causet(e.to_string()))?;
+        self.insert_searches(causets, search_type, false)
+    }
+
+    fn insert_fts_searches<'a>(&self, causets: &'a [Reducedcauset], search_type: SearchType) -> Result<()> {
+        self.insert_searches(causets, search_type, true)
+    }
+
+    /// Resolve the collected searches against EAVT.  Nothing is written until
+    /// `commit_einstdb_causet`.
+    fn materialize_einstdb_causet(&self, _tx_id: Causetid) -> Result<()> {
+        let mut state = self.tx.lock().unwrap();
+        let searches = ::std::mem::replace(&mut state.searches, Vec::new());
+        let mut results = Vec::with_capacity(searches.len());
+        for search in searches {
+            let existing = match search.search_type {
+                SearchType::Exact => self.find(search.e, search.a, Some(&search.v))?,
+                SearchType::Inexact => self.find(search.e, search.a, None)?,
+            };
+            results.push(SearchResult { search, existing });
+        }
+        self.check_unique(&results)?;
+        state.results = results;
+        Ok(())
+    }
+
+    /// Apply the materialized transaction to the indexes and the transaction log, atomically.
+    fn commit_einstdb_causet(&self, tx_id: Causetid) -> Result<()> {
+        let mut state = self.tx.lock().unwrap();
+        let mut wb = self.einstein_merkle_tree.write_alexandrov_poset_process();
+        let mut tx = Vec::new();
+        put_i64(&mut tx, tx_id);
+
+        // Retract first: a cardinality-one replacement retracts and asserts under the same [e a].
+        for r in &state.results {
+            if let Some(old) = r.retracts() {
+                let (e, a) = (r.search.e, r.search.a);
+                wb.delete(&eavt_key(e, a, old)).map_err(store_error)?;
+                wb.delete(&aevt_key(e, a, old)).map_err(store_error)?;
+                wb.delete(&avet_key(e, a, old)).map_err(store_error)?;
+                wb.delete(&vaet_key(e, a, old)).map_err(store_error)?;
+                wb.put(&log_key(tx_id, e, a, old, false), &[]).map_err(store_error)?;
+            }
+        }
+        for r in state.results.iter().filter(|r| r.adds()) {
+            let Search { e, a, ref v, flags, .. } = r.search;
+            let mut eavt_causet_locale = tx.clone();
+            eavt_causet_locale.push(flags);
+            wb.put(&eavt_key(e, a, v), &eavt_causet_locale).map_err(store_error)?;
+            wb.put(&aevt_key(e, a, v), &tx).map_err(store_error)?;
+            if flags & (AttributeBitFlags::IndexAVET as u8 | AttributeBitFlags::UniqueValue as u8) != 0 {
+                wb.put(&avet_key(e, a, v), &tx).map_err(store_error)?;
+            }
+            if let causetq_TV::Ref(_) = *v {
+                if flags & AttributeBitFlags::IndexVAET as u8 != 0 {
+                    wb.put(&vaet_key(e, a, v), &tx).map_err(store_error)?;
+                }
+            }
+            wb.put(&log_key(tx_id, e, a, v, true), &[]).map_err(store_error)?;
+        }
+        wb.write().map_err(store_error)?;
+        *state = TxState::default();
+        Ok(())
+    }
+
+    fn resolved_spacetime_lightlike_dagger_upsert(&self) -> Result<Vec<(Causetid, Causetid, causetq_TV, bool)>> {
+        let state = self.tx.lock().unwrap();
+        let mut causets = Vec::new();
+        for r in state.results.iter().filter(|r| causetids::might_update_spacetime(r.search.a)) {
+            if r.adds() {
+                causets.push((r.search.e, r.search.a, r.search.v.clone(), true));
+            }
+            if let Some(old) = r.retracts() {
+                causets.push((r.search.e, r.search.a, old.clone(), false));
+            }
+        }
+        causets.sort_by(|x, y| (x.0, x.1, &x.2, x.3).cmp(&(y.0, y.1, &y.2, y.3)));
+        causets.dedup();
+        Ok(causets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use causetq::attribute::Unique;
+    use einstein_merkle_tree_memory::MemoryEinsteinMerkleTree;
+    use fdb_traits::ALL_NAMESPACEDS;
+
+    fn store() -> KvStoring<MemoryEinsteinMerkleTree> {
+        KvStoring::new(MemoryEinsteinMerkleTree::new(ALL_NAMESPACEDS))
+    }
+
+    fn transact(store: &KvStoring<MemoryEinsteinMerkleTree>, tx: Causetid, exact: &[Reducedcauset], inexact: &[Reducedcauset]) {
+        store.begin_tx_application().unwrap();
+        store.insert_non_fts_searches(exact, SearchType::Exact).unwrap();
+        store.insert_non_fts_searches(inexact, SearchType::Inexact).unwrap();
+        store.materialize_einstdb_causet(tx).unwrap();
+        store.commit_einstdb_causet(tx).unwrap();
+    }
+
+    #[test]
+    fn test_causet_locale_order() {
+        let causet_locales = vec![
+            causetq_TV::Ref(-1),
+            causetq_TV::Ref(7),
+            causetq_TV::Boolean(false),
+            causetq_TV::Boolean(true),
+            causetq_TV::Long(-300),
+            causetq_TV::Long(2),
+            causetq_TV::Double((-1.5).into()),
+            causetq_TV::Double(0.0.into()),
+            causetq_TV::Double(2.25.into()),
+            causetq_TV::typed_string("a"),
+            causetq_TV::typed_string("a\u{0}b"),
+            causetq_TV::typed_string("ab"),
+        ];
+        let encoded: Vec<Vec<u8>> = causet_locales.iter().map(|v| {
+            let mut buf = Vec::new();
+            encode_causet_locale(&mut buf, v);
+            buf
+        }).collect();
+        for (i, bytes) in encoded.iter().enumerate() {
+            let (decoded, rest) = decode_causet_locale(bytes).unwrap();
+            assert_eq!(decoded, causet_locales[i]);
+            assert!(rest.is_empty());
+            if i > 0 {
+                assert!(encoded[i - 1] < *bytes, "{:?} < {:?}", causet_locales[i - 1], causet_locales[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transact_and_resolve() {
+        let store = store();
+        let name = Attribute { index: true, unique: Some(Unique::Idcauset), ..Default::default() };
+        let age = Attribute::default();
+        let friend = Attribute { value_type: causetq::causetq_VT::Ref, multival: true, ..Default::default() };
+
+        let tx1 = 1000;
+        transact(&store, tx1, &[
+            (65536, 100, &name, causetq_TV::typed_string("ann"), true),
+            (65536, 102, &friend, causetq_TV::Ref(65537), true),
+        ], &[
+            (65536, 101, &age, causetq_TV::Long(30), true),
+        ]);
+        assert_eq!(store.causets_for(65536).unwrap(), vec![
+            (100, causetq_TV::typed_string("ann"), tx1),
+            (101, causetq_TV::Long(30), tx1),
+            (102, causetq_TV::Ref(65537), tx1),
+        ]);
+        assert_eq!(store.referencing(65537).unwrap(), vec![(102, 65536)]);
+
+        let ann: AVPair = (100, causetq_TV::typed_string("ann"));
+        let bob: AVPair = (100, causetq_TV::typed_string("bob"));
+        let avs = [&ann, &bob];
+        let resolved = store.resolve_avs(&avs).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved.get(&ann), Some(&65536));
+
+        // Replacing a cardinality-one causet_locale retracts the old one; retracting the friend
+        // drops it from every index.
+        let tx2 = 1001;
+        transact(&store, tx2, &[
+            (65536, 102, &friend, causetq_TV::Ref(65537), false),
+        ], &[
+            (65536, 101, &age, causetq_TV::Long(31), true),
+        ]);
+        assert_eq!(store.causets_for(65536).unwrap(), vec![
+            (100, causetq_TV::typed_string("ann"), tx1),
+            (101, causetq_TV::Long(31), tx2),
+        ]);
+        assert!(store.referencing(65537).unwrap().is_empty());
+        let mut log = store.transaction(tx2).unwrap();
+        log.sort_by(|x, y| (x.1, x.3).cmp(&(y.1, y.3)));
+        assert_eq!(log, vec![
+            (65536, 101, causetq_TV::Long(30), false),
+            (65536, 101, causetq_TV::Long(31), true),
+            (65536, 102, causetq_TV::Ref(65537), false),
+        ]);
+
+        // Asserting what is already there changes nothing.
+        let tx3 = 1002;
+        transact(&store, tx3, &[(65536, 100, &name, causetq_TV::typed_string("ann"), true)], &[]);
+        assert!(store.transaction(tx3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unique_causet_locales() {
+        let store = store();
+        let email = Attribute { unique: Some(Unique::Value), ..Default::default() };
+        let try_transact = |tx: Causetid, exact: &[Reducedcauset]| -> Result<()> {
+            store.begin_tx_application()?;
+            store.insert_non_fts_searches(exact, SearchType::Exact)?;
+            store.materialize_einstdb_causet(tx)?;
+            store.commit_einstdb_causet(tx)
+        };
+        let a = || causetq_TV::typed_string("a@example.com");
+        let b = || causetq_TV::typed_string("b@example.com");
+
+        try_transact(1000, &[(65536, 100, &email, a(), true)]).unwrap();
+        // Asserting it again on the same causet is fine.
+        try_transact(1001, &[(65536, 100, &email, a(), true)]).unwrap();
+
+        // Another causet can't take it, from the store or within the transaction.
+        assert!(try_transact(1002, &[(65537, 100, &email, a(), true)]).is_err());
+        assert!(try_transact(1003, &[
+            (65537, 100, &email, b(), true),
+            (65538, 100, &email, b(), true),
+        ]).is_err());
+        assert!(store.causets_for(65537).unwrap().is_empty());
+        assert!(store.causets_for(65538).unwrap().is_empty());
+
+        // Unless the transaction takes it away from the first.
+        try_transact(1004, &[
+            (65536, 100, &email, a(), false),
+            (65537, 100, &email, a(), true),
+        ]).unwrap();
+        assert!(store.causets_for(65536).unwrap().is_empty());
+        assert_eq!(store.causets_for(65537).unwrap(), vec![(100, a(), 1004)]);
+    }
+
+    #[test]
+    fn test_uncommitted_searches_write_nothing() {
+        let store = store();
+        let age = Attribute::default();
+        store.begin_tx_application().unwrap();
+        store.insert_non_fts_searches(&[(65536, 101, &age, causetq_TV::Long(30), true)], SearchType::Inexact).unwrap();
+        assert!(store.insert_non_fts_searches(&[(65536, 101, &age, causetq_TV::Long(31), true)], SearchType::Inexact).is_err());
+        assert!(store.insert_fts_searches(&[(65536, 103, &age, causetq_TV::Long(1), true)], SearchType::Exact).is_err());
+        store.materialize_einstdb_causet(1000).unwrap();
+        assert!(store.causets_for(65536).unwrap().is_empty());
+
+        // A new transaction starts from scratch.
+        store.begin_tx_application().unwrap();
+        store.materialize_einstdb_causet(1001).unwrap();
+        store.commit_einstdb_causet(1001).unwrap();
+        assert!(store.causets_for(65536).unwrap().is_empty());
+    }
+}
@@ -27,11 +27,14 @@ use std::hash::{Hash, Hasher};
 use std::cmp::{PartialEq, Eq};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::hash_map::Iter;
 use std::collections::hash_map::IterMut;
 use einstein_db_alexandrov_processing::{AlexandrovHash, Hashable, HashableRef, HashableRefMut};
 use crate::EinsteinDB::causet::{Causet, CausetError};
+use crate::cache_resolution::ReverseCacheResolver;
+use einsteindb_core::cache::SQLiteAttributeCache;
 use allegro_poset::{Poset, Position, Proof, ProofError, ProofErrorType, ProofType,
                     Root, RootError, RootErrorType, RootType,
                     Vertex, VertexError, VertexErrorType, VertexType};
@@ -132,6 +135,8 @@ pub struct GravitySecretKeyHash(pub [u8; 32]);
 pub struct CausetLocaleNucleon<'s, 'c> {
     pub locale: &'s str,
     pub nucleon: &'c str,
+    /// Set when the reverse attribute caches may answer patterns in place of `causets` joins.
+    pub reverse_cache: Option<ReverseCacheResolver<'s, 'c>>,
 }
 
 
@@ -148,6 +153,7 @@ impl<'s, 'c> CausetLocaleNucleon<'s, 'c> {
         CausetLocaleNucleon {
             locale: "",
             nucleon: "",
+            reverse_cache: None,
         }
 
 
@@ -157,11 +163,20 @@ impl<'s, 'c> CausetLocaleNucleon<'s, 'c> {
         CausetLocaleNucleon {
             locale: "",
             nucleon: "",
+            reverse_cache: None,
         }
 
 
     }
 
+    pub fn with_reverse_cache(s: &'s Topograph, c: &'c SQLiteAttributeCache) -> CausetLocaleNucleon<'s, 'c> {
+        CausetLocaleNucleon {
+            locale: "",
+            nucleon: "",
+            reverse_cache: Some(ReverseCacheResolver::new(s, c)),
+        }
+    }
+
 
     pub fn new_from_hash(s: &'s Topograph, c: Option<&'c CachedAttrs>) -> CausetLocaleNucleonHash<'s, 'c> {
         CausetLocaleNucleonHash {
@@ -702,6 +717,7 @@ pub fn algebrize_with_inputs(causet_locale_nucleon: CausetLocaleNucleon,
                              inputs: QueryInputs) -> Result<AlgebraicQuery> {
     let alias_counter = RcPetri::with_initial(counter);
     ConjoiningClauses::from_parsed(parsed, &alias_counter, &inputs)?;
+    let in_vars = parsed.in_vars.clone();
     let mut cc = ConjoiningClauses::with_inputs_and_alias_counter(parsed.in_vars, inputs, alias_counter);
 
     // This is so the rest of the query knows that `?x` is a ref if `(pull ?x …)` appears in `:find`.
@@ -714,7 +730,19 @@ pub fn algebrize_with_inputs(causet_locale_nucleon: CausetLocaleNucleon,
 
     // TODO: integrate default source into parity_filter processing.
     // TODO: flesh out the rest of find-into-context.
-    cc.apply_clauses(causet_locale_nucleon, parsed.where_clauses)?;
+    // Patterns the reverse attribute caches can resolve -- against a constant, or a causet_locale
+    // bound through `:in` -- become `ground`s of the causets they match.
+    let where_clauses = match causet_locale_nucleon.reverse_cache {
+        Some(resolver) => {
+            let bound: BTreeMap<Variable, Vec<causetq_TV>> =
+                in_vars.iter()
+                       .filter_map(|var| cc.bound_causet_locale(var).map(|v| (var.clone(), vec![v])))
+                       .collect();
+            resolver.substitute_patterns(parsed.where_clauses, &bound)
+        },
+        None => parsed.where_clauses,
+    };
+    cc.apply_clauses(causet_locale_nucleon, where_clauses)?;
 
     cc.expand_column_bindings();
     cc.prune_extracted_types();
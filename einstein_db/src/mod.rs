@@ -59,6 +59,13 @@ extern crate serde_json_utils;
 
 
 mod einsteindb;
+pub mod ttl;
+pub mod encryption_rotation;
+pub mod cache_resolution;
+pub mod json_index;
+pub mod json_query;
+pub mod json_schemas;
+pub mod kv_storing;
 
 
 pub use einsteindb::*;
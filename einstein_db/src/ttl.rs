@@ -0,0 +1,388 @@
+// Whtcorps Inc 2022 Apache 2.0 License; All Rights Reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+//! Datom expiry driven by `:einsteindb/ttl`.
+//!
+//! A TTL (in seconds) can be asserted in two places:
+//!
+//! - on an attribute, where it is the default TTL of every datom of that attribute;
+//! - on any other causet, where it is the TTL of every datom about that causet, and wins over
+//!   the attribute default.
+//!
+//! A datom expires once the instant of the transaction that asserted it plus its TTL is in the
+//! past.  Expired datoms are not deleted behind the transactor's back: they are retracted by an
+//! ordinary transaction, so observers, the transaction log and history queries all see the
+//! expiry like any other retraction.  The `:einsteindb/ttl` datoms themselves never expire, and
+//! neither do the datoms about an attribute: a TTL asserted on an attribute applies to its datoms,
+//! never to its topograph.
+//!
+//! A datom that can't be decoded or retracted is logged and skipped, and the pass carries on with
+//! the rest; it is tried again on the next pass.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::sync::mpsc::{
+    channel,
+    RecvTimeoutError,
+    Sender,
+};
+use std::thread;
+use std::time::Duration;
+
+use causetq::{
+    Causetid,
+    causetq_TV,
+};
+use einsteindb_core::TxReport;
+use public_traits::errors::Result;
+use rusqlite;
+
+use crate::causetids;
+use crate::conn::Conn;
+use crate::einsteindb::{
+    self,
+    TypedBerolinaSQLValue,
+};
+
+/// Upper bound on the number of datoms retracted by a single expiry transaction.  Keeps the
+/// write lock short on stores with a large backlog; the next pass picks up the rest.
+pub const DEFAULT_EXPIRY_BATCH_SIZE: usize = 1024;
+
+/// How often the background task looks for expired datoms.
+pub const DEFAULT_EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TtlExpiryOptions {
+    pub interval: Duration,
+    pub batch_size: usize,
+}
+
+impl Default for TtlExpiryOptions {
+    fn default() -> TtlExpiryOptions {
+        TtlExpiryOptions {
+            interval: DEFAULT_EXPIRY_INTERVAL,
+            batch_size: DEFAULT_EXPIRY_BATCH_SIZE,
+        }
+    }
+}
+
+/// A batch of datoms whose TTL has elapsed, as `expired_causets` returns it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpiredCausets {
+    /// The [e a v] datoms of the batch that could be decoded.
+    pub causets: Vec<(Causetid, Causetid, causetq_TV)>,
+    /// The rowid of the last row of the batch, decoded or not, which the next batch starts after;
+    /// `None` if there were no rows left.
+    pub last_rowid: Option<i64>,
+}
+
+/// Return up to `limit` datoms stored after row `after_rowid` whose TTL had elapsed at
+/// `now_micros`, in rowid order.
+///
+/// The instant of the asserting transaction is read from its `:einsteindb/txInstant` datom, so
+/// expiry is relative to when the datom was written, not to when the TTL was declared.  Datoms
+/// about attributes -- causets with a `:einsteindb/causet_localeType` -- are never returned.  A
+/// datom whose causet_locale doesn't decode is logged and left out; it still counts towards
+/// `limit`, and `last_rowid` moves past it.
+pub fn expired_causets(conn: &rusqlite::Connection, now_micros: i64, after_rowid: i64, limit: usize) -> Result<ExpiredCausets> {
+    let mut stmt: rusqlite::Statement = conn.prepare_cached(r#"
+        SELECT d.rowid, d.e, d.a, d.v, d.causet_locale_type_tag
+        FROM causets AS d
+        INNER JOIN causets AS t
+            ON t.e = d.tx AND t.a = ?1
+        LEFT JOIN causets AS causet_ttl
+            ON causet_ttl.e = d.e AND causet_ttl.a = ?2
+        LEFT JOIN causets AS attribute_ttl
+            ON attribute_ttl.e = d.a AND attribute_ttl.a = ?2
+        WHERE d.a != ?2 AND d.a != ?1
+          AND NOT EXISTS (SELECT 1 FROM causets AS attribute WHERE attribute.e = d.e AND attribute.a = ?5)
+          AND COALESCE(causet_ttl.v, attribute_ttl.v) IS NOT NULL
+          AND t.v + COALESCE(causet_ttl.v, attribute_ttl.v) * 1000000 <= ?3
+          AND d.rowid > ?6
+        ORDER BY d.rowid
+        LIMIT ?4"#)?;
+
+    let rows: Result<Vec<(i64, Causetid, Causetid, rusqlite::types::Value, i32)>> = stmt.query_and_then(
+        &[&causetids::EINSTEINDB_TX_INSTANT, &causetids::EINSTEINDB_TTL, &now_micros, &(limit as i64), &causetids::EINSTEINDB_VALUE_TYPE, &after_rowid],
+        |event| -> Result<_> {
+            Ok((event.get_checked(0)?, event.get_checked(1)?, event.get_checked(2)?, event.get_checked(3)?, event.get_checked(4)?))
+        })?.collect();
+
+    let mut expired = ExpiredCausets {
+        causets: Vec::new(),
+        last_rowid: None,
+    };
+    for (rowid, e, a, v, causet_locale_type_tag) in rows? {
+        expired.last_rowid = Some(rowid);
+        match causetq_TV::from_berolina_sql_causet_locale_pair(v, causet_locale_type_tag) {
+            Ok(typed_causet_locale) => expired.causets.push((e, a, typed_causet_locale)),
+            Err(err) => warn!("ttl expiry skipped [{} {}] with causet_locale type tag {}: {}", e, a, causet_locale_type_tag, err),
+        }
+    }
+    Ok(expired)
+}
+
+/// Render `[:einsteindb/retract e a v]` terms for the given datoms as a single transaction.
+pub fn retractions_for(causets: &[(Causetid, Causetid, causetq_TV)]) -> String {
+    let mut out = String::from("[");
+    for &(e, a, ref v) in causets {
+        let (causet_locale, _) = v.to_einstein_ml_causet_locale_pair();
+        // Writing into a `String` can't fail.
+        write!(out, "[:einsteindb/retract {} {} {}]", e, a, causet_locale.to_pretty_string()).unwrap();
+    }
+    out.push(']');
+    out
+}
+
+/// Run an expiry pass over the datoms whose TTL had elapsed at `now_micros`, handing them to
+/// `retract` up to `batch_size` at a time.  Returns the reports `retract` returned.
+fn expiry_pass<F>(sqlite: &mut rusqlite::Connection,
+                  now_micros: i64,
+                  batch_size: usize,
+                  mut retract: F) -> Result<Vec<TxReport>>
+    where F: FnMut(&mut rusqlite::Connection, &[(Causetid, Causetid, causetq_TV)]) -> Vec<TxReport> {
+    let mut reports = Vec::new();
+    let mut after_rowid = 0;
+    loop {
+        let expired = expired_causets(sqlite, now_micros, after_rowid, batch_size)?;
+        match expired.last_rowid {
+            Some(rowid) => after_rowid = rowid,
+            None => return Ok(reports),
+        }
+        if !expired.causets.is_empty() {
+            debug!("expiring {} causets at {}", expired.causets.len(), now_micros);
+            reports.extend(retract(sqlite, &expired.causets));
+        }
+    }
+}
+
+impl Conn {
+    /// Retract every datom whose TTL had elapsed at `now_micros`, in transactions of up to
+    /// `batch_size` retractions.
+    ///
+    /// Returns the reports of the expiry transactions, none if nothing had expired.  Datoms that
+    /// can't be decoded or retracted are logged and skipped rather than failing the pass.
+    pub fn expire_ttl_causets(&mut self,
+                              sqlite: &mut rusqlite::Connection,
+                              now_micros: i64,
+                              batch_size: usize) -> Result<Vec<TxReport>> {
+        expiry_pass(sqlite, now_micros, batch_size, |sqlite, causets| self.retract_expired_causets(sqlite, causets))
+    }
+
+    /// Retract `causets` in one transaction.  If that fails, retract them one at a time, logging
+    /// and skipping each that still fails, so one bad datom doesn't keep the rest from expiring.
+    pub fn retract_expired_causets(&mut self,
+                                   sqlite: &mut rusqlite::Connection,
+                                   causets: &[(Causetid, Causetid, causetq_TV)]) -> Vec<TxReport> {
+        match self.transact(sqlite, retractions_for(causets)) {
+            Ok(report) => return vec![report],
+            Err(e) if causets.len() > 1 => debug!("ttl expiry of {} causets failed, retrying one at a time: {}", causets.len(), e),
+            Err(_) => {},
+        }
+        let mut reports = Vec::new();
+        for causet in causets {
+            match self.transact(sqlite, retractions_for(::std::slice::from_ref(causet))) {
+                Ok(report) => reports.push(report),
+                Err(e) => warn!("ttl expiry skipped [{} {} {:?}]: {}", causet.0, causet.1, causet.2, e),
+            }
+        }
+        reports
+    }
+}
+
+/// Handle to the background expiry task.  Dropping the handle stops the task.
+pub struct TtlExpiryHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TtlExpiryHandle {
+    /// Stop the task and wait for an in-flight pass to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        // Dropping the sender wakes the task immediately.
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for TtlExpiryHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Spawn a thread that periodically retracts expired datoms.
+///
+/// The task opens its own connection to the store at `path`, so it never contends with the
+/// caller's connection for anything but the `Conn` itself, which is held only while a batch is
+/// transacted.  Datoms that fail are logged and skipped; any other error ends the pass, and both
+/// are retried on the next pass.
+pub fn spawn_ttl_expiry(path: PathBuf, conn: Arc<Mutex<Conn>>, options: TtlExpiryOptions) -> Result<TtlExpiryHandle> {
+    let mut sqlite = einsteindb::new_connection(&path)?;
+    let (stop, stopped) = channel::<()>();
+
+    let thread = thread::Builder::new()
+        .name("einsteindb-ttl-expiry".to_string())
+        .spawn(move || {
+            loop {
+                match stopped.recv_timeout(options.interval) {
+                    Err(RecvTimeoutError::Timeout) => {},
+                    // Either an explicit stop or the handle was dropped.
+                    _ => break,
+                }
+
+                let now_micros = ::chrono::Utc::now().timestamp() * 1_000_000;
+                let pass = expiry_pass(&mut sqlite, now_micros, options.batch_size, |sqlite, causets| {
+                    conn.lock().unwrap().retract_expired_causets(sqlite, causets)
+                });
+                match pass {
+                    Ok(reports) => for report in reports {
+                        debug!("ttl expiry committed tx {}", report.tx_id);
+                    },
+                    Err(e) => warn!("ttl expiry failed: {}", e),
+                }
+            }
+        })?;
+
+    Ok(TtlExpiryHandle {
+        stop: Some(stop),
+        thread: Some(thread),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retractions_for_empty() {
+        assert_eq!(retractions_for(&[]), "[]");
+    }
+
+    #[test]
+    fn test_retractions_for() {
+        let causets = vec![
+            (65536, 100, causetq_TV::Long(7)),
+            (65537, 101, causetq_TV::Boolean(true)),
+        ];
+        assert_eq!(retractions_for(&causets),
+                   "[[:einsteindb/retract 65536 100 7][:einsteindb/retract 65537 101 true]]");
+    }
+
+    #[test]
+    fn test_expire_ttl_causets() {
+        let mut sqlite = einsteindb::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+
+        // 2017-06-16T00:00:00Z.
+        let t0: i64 = 1_497_571_200 * 1_000_000;
+        let seconds = |s: i64| t0 + s * 1_000_000;
+
+        // `:test/session` expires a minute after it is written; `:test/name` only where its causet
+        // says so.  The attribute's own topograph must outlive every expiry.
+        let report = conn.transact(&mut sqlite, "[[:einsteindb/add (transaction-tx) :einsteindb/txInstant #inst \"2017-06-16T00:00:00Z\"]
+                                                  [:einsteindb/add \"n\" :einsteindb/solitonid :test/name]
+                                                  [:einsteindb/add \"n\" :einsteindb/causet_localeType :einsteindb.type/string]
+                                                  [:einsteindb/add \"n\" :einsteindb/cardinality :einsteindb.cardinality/one]
+                                                  [:einsteindb/add \"s\" :einsteindb/solitonid :test/session]
+                                                  [:einsteindb/add \"s\" :einsteindb/causet_localeType :einsteindb.type/string]
+                                                  [:einsteindb/add \"s\" :einsteindb/cardinality :einsteindb.cardinality/one]
+                                                  [:einsteindb/add \"s\" :einsteindb/ttl 60]]").unwrap();
+        let (name, session) = (report.tempids["n"], report.tempids["s"]);
+
+        let report = conn.transact(&mut sqlite, "[[:einsteindb/add (transaction-tx) :einsteindb/txInstant #inst \"2017-06-16T00:00:00Z\"]
+                                                  [:einsteindb/add \"long\" :test/name \"long-lived\"]
+                                                  [:einsteindb/add \"long\" :test/session \"s1\"]
+                                                  [:einsteindb/add \"short\" :test/name \"short-lived\"]
+                                                  [:einsteindb/add \"short\" :einsteindb/ttl 10]]").unwrap();
+        let (long, short) = (report.tempids["long"], report.tempids["short"]);
+
+        assert!(expired_causets(&sqlite, seconds(5), 0, 100).unwrap().causets.is_empty());
+        assert!(conn.expire_ttl_causets(&mut sqlite, seconds(5), 100).unwrap().is_empty());
+
+        let expired = expired_causets(&sqlite, seconds(30), 0, 100).unwrap();
+        assert_eq!(retractions_for(&expired.causets),
+                   format!("[[:einsteindb/retract {} {} \"short-lived\"]]", short, name));
+        assert_eq!(conn.expire_ttl_causets(&mut sqlite, seconds(30), 100).unwrap().len(), 1);
+
+        let expired = expired_causets(&sqlite, seconds(120), 0, 100).unwrap();
+        assert_eq!(retractions_for(&expired.causets),
+                   format!("[[:einsteindb/retract {} {} \"s1\"]]", long, session));
+        assert_eq!(conn.expire_ttl_causets(&mut sqlite, seconds(120), 100).unwrap().len(), 1);
+
+        // Everything due is gone; the long-lived name, the TTLs and the topograph remain.
+        assert_eq!(expired_causets(&sqlite, seconds(3600), 0, 100).unwrap().last_rowid, None);
+        let remaining: i64 = sqlite.query_row("SELECT count(*) FROM causets WHERE e IN (?1, ?2, ?3)",
+                                              &[&long, &short, &session],
+                                              |row| row.get(0)).unwrap();
+        // [long :test/name], [short :einsteindb/ttl], and the four topograph datoms of :test/session.
+        assert_eq!(remaining, 6);
+    }
+
+    #[test]
+    fn test_expiry_skips_malformed_causets() {
+        let mut sqlite = einsteindb::new_connection("").unwrap();
+        let mut conn = Conn::connect(&mut sqlite).unwrap();
+
+        // 2017-06-16T00:00:00Z.
+        let t0: i64 = 1_497_571_200 * 1_000_000;
+        let seconds = |s: i64| t0 + s * 1_000_000;
+
+        let report = conn.transact(&mut sqlite, "[[:einsteindb/add (transaction-tx) :einsteindb/txInstant #inst \"2017-06-16T00:00:00Z\"]
+                                                  [:einsteindb/add \"s\" :einsteindb/solitonid :test/session]
+                                                  [:einsteindb/add \"s\" :einsteindb/causet_localeType :einsteindb.type/string]
+                                                  [:einsteindb/add \"s\" :einsteindb/cardinality :einsteindb.cardinality/one]
+                                                  [:einsteindb/add \"s\" :einsteindb/ttl 60]]").unwrap();
+        let session = report.tempids["s"];
+        let report = conn.transact(&mut sqlite, "[[:einsteindb/add (transaction-tx) :einsteindb/txInstant #inst \"2017-06-16T00:00:00Z\"]
+                                                  [:einsteindb/add \"bad\" :test/session \"s1\"]
+                                                  [:einsteindb/add \"good\" :test/session \"s2\"]]").unwrap();
+        let (bad, good) = (report.tempids["bad"], report.tempids["good"]);
+
+        // A causet_locale type tag no causet_locale has.
+        sqlite.execute("UPDATE causets SET causet_locale_type_tag = 99 WHERE e = ? AND a = ?", &[&bad, &session]).unwrap();
+
+        let expired = expired_causets(&sqlite, seconds(120), 0, 100).unwrap();
+        assert_eq!(retractions_for(&expired.causets),
+                   format!("[[:einsteindb/retract {} {} \"s2\"]]", good, session));
+
+        // Even a batch holding nothing but the malformed datom doesn't end the pass.
+        let reports = conn.expire_ttl_causets(&mut sqlite, seconds(120), 1).unwrap();
+        assert_eq!(reports.len(), 1);
+        let remaining: Vec<i64> = sqlite.prepare("SELECT e FROM causets WHERE a = ? ORDER BY e").unwrap()
+            .query_map(&[&session], |row| row.get(0)).unwrap()
+            .map(|e| e.unwrap())
+            .collect();
+        assert_eq!(remaining, vec![bad]);
+
+        // The malformed datom is skipped again on the next pass.
+        assert!(conn.expire_ttl_causets(&mut sqlite, seconds(180), 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_default_options() {
+        let options = TtlExpiryOptions::default();
+        assert_eq!(options.interval, DEFAULT_EXPIRY_INTERVAL);
+        assert_eq!(options.batch_size, DEFAULT_EXPIRY_BATCH_SIZE);
+    }
+}
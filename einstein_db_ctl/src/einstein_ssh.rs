@@ -343,7 +343,214 @@ pub enum Cmd {
         ///
         log_level: Option<String>,
 
+    },
+    /// Rotate the encryption soliton_id of an encrypted store
+    RotateKey {
+        #[structopt(short = "d")]
+        /// Set the path of the store to rotate
+        db: String,
+
+        #[structopt(long)]
+        /// Set the file holding the current encryption soliton_id
+        old_soliton_id_file: String,
+
+        #[structopt(long)]
+        /// Set the file holding the new encryption soliton_id
+        new_soliton_id_file: String,
+
+        #[structopt(long)]
+        /// Keep the pre-rotation copy of the store after a successful rotation
+        keep_previous: bool,
+    },
+    /// Create a point-in-time checkpoint of a kv store, which must not be open in a running server
+    Checkpoint {
+        #[structopt(short = "d")]
+        /// Set the path of the kv store
+        db: String,
+
+        #[structopt(long)]
+        /// Set the directory to create the checkpoint in, which must not exist yet
+        dir: String,
+
+        #[structopt(long)]
+        /// Set the file holding the master soliton_id of an encrypted kv store
+        master_soliton_id_file: Option<String>,
+
+        #[structopt(long, requires = "master_soliton_id_file")]
+        /// Set how files written to the kv store are encrypted: plaintext, aes128-ctr, aes256-ctr
+        /// or aes256-gcm, if not specified, the way the kv store last encrypted a file
+        encryption_method: Option<String>,
+    },
+    /// Back a kv store up, copying only the files earlier backups do not hold; the kv store must
+    /// not be open in a running server
+    Backup {
+        #[structopt(short = "d")]
+        /// Set the path of the kv store
+        db: String,
+
+        #[structopt(long)]
+        /// Set the backup directory
+        backup_dir: String,
+
+        #[structopt(long)]
+        /// Set the file holding the master soliton_id of an encrypted kv store
+        master_soliton_id_file: Option<String>,
+
+        #[structopt(long, requires = "master_soliton_id_file")]
+        /// Set how files written to the kv store are encrypted: plaintext, aes128-ctr, aes256-ctr
+        /// or aes256-gcm, if not specified, the way the kv store last encrypted a file
+        encryption_method: Option<String>,
+    },
+    /// Restore a kv store from a backup, checking every file against its checksum
+    Restore {
+        #[structopt(long)]
+        /// Set the backup directory
+        backup_dir: String,
+
+        #[structopt(long)]
+        /// Set the backup to restore, if not specified, restore the latest one
+        backup_id: Option<u64>,
+
+        #[structopt(short = "d")]
+        /// Set the path to restore the kv store to, which must be empty
+        db: String,
+    },
+}
+
+
+/// Read an encryption soliton_id from `path`, ignoring a trailing newline.
+fn read_soliton_id_file(path: &str) -> std::io::Result<String> {
+    let mut s = String::new();
+    File::open(path)?.read_to_string(&mut s)?;
+    Ok(s.trim_end_matches(|c| c == '\n' || c == '\r').to_string())
+}
+
+
+/// Run `Cmd::RotateKey`, printing progress to stderr.
+pub fn run_rotate_key(db: &str, old_soliton_id_file: &str, new_soliton_id_file: &str, keep_previous: bool) -> Result<(), EinsteinDBError> {
+    use einstein_db::encryption_rotation::{
+        rotate_encryption_soliton_id,
+        RotationOptions,
+        RotationStage,
+    };
+
+    let old_soliton_id = read_soliton_id_file(old_soliton_id_file)?;
+    let new_soliton_id = read_soliton_id_file(new_soliton_id_file)?;
+    let options = RotationOptions {
+        keep_previous,
+        ..RotationOptions::default()
+    };
+
+    rotate_encryption_soliton_id(db, old_soliton_id, new_soliton_id, &options, |progress| {
+        match progress.stage {
+            RotationStage::Exporting if progress.page_count > 0 => {
+                eprint!("\rexporting {}/{} pages ({}%)", progress.pages_done, progress.page_count,
+                        progress.pages_done * 100 / progress.page_count);
+            },
+            stage => eprintln!("\n{:?}", stage),
+        }
+    })?;
+    Ok(())
+}
+
+
+/// A kv store is open in one process at a time, which holds the LOCK file in its directory.
+fn store_in_use(db: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::WouldBlock,
+                        format!("the kv store at {} is open elsewhere; stop the server using it first", db))
+}
+
+
+/// Open the existing kv store at `db`, through an `EncryptedEnv` if a master soliton_id file is
+/// given. Files it writes are encrypted with `encryption_method`, or else with the method of the
+/// newest data key in the store's key dictionary.
+///
+/// The kv store must not be open in a running server: the server holds its LOCK file, and this
+/// fails with `store_in_use` rather than open a second einstein_merkle_tree over the same files.
+fn open_lsm_db(db: &str,
+               master_soliton_id_file: Option<&str>,
+               encryption_method: Option<&str>) -> Result<einstein_merkle_tree_lsm::LsmEinsteinMerkleTree, EinsteinDBError> {
+    use einstein_merkle_tree_lsm::{
+        AesMasterKey,
+        DataKeyManager,
+        DefaultEnv,
+        EncryptedEnv,
+        EncryptionMethod,
+        EncryptionOptions,
+        Env,
+        LsmEinsteinMerkleTree,
+        LsmOptions,
+    };
+
+    // Opening creates a kv store that isn't there, and a mistyped path shouldn't.
+    let dir = Path::new(db);
+    if !dir.join("MANIFEST").is_file() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, format!("there is no kv store at {}", db)).into());
+    }
+
+    // Look for a holder of the lock before opening the key dictionary, which may write to the
+    // directory.  Opening the einstein_merkle_tree takes the lock again and keeps it.  A kv store
+    // without a LOCK file has no holder, and probing mustn't create one.
+    let lock = dir.join("LOCK");
+    if lock.exists() {
+        match DefaultEnv.lock_file(&lock) {
+            Ok(_) => {},
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => return Err(store_in_use(db).into()),
+            Err(e) => return Err(e.into()),
+        }
     }
+
+    let mut opts = LsmOptions::default();
+    if let Some(path) = master_soliton_id_file {
+        let master_key = AesMasterKey::from_file(path)?;
+        let method = match encryption_method {
+            Some(name) => name.parse::<EncryptionMethod>()?,
+            None => DataKeyManager::newest_method(&DefaultEnv, dir, &master_key)?.unwrap_or(EncryptionMethod::Plaintext),
+        };
+        let encryption = EncryptionOptions::new(method, Arc::new(master_key));
+        opts.env = Arc::new(EncryptedEnv::open(Arc::new(DefaultEnv), dir, encryption)?);
+    }
+    match LsmEinsteinMerkleTree::open(db, opts, Vec::new()) {
+        Err(fdb_traits::Error::Io(ref e)) if e.kind() == std::io::ErrorKind::WouldBlock => Err(store_in_use(db).into()),
+        r => Ok(r?),
+    }
+}
+
+
+/// Run `Cmd::Checkpoint`.
+pub fn run_checkpoint(db: &str,
+                      dir: &str,
+                      master_soliton_id_file: Option<&str>,
+                      encryption_method: Option<&str>) -> Result<(), EinsteinDBError> {
+    let db = open_lsm_db(db, master_soliton_id_file, encryption_method)?;
+    db.create_checkpoint(dir)?;
+    println!("checkpoint at seqno {} created in {}", db.latest_seqno(), dir);
+    Ok(())
+}
+
+
+/// Run `Cmd::Backup`, printing what the backup holds.
+pub fn run_backup(db: &str,
+                  backup_dir: &str,
+                  master_soliton_id_file: Option<&str>,
+                  encryption_method: Option<&str>) -> Result<(), EinsteinDBError> {
+    use einstein_merkle_tree_lsm::BackupEngine;
+
+    let db = open_lsm_db(db, master_soliton_id_file, encryption_method)?;
+    let info = BackupEngine::open(backup_dir)?.create_backup(&db)?;
+    println!("backup {} at seqno {}: {} files, {} bytes, {} files copied",
+             info.id, info.seqno, info.num_files, info.size, info.new_files);
+    Ok(())
+}
+
+
+/// Run `Cmd::Restore`.
+pub fn run_restore(backup_dir: &str, backup_id: Option<u64>, db: &str) -> Result<(), EinsteinDBError> {
+    use einstein_merkle_tree_lsm::BackupEngine;
+
+    let info = BackupEngine::open(backup_dir)?.restore(backup_id, Path::new(db))?;
+    println!("backup {} at seqno {} restored to {}", info.id, info.seqno, db);
+    Ok(())
 }
 
 
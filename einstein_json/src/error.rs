@@ -0,0 +1,92 @@
+//Copyright 2021-2023 WHTCORPS INC
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+use regex::Error as RegexpError;
+use serde_json::error::Error as SerdeError;
+use std::error;
+use std::fmt::Display;
+use std::io;
+use std::num::ParseFloatError;
+use std::str::Utf8Error;
+use std::string::FromUtf8Error;
+
+pub const ERR_UNKNOWN: i32 = 1105;
+pub const ERR_INVALID_TYPE_FOR_JSON: i32 = 3146;
+pub const ERR_REGEXP: i32 = 1139;
+
+quick_error! {
+    #[derive(Debug)]
+    pub enum Error {
+        InvalidDataType(reason: String) {
+            display("invalid data type: {}", reason)
+        }
+        Encoding(err: Utf8Error) {
+            from()
+            cause(err)
+            display("encoding failed")
+        }
+        Eval(s: String, code: i32) {
+            display("evaluation failed: {}", s)
+        }
+        Other(err: Box<dyn error::Error + Send + Sync>) {
+            from()
+            cause(err.as_ref())
+            display("{}", err)
+        }
+    }
+}
+
+impl Error {
+    /// A JSON function was given a document of the wrong type.
+    pub fn invalid_type(expected: impl Display, got: impl std::fmt::Debug) -> Error {
+        let msg = format!("Invalid JSON type {:?}, expected {}", got, expected);
+        Error::Eval(msg, ERR_INVALID_TYPE_FOR_JSON)
+    }
+
+    pub fn code(&self) -> i32 {
+        match *self {
+            Error::Eval(_, code) => code,
+            _ => ERR_UNKNOWN,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Error {
+        Error::Encoding(err.utf8_error())
+    }
+}
+
+impl From<SerdeError> for Error {
+    fn from(err: SerdeError) -> Error {
+        box_err!("serde:{:?}", err)
+    }
+}
+
+impl From<ParseFloatError> for Error {
+    fn from(err: ParseFloatError) -> Error {
+        box_err!("parse float: {:?}", err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        box_err!("io: {:?}", err)
+    }
+}
+
+impl From<RegexpError> for Error {
+    fn from(err: RegexpError) -> Error {
+        let msg = format!("Got error '{:.64}' from regexp", err);
+        Error::Eval(msg, ERR_REGEXP)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
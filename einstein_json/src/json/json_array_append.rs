@@ -0,0 +1,106 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use super::{Json, JsonRef, JsonType};
+use super::json_extract::extract_json;
+use super::modifier::BinaryModifier;
+use super::path_expr::LocalPathExpression;
+use super::super::Result;
+
+impl<'a> JsonRef<'a> {
+    /// Appends each causet_locale to the end of the array at the matching local_path. A
+    /// nonarray causet_locale is autowrapped as an array first; local_paths that don't exist
+    /// are ignored. Pairs are applied from left to right, each seeing the previous result.
+    ///
+    /// See `ArrayAppend()` in MEDB `json/binary_function.go`
+    pub fn array_append(
+        &self,
+        local_path_expr_list: &[LocalPathExpression],
+        causet_locales: Vec<Json>,
+    ) -> Result<Json> {
+        if local_path_expr_list.len() != causet_locales.len() {
+            return Err(box_err!(
+                "Incorrect number of parameters: expected: {:?}, found {:?}",
+                causet_locales.len(),
+                local_path_expr_list.len()
+            ));
+        }
+        for expr in local_path_expr_list {
+            if expr.could_match_multiple() {
+                return Err(box_err!(
+                    "Invalid local_path expression: expected no asterisk or range, found {:?}",
+                    expr
+                ));
+            }
+        }
+        let mut res = self.to_owned();
+        for (expr, causet_locale) in local_path_expr_list.iter().zip(causet_locales.into_iter()) {
+            let appended = match extract_json(res.as_ref(), &expr.legs)?.first() {
+                Some(target) => {
+                    let mut elems = vec![];
+                    if target.get_type() == JsonType::Array {
+                        for i in 0..target.get_elem_count() {
+                            elems.push(target.array_get_elem(i)?);
+                        }
+                    } else {
+                        elems.push(*target);
+                    }
+                    elems.push(causet_locale.as_ref());
+                    Json::from_ref_array(elems)?
+                }
+                None => continue,
+            };
+            res = BinaryModifier::new(res.as_ref()).replace(expr, appended)?;
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::path_expr::parse_json_local_path_expr;
+
+    #[test]
+    fn test_json_array_append() {
+        let test_cases = vec![
+            (r#"[1, 2]"#, vec![("$", "3")], Some(r#"[1, 2, 3]"#)),
+            (r#"[1, [2]]"#, vec![("$[1]", "3")], Some(r#"[1, [2, 3]]"#)),
+            (r#"[1, 2]"#, vec![("$[0]", "3")], Some(r#"[[1, 3], 2]"#)),
+            (r#"{"a": 1}"#, vec![("$", "2")], Some(r#"[{"a": 1}, 2]"#)),
+            (r#"{"a": {"b": 1}}"#, vec![("$.a", "[2]")], Some(r#"{"a": [{"b": 1}, [2]]}"#)),
+            (
+                r#"{"a": [1]}"#,
+                vec![("$.a", "2"), ("$.a", "3")],
+                Some(r#"{"a": [1, 2, 3]}"#),
+            ),
+            // Nothing changed because the local_path doesn't exist.
+            (r#"{"a": [1]}"#, vec![("$.b", "2")], Some(r#"{"a": [1]}"#)),
+            (r#"[1]"#, vec![("$[*]", "2")], None),
+        ];
+        for (i, (json, pairs, expected)) in test_cases.into_iter().enumerate() {
+            let j: Json = json.parse().unwrap();
+            let exprs: Vec<_> = pairs
+                .iter()
+                .map(|(p, _)| parse_json_local_path_expr(p).unwrap())
+                .collect();
+            let causet_locales: Vec<Json> = pairs.iter().map(|(_, v)| v.parse().unwrap()).collect();
+            let got = j.as_ref().array_append(&exprs, causet_locales);
+            match expected {
+                Some(expected) => {
+                    assert!(got.is_ok(), "#{} expect array_append ok but got {:?}", i, got);
+                    let expected: Json = expected.parse().unwrap();
+                    assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+                }
+                None => assert!(got.is_err(), "#{} expect error but got {:?}", i, got),
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use super::{Json, JsonRef, JsonType};
+use super::json_extract::extract_json;
+use super::modifier::BinaryModifier;
+use super::path_expr::{ArrayIndex, LocalPathExpression, LocalPathLeg};
+use super::super::Result;
+
+impl<'a> JsonRef<'a> {
+    /// Inserts each causet_locale into the array at the matching local_path, which must end in
+    /// an array index. Elements from that index on shift right; an index past the end
+    /// appends, and one before the start (`last-n` for a short array) prepends. Local_paths
+    /// whose parent doesn't exist or isn't an array are ignored.
+    ///
+    /// See `ArrayInsert()` in MEDB `json/binary_function.go`
+    pub fn array_insert(
+        &self,
+        local_path_expr_list: &[LocalPathExpression],
+        causet_locales: Vec<Json>,
+    ) -> Result<Json> {
+        if local_path_expr_list.len() != causet_locales.len() {
+            return Err(box_err!(
+                "Incorrect number of parameters: expected: {:?}, found {:?}",
+                causet_locales.len(),
+                local_path_expr_list.len()
+            ));
+        }
+        for expr in local_path_expr_list {
+            if expr.could_match_multiple() {
+                return Err(box_err!(
+                    "Invalid local_path expression: expected no asterisk or range, found {:?}",
+                    expr
+                ));
+            }
+            match expr.legs.last() {
+                Some(LocalPathLeg::Index(_)) | Some(LocalPathLeg::LastIndex(_)) => {}
+                _ => {
+                    return Err(box_err!(
+                        "A local_path expression is not a local_path to a cell in an array: {:?}",
+                        expr
+                    ))
+                }
+            }
+        }
+        let mut res = self.to_owned();
+        for (expr, causet_locale) in local_path_expr_list.iter().zip(causet_locales.into_iter()) {
+            let legs_len = expr.legs.len();
+            let parent = LocalPathExpression {
+                legs: expr.legs[..legs_len - 1].to_vec(),
+                flags: expr.flags,
+            };
+            let idx = match expr.legs[legs_len - 1] {
+                LocalPathLeg::Index(idx) => ArrayIndex::Left(idx as u32),
+                LocalPathLeg::LastIndex(n) => ArrayIndex::Right(n),
+                _ => unreachable!(),
+            };
+            let inserted = match extract_json(res.as_ref(), &parent.legs)?.first() {
+                Some(target) if target.get_type() == JsonType::Array => {
+                    let elem_count = target.get_elem_count();
+                    let mut elems = Vec::with_capacity(elem_count + 1);
+                    for i in 0..elem_count {
+                        elems.push(target.array_get_elem(i)?);
+                    }
+                    let idx = idx.resolve(elem_count).unwrap_or(0).min(elem_count);
+                    elems.insert(idx, causet_locale.as_ref());
+                    Json::from_ref_array(elems)?
+                }
+                _ => continue,
+            };
+            res = BinaryModifier::new(res.as_ref()).replace(&parent, inserted)?;
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::path_expr::parse_json_local_path_expr;
+
+    #[test]
+    fn test_json_array_insert() {
+        let test_cases = vec![
+            (r#"[1, 2]"#, vec![("$[0]", "0")], Some(r#"[0, 1, 2]"#)),
+            (r#"[1, 2]"#, vec![("$[1]", "3")], Some(r#"[1, 3, 2]"#)),
+            (r#"[1, 2]"#, vec![("$[100]", "3")], Some(r#"[1, 2, 3]"#)),
+            (r#"{"a": [1]}"#, vec![("$.a[0]", "[0]")], Some(r#"{"a": [[0], 1]}"#)),
+            (
+                r#"[1, 2]"#,
+                vec![("$[0]", "0"), ("$[3]", "3")],
+                Some(r#"[0, 1, 2, 3]"#),
+            ),
+            (r#"[1, 2, 3]"#, vec![("$[last]", "9")], Some(r#"[1, 2, 9, 3]"#)),
+            (r#"[1, 2, 3]"#, vec![("$[last-2]", "9")], Some(r#"[9, 1, 2, 3]"#)),
+            (r#"[1, 2, 3]"#, vec![("$[last-9]", "9")], Some(r#"[9, 1, 2, 3]"#)),
+            (r#"[]"#, vec![("$[last]", "9")], Some(r#"[9]"#)),
+            // Nothing changed because the parent isn't an array.
+            (r#"{"a": 1}"#, vec![("$.a[0]", "0")], Some(r#"{"a": 1}"#)),
+            // Nothing changed because the parent doesn't exist.
+            (r#"{"a": 1}"#, vec![("$.b[0]", "0")], Some(r#"{"a": 1}"#)),
+            // The last leg must be an array index.
+            (r#"{"a": [1]}"#, vec![("$.a", "0")], None),
+            (r#"[1]"#, vec![("$", "0")], None),
+            (r#"[1]"#, vec![("$[*]", "0")], None),
+            (r#"[1]"#, vec![("$[0 to 1]", "0")], None),
+        ];
+        for (i, (json, pairs, expected)) in test_cases.into_iter().enumerate() {
+            let j: Json = json.parse().unwrap();
+            let exprs: Vec<_> = pairs
+                .iter()
+                .map(|(p, _)| parse_json_local_path_expr(p).unwrap())
+                .collect();
+            let causet_locales: Vec<Json> = pairs.iter().map(|(_, v)| v.parse().unwrap()).collect();
+            let got = j.as_ref().array_insert(&exprs, causet_locales);
+            match expected {
+                Some(expected) => {
+                    assert!(got.is_ok(), "#{} expect array_insert ok but got {:?}", i, got);
+                    let expected: Json = expected.parse().unwrap();
+                    assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+                }
+                None => assert!(got.is_err(), "#{} expect error but got {:?}", i, got),
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use super::{JsonRef, JsonType};
+use super::json_extract::extract_json;
+use super::path_expr::LocalPathExpression;
+use super::super::Result;
+
+impl<'a> JsonRef<'a> {
+    /// `contains` reports whether `candidate` is contained in this JSON document:
+    ///   1) a scalar is contained in a scalar if they are equal;
+    ///   2) an object is contained in an object if every soliton_id of the candidate is in the
+    ///      target and its causet_locale is contained in the target's causet_locale;
+    ///   3) an array is contained in an array if every element of the candidate is contained
+    ///      in some element of the target;
+    ///   4) a nonarray is contained in an array if it is contained in some element of it.
+    ///
+    /// See `ContainsBinary()` in MEDB `json/binary_function.go`
+    pub fn contains(&self, candidate: JsonRef<'_>) -> Result<bool> {
+        match (self.get_type(), candidate.get_type()) {
+            (JsonType::Object, JsonType::Object) => {
+                for i in 0..candidate.get_elem_count() {
+                    let soliton_id = candidate.object_get_soliton_id(i);
+                    let idx = match self.object_search_soliton_id(soliton_id) {
+                        Some(idx) => idx,
+                        None => return Ok(false),
+                    };
+                    if !self.object_get_val(idx)?.contains(candidate.object_get_val(i)?)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (JsonType::Object, _) => Ok(false),
+            (JsonType::Array, JsonType::Array) => {
+                for i in 0..candidate.get_elem_count() {
+                    if !self.contains(candidate.array_get_elem(i)?)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+            (JsonType::Array, _) => {
+                for i in 0..self.get_elem_count() {
+                    if self.array_get_elem(i)?.contains(candidate)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            (_, JsonType::Object) | (_, JsonType::Array) => Ok(false),
+            _ => Ok(*self == candidate),
+        }
+    }
+
+    /// `contains_at` is `JSON_CONTAINS(target, candidate, local_path)`: the containment test
+    /// applies to the causet_locale at `local_path`, and `None` is returned if there isn't one.
+    pub fn contains_at(
+        &self,
+        candidate: JsonRef<'_>,
+        local_path_expr: &LocalPathExpression,
+    ) -> Result<Option<bool>> {
+        if local_path_expr.could_match_multiple() {
+            return Err(box_err!(
+                "Invalid local_path expression: expected no asterisk or range, found {:?}",
+                local_path_expr
+            ));
+        }
+        match extract_json(*self, &local_path_expr.legs)?.first() {
+            Some(target) => Ok(Some(target.contains(candidate)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Json;
+    use super::super::path_expr::parse_json_local_path_expr;
+
+    #[test]
+    fn test_json_contains() {
+        let test_cases = vec![
+            (r#"1"#, r#"1"#, true),
+            (r#"1"#, r#"1.0"#, true),
+            (r#"1"#, r#""1""#, false),
+            (r#""a""#, r#"["a"]"#, false),
+            (r#"[1, 2, 3]"#, r#"2"#, true),
+            (r#"[1, 2, 3]"#, r#"[3, 1]"#, true),
+            (r#"[1, 2, 3]"#, r#"[1, 4]"#, false),
+            (r#"[1, 2, 3]"#, r#"[]"#, true),
+            (r#"[[1, 2, 3], 4]"#, r#"[[1, 2]]"#, true),
+            (r#"[[1, 2, 3], 4]"#, r#"[1, 4]"#, true),
+            (r#"[{"a": 1, "b": 2}]"#, r#"{"a": 1}"#, true),
+            (r#"{"a": 1, "b": 2}"#, r#"{"a": 1}"#, true),
+            (r#"{"a": 1, "b": 2}"#, r#"{"a": 2}"#, false),
+            (r#"{"a": 1, "b": 2}"#, r#"{"c": 1}"#, false),
+            (r#"{"a": 1, "b": 2}"#, r#"{}"#, true),
+            (r#"{"a": [1, 2], "b": 2}"#, r#"{"a": 2}"#, true),
+            (r#"{"a": 1}"#, r#"1"#, false),
+            (r#"{"a": 1}"#, r#"[{"a": 1}]"#, false),
+            (r#"null"#, r#"null"#, true),
+            (r#"[null, true]"#, r#"true"#, true),
+        ];
+        for (i, (target, candidate, expected)) in test_cases.into_iter().enumerate() {
+            let target: Json = target.parse().unwrap();
+            let candidate: Json = candidate.parse().unwrap();
+            let got = target.as_ref().contains(candidate.as_ref());
+            assert!(got.is_ok(), "#{} expect contains ok but got {:?}", i, got);
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+
+    #[test]
+    fn test_json_contains_at() {
+        let test_cases = vec![
+            (r#"{"a": 1, "b": {"c": [1, 2]}}"#, r#"2"#, "$.b.c", Some(Some(true))),
+            (r#"{"a": 1, "b": {"c": [1, 2]}}"#, r#"1"#, "$.a", Some(Some(true))),
+            (r#"{"a": 1, "b": {"c": [1, 2]}}"#, r#"1"#, "$.b", Some(Some(false))),
+            (r#"{"a": 1, "b": {"c": [1, 2]}}"#, r#"1"#, "$.d", Some(None)),
+            (r#"{"a": 1}"#, r#"1"#, "$.*", None),
+            (r#"[1]"#, r#"1"#, "$[*]", None),
+        ];
+        for (i, (target, candidate, local_path, expected)) in test_cases.into_iter().enumerate() {
+            let target: Json = target.parse().unwrap();
+            let candidate: Json = candidate.parse().unwrap();
+            let p = parse_json_local_path_expr(local_path).unwrap();
+            let got = target.as_ref().contains_at(candidate.as_ref(), &p);
+            match expected {
+                Some(expected) => {
+                    assert!(got.is_ok(), "#{} expect contains ok but got {:?}", i, got);
+                    assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+                }
+                None => assert!(got.is_err(), "#{} expect error but got {:?}", i, got),
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use std::str::FromStr;
+
+use super::JsonRef;
+use super::json_extract::extract_json;
+use super::path_expr::LocalPathExpression;
+use super::super::{Error, Result};
+
+/// The `one_or_all` argument of `JSON_CONTAINS_PATH` and `JSON_SEARCH`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OneOrAll {
+    One,
+    All,
+}
+
+impl FromStr for OneOrAll {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<OneOrAll> {
+        if s.eq_ignore_ascii_case("one") {
+            Ok(OneOrAll::One)
+        } else if s.eq_ignore_ascii_case("all") {
+            Ok(OneOrAll::All)
+        } else {
+            Err(box_err!("The oneOrAll argument may take these causet_locales: 'one' or 'all'"))
+        }
+    }
+}
+
+impl<'a> JsonRef<'a> {
+    /// `contains_path` reports whether any (`One`) or every (`All`) local_path in
+    /// `local_path_expr_list` matches some causet_locale in this JSON document.
+    ///
+    /// See `ContainsPath()` in MEDB `json/binary_function.go`
+    pub fn contains_path(
+        &self,
+        one_or_all: OneOrAll,
+        local_path_expr_list: &[LocalPathExpression],
+    ) -> Result<bool> {
+        for local_path_expr in local_path_expr_list {
+            let found = !extract_json(*self, &local_path_expr.legs)?.is_empty();
+            match one_or_all {
+                OneOrAll::One if found => return Ok(true),
+                OneOrAll::All if !found => return Ok(false),
+                _ => {}
+            }
+        }
+        Ok(one_or_all == OneOrAll::All)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Json;
+    use super::super::path_expr::parse_json_local_path_expr;
+
+    #[test]
+    fn test_one_or_all() {
+        assert_eq!("one".parse::<OneOrAll>().unwrap(), OneOrAll::One);
+        assert_eq!("ALL".parse::<OneOrAll>().unwrap(), OneOrAll::All);
+        assert!("any".parse::<OneOrAll>().is_err());
+    }
+
+    #[test]
+    fn test_json_contains_path() {
+        let j = r#"{"a": 1, "b": 2, "c": {"d": [3, 4]}}"#;
+        let test_cases = vec![
+            ("one", vec!["$.a", "$.e"], true),
+            ("all", vec!["$.a", "$.e"], false),
+            ("all", vec!["$.a", "$.c.d"], true),
+            ("one", vec!["$.c.d[1]"], true),
+            ("one", vec!["$.c.d[2]"], false),
+            ("one", vec!["$.c.*"], true),
+            ("one", vec!["$**.d"], true),
+            ("all", vec!["$**.e"], false),
+        ];
+        let j: Json = j.parse().unwrap();
+        for (i, (one_or_all, local_paths, expected)) in test_cases.into_iter().enumerate() {
+            let one_or_all: OneOrAll = one_or_all.parse().unwrap();
+            let exprs: Vec<_> = local_paths
+                .iter()
+                .map(|p| parse_json_local_path_expr(p).unwrap())
+                .collect();
+            let got = j.as_ref().contains_path(one_or_all, &exprs);
+            assert!(got.is_ok(), "#{} expect contains_path ok but got {:?}", i, got);
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+}
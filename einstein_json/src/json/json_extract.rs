@@ -23,6 +23,8 @@ use std::error::Error;
 use std::result::Result as StdResult;
 use std::convert::From;
 
+use super::path_expr::ArrayIndex;
+
 
 
 /// This enum is used to represent the different types of json values.
@@ -81,6 +83,33 @@ pub fn extract_json<'a>(j: JsonRef<'a>, local_path_legs: &[local_pathLeg]) -> Re
                 }
             }
         },
+        local_pathLeg::LastIndex(n) => {
+            // A nonarray is autowrapped as an array of one element.
+            match j.get_type() {
+                JsonType::Array => {
+                    if let Some(k) = ArrayIndex::Right(n).resolve(j.get_elem_count()) {
+                        ret.append(&mut extract_json(j.array_get_elem(k)?, sub_local_path_legs)?)
+                    }
+                }
+                _ => {
+                    if n == 0 {
+                        ret.append(&mut extract_json(j, sub_local_path_legs)?)
+                    }
+                }
+            }
+        }
+        local_pathLeg::Range(from, to) => match j.get_type() {
+            JsonType::Array => {
+                for k in ArrayIndex::range(from, to, j.get_elem_count()) {
+                    ret.append(&mut extract_json(j.array_get_elem(k)?, sub_local_path_legs)?)
+                }
+            }
+            _ => {
+                if !ArrayIndex::range(from, to, 1).is_empty() {
+                    ret.append(&mut extract_json(j, sub_local_path_legs)?)
+                }
+            }
+        },
         local_pathLeg::Key(ref soliton_id) => {
             if j.get_type() == JsonType::Object {
                 if soliton_id == local_path_EXPR_ASTERISK {
@@ -307,4 +336,38 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_json_extract_ranges() {
+        use super::super::path_expr::parse_json_local_path_expr;
+
+        let test_cases = vec![
+            ("[1, 2, 3, 4]", "$[last]", Some("4")),
+            ("[1, 2, 3, 4]", "$[last-1]", Some("3")),
+            ("[1, 2, 3, 4]", "$[last-3]", Some("1")),
+            ("[1, 2, 3, 4]", "$[last-4]", None),
+            ("[1, 2, 3, 4]", "$[1 to 2]", Some("[2, 3]")),
+            ("[1, 2, 3, 4]", "$[2 to 9]", Some("[3, 4]")),
+            ("[1, 2, 3, 4]", "$[last-1 to last]", Some("[3, 4]")),
+            ("[1, 2, 3, 4]", "$[last-9 to 1]", Some("[1, 2]")),
+            ("[1, 2, 3, 4]", "$[last to 1]", None),
+            ("[1, 2, 3, 4]", "$[4 to 9]", None),
+            ("[1, 2, 3, 4]", "$[2 to 2]", Some("3")),
+            ("[[1, 2], [3, 4]]", "$[last][0 to last]", Some("[3, 4]")),
+            ("[[1, 2], [3, 4]]", "$[0 to last][last]", Some("[2, 4]")),
+            ("[]", "$[last]", None),
+            // A nonarray is autowrapped as an array of one element.
+            (r#"{"a": 1}"#, "$[last].a", Some("1")),
+            (r#"{"a": 1}"#, "$[last-1]", None),
+            (r#""s""#, "$[0 to last]", Some(r#""s""#)),
+            (r#""s""#, "$[1 to 2]", None),
+        ];
+        for (i, (js, local_path, expected)) in test_cases.into_iter().enumerate() {
+            let j: Json = js.parse().unwrap();
+            let expr = parse_json_local_path_expr(local_path).unwrap();
+            let expected = expected.map(|e| Json::from_str(e).unwrap());
+            let got = j.as_ref().extract(&[expr]).unwrap();
+            assert_eq!(got, expected, "#{} expect {:?}, but got {:?}", i, expected, got);
+        }
+    }
 }
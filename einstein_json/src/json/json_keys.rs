@@ -199,10 +199,10 @@ impl<'a> JsonRef<'a> {
             }
             if local_path_expr_list
                 .iter()
-                .any(|expr| expr.contains_any_asterisk())
+                .any(|expr| expr.could_match_multiple())
             {
                 return Err(box_err!(
-                    "Invalid local_path expression: expected no asterisk, but {:?}",
+                    "Invalid local_path expression: expected no asterisk or range, but {:?}",
                     local_path_expr_list
                 ));
             }
@@ -227,10 +227,10 @@ impl JsonRef {
             }
             if local_path_expr_list
                 .iter()
-                .any(|expr| expr.contains_any_asterisk())
+                .any(|expr| expr.could_match_multiple())
             {
                 return Err(box_err!(
-                    "Invalid local_path expression: expected no asterisk, but {:?}",
+                    "Invalid local_path expression: expected no asterisk or range, but {:?}",
                     local_path_expr_list
                 ));
             }
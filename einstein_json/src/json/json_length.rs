@@ -97,7 +97,7 @@ impl<'a> JsonRef<'a> {
         if local_path_expr_list.is_empty() {
             return Ok(self.len());
         }
-        if local_path_expr_list.len() == 1 && local_path_expr_list[0].contains_any_asterisk() {
+        if local_path_expr_list.len() == 1 && local_path_expr_list[0].could_match_multiple() {
             return Ok(None);
         }
         Ok(self.extract(local_path_expr_list)?.and_then(|j| j.as_ref().len()))
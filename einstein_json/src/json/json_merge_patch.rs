@@ -0,0 +1,102 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use std::collections::BTreeMap;
+use std::str;
+
+use super::{Json, JsonRef, JsonType};
+use super::super::Result;
+
+impl Json {
+    /// `merge_patch` applies each document in turn as an RFC 7396 merge patch to the one
+    /// before it: a nonobject patch replaces the target, and an object patch is merged soliton_id
+    /// by soliton_id, where a `null` causet_locale removes the soliton_id from the target.
+    ///
+    /// See `MergePatchBinaryJSON()` in MEDB `json/binary_function.go`
+    pub fn merge_patch(elems: Vec<JsonRef<'_>>) -> Result<Json> {
+        let mut elems = elems.into_iter();
+        let mut res = match elems.next() {
+            Some(first) => first.to_owned(),
+            None => return Err(box_err!("Incorrect parameter count in the call to native function 'JSON_MERGE_PATCH'")),
+        };
+        for patch in elems {
+            res = merge_patch_pair(Some(res.as_ref()), patch)?;
+        }
+        Ok(res)
+    }
+}
+
+fn merge_patch_pair(target: Option<JsonRef<'_>>, patch: JsonRef<'_>) -> Result<Json> {
+    if patch.get_type() != JsonType::Object {
+        return Ok(patch.to_owned());
+    }
+    let target = target.filter(|t| t.get_type() == JsonType::Object);
+    let mut entries = BTreeMap::new();
+    if let Some(target) = target {
+        for i in 0..target.get_elem_count() {
+            let soliton_id = str::from_utf8(target.object_get_soliton_id(i))?.to_owned();
+            entries.insert(soliton_id, target.object_get_val(i)?.to_owned());
+        }
+    }
+    for i in 0..patch.get_elem_count() {
+        let soliton_id = str::from_utf8(patch.object_get_soliton_id(i))?.to_owned();
+        let causet_locale = patch.object_get_val(i)?;
+        if causet_locale.get_type() == JsonType::Literal && causet_locale.get_literal().is_none() {
+            entries.remove(&soliton_id);
+            continue;
+        }
+        let merged = {
+            let existing = entries.get(&soliton_id).map(|j| j.as_ref());
+            merge_patch_pair(existing, causet_locale)?
+        };
+        entries.insert(soliton_id, merged);
+    }
+    Json::from_object(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_merge_patch() {
+        // The cases of RFC 7396, appendix A, plus a few of more than two documents.
+        let test_cases = vec![
+            (vec![r#"{"a": "b"}"#, r#"{"a": "c"}"#], r#"{"a": "c"}"#),
+            (vec![r#"{"a": "b"}"#, r#"{"b": "c"}"#], r#"{"a": "b", "b": "c"}"#),
+            (vec![r#"{"a": "b"}"#, r#"{"a": null}"#], r#"{}"#),
+            (vec![r#"{"a": "b", "b": "c"}"#, r#"{"a": null}"#], r#"{"b": "c"}"#),
+            (vec![r#"{"a": ["b"]}"#, r#"{"a": "c"}"#], r#"{"a": "c"}"#),
+            (vec![r#"{"a": "c"}"#, r#"{"a": ["b"]}"#], r#"{"a": ["b"]}"#),
+            (
+                vec![r#"{"a": {"b": "c"}}"#, r#"{"a": {"b": "d", "c": null}}"#],
+                r#"{"a": {"b": "d"}}"#,
+            ),
+            (vec![r#"{"a": [{"b": "c"}]}"#, r#"{"a": [1]}"#], r#"{"a": [1]}"#),
+            (vec![r#"["a", "b"]"#, r#"["c", "d"]"#], r#"["c", "d"]"#),
+            (vec![r#"{"a": "b"}"#, r#"["c"]"#], r#"["c"]"#),
+            (vec![r#"{"a": "foo"}"#, r#"null"#], r#"null"#),
+            (vec![r#"{"a": "foo"}"#, r#""bar""#], r#""bar""#),
+            (vec![r#"{"e": null}"#, r#"{"a": 1}"#], r#"{"e": null, "a": 1}"#),
+            (vec![r#"[1, 2]"#, r#"{"a": "b", "c": null}"#], r#"{"a": "b"}"#),
+            (vec![r#"{}"#, r#"{"a": {"bb": {"ccc": null}}}"#], r#"{"a": {"bb": {}}}"#),
+            (vec![r#"{"a": 1}"#, r#"{"b": 2}"#, r#"{"a": null}"#], r#"{"b": 2}"#),
+            (vec![r#"1"#], r#"1"#),
+        ];
+        for (i, (docs, expected)) in test_cases.into_iter().enumerate() {
+            let docs: Vec<Json> = docs.into_iter().map(|d| d.parse().unwrap()).collect();
+            let got = Json::merge_patch(docs.iter().map(|d| d.as_ref()).collect());
+            assert!(got.is_ok(), "#{} expect merge_patch ok but got {:?}", i, got);
+            let expected: Json = expected.parse().unwrap();
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+        assert!(Json::merge_patch(vec![]).is_err());
+    }
+}
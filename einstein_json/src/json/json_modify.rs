@@ -130,9 +130,9 @@ impl<'a> JsonRef<'a> {
             ));
         }
         for expr in local_path_expr_list {
-            if expr.contains_any_asterisk() {
+            if expr.could_match_multiple() {
                 return Err(box_err!(
-                    "Invalid local_path expression: expected no asterisk, found {:?}",
+                    "Invalid local_path expression: expected no asterisk or range, found {:?}",
                     expr
                 ));
             }
@@ -264,6 +264,17 @@ mod tests {
                 r#"null"#,
                 false,
             ),
+            (r#"[1, 2, 3]"#, "$[last]", r#"9"#, ModifyType::Set, r#"[1, 2, 9]"#, true),
+            (r#"[1, 2, 3]"#, "$[last-1]", r#"9"#, ModifyType::Replace, r#"[1, 9, 3]"#, true),
+            (r#"{"a": [1]}"#, "$.a[last]", r#"9"#, ModifyType::Set, r#"{"a": [9]}"#, true),
+            // Nothing changed because we want to insert but the full local_path exists.
+            (r#"[1, 2, 3]"#, "$[last]", r#"9"#, ModifyType::Insert, r#"[1, 2, 3]"#, true),
+            // Nothing changed because `last-5` is before the first element.
+            (r#"[1, 2, 3]"#, "$[last-5]", r#"9"#, ModifyType::Set, r#"[1, 2, 3]"#, true),
+            (r#"[1, 2, 3]"#, "$[last-5]", r#"9"#, ModifyType::Replace, r#"[1, 2, 3]"#, true),
+            // Ranges are rejected like wildcards.
+            (r#"[1, 2, 3]"#, "$[0 to 1]", r#"9"#, ModifyType::Set, r#"null"#, false),
+            (r#"[1, 2, 3]"#, "$[last-1 to last]", r#"9"#, ModifyType::Replace, r#"null"#, false),
         ];
         for (i, (json, local_path, causet_locale, mt, expected, success)) in test_cases.drain(..).enumerate() {
             let json: Result<Json> = json.parse();
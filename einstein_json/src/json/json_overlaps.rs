@@ -0,0 +1,78 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use super::{JsonRef, JsonType};
+use super::super::Result;
+
+impl<'a> JsonRef<'a> {
+    /// `overlaps` reports whether two JSON documents have anything in common: two objects
+    /// overlap if they share a soliton_id with equal causet_locales; otherwise both sides are
+    /// treated as arrays (a nonarray is autowrapped) which overlap if they share an element.
+    ///
+    /// See `OverlapsBinaryJSON()` in MEDB `json/binary_function.go`
+    pub fn overlaps(&self, other: JsonRef<'_>) -> Result<bool> {
+        if self.get_type() == JsonType::Object && other.get_type() == JsonType::Object {
+            for i in 0..self.get_elem_count() {
+                if let Some(idx) = other.object_search_soliton_id(self.object_get_soliton_id(i)) {
+                    if self.object_get_val(i)? == other.object_get_val(idx)? {
+                        return Ok(true);
+                    }
+                }
+            }
+            return Ok(false);
+        }
+        let (left, right) = (autowrapped_elems(*self)?, autowrapped_elems(other)?);
+        Ok(left.iter().any(|l| right.iter().any(|r| l == r)))
+    }
+}
+
+fn autowrapped_elems(j: JsonRef<'_>) -> Result<Vec<JsonRef<'_>>> {
+    if j.get_type() != JsonType::Array {
+        return Ok(vec![j]);
+    }
+    let mut elems = Vec::with_capacity(j.get_elem_count());
+    for i in 0..j.get_elem_count() {
+        elems.push(j.array_get_elem(i)?);
+    }
+    Ok(elems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Json;
+
+    #[test]
+    fn test_json_overlaps() {
+        let test_cases = vec![
+            (r#"[1, 3, 5, 7]"#, r#"[2, 5, 7]"#, true),
+            (r#"[1, 3, 5, 7]"#, r#"[2, 6, 8]"#, false),
+            (r#"[[1, 2], [3, 4], 5]"#, r#"[1, [2, 3], [4, 5]]"#, false),
+            (r#"[[1, 2], [3, 4], 5]"#, r#"[[1, 2]]"#, true),
+            (r#"[1, 3, 5, 7]"#, r#"5"#, true),
+            (r#"5"#, r#"5"#, true),
+            (r#"5"#, r#""5""#, false),
+            (r#"{"a": 1, "b": 10, "d": 10}"#, r#"{"c": 1, "e": 10, "f": 1, "d": 10}"#, true),
+            (r#"{"a": 1, "b": 10, "d": 10}"#, r#"{"a": 5, "e": 10, "f": 1, "d": 20}"#, false),
+            (r#"[{"a": 1}]"#, r#"{"a": 1}"#, true),
+            (r#"[]"#, r#"[]"#, false),
+            (r#"null"#, r#"[null]"#, true),
+        ];
+        for (i, (left, right, expected)) in test_cases.into_iter().enumerate() {
+            let left: Json = left.parse().unwrap();
+            let right: Json = right.parse().unwrap();
+            let got = left.as_ref().overlaps(right.as_ref());
+            assert!(got.is_ok(), "#{} expect overlaps ok but got {:?}", i, got);
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+            let got = right.as_ref().overlaps(left.as_ref());
+            assert_eq!(got.unwrap(), expected, "#{} expect overlaps to be symmetric", i);
+        }
+    }
+}
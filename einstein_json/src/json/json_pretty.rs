@@ -0,0 +1,96 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use std::str;
+
+use super::{JsonRef, JsonType};
+use super::super::Result;
+
+const PRETTY_INDENT: &str = "  ";
+
+impl<'a> JsonRef<'a> {
+    /// `pretty` prints this JSON document the way MyBerolinaSQL's `JSON_PRETTY` does: each
+    /// array element and object member on its own line, indented by two spaces per level,
+    /// with `": "` between a soliton_id and its causet_locale. Empty arrays and objects stay on
+    /// one line.
+    pub fn pretty(&self) -> Result<String> {
+        let mut buf = String::new();
+        pretty_to(*self, 0, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+fn pretty_to(j: JsonRef<'_>, depth: usize, buf: &mut String) -> Result<()> {
+    let elem_count = match j.get_type() {
+        JsonType::Array | JsonType::Object => j.get_elem_count(),
+        _ => {
+            buf.push_str(&j.to_owned().to_string());
+            return Ok(());
+        }
+    };
+    let (open, close) = if j.get_type() == JsonType::Array {
+        ('[', ']')
+    } else {
+        ('{', '}')
+    };
+    buf.push(open);
+    for i in 0..elem_count {
+        buf.push_str(if i == 0 { "\n" } else { ",\n" });
+        push_indent(depth + 1, buf);
+        if j.get_type() == JsonType::Array {
+            pretty_to(j.array_get_elem(i)?, depth + 1, buf)?;
+        } else {
+            let soliton_id = str::from_utf8(j.object_get_soliton_id(i))?;
+            buf.push_str(&serde_json::to_string(soliton_id).unwrap());
+            buf.push_str(": ");
+            pretty_to(j.object_get_val(i)?, depth + 1, buf)?;
+        }
+    }
+    if elem_count > 0 {
+        buf.push('\n');
+        push_indent(depth, buf);
+    }
+    buf.push(close);
+    Ok(())
+}
+
+fn push_indent(depth: usize, buf: &mut String) {
+    for _ in 0..depth {
+        buf.push_str(PRETTY_INDENT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Json;
+
+    #[test]
+    fn test_json_pretty() {
+        let test_cases = vec![
+            (r#"1"#, "1"),
+            (r#""a\"b""#, r#""a\"b""#),
+            (r#"null"#, "null"),
+            (r#"[]"#, "[]"),
+            (r#"{}"#, "{}"),
+            (r#"[1, "2"]"#, "[\n  1,\n  \"2\"\n]"),
+            (
+                r#"{"b": [1, {"c": []}], "a": {}}"#,
+                "{\n  \"a\": {},\n  \"b\": [\n    1,\n    {\n      \"c\": []\n    }\n  ]\n}",
+            ),
+        ];
+        for (i, (json, expected)) in test_cases.into_iter().enumerate() {
+            let j: Json = json.parse().unwrap();
+            let got = j.as_ref().pretty();
+            assert!(got.is_ok(), "#{} expect pretty ok but got {:?}", i, got);
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+}
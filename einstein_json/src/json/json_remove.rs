@@ -20,7 +20,7 @@
     pub fn remove(&self, local_path_expr_list: &[local_pathExpression]) -> Result<Json> {
         if local_path_expr_list
             .iter()
-            .any(|expr| expr.legs.is_empty() || expr.contains_any_asterisk())
+            .any(|expr| expr.legs.is_empty() || expr.could_match_multiple())
         {
             return Err(box_err!("Invalid local_path expression"));
         }
@@ -59,6 +59,13 @@ mod tests {
             (r#"null"#, "$[*]", r#"null"#, false),
             (r#"null"#, "$**.a", r#"null"#, false),
             (r#"null"#, "$**[3]", r#"null"#, false),
+            (r#"[1, 2, 3]"#, "$[last]", r#"[1, 2]"#, true),
+            (r#"[1, 2, 3]"#, "$[last-2]", r#"[2, 3]"#, true),
+            (r#"{"a": [3, 4]}"#, "$.a[last]", r#"{"a": [3]}"#, true),
+            // Nothing changed because `last-3` is before the first element.
+            (r#"[1, 2, 3]"#, "$[last-3]", r#"[1, 2, 3]"#, true),
+            (r#"[1, 2, 3]"#, "$[0 to 1]", r#"null"#, false),
+            (r#"[1, 2, 3]"#, "$[last-1 to last]", r#"null"#, false),
         ];
 
         for (i, (json, local_path, expected, success)) in test_cases.into_iter().enumerate() {
@@ -0,0 +1,705 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use std::collections::{BTreeMap, HashMap};
+use std::str;
+
+use regex::Regex;
+
+use super::{Json, JsonRef, JsonType};
+use super::super::Result;
+
+/// The causet_locales of the `type` keyword.
+const SCHEMA_TYPES: &[&str] = &["array", "boolean", "integer", "null", "number", "object", "string"];
+
+/// A draft-04 JSON Schema, checked to be well formed.
+///
+/// The supported keywords are `type`, `enum`, `minimum`/`maximum` (with the boolean
+/// `exclusiveMinimum`/`exclusiveMaximum`), `minLength`/`maxLength`, `pattern`, `items`,
+/// `minItems`/`maxItems`, `required`, `properties`, `patternProperties`,
+/// `additionalProperties`, `minProperties`/`maxProperties` and `$ref` to a JSON pointer within
+/// the schema itself, such as `#/definitions/address`. Other keywords are ignored.
+#[derive(Clone, Debug)]
+pub struct JsonSchema {
+    schema: Json,
+    /// The `pattern`s and `patternProperties` names of the schema, compiled.
+    regexes: HashMap<String, Regex>,
+}
+
+/// Where a document first failed its schema.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaFailure {
+    /// A JSON pointer into the schema, e.g. `#/properties/age`.
+    pub schema_location: String,
+    /// A JSON pointer into the document, e.g. `#/age`.
+    pub document_location: String,
+    pub keyword: &'static str,
+}
+
+impl SchemaFailure {
+    pub fn reason(&self) -> String {
+        format!(
+            "The JSON document location '{}' failed requirement '{}' at JSON Schema location '{}'",
+            self.document_location, self.keyword, self.schema_location
+        )
+    }
+}
+
+impl JsonSchema {
+    pub fn new(schema: JsonRef<'_>) -> Result<JsonSchema> {
+        let mut regexes = HashMap::new();
+        check_schema(schema, schema, "#", &mut regexes)?;
+        Ok(JsonSchema {
+            schema: schema.to_owned(),
+            regexes,
+        })
+    }
+
+    /// The first failure of `doc`, or `None` if it is valid.
+    pub fn validate(&self, doc: JsonRef<'_>) -> Result<Option<SchemaFailure>> {
+        let root = self.schema.as_ref();
+        validate(root, &self.regexes, root, "#", doc, "#", &mut vec![])
+    }
+
+    pub fn is_valid(&self, doc: JsonRef<'_>) -> Result<bool> {
+        Ok(self.validate(doc)?.is_none())
+    }
+
+    /// The object `JSON_SCHEMA_VALIDATION_REPORT` returns: `{"valid": true}`, or the failure's
+    /// `reason`, `schema-location`, `document-location` and `schema-failed-keyword` as well.
+    pub fn validation_report(&self, doc: JsonRef<'_>) -> Result<Json> {
+        let mut report = BTreeMap::new();
+        match self.validate(doc)? {
+            None => {
+                report.insert("valid".to_owned(), Json::from_bool(true)?);
+            }
+            Some(failure) => {
+                report.insert("valid".to_owned(), Json::from_bool(false)?);
+                report.insert("reason".to_owned(), Json::from_string(failure.reason())?);
+                report.insert("schema-location".to_owned(), Json::from_string(failure.schema_location)?);
+                report.insert("document-location".to_owned(), Json::from_string(failure.document_location)?);
+                report.insert("schema-failed-keyword".to_owned(), Json::from_str_val(failure.keyword)?);
+            }
+        }
+        Json::from_object(report)
+    }
+}
+
+impl<'a> JsonRef<'a> {
+    /// `schema_valid` is `JSON_SCHEMA_VALID(self, doc)`, with this JSON document as the schema.
+    pub fn schema_valid(&self, doc: JsonRef<'_>) -> Result<bool> {
+        JsonSchema::new(*self)?.is_valid(doc)
+    }
+
+    /// `schema_validation_report` is `JSON_SCHEMA_VALIDATION_REPORT(self, doc)`.
+    pub fn schema_validation_report(&self, doc: JsonRef<'_>) -> Result<Json> {
+        JsonSchema::new(*self)?.validation_report(doc)
+    }
+}
+
+fn get<'a>(j: JsonRef<'a>, soliton_id: &str) -> Result<Option<JsonRef<'a>>> {
+    match j.object_search_soliton_id(soliton_id.as_bytes()) {
+        Some(i) => Ok(Some(j.object_get_val(i)?)),
+        None => Ok(None),
+    }
+}
+
+fn as_number(j: JsonRef<'_>) -> Option<f64> {
+    match j.get_type() {
+        JsonType::I64 => Some(j.get_i64() as f64),
+        JsonType::U64 => Some(j.get_u64() as f64),
+        JsonType::Double => Some(j.get_double()),
+        _ => None,
+    }
+}
+
+fn is_integer(j: JsonRef<'_>) -> bool {
+    match j.get_type() {
+        JsonType::I64 | JsonType::U64 => true,
+        JsonType::Double => j.get_double().fract() == 0.0,
+        _ => false,
+    }
+}
+
+/// A non-negative integer, as the `min*`/`max*` keywords take.
+fn as_count(j: JsonRef<'_>) -> Option<usize> {
+    match as_number(j) {
+        Some(n) if is_integer(j) && n >= 0.0 => Some(n as usize),
+        _ => None,
+    }
+}
+
+fn type_name(j: JsonRef<'_>) -> &'static str {
+    match j.get_type() {
+        JsonType::Object => "object",
+        JsonType::Array => "array",
+        JsonType::String => "string",
+        JsonType::Literal => match j.get_literal() {
+            Some(_) => "boolean",
+            None => "null",
+        },
+        JsonType::I64 | JsonType::U64 | JsonType::Double => "number",
+    }
+}
+
+fn has_type(j: JsonRef<'_>, t: &str) -> bool {
+    match t {
+        "integer" => is_integer(j),
+        t => type_name(j) == t,
+    }
+}
+
+/// Escapes a soliton_id for use as a JSON pointer token.
+fn pointer_token(soliton_id: &str) -> String {
+    soliton_id.replace('~', "~0").replace('/', "~1")
+}
+
+fn pointer_child(location: &str, token: &str) -> String {
+    format!("{}/{}", location, pointer_token(token))
+}
+
+/// Follows a `$ref` pointer from the root of the schema.
+fn resolve<'a>(root: JsonRef<'a>, reference: &str) -> Result<JsonRef<'a>> {
+    if !reference.starts_with('#') {
+        return Err(box_err!(
+            "Invalid JSON Schema: only references within the schema are supported, found {:?}",
+            reference
+        ));
+    }
+    let mut j = root;
+    for token in reference[1..].split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        let next = match j.get_type() {
+            JsonType::Object => get(j, &token)?,
+            JsonType::Array => match token.parse::<usize>() {
+                Ok(i) if i < j.get_elem_count() => Some(j.array_get_elem(i)?),
+                _ => None,
+            },
+            _ => None,
+        };
+        j = match next {
+            Some(next) => next,
+            None => return Err(box_err!("Invalid JSON Schema: unresolvable $ref {:?}", reference)),
+        };
+    }
+    Ok(j)
+}
+
+/// Compiles `pattern` into `regexes`, unless it is there already.
+fn compile(regexes: &mut HashMap<String, Regex>, pattern: &str) -> bool {
+    if regexes.contains_key(pattern) {
+        return true;
+    }
+    match Regex::new(pattern) {
+        Ok(re) => {
+            regexes.insert(pattern.to_owned(), re);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Whether `s` matches `pattern`, as compiled by `check_schema`.
+fn is_match(regexes: &HashMap<String, Regex>, pattern: &str, s: &str) -> Result<bool> {
+    match regexes.get(pattern) {
+        Some(re) => Ok(re.is_match(s)),
+        // Only a `$ref` into a part of the schema that `check_schema` doesn't walk gets here.
+        None => match Regex::new(pattern) {
+            Ok(re) => Ok(re.is_match(s)),
+            Err(e) => Err(box_err!("Invalid JSON Schema: bad pattern {:?}: {}", pattern, e)),
+        },
+    }
+}
+
+/// Rejects schemas whose keywords have causet_locales of the wrong shape, so that validating
+/// against a schema can't fail halfway through a document.  Compiles the regexes of the schema
+/// into `regexes` on the way.
+fn check_schema(
+    root: JsonRef<'_>,
+    schema: JsonRef<'_>,
+    location: &str,
+    regexes: &mut HashMap<String, Regex>,
+) -> Result<()> {
+    let invalid = |keyword: &str| -> Result<()> {
+        Err(box_err!(
+            "Invalid JSON Schema: keyword {:?} at {:?} is malformed",
+            keyword,
+            location
+        ))
+    };
+    if schema.get_type() != JsonType::Object {
+        return Err(box_err!("Invalid JSON Schema: expected an object at {:?}", location));
+    }
+    for i in 0..schema.get_elem_count() {
+        let keyword = str::from_utf8(schema.object_get_soliton_id(i))?;
+        let v = schema.object_get_val(i)?;
+        let here = pointer_child(location, keyword);
+        match keyword {
+            "$ref" => {
+                if v.get_type() != JsonType::String {
+                    return invalid(keyword);
+                }
+                let target = resolve(root, v.get_str()?)?;
+                if target.get_type() != JsonType::Object {
+                    return invalid(keyword);
+                }
+            }
+            "type" => {
+                let ok = match v.get_type() {
+                    JsonType::String => SCHEMA_TYPES.contains(&v.get_str()?),
+                    JsonType::Array if v.get_elem_count() > 0 => {
+                        let mut ok = true;
+                        for e in 0..v.get_elem_count() {
+                            let t = v.array_get_elem(e)?;
+                            ok &= t.get_type() == JsonType::String && SCHEMA_TYPES.contains(&t.get_str()?);
+                        }
+                        ok
+                    }
+                    _ => false,
+                };
+                if !ok {
+                    return invalid(keyword);
+                }
+            }
+            "enum" => {
+                if v.get_type() != JsonType::Array || v.get_elem_count() == 0 {
+                    return invalid(keyword);
+                }
+            }
+            "minimum" | "maximum" => {
+                if as_number(v).is_none() {
+                    return invalid(keyword);
+                }
+            }
+            "exclusiveMinimum" | "exclusiveMaximum" => {
+                if v.get_type() != JsonType::Literal || v.get_literal().is_none() {
+                    return invalid(keyword);
+                }
+            }
+            "minLength" | "maxLength" | "minItems" | "maxItems" | "minProperties"
+            | "maxProperties" => {
+                if as_count(v).is_none() {
+                    return invalid(keyword);
+                }
+            }
+            "pattern" => {
+                if v.get_type() != JsonType::String || !compile(regexes, v.get_str()?) {
+                    return invalid(keyword);
+                }
+            }
+            "required" => {
+                if v.get_type() != JsonType::Array {
+                    return invalid(keyword);
+                }
+                for e in 0..v.get_elem_count() {
+                    if v.array_get_elem(e)?.get_type() != JsonType::String {
+                        return invalid(keyword);
+                    }
+                }
+            }
+            "properties" | "patternProperties" | "definitions" => {
+                if v.get_type() != JsonType::Object {
+                    return invalid(keyword);
+                }
+                for p in 0..v.get_elem_count() {
+                    let name = str::from_utf8(v.object_get_soliton_id(p))?;
+                    if keyword == "patternProperties" && !compile(regexes, name) {
+                        return invalid(keyword);
+                    }
+                    check_schema(root, v.object_get_val(p)?, &pointer_child(&here, name), regexes)?;
+                }
+            }
+            "additionalProperties" => match v.get_type() {
+                JsonType::Literal if v.get_literal().is_some() => {}
+                JsonType::Object => check_schema(root, v, &here, regexes)?,
+                _ => return invalid(keyword),
+            },
+            "items" => match v.get_type() {
+                JsonType::Object => check_schema(root, v, &here, regexes)?,
+                JsonType::Array => {
+                    for e in 0..v.get_elem_count() {
+                        check_schema(root, v.array_get_elem(e)?, &pointer_child(&here, &e.to_string()), regexes)?;
+                    }
+                }
+                _ => return invalid(keyword),
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Validates `doc` against `schema`, which lives at `schema_loc` within `root`. `refs` holds the
+/// `$ref`s followed since last stepping into the document, to catch reference cycles.
+fn validate(
+    root: JsonRef<'_>,
+    regexes: &HashMap<String, Regex>,
+    schema: JsonRef<'_>,
+    schema_loc: &str,
+    doc: JsonRef<'_>,
+    doc_loc: &str,
+    refs: &mut Vec<String>,
+) -> Result<Option<SchemaFailure>> {
+    let fail = |keyword: &'static str| -> Result<Option<SchemaFailure>> {
+        Ok(Some(SchemaFailure {
+            schema_location: schema_loc.to_owned(),
+            document_location: doc_loc.to_owned(),
+            keyword,
+        }))
+    };
+
+    // In draft-04 a `$ref` replaces the schema it appears in.
+    if let Some(reference) = get(schema, "$ref")? {
+        let reference = reference.get_str()?.to_owned();
+        if refs.contains(&reference) {
+            return Err(box_err!("Invalid JSON Schema: circular $ref {:?}", reference));
+        }
+        let target = resolve(root, &reference)?;
+        refs.push(reference.clone());
+        return validate(root, regexes, target, &reference, doc, doc_loc, refs);
+    }
+
+    if let Some(types) = get(schema, "type")? {
+        let ok = match types.get_type() {
+            JsonType::String => has_type(doc, types.get_str()?),
+            _ => {
+                let mut ok = false;
+                for i in 0..types.get_elem_count() {
+                    ok |= has_type(doc, types.array_get_elem(i)?.get_str()?);
+                }
+                ok
+            }
+        };
+        if !ok {
+            return fail("type");
+        }
+    }
+
+    if let Some(causet_locales) = get(schema, "enum")? {
+        let mut found = false;
+        for i in 0..causet_locales.get_elem_count() {
+            found |= causet_locales.array_get_elem(i)? == doc;
+        }
+        if !found {
+            return fail("enum");
+        }
+    }
+
+    if let Some(n) = as_number(doc) {
+        let exclusive = |keyword: &str| -> Result<bool> {
+            Ok(get(schema, keyword)?.and_then(|e| e.get_literal()).unwrap_or(false))
+        };
+        if let Some(min) = get(schema, "minimum")?.and_then(as_number) {
+            if n < min || (n == min && exclusive("exclusiveMinimum")?) {
+                return fail("minimum");
+            }
+        }
+        if let Some(max) = get(schema, "maximum")?.and_then(as_number) {
+            if n > max || (n == max && exclusive("exclusiveMaximum")?) {
+                return fail("maximum");
+            }
+        }
+    }
+
+    if doc.get_type() == JsonType::String {
+        let s = doc.get_str()?;
+        let len = s.chars().count();
+        if let Some(min) = get(schema, "minLength")?.and_then(as_count) {
+            if len < min {
+                return fail("minLength");
+            }
+        }
+        if let Some(max) = get(schema, "maxLength")?.and_then(as_count) {
+            if len > max {
+                return fail("maxLength");
+            }
+        }
+        if let Some(pattern) = get(schema, "pattern")? {
+            if !is_match(regexes, pattern.get_str()?, s)? {
+                return fail("pattern");
+            }
+        }
+    }
+
+    if doc.get_type() == JsonType::Array {
+        let count = doc.get_elem_count();
+        if let Some(min) = get(schema, "minItems")?.and_then(as_count) {
+            if count < min {
+                return fail("minItems");
+            }
+        }
+        if let Some(max) = get(schema, "maxItems")?.and_then(as_count) {
+            if count > max {
+                return fail("maxItems");
+            }
+        }
+        if let Some(items) = get(schema, "items")? {
+            let items_loc = pointer_child(schema_loc, "items");
+            for i in 0..count {
+                // A single schema applies to every element; an array of schemas applies
+                // position by position, and elements past its end are unconstrained.
+                let (item_schema, item_loc) = match items.get_type() {
+                    JsonType::Object => (items, items_loc.clone()),
+                    _ if i < items.get_elem_count() => {
+                        (items.array_get_elem(i)?, pointer_child(&items_loc, &i.to_string()))
+                    }
+                    _ => break,
+                };
+                let elem_loc = pointer_child(doc_loc, &i.to_string());
+                let failure = validate(root, regexes, item_schema, &item_loc, doc.array_get_elem(i)?, &elem_loc, &mut vec![])?;
+                if failure.is_some() {
+                    return Ok(failure);
+                }
+            }
+        }
+    }
+
+    if doc.get_type() == JsonType::Object {
+        let count = doc.get_elem_count();
+        if let Some(min) = get(schema, "minProperties")?.and_then(as_count) {
+            if count < min {
+                return fail("minProperties");
+            }
+        }
+        if let Some(max) = get(schema, "maxProperties")?.and_then(as_count) {
+            if count > max {
+                return fail("maxProperties");
+            }
+        }
+        if let Some(required) = get(schema, "required")? {
+            for i in 0..required.get_elem_count() {
+                if get(doc, required.array_get_elem(i)?.get_str()?)?.is_none() {
+                    return fail("required");
+                }
+            }
+        }
+
+        let properties = get(schema, "properties")?;
+        let pattern_properties = get(schema, "patternProperties")?;
+        let mut patterns = vec![];
+        if let Some(pp) = pattern_properties {
+            for i in 0..pp.get_elem_count() {
+                let pattern = str::from_utf8(pp.object_get_soliton_id(i))?;
+                patterns.push((pattern, pp.object_get_val(i)?));
+            }
+        }
+        let additional = get(schema, "additionalProperties")?;
+
+        for i in 0..count {
+            let name = str::from_utf8(doc.object_get_soliton_id(i))?;
+            let member = doc.object_get_val(i)?;
+            let member_loc = pointer_child(doc_loc, name);
+            // Every schema that applies to the member: the named property and any matching
+            // patterns, or else `additionalProperties`.
+            let mut applicable = vec![];
+            if let Some(sub) = properties.map(|p| get(p, name)).transpose()?.and_then(|s| s) {
+                let loc = pointer_child(&pointer_child(schema_loc, "properties"), name);
+                applicable.push((sub, loc));
+            }
+            for &(pattern, sub) in patterns.iter() {
+                if is_match(regexes, pattern, name)? {
+                    let loc = pointer_child(&pointer_child(schema_loc, "patternProperties"), pattern);
+                    applicable.push((sub, loc));
+                }
+            }
+            if applicable.is_empty() {
+                match additional {
+                    Some(a) if a.get_type() == JsonType::Literal => {
+                        if a.get_literal() == Some(false) {
+                            return fail("additionalProperties");
+                        }
+                    }
+                    Some(a) => applicable.push((a, pointer_child(schema_loc, "additionalProperties"))),
+                    None => {}
+                }
+            }
+            for (sub, loc) in applicable {
+                let failure = validate(root, regexes, sub, &loc, member, &member_loc, &mut vec![])?;
+                if failure.is_some() {
+                    return Ok(failure);
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_schema_valid() {
+        let person = r##"{
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string", "minLength": 1, "maxLength": 5},
+                "age": {"type": "integer", "minimum": 0, "maximum": 150, "exclusiveMaximum": true},
+                "email": {"type": "string", "pattern": "^[^@]+@[^@]+$"},
+                "role": {"enum": ["admin", "user", null]},
+                "tags": {"type": "array", "items": {"type": "string"}, "maxItems": 2},
+                "home": {"$ref": "#/definitions/address"}
+            },
+            "additionalProperties": false,
+            "definitions": {
+                "address": {"type": "object", "properties": {"zip": {"type": ["string", "integer"]}}}
+            }
+        }"##;
+        let test_cases = vec![
+            (r#"{"name": "Ann"}"#, true),
+            (r#"{"name": "Ann", "age": 30, "email": "a@b", "role": null}"#, true),
+            (r#"{"name": "Ann", "age": 30.0}"#, true),
+            (r#"{"name": "Ann", "tags": ["a", "b"], "home": {"zip": 10115}}"#, true),
+            (r#"{"name": "Ann", "home": {"zip": "10115"}}"#, true),
+            (r#"{}"#, false),
+            (r#"[]"#, false),
+            (r#"{"name": ""}"#, false),
+            (r#"{"name": "Annabel"}"#, false),
+            (r#"{"name": "Ann", "age": 30.5}"#, false),
+            (r#"{"name": "Ann", "age": -1}"#, false),
+            (r#"{"name": "Ann", "age": 150}"#, false),
+            (r#"{"name": "Ann", "email": "ab"}"#, false),
+            (r#"{"name": "Ann", "role": "root"}"#, false),
+            (r#"{"name": "Ann", "tags": ["a", 1]}"#, false),
+            (r#"{"name": "Ann", "tags": ["a", "b", "c"]}"#, false),
+            (r#"{"name": "Ann", "home": {"zip": true}}"#, false),
+            (r#"{"name": "Ann", "nickname": "A"}"#, false),
+        ];
+        let schema: Json = person.parse().unwrap();
+        for (i, (doc, expected)) in test_cases.into_iter().enumerate() {
+            let doc: Json = doc.parse().unwrap();
+            let got = schema.as_ref().schema_valid(doc.as_ref());
+            assert!(got.is_ok(), "#{} expect schema_valid ok but got {:?}", i, got);
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+
+    #[test]
+    fn test_json_schema_keywords() {
+        let test_cases = vec![
+            (r#"{"items": [{"type": "integer"}, {"type": "string"}]}"#, r#"[1, "a", null]"#, true),
+            (r#"{"items": [{"type": "integer"}, {"type": "string"}]}"#, r#"[1, 2]"#, false),
+            (r#"{"minItems": 1}"#, r#"[]"#, false),
+            (r#"{"minItems": 1}"#, r#"{}"#, true),
+            (r#"{"minProperties": 1, "maxProperties": 1}"#, r#"{"a": 1}"#, true),
+            (r#"{"maxProperties": 1}"#, r#"{"a": 1, "b": 2}"#, false),
+            (r#"{"minimum": 1, "exclusiveMinimum": true}"#, r#"1"#, false),
+            (r#"{"minimum": 1}"#, r#"1"#, true),
+            (r#"{"minimum": 1}"#, r#""0""#, true),
+            (r#"{"pattern": "b"}"#, r#""abc""#, true),
+            (r#"{"minLength": 2}"#, r#""ü""#, false),
+            (r#"{"patternProperties": {"^x-": {"type": "integer"}}, "additionalProperties": false}"#, r#"{"x-a": 1}"#, true),
+            (r#"{"patternProperties": {"^x-": {"type": "integer"}}, "additionalProperties": false}"#, r#"{"x-a": "1"}"#, false),
+            (r#"{"patternProperties": {"^x-": {"type": "integer"}}, "additionalProperties": false}"#, r#"{"y": 1}"#, false),
+            (r#"{"additionalProperties": {"type": "boolean"}}"#, r#"{"a": true}"#, true),
+            (r#"{"additionalProperties": {"type": "boolean"}}"#, r#"{"a": 1}"#, false),
+            (r#"{"type": "null"}"#, r#"null"#, true),
+            (r#"{"type": "boolean"}"#, r#"null"#, false),
+            (r#"{"type": "number"}"#, r#"1"#, true),
+            (r#"{"enum": [[1, 2], {"a": 1}]}"#, r#"{"a": 1.0}"#, true),
+            (r#"{"unknownKeyword": 1}"#, r#"1"#, true),
+            (r#"{"$ref": "#/definitions/a", "definitions": {"a": {"type": "string"}}}"#, r#"1"#, false),
+            // A recursive schema: a tree of integers.
+            (
+                r#"{"type": "object", "properties": {"v": {"type": "integer"}, "children": {"type": "array", "items": {"$ref": "#"}}}}"#,
+                r#"{"v": 1, "children": [{"v": 2, "children": []}, {"v": 3}]}"#,
+                true,
+            ),
+            (
+                r#"{"type": "object", "properties": {"v": {"type": "integer"}, "children": {"type": "array", "items": {"$ref": "#"}}}}"#,
+                r#"{"v": 1, "children": [{"v": "2"}]}"#,
+                false,
+            ),
+        ];
+        for (i, (schema, doc, expected)) in test_cases.into_iter().enumerate() {
+            let schema: Json = schema.parse().unwrap();
+            let doc: Json = doc.parse().unwrap();
+            let got = schema.as_ref().schema_valid(doc.as_ref());
+            assert!(got.is_ok(), "#{} expect schema_valid ok but got {:?}", i, got);
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+
+    #[test]
+    fn test_json_schema_compiles_patterns() {
+        let schema: Json = r##"{
+            "properties": {"id": {"pattern": "^[0-9]+$"}, "ref": {"$ref": "#/definitions/code"}},
+            "patternProperties": {"^x-": {"pattern": "^[0-9]+$"}},
+            "definitions": {"code": {"pattern": "^[A-Z]{3}$"}}
+        }"##.parse().unwrap();
+        let schema = JsonSchema::new(schema.as_ref()).unwrap();
+        let mut patterns: Vec<&str> = schema.regexes.keys().map(|p| p.as_str()).collect();
+        patterns.sort();
+        assert_eq!(patterns, vec!["^[0-9]+$", "^[A-Z]{3}$", "^x-"]);
+
+        let valid = |doc: &str| schema.validate(doc.parse::<Json>().unwrap().as_ref()).unwrap().is_none();
+        assert!(valid(r#"{"id": "42", "ref": "ABC", "x-a": "7"}"#));
+        assert!(!valid(r#"{"id": "4a"}"#));
+        assert!(!valid(r#"{"ref": "AB"}"#));
+        assert!(!valid(r#"{"x-a": "b"}"#));
+    }
+
+    #[test]
+    fn test_json_schema_invalid() {
+        let test_cases = vec![
+            r#"[]"#,
+            r#"{"type": "int"}"#,
+            r#"{"type": []}"#,
+            r#"{"enum": []}"#,
+            r#"{"minimum": "1"}"#,
+            r#"{"minLength": -1}"#,
+            r#"{"pattern": "("}"#,
+            r#"{"required": [1]}"#,
+            r#"{"properties": {"a": 1}}"#,
+            r#"{"items": true}"#,
+            r#"{"additionalProperties": 1}"#,
+            r#"{"$ref": "#/definitions/missing"}"#,
+            r#"{"$ref": "http://example.com/schema"}"#,
+        ];
+        for (i, schema) in test_cases.into_iter().enumerate() {
+            let schema: Json = schema.parse().unwrap();
+            let got = JsonSchema::new(schema.as_ref());
+            assert!(got.is_err(), "#{} expect error but got {:?}", i, got);
+        }
+
+        // A cycle of references that never steps into the document.
+        let schema: Json = r#"{"$ref": "#/definitions/a", "definitions": {"a": {"$ref": "#"}}}"#.parse().unwrap();
+        let doc: Json = "1".parse().unwrap();
+        assert!(schema.as_ref().schema_valid(doc.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_json_schema_validation_report() {
+        let schema = r#"{"properties": {"a": {"type": "array", "items": {"maximum": 2}}}}"#;
+        let test_cases = vec![
+            (r#"{"a": [1, 2]}"#, r#"{"valid": true}"#),
+            (
+                r#"{"a": [1, 3]}"#,
+                r#"{
+                    "valid": false,
+                    "reason": "The JSON document location '#/a/1' failed requirement 'maximum' at JSON Schema location '#/properties/a/items'",
+                    "schema-location": "#/properties/a/items",
+                    "document-location": "#/a/1",
+                    "schema-failed-keyword": "maximum"
+                }"#,
+            ),
+        ];
+        let schema: Json = schema.parse().unwrap();
+        for (i, (doc, expected)) in test_cases.into_iter().enumerate() {
+            let doc: Json = doc.parse().unwrap();
+            let got = schema.as_ref().schema_validation_report(doc.as_ref());
+            assert!(got.is_ok(), "#{} expect report ok but got {:?}", i, got);
+            let expected: Json = expected.parse().unwrap();
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+}
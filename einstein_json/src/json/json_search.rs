@@ -0,0 +1,323 @@
+ //Copyright 2021-2023 WHTCORPS INC
+ //
+ // Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+ // this file File except in compliance with the License. You may obtain a copy of the
+ // License at http://www.apache.org/licenses/LICENSE-2.0
+ // Unless required by applicable law or agreed to in writing, software distributed
+ // under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+ // CONDITIONS OF ANY KIND, either express or implied. See the License for the
+ // specific language governing permissions and limitations under the License.
+
+use std::str;
+
+use super::{Json, JsonRef, JsonType};
+use super::json_contains_path::OneOrAll;
+use super::path_expr::{
+    ArrayIndex, LocalPathExpression, LocalPathLeg, LOCAL_PATH_EXPR_ARRAY_INDEX_ASTERISK,
+    LOCAL_PATH_EXPR_ASTERISK,
+};
+use super::super::Result;
+
+/// The escape character of `JSON_SEARCH` when none is given.
+pub const DEFAULT_SEARCH_ESCAPE: char = '\\';
+
+impl<'a> JsonRef<'a> {
+    /// `search` returns the local_paths of the strings in this JSON document that match the
+    /// LIKE pattern `search_str`, where `%` matches any number of characters and `_` matches
+    /// exactly one; `escape` (`\` by default) makes either of them literal.
+    ///
+    /// With `One` the first match is returned; with `All` every match is, autowrapped as an
+    /// array when there is more than one. Only strings under one of `local_path_expr_list`
+    /// are searched if it isn't empty. `None` means nothing matched.
+    ///
+    /// See `Search()` in MEDB `json/binary_function.go`
+    pub fn search(
+        &self,
+        one_or_all: OneOrAll,
+        search_str: &str,
+        escape: Option<char>,
+        local_path_expr_list: &[LocalPathExpression],
+    ) -> Result<Option<Json>> {
+        let pattern: Vec<char> = search_str.chars().collect();
+        let escape = escape.unwrap_or(DEFAULT_SEARCH_ESCAPE);
+        let mut found = vec![];
+        let mut local_path = vec![];
+        search_json(
+            *self,
+            &mut local_path,
+            &pattern,
+            escape,
+            local_path_expr_list,
+            one_or_all,
+            &mut found,
+        )?;
+        match found.len() {
+            0 => Ok(None),
+            1 => Ok(Some(Json::from_string(found.remove(0))?)),
+            _ => Ok(Some(Json::from_array(
+                found
+                    .into_iter()
+                    .map(Json::from_string)
+                    .collect::<Result<Vec<_>>>()?,
+            )?)),
+        }
+    }
+}
+
+/// A leg of the concrete local_path to a causet_locale, with the element count of the array an
+/// index leg steps into so that `last` and ranges can be matched against it.
+type ConcreteLeg = (LocalPathLeg, usize);
+
+fn search_json(
+    j: JsonRef<'_>,
+    local_path: &mut Vec<ConcreteLeg>,
+    pattern: &[char],
+    escape: char,
+    local_path_expr_list: &[LocalPathExpression],
+    one_or_all: OneOrAll,
+    found: &mut Vec<String>,
+) -> Result<()> {
+    if one_or_all == OneOrAll::One && !found.is_empty() {
+        return Ok(());
+    }
+    match j.get_type() {
+        JsonType::String => {
+            let in_scope = local_path_expr_list.is_empty()
+                || local_path_expr_list
+                    .iter()
+                    .any(|expr| leg_prefix_matches(&expr.legs, local_path));
+            let text: Vec<char> = j.get_str()?.chars().collect();
+            if in_scope && like_match(pattern, &text, escape) {
+                let concrete: Vec<LocalPathLeg> =
+                    local_path.iter().map(|(leg, _)| leg.clone()).collect();
+                found.push(format_local_path(&concrete));
+            }
+        }
+        JsonType::Array => {
+            let elem_count = j.get_elem_count();
+            for i in 0..elem_count {
+                local_path.push((LocalPathLeg::Index(i as i32), elem_count));
+                search_json(
+                    j.array_get_elem(i)?,
+                    local_path,
+                    pattern,
+                    escape,
+                    local_path_expr_list,
+                    one_or_all,
+                    found,
+                )?;
+                local_path.pop();
+            }
+        }
+        JsonType::Object => {
+            for i in 0..j.get_elem_count() {
+                let soliton_id = str::from_utf8(j.object_get_soliton_id(i))?;
+                local_path.push((LocalPathLeg::Key(soliton_id.to_owned()), 0));
+                search_json(
+                    j.object_get_val(i)?,
+                    local_path,
+                    pattern,
+                    escape,
+                    local_path_expr_list,
+                    one_or_all,
+                    found,
+                )?;
+                local_path.pop();
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Whether the concrete `local_path` lies under some causet_locale matched by `legs`.
+fn leg_prefix_matches(legs: &[LocalPathLeg], local_path: &[ConcreteLeg]) -> bool {
+    if legs.is_empty() {
+        return true;
+    }
+    match legs[0] {
+        LocalPathLeg::DoubleAsterisk => (0..=local_path.len())
+            .any(|skip| leg_prefix_matches(&legs[1..], &local_path[skip..])),
+        ref leg => match local_path.first() {
+            Some((concrete, elem_count)) => {
+                let matched = match (leg, concrete) {
+                    (LocalPathLeg::Index(i), LocalPathLeg::Index(c)) => {
+                        *i == LOCAL_PATH_EXPR_ARRAY_INDEX_ASTERISK || i == c
+                    }
+                    (LocalPathLeg::LastIndex(n), LocalPathLeg::Index(c)) => {
+                        ArrayIndex::Right(*n).resolve(*elem_count) == Some(*c as usize)
+                    }
+                    (LocalPathLeg::Range(from, to), LocalPathLeg::Index(c)) => {
+                        ArrayIndex::range(*from, *to, *elem_count).contains(&(*c as usize))
+                    }
+                    (LocalPathLeg::Key(k), LocalPathLeg::Key(c)) => {
+                        k == LOCAL_PATH_EXPR_ASTERISK || k == c
+                    }
+                    _ => false,
+                };
+                matched && leg_prefix_matches(&legs[1..], &local_path[1..])
+            }
+            None => false,
+        },
+    }
+}
+
+/// Renders a concrete local_path the way `JSON_SEARCH` reports it, e.g. `$.a[0]."b c"`.
+pub fn format_local_path(local_path: &[LocalPathLeg]) -> String {
+    let mut s = String::from("$");
+    for leg in local_path {
+        match leg {
+            LocalPathLeg::Index(i) => s.push_str(&format!("[{}]", i)),
+            LocalPathLeg::Key(k) => {
+                s.push('.');
+                if is_ecmascript_identifier(k) {
+                    s.push_str(k);
+                } else {
+                    s.push_str(&serde_json::to_string(k).unwrap());
+                }
+            }
+            LocalPathLeg::LastIndex(n) => s.push_str(&format!("[{}]", format_array_index(ArrayIndex::Right(*n)))),
+            LocalPathLeg::Range(from, to) => {
+                s.push_str(&format!("[{} to {}]", format_array_index(*from), format_array_index(*to)))
+            }
+            LocalPathLeg::DoubleAsterisk => s.push_str("**"),
+        }
+    }
+    s
+}
+
+fn format_array_index(idx: ArrayIndex) -> String {
+    match idx {
+        ArrayIndex::Left(n) => n.to_string(),
+        ArrayIndex::Right(0) => String::from("last"),
+        ArrayIndex::Right(n) => format!("last-{}", n),
+    }
+}
+
+fn is_ecmascript_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// LIKE matching of `text` against `pattern`, in characters rather than bytes.
+fn like_match(pattern: &[char], text: &[char], escape: char) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the most recent `%`: the pattern position after it and the text
+    // position it is currently assumed to extend to.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '%' => {
+                    p += 1;
+                    backtrack = Some((p, t));
+                    continue;
+                }
+                '_' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                c => {
+                    let (literal, width) = if c == escape && p + 1 < pattern.len() {
+                        (pattern[p + 1], 2)
+                    } else {
+                        (c, 1)
+                    };
+                    if literal == text[t] {
+                        p += width;
+                        t += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+        match backtrack {
+            Some((bp, bt)) => {
+                p = bp;
+                t = bt + 1;
+                backtrack = Some((bp, bt + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::path_expr::parse_json_local_path_expr;
+
+    #[test]
+    fn test_like_match() {
+        let test_cases = vec![
+            ("abc", "abc", true),
+            ("a%", "abc", true),
+            ("%c", "abc", true),
+            ("%b%", "abc", true),
+            ("a_c", "abc", true),
+            ("a_", "abc", false),
+            ("%", "", true),
+            ("_", "", false),
+            ("a\\%c", "a%c", true),
+            ("a\\%c", "abc", false),
+            ("a\\_c", "abc", false),
+            ("%a%a%", "banana", true),
+            ("%x%", "banana", false),
+            ("ü_", "üb", true),
+        ];
+        for (i, (pattern, text, expected)) in test_cases.into_iter().enumerate() {
+            let pattern: Vec<char> = pattern.chars().collect();
+            let text: Vec<char> = text.chars().collect();
+            assert_eq!(
+                like_match(&pattern, &text, DEFAULT_SEARCH_ESCAPE),
+                expected,
+                "#{} expect {:?}",
+                i,
+                expected
+            );
+        }
+        let pattern: Vec<char> = "a|%c".chars().collect();
+        let text: Vec<char> = "a%c".chars().collect();
+        assert!(like_match(&pattern, &text, '|'));
+    }
+
+    #[test]
+    fn test_json_search() {
+        let j = r#"["abc", [{"k": "10"}, "def"], {"x": "abc"}, {"y": "bcd"}, {"a b": "abc"}]"#;
+        let test_cases = vec![
+            ("one", "abc", vec![], Some(r#""$[0]""#)),
+            ("all", "abc", vec![], Some(r#"["$[0]", "$[2].x", "$[4].\"a b\""]"#)),
+            ("all", "ghi", vec![], None),
+            ("all", "10", vec![], Some(r#""$[1][0].k""#)),
+            ("all", "%b%", vec!["$[3]"], Some(r#""$[3].y""#)),
+            ("all", "%b%", vec!["$[*].x"], Some(r#""$[2].x""#)),
+            ("all", "%b%", vec!["$**.y"], Some(r#""$[3].y""#)),
+            ("all", "%b%", vec!["$[1]"], None),
+            ("all", "d_f", vec!["$[1]"], Some(r#""$[1][1]""#)),
+            ("all", "abc", vec!["$[last]"], Some(r#""$[4].\"a b\"""#)),
+            ("all", "abc", vec!["$[1 to last-1]"], Some(r#""$[2].x""#)),
+            ("all", "abc", vec!["$[last-9 to 0]"], Some(r#""$[0]""#)),
+        ];
+        let j: Json = j.parse().unwrap();
+        for (i, (one_or_all, search_str, local_paths, expected)) in
+            test_cases.into_iter().enumerate()
+        {
+            let exprs: Vec<_> = local_paths
+                .iter()
+                .map(|p| parse_json_local_path_expr(p).unwrap())
+                .collect();
+            let got = j
+                .as_ref()
+                .search(one_or_all.parse().unwrap(), search_str, None, &exprs);
+            assert!(got.is_ok(), "#{} expect search ok but got {:?}", i, got);
+            let expected = expected.map(|e| e.parse::<Json>().unwrap());
+            assert_eq!(got.unwrap(), expected, "#{} expect {:?}", i, expected);
+        }
+    }
+}
@@ -36,6 +36,30 @@
 //!   // the number of members in object or number of elements in array
 //!   element-count ::= uint32
 
+pub mod binary;
+pub mod constants;
+pub mod jcodec;
+pub mod json_array_append;
+pub mod json_array_insert;
+pub mod json_contains;
+pub mod json_contains_path;
+pub mod json_depth;
+pub mod json_extract;
+pub mod json_keys;
+pub mod json_length;
+pub mod json_merge;
+pub mod json_merge_patch;
+pub mod json_modify;
+pub mod json_overlaps;
+pub mod json_pretty;
+pub mod json_remove;
+pub mod json_schema;
+pub mod json_search;
+pub mod json_type;
+pub mod json_unquote;
+pub mod modifier;
+pub mod path_expr;
+pub mod serde;
 
  use std::error::Error;
     use std::fmt;
@@ -310,6 +334,25 @@ impl Json {
             causet_locale: self.causet_locale.as_slice(),
         }
     }
+
+    /// Encodes the JSON for storage as a single blob: the type code followed by the
+    /// binary causet_locale, the same bytes `JsonEncoder::write_json` writes.
+    pub fn to_storage_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.causet_locale.len());
+        bytes.push(self.type_code as u8);
+        bytes.extend_from_slice(&self.causet_locale);
+        bytes
+    }
+
+    /// Decodes a blob written by `to_storage_bytes`
+    pub fn from_storage_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.split_first() {
+            Some((&type_code, causet_locale)) => {
+                Ok(Self::new(JsonType::try_from(type_code)?, causet_locale.to_vec()))
+            }
+            None => Err(box_err!("Invalid JSON storage encoding: missing type code")),
+        }
+    }
 }
 
 /// Create JSON arrayy by given elements
@@ -32,6 +32,7 @@ use std::sync::atomic::{AtomicBool, Partitioning};
 use std::time::Duration;
 
 use crate :: error::{Error, Result};
+use crate::path_expr::ArrayIndex;
 use crate :: util::{self, foundationdb_to_engine_error};
 use crate::{EngineIterator};
 use crate::Engine;
@@ -377,6 +378,8 @@ impl<'a> BinaryModifier<'a> {
                 }
                 self.new_causet_locale = Some(Json::from_einsteindb_fdb_kv_pairs(entries)?);
             }
+            // A `last`-relative index that doesn't exist lies before the first element, so
+            // there is nowhere to insert.
             _ => {}
         }
         Ok(())
@@ -404,12 +407,19 @@ impl<'a> BinaryModifier<'a> {
         }
         let parent_node = &result[0];
         match &*last_leg {
-            local_pathLeg::Index(remove_idx) => {
+            local_pathLeg::Index(_) | local_pathLeg::LastIndex(_) => {
                 if parent_node.get_type() == JsonType::Array {
                     self.to_be_modified_ptr = parent_node.as_ptr();
                     let elems_count = parent_node.get_elem_count();
                     let mut elems = Vec::with_capacity(elems_count - 1);
-                    let remove_idx = *remove_idx as usize;
+                    let remove_idx = match *last_leg {
+                        local_pathLeg::LastIndex(n) => match ArrayIndex::Right(n).resolve(elems_count) {
+                            Some(i) => i,
+                            None => return Ok(()),
+                        },
+                        local_pathLeg::Index(i) => i as usize,
+                        _ => unreachable!(),
+                    };
                     for i in 0..elems_count {
                         if i != remove_idx {
                             elems.push(parent_node.array_get_elem(i)?);
@@ -76,12 +76,14 @@
 //     columnReference ::= // omit...
 //     LocalPathLeg ::= member | arrayLocation | '**'
 //     member ::= '.' (soliton_idName | '*')
-//     arrayLocation ::= '[' (non-negative-integer | '*') ']'
+//     arrayLocation ::= '[' (arrayIndex | arrayIndex 'to' arrayIndex | '*') ']'
+//     arrayIndex ::= non-negative-integer | 'last' [ '-' non-negative-integer ]
 //     soliton_idName ::= ECMAScript-causetidifier | ECMAScript-string-literal
 //
 // And some implementation limits in MyBerolinaSQL 5.7:
 //     1) columnReference in scope must be empty now;
 //     2) double asterisk(**) could not be last leg;
+//     3) a range must not start after it ends, when both ends are counted from the start;
 //
 // Examples:
 //     select json_extract('{"a": "b", "c": [1, "2"]}', '$.a') -> "b"
@@ -91,6 +93,8 @@
 //     select json_extract('{"a": "b", "c": [1, "2"]}', '$.c[2]') -> NULL
 //     select json_extract('{"a": "b", "c": [1, "2"]}', '$.c[*]') -> [1, "2"]
 //     select json_extract('{"a": "b", "c": [1, "2"]}', '$.*') -> ["b", [1, "2"]]
+//     select json_extract('[1, 2, 3, 4]', '$[1 to 2]') -> [2, 3]
+//     select json_extract('[1, 2, 3, 4]', '$[last-1]') -> 3
 
 
 use std::rc::Rc;
@@ -100,7 +104,7 @@ use std::fmt::{self, Debug};
 use std::cmp::Partitioning;
 use std::hash::{Hash, Hasher};
 use std::mem;
-use std::ops::Deref;
+use std::ops::{self, Deref};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Partitioning as AtomicPartitioning};
 use std::sync::Mutex;
@@ -236,12 +240,10 @@ pub const LOCAL_PATH_EXPR_DOT: &str = ".";
 // "[^"\\]*(\\.[^"\\]*)*" matches any string literal which can carry escaped quotes.
 
 
+// [0-9]+|last(\s*-\s*[0-9]+)? matches an array index, which may be a range of two.
 const LOCAL_PATH_EXPR_LEG_RE_STR: &str =
-    r#"(\.\s*([a-zA-Z_][a-zA-Z0-9_]*|\*|"[^"\\]*(\\.[^"\\]*)*")|(\[\s*([0-9]+|\*)\s*\])|\*\*)"#;
+    r#"(\.\s*([a-zA-Z_][a-zA-Z0-9_]*|\*|"[^"\\]*(\\.[^"\\]*)*")|(\[\s*(\*|([0-9]+|last(\s*-\s*[0-9]+)?)(\s+to\s+([0-9]+|last(\s*-\s*[0-9]+)?))?)\s*\])|\*\*)"#;
 const LOCAL_PATH_EXPR_LEG_RE: &str = LOCAL_PATH_EXPR_LEG_RE_STR;
-const LOCAL_PATH_EXPR_LEG_RE_CAPTURE_GROUP: &str = r#"(?P<leg>\.\s*([a-zA-Z_][a-zA-Z0-9_]*|\*|"[^"\\]*(\\.[^"\\]*)*")|(\[\s*([0-9]+|\*)\s*\])|\*\*)"#;
- //k8s specific
-const LOCAL_PATH_EXPR_LEG_RE_CAPTURE_GROUP_K8S: &str = r#"(?P<leg>\.\s*([a-zA-Z_][a-zA-Z0-9_]*|\*|"[^"\\]*(\\.[^"\\]*)*")|(\[\s*([0-9]+|\*)\s*\])|\*\*)"#;
 
 
 
@@ -258,12 +260,53 @@ pub struct GremlinRequest {
  }
 
 
-#[derive(clone,debug,partial_eq)]
+/// A position in a JSON array: counted from the start, or back from the `last` element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ArrayIndex {
+    /// `n`
+    Left(u32),
+    /// `last-n`
+    Right(u32),
+}
+
+impl ArrayIndex {
+    /// The position this index denotes in an array of `elem_count` elements, which may lie
+    /// past either end: `None` is before the first element, and anything `>= elem_count`
+    /// is past the last one.
+    pub fn resolve(&self, elem_count: usize) -> Option<usize> {
+        match *self {
+            ArrayIndex::Left(n) => Some(n as usize),
+            ArrayIndex::Right(n) => (elem_count as u64)
+                .checked_sub(1 + n as u64)
+                .map(|i| i as usize),
+        }
+    }
+
+    /// The positions `[from to to]` covers in an array of `elem_count` elements. Ends past
+    /// the array are clamped to it, so the range may be empty.
+    pub fn range(from: ArrayIndex, to: ArrayIndex, elem_count: usize) -> ops::Range<usize> {
+        let start = from.resolve(elem_count).unwrap_or(0);
+        let end = to.resolve(elem_count).map_or(0, |i| (i + 1).min(elem_count));
+        if start < end {
+            start..end
+        } else {
+            0..0
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum LocalPathLeg {
-    Asterisk,
-    Dot,
-    Identifier(String),
-    Index(u64),
+    /// `.soliton_id`, or `.*` for every member of an object.
+    Key(String),
+    /// `[n]`, or `[*]` (as `LOCAL_PATH_EXPR_ARRAY_INDEX_ASTERISK`) for every element.
+    Index(i32),
+    /// `[last]` or `[last-n]`.
+    LastIndex(u32),
+    /// `[m to n]`, both ends inclusive; either end may be counted from `last`.
+    Range(ArrayIndex, ArrayIndex),
+    /// `**`
+    DoubleAsterisk,
 }
 
 
@@ -356,6 +399,7 @@ pub type LocalPathExpressionFlag = u8;
 
 pub const LOCAL_PATH_EXPRESSION_CONTAINS_ASTERISK: LocalPathExpressionFlag = 0x01;
 pub const LOCAL_PATH_EXPRESSION_CONTAINS_DOUBLE_ASTERISK: LocalPathExpressionFlag = 0x02;
+pub const LOCAL_PATH_EXPRESSION_CONTAINS_RANGE: LocalPathExpressionFlag = 0x04;
 
 
 
@@ -365,6 +409,32 @@ impl LocalPathExpression {
             & (LOCAL_PATH_EXPRESSION_CONTAINS_ASTERISK | LOCAL_PATH_EXPRESSION_CONTAINS_DOUBLE_ASTERISK))
             != 0
     }
+
+    pub fn contains_any_range(&self) -> bool {
+        (self.flags & LOCAL_PATH_EXPRESSION_CONTAINS_RANGE) != 0
+    }
+
+    /// Whether this local_path may match more than one causet_locale, which the functions that
+    /// modify a JSON document reject.
+    pub fn could_match_multiple(&self) -> bool {
+        self.contains_any_asterisk() || self.contains_any_range()
+    }
+}
+
+fn parse_array_index(s: &str) -> Result<ArrayIndex> {
+    let s = s.trim();
+    match s.strip_prefix("last") {
+        Some(rest) => {
+            let rest = rest.trim_start();
+            if rest.is_empty() {
+                return Ok(ArrayIndex::Right(0));
+            }
+            // The regex guarantees a '-' here.
+            let n = rest[1..].trim();
+            Ok(ArrayIndex::Right(box_try!(n.parse::<u32>())))
+        }
+        None => Ok(ArrayIndex::Left(box_try!(s.parse::<u32>()))),
+    }
 }
 
 /// Parses a JSON local_path expression. Returns a `LocalPathExpression`
@@ -387,7 +457,8 @@ pub fn parse_json_local_path_expr(local_path_expr: &str) -> Result<LocalPathExpr
     let expr = local_path_expr.index(dollar_index + 1..).trim_start();
 
     lazy_static::lazy_static! {
-        static ref RE: Regex = Regex::new(local_path_EXPR_LEG_RE_STR).unwrap();
+        static ref RE: Regex = Regex::new(LOCAL_PATH_EXPR_LEG_RE_STR).unwrap();
+        static ref RANGE_TO_RE: Regex = Regex::new(r"\s+to\s+").unwrap();
     }
     let mut legs = vec![];
     let mut flags = LocalPathExpressionFlag::default();
@@ -409,13 +480,32 @@ pub fn parse_json_local_path_expr(local_path_expr: &str) -> Result<LocalPathExpr
             // The leg is an Index of a JSON array.
             let leg = expr[start + 1..end].trim();
             let index_str = leg[0..leg.len() - 1].trim();
-            let index = if index_str == LOCAL_PATH_EXPR_ASTERISK {
+            if index_str == LOCAL_PATH_EXPR_ASTERISK {
                 flags |= LOCAL_PATH_EXPRESSION_CONTAINS_ASTERISK;
-                LOCAL_PATH_EXPR_ARRAY_INDEX_ASTERISK
-            } else {
-                box_try!(index_str.parse::<i32>())
-            };
-            legs.push(LocalPathLeg::Index(index))
+                legs.push(LocalPathLeg::Index(LOCAL_PATH_EXPR_ARRAY_INDEX_ASTERISK));
+                continue;
+            }
+            let mut ends = RANGE_TO_RE.splitn(index_str, 2);
+            let from = parse_array_index(ends.next().unwrap())?;
+            match ends.next() {
+                Some(to) => {
+                    let to = parse_array_index(to)?;
+                    if let (ArrayIndex::Left(m), ArrayIndex::Left(n)) = (from, to) {
+                        if m > n {
+                            return Err(box_err!("Invalid JSON local_path: {}", local_path_expr));
+                        }
+                    }
+                    flags |= LOCAL_PATH_EXPRESSION_CONTAINS_RANGE;
+                    legs.push(LocalPathLeg::Range(from, to));
+                }
+                None => match from {
+                    ArrayIndex::Left(n) if n <= i32::MAX as u32 => legs.push(LocalPathLeg::Index(n as i32)),
+                    ArrayIndex::Left(_) => {
+                        return Err(box_err!("Invalid JSON local_path: {}", local_path_expr))
+                    }
+                    ArrayIndex::Right(n) => legs.push(LocalPathLeg::LastIndex(n)),
+                },
+            }
         } else if next_char == '.' {
             // The leg is a soliton_id of a JSON object.
             let mut soliton_id = expr[start + 1..end].trim().to_owned();
@@ -461,6 +551,28 @@ mod tests {
         e.flags = LocalPathExpressionFlag::default();
         e.flags |= LOCAL_PATH_EXPRESSION_CONTAINS_DOUBLE_ASTERISK;
         assert!(e.contains_any_asterisk());
+        assert!(!e.contains_any_range());
+        e.flags = LOCAL_PATH_EXPRESSION_CONTAINS_RANGE;
+        assert!(!e.contains_any_asterisk());
+        assert!(e.contains_any_range());
+        assert!(e.could_match_multiple());
+    }
+
+    #[test]
+    fn test_array_index_resolve() {
+        assert_eq!(ArrayIndex::Left(2).resolve(3), Some(2));
+        assert_eq!(ArrayIndex::Left(5).resolve(3), Some(5));
+        assert_eq!(ArrayIndex::Right(0).resolve(3), Some(2));
+        assert_eq!(ArrayIndex::Right(2).resolve(3), Some(0));
+        assert_eq!(ArrayIndex::Right(3).resolve(3), None);
+        assert_eq!(ArrayIndex::Right(0).resolve(0), None);
+
+        assert_eq!(ArrayIndex::range(ArrayIndex::Left(1), ArrayIndex::Left(2), 4), 1..3);
+        assert_eq!(ArrayIndex::range(ArrayIndex::Left(1), ArrayIndex::Left(9), 4), 1..4);
+        assert_eq!(ArrayIndex::range(ArrayIndex::Right(9), ArrayIndex::Right(0), 4), 0..4);
+        assert_eq!(ArrayIndex::range(ArrayIndex::Left(5), ArrayIndex::Left(9), 4), 0..0);
+        assert_eq!(ArrayIndex::range(ArrayIndex::Right(0), ArrayIndex::Left(1), 4), 0..0);
+        assert_eq!(ArrayIndex::range(ArrayIndex::Left(0), ArrayIndex::Right(0), 0), 0..0);
     }
 
     #[test]
@@ -500,8 +612,53 @@ mod tests {
                 true,
                 Some(LocalPathExpression { legs: vec![LocalPathLeg::DoubleAsterisk, LocalPathLeg::Key(String::from("a"))], flags: LOCAL_PATH_EXPRESSION_CONTAINS_DOUBLE_ASTERISK, }),
             ),
+            (
+                "$[last]",
+                true,
+                Some(LocalPathExpression {
+                    legs: vec![LocalPathLeg::LastIndex(0)],
+                    flags: LocalPathExpressionFlag::default(),
+                }),
+            ),
+            (
+                "$.a[ last - 2 ]",
+                true,
+                Some(LocalPathExpression {
+                    legs: vec![LocalPathLeg::Key(String::from("a")), LocalPathLeg::LastIndex(2)],
+                    flags: LocalPathExpressionFlag::default(),
+                }),
+            ),
+            (
+                "$[1 to 3]",
+                true,
+                Some(LocalPathExpression {
+                    legs: vec![LocalPathLeg::Range(ArrayIndex::Left(1), ArrayIndex::Left(3))],
+                    flags: LOCAL_PATH_EXPRESSION_CONTAINS_RANGE,
+                }),
+            ),
+            (
+                "$[last-3 to last]",
+                true,
+                Some(LocalPathExpression {
+                    legs: vec![LocalPathLeg::Range(ArrayIndex::Right(3), ArrayIndex::Right(0))],
+                    flags: LOCAL_PATH_EXPRESSION_CONTAINS_RANGE,
+                }),
+            ),
+            (
+                "$[0 to last-1]",
+                true,
+                Some(LocalPathExpression {
+                    legs: vec![LocalPathLeg::Range(ArrayIndex::Left(0), ArrayIndex::Right(1))],
+                    flags: LOCAL_PATH_EXPRESSION_CONTAINS_RANGE,
+                }),
+            ),
             // invalid local_path expressions
             (".a", false, None),
+            ("$[3 to 1]", false, None),
+            ("$[1to3]", false, None),
+            ("$[last+1]", false, None),
+            ("$[-1]", false, None),
+            ("$[* to 2]", false, None),
             ("xx$[1]", false, None),
             ("$.a xx .b", false, None),
             ("$[a]", false, None),
@@ -0,0 +1,44 @@
+//Copyright 2021-2023 WHTCORPS INC
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use
+// this file File except in compliance with the License. You may obtain a copy of the
+// License at http://www.apache.org/licenses/LICENSE-2.0
+// Unless required by applicable law or agreed to in writing, software distributed
+// under the License is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR
+// CONDITIONS OF ANY KIND, either express or implied. See the License for the
+// specific language governing permissions and limitations under the License.
+
+//! The MyBerolinaSQL binary JSON codec, with JSON path expressions, the JSON functions and JSON
+//! Schema validation built on it.
+//!
+//! BerolinaSQL evaluates JSON columns with it and EinsteinDB stores `:einsteindb.type/json`
+//! causet_locales in its encoding. It depends on neither, so both can depend on it.
+
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate quick_error;
+
+/// Box a formatted message into an `Error::Other`.
+macro_rules! box_err {
+    ($e:expr) => ({
+        let e: Box<dyn std::error::Error + Send + Sync> = ($e).into();
+        e.into()
+    });
+    ($f:tt, $($arg:expr),+) => ({
+        box_err!(format!($f, $($arg),+))
+    });
+}
+
+pub mod error;
+pub mod json;
+
+pub use crate::error::{
+    Error,
+    Result,
+};
+pub use crate::json::{
+    Json,
+    JsonRef,
+    JsonType,
+};
@@ -0,0 +1,412 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Incremental backups of an einstein_merkle_tree.
+//!
+//! A backup directory holds any number of backups of one einstein_merkle_tree.  Table files never
+//! change once written, so they are shared between backups: a backup only copies the table files
+//! no earlier backup holds.  A shared table file is named by a digest of its contents and its size
+//! as well as its number, so a table that reuses the number of one backed up before, e.g. after
+//! the einstein_merkle_tree was restored, is not taken for it.  The digest is a sha256 rather than
+//! the crc32: every block of a table ends in its own crc32, which leaves the crc32 of the whole
+//! file the same for tables that differ only inside their blocks.  What each backup holds is recorded,
+//! with the size and crc32 of every file, in a meta file written once the backup is complete.
+//!
+//! ```text
+//! <dir>/shared/000043_<sha256>_<size>.sst  table files
+//! <dir>/private/<id>/MANIFEST              the manifest, write-ahead logs and key dictionary of a backup
+//! <dir>/meta/<id>                          what backup <id> holds
+//! ```
+//!
+//! Like checkpoints, backups hold files as they are on disk.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fdb_traits::{Error, Result};
+use sha2::{Digest, Sha256};
+
+use crate::checkpoint::sync_dir;
+use crate::coding::{checksum, corruption, put_bytes, put_u32, put_u64, put_varint, Decoder};
+use crate::einstein_merkle_tree::LsmEinsteinMerkleTree;
+
+const SHARED: &str = "shared";
+const PRIVATE: &str = "private";
+const META: &str = "meta";
+
+/// A file of a backup, by its path relative to the backup directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct BackupFile {
+    path: String,
+    size: u64,
+    crc: u32,
+}
+
+/// Describes a complete backup.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackupInfo {
+    pub id: u64,
+    /// When the backup was taken, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The sequence number of the last write in the backup.
+    pub seqno: u64,
+    /// The files the backup holds.
+    pub num_files: usize,
+    /// The bytes of all the files the backup holds.
+    pub size: u64,
+    /// The files the backup copied; the others were shared with earlier backups.
+    pub new_files: usize,
+}
+
+struct BackupMeta {
+    info: BackupInfo,
+    files: Vec<BackupFile>,
+}
+
+impl BackupMeta {
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        put_u64(&mut payload, self.info.id);
+        put_u64(&mut payload, self.info.timestamp);
+        put_u64(&mut payload, self.info.seqno);
+        put_varint(&mut payload, self.info.new_files as u64);
+        put_varint(&mut payload, self.files.len() as u64);
+        for f in &self.files {
+            put_bytes(&mut payload, f.path.as_bytes());
+            put_u64(&mut payload, f.size);
+            put_u32(&mut payload, f.crc);
+        }
+        let mut buf = Vec::with_capacity(payload.len() + 4);
+        put_u32(&mut buf, checksum(&payload));
+        buf.extend_from_slice(&payload);
+        buf
+    }
+
+    fn decode(data: &[u8]) -> Result<BackupMeta> {
+        let mut d = Decoder::new(data);
+        let crc = d.u32()?;
+        if checksum(&data[4..]) != crc {
+            return Err(corruption("backup meta fails its checksum"));
+        }
+        let (id, timestamp, seqno) = (d.u64()?, d.u64()?, d.u64()?);
+        let new_files = d.varint()? as usize;
+        let mut files = Vec::new();
+        for _ in 0..d.varint()? {
+            let path = String::from_utf8(d.bytes()?.to_vec()).map_err(|_| corruption("backup file name is not UTF-8"))?;
+            files.push(BackupFile {
+                path,
+                size: d.u64()?,
+                crc: d.u32()?,
+            });
+        }
+        Ok(BackupMeta {
+            info: BackupInfo {
+                id,
+                timestamp,
+                seqno,
+                num_files: files.len(),
+                size: files.iter().map(|f| f.size).sum(),
+                new_files,
+            },
+            files,
+        })
+    }
+}
+
+/// The size, crc32 and sha256 of `from`, copied to `to` on the way if given, and the copy made
+/// durable.
+fn read_with_digests(from: &Path, to: Option<&Path>) -> io::Result<(u64, u32, [u8; 32])> {
+    let mut src = File::open(from)?;
+    let mut dst = to.map(File::create).transpose()?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut sha = Sha256::new();
+    let mut buf = vec![0; 64 << 10];
+    let mut size = 0;
+    loop {
+        let n = src.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        sha.update(&buf[..n]);
+        if let Some(dst) = dst.as_mut() {
+            dst.write_all(&buf[..n])?;
+        }
+        size += n as u64;
+    }
+    if let Some(dst) = dst {
+        dst.sync_all()?;
+    }
+    Ok((size, hasher.finalize(), sha.finalize().into()))
+}
+
+/// Copy `from` to `to`, returning the size and crc32 of what was copied, and make the copy
+/// durable.
+fn copy_with_crc(from: &Path, to: &Path) -> io::Result<(u64, u32)> {
+    read_with_digests(from, Some(to)).map(|(size, crc, _)| (size, crc))
+}
+
+/// The name table file `path` is shared under: `000043.sst` becomes `000043_<sha256>_<size>.sst`,
+/// with the first 16 hex digits of the sha256.
+fn shared_name(path: &Path, size: u64, sha: &[u8; 32]) -> String {
+    let stem = path.file_stem().unwrap().to_string_lossy();
+    let digest: String = sha[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}_{}_{}.sst", stem, digest, size)
+}
+
+/// The name a backed-up file is restored under, which for a shared table file drops what
+/// `shared_name` added.
+fn restored_name(file_name: &str) -> String {
+    if let Some(base) = file_name.strip_suffix(".sst") {
+        let mut parts = base.rsplitn(3, '_');
+        if let (Some(_size), Some(_crc), Some(stem)) = (parts.next(), parts.next(), parts.next()) {
+            return format!("{}.sst", stem);
+        }
+    }
+    file_name.to_owned()
+}
+
+/// Creates, verifies and restores the backups in a directory.
+#[derive(Clone, Debug)]
+pub struct BackupEngine {
+    dir: PathBuf,
+}
+
+impl BackupEngine {
+    /// Open the backup directory `dir`, creating it if needed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<BackupEngine> {
+        let dir = dir.as_ref().to_owned();
+        for sub in &[SHARED, PRIVATE, META] {
+            fs::create_dir_all(dir.join(sub))?;
+        }
+        Ok(BackupEngine { dir })
+    }
+
+    fn metas(&self) -> Result<BTreeMap<u64, BackupMeta>> {
+        let mut metas = BTreeMap::new();
+        for entry in fs::read_dir(self.dir.join(META))? {
+            let path = entry?.path();
+            if let Some(id) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.parse().ok()) {
+                metas.insert(id, BackupMeta::decode(&fs::read(&path)?)?);
+            }
+        }
+        Ok(metas)
+    }
+
+    fn meta(&self, id: Option<u64>) -> Result<BackupMeta> {
+        let mut metas = self.metas()?;
+        let id = match id.or_else(|| metas.keys().next_back().copied()) {
+            Some(id) => id,
+            None => return Err(Error::Other(format!("{} holds no backups", self.dir.display()))),
+        };
+        metas
+            .remove(&id)
+            .ok_or_else(|| Error::Other(format!("{} holds no backup {}", self.dir.display(), id)))
+    }
+
+    /// Every complete backup, oldest first.
+    pub fn backups(&self) -> Result<Vec<BackupInfo>> {
+        Ok(self.metas()?.into_values().map(|m| m.info).collect())
+    }
+
+    /// Back `db` up, copying only the table files no earlier backup holds.
+    pub fn create_backup(&self, db: &LsmEinsteinMerkleTree) -> Result<BackupInfo> {
+        let metas = self.metas()?;
+        let id = metas.keys().next_back().map_or(1, |id| id + 1);
+        let shared: BTreeSet<&str> = metas
+            .values()
+            .flat_map(|m| &m.files)
+            .filter(|f| f.path.starts_with(SHARED))
+            .map(|f| f.path.as_str())
+            .collect();
+
+        let private = Path::new(PRIVATE).join(id.to_string());
+        let _ = fs::remove_dir_all(self.dir.join(&private));
+        fs::create_dir_all(self.dir.join(&private))?;
+        let mut files = Vec::new();
+        let live = db.core.live_files(|mutable| {
+            for path in mutable {
+                let rel = private.join(path.file_name().unwrap());
+                let (size, crc) = copy_with_crc(path, &self.dir.join(&rel))?;
+                files.push(BackupFile {
+                    path: rel.to_string_lossy().into_owned(),
+                    size,
+                    crc,
+                });
+            }
+            Ok(())
+        })?;
+        let mut new_files = files.len();
+        for path in &live.tables {
+            let (size, crc, sha) = read_with_digests(path, None)?;
+            let rel = Path::new(SHARED).join(shared_name(path, size, &sha));
+            let rel = rel.to_string_lossy().into_owned();
+            if !shared.contains(rel.as_str()) {
+                if copy_with_crc(path, &self.dir.join(&rel))? != (size, crc) {
+                    return Err(corruption(format!("table file {} changed while it was backed up", path.display())));
+                }
+                new_files += 1;
+            }
+            files.push(BackupFile { path: rel, size, crc });
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let meta = BackupMeta {
+            info: BackupInfo {
+                id,
+                timestamp,
+                seqno: live.seqno,
+                num_files: files.len(),
+                size: files.iter().map(|f| f.size).sum(),
+                new_files,
+            },
+            files,
+        };
+        let path = self.dir.join(META).join(id.to_string());
+        let tmp = path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(&meta.encode())?;
+        file.sync_all()?;
+        fs::rename(&tmp, &path)?;
+        Ok(meta.info)
+    }
+
+    /// Check the size and crc32 of every file of backup `id`, or of the latest backup.
+    pub fn verify_backup(&self, id: Option<u64>) -> Result<BackupInfo> {
+        let meta = self.meta(id)?;
+        for f in &meta.files {
+            let data = fs::read(self.dir.join(&f.path))?;
+            if data.len() as u64 != f.size || checksum(&data) != f.crc {
+                return Err(corruption(format!("backup file {} fails its checksum", f.path)));
+            }
+        }
+        Ok(meta.info)
+    }
+
+    /// Restore backup `id`, or the latest backup, into `db_dir`, which must be empty or not
+    /// exist yet.  Every file is checked against its size and crc32 as it is copied.
+    pub fn restore(&self, id: Option<u64>, db_dir: &Path) -> Result<BackupInfo> {
+        let meta = self.meta(id)?;
+        fs::create_dir_all(db_dir)?;
+        if fs::read_dir(db_dir)?.next().is_some() {
+            return Err(Error::Other(format!("{} is not empty", db_dir.display())));
+        }
+        let restored = meta.files.iter().try_for_each(|f| {
+            let from = self.dir.join(&f.path);
+            let to = db_dir.join(restored_name(&from.file_name().unwrap().to_string_lossy()));
+            if copy_with_crc(&from, &to)? != (f.size, f.crc) {
+                return Err(corruption(format!("backup file {} fails its checksum", f.path)));
+            }
+            Ok(())
+        });
+        if let Err(e) = restored {
+            for entry in fs::read_dir(db_dir)?.flatten() {
+                let _ = fs::remove_file(entry.path());
+            }
+            return Err(e);
+        }
+        sync_dir(db_dir)?;
+        Ok(meta.info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{Peekable, SyncMutable, ALL_NAMESPACEDS};
+
+    use super::*;
+    use crate::options::{LsmNamespacedOptions, LsmOptions};
+
+    fn open_einstein_merkle_tree(path: &Path) -> LsmEinsteinMerkleTree {
+        let namespaceds = ALL_NAMESPACEDS
+            .iter()
+            .map(|n| (n.to_string(), LsmNamespacedOptions::default()))
+            .collect();
+        LsmEinsteinMerkleTree::open(path, LsmOptions::default(), namespaceds).unwrap()
+    }
+
+    #[test]
+    fn test_incremental_backup_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        let backups = BackupEngine::open(dir.path().join("backups")).unwrap();
+        assert!(backups.restore(None, &dir.path().join("nothing")).is_err());
+
+        db.put(b"a", b"1").unwrap();
+        db.flush().unwrap();
+        db.put(b"b", b"2").unwrap();
+        let first = backups.create_backup(&db).unwrap();
+        assert_eq!(first.id, 1);
+        assert_eq!(first.new_files, first.num_files);
+
+        db.put(b"c", b"3").unwrap();
+        db.flush().unwrap();
+        db.delete(b"a").unwrap();
+        let second = backups.create_backup(&db).unwrap();
+        assert_eq!(second.id, 2);
+        // The table file holding "a" was copied by the first backup.
+        assert!(second.new_files < second.num_files);
+        assert_eq!(backups.backups().unwrap(), vec![first.clone(), second.clone()]);
+        backups.verify_backup(None).unwrap();
+
+        let restored = dir.path().join("restored-1");
+        assert_eq!(backups.restore(Some(1), &restored).unwrap(), first);
+        let db1 = open_einstein_merkle_tree(&restored);
+        assert_eq!(db1.latest_seqno(), first.seqno);
+        assert_eq!(db1.get_value(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db1.get_value(b"b").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db1.get_value(b"c").unwrap(), None);
+        assert!(backups.restore(Some(1), &restored).is_err());
+
+        let restored = dir.path().join("restored-2");
+        backups.restore(None, &restored).unwrap();
+        let db2 = open_einstein_merkle_tree(&restored);
+        assert_eq!(db2.get_value(b"a").unwrap(), None);
+        assert_eq!(db2.get_value(b"c").unwrap(), Some(b"3".to_vec()));
+
+        // A damaged file fails verification and restores.
+        let meta = backups.meta(Some(1)).unwrap();
+        let victim = backups.dir.join(&meta.files.iter().find(|f| f.path.starts_with(SHARED)).unwrap().path);
+        let mut data = fs::read(&victim).unwrap();
+        data[0] ^= 0xff;
+        fs::write(&victim, &data).unwrap();
+        assert!(backups.verify_backup(Some(1)).is_err());
+        let restored = dir.path().join("restored-3");
+        assert!(backups.restore(Some(1), &restored).is_err());
+        assert_eq!(fs::read_dir(&restored).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_backup_does_not_share_a_different_table_of_the_same_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = BackupEngine::open(dir.path().join("backups")).unwrap();
+        for (name, causet_locale) in [("db-1", b"1"), ("db-2", b"2")] {
+            let db = open_einstein_merkle_tree(&dir.path().join(name));
+            db.put(b"a", causet_locale).unwrap();
+            db.flush().unwrap();
+            backups.create_backup(&db).unwrap();
+        }
+        // Both einstein_merkle_trees wrote a table of the same number, size and crc32.
+        let metas = backups.metas().unwrap();
+        let tables = |id: u64| -> Vec<BackupFile> {
+            metas[&id].files.iter().filter(|f| f.path.starts_with(SHARED)).cloned().collect()
+        };
+        let (first, second) = (tables(1), tables(2));
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(first[0].size, second[0].size);
+        assert_eq!(first[0].crc, second[0].crc);
+        assert_ne!(first[0].path, second[0].path);
+        assert_eq!(backups.backups().unwrap()[1].new_files, backups.backups().unwrap()[1].num_files);
+
+        let restored = dir.path().join("restored");
+        backups.restore(Some(2), &restored).unwrap();
+        assert!(restored.join(restored_name(Path::new(&second[0].path).file_name().unwrap().to_str().unwrap())).exists());
+        let db = open_einstein_merkle_tree(&restored);
+        assert_eq!(db.get_value(b"a").unwrap(), Some(b"2".to_vec()));
+    }
+}
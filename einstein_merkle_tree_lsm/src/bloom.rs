@@ -0,0 +1,80 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Bloom filters over the soliton_ids of a table file.
+//!
+//! A filter is the bit array followed by one byte holding the number of probes.  Probes are
+//! derived from a single 64-bit hash by double hashing.
+
+fn hash(soliton_id: &[u8]) -> u64 {
+    // FNV-1a, then a finalizer so that both halves are well mixed.
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in soliton_id {
+        h ^= u64::from(*b);
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h
+}
+
+/// Build the filter of `soliton_ids` with about `bits_per_soliton_id` bits per soliton_id.
+pub(crate) fn build(soliton_ids: &[Vec<u8>], bits_per_soliton_id: usize) -> Vec<u8> {
+    // ln(2) * bits per soliton_id minimizes the false positive rate.
+    let probes = ((bits_per_soliton_id as f64 * 0.69) as usize).clamp(1, 30);
+    let bits = (soliton_ids.len() * bits_per_soliton_id).max(64);
+    let bytes = bits.div_ceil(8);
+    let bits = bytes * 8;
+    let mut filter = vec![0u8; bytes + 1];
+    for soliton_id in soliton_ids {
+        let h = hash(soliton_id);
+        let delta = (h >> 32) | 1;
+        let mut pos = h;
+        for _ in 0..probes {
+            let bit = (pos % bits as u64) as usize;
+            filter[bit / 8] |= 1 << (bit % 8);
+            pos = pos.wrapping_add(delta);
+        }
+    }
+    filter[bytes] = probes as u8;
+    filter
+}
+
+/// Whether `soliton_id` may be one of the soliton_ids `filter` was built from.  An empty or
+/// malformed filter matches everything.
+pub(crate) fn may_contain(filter: &[u8], soliton_id: &[u8]) -> bool {
+    if filter.len() < 2 {
+        return true;
+    }
+    let bytes = filter.len() - 1;
+    let bits = (bytes * 8) as u64;
+    let probes = filter[bytes];
+    let h = hash(soliton_id);
+    let delta = (h >> 32) | 1;
+    let mut pos = h;
+    for _ in 0..probes {
+        let bit = (pos % bits) as usize;
+        if filter[bit / 8] & (1 << (bit % 8)) == 0 {
+            return false;
+        }
+        pos = pos.wrapping_add(delta);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bloom() {
+        let soliton_ids: Vec<Vec<u8>> = (0..1000u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        let filter = build(&soliton_ids, 10);
+        assert!(soliton_ids.iter().all(|k| may_contain(&filter, k)));
+        let false_positives = (1000..11000u32)
+            .filter(|i| may_contain(&filter, &i.to_be_bytes()))
+            .count();
+        assert!(false_positives < 300, "{} false positives", false_positives);
+        assert!(may_contain(&[], b"anything"));
+    }
+}
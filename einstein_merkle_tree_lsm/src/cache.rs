@@ -0,0 +1,118 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use crate::coding::Entry;
+
+/// A decoded data block.
+pub(crate) type Block = Arc<Vec<Entry>>;
+
+/// Identifies a data block: the number of its table file and its offset in the file.
+pub(crate) type BlockId = (u64, u64);
+
+struct CacheState {
+    blocks: HashMap<BlockId, (Block, usize, u64)>,
+    /// Blocks by the tick of their last use, least recently used first.
+    lru: BTreeMap<u64, BlockId>,
+    tick: u64,
+    usage: usize,
+}
+
+/// A least-recently-used cache of decoded data blocks, shared by every table file of an
+/// einstein_merkle_tree and charged by the encoded size of the blocks.
+pub(crate) struct BlockCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl BlockCache {
+    pub(crate) fn new(capacity: usize) -> BlockCache {
+        BlockCache {
+            capacity,
+            state: Mutex::new(CacheState {
+                blocks: HashMap::new(),
+                lru: BTreeMap::new(),
+                tick: 0,
+                usage: 0,
+            }),
+        }
+    }
+
+    pub(crate) fn get(&self, id: BlockId) -> Option<Block> {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        let (block, _, last_use) = state.blocks.get_mut(&id)?;
+        let block = block.clone();
+        let previous = std::mem::replace(last_use, tick);
+        state.lru.remove(&previous);
+        state.lru.insert(tick, id);
+        Some(block)
+    }
+
+    pub(crate) fn insert(&self, id: BlockId, block: Block, charge: usize) {
+        if charge > self.capacity {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((_, old_charge, last_use)) = state.blocks.insert(id, (block, charge, tick)) {
+            state.lru.remove(&last_use);
+            state.usage -= old_charge;
+        }
+        state.lru.insert(tick, id);
+        state.usage += charge;
+        while state.usage > self.capacity {
+            let (_, victim) = state.lru.pop_first().unwrap();
+            let (_, victim_charge, _) = state.blocks.remove(&victim).unwrap();
+            state.usage -= victim_charge;
+        }
+    }
+
+    /// Drop every cached block of table file `file_number`.
+    pub(crate) fn evict_file(&self, file_number: u64) {
+        let mut state = self.state.lock().unwrap();
+        let victims: Vec<_> = state
+            .blocks
+            .iter()
+            .filter(|(id, _)| id.0 == file_number)
+            .map(|(id, (_, charge, last_use))| (*id, *charge, *last_use))
+            .collect();
+        for (id, charge, last_use) in victims {
+            state.blocks.remove(&id);
+            state.lru.remove(&last_use);
+            state.usage -= charge;
+        }
+    }
+
+    pub(crate) fn usage(&self) -> usize {
+        self.state.lock().unwrap().usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = BlockCache::new(100);
+        let block: Block = Arc::new(Vec::new());
+        cache.insert((1, 0), block.clone(), 40);
+        cache.insert((1, 40), block.clone(), 40);
+        assert!(cache.get((1, 0)).is_some());
+        // (1, 40) is now the least recently used block.
+        cache.insert((2, 0), block.clone(), 40);
+        assert!(cache.get((1, 40)).is_none());
+        assert!(cache.get((1, 0)).is_some());
+        assert_eq!(cache.usage(), 80);
+
+        cache.insert((3, 0), block, 101);
+        assert!(cache.get((3, 0)).is_none());
+        cache.evict_file(1);
+        assert!(cache.get((1, 0)).is_none());
+        assert_eq!(cache.usage(), 40);
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Point-in-time copies of an einstein_merkle_tree.
+//!
+//! Files are copied as they are on disk, so the copy of an einstein_merkle_tree opened with an
+//! `EncryptedEnv` is encrypted the same way, and carries the key dictionary needed to read it.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+
+use fdb_traits::Result;
+
+use crate::einstein_merkle_tree::LsmEinsteinMerkleTree;
+
+/// Copy `from` to `to` and make the copy durable.
+pub(crate) fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    File::open(to)?.sync_all()
+}
+
+#[cfg(unix)]
+pub(crate) fn sync_dir(path: &Path) -> io::Result<()> {
+    File::open(path)?.sync_all()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl LsmEinsteinMerkleTree {
+    /// Create a checkpoint of the einstein_merkle_tree in `dir`, which must not exist yet.
+    ///
+    /// The checkpoint is an einstein_merkle_tree of its own holding every write up to now.  Its
+    /// table files are hard links to the live ones where the file system allows, so it costs
+    /// little space until the live einstein_merkle_tree compacts them away.  The manifest and the
+    /// tail of the write-ahead log are copied while writes are held off for a moment.
+    pub fn create_checkpoint<P: AsRef<Path>>(&self, dir: P) -> Result<()> {
+        let dir = dir.as_ref();
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::create_dir(dir)?;
+        let made = self.fill_checkpoint(dir);
+        if made.is_err() {
+            let _ = fs::remove_dir_all(dir);
+        }
+        made
+    }
+
+    fn fill_checkpoint(&self, dir: &Path) -> Result<()> {
+        let live = self.core.live_files(|mutable| {
+            for path in mutable {
+                copy_file(path, &dir.join(path.file_name().unwrap()))?;
+            }
+            Ok(())
+        })?;
+        for path in &live.tables {
+            let to = dir.join(path.file_name().unwrap());
+            if fs::hard_link(path, &to).is_err() {
+                copy_file(path, &to)?;
+            }
+        }
+        sync_dir(dir)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{Peekable, SyncMutable, ALL_NAMESPACEDS};
+
+    use super::*;
+    use crate::options::{LsmNamespacedOptions, LsmOptions};
+
+    fn open_einstein_merkle_tree(path: &Path) -> LsmEinsteinMerkleTree {
+        let namespaceds = ALL_NAMESPACEDS
+            .iter()
+            .map(|n| (n.to_string(), LsmNamespacedOptions::default()))
+            .collect();
+        LsmEinsteinMerkleTree::open(path, LsmOptions::default(), namespaceds).unwrap()
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        db.put(b"flushed", b"1").unwrap();
+        db.flush().unwrap();
+        db.put(b"logged", b"2").unwrap();
+
+        let checkpoint = dir.path().join("checkpoint");
+        db.create_checkpoint(&checkpoint).unwrap();
+        assert!(db.create_checkpoint(&checkpoint).is_err());
+        db.put(b"later", b"3").unwrap();
+        db.delete(b"flushed").unwrap();
+        db.flush().unwrap();
+
+        let copy = open_einstein_merkle_tree(&checkpoint);
+        assert_eq!(copy.latest_seqno(), 2);
+        assert_eq!(copy.get_value(b"flushed").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(copy.get_value(b"logged").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(copy.get_value(b"later").unwrap(), None);
+        copy.put(b"copy", b"4").unwrap();
+        assert_eq!(db.get_value(b"copy").unwrap(), None);
+        assert_eq!(db.get_value(b"later").unwrap(), Some(b"3".to_vec()));
+    }
+}
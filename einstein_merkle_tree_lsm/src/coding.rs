@@ -0,0 +1,226 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Encodings shared by the write-ahead log, the table files and the manifest.
+
+use std::cmp::Ordering;
+
+use fdb_traits::{Error, Result};
+
+pub(crate) fn put_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+pub(crate) fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub(crate) fn put_u64(buf: &mut Vec<u8>, v: u64) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Append `bytes` prefixed with their length.
+pub(crate) fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+pub(crate) fn checksum(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+pub(crate) fn corruption(what: impl Into<String>) -> Error {
+    Error::Corruption(what.into())
+}
+
+/// Reads back what the `put_*` functions wrote.
+pub(crate) struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Decoder<'a> {
+        Decoder { buf }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(corruption("unexpected end of buffer"));
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let mut b = [0; 4];
+        b.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(b))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        let mut b = [0; 8];
+        b.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(b))
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            v |= u64::from(b & 0x7f) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(corruption("varint overflows u64"))
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+}
+
+/// What a version of a soliton_id records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ValueKind {
+    Delete = 0,
+    Put = 1,
+    /// A merge operand, applied to the versions below it when read.
+    Merge = 2,
+}
+
+impl ValueKind {
+    pub(crate) fn from_u8(b: u8) -> Result<ValueKind> {
+        match b {
+            0 => Ok(ValueKind::Delete),
+            1 => Ok(ValueKind::Put),
+            2 => Ok(ValueKind::Merge),
+            _ => Err(corruption(format!("unknown value kind {}", b))),
+        }
+    }
+}
+
+/// One version of a soliton_id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub(crate) soliton_id: Vec<u8>,
+    pub(crate) seqno: u64,
+    pub(crate) kind: ValueKind,
+    pub(crate) causet_locale: Vec<u8>,
+}
+
+impl Entry {
+    pub(crate) fn internal_key(&self) -> (&[u8], u64) {
+        (&self.soliton_id, self.seqno)
+    }
+
+    pub(crate) fn encode(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, &self.soliton_id);
+        put_u64(buf, self.seqno);
+        buf.push(self.kind as u8);
+        put_bytes(buf, &self.causet_locale);
+    }
+
+    pub(crate) fn decode(d: &mut Decoder<'_>) -> Result<Entry> {
+        Ok(Entry {
+            soliton_id: d.bytes()?.to_vec(),
+            seqno: d.u64()?,
+            kind: ValueKind::from_u8(d.u8()?)?,
+            causet_locale: d.bytes()?.to_vec(),
+        })
+    }
+}
+
+/// Order versions by soliton_id, and the versions of a soliton_id newest first.
+///
+/// Sequence numbers start at 1, so `(soliton_id, u64::MAX)` sorts before every version of
+/// `soliton_id` and `(soliton_id, 0)` after all of them.
+pub(crate) fn cmp_internal(a: (&[u8], u64), b: (&[u8], u64)) -> Ordering {
+    a.0.cmp(b.0).then(b.1.cmp(&a.1))
+}
+
+/// A range delete of `[start, end)` issued at `seqno`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RangeTombstone {
+    pub(crate) start: Vec<u8>,
+    pub(crate) end: Vec<u8>,
+    pub(crate) seqno: u64,
+}
+
+impl RangeTombstone {
+    /// Whether this tombstone hides a version of `soliton_id` written at `version_seqno` from a
+    /// reader at `read_seqno`.
+    pub(crate) fn covers(&self, soliton_id: &[u8], version_seqno: u64, read_seqno: u64) -> bool {
+        self.seqno > version_seqno
+            && self.seqno <= read_seqno
+            && self.start.as_slice() <= soliton_id
+            && soliton_id < self.end.as_slice()
+    }
+
+    pub(crate) fn encode(&self, buf: &mut Vec<u8>) {
+        put_bytes(buf, &self.start);
+        put_bytes(buf, &self.end);
+        put_u64(buf, self.seqno);
+    }
+
+    pub(crate) fn decode(d: &mut Decoder<'_>) -> Result<RangeTombstone> {
+        Ok(RangeTombstone {
+            start: d.bytes()?.to_vec(),
+            end: d.bytes()?.to_vec(),
+            seqno: d.u64()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut buf = Vec::new();
+        for v in &[0, 1, 127, 128, 300, u64::MAX] {
+            put_varint(&mut buf, *v);
+        }
+        put_u32(&mut buf, 7);
+        put_bytes(&mut buf, b"soliton");
+        let entry = Entry {
+            soliton_id: b"k".to_vec(),
+            seqno: 9,
+            kind: ValueKind::Put,
+            causet_locale: b"v".to_vec(),
+        };
+        entry.encode(&mut buf);
+
+        let mut d = Decoder::new(&buf);
+        for v in &[0, 1, 127, 128, 300, u64::MAX] {
+            assert_eq!(d.varint().unwrap(), *v);
+        }
+        assert_eq!(d.u32().unwrap(), 7);
+        assert_eq!(d.bytes().unwrap(), b"soliton");
+        assert_eq!(Entry::decode(&mut d).unwrap(), entry);
+        assert!(d.is_empty());
+        assert!(d.u8().is_err());
+    }
+
+    #[test]
+    fn test_internal_order() {
+        assert_eq!(cmp_internal((b"a", 5), (b"a", 3)), Ordering::Less);
+        assert_eq!(cmp_internal((b"a", 1), (b"b", 9)), Ordering::Less);
+        assert_eq!(cmp_internal((b"a", u64::MAX), (b"a", 1)), Ordering::Less);
+        assert_eq!(cmp_internal((b"a", 0), (b"a", 1)), Ordering::Greater);
+    }
+}
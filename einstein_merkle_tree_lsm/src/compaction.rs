@@ -0,0 +1,528 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Flushes and leveled compaction.
+//!
+//! L_naught 0 is compacted as a whole into l_naught 1 once it holds
+//! `l_naught_zero_file_num_jet_bundle_trigger` files.  Every other l_naught `n` may hold
+//! `max_bytes_for_l_naught_base * max_bytes_for_l_naught_multiplier ^ (n - 1)` bytes; past that,
+//! one of its files is merged with the files of l_naught `n + 1` it overlaps.  Files of an
+//! l_naught are picked round robin, so that the whole soliton_id space is compacted in turn.
+//!
+//! Readers keep the files they started with alive, so a merge only ever keeps the newest version
+//! of each soliton_id.  Deletions, and range tombstones, are dropped once no file outside the
+//! compaction may hold an older version they hide.
+//!
+//! A newest version that is a merge operand is merged with the operands below it: fully, into a
+//! plain causet_locale, when the merge reaches the version they apply to or no other file may
+//! hold one, and otherwise partially, into a single operand.  An operand chain the merge operator
+//! cannot combine is written out as it is, and is never split across output files.
+//!
+//! Compactions, though not flushes, then hand a newest version that is a plain causet_locale to
+//! the compaction filter of the causet_merge family.  A causet_locale it removes is dropped like
+//! a deletion, or turned into one where another file may hold an older version.
+//!
+//! Compaction output is cut into files of `target_file_size`, and also wherever the causet
+//! partitioner of the causet_merge family asks for a cut.  A file the partitioner would cut is
+//! rewritten rather than moved down as it is.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+use fdb_traits::{
+    CausetPartitioner, CausetPartitionerRequest, CausetPartitionerResult, CompactionFilter, CompactionFilterDecision,
+    MergeOperator, Result,
+};
+
+use crate::cache::BlockCache;
+use crate::coding::{cmp_internal, Entry, RangeTombstone, ValueKind};
+use crate::options::{LsmNamespacedOptions, LsmOptions};
+use crate::table::{Table, TableBuilder};
+use crate::version::{table_path, FileMeta, NamespacedVersion, TableFile};
+
+/// A merge of table files of one l_naught into the next.
+pub(crate) struct Compaction {
+    pub(crate) l_naught: usize,
+    pub(crate) output_l_naught: usize,
+    pub(crate) inputs: Vec<Arc<TableFile>>,
+    /// The files of `output_l_naught` overlapping `inputs`.
+    pub(crate) output_l_naught_inputs: Vec<Arc<TableFile>>,
+}
+
+impl Compaction {
+    fn new(v: &NamespacedVersion, l_naught: usize, output_l_naught: usize, inputs: Vec<Arc<TableFile>>) -> Compaction {
+        let (smallest, largest) = key_range(&inputs);
+        let output_l_naught_inputs = if output_l_naught == l_naught {
+            Vec::new()
+        } else {
+            v.overlapping(output_l_naught, &smallest, &largest)
+        };
+        Compaction {
+            l_naught,
+            output_l_naught,
+            inputs,
+            output_l_naught_inputs,
+        }
+    }
+
+    pub(crate) fn all_inputs(&self) -> impl std::iter::Iterator<Item = &Arc<TableFile>> {
+        self.inputs.iter().chain(&self.output_l_naught_inputs)
+    }
+
+    /// A single file with nothing to merge with is moved to the next l_naught as it is.
+    pub(crate) fn is_trivial_move(&self) -> bool {
+        self.inputs.len() == 1 && self.output_l_naught_inputs.is_empty() && self.output_l_naught != self.l_naught
+    }
+}
+
+fn key_range(files: &[Arc<TableFile>]) -> (Vec<u8>, Vec<u8>) {
+    let smallest = files.iter().map(|f| f.meta.smallest()).min().unwrap_or_default();
+    let largest = files.iter().map(|f| f.meta.largest()).max().unwrap_or_default();
+    (smallest.to_vec(), largest.to_vec())
+}
+
+pub(crate) fn max_bytes_for_l_naught(opts: &LsmOptions, l_naught: usize) -> u64 {
+    let mut max = opts.max_bytes_for_l_naught_base;
+    for _ in 1..l_naught {
+        max = max.saturating_mul(opts.max_bytes_for_l_naught_multiplier);
+    }
+    max
+}
+
+/// Where the last compaction of each l_naught of each causet_merge family ended.
+pub(crate) type CompactPointers = HashMap<(String, usize), Vec<u8>>;
+
+/// The compaction of causet_merge family `namespaced` that is most overdue, if any is.
+pub(crate) fn pick(
+    namespaced: &str,
+    v: &NamespacedVersion,
+    opts: &LsmOptions,
+    namespaced_opts: &LsmNamespacedOptions,
+    pointers: &CompactPointers,
+) -> Option<Compaction> {
+    let num_l_naughts = v.l_naughts.len();
+    let mut best: Option<(f64, usize)> = None;
+    let l0_score = v.l_naught_zero_files() as f64 / namespaced_opts.l_naught_zero_file_num_jet_bundle_trigger.max(1) as f64;
+    if l0_score >= 1.0 && num_l_naughts > 1 {
+        best = Some((l0_score, 0));
+    }
+    for l_naught in 1..num_l_naughts.saturating_sub(1) {
+        let score = v.l_naught_size(l_naught) as f64 / max_bytes_for_l_naught(opts, l_naught) as f64;
+        if score >= 1.0 && best.is_none_or(|(s, _)| score > s) {
+            best = Some((score, l_naught));
+        }
+    }
+    let (_, l_naught) = best?;
+    if l_naught == 0 {
+        return Some(Compaction::new(v, 0, 1, v.l_naughts[0].clone()));
+    }
+    let files = &v.l_naughts[l_naught];
+    let pointer = pointers.get(&(namespaced.to_owned(), l_naught));
+    let file = files
+        .iter()
+        .find(|f| pointer.is_none_or(|p| f.meta.smallest() > p.as_slice()))
+        .unwrap_or(&files[0])
+        .clone();
+    Some(Compaction::new(v, l_naught, l_naught + 1, vec![file]))
+}
+
+/// The compaction of the files of `l_naught` holding soliton_ids in `[start, end]` into
+/// `output_l_naught`, which is either the next l_naught or, to rewrite the bottom l_naught,
+/// `l_naught` itself.  L_naught 0 is always compacted as a whole, as its files may overlap.
+pub(crate) fn pick_manual(
+    v: &NamespacedVersion,
+    l_naught: usize,
+    output_l_naught: usize,
+    start: &[u8],
+    end: Option<&[u8]>,
+) -> Option<Compaction> {
+    let inputs: Vec<Arc<TableFile>> = if l_naught == 0 {
+        v.l_naughts[0].clone()
+    } else {
+        v.l_naughts[l_naught]
+            .iter()
+            .filter(|f| f.meta.largest() >= start && end.is_none_or(|e| f.meta.smallest() <= e))
+            .cloned()
+            .collect()
+    };
+    if inputs.is_empty() {
+        return None;
+    }
+    Some(Compaction::new(v, l_naught, output_l_naught, inputs))
+}
+
+/// The deepest l_naught with a file holding soliton_ids in `[start, end]`.
+pub(crate) fn bottom_l_naught(v: &NamespacedVersion, start: &[u8], end: Option<&[u8]>) -> Option<usize> {
+    (0..v.l_naughts.len()).rev().find(|l| {
+        v.l_naughts[*l]
+            .iter()
+            .any(|f| f.meta.largest() >= start && end.is_none_or(|e| f.meta.smallest() <= e))
+    })
+}
+
+type EntryStream = Box<dyn std::iter::Iterator<Item = Result<Entry>>>;
+
+struct HeapItem {
+    entry: Entry,
+    stream: usize,
+}
+
+impl PartialEq for HeapItem {
+    fn eq(&self, other: &HeapItem) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapItem {}
+
+impl PartialOrd for HeapItem {
+    fn partial_cmp(&self, other: &HeapItem) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for HeapItem {
+    /// Reversed, so that the max-heap pops the smallest version first.
+    fn cmp(&self, other: &HeapItem) -> Ordering {
+        cmp_internal(other.entry.internal_key(), self.entry.internal_key()).then(other.stream.cmp(&self.stream))
+    }
+}
+
+/// Where the output of a flush or compaction goes.
+pub(crate) struct OutputOptions<'a> {
+    pub(crate) opts: &'a LsmOptions,
+    pub(crate) dir: &'a Path,
+    pub(crate) cache: &'a Option<Arc<BlockCache>>,
+    /// Outputs are cut once they reach this size.
+    pub(crate) target_file_size: u64,
+    pub(crate) new_file_number: &'a dyn Fn() -> u64,
+}
+
+struct OutputFile {
+    builder: TableBuilder,
+    number: u64,
+    /// The first soliton_id of the file, or `None` for the first file of the output.
+    lower: Option<Vec<u8>>,
+}
+
+/// Writes the merged stream of a flush or compaction, cutting it into files.
+struct Outputs<'a> {
+    out: &'a OutputOptions<'a>,
+    partitioner: Option<&'a mut dyn CausetPartitioner>,
+    range_tombstones: Vec<RangeTombstone>,
+    current: Option<OutputFile>,
+    files: Vec<(FileMeta, Arc<Table>)>,
+}
+
+impl<'a> Outputs<'a> {
+    fn open(&mut self, lower: Option<Vec<u8>>) -> Result<()> {
+        let number = (self.out.new_file_number)();
+        let builder = TableBuilder::create(
+            self.out.opts.env.as_ref(),
+            &table_path(self.out.dir, number),
+            self.out.opts.block_size,
+            self.out.opts.bloom_bits_per_soliton_id,
+        )?;
+        self.current = Some(OutputFile { builder, number, lower });
+        Ok(())
+    }
+
+    fn add(&mut self, entry: &Entry) -> Result<()> {
+        let cut = match &self.current {
+            None => {
+                self.open(if self.files.is_empty() { None } else { Some(entry.soliton_id.clone()) })?;
+                false
+            }
+            Some(f) => match f.builder.last_soliton_id() {
+                Some(last) if last != entry.soliton_id.as_slice() => {
+                    let size = f.builder.estimated_size();
+                    let partition = self.partitioner.as_mut().map(|p| {
+                        p.should_partition(&CausetPartitionerRequest {
+                            prev_user_soliton_id: last,
+                            current_user_soliton_id: &entry.soliton_id,
+                            current_output_file_size: size,
+                        })
+                    });
+                    partition == Some(CausetPartitionerResult::Required) || size >= self.out.target_file_size
+                }
+                _ => false,
+            },
+        };
+        if cut {
+            self.finish(Some(&entry.soliton_id))?;
+            self.open(Some(entry.soliton_id.clone()))?;
+        }
+        self.current.as_mut().unwrap().builder.add(entry)
+    }
+
+    /// Finish the current file, giving it the pieces of the range tombstones that fall between
+    /// its first soliton_id and `upper`, the first soliton_id of the next file.
+    fn finish(&mut self, upper: Option<&[u8]>) -> Result<()> {
+        let OutputFile {
+            mut builder,
+            number,
+            lower,
+        } = self.current.take().unwrap();
+        for t in &self.range_tombstones {
+            let start = match &lower {
+                Some(l) if l > &t.start => l.clone(),
+                _ => t.start.clone(),
+            };
+            let end = match upper {
+                Some(u) if u < t.end.as_slice() => u.to_vec(),
+                _ => t.end.clone(),
+            };
+            if start < end {
+                builder.add_range_tombstone(RangeTombstone {
+                    start,
+                    end,
+                    seqno: t.seqno,
+                });
+            }
+        }
+        let summary = builder.finish()?;
+        let env = self.out.opts.env.clone();
+        let table = Table::open(env, &table_path(self.out.dir, number), number, self.out.cache.clone())?;
+        self.files.push((FileMeta { number, summary }, Arc::new(table)));
+        Ok(())
+    }
+}
+
+/// The versions to keep of a soliton_id, given all of its `versions` in the merge, newest first.
+fn compact_versions(
+    versions: Vec<Entry>,
+    range_tombstones: &[RangeTombstone],
+    overlaps_others: &dyn Fn(&[u8], &[u8]) -> bool,
+    merge_operator: Option<&dyn MergeOperator>,
+) -> Vec<Entry> {
+    let covered = |e: &Entry| range_tombstones.iter().any(|t| t.covers(&e.soliton_id, e.seqno, u64::MAX));
+    let mut versions = versions.into_iter();
+    let newest = versions.next().unwrap();
+    let soliton_id = newest.soliton_id.clone();
+    let elsewhere = overlaps_others(&soliton_id, &soliton_id);
+    if covered(&newest) {
+        return vec![];
+    }
+    match newest.kind {
+        ValueKind::Put => return vec![newest],
+        ValueKind::Delete => return if elsewhere { vec![newest] } else { vec![] },
+        ValueKind::Merge => {}
+    }
+
+    // The operands, newest first, down to the version they apply to.
+    let mut chain = vec![newest];
+    let mut base = None;
+    let mut reached_base = !elsewhere;
+    for e in versions {
+        if covered(&e) {
+            reached_base = true;
+            break;
+        }
+        match e.kind {
+            ValueKind::Merge => chain.push(e),
+            _ => {
+                reached_base = true;
+                base = Some(e);
+                break;
+            }
+        }
+    }
+
+    if let Some(op) = merge_operator {
+        let operands: Vec<&[u8]> = chain.iter().rev().map(|e| e.causet_locale.as_slice()).collect();
+        let merged = if reached_base {
+            let existing = base
+                .as_ref()
+                .filter(|b| b.kind == ValueKind::Put)
+                .map(|b| b.causet_locale.as_slice());
+            op.full_merge(&soliton_id, existing, &operands)
+                .ok()
+                .map(|v| (ValueKind::Put, v))
+        } else if chain.len() > 1 {
+            op.partial_merge(&soliton_id, &operands).map(|v| (ValueKind::Merge, v))
+        } else {
+            None
+        };
+        if let Some((kind, causet_locale)) = merged {
+            return vec![Entry {
+                soliton_id,
+                seqno: chain[0].seqno,
+                kind,
+                causet_locale,
+            }];
+        }
+    }
+    // Left for reads to merge, which report the failure.
+    chain.extend(base.filter(|b| b.kind == ValueKind::Put || elsewhere));
+    chain
+}
+
+/// Pass the versions `compact_versions` kept of a soliton_id through `filter`.
+fn filter_versions(
+    mut kept: Vec<Entry>,
+    filter: &mut dyn CompactionFilter,
+    overlaps_others: &dyn Fn(&[u8], &[u8]) -> bool,
+) -> Vec<Entry> {
+    if kept.len() != 1 || kept[0].kind != ValueKind::Put {
+        return kept;
+    }
+    let e = &mut kept[0];
+    match filter.filter(&e.soliton_id, &e.causet_locale) {
+        CompactionFilterDecision::Keep => {}
+        CompactionFilterDecision::Remove if overlaps_others(&e.soliton_id, &e.soliton_id) => {
+            e.kind = ValueKind::Delete;
+            e.causet_locale.clear();
+        }
+        CompactionFilterDecision::Remove => kept.clear(),
+        CompactionFilterDecision::ChangeValue(causet_locale) => e.causet_locale = causet_locale,
+    }
+    kept
+}
+
+/// Merge `streams`, each in `cmp_internal` order, and `range_tombstones` into new table files.
+///
+/// `overlaps_others(smallest, largest)` tells whether a file that is not being merged may hold
+/// soliton_ids in `[smallest, largest]`; deletions are only dropped where it does not.  Merge
+/// operands are combined with `merge_operator`, the operator of the causet_merge family, the
+/// causet_locales kept are passed through `filter`, and the output is cut where `partitioner`
+/// asks as well as by size.
+pub(crate) fn merge<'a>(
+    streams: Vec<EntryStream>,
+    range_tombstones: Vec<RangeTombstone>,
+    overlaps_others: &dyn Fn(&[u8], &[u8]) -> bool,
+    merge_operator: Option<&dyn MergeOperator>,
+    mut filter: Option<&mut dyn CompactionFilter>,
+    partitioner: Option<&'a mut dyn CausetPartitioner>,
+    out: &'a OutputOptions<'a>,
+) -> Result<Vec<(FileMeta, Arc<Table>)>> {
+    let mut streams = streams;
+    let mut heap = BinaryHeap::new();
+    for (i, s) in streams.iter_mut().enumerate() {
+        if let Some(entry) = s.next().transpose()? {
+            heap.push(HeapItem { entry, stream: i });
+        }
+    }
+    let kept_tombstones: Vec<RangeTombstone> = range_tombstones
+        .iter()
+        .filter(|t| overlaps_others(&t.start, &t.end))
+        .cloned()
+        .collect();
+    let mut outputs = Outputs {
+        out,
+        partitioner,
+        range_tombstones: kept_tombstones,
+        current: None,
+        files: Vec::new(),
+    };
+
+    // The versions of the current soliton_id, newest first.
+    let mut versions: Vec<Entry> = Vec::new();
+    let mut compact = |versions: Vec<Entry>, outputs: &mut Outputs<'_>| -> Result<()> {
+        let mut kept = compact_versions(versions, &range_tombstones, overlaps_others, merge_operator);
+        if let Some(filter) = filter.as_deref_mut() {
+            kept = filter_versions(kept, filter, overlaps_others);
+        }
+        for e in kept {
+            outputs.add(&e)?;
+        }
+        Ok(())
+    };
+    while let Some(HeapItem { entry, stream }) = heap.pop() {
+        if let Some(next) = streams[stream].next().transpose()? {
+            heap.push(HeapItem { entry: next, stream });
+        }
+        if versions.first().is_some_and(|v| v.soliton_id != entry.soliton_id) {
+            compact(std::mem::take(&mut versions), &mut outputs)?;
+        }
+        versions.push(entry);
+    }
+    if !versions.is_empty() {
+        compact(versions, &mut outputs)?;
+    }
+
+    if outputs.current.is_some() {
+        outputs.finish(None)?;
+    } else if !outputs.range_tombstones.is_empty() {
+        outputs.open(None)?;
+        outputs.finish(None)?;
+    }
+    Ok(outputs.files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+    use crate::source::Source;
+    use crate::table::TableSource;
+
+    fn entry(soliton_id: &[u8], seqno: u64, kind: ValueKind) -> Entry {
+        Entry {
+            soliton_id: soliton_id.to_vec(),
+            seqno,
+            kind,
+            causet_locale: seqno.to_string().into_bytes(),
+        }
+    }
+
+    fn stream(entries: Vec<Entry>) -> EntryStream {
+        Box::new(entries.into_iter().map(Ok))
+    }
+
+    #[test]
+    fn test_merge_drops_obsolete_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let opts = LsmOptions::default();
+        let next = Cell::new(1);
+        let new_file_number = || {
+            next.set(next.get() + 1);
+            next.get()
+        };
+        let out = OutputOptions {
+            opts: &opts,
+            dir: dir.path(),
+            cache: &None,
+            target_file_size: 1,
+            new_file_number: &new_file_number,
+        };
+        let newer = vec![
+            entry(b"a", 10, ValueKind::Put),
+            entry(b"b", 11, ValueKind::Delete),
+            entry(b"d", 12, ValueKind::Delete),
+        ];
+        let older = vec![
+            entry(b"a", 1, ValueKind::Put),
+            entry(b"b", 2, ValueKind::Put),
+            entry(b"c", 3, ValueKind::Put),
+            entry(b"d", 4, ValueKind::Put),
+            entry(b"e", 5, ValueKind::Put),
+        ];
+        let tombstones = vec![RangeTombstone {
+            start: b"c".to_vec(),
+            end: b"f".to_vec(),
+            seqno: 9,
+        }];
+        // Something outside the merge overlaps "d" and "e" only.
+        let overlaps_others = |s: &[u8], l: &[u8]| s <= &b"e"[..] && &b"d"[..] <= l;
+        let streams = vec![stream(newer), stream(older)];
+        let files = merge(streams, tombstones, &overlaps_others, None, None, None, &out).unwrap();
+
+        // One soliton_id per file as the target size is tiny: a@10 and the deletion of d.
+        assert_eq!(files.len(), 2);
+        let read = |i: usize| -> Vec<Entry> { files[i].1.scan().collect::<Result<_>>().unwrap() };
+        assert_eq!(read(0), vec![entry(b"a", 10, ValueKind::Put)]);
+        assert_eq!(read(1), vec![entry(b"d", 12, ValueKind::Delete)]);
+        // The tombstone is kept, as it may hide "d" and "e" elsewhere, and clipped to the files.
+        let pieces: Vec<_> = files
+            .iter()
+            .flat_map(|(_, t)| TableSource { table: t.clone(), fill_cache: false }.range_tombstones())
+            .map(|t| (t.start, t.end))
+            .collect();
+        assert_eq!(pieces, vec![(b"c".to_vec(), b"d".to_vec()), (b"d".to_vec(), b"f".to_vec())]);
+        assert_eq!(files[0].0.smallest(), b"a");
+        assert_eq!(files[0].0.largest(), b"d");
+        assert_eq!(files[1].0.largest(), b"f");
+    }
+}
@@ -0,0 +1,1497 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use fdb_traits::{
+    CausetPartitioner, CausetPartitionerContext, CompactionFilter, CompactionFilterContext, Error, IterOptions,
+    Iterable, KvEinsteinMerkleTree, LightlikePersistenceExt, MergeOperator, Peekable, ReadOptions, Result, SyncMutable,
+    WriteBatch, WriteBatchExt, WriteOptions,
+};
+
+use crate::cache::BlockCache;
+use crate::coding::{corruption, Entry, ValueKind};
+use crate::compaction::{self, Compaction, CompactPointers, OutputOptions};
+use crate::env::FileLock;
+use crate::import::{ExternalFile, LsmIngestlightlikeFileOptions};
+use crate::iterator::LsmEinsteinMerkleTreeIterator;
+use crate::key_manager::KEY_DICTIONARY;
+use crate::lightlike_persistence::LsmLightlikePersistence;
+use crate::memtable::MemTable;
+use crate::options::{LsmNamespacedOptions, LsmOptions};
+use crate::source::{Source, SourceSet};
+use crate::table::{Table, TableBuilder, TableSource, TableSummary};
+use crate::version::{log_path, parse_file_name, table_path, FileMeta, ManifestState, TableFile, Version, LOCK, MANIFEST};
+use crate::wal::{self, LogWriter};
+use crate::write_batch::{LsmWriteBatch, WriteOp};
+
+/// Writes wait once this many memtables are waiting to be flushed.
+const MAX_IMMUTABLE_MEMTABLES: usize = 2;
+
+/// How long a write is delayed while l_naught 0 is past its slowdown trigger.
+const SLOWDOWN_DELAY: Duration = Duration::from_millis(1);
+
+/// The memtables of every causet_merge family that share one write-ahead log.
+pub(crate) struct MemTables {
+    log_number: u64,
+    tables: BTreeMap<String, Arc<MemTable>>,
+}
+
+impl MemTables {
+    fn new(log_number: u64, namespaced_names: &[String]) -> MemTables {
+        MemTables {
+            log_number,
+            tables: namespaced_names
+                .iter()
+                .map(|n| (n.clone(), Arc::new(MemTable::default())))
+                .collect(),
+        }
+    }
+
+    fn approximate_size(&self) -> usize {
+        self.tables.values().map(|t| t.approximate_size()).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tables.values().all(|t| t.is_empty())
+    }
+
+    fn apply(&self, ops: &[WriteOp], first_seqno: u64) {
+        for (op, seqno) in ops.iter().zip(first_seqno..) {
+            let table = &self.tables[op.namespaced()];
+            match op {
+                WriteOp::Put { soliton_id, causet_locale, .. } => {
+                    table.add(ValueKind::Put, soliton_id, causet_locale, seqno)
+                }
+                WriteOp::Delete { soliton_id, .. } => table.add(ValueKind::Delete, soliton_id, &[], seqno),
+                WriteOp::DeleteRange { start, end, .. } => table.delete_range(start, end, seqno),
+                WriteOp::Merge { soliton_id, operand, .. } => table.add(ValueKind::Merge, soliton_id, operand, seqno),
+            }
+        }
+    }
+}
+
+/// Everything a read needs: the memtables and table files as of some point in time.
+pub(crate) struct SuperVersion {
+    mem: Arc<MemTables>,
+    /// Memtables waiting to be flushed, oldest first.
+    imm: Vec<Arc<MemTables>>,
+    version: Arc<Version>,
+}
+
+impl SuperVersion {
+    pub(crate) fn sources(
+        &self,
+        namespaced: &str,
+        read_seqno: u64,
+        fill_cache: bool,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
+    ) -> Result<SourceSet> {
+        let files = self
+            .version
+            .namespaceds
+            .get(namespaced)
+            .ok_or_else(|| Error::NAMESPACEDName(namespaced.to_owned()))?;
+        let mut sources: Vec<Box<dyn Source>> = Vec::new();
+        for mem in std::iter::once(&self.mem).chain(self.imm.iter().rev()) {
+            sources.push(Box::new(mem.tables[namespaced].clone()));
+        }
+        for f in files.files() {
+            sources.push(Box::new(TableSource {
+                table: f.table.clone(),
+                fill_cache,
+            }));
+        }
+        Ok(SourceSet::new(sources, read_seqno, namespaced, merge_operator))
+    }
+
+    /// Whether causet_merge family `namespaced` may hold anything in `[smallest, largest]`.
+    fn overlaps(&self, namespaced: &str, smallest: &[u8], largest: &[u8]) -> bool {
+        std::iter::once(&self.mem)
+            .chain(&self.imm)
+            .any(|mem| mem.tables[namespaced].overlaps(smallest, largest))
+            || self.version.namespaceds[namespaced]
+                .files()
+                .any(|f| f.meta.overlaps(smallest, largest))
+    }
+}
+
+/// The table files of a consistent view of the einstein_merkle_tree; see `Core::live_files`.
+pub(crate) struct LiveFiles {
+    /// The sequence number of the last write in the view.
+    pub(crate) seqno: u64,
+    pub(crate) tables: Vec<PathBuf>,
+    _super_version: Arc<SuperVersion>,
+}
+
+struct State {
+    super_version: Arc<SuperVersion>,
+    compact_pointers: CompactPointers,
+    /// Set when a flush or compaction fails; every later write fails with it.
+    bg_error: Option<String>,
+    shutting_down: bool,
+}
+
+pub(crate) struct Core {
+    dir: PathBuf,
+    pub(crate) opts: LsmOptions,
+    namespaced_names: Vec<String>,
+    namespaced_opts: BTreeMap<String, LsmNamespacedOptions>,
+    cache: Option<Arc<BlockCache>>,
+    /// The sequence number of the last write visible to readers.
+    seqno: AtomicU64,
+    next_file_number: AtomicU64,
+    /// Serializes writers.
+    write_lock: Mutex<()>,
+    log: Mutex<LogWriter>,
+    state: Mutex<State>,
+    /// Wakes the background thread.
+    work_cv: Condvar,
+    /// Wakes writers waiting on a flush or compaction.
+    stall_cv: Condvar,
+    /// Serializes flushes and compactions, which are the only changes to the `Version`.
+    bg_lock: Mutex<()>,
+    /// Keeps any other einstein_merkle_tree from opening `dir` while this one has it open;
+    /// dropped last.
+    _dir_lock: Box<dyn FileLock>,
+}
+
+impl Core {
+    fn new_file_number(&self) -> u64 {
+        self.next_file_number.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub(crate) fn namespaced_names(&self) -> Vec<&str> {
+        self.namespaced_names.iter().map(String::as_str).collect()
+    }
+
+    pub(crate) fn super_version(&self) -> Arc<SuperVersion> {
+        self.state.lock().unwrap().super_version.clone()
+    }
+
+    /// The latest sequence number and a `SuperVersion` holding every write up to it.
+    ///
+    /// The sequence number is read first: a write it covers is in a memtable that is still in
+    /// any later `SuperVersion`, or in the table file that memtable was flushed to.
+    pub(crate) fn read_view(&self) -> (u64, Arc<SuperVersion>) {
+        let seqno = self.seqno.load(Ordering::Acquire);
+        (seqno, self.super_version())
+    }
+
+    pub(crate) fn check_namespaced(&self, namespaced: &str) -> Result<()> {
+        if self.namespaced_opts.contains_key(namespaced) {
+            Ok(())
+        } else {
+            Err(Error::NAMESPACEDName(namespaced.to_owned()))
+        }
+    }
+
+    fn namespaced_opts(&self, namespaced: &str) -> &LsmNamespacedOptions {
+        &self.namespaced_opts[namespaced]
+    }
+
+    fn output_options<'a>(&'a self, target_file_size: u64, new_file_number: &'a dyn Fn() -> u64) -> OutputOptions<'a> {
+        OutputOptions {
+            opts: &self.opts,
+            dir: &self.dir,
+            cache: &self.cache,
+            target_file_size,
+            new_file_number,
+        }
+    }
+
+    fn write_manifest(&self, version: &Version, log_number: u64) -> Result<()> {
+        ManifestState::of_version(
+            version,
+            self.next_file_number.load(Ordering::SeqCst),
+            self.seqno.load(Ordering::Acquire),
+            log_number,
+        )
+        .write(self.opts.env.as_ref(), &self.dir)
+    }
+
+    /// Make sure the memtables can take another write, stalling the writer as the options
+    /// require.  Called with the write lock held.
+    fn make_room(&self, namespaceds: &BTreeSet<&str>, no_slowdown: bool) -> Result<()> {
+        let mut delayed = false;
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(e) = &state.bg_error {
+                return Err(Error::EinsteinMerkleTree(format!("background error: {}", e)));
+            }
+            let sv = state.super_version.clone();
+            let (mut stop, mut slowdown) = (false, false);
+            for namespaced in namespaceds {
+                let opts = self.namespaced_opts(namespaced);
+                if opts.disable_auto_jet_bundles {
+                    continue;
+                }
+                let l0 = sv.version.namespaceds[*namespaced].l_naught_zero_files();
+                stop |= l0 >= opts.l_naught_zero_stop_writes_trigger;
+                slowdown |= l0 >= opts.l_naught_zero_slowdown_writes_trigger;
+            }
+            if stop || sv.imm.len() >= MAX_IMMUTABLE_MEMTABLES && sv.mem.approximate_size() >= self.opts.write_buffer_size {
+                if no_slowdown {
+                    return Err(Error::WriteStall(if stop {
+                        "too many l_naught 0 files".to_owned()
+                    } else {
+                        "too many memtables waiting to be flushed".to_owned()
+                    }));
+                }
+                self.work_cv.notify_all();
+                state = self.stall_cv.wait(state).unwrap();
+                continue;
+            }
+            if slowdown && !delayed {
+                if no_slowdown {
+                    return Err(Error::WriteStall("too many l_naught 0 files".to_owned()));
+                }
+                drop(state);
+                thread::sleep(SLOWDOWN_DELAY);
+                delayed = true;
+                state = self.state.lock().unwrap();
+                continue;
+            }
+            if sv.mem.approximate_size() >= self.opts.write_buffer_size {
+                self.switch_memtables(&mut state)?;
+            }
+            return Ok(());
+        }
+    }
+
+    /// Freeze the memtables and start new ones on a new write-ahead log.
+    fn switch_memtables(&self, state: &mut MutexGuard<'_, State>) -> Result<()> {
+        let number = self.new_file_number();
+        let log = LogWriter::create(self.opts.env.as_ref(), &log_path(&self.dir, number))?;
+        let mut old_log = std::mem::replace(&mut *self.log.lock().unwrap(), log);
+        old_log.sync()?;
+        let sv = &state.super_version;
+        let mut imm = sv.imm.clone();
+        imm.push(sv.mem.clone());
+        state.super_version = Arc::new(SuperVersion {
+            mem: Arc::new(MemTables::new(number, &self.namespaced_names)),
+            imm,
+            version: sv.version.clone(),
+        });
+        self.work_cv.notify_all();
+        Ok(())
+    }
+
+    fn write(&self, ops: &[WriteOp], opts: &WriteOptions) -> Result<()> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        let mut namespaceds = BTreeSet::new();
+        for op in ops {
+            if !self.namespaced_opts.contains_key(op.namespaced()) {
+                return Err(Error::NAMESPACEDName(op.namespaced().to_owned()));
+            }
+            if let WriteOp::Merge { .. } = op {
+                self.opts.merge_operators.require(op.namespaced())?;
+            }
+            namespaceds.insert(op.namespaced());
+        }
+        let _writer = self.write_lock.lock().unwrap();
+        self.make_room(&namespaceds, opts.no_slowdown())?;
+        let first_seqno = self.seqno.load(Ordering::Acquire) + 1;
+        {
+            let mut log = self.log.lock().unwrap();
+            log.add_record(&wal::encode_batch(first_seqno, ops))?;
+            if opts.sync() {
+                log.sync()?;
+            }
+        }
+        self.super_version().mem.apply(ops, first_seqno);
+        self.seqno
+            .store(first_seqno + ops.len() as u64 - 1, Ordering::Release);
+        Ok(())
+    }
+
+    /// Write the oldest memtables waiting to be flushed to l_naught 0.  Called with the
+    /// background lock held.  Returns whether there was anything to flush.
+    fn flush_one(&self) -> Result<bool> {
+        let sv = self.super_version();
+        let imm = match sv.imm.first() {
+            Some(imm) => imm.clone(),
+            None => return Ok(false),
+        };
+        let new_file_number = || self.new_file_number();
+        let out = self.output_options(u64::MAX, &new_file_number);
+        let mut version = (*sv.version).clone();
+        for (namespaced, mem) in &imm.tables {
+            if mem.is_empty() {
+                continue;
+            }
+            let files = &sv.version.namespaceds[namespaced];
+            let overlaps_others = |s: &[u8], l: &[u8]| files.files().any(|f| f.meta.overlaps(s, l));
+            let outputs = compaction::merge(
+                vec![Box::new(mem.entries().into_iter().map(Ok))],
+                mem.range_tombstones(),
+                &overlaps_others,
+                self.opts.merge_operators.get(namespaced).map(|op| op.as_ref()),
+                None,
+                None,
+                &out,
+            )?;
+            let added: Vec<_> = outputs
+                .into_iter()
+                .map(|(meta, table)| (0, Arc::new(TableFile { meta, table })))
+                .collect();
+            version = version.edited(namespaced, &[], &added);
+        }
+        // Every write in the flushed memtables is now in a table file, so the logs before the
+        // next memtables are no longer needed.
+        let log_number = sv.imm.get(1).unwrap_or(&sv.mem).log_number;
+        self.write_manifest(&version, log_number)?;
+        self.install(Arc::new(version), 1);
+        self.delete_obsolete_logs(log_number);
+        Ok(true)
+    }
+
+    /// Swap in `version`, dropping the oldest `flushed` memtables.
+    fn install(&self, version: Arc<Version>, flushed: usize) {
+        let mut state = self.state.lock().unwrap();
+        let sv = &state.super_version;
+        state.super_version = Arc::new(SuperVersion {
+            mem: sv.mem.clone(),
+            imm: sv.imm[flushed..].to_vec(),
+            version,
+        });
+        self.stall_cv.notify_all();
+    }
+
+    /// Delete table files left behind by a flush or compaction that did not make it into the
+    /// manifest, and a manifest that was never renamed into place.
+    fn delete_obsolete_files(&self) {
+        let env = self.opts.env.as_ref();
+        let sv = self.super_version();
+        let live: BTreeSet<u64> = sv
+            .version
+            .namespaceds
+            .values()
+            .flat_map(|v| v.files())
+            .map(|f| f.meta.number)
+            .collect();
+        if let Ok(paths) = env.list_dir(&self.dir) {
+            for path in paths {
+                let obsolete = match parse_file_name(&path) {
+                    Some((number, "sst")) => !live.contains(&number),
+                    Some((_, "tmp")) | None => path.file_name().is_some_and(|n| n == "MANIFEST.tmp"),
+                    _ => false,
+                };
+                if obsolete {
+                    let _ = env.delete_file(&path);
+                }
+            }
+        }
+    }
+
+    fn delete_obsolete_logs(&self, log_number: u64) {
+        let env = self.opts.env.as_ref();
+        if let Ok(paths) = env.list_dir(&self.dir) {
+            for path in paths {
+                if let Some((number, "log")) = parse_file_name(&path) {
+                    if number < log_number {
+                        let _ = env.delete_file(&path);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Run `c` on causet_merge family `namespaced`, through its compaction filter and causet
+    /// partitioner unless `c` is a trivial move the partitioner allows.  `manual` tells whether
+    /// `c` was asked for.  Returns whether the inputs were rewritten rather than moved.  Called
+    /// with the background lock held.
+    fn run_compaction(&self, namespaced: &str, c: Compaction, manual: bool) -> Result<bool> {
+        let sv = self.super_version();
+        let files = &sv.version.namespaceds[namespaced];
+        let removed: Vec<u64> = c.all_inputs().map(|f| f.meta.number).collect();
+        let smallest = c.all_inputs().map(|f| f.meta.smallest()).min().unwrap_or_default();
+        let largest = c.all_inputs().map(|f| f.meta.largest()).max().unwrap_or_default();
+        let mut partitioner = self.opts.causet_partitioner_factories.get(namespaced).and_then(|factory| {
+            factory.create_partitioner(&CausetPartitionerContext {
+                is_full_jet_bundle: removed.len() == files.files().count(),
+                is_manual_jet_bundle: manual,
+                output_l_naught: c.output_l_naught,
+                smallest_soliton_id: smallest,
+                largest_soliton_id: largest,
+            })
+        });
+        let trivial_move = c.is_trivial_move()
+            && partitioner.as_mut().is_none_or(|p| p.can_do_trivial_move(smallest, largest));
+        let added: Vec<(usize, Arc<TableFile>)> = if trivial_move {
+            vec![(c.output_l_naught, c.inputs[0].clone())]
+        } else {
+            let others: Vec<&Arc<TableFile>> = files.files().filter(|f| !removed.contains(&f.meta.number)).collect();
+            let overlaps_others = |s: &[u8], l: &[u8]| others.iter().any(|f| f.meta.overlaps(s, l));
+            let streams = c
+                .all_inputs()
+                .map(|f| Box::new(f.table.scan()) as Box<dyn std::iter::Iterator<Item = _>>)
+                .collect();
+            let range_tombstones = c
+                .all_inputs()
+                .flat_map(|f| f.table.range_tombstones().to_vec())
+                .collect();
+            let new_file_number = || self.new_file_number();
+            let out = self.output_options(self.opts.target_file_size, &new_file_number);
+            let merge_operator = self.opts.merge_operators.get(namespaced).map(|op| op.as_ref());
+            let mut filter = self.opts.compaction_filter_factories.get(namespaced).and_then(|factory| {
+                factory.create_compaction_filter(&CompactionFilterContext {
+                    namespaced,
+                    is_manual_compaction: manual,
+                    is_bottommost_l_naught: !overlaps_others(smallest, largest),
+                })
+            });
+            compaction::merge(
+                streams,
+                range_tombstones,
+                &overlaps_others,
+                merge_operator,
+                filter.as_mut().map(|f| f.as_mut() as &mut dyn CompactionFilter),
+                partitioner.as_mut().map(|p| p.as_mut() as &mut dyn CausetPartitioner),
+                &out,
+            )?
+            .into_iter()
+            .map(|(meta, table)| (c.output_l_naught, Arc::new(TableFile { meta, table })))
+            .collect()
+        };
+        let version = sv.version.edited(namespaced, &removed, &added);
+        self.write_manifest(&version, sv.imm.first().unwrap_or(&sv.mem).log_number)?;
+        self.install(Arc::new(version), 0);
+        if !trivial_move {
+            for f in c.all_inputs() {
+                f.table.mark_obsolete();
+            }
+        }
+        Ok(!trivial_move)
+    }
+
+    /// Add the table files at `paths` to causet_merge family `namespaced`, all or none.
+    ///
+    /// The files are checked before any lock is taken.  Files overlapping nothing in the
+    /// causet_merge family go to the bottom l_naught at sequence number 0, as if they had always
+    /// been there.  Otherwise they must shadow what they overlap: they are rewritten with the next
+    /// sequence number and go to l_naught 0.  Files are moved into the einstein_merkle_tree only
+    /// when they need no rewriting.
+    pub(crate) fn ingest(&self, namespaced: &str, paths: &[&str], opts: &LsmIngestlightlikeFileOptions) -> Result<()> {
+        self.check_namespaced(namespaced)?;
+        let env = &self.opts.env;
+        let mut files = paths
+            .iter()
+            .map(|p| ExternalFile::check(env, Path::new(p), namespaced))
+            .collect::<Result<Vec<_>>>()?;
+        if files.is_empty() {
+            return Ok(());
+        }
+        files.sort_by(|a, b| a.smallest.cmp(&b.smallest));
+        if let Some(w) = files.windows(2).find(|w| w[1].smallest <= w[0].largest) {
+            return Err(Error::Ingest(format!(
+                "{} overlaps {}",
+                w[0].path.display(),
+                w[1].path.display()
+            )));
+        }
+
+        let _writer = self.write_lock.lock().unwrap();
+        let _bg = self.bg_lock.lock().unwrap();
+        self.check_bg_error()?;
+        let sv = self.super_version();
+        let overlapping = files
+            .iter()
+            .find(|f| sv.overlaps(namespaced, &f.smallest, &f.largest));
+        let (seqno, l_naught) = match overlapping {
+            Some(f) if !opts.write_global_seqno => {
+                return Err(Error::Ingest(format!(
+                    "{} overlaps existing data and may not be given a global sequence number",
+                    f.path.display()
+                )));
+            }
+            Some(_) => (self.seqno.load(Ordering::Acquire) + 1, 0),
+            None => (0, sv.version.namespaceds[namespaced].l_naughts.len() - 1),
+        };
+
+        let mut placed: Vec<(usize, Arc<TableFile>)> = Vec::new();
+        let moved = seqno == 0 && opts.move_files;
+        let undo = |placed: &[(usize, Arc<TableFile>)]| {
+            for ((_, f), src) in placed.iter().zip(&files) {
+                let dst = table_path(&self.dir, f.meta.number);
+                let _ = if moved { env.rename_file(&dst, &src.path) } else { env.delete_file(&dst) };
+            }
+        };
+        for f in &files {
+            match self.place_external_file(f, seqno, moved) {
+                Ok(file) => placed.push((l_naught, Arc::new(file))),
+                Err(e) => {
+                    undo(&placed);
+                    return Err(e);
+                }
+            }
+        }
+        if seqno != 0 {
+            self.seqno.store(seqno, Ordering::Release);
+        }
+        let version = sv.version.edited(namespaced, &[], &placed);
+        if let Err(e) = self.write_manifest(&version, sv.imm.first().unwrap_or(&sv.mem).log_number) {
+            undo(&placed);
+            return Err(e);
+        }
+        self.install(Arc::new(version), 0);
+        if opts.move_files && !moved {
+            for f in &files {
+                let _ = env.delete_file(&f.path);
+            }
+        }
+        self.work_cv.notify_all();
+        Ok(())
+    }
+
+    /// Put `f` in a table file of the einstein_merkle_tree, by renaming it if `moved`, otherwise
+    /// by rewriting its versions with sequence number `seqno`.
+    fn place_external_file(&self, f: &ExternalFile, seqno: u64, moved: bool) -> Result<TableFile> {
+        let env = &self.opts.env;
+        let number = self.new_file_number();
+        let path = table_path(&self.dir, number);
+        let summary = if moved {
+            env.rename_file(&f.path, &path)?;
+            TableSummary {
+                file_size: f.table.file_size(),
+                smallest: f.smallest.clone(),
+                largest: f.largest.clone(),
+                smallest_seqno: 0,
+                largest_seqno: 0,
+            }
+        } else {
+            let mut builder =
+                TableBuilder::create(env.as_ref(), &path, self.opts.block_size, self.opts.bloom_bits_per_soliton_id)?;
+            for (name, causet_locale) in f.table.greedoids() {
+                builder.set_greedoid(name, causet_locale);
+            }
+            for entry in f.table.scan() {
+                builder.add(&Entry { seqno, ..entry? })?;
+            }
+            builder.finish()?
+        };
+        let table = Table::open(env.clone(), &path, number, self.cache.clone())?;
+        Ok(TableFile {
+            meta: FileMeta { number, summary },
+            table: Arc::new(table),
+        })
+    }
+
+    /// The files making up the einstein_merkle_tree as of now.
+    ///
+    /// The manifest, the write-ahead logs it still needs and the key dictionary change as the
+    /// einstein_merkle_tree is written to, so they are handed to `copy` while no write, flush or
+    /// compaction can run.  Table files never change once written; the returned `LiveFiles`
+    /// keeps the ones it names from being deleted.
+    pub(crate) fn live_files(&self, copy: impl FnOnce(&[PathBuf]) -> Result<()>) -> Result<LiveFiles> {
+        let _writer = self.write_lock.lock().unwrap();
+        let _bg = self.bg_lock.lock().unwrap();
+        self.check_bg_error()?;
+        self.log.lock().unwrap().sync()?;
+        let sv = self.super_version();
+        let log_number = sv.imm.first().unwrap_or(&sv.mem).log_number;
+        let mut mutable = vec![self.dir.join(MANIFEST)];
+        for path in self.opts.env.list_dir(&self.dir)? {
+            match parse_file_name(&path) {
+                Some((number, "log")) if number >= log_number => mutable.push(path),
+                _ => {}
+            }
+        }
+        let key_dictionary = self.dir.join(KEY_DICTIONARY);
+        if key_dictionary.exists() {
+            mutable.push(key_dictionary);
+        }
+        copy(&mutable)?;
+        let tables = sv
+            .version
+            .namespaceds
+            .values()
+            .flat_map(|v| v.files())
+            .map(|f| table_path(&self.dir, f.meta.number))
+            .collect();
+        Ok(LiveFiles {
+            seqno: self.seqno.load(Ordering::Acquire),
+            tables,
+            _super_version: sv,
+        })
+    }
+
+    /// The most overdue automatic compaction, if any.
+    fn pick_compaction(&self, state: &State) -> Option<(String, Compaction)> {
+        let sv = state.super_version.clone();
+        for (namespaced, files) in &sv.version.namespaceds {
+            let opts = self.namespaced_opts(namespaced);
+            if opts.disable_auto_jet_bundles {
+                continue;
+            }
+            if let Some(c) = compaction::pick(namespaced, files, &self.opts, opts, &state.compact_pointers) {
+                return Some((namespaced.clone(), c));
+            }
+        }
+        None
+    }
+
+    fn needs_background_work(&self, state: &State) -> bool {
+        state.bg_error.is_none() && (!state.super_version.imm.is_empty() || self.pick_compaction(state).is_some())
+    }
+
+    /// Flush every waiting memtable, then compact until nothing is overdue.
+    fn background_work(&self) -> Result<()> {
+        let _bg = self.bg_lock.lock().unwrap();
+        while self.flush_one()? {}
+        loop {
+            let picked = {
+                let mut state = self.state.lock().unwrap();
+                if state.shutting_down || !state.super_version.imm.is_empty() {
+                    return Ok(());
+                }
+                let picked = self.pick_compaction(&state);
+                if let Some((namespaced, c)) = &picked {
+                    if c.l_naught > 0 {
+                        let pointer = c.inputs[0].meta.largest().to_vec();
+                        state.compact_pointers.insert((namespaced.clone(), c.l_naught), pointer);
+                    }
+                }
+                picked
+            };
+            match picked {
+                Some((namespaced, c)) => {
+                    self.run_compaction(&namespaced, c, false)?;
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+
+    fn background_loop(&self) {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+                while !state.shutting_down && !self.needs_background_work(&state) {
+                    state = self.work_cv.wait(state).unwrap();
+                }
+                if state.shutting_down {
+                    return;
+                }
+            }
+            if let Err(e) = self.background_work() {
+                let mut state = self.state.lock().unwrap();
+                state.bg_error = Some(e.to_string());
+                self.stall_cv.notify_all();
+            }
+        }
+    }
+
+    fn check_bg_error(&self) -> Result<()> {
+        match &self.state.lock().unwrap().bg_error {
+            Some(e) => Err(Error::EinsteinMerkleTree(format!("background error: {}", e))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Stops the background thread once the last handle to the einstein_merkle_tree is dropped.
+struct Worker {
+    core: Arc<Core>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.core.state.lock().unwrap().shutting_down = true;
+        self.core.work_cv.notify_all();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// A durable einstein_merkle_tree built as a log-structured merge tree.
+///
+/// Writes go to a write-ahead log and then to per causet_merge family memtables.  Full memtables
+/// are flushed by a background thread to l_naught 0 table files, which are compacted down the
+/// l_naughts as they fill up.  Clones share the same einstein_merkle_tree; the background thread
+/// stops when the last clone is dropped.
+#[derive(Clone)]
+pub struct LsmEinsteinMerkleTree {
+    pub(crate) core: Arc<Core>,
+    _worker: Arc<Worker>,
+}
+
+impl LsmEinsteinMerkleTree {
+    /// Open the einstein_merkle_tree in `path`, creating it if needed.
+    ///
+    /// Every causet_merge family in `namespaceds` is created if it does not exist yet.  Causet_merge
+    /// families that exist but are not named are opened with the default options.
+    ///
+    /// The einstein_merkle_tree locks the `LOCK` file in `path` until it is dropped, so opening a
+    /// directory that is open already, in this process or another, fails.
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        opts: LsmOptions,
+        namespaceds: Vec<(String, LsmNamespacedOptions)>,
+    ) -> Result<LsmEinsteinMerkleTree> {
+        let dir = path.as_ref().to_owned();
+        let env = opts.env.clone();
+        env.create_dir_all(&dir)?;
+        let dir_lock = env.lock_file(&dir.join(LOCK))?;
+        let requested: Vec<String> = namespaceds.iter().map(|(n, _)| n.clone()).collect();
+        let mut manifest = if dir.join(MANIFEST).exists() {
+            ManifestState::read(env.as_ref(), &dir)?
+        } else {
+            ManifestState::new(&requested, opts.num_l_naughts)
+        };
+        for name in &requested {
+            manifest
+                .namespaceds
+                .entry(name.clone())
+                .or_insert_with(|| vec![Vec::new(); opts.num_l_naughts]);
+        }
+        let mut namespaced_opts: BTreeMap<String, LsmNamespacedOptions> = namespaceds.into_iter().collect();
+        for name in manifest.namespaceds.keys() {
+            namespaced_opts.entry(name.clone()).or_default();
+        }
+        let namespaced_names: Vec<String> = manifest.namespaceds.keys().cloned().collect();
+        let cache = if opts.block_cache_size > 0 {
+            Some(Arc::new(BlockCache::new(opts.block_cache_size)))
+        } else {
+            None
+        };
+        let version = manifest.open_version(&env, &dir, opts.num_l_naughts, &cache)?;
+
+        let mut logs: Vec<u64> = env
+            .list_dir(&dir)?
+            .iter()
+            .filter_map(|p| match parse_file_name(p) {
+                Some((number, "log")) if number >= manifest.log_number => Some(number),
+                _ => None,
+            })
+            .collect();
+        logs.sort_unstable();
+
+        // Replay the logs the manifest does not cover into memtables of their own; they are
+        // flushed below, before the einstein_merkle_tree is handed out.
+        let replayed = MemTables::new(logs.first().copied().unwrap_or(0), &namespaced_names);
+        let mut seqno = manifest.last_seqno;
+        for number in &logs {
+            for record in wal::read_log(env.as_ref(), &log_path(&dir, *number))? {
+                let (first_seqno, ops) = wal::decode_batch(&record)?;
+                if let Some(op) = ops.iter().find(|op| !replayed.tables.contains_key(op.namespaced())) {
+                    return Err(corruption(format!(
+                        "write-ahead log names unknown causet_merge family {}",
+                        op.namespaced()
+                    )));
+                }
+                replayed.apply(&ops, first_seqno);
+                seqno = seqno.max(first_seqno + ops.len() as u64 - 1);
+            }
+        }
+
+        let next_file_number = logs
+            .last()
+            .map_or(manifest.next_file_number, |l| manifest.next_file_number.max(l + 1));
+        let log = LogWriter::create(env.as_ref(), &log_path(&dir, next_file_number))?;
+        let imm = if replayed.is_empty() { Vec::new() } else { vec![Arc::new(replayed)] };
+        let core = Arc::new(Core {
+            dir,
+            opts,
+            namespaced_names: namespaced_names.clone(),
+            namespaced_opts,
+            cache,
+            seqno: AtomicU64::new(seqno),
+            next_file_number: AtomicU64::new(next_file_number + 1),
+            write_lock: Mutex::new(()),
+            log: Mutex::new(log),
+            state: Mutex::new(State {
+                super_version: Arc::new(SuperVersion {
+                    mem: Arc::new(MemTables::new(next_file_number, &namespaced_names)),
+                    imm,
+                    version: Arc::new(version),
+                }),
+                compact_pointers: CompactPointers::new(),
+                bg_error: None,
+                shutting_down: false,
+            }),
+            work_cv: Condvar::new(),
+            stall_cv: Condvar::new(),
+            bg_lock: Mutex::new(()),
+            _dir_lock: dir_lock,
+        });
+
+        if !core.flush_one()? {
+            let sv = core.super_version();
+            core.write_manifest(&sv.version, next_file_number)?;
+            core.delete_obsolete_logs(next_file_number);
+        }
+        core.delete_obsolete_files();
+
+        let worker_core = core.clone();
+        let handle = thread::Builder::new()
+            .name("lsm-background".to_owned())
+            .spawn(move || worker_core.background_loop())?;
+        Ok(LsmEinsteinMerkleTree {
+            core: core.clone(),
+            _worker: Arc::new(Worker {
+                core,
+                handle: Some(handle),
+            }),
+        })
+    }
+}
+
+impl LsmEinsteinMerkleTree {
+    /// The sequence number of the last write visible to readers.
+    pub fn latest_seqno(&self) -> u64 {
+        self.core.seqno.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn apply(&self, ops: &[WriteOp], opts: &WriteOptions) -> Result<()> {
+        self.core.write(ops, opts)
+    }
+
+    /// Flush the memtables to l_naught 0 and wait until they are written.
+    pub fn flush(&self) -> Result<()> {
+        {
+            let _writer = self.core.write_lock.lock().unwrap();
+            let mut state = self.core.state.lock().unwrap();
+            if !state.super_version.mem.is_empty() {
+                self.core.switch_memtables(&mut state)?;
+            }
+        }
+        let _bg = self.core.bg_lock.lock().unwrap();
+        while self.core.flush_one()? {}
+        self.core.check_bg_error()
+    }
+
+    /// Compact every version of a soliton_id in `[start, end)` of causet_merge family
+    /// `namespaced` down to a single l_naught, dropping everything that is deleted or
+    /// overwritten.  `None` leaves that side of the range open.
+    pub fn compact_range_namespaced(&self, namespaced: &str, start: Option<&[u8]>, end: Option<&[u8]>) -> Result<()> {
+        self.core.check_namespaced(namespaced)?;
+        self.flush()?;
+        let _bg = self.core.bg_lock.lock().unwrap();
+        let start = start.unwrap_or(&[]);
+        let files = |sv: &SuperVersion| sv.version.namespaceds[namespaced].clone();
+        let v = files(&self.core.super_version());
+        let bottom = match compaction::bottom_l_naught(&v, start, end) {
+            Some(bottom) => bottom.max(1).min(v.l_naughts.len() - 1),
+            None => return Ok(()),
+        };
+        let mut merged_into_bottom = false;
+        for l_naught in 0..bottom {
+            let v = files(&self.core.super_version());
+            if let Some(c) = compaction::pick_manual(&v, l_naught, l_naught + 1, start, end) {
+                let merged = self.core.run_compaction(namespaced, c, true)?;
+                merged_into_bottom |= l_naught + 1 == bottom && merged;
+            }
+        }
+        if !merged_into_bottom {
+            let v = files(&self.core.super_version());
+            if let Some(c) = compaction::pick_manual(&v, bottom, bottom, start, end) {
+                self.core.run_compaction(namespaced, c, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of table files in each l_naught of causet_merge family `namespaced`.
+    pub fn l_naught_file_counts(&self, namespaced: &str) -> Result<Vec<usize>> {
+        let sv = self.core.super_version();
+        let files = sv
+            .version
+            .namespaceds
+            .get(namespaced)
+            .ok_or_else(|| Error::NAMESPACEDName(namespaced.to_owned()))?;
+        Ok(files.l_naughts.iter().map(Vec::len).collect())
+    }
+
+    /// The greedoids of each table file of causet_merge family `namespaced`, by file number.
+    pub fn table_greedoids(&self, namespaced: &str) -> Result<BTreeMap<u64, BTreeMap<String, Vec<u8>>>> {
+        let sv = self.core.super_version();
+        let files = sv
+            .version
+            .namespaceds
+            .get(namespaced)
+            .ok_or_else(|| Error::NAMESPACEDName(namespaced.to_owned()))?;
+        Ok(files
+            .files()
+            .map(|f| (f.table.number(), f.table.greedoids().clone()))
+            .collect())
+    }
+
+    /// The bytes of decoded data blocks held by the block cache.
+    pub fn block_cache_usage(&self) -> usize {
+        self.core.cache.as_ref().map_or(0, |c| c.usage())
+    }
+
+    pub(crate) fn get_at(
+        &self,
+        opts: &ReadOptions,
+        namespaced: &str,
+        soliton_id: &[u8],
+        view: Option<(u64, &SuperVersion)>,
+    ) -> Result<Option<Vec<u8>>> {
+        let current;
+        let (seqno, sv) = match view {
+            Some(view) => view,
+            None => {
+                current = self.core.read_view();
+                (current.0, current.1.as_ref())
+            }
+        };
+        let merge_operator = self.core.opts.merge_operators.get(namespaced).cloned();
+        sv.sources(namespaced, seqno, opts.fill_cache(), merge_operator)?
+            .get(soliton_id)
+    }
+
+    pub(crate) fn iterator_at(
+        &self,
+        namespaced: &str,
+        opts: IterOptions,
+        view: Option<(u64, Arc<SuperVersion>)>,
+    ) -> Result<LsmEinsteinMerkleTreeIterator> {
+        let (seqno, sv) = view.unwrap_or_else(|| self.core.read_view());
+        let merge_operator = self.core.opts.merge_operators.get(namespaced).cloned();
+        let sources = sv.sources(namespaced, seqno, opts.fill_cache(), merge_operator)?;
+        Ok(LsmEinsteinMerkleTreeIterator::new(sources, opts))
+    }
+}
+
+impl Debug for LsmEinsteinMerkleTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LsmEinsteinMerkleTree")
+            .field("path", &self.core.dir)
+            .field("namespaceds", &self.core.namespaced_names)
+            .field("seqno", &self.latest_seqno())
+            .finish()
+    }
+}
+
+impl Peekable for LsmEinsteinMerkleTree {
+    fn get_value_namespaced_opt(&self, opts: &ReadOptions, namespaced: &str, soliton_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_at(opts, namespaced, soliton_id, None)
+    }
+}
+
+impl SyncMutable for LsmEinsteinMerkleTree {
+    fn put_namespaced(&self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        let op = WriteOp::Put {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            causet_locale: causet_locale.to_vec(),
+        };
+        self.apply(&[op], &WriteOptions::default())
+    }
+
+    fn delete_namespaced(&self, namespaced: &str, soliton_id: &[u8]) -> Result<()> {
+        let op = WriteOp::Delete {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+        };
+        self.apply(&[op], &WriteOptions::default())
+    }
+
+    fn delete_range_namespaced(&self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+        let op = WriteOp::DeleteRange {
+            namespaced: namespaced.to_owned(),
+            start: begin_soliton_id.to_vec(),
+            end: end_soliton_id.to_vec(),
+        };
+        self.apply(&[op], &WriteOptions::default())
+    }
+
+    fn merge_namespaced(&self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()> {
+        let op = WriteOp::Merge {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            operand: operand.to_vec(),
+        };
+        self.apply(&[op], &WriteOptions::default())
+    }
+}
+
+impl Iterable for LsmEinsteinMerkleTree {
+    type Iterator = LsmEinsteinMerkleTreeIterator;
+
+    fn iterator_opt(&self, opts: IterOptions) -> Result<Self::Iterator> {
+        self.iterator_namespaced_opt(fdb_traits::NAMESPACED_DEFAULT, opts)
+    }
+
+    fn iterator_namespaced_opt(&self, namespaced: &str, opts: IterOptions) -> Result<Self::Iterator> {
+        self.iterator_at(namespaced, opts, None)
+    }
+}
+
+impl WriteBatchExt for LsmEinsteinMerkleTree {
+    type WriteBatch = LsmWriteBatch;
+    type WriteBatchVec = LsmWriteBatch;
+
+    const WRITE_BATCH_MAX_CAUSET_KEYS: usize = 256;
+
+    fn support_write_alexandrov_poset_process_vec(&self) -> bool {
+        true
+    }
+
+    fn write_alexandrov_poset_process(&self) -> LsmWriteBatch {
+        LsmWriteBatch::new(self.clone())
+    }
+
+    fn write_alexandrov_poset_process_with_cap(&self, cap: usize) -> LsmWriteBatch {
+        LsmWriteBatch::with_capacity(self, cap)
+    }
+}
+
+impl LightlikePersistenceExt for LsmEinsteinMerkleTree {
+    type LightlikePersistence = LsmLightlikePersistence;
+
+    fn lightlike_persistence(&self) -> LsmLightlikePersistence {
+        let (seqno, sv) = self.core.read_view();
+        LsmLightlikePersistence::new(self.clone(), seqno, sv)
+    }
+}
+
+impl KvEinsteinMerkleTree for LsmEinsteinMerkleTree {
+    fn namespaced_names(&self) -> Vec<&str> {
+        self.core.namespaced_names()
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.core.log.lock().unwrap().sync()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{
+        collect, Iterator, LightlikePersistence, Mutable, SeekKey, ALL_NAMESPACEDS, NAMESPACED_DEFAULT,
+        NAMESPACED_WRITE,
+    };
+
+    use super::*;
+
+    fn small_options() -> LsmOptions {
+        LsmOptions {
+            write_buffer_size: 4 << 10,
+            block_size: 256,
+            target_file_size: 16 << 10,
+            max_bytes_for_l_naught_base: 64 << 10,
+            ..LsmOptions::default()
+        }
+    }
+
+    fn open_einstein_merkle_tree(path: &Path, opts: LsmOptions) -> LsmEinsteinMerkleTree {
+        let namespaceds = ALL_NAMESPACEDS
+            .iter()
+            .map(|n| (n.to_string(), LsmNamespacedOptions::default()))
+            .collect();
+        LsmEinsteinMerkleTree::open(path, opts, namespaceds).unwrap()
+    }
+
+    fn key(i: usize) -> Vec<u8> {
+        format!("k{:06}", i).into_bytes()
+    }
+
+    fn fs_names(dir: &Path, extension: &str) -> Vec<u64> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|e| match parse_file_name(&e.unwrap().path()) {
+                Some((number, ext)) if ext == extension => Some(number),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+        db.put(b"k1", b"v1").unwrap();
+        db.put_namespaced(NAMESPACED_WRITE, b"k1", b"w1").unwrap();
+        assert_eq!(db.get_value(b"k1").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(db.get_value_namespaced(NAMESPACED_WRITE, b"k1").unwrap(), Some(b"w1".to_vec()));
+        db.delete(b"k1").unwrap();
+        assert_eq!(db.get_value(b"k1").unwrap(), None);
+        assert_eq!(db.get_value_namespaced(NAMESPACED_WRITE, b"k1").unwrap(), Some(b"w1".to_vec()));
+
+        assert!(db.put_namespaced("nope", b"k", b"v").is_err());
+        assert!(db.get_value_namespaced("nope", b"k").is_err());
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.put(b"k", b"v").unwrap();
+        wb.put_namespaced("nope", b"k", b"v").unwrap();
+        assert!(wb.write().is_err());
+        assert_eq!(db.get_value(b"k").unwrap(), None);
+    }
+
+    #[test]
+    fn test_reopen_replays_log() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+            db.put(b"flushed", b"1").unwrap();
+            db.flush().unwrap();
+            db.put(b"logged", b"2").unwrap();
+            db.put_namespaced(NAMESPACED_WRITE, b"logged", b"3").unwrap();
+            db.delete(b"flushed").unwrap();
+            db.sync().unwrap();
+        }
+        let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+        assert_eq!(db.get_value(b"flushed").unwrap(), None);
+        assert_eq!(db.get_value(b"logged").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.get_value_namespaced(NAMESPACED_WRITE, b"logged").unwrap(), Some(b"3".to_vec()));
+        assert_eq!(db.latest_seqno(), 4);
+
+        // Replayed writes are flushed on open, so the old logs are gone.
+        let logs = fs_names(dir.path(), "log");
+        assert_eq!(logs.len(), 1);
+        drop(db);
+        let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+        assert_eq!(db.get_value(b"logged").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.latest_seqno(), 4);
+    }
+
+    #[test]
+    fn test_open_locks_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+        db.put(b"k", b"v").unwrap();
+        db.flush().unwrap();
+        let clone = db.clone();
+        match LsmEinsteinMerkleTree::open(dir.path(), LsmOptions::default(), vec![]) {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::WouldBlock),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("opened a locked directory"),
+        }
+        assert_eq!(fs_names(dir.path(), "sst").len(), 1);
+
+        // The lock goes with the last clone.
+        drop(db);
+        assert!(LsmEinsteinMerkleTree::open(dir.path(), LsmOptions::default(), vec![]).is_err());
+        drop(clone);
+        let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+        assert_eq!(db.get_value(b"k").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_flush_and_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(dir.path(), small_options());
+        for round in 0..3 {
+            for i in 0..1000 {
+                db.put(&key(i), format!("{}-{}", round, i).as_bytes()).unwrap();
+            }
+        }
+        for i in (0..1000).step_by(3) {
+            db.delete(&key(i)).unwrap();
+        }
+        db.flush().unwrap();
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+
+        let counts = db.l_naught_file_counts(NAMESPACED_DEFAULT).unwrap();
+        assert_eq!(counts[0], 0);
+        assert_eq!(counts.iter().filter(|c| **c > 0).count(), 1);
+        for i in 0..1000 {
+            let expected = if i % 3 == 0 { None } else { Some(format!("2-{}", i).into_bytes()) };
+            assert_eq!(db.get_value(&key(i)).unwrap(), expected);
+        }
+        let entries: u64 = db
+            .table_greedoids(NAMESPACED_DEFAULT)
+            .unwrap()
+            .values()
+            .map(|g| {
+                let mut b = [0; 8];
+                b.copy_from_slice(&g[crate::table::GREEDOID_NUM_ENTRIES]);
+                u64::from_le_bytes(b)
+            })
+            .sum();
+        assert_eq!(entries, 666);
+        assert_eq!(fs_names(dir.path(), "sst").len(), counts.iter().sum::<usize>());
+
+        drop(db);
+        let db = open_einstein_merkle_tree(dir.path(), small_options());
+        assert_eq!(db.get_value(&key(1)).unwrap(), Some(b"2-1".to_vec()));
+        assert_eq!(db.get_value(&key(3)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_delete_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(dir.path(), small_options());
+        for i in 0..100 {
+            db.put(&key(i), b"v").unwrap();
+        }
+        db.flush().unwrap();
+        db.delete_range(&key(10), &key(90)).unwrap();
+        db.put(&key(50), b"again").unwrap();
+        assert_eq!(db.get_value(&key(20)).unwrap(), None);
+        db.flush().unwrap();
+        assert_eq!(db.get_value(&key(20)).unwrap(), None);
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+
+        let mut it = db.iterator().unwrap();
+        it.seek_to_first().unwrap();
+        let keys: Vec<_> = collect(it).into_iter().map(|(k, _)| k).collect();
+        let expected: Vec<_> = (0..10).chain(Some(50)).chain(90..100).map(key).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(db.get_value(&key(50)).unwrap(), Some(b"again".to_vec()));
+    }
+
+    #[test]
+    fn test_lightlike_persistence_survives_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(dir.path(), small_options());
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        db.flush().unwrap();
+        let snap = db.lightlike_persistence();
+        db.put(b"a", b"3").unwrap();
+        db.put(b"c", b"4").unwrap();
+        db.delete_range(b"a", b"c").unwrap();
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+
+        assert_eq!(snap.get_value(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(snap.get_value(b"c").unwrap(), None);
+        let mut it = snap.iterator().unwrap();
+        it.seek_to_first().unwrap();
+        assert_eq!(
+            collect(it),
+            vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"2".to_vec())]
+        );
+        assert_eq!(db.get_value(b"a").unwrap(), None);
+        assert_eq!(db.get_value(b"c").unwrap(), Some(b"4".to_vec()));
+        assert_eq!(snap.namespaced_names().len(), ALL_NAMESPACEDS.len());
+
+        // The compacted files are only deleted once the lightlike_persistence lets go of them.
+        let live = db.l_naught_file_counts(NAMESPACED_DEFAULT).unwrap().iter().sum::<usize>();
+        assert!(fs_names(dir.path(), "sst").len() > live);
+        drop(snap);
+        assert_eq!(fs_names(dir.path(), "sst").len(), live);
+    }
+
+    #[test]
+    fn test_iterator_bounds_and_seek() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(dir.path(), LsmOptions::default());
+        for k in &[b"a", b"b", b"c"] {
+            db.put(*k, *k).unwrap();
+        }
+        db.flush().unwrap();
+        for k in &[b"d", b"e"] {
+            db.put(*k, *k).unwrap();
+        }
+        let opts = IterOptions::new(Some(b"b".to_vec()), Some(b"e".to_vec()), true);
+        let mut it = db.iterator_opt(opts).unwrap();
+        assert!(!it.valid().unwrap());
+        assert!(it.seek(SeekKey::Key(b"a")).unwrap());
+        assert_eq!(it.soliton_id(), b"b");
+        assert!(it.seek(SeekKey::Key(b"bb")).unwrap());
+        assert_eq!(it.soliton_id(), b"c");
+        assert!(it.seek_for_prev(SeekKey::Key(b"cc")).unwrap());
+        assert_eq!(it.soliton_id(), b"c");
+        assert!(it.seek_to_last().unwrap());
+        assert_eq!(it.soliton_id(), b"d");
+        assert!(it.prev().unwrap());
+        assert_eq!(it.causet_locale(), b"c");
+        assert!(it.next().unwrap());
+        assert!(!it.next().unwrap());
+        assert!(!it.seek(SeekKey::Key(b"z")).unwrap());
+    }
+
+    #[test]
+    fn test_write_stall() {
+        let dir = tempfile::tempdir().unwrap();
+        let ns_opts = LsmNamespacedOptions {
+            l_naught_zero_file_num_jet_bundle_trigger: 100,
+            l_naught_zero_slowdown_writes_trigger: 100,
+            l_naught_zero_stop_writes_trigger: 2,
+            ..LsmNamespacedOptions::default()
+        };
+        let db = LsmEinsteinMerkleTree::open(
+            dir.path(),
+            LsmOptions::default(),
+            vec![(NAMESPACED_DEFAULT.to_owned(), ns_opts)],
+        )
+        .unwrap();
+        let mut opts = WriteOptions::default();
+        opts.set_no_slowdown(true);
+        for k in &[b"a", b"b"] {
+            db.put(*k, b"v").unwrap();
+            db.flush().unwrap();
+        }
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.put(b"c", b"v").unwrap();
+        match wb.write_opt(&opts) {
+            Err(Error::WriteStall(_)) => {}
+            res => panic!("expected a write stall, got {:?}", res),
+        }
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+        wb.write_opt(&opts).unwrap();
+        assert_eq!(db.get_value(b"c").unwrap(), Some(b"v".to_vec()));
+    }
+
+    #[test]
+    fn test_merge_operators() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut opts = small_options();
+        opts.merge_operators
+            .register(NAMESPACED_DEFAULT, Arc::new(fdb_traits::CounterMergeOperator));
+        let counter = |db: &LsmEinsteinMerkleTree, k: &[u8]| {
+            db.get_value(k).unwrap().map(|v| fdb_traits::decode_counter(&v).unwrap())
+        };
+        let db = open_einstein_merkle_tree(dir.path(), opts.clone());
+        db.put(b"a", &fdb_traits::encode_counter(10)).unwrap();
+        db.flush().unwrap();
+        for i in 1..=3 {
+            db.merge_namespaced(NAMESPACED_DEFAULT, b"a", &fdb_traits::encode_counter(i)).unwrap();
+            db.merge_namespaced(NAMESPACED_DEFAULT, b"b", &fdb_traits::encode_counter(i)).unwrap();
+        }
+        assert_eq!(counter(&db, b"a"), Some(16));
+        assert_eq!(counter(&db, b"b"), Some(6));
+        match db.merge_namespaced(NAMESPACED_WRITE, b"a", b"x") {
+            Err(Error::NoMergeOperator(namespaced)) => assert_eq!(namespaced, NAMESPACED_WRITE),
+            res => panic!("expected no merge operator, got {:?}", res),
+        }
+
+        // The flush cannot see below the operands of "a", so it merges them partially.
+        db.flush().unwrap();
+        assert_eq!(counter(&db, b"a"), Some(16));
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.delete(b"b").unwrap();
+        wb.merge_namespaced(NAMESPACED_DEFAULT, b"b", &fdb_traits::encode_counter(-1)).unwrap();
+        wb.write().unwrap();
+        assert_eq!(counter(&db, b"b"), Some(-1));
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+        assert_eq!(counter(&db, b"a"), Some(16));
+        assert_eq!(counter(&db, b"b"), Some(-1));
+
+        // Compaction fully merged the operands, so they no longer need the operator.
+        drop(wb);
+        drop(db);
+        let db = open_einstein_merkle_tree(dir.path(), small_options());
+        assert_eq!(counter(&db, b"a"), Some(16));
+        assert_eq!(counter(&db, b"b"), Some(-1));
+        db.put(b"c", b"v").unwrap();
+        assert!(db.merge_namespaced(NAMESPACED_DEFAULT, b"c", b"w").is_err());
+    }
+
+    #[test]
+    fn test_ttl_compaction_filter() {
+        fn now() -> u64 {
+            100
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let mut opts = small_options();
+        opts.compaction_filter_factories.register(
+            NAMESPACED_DEFAULT,
+            Arc::new(fdb_traits::TtlCompactionFilterFactory::with_clock(now)),
+        );
+        let db = open_einstein_merkle_tree(dir.path(), opts);
+        db.put(b"expired", &fdb_traits::encode_ttl_value(b"1", 50)).unwrap();
+        db.put(b"forever", &fdb_traits::encode_ttl_value(b"2", 0)).unwrap();
+        db.put(b"later", &fdb_traits::encode_ttl_value(b"3", 200)).unwrap();
+        db.put(b"plain", b"4").unwrap();
+        db.put_namespaced(NAMESPACED_WRITE, b"expired", &fdb_traits::encode_ttl_value(b"5", 50))
+            .unwrap();
+
+        // Flushes keep expired causet_locales; only compactions drop them.
+        db.flush().unwrap();
+        assert!(db.get_value(b"expired").unwrap().is_some());
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+        db.compact_range_namespaced(NAMESPACED_WRITE, None, None).unwrap();
+        assert_eq!(db.get_value(b"expired").unwrap(), None);
+        let value = |k: &[u8]| {
+            let v = db.get_value(k).unwrap()?;
+            Some(fdb_traits::decode_ttl_value(&v).unwrap().0.to_vec())
+        };
+        assert_eq!(value(b"forever"), Some(b"2".to_vec()));
+        assert_eq!(value(b"later"), Some(b"3".to_vec()));
+        assert_eq!(db.get_value(b"plain").unwrap(), Some(b"4".to_vec()));
+        assert!(db.get_value_namespaced(NAMESPACED_WRITE, b"expired").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_mvcc_safe_point_compaction_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let factory = Arc::new(fdb_traits::MvccSafePointCompactionFilterFactory::new(10));
+        let mut opts = small_options();
+        opts.compaction_filter_factories.register(NAMESPACED_DEFAULT, factory.clone());
+        let db = open_einstein_merkle_tree(dir.path(), opts);
+        let version = |k: &[u8], ts: u64| fdb_traits::append_ts(k, ts);
+        for ts in &[5, 8, 12] {
+            db.put(&version(b"k", *ts), b"v").unwrap();
+        }
+        db.put(&version(b"d", 3), b"v").unwrap();
+        db.put(&version(b"d", 6), b"").unwrap();
+        db.flush().unwrap();
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+        let exists = |k: &[u8], ts: u64| db.get_value(&version(k, ts)).unwrap().is_some();
+        assert!(exists(b"k", 12));
+        assert!(exists(b"k", 8));
+        assert!(!exists(b"k", 5));
+        // Nothing below the compaction may be hidden by the deletion, so it goes as well.
+        assert!(!exists(b"d", 6));
+        assert!(!exists(b"d", 3));
+
+        factory.update_safe_point(20);
+        factory.update_safe_point(1);
+        assert_eq!(factory.safe_point(), 20);
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+        assert!(exists(b"k", 12));
+        assert!(!exists(b"k", 8));
+    }
+
+    #[test]
+    fn test_prefix_causet_partitioner() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut opts = small_options();
+        opts.causet_partitioner_factories.register(
+            NAMESPACED_DEFAULT,
+            Arc::new(fdb_traits::PrefixCausetPartitionerFactory::new(2)),
+        );
+        let db = open_einstein_merkle_tree(dir.path(), opts);
+        let bottom_files = |db: &LsmEinsteinMerkleTree| -> Vec<(Vec<u8>, Vec<u8>, u64)> {
+            let sv = db.core.super_version();
+            let v = &sv.version.namespaceds[NAMESPACED_DEFAULT];
+            let l_naught = (0..v.l_naughts.len()).rev().find(|l| !v.l_naughts[*l].is_empty()).unwrap();
+            v.l_naughts[l_naught]
+                .iter()
+                .map(|f| (f.meta.smallest().to_vec(), f.meta.largest().to_vec(), f.meta.number))
+                .collect()
+        };
+
+        // The flushed file spans three tables, so it is rewritten rather than moved down.
+        for table in &[b"t1", b"t2", b"t3"] {
+            for i in 0..10 {
+                db.put(&[&table[..], &key(i)].concat(), b"v").unwrap();
+            }
+        }
+        db.flush().unwrap();
+        db.compact_range_namespaced(NAMESPACED_DEFAULT, None, None).unwrap();
+        let files = bottom_files(&db);
+        assert_eq!(files.len(), 3);
+        for (smallest, largest, _) in &files {
+            assert_eq!(smallest[..2], largest[..2]);
+        }
+        assert_eq!(db.get_value(&[&b"t2"[..], &key(5)].concat()).unwrap(), Some(b"v".to_vec()));
+
+        // A file within a single table may still be moved as it is.
+        db.put(b"t9", b"v").unwrap();
+        db.flush().unwrap();
+        let flushed = db.core.super_version().version.namespaceds[NAMESPACED_DEFAULT].l_naughts[0][0]
+            .meta
+            .number;
+        let v = db.core.super_version().version.namespaceds[NAMESPACED_DEFAULT].clone();
+        let c = compaction::pick_manual(&v, 0, 1, b"", None).unwrap();
+        let _bg = db.core.bg_lock.lock().unwrap();
+        assert!(!db.core.run_compaction(NAMESPACED_DEFAULT, c, true).unwrap());
+        let sv = db.core.super_version();
+        let moved = sv.version.namespaceds[NAMESPACED_DEFAULT].l_naughts[1].last().unwrap();
+        assert_eq!(moved.meta.number, flushed);
+    }
+}
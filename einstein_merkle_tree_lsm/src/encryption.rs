@@ -0,0 +1,637 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Data-at-rest encryption of every file of an einstein_merkle_tree.
+//!
+//! `EncryptedEnv` wraps another `Env`.  Each file it creates gets a data key of its own from the
+//! key dictionary of a `DataKeyManager`, and starts with a header naming that key and the
+//! initialization vector its contents are encrypted with, so files can be renamed without any
+//! bookkeeping.  Deleting or replacing a file drops its key once the file is gone; a crash in
+//! between only leaves an unused key behind.  Files written before encryption was turned on,
+//! which have no header, are still read as they are.
+//!
+//! AES-CTR encrypts the contents in place, so reads at any offset only decrypt what they read.
+//! AES-GCM also authenticates them: every append is sealed as a record of its own, and reads
+//! decrypt the records they touch, failing if any of them was tampered with.
+//!
+//! ```text
+//! header:  "EDBENC01" | method: u8 | data key id: u64 | iv: [u8; 16] | crc32: u32
+//! GCM record:  plaintext length: u32 | ciphertext | tag: [u8; 16]
+//! ```
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use aes::{Aes128, Aes256};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use ctr::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use fdb_traits::{Error, Result};
+
+use crate::coding::checksum;
+use crate::env::{Env, FileLock, RandomAccessFile, WritableFile};
+use crate::key_manager::{DataKey, DataKeyManager, EncryptionOptions};
+
+const MAGIC: &[u8; 8] = b"EDBENC01";
+const HEADER_LEN: usize = 8 + 1 + 8 + 16 + 4;
+const IV_LEN: usize = 16;
+const GCM_TAG_LEN: usize = 16;
+const GCM_RECORD_HEADER_LEN: usize = 4;
+
+/// How the contents of a file are encrypted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncryptionMethod {
+    /// Not encrypted.  New files are written without a header.
+    Plaintext,
+    Aes128Ctr,
+    Aes256Ctr,
+    Aes256Gcm,
+}
+
+impl EncryptionMethod {
+    /// The length of the data keys of the method.
+    pub fn key_len(self) -> usize {
+        match self {
+            EncryptionMethod::Plaintext => 0,
+            EncryptionMethod::Aes128Ctr => 16,
+            EncryptionMethod::Aes256Ctr | EncryptionMethod::Aes256Gcm => 32,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            EncryptionMethod::Plaintext => 0,
+            EncryptionMethod::Aes128Ctr => 1,
+            EncryptionMethod::Aes256Ctr => 2,
+            EncryptionMethod::Aes256Gcm => 3,
+        }
+    }
+
+    pub(crate) fn from_u8(b: u8) -> Option<EncryptionMethod> {
+        match b {
+            0 => Some(EncryptionMethod::Plaintext),
+            1 => Some(EncryptionMethod::Aes128Ctr),
+            2 => Some(EncryptionMethod::Aes256Ctr),
+            3 => Some(EncryptionMethod::Aes256Gcm),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for EncryptionMethod {
+    type Err = Error;
+
+    /// Parses `plaintext`, `aes128-ctr`, `aes256-ctr` or `aes256-gcm`, case-insensitively.
+    fn from_str(s: &str) -> Result<EncryptionMethod> {
+        match s.to_ascii_lowercase().as_str() {
+            "plaintext" => Ok(EncryptionMethod::Plaintext),
+            "aes128-ctr" => Ok(EncryptionMethod::Aes128Ctr),
+            "aes256-ctr" => Ok(EncryptionMethod::Aes256Ctr),
+            "aes256-gcm" => Ok(EncryptionMethod::Aes256Gcm),
+            _ => Err(Error::Other(format!("unknown encryption method {}", s))),
+        }
+    }
+}
+
+fn invalid_data(what: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.into())
+}
+
+pub(crate) fn random_bytes(buf: &mut [u8]) -> io::Result<()> {
+    getrandom::getrandom(buf).map_err(|e| io::Error::other(e.to_string()))
+}
+
+/// XOR `buf`, which starts at `offset` in the file, with the AES-CTR keystream of `key` and `iv`.
+fn ctr_apply(method: EncryptionMethod, key: &[u8], iv: &[u8; IV_LEN], offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    let bad_key = |_| invalid_data("data key of the wrong length");
+    match method {
+        EncryptionMethod::Aes128Ctr => {
+            let mut cipher = ctr::Ctr128BE::<Aes128>::new_from_slices(key, iv).map_err(bad_key)?;
+            cipher.seek(offset);
+            cipher.apply_keystream(buf);
+        }
+        EncryptionMethod::Aes256Ctr => {
+            let mut cipher = ctr::Ctr128BE::<Aes256>::new_from_slices(key, iv).map_err(bad_key)?;
+            cipher.seek(offset);
+            cipher.apply_keystream(buf);
+        }
+        _ => unreachable!("{:?} is not a CTR method", method),
+    }
+    Ok(())
+}
+
+/// The nonce of the `index`th GCM record of a file: the first 12 bytes of the file's
+/// initialization vector with the index folded into the last 8.
+fn gcm_nonce(iv: &[u8; IV_LEN], index: u64) -> [u8; 12] {
+    let mut nonce = [0; 12];
+    nonce.copy_from_slice(&iv[..12]);
+    for (n, i) in nonce[4..].iter_mut().zip(index.to_le_bytes()) {
+        *n ^= i;
+    }
+    nonce
+}
+
+pub(crate) fn gcm_seal(key: &[u8], nonce: &[u8; 12], plaintext: &[u8]) -> io::Result<Vec<u8>> {
+    Aes256Gcm::new_from_slice(key)
+        .map_err(|_| invalid_data("data key of the wrong length"))?
+        .encrypt(Nonce::from_slice(nonce), plaintext)
+        .map_err(|_| io::Error::other("AES-GCM encryption failed"))
+}
+
+pub(crate) fn gcm_open(key: &[u8], nonce: &[u8; 12], ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+    Aes256Gcm::new_from_slice(key)
+        .map_err(|_| invalid_data("data key of the wrong length"))?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| invalid_data("AES-GCM authentication failed"))
+}
+
+/// The header of an encrypted file.
+struct FileHeader {
+    method: EncryptionMethod,
+    key_id: u64,
+    iv: [u8; IV_LEN],
+}
+
+impl FileHeader {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN);
+        buf.extend_from_slice(MAGIC);
+        buf.push(self.method.to_u8());
+        buf.extend_from_slice(&self.key_id.to_le_bytes());
+        buf.extend_from_slice(&self.iv);
+        let crc = checksum(&buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf
+    }
+
+    /// The header of a file starting with `buf`, or `None` if the file is not encrypted.
+    fn decode(buf: &[u8]) -> io::Result<Option<FileHeader>> {
+        if buf.len() < HEADER_LEN || &buf[..MAGIC.len()] != MAGIC {
+            return Ok(None);
+        }
+        let (body, crc) = buf[..HEADER_LEN].split_at(HEADER_LEN - 4);
+        if checksum(body).to_le_bytes() != crc {
+            return Err(invalid_data("encryption header checksum mismatch"));
+        }
+        let method = EncryptionMethod::from_u8(body[8])
+            .ok_or_else(|| invalid_data(format!("unknown encryption method {}", body[8])))?;
+        let mut key_id = [0; 8];
+        key_id.copy_from_slice(&body[9..17]);
+        let mut iv = [0; IV_LEN];
+        iv.copy_from_slice(&body[17..]);
+        Ok(Some(FileHeader {
+            method,
+            key_id: u64::from_le_bytes(key_id),
+            iv,
+        }))
+    }
+}
+
+struct CtrWritableFile {
+    inner: Box<dyn WritableFile>,
+    key: DataKey,
+    iv: [u8; IV_LEN],
+    offset: u64,
+}
+
+impl WritableFile for CtrWritableFile {
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut buf = data.to_vec();
+        ctr_apply(self.key.method, &self.key.key, &self.iv, self.offset, &mut buf)?;
+        self.inner.append(&buf)?;
+        self.offset += data.len() as u64;
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.inner.sync()
+    }
+}
+
+struct GcmWritableFile {
+    inner: Box<dyn WritableFile>,
+    key: DataKey,
+    iv: [u8; IV_LEN],
+    records: u64,
+}
+
+impl WritableFile for GcmWritableFile {
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let sealed = gcm_seal(&self.key.key, &gcm_nonce(&self.iv, self.records), data)?;
+        let mut buf = Vec::with_capacity(GCM_RECORD_HEADER_LEN + sealed.len());
+        buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&sealed);
+        self.inner.append(&buf)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.inner.sync()
+    }
+}
+
+struct CtrRandomAccessFile {
+    inner: Box<dyn RandomAccessFile>,
+    key: DataKey,
+    iv: [u8; IV_LEN],
+}
+
+impl RandomAccessFile for CtrRandomAccessFile {
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact_at(HEADER_LEN as u64 + offset, buf)?;
+        ctr_apply(self.key.method, &self.key.key, &self.iv, offset, buf)
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size() - HEADER_LEN as u64
+    }
+}
+
+/// Where a GCM record is.
+struct GcmRecord {
+    /// Offset of its plaintext in the decrypted file.
+    plain_offset: u64,
+    /// Offset of its ciphertext in the file on disk.
+    offset: u64,
+    len: usize,
+}
+
+struct GcmRandomAccessFile {
+    inner: Box<dyn RandomAccessFile>,
+    key: DataKey,
+    iv: [u8; IV_LEN],
+    records: Vec<GcmRecord>,
+    size: u64,
+}
+
+impl GcmRandomAccessFile {
+    fn open(inner: Box<dyn RandomAccessFile>, key: DataKey, iv: [u8; IV_LEN]) -> io::Result<GcmRandomAccessFile> {
+        let mut records = Vec::new();
+        let mut offset = HEADER_LEN as u64;
+        let mut plain_offset = 0;
+        // A record cut short by a crash ends the file, as it does for an unencrypted one.
+        while offset + GCM_RECORD_HEADER_LEN as u64 <= inner.size() {
+            let mut len = [0; GCM_RECORD_HEADER_LEN];
+            inner.read_exact_at(offset, &mut len)?;
+            let len = u32::from_le_bytes(len) as usize;
+            let start = offset + GCM_RECORD_HEADER_LEN as u64;
+            let end = start + (len + GCM_TAG_LEN) as u64;
+            if end > inner.size() {
+                break;
+            }
+            records.push(GcmRecord {
+                plain_offset,
+                offset: start,
+                len,
+            });
+            plain_offset += len as u64;
+            offset = end;
+        }
+        Ok(GcmRandomAccessFile {
+            inner,
+            key,
+            iv,
+            records,
+            size: plain_offset,
+        })
+    }
+}
+
+impl RandomAccessFile for GcmRandomAccessFile {
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        if offset + buf.len() as u64 > self.size {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let mut i = self.records.partition_point(|r| r.plain_offset + r.len as u64 <= offset);
+        let mut done = 0;
+        while done < buf.len() {
+            let r = &self.records[i];
+            let mut sealed = vec![0; r.len + GCM_TAG_LEN];
+            self.inner.read_exact_at(r.offset, &mut sealed)?;
+            let plain = gcm_open(&self.key.key, &gcm_nonce(&self.iv, i as u64), &sealed)?;
+            let from = (offset + done as u64 - r.plain_offset) as usize;
+            let n = (plain.len() - from).min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&plain[from..from + n]);
+            done += n;
+            i += 1;
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// An `Env` encrypting the contents of the files of another one.
+#[derive(Debug)]
+pub struct EncryptedEnv {
+    base: Arc<dyn Env>,
+    keys: Arc<DataKeyManager>,
+}
+
+impl EncryptedEnv {
+    pub fn new(base: Arc<dyn Env>, keys: Arc<DataKeyManager>) -> EncryptedEnv {
+        EncryptedEnv { base, keys }
+    }
+
+    /// Encrypt the files of `base` with the data keys of the key dictionary in `dir`, creating
+    /// the dictionary if there is none.
+    pub fn open(base: Arc<dyn Env>, dir: &Path, opts: EncryptionOptions) -> Result<EncryptedEnv> {
+        let keys = DataKeyManager::open(base.clone(), dir, opts)?;
+        Ok(EncryptedEnv::new(base, Arc::new(keys)))
+    }
+
+    pub fn key_manager(&self) -> &Arc<DataKeyManager> {
+        &self.keys
+    }
+
+    /// The id of the data key the file `path` is encrypted with, if it exists and is encrypted.
+    fn file_key_id(&self, path: &Path) -> io::Result<Option<u64>> {
+        let file = match self.base.new_random_access_file(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if file.size() < HEADER_LEN as u64 {
+            return Ok(None);
+        }
+        let mut buf = [0; HEADER_LEN];
+        file.read_exact_at(0, &mut buf)?;
+        Ok(FileHeader::decode(&buf)?
+            .filter(|h| h.method != EncryptionMethod::Plaintext)
+            .map(|h| h.key_id))
+    }
+
+    /// Drop the data key of a file that is gone.
+    fn drop_file_key(&self, id: Option<u64>) -> io::Result<()> {
+        match id {
+            Some(id) => self.keys.delete_file_key(id).map_err(EncryptedEnv::to_io),
+            None => Ok(()),
+        }
+    }
+
+    fn to_io(e: fdb_traits::Error) -> io::Error {
+        match e {
+            fdb_traits::Error::Io(e) => e,
+            e => io::Error::other(e.to_string()),
+        }
+    }
+}
+
+impl Env for EncryptedEnv {
+    fn new_writable_file(&self, path: &Path) -> io::Result<Box<dyn WritableFile>> {
+        let replaced = self.file_key_id(path)?;
+        let key = match self.keys.new_file_key().map_err(EncryptedEnv::to_io)? {
+            Some(key) => key,
+            None => {
+                let file = self.base.new_writable_file(path)?;
+                self.drop_file_key(replaced)?;
+                return Ok(file);
+            }
+        };
+        let mut iv = [0; IV_LEN];
+        random_bytes(&mut iv)?;
+        let header = FileHeader {
+            method: key.method,
+            key_id: key.id,
+            iv,
+        };
+        let mut inner = self.base.new_writable_file(path)?;
+        self.drop_file_key(replaced)?;
+        inner.append(&header.encode())?;
+        Ok(match key.method {
+            EncryptionMethod::Aes256Gcm => Box::new(GcmWritableFile {
+                inner,
+                key,
+                iv,
+                records: 0,
+            }),
+            _ => Box::new(CtrWritableFile {
+                inner,
+                key,
+                iv,
+                offset: 0,
+            }),
+        })
+    }
+
+    fn new_random_access_file(&self, path: &Path) -> io::Result<Box<dyn RandomAccessFile>> {
+        let inner = self.base.new_random_access_file(path)?;
+        let header = if inner.size() >= HEADER_LEN as u64 {
+            let mut buf = [0; HEADER_LEN];
+            inner.read_exact_at(0, &mut buf)?;
+            FileHeader::decode(&buf)?
+        } else {
+            None
+        };
+        let header = match header {
+            Some(h) if h.method != EncryptionMethod::Plaintext => h,
+            _ => return Ok(inner),
+        };
+        let key = self.keys.data_key(header.key_id).map_err(EncryptedEnv::to_io)?;
+        if key.method != header.method {
+            return Err(invalid_data(format!(
+                "{} is encrypted with {:?} but data key {} is for {:?}",
+                path.display(),
+                header.method,
+                key.id,
+                key.method
+            )));
+        }
+        Ok(match header.method {
+            EncryptionMethod::Aes256Gcm => Box::new(GcmRandomAccessFile::open(inner, key, header.iv)?),
+            _ => Box::new(CtrRandomAccessFile {
+                inner,
+                key,
+                iv: header.iv,
+            }),
+        })
+    }
+
+    fn delete_file(&self, path: &Path) -> io::Result<()> {
+        let id = self.file_key_id(path)?;
+        self.base.delete_file(path)?;
+        self.drop_file_key(id)
+    }
+
+    fn rename_file(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let replaced = self.file_key_id(to)?;
+        self.base.rename_file(from, to)?;
+        // Renaming a file onto itself replaces nothing.
+        if replaced.is_some() && replaced != self.file_key_id(to)? {
+            self.drop_file_key(replaced)?;
+        }
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.base.create_dir_all(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        self.base.list_dir(path)
+    }
+
+    fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        self.base.sync_dir(path)
+    }
+
+    fn lock_file(&self, path: &Path) -> io::Result<Box<dyn FileLock>> {
+        self.base.lock_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{KvEinsteinMerkleTree, Peekable, SyncMutable, ALL_NAMESPACEDS};
+
+    use super::*;
+    use crate::env::DefaultEnv;
+    use crate::key_manager::AesMasterKey;
+    use crate::options::{LsmNamespacedOptions, LsmOptions};
+    use crate::LsmEinsteinMerkleTree;
+
+    fn encrypted_env(dir: &Path, method: EncryptionMethod) -> EncryptedEnv {
+        let master_key = Arc::new(AesMasterKey::derive(b"master"));
+        EncryptedEnv::open(Arc::new(DefaultEnv), dir, EncryptionOptions::new(method, master_key)).unwrap()
+    }
+
+    fn write_file(env: &dyn Env, path: &Path, appends: &[&[u8]]) {
+        let mut file = env.new_writable_file(path).unwrap();
+        for data in appends {
+            file.append(data).unwrap();
+        }
+        file.sync().unwrap();
+    }
+
+    #[test]
+    fn test_read_at_any_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let methods = [
+            EncryptionMethod::Plaintext,
+            EncryptionMethod::Aes128Ctr,
+            EncryptionMethod::Aes256Ctr,
+            EncryptionMethod::Aes256Gcm,
+        ];
+        for method in methods {
+            let env = encrypted_env(dir.path(), method);
+            let path = dir.path().join(format!("{:?}", method));
+            write_file(&env, &path, &[&data[..1000], &data[1000..1003], &data[1003..]]);
+
+            let raw = std::fs::read(&path).unwrap();
+            let encrypted = !raw.windows(64).any(|w| w == &data[1000..1064]);
+            assert_eq!(encrypted, method != EncryptionMethod::Plaintext);
+            let file = env.new_random_access_file(&path).unwrap();
+            assert_eq!(file.size(), data.len() as u64);
+            assert_eq!(file.read_all().unwrap(), data);
+            for (offset, len) in [(0, 17), (990, 20), (1001, 1), (4999, 1), (17, 4900)] {
+                let mut buf = vec![0; len];
+                file.read_exact_at(offset as u64, &mut buf).unwrap();
+                assert_eq!(buf, &data[offset..offset + len], "{:?} at {}", method, offset);
+            }
+            assert!(file.read_exact_at(4999, &mut [0; 2]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_method_names() {
+        assert_eq!("aes256-gcm".parse::<EncryptionMethod>().unwrap(), EncryptionMethod::Aes256Gcm);
+        assert_eq!("AES128-CTR".parse::<EncryptionMethod>().unwrap(), EncryptionMethod::Aes128Ctr);
+        assert!("rot13".parse::<EncryptionMethod>().is_err());
+    }
+
+    #[test]
+    fn test_gcm_detects_tampering_and_torn_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = encrypted_env(dir.path(), EncryptionMethod::Aes256Gcm);
+        let path = dir.path().join("f");
+        write_file(&env, &path, &[b"first record", b"second record"]);
+
+        // A record cut short ends the file.
+        let raw = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &raw[..raw.len() - 3]).unwrap();
+        assert_eq!(env.new_random_access_file(&path).unwrap().read_all().unwrap(), b"first record");
+
+        let mut tampered = raw.clone();
+        tampered[HEADER_LEN + GCM_RECORD_HEADER_LEN + 2] ^= 1;
+        std::fs::write(&path, &tampered).unwrap();
+        let file = env.new_random_access_file(&path).unwrap();
+        assert_eq!(file.read_all().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_a_data_key_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = encrypted_env(dir.path(), EncryptionMethod::Aes256Ctr);
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        write_file(&env, &a, &[b"contents of a"]);
+        write_file(&env, &b, &[b"contents of b"]);
+        let keys = env.key_manager().data_key_ids();
+        assert_eq!(keys.len(), 2);
+
+        // Replacing a file, by renaming over it or by writing it anew, drops its key.
+        env.rename_file(&a, &b).unwrap();
+        assert_eq!(env.key_manager().data_key_ids(), vec![keys[0]]);
+        write_file(&env, &b, &[b"new contents of b"]);
+        assert_eq!(env.key_manager().data_key_ids().len(), 1);
+        assert_ne!(env.key_manager().data_key_ids(), vec![keys[0]]);
+        env.rename_file(&b, &b).unwrap();
+        assert_eq!(env.new_random_access_file(&b).unwrap().read_all().unwrap(), b"new contents of b");
+        env.delete_file(&b).unwrap();
+        assert!(env.key_manager().data_key_ids().is_empty());
+    }
+
+    #[test]
+    fn test_plaintext_files_stay_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("f");
+        std::fs::write(&path, b"written before encryption was turned on").unwrap();
+        let env = encrypted_env(dir.path(), EncryptionMethod::Aes256Ctr);
+        let file = env.new_random_access_file(&path).unwrap();
+        assert_eq!(file.read_all().unwrap(), b"written before encryption was turned on");
+    }
+
+    #[test]
+    fn test_encrypted_einstein_merkle_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = |method| {
+            let opts = LsmOptions {
+                env: Arc::new(encrypted_env(dir.path(), method)),
+                ..LsmOptions::default()
+            };
+            let namespaceds = ALL_NAMESPACEDS
+                .iter()
+                .map(|n| (n.to_string(), LsmNamespacedOptions::default()))
+                .collect();
+            LsmEinsteinMerkleTree::open(dir.path(), opts, namespaceds).unwrap()
+        };
+        let secret = b"a causet_locale nobody may read off the disk";
+        let db = open(EncryptionMethod::Aes256Ctr);
+        db.put(b"flushed", secret).unwrap();
+        db.flush().unwrap();
+        db.put(b"logged", secret).unwrap();
+        db.sync().unwrap();
+        drop(db);
+
+        for entry in std::fs::read_dir(dir.path()).unwrap() {
+            let data = std::fs::read(entry.unwrap().path()).unwrap();
+            assert!(!data.windows(secret.len()).any(|w| w == secret));
+        }
+        // Files written with the old data key stay readable after switching methods.
+        let db = open(EncryptionMethod::Aes256Gcm);
+        assert_eq!(db.get_value(b"flushed").unwrap(), Some(secret.to_vec()));
+        assert_eq!(db.get_value(b"logged").unwrap(), Some(secret.to_vec()));
+        db.put(b"new", secret).unwrap();
+        db.flush().unwrap();
+        drop(db);
+        let db = open(EncryptionMethod::Aes256Gcm);
+        assert_eq!(db.get_value(b"new").unwrap(), Some(secret.to_vec()));
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! The file system underneath an `LsmEinsteinMerkleTree`.
+//!
+//! Every file the einstein_merkle_tree touches goes through an `Env`, so that an `Env` wrapping
+//! another one can transform file contents, e.g. to encrypt them, without the rest of the
+//! einstein_merkle_tree knowing.
+
+use std::fmt::Debug;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A file that is only ever appended to.
+pub trait WritableFile: Send {
+    fn append(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Make everything appended so far durable.
+    fn sync(&mut self) -> io::Result<()>;
+}
+
+/// A file that is read at arbitrary offsets.
+pub trait RandomAccessFile: Send + Sync {
+    /// Fill `buf` with the bytes starting at `offset`.
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()>;
+
+    fn size(&self) -> u64;
+
+    /// Read the whole file.
+    fn read_all(&self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; self.size() as usize];
+        self.read_exact_at(0, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+/// An exclusive lock on a file, released when dropped.
+pub trait FileLock: Send + Sync {}
+
+pub trait Env: Send + Sync + Debug {
+    /// Create a file, truncating it if it exists.
+    fn new_writable_file(&self, path: &Path) -> io::Result<Box<dyn WritableFile>>;
+
+    fn new_random_access_file(&self, path: &Path) -> io::Result<Box<dyn RandomAccessFile>>;
+
+    fn delete_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Atomically replace `to` with `from`.
+    fn rename_file(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// The paths of the entries of the directory `path`.
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+
+    /// Make the creation, renaming and deletion of the entries of `path` durable.
+    fn sync_dir(&self, path: &Path) -> io::Result<()>;
+
+    /// Lock the file `path`, creating it if needed, against every other `lock_file` of it, in
+    /// this process or another. Fails with `WouldBlock` if it is locked already.
+    fn lock_file(&self, path: &Path) -> io::Result<Box<dyn FileLock>>;
+}
+
+/// The `Env` of the local file system, storing file contents as they are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultEnv;
+
+struct PlainWritableFile(File);
+
+impl WritableFile for PlainWritableFile {
+    fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        self.0.write_all(data)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.0.sync_data()
+    }
+}
+
+struct PlainRandomAccessFile {
+    file: File,
+    size: u64,
+}
+
+impl RandomAccessFile for PlainRandomAccessFile {
+    #[cfg(unix)]
+    fn read_exact_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        std::os::unix::fs::FileExt::read_exact_at(&self.file, buf, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_exact_at(&self, mut offset: u64, mut buf: &mut [u8]) -> io::Result<()> {
+        while !buf.is_empty() {
+            match std::os::windows::fs::FileExt::seek_read(&self.file, buf, offset)? {
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n => {
+                    buf = &mut buf[n..];
+                    offset += n as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Holds the file open: closing it releases the lock.
+struct PlainFileLock {
+    _file: File,
+}
+
+impl FileLock for PlainFileLock {}
+
+impl Env for DefaultEnv {
+    fn new_writable_file(&self, path: &Path) -> io::Result<Box<dyn WritableFile>> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Box::new(PlainWritableFile(file)))
+    }
+
+    fn new_random_access_file(&self, path: &Path) -> io::Result<Box<dyn RandomAccessFile>> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Box::new(PlainRandomAccessFile { file, size }))
+    }
+
+    fn delete_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn rename_file(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn list_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?.map(|e| e.map(|e| e.path())).collect()
+    }
+
+    #[cfg(unix)]
+    fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        File::open(path)?.sync_all()
+    }
+
+    #[cfg(not(unix))]
+    fn sync_dir(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn lock_file(&self, path: &Path) -> io::Result<Box<dyn FileLock>> {
+        let file = OpenOptions::new().write(true).create(true).truncate(false).open(path)?;
+        match file.try_lock() {
+            Ok(()) => Ok(Box::new(PlainFileLock { _file: file })),
+            Err(TryLockError::WouldBlock) => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                format!("{} is locked by another open einstein_merkle_tree", path.display()),
+            )),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
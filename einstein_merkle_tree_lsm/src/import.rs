@@ -0,0 +1,221 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use fdb_traits::{Error, ImportExt, IngestlightlikeFileOptions, Result};
+
+use crate::einstein_merkle_tree::LsmEinsteinMerkleTree;
+use crate::env::Env;
+use crate::table::{Table, GREEDOID_NAMESPACED};
+
+#[derive(Clone, Debug)]
+pub struct LsmIngestlightlikeFileOptions {
+    pub(crate) move_files: bool,
+    pub(crate) write_global_seqno: bool,
+}
+
+impl IngestlightlikeFileOptions for LsmIngestlightlikeFileOptions {
+    fn new() -> Self {
+        LsmIngestlightlikeFileOptions {
+            move_files: false,
+            write_global_seqno: true,
+        }
+    }
+
+    fn move_filefs(&mut self, f: bool) {
+        self.move_files = f;
+    }
+
+    fn get_write_global_seqno(&self) -> bool {
+        self.write_global_seqno
+    }
+
+    fn set_write_global_seqno(&mut self, write_global_seqno: bool) {
+        self.write_global_seqno = write_global_seqno;
+    }
+}
+
+impl ImportExt for LsmEinsteinMerkleTree {
+    type IngestlightlikeFileOptions = LsmIngestlightlikeFileOptions;
+
+    fn ingest_lightlike_file_namespaced(
+        &self,
+        namespaced: &str,
+        opts: &LsmIngestlightlikeFileOptions,
+        files: &[&str],
+    ) -> Result<()> {
+        self.core.ingest(namespaced, files, opts)
+    }
+}
+
+/// A table file to be ingested, checked from end to end.
+pub(crate) struct ExternalFile {
+    pub(crate) path: PathBuf,
+    pub(crate) table: Arc<Table>,
+    pub(crate) smallest: Vec<u8>,
+    pub(crate) largest: Vec<u8>,
+}
+
+impl ExternalFile {
+    /// Open the file at `path` and read all of it, checking every checksum, that its soliton_ids
+    /// are strictly increasing and unsequenced, and that it was written for `namespaced`.
+    pub(crate) fn check(env: &Arc<dyn Env>, path: &Path, namespaced: &str) -> Result<ExternalFile> {
+        let invalid = |what: String| Error::Ingest(format!("{}: {}", path.display(), what));
+        let table = Arc::new(Table::open(env.clone(), path, 0, None)?);
+        if let Some(written_for) = table.greedoids().get(GREEDOID_NAMESPACED) {
+            if written_for.as_slice() != namespaced.as_bytes() {
+                return Err(invalid(format!(
+                    "written for NAMESPACED {}",
+                    String::from_utf8_lossy(written_for)
+                )));
+            }
+        }
+        if !table.range_tombstones().is_empty() {
+            return Err(invalid("holds range tombstones".to_owned()));
+        }
+        let mut bounds: Option<(Vec<u8>, Vec<u8>)> = None;
+        for entry in table.scan() {
+            let entry = entry?;
+            if entry.seqno != 0 {
+                return Err(invalid(format!("{:?} has sequence number {}", entry.soliton_id, entry.seqno)));
+            }
+            match &mut bounds {
+                Some((_, largest)) if entry.soliton_id <= *largest => {
+                    return Err(invalid(format!("{:?} follows {:?}", entry.soliton_id, largest)));
+                }
+                Some((_, largest)) => *largest = entry.soliton_id,
+                None => bounds = Some((entry.soliton_id.clone(), entry.soliton_id)),
+            }
+        }
+        let (smallest, largest) = bounds.ok_or_else(|| invalid("is empty".to_owned()))?;
+        Ok(ExternalFile {
+            path: path.to_owned(),
+            table,
+            smallest,
+            largest,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{
+        Peekable, SstExt, SstWriter, SstWriterBuilder, SyncMutable, ALL_NAMESPACEDS, NAMESPACED_DEFAULT, NAMESPACED_WRITE,
+    };
+
+    use super::*;
+    use crate::options::{LsmNamespacedOptions, LsmOptions};
+
+    fn open_einstein_merkle_tree(path: &Path) -> LsmEinsteinMerkleTree {
+        let namespaceds = ALL_NAMESPACEDS
+            .iter()
+            .map(|n| (n.to_string(), LsmNamespacedOptions::default()))
+            .collect();
+        LsmEinsteinMerkleTree::open(path, LsmOptions::default(), namespaceds).unwrap()
+    }
+
+    fn write_sst(db: &LsmEinsteinMerkleTree, path: &Path, kvs: &[(&[u8], &[u8])]) -> String {
+        let path = path.to_str().unwrap().to_owned();
+        let mut w = <LsmEinsteinMerkleTree as SstExt>::SstWriterBuilder::new()
+            .set_db(db)
+            .set_namespaced(NAMESPACED_DEFAULT)
+            .build(&path)
+            .unwrap();
+        for (k, v) in kvs {
+            w.put(k, v).unwrap();
+        }
+        w.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn test_ingest_without_overlap() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        db.put(b"a", b"1").unwrap();
+        let sst = write_sst(&db, &dir.path().join("1.sst"), &[(b"m", b"2"), (b"n", b"3")]);
+
+        let mut opts = LsmIngestlightlikeFileOptions::new();
+        opts.move_filefs(true);
+        db.ingest_lightlike_file_namespaced(NAMESPACED_DEFAULT, &opts, &[&sst])
+            .unwrap();
+        assert!(!Path::new(&sst).exists());
+        let counts = db.l_naught_file_counts(NAMESPACED_DEFAULT).unwrap();
+        assert_eq!(counts.last(), Some(&1));
+        assert_eq!(db.latest_seqno(), 1);
+        assert_eq!(db.get_value(b"m").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.get_value(b"a").unwrap(), Some(b"1".to_vec()));
+
+        drop(db);
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        assert_eq!(db.get_value(b"n").unwrap(), Some(b"3".to_vec()));
+    }
+
+    #[test]
+    fn test_ingest_with_overlap() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        db.put(b"m", b"old").unwrap();
+        db.flush().unwrap();
+        db.put(b"z", b"memtable").unwrap();
+        let sst = write_sst(&db, &dir.path().join("1.sst"), &[(b"m", b"new"), (b"z", b"ingested")]);
+
+        let mut opts = LsmIngestlightlikeFileOptions::new();
+        opts.set_write_global_seqno(false);
+        assert!(db
+            .ingest_lightlike_file_namespaced(NAMESPACED_DEFAULT, &opts, &[&sst])
+            .is_err());
+        assert_eq!(db.get_value(b"m").unwrap(), Some(b"old".to_vec()));
+
+        let before = db.latest_seqno();
+        opts.set_write_global_seqno(true);
+        db.ingest_lightlike_file_namespaced(NAMESPACED_DEFAULT, &opts, &[&sst])
+            .unwrap();
+        assert!(Path::new(&sst).exists());
+        assert_eq!(db.latest_seqno(), before + 1);
+        assert_eq!(db.l_naught_file_counts(NAMESPACED_DEFAULT).unwrap()[0], 2);
+        assert_eq!(db.get_value(b"m").unwrap(), Some(b"new".to_vec()));
+        assert_eq!(db.get_value(b"z").unwrap(), Some(b"ingested".to_vec()));
+
+        db.put(b"m", b"newer").unwrap();
+        assert_eq!(db.latest_seqno(), before + 2);
+        drop(db);
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        assert_eq!(db.get_value(b"m").unwrap(), Some(b"newer".to_vec()));
+        assert_eq!(db.get_value(b"z").unwrap(), Some(b"ingested".to_vec()));
+    }
+
+    #[test]
+    fn test_ingest_rejects_bad_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_einstein_merkle_tree(&dir.path().join("db"));
+        let opts = LsmIngestlightlikeFileOptions::new();
+
+        let mut w = <LsmEinsteinMerkleTree as SstExt>::SstWriterBuilder::new()
+            .set_db(&db)
+            .build(dir.path().join("0.sst").to_str().unwrap())
+            .unwrap();
+        w.put(b"b", b"").unwrap();
+        assert!(w.put(b"a", b"").is_err());
+        assert!(w.delete(b"b").is_err());
+
+        let first = write_sst(&db, &dir.path().join("1.sst"), &[(b"a", b"1"), (b"c", b"1")]);
+        let second = write_sst(&db, &dir.path().join("2.sst"), &[(b"b", b"2")]);
+        assert!(db
+            .ingest_lightlike_file_namespaced(NAMESPACED_DEFAULT, &opts, &[&first, &second])
+            .is_err());
+        assert!(db
+            .ingest_lightlike_file_namespaced(NAMESPACED_WRITE, &opts, &[&second])
+            .is_err());
+
+        let mut data = std::fs::read(&second).unwrap();
+        data[3] ^= 0xff;
+        std::fs::write(&second, &data).unwrap();
+        assert!(db
+            .ingest_lightlike_file_namespaced(NAMESPACED_DEFAULT, &opts, &[&second])
+            .is_err());
+        assert_eq!(db.get_value(b"a").unwrap(), None);
+        assert_eq!(db.l_naught_file_counts(NAMESPACED_DEFAULT).unwrap().iter().sum::<usize>(), 0);
+    }
+}
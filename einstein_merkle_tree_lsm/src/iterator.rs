@@ -0,0 +1,118 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use fdb_traits::{IterOptions, Iterator, Result, SeekKey};
+
+use crate::source::SourceSet;
+
+/// An iterator over a causet_merge family as of a single sequence number.
+///
+/// The iterator holds the memtables and table files that were live when it was created, so it
+/// is unaffected by later writes, flushes and compactions.  It only remembers its current
+/// soliton_id: every move looks up the neighbouring soliton_id in each memtable and table file
+/// and resolves its newest visible version, skipping soliton_ids that are deleted.
+pub struct LsmEinsteinMerkleTreeIterator {
+    sources: SourceSet,
+    opts: IterOptions,
+    current: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl LsmEinsteinMerkleTreeIterator {
+    pub(crate) fn new(sources: SourceSet, opts: IterOptions) -> LsmEinsteinMerkleTreeIterator {
+        LsmEinsteinMerkleTreeIterator {
+            sources,
+            opts,
+            current: None,
+        }
+    }
+
+    /// Move to the first live soliton_id at or after `from` (after it, if not `inclusive`).
+    fn forward(&mut self, from: &[u8], inclusive: bool) -> Result<bool> {
+        let mut from = from.to_vec();
+        let mut inclusive = inclusive;
+        self.current = None;
+        while let Some(soliton_id) = self.sources.next_soliton_id(&from, inclusive)? {
+            if self.opts.upper_bound().is_some_and(|u| soliton_id.as_slice() >= u) {
+                break;
+            }
+            if let Some(causet_locale) = self.sources.get(&soliton_id)? {
+                self.current = Some((soliton_id, causet_locale));
+                break;
+            }
+            from = soliton_id;
+            inclusive = false;
+        }
+        Ok(self.current.is_some())
+    }
+
+    /// Move to the last live soliton_id before `from` (or at it, if `inclusive`); `None` for
+    /// `from` is past the end.
+    fn backward(&mut self, from: Option<&[u8]>, inclusive: bool) -> Result<bool> {
+        let mut from = from.map(<[u8]>::to_vec);
+        let mut inclusive = inclusive;
+        self.current = None;
+        while let Some(soliton_id) = self.sources.prev_soliton_id(from.as_deref(), inclusive)? {
+            if self.opts.lower_bound().is_some_and(|l| soliton_id.as_slice() < l) {
+                break;
+            }
+            if let Some(causet_locale) = self.sources.get(&soliton_id)? {
+                self.current = Some((soliton_id, causet_locale));
+                break;
+            }
+            from = Some(soliton_id);
+            inclusive = false;
+        }
+        Ok(self.current.is_some())
+    }
+
+    fn current(&self) -> &(Vec<u8>, Vec<u8>) {
+        self.current.as_ref().expect("iterator is invalid")
+    }
+}
+
+impl Iterator for LsmEinsteinMerkleTreeIterator {
+    fn seek(&mut self, soliton_id: SeekKey<'_>) -> Result<bool> {
+        let lower = self.opts.lower_bound().unwrap_or(&[]).to_vec();
+        match soliton_id {
+            SeekKey::Start => self.forward(&lower, true),
+            SeekKey::End => {
+                let upper = self.opts.upper_bound().map(<[u8]>::to_vec);
+                self.backward(upper.as_deref(), false)
+            }
+            SeekKey::Key(k) => self.forward(k.max(lower.as_slice()), true),
+        }
+    }
+
+    fn seek_for_prev(&mut self, soliton_id: SeekKey<'_>) -> Result<bool> {
+        let upper = self.opts.upper_bound().map(<[u8]>::to_vec);
+        match soliton_id {
+            SeekKey::Start => self.seek(SeekKey::Start),
+            SeekKey::End => self.backward(upper.as_deref(), false),
+            SeekKey::Key(k) => match upper {
+                Some(u) if k >= u.as_slice() => self.backward(Some(&u), false),
+                _ => self.backward(Some(k), true),
+            },
+        }
+    }
+
+    fn prev(&mut self) -> Result<bool> {
+        let soliton_id = self.current().0.clone();
+        self.backward(Some(&soliton_id), false)
+    }
+
+    fn next(&mut self) -> Result<bool> {
+        let soliton_id = self.current().0.clone();
+        self.forward(&soliton_id, false)
+    }
+
+    fn soliton_id(&self) -> &[u8] {
+        &self.current().0
+    }
+
+    fn causet_locale(&self) -> &[u8] {
+        &self.current().1
+    }
+
+    fn valid(&self) -> Result<bool> {
+        Ok(self.current.is_some())
+    }
+}
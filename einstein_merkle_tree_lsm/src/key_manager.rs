@@ -0,0 +1,444 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! The data keys files are encrypted with, and the master key that protects them.
+//!
+//! Every file gets a data key of its own, generated when the file is created and dropped from
+//! the key dictionary when the file is deleted or replaced, so data keys rotate with the files
+//! and a leaked key exposes a single file.  The dictionary is encrypted with the master key and,
+//! like the manifest, rewritten to a temporary file and renamed into place on every change.
+//! Rotating the master key only rewrites the dictionary.
+//!
+//! ```text
+//! <dir>/KEY_DICTIONARY    the data keys, sealed with the master key
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fdb_traits::{Error, Result};
+use sha2::{Digest, Sha256};
+
+use crate::coding::{checksum, corruption, put_bytes, put_u32, put_u64, Decoder};
+use crate::encryption::{gcm_open, gcm_seal, random_bytes, EncryptionMethod};
+use crate::env::Env;
+
+pub(crate) const KEY_DICTIONARY: &str = "KEY_DICTIONARY";
+const KEY_DICTIONARY_TMP: &str = "KEY_DICTIONARY.tmp";
+
+/// Seals the key dictionary.
+pub trait MasterKey: Send + Sync + fmt::Debug {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>>;
+
+    /// Fails if `ciphertext` was not sealed by `encrypt` of this key.
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Leaves the key dictionary unencrypted, so that anyone who can read the einstein_merkle_tree's
+/// directory can read its data keys.  Only meant for testing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PlaintextMasterKey;
+
+impl MasterKey for PlaintextMasterKey {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        Ok(plaintext.to_vec())
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        Ok(ciphertext.to_vec())
+    }
+}
+
+/// A 256-bit AES-GCM master key.
+#[derive(Clone)]
+pub struct AesMasterKey {
+    key: [u8; 32],
+}
+
+impl AesMasterKey {
+    pub fn new(key: [u8; 32]) -> AesMasterKey {
+        AesMasterKey { key }
+    }
+
+    /// Read the key from a local file holding it as 64 hexadecimal digits.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<AesMasterKey> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        let hex = text.trim();
+        let invalid = || {
+            Error::Other(format!(
+                "{} does not hold a 256-bit key in hexadecimal",
+                path.as_ref().display()
+            ))
+        };
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let mut key = [0; 32];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(AesMasterKey::new(key))
+    }
+
+    /// Derive a key from a secret of any length.
+    pub fn derive(secret: &[u8]) -> AesMasterKey {
+        AesMasterKey::new(Sha256::digest(secret).into())
+    }
+}
+
+impl fmt::Debug for AesMasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AesMasterKey").finish_non_exhaustive()
+    }
+}
+
+impl MasterKey for AesMasterKey {
+    /// The random nonce followed by the sealed `plaintext`.
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0; 12];
+        random_bytes(&mut nonce)?;
+        let mut buf = nonce.to_vec();
+        buf.extend(gcm_seal(&self.key, &nonce, plaintext)?);
+        Ok(buf)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < 12 {
+            return Err(corruption("sealed data shorter than its nonce"));
+        }
+        let mut nonce = [0; 12];
+        nonce.copy_from_slice(&ciphertext[..12]);
+        Ok(gcm_open(&self.key, &nonce, &ciphertext[12..])?)
+    }
+}
+
+/// The key one file is encrypted with.
+#[derive(Clone)]
+pub(crate) struct DataKey {
+    pub(crate) id: u64,
+    pub(crate) method: EncryptionMethod,
+    pub(crate) key: Vec<u8>,
+    /// Seconds since the Unix epoch.
+    pub(crate) created: u64,
+}
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataKey")
+            .field("id", &self.id)
+            .field("method", &self.method)
+            .field("created", &self.created)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeyDictionary {
+    next_id: u64,
+    /// The data key of every encrypted file.
+    keys: BTreeMap<u64, DataKey>,
+}
+
+impl KeyDictionary {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_u64(&mut buf, self.next_id);
+        put_u32(&mut buf, self.keys.len() as u32);
+        for k in self.keys.values() {
+            put_u64(&mut buf, k.id);
+            buf.push(k.method.to_u8());
+            put_u64(&mut buf, k.created);
+            put_bytes(&mut buf, &k.key);
+        }
+        let crc = checksum(&buf);
+        put_u32(&mut buf, crc);
+        buf
+    }
+
+    fn decode(data: &[u8]) -> Result<KeyDictionary> {
+        if data.len() < 4 {
+            return Err(corruption("key dictionary too short"));
+        }
+        let (body, crc) = data.split_at(data.len() - 4);
+        if checksum(body) != Decoder::new(crc).u32()? {
+            return Err(corruption("key dictionary checksum mismatch"));
+        }
+        let mut d = Decoder::new(body);
+        let next_id = d.u64()?;
+        let mut keys = BTreeMap::new();
+        for _ in 0..d.u32()? {
+            let id = d.u64()?;
+            let method = d.u8()?;
+            let method = EncryptionMethod::from_u8(method)
+                .ok_or_else(|| corruption(format!("unknown encryption method {}", method)))?;
+            let created = d.u64()?;
+            let key = d.bytes()?.to_vec();
+            if key.len() != method.key_len() {
+                return Err(corruption(format!("data key {} of {} bytes for {:?}", id, key.len(), method)));
+            }
+            keys.insert(
+                id,
+                DataKey {
+                    id,
+                    method,
+                    key,
+                    created,
+                },
+            );
+        }
+        Ok(KeyDictionary { next_id, keys })
+    }
+}
+
+/// How an `EncryptedEnv` encrypts new files.
+#[derive(Clone, Debug)]
+pub struct EncryptionOptions {
+    pub method: EncryptionMethod,
+    pub master_key: Arc<dyn MasterKey>,
+    /// The master key the key dictionary may still be sealed with, while rotating master keys.
+    /// The dictionary is resealed with `master_key` when it is opened.
+    pub previous_master_key: Option<Arc<dyn MasterKey>>,
+}
+
+impl EncryptionOptions {
+    pub fn new(method: EncryptionMethod, master_key: Arc<dyn MasterKey>) -> EncryptionOptions {
+        EncryptionOptions {
+            method,
+            master_key,
+            previous_master_key: None,
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Read the key dictionary at `path`, and whether it was sealed with `previous_master_key`
+/// rather than `master_key`; `None` if there is none.
+fn read_dictionary(
+    env: &dyn Env,
+    path: &Path,
+    master_key: &dyn MasterKey,
+    previous_master_key: Option<&dyn MasterKey>,
+) -> Result<Option<(KeyDictionary, bool)>> {
+    let sealed = match env.new_random_access_file(path) {
+        Ok(file) => file.read_all()?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    match master_key.decrypt(&sealed) {
+        Ok(data) => Ok(Some((KeyDictionary::decode(&data)?, false))),
+        Err(e) => match previous_master_key {
+            Some(previous) => Ok(Some((KeyDictionary::decode(&previous.decrypt(&sealed)?)?, true))),
+            None => Err(e),
+        },
+    }
+}
+
+/// The key dictionary of an einstein_merkle_tree directory.
+#[derive(Debug)]
+pub struct DataKeyManager {
+    /// The `Env` the dictionary itself is read and written through, without encryption.
+    env: Arc<dyn Env>,
+    dir: PathBuf,
+    method: EncryptionMethod,
+    master_key: RwLock<Arc<dyn MasterKey>>,
+    dict: RwLock<KeyDictionary>,
+}
+
+impl DataKeyManager {
+    /// Open the key dictionary of `dir`, creating it if there is none.  New files are encrypted
+    /// with `opts.method`; files written with another one stay readable.
+    pub fn open(env: Arc<dyn Env>, dir: &Path, opts: EncryptionOptions) -> Result<DataKeyManager> {
+        env.create_dir_all(dir)?;
+        let path = dir.join(KEY_DICTIONARY);
+        let previous = opts.previous_master_key.as_deref();
+        let (dict, reseal) = match read_dictionary(env.as_ref(), &path, opts.master_key.as_ref(), previous)? {
+            Some(read) => read,
+            None => (
+                KeyDictionary {
+                    next_id: 1,
+                    ..KeyDictionary::default()
+                },
+                true,
+            ),
+        };
+        let manager = DataKeyManager {
+            env,
+            dir: dir.to_path_buf(),
+            method: opts.method,
+            master_key: RwLock::new(opts.master_key),
+            dict: RwLock::new(dict),
+        };
+        if reseal {
+            manager.save(&manager.dict.read().unwrap())?;
+        }
+        Ok(manager)
+    }
+
+    /// The method of the newest data key in the key dictionary of `dir`, which is how the
+    /// einstein_merkle_tree last encrypted a file; `None` if there is no dictionary or it holds
+    /// no keys.  Nothing is written.
+    pub fn newest_method(env: &dyn Env, dir: &Path, master_key: &dyn MasterKey) -> Result<Option<EncryptionMethod>> {
+        let dict = read_dictionary(env, &dir.join(KEY_DICTIONARY), master_key, None)?;
+        Ok(dict.and_then(|(dict, _)| dict.keys.values().next_back().map(|k| k.method)))
+    }
+
+    fn save(&self, dict: &KeyDictionary) -> Result<()> {
+        self.save_with(dict, self.master_key.read().unwrap().as_ref())
+    }
+
+    fn save_with(&self, dict: &KeyDictionary, master_key: &dyn MasterKey) -> Result<()> {
+        let sealed = master_key.encrypt(&dict.encode())?;
+        let tmp = self.dir.join(KEY_DICTIONARY_TMP);
+        let mut file = self.env.new_writable_file(&tmp)?;
+        file.append(&sealed)?;
+        file.sync()?;
+        self.env.rename_file(&tmp, &self.dir.join(KEY_DICTIONARY))?;
+        self.env.sync_dir(&self.dir)?;
+        Ok(())
+    }
+
+    /// Reseal the key dictionary with `master_key`.  Until this returns, the dictionary may
+    /// still be sealed with the old master key, which should then be given as
+    /// `previous_master_key` on the next open.
+    pub fn rotate_master_key(&self, master_key: Arc<dyn MasterKey>) -> Result<()> {
+        let dict = self.dict.read().unwrap();
+        let mut current = self.master_key.write().unwrap();
+        self.save_with(&dict, master_key.as_ref())?;
+        *current = master_key;
+        Ok(())
+    }
+
+    /// The ids of every data key in the dictionary.
+    pub fn data_key_ids(&self) -> Vec<u64> {
+        self.dict.read().unwrap().keys.keys().copied().collect()
+    }
+
+    /// A new data key for a file about to be created, or `None` if new files are written
+    /// unencrypted.  The key is in the dictionary on disk before this returns, so that the file
+    /// stays readable whenever it was written.
+    pub(crate) fn new_file_key(&self) -> Result<Option<DataKey>> {
+        if self.method == EncryptionMethod::Plaintext {
+            return Ok(None);
+        }
+        let mut key = vec![0; self.method.key_len()];
+        random_bytes(&mut key)?;
+        let mut dict = self.dict.write().unwrap();
+        let id = dict.next_id;
+        let data_key = DataKey {
+            id,
+            method: self.method,
+            key,
+            created: now_secs(),
+        };
+        dict.next_id += 1;
+        dict.keys.insert(id, data_key.clone());
+        if let Err(e) = self.save(&dict) {
+            dict.keys.remove(&id);
+            return Err(e);
+        }
+        Ok(Some(data_key))
+    }
+
+    /// Drop the data key of a file that has been deleted or replaced.
+    pub(crate) fn delete_file_key(&self, id: u64) -> Result<()> {
+        let mut dict = self.dict.write().unwrap();
+        let key = match dict.keys.remove(&id) {
+            Some(key) => key,
+            None => return Ok(()),
+        };
+        if let Err(e) = self.save(&dict) {
+            dict.keys.insert(id, key);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn data_key(&self, id: u64) -> Result<DataKey> {
+        self.dict
+            .read()
+            .unwrap()
+            .keys
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| corruption(format!("data key {} is not in the key dictionary", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::DefaultEnv;
+
+    fn options(method: EncryptionMethod, master_key: AesMasterKey) -> EncryptionOptions {
+        EncryptionOptions::new(method, Arc::new(master_key))
+    }
+
+    #[test]
+    fn test_master_key_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("master.key");
+        std::fs::write(&path, format!("{}\n", "0f".repeat(32))).unwrap();
+        assert_eq!(AesMasterKey::from_file(&path).unwrap().key, [0x0f; 32]);
+        std::fs::write(&path, "0f0f").unwrap();
+        assert!(AesMasterKey::from_file(&path).is_err());
+        std::fs::write(&path, "zz".repeat(32)).unwrap();
+        assert!(AesMasterKey::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_file_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let env: Arc<dyn Env> = Arc::new(DefaultEnv);
+        let old_master = AesMasterKey::derive(b"old");
+        let open = |method, master_key: &AesMasterKey| {
+            DataKeyManager::open(env.clone(), dir.path(), options(method, master_key.clone()))
+        };
+        let keys = open(EncryptionMethod::Aes256Ctr, &old_master).unwrap();
+        let first = keys.new_file_key().unwrap().unwrap();
+        let second = keys.new_file_key().unwrap().unwrap();
+        assert_ne!(first.id, second.id);
+        assert_ne!(first.key, second.key);
+        assert_eq!(keys.data_key_ids(), vec![first.id, second.id]);
+        keys.delete_file_key(first.id).unwrap();
+        assert!(keys.data_key(first.id).is_err());
+        drop(keys);
+
+        // The dictionary on disk has every key handed out and none of the dropped ones.
+        let keys = open(EncryptionMethod::Aes256Gcm, &old_master).unwrap();
+        assert_eq!(keys.data_key_ids(), vec![second.id]);
+        assert_eq!(keys.data_key(second.id).unwrap().key, second.key);
+        assert_eq!(keys.data_key(second.id).unwrap().method, EncryptionMethod::Aes256Ctr);
+        let third = keys.new_file_key().unwrap().unwrap();
+        assert!(third.id > second.id);
+        assert_eq!(third.method, EncryptionMethod::Aes256Gcm);
+        drop(keys);
+        let newest = DataKeyManager::newest_method(env.as_ref(), dir.path(), &old_master).unwrap();
+        assert_eq!(newest, Some(EncryptionMethod::Aes256Gcm));
+        let keys = open(EncryptionMethod::Plaintext, &old_master).unwrap();
+        assert!(keys.new_file_key().unwrap().is_none());
+        assert_eq!(keys.data_key_ids(), vec![second.id, third.id]);
+        drop(keys);
+
+        // The master key.
+        let new_master = AesMasterKey::derive(b"new");
+        let opts = options(EncryptionMethod::Plaintext, new_master.clone());
+        assert!(DataKeyManager::open(env.clone(), dir.path(), opts.clone()).is_err());
+        let mut rotating = opts.clone();
+        rotating.previous_master_key = Some(Arc::new(old_master.clone()));
+        DataKeyManager::open(env.clone(), dir.path(), rotating).unwrap();
+        let keys = DataKeyManager::open(env.clone(), dir.path(), opts).unwrap();
+        assert_eq!(keys.data_key_ids().len(), 2);
+        keys.rotate_master_key(Arc::new(old_master.clone())).unwrap();
+        drop(keys);
+        let keys = open(EncryptionMethod::Plaintext, &old_master).unwrap();
+        assert_eq!(keys.data_key_ids().len(), 2);
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! A durable einstein_merkle_tree built as a log-structured merge tree, in pure Rust.
+//!
+//! Every write alexandrov_poset_process is appended to a write-ahead log and then applied to a
+//! memtable per causet_merge family.  Once the memtables are full they are frozen, a new log is
+//! started, and a background thread flushes them to sorted table files in l_naught 0.  Table
+//! files are compacted down the l_naughts as those fill up, dropping versions nothing can read
+//! any more and whatever the compaction filter of their causet_merge family removes, and cut
+//! where its causet partitioner asks.  Which table files are live is recorded in a manifest, so
+//! that reopening the einstein_merkle_tree only has to replay the logs written since the last
+//! flush.
+//!
+//! Reads look a soliton_id up in every memtable and table file and keep its newest version
+//! visible at the read's sequence number.  Table files carry bloom filters, which let point reads
+//! skip most of them, and their data blocks are read through a shared block cache.
+//!
+//! Writes are slowed down, and then stopped, while l_naught 0 holds too many files, giving
+//! compaction the chance to catch up.
+//!
+//! Bulk loads bypass the write path: an `LsmSstWriter` writes sorted soliton_ids to a table file
+//! that is then ingested whole, below everything it does not overlap.
+//!
+//! A checkpoint is a consistent copy of the einstein_merkle_tree, made mostly of hard links, and
+//! a `BackupEngine` keeps incremental backups that share the table files they have in common.
+//!
+//! Files are read and written through an `Env`; an `EncryptedEnv` encrypts all of them at rest.
+//!
+//! ```text
+//! <dir>/MANIFEST          the live table files of each causet_merge family, by l_naught
+//! <dir>/000042.log        write-ahead log
+//! <dir>/000043.sst        table file
+//! <dir>/KEY_DICTIONARY    data keys of an `EncryptedEnv`
+//! ```
+
+mod backup;
+mod bloom;
+mod cache;
+mod checkpoint;
+mod coding;
+mod compaction;
+mod einstein_merkle_tree;
+mod encryption;
+mod env;
+mod import;
+mod iterator;
+mod key_manager;
+mod lightlike_persistence;
+mod memtable;
+mod options;
+mod source;
+mod sst;
+mod table;
+mod version;
+mod wal;
+mod write_batch;
+
+pub use crate::backup::{BackupEngine, BackupInfo};
+pub use crate::einstein_merkle_tree::LsmEinsteinMerkleTree;
+pub use crate::encryption::{EncryptedEnv, EncryptionMethod};
+pub use crate::env::{DefaultEnv, Env, FileLock, RandomAccessFile, WritableFile};
+pub use crate::import::LsmIngestlightlikeFileOptions;
+pub use crate::iterator::LsmEinsteinMerkleTreeIterator;
+pub use crate::key_manager::{AesMasterKey, DataKeyManager, EncryptionOptions, MasterKey, PlaintextMasterKey};
+pub use crate::lightlike_persistence::LsmLightlikePersistence;
+pub use crate::options::{LsmNamespacedOptions, LsmOptions};
+pub use crate::sst::{LsmSstFileInfo, LsmSstWriter, LsmSstWriterBuilder};
+pub use crate::write_batch::LsmWriteBatch;
@@ -0,0 +1,68 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::fmt::{self, Debug, Formatter};
+use std::sync::Arc;
+
+use fdb_traits::{IterOptions, Iterable, LightlikePersistence, Peekable, ReadOptions, Result};
+
+use crate::einstein_merkle_tree::{LsmEinsteinMerkleTree, SuperVersion};
+use crate::iterator::LsmEinsteinMerkleTreeIterator;
+
+/// A point-in-time view of an `LsmEinsteinMerkleTree`.
+///
+/// It holds on to the memtables and table files that were live when it was taken, so table
+/// files compacted away in the meantime are only deleted once it is dropped.
+pub struct LsmLightlikePersistence {
+    db: LsmEinsteinMerkleTree,
+    seqno: u64,
+    super_version: Arc<SuperVersion>,
+}
+
+impl LsmLightlikePersistence {
+    pub(crate) fn new(db: LsmEinsteinMerkleTree, seqno: u64, super_version: Arc<SuperVersion>) -> LsmLightlikePersistence {
+        LsmLightlikePersistence {
+            db,
+            seqno,
+            super_version,
+        }
+    }
+
+    /// The sequence number of the last write visible through this lightlike_persistence.
+    pub fn seqno(&self) -> u64 {
+        self.seqno
+    }
+}
+
+impl Debug for LsmLightlikePersistence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LsmLightlikePersistence")
+            .field("seqno", &self.seqno)
+            .finish()
+    }
+}
+
+impl Peekable for LsmLightlikePersistence {
+    fn get_value_namespaced_opt(&self, opts: &ReadOptions, namespaced: &str, soliton_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.db
+            .get_at(opts, namespaced, soliton_id, Some((self.seqno, &self.super_version)))
+    }
+}
+
+impl Iterable for LsmLightlikePersistence {
+    type Iterator = LsmEinsteinMerkleTreeIterator;
+
+    fn iterator_opt(&self, opts: IterOptions) -> Result<Self::Iterator> {
+        self.iterator_namespaced_opt(fdb_traits::NAMESPACED_DEFAULT, opts)
+    }
+
+    fn iterator_namespaced_opt(&self, namespaced: &str, opts: IterOptions) -> Result<Self::Iterator> {
+        self.db
+            .iterator_at(namespaced, opts, Some((self.seqno, self.super_version.clone())))
+    }
+}
+
+impl LightlikePersistence for LsmLightlikePersistence {
+    fn namespaced_names(&self) -> Vec<&str> {
+        self.db.core.namespaced_names()
+    }
+}
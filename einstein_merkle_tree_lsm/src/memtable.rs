@@ -0,0 +1,116 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use fdb_traits::Result;
+
+use crate::coding::{Entry, RangeTombstone, ValueKind};
+use crate::source::Source;
+
+/// A soliton_id and the sequence number of one of its versions, ordered like `cmp_internal`.
+type VersionKey = (Vec<u8>, Reverse<u64>);
+
+#[derive(Default)]
+struct Inner {
+    versions: BTreeMap<VersionKey, (ValueKind, Vec<u8>)>,
+    range_tombstones: Vec<RangeTombstone>,
+    approximate_size: usize,
+}
+
+/// The recent writes to a causet_merge family that are not yet in a table file.
+///
+/// Versions are only ever added, and always with a sequence number above every sequence number
+/// published to readers, so readers at an older sequence number can share a memtable with the
+/// writer.
+#[derive(Default)]
+pub(crate) struct MemTable {
+    inner: RwLock<Inner>,
+}
+
+fn to_entry((soliton_id, Reverse(seqno)): &VersionKey, (kind, causet_locale): &(ValueKind, Vec<u8>)) -> Entry {
+    Entry {
+        soliton_id: soliton_id.clone(),
+        seqno: *seqno,
+        kind: *kind,
+        causet_locale: causet_locale.clone(),
+    }
+}
+
+impl MemTable {
+    pub(crate) fn add(&self, kind: ValueKind, soliton_id: &[u8], causet_locale: &[u8], seqno: u64) {
+        let mut inner = self.inner.write().unwrap();
+        inner.approximate_size += soliton_id.len() + causet_locale.len() + 32;
+        inner
+            .versions
+            .insert((soliton_id.to_vec(), Reverse(seqno)), (kind, causet_locale.to_vec()));
+    }
+
+    pub(crate) fn delete_range(&self, start: &[u8], end: &[u8], seqno: u64) {
+        if start >= end {
+            return;
+        }
+        let mut inner = self.inner.write().unwrap();
+        inner.approximate_size += start.len() + end.len() + 32;
+        inner.range_tombstones.push(RangeTombstone {
+            start: start.to_vec(),
+            end: end.to_vec(),
+            seqno,
+        });
+    }
+
+    pub(crate) fn approximate_size(&self) -> usize {
+        self.inner.read().unwrap().approximate_size
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        let inner = self.inner.read().unwrap();
+        inner.versions.is_empty() && inner.range_tombstones.is_empty()
+    }
+
+    /// Whether the memtable holds a version or range tombstone of a soliton_id in
+    /// `[smallest, largest]`.
+    pub(crate) fn overlaps(&self, smallest: &[u8], largest: &[u8]) -> bool {
+        let inner = self.inner.read().unwrap();
+        let from = (smallest.to_vec(), Reverse(u64::MAX));
+        inner
+            .versions
+            .range(from..)
+            .next()
+            .is_some_and(|((soliton_id, _), _)| soliton_id.as_slice() <= largest)
+            || inner
+                .range_tombstones
+                .iter()
+                .any(|t| t.start.as_slice() <= largest && smallest < t.end.as_slice())
+    }
+
+    /// Every version, in `cmp_internal` order.
+    pub(crate) fn entries(&self) -> Vec<Entry> {
+        let inner = self.inner.read().unwrap();
+        inner.versions.iter().map(|(k, v)| to_entry(k, v)).collect()
+    }
+}
+
+impl Source for MemTable {
+    fn seek(&self, (soliton_id, seqno): (&[u8], u64)) -> Result<Option<Entry>> {
+        let inner = self.inner.read().unwrap();
+        let from = (soliton_id.to_vec(), Reverse(seqno));
+        Ok(inner.versions.range(from..).next().map(|(k, v)| to_entry(k, v)))
+    }
+
+    fn seek_before(&self, (soliton_id, seqno): (&[u8], u64)) -> Result<Option<Entry>> {
+        let inner = self.inner.read().unwrap();
+        let to = (soliton_id.to_vec(), Reverse(seqno));
+        Ok(inner.versions.range(..to).next_back().map(|(k, v)| to_entry(k, v)))
+    }
+
+    fn last(&self) -> Result<Option<Entry>> {
+        let inner = self.inner.read().unwrap();
+        Ok(inner.versions.iter().next_back().map(|(k, v)| to_entry(k, v)))
+    }
+
+    fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        self.inner.read().unwrap().range_tombstones.clone()
+    }
+}
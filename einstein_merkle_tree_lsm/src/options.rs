@@ -0,0 +1,81 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::sync::Arc;
+
+use fdb_traits::{CausetPartitionerFactories, CompactionFilterFactories, MergeOperators};
+
+use crate::env::{DefaultEnv, Env};
+
+/// Options shared by every causet_merge family of an `LsmEinsteinMerkleTree`.
+#[derive(Clone, Debug)]
+pub struct LsmOptions {
+    /// Where every file of the einstein_merkle_tree is read from and written to.
+    pub env: Arc<dyn Env>,
+    /// The memtables are switched and flushed once they hold this many bytes in total.
+    pub write_buffer_size: usize,
+    /// Approximate size of a data block of a table file, before its checksum.
+    pub block_size: usize,
+    /// Capacity in bytes of the cache of decoded data blocks.  Zero disables the cache.
+    pub block_cache_size: usize,
+    /// Bits of bloom filter per soliton_id in each table file.  Zero disables the filters.
+    pub bloom_bits_per_soliton_id: usize,
+    /// Compaction output is cut into files of about this size.
+    pub target_file_size: u64,
+    /// Size at which l_naught 1 is compacted into l_naught 2.
+    pub max_bytes_for_l_naught_base: u64,
+    /// Each l_naught below l_naught 1 may hold this many times more bytes than the one above.
+    pub max_bytes_for_l_naught_multiplier: u64,
+    /// Number of l_naughts, l_naught 0 included.
+    pub num_l_naughts: usize,
+    /// The merge operator of each causet_merge family.  Operators are not persisted: a causet_merge
+    /// family holding merge operands must be opened with the same operator again.
+    pub merge_operators: MergeOperators,
+    /// The compaction filter factory of each causet_merge family.  Flushes are not filtered.
+    pub compaction_filter_factories: CompactionFilterFactories,
+    /// The causet partitioner factory of each causet_merge family.  Flush output is not
+    /// partitioned.
+    pub causet_partitioner_factories: CausetPartitionerFactories,
+}
+
+impl Default for LsmOptions {
+    fn default() -> LsmOptions {
+        LsmOptions {
+            env: Arc::new(DefaultEnv),
+            write_buffer_size: 4 << 20,
+            block_size: 4 << 10,
+            block_cache_size: 8 << 20,
+            bloom_bits_per_soliton_id: 10,
+            target_file_size: 8 << 20,
+            max_bytes_for_l_naught_base: 64 << 20,
+            max_bytes_for_l_naught_multiplier: 10,
+            num_l_naughts: 7,
+            merge_operators: MergeOperators::default(),
+            compaction_filter_factories: CompactionFilterFactories::default(),
+            causet_partitioner_factories: CausetPartitionerFactories::default(),
+        }
+    }
+}
+
+/// Options for a single causet_merge family of an `LsmEinsteinMerkleTree`.
+#[derive(Clone, Debug)]
+pub struct LsmNamespacedOptions {
+    /// Only compact when asked to through `compact_range_namespaced`.
+    pub disable_auto_jet_bundles: bool,
+    /// L_naught 0 is compacted into l_naught 1 once it holds this many files.
+    pub l_naught_zero_file_num_jet_bundle_trigger: usize,
+    /// Writes are delayed while l_naught 0 holds at least this many files.
+    pub l_naught_zero_slowdown_writes_trigger: usize,
+    /// Writes wait for compaction while l_naught 0 holds at least this many files.
+    pub l_naught_zero_stop_writes_trigger: usize,
+}
+
+impl Default for LsmNamespacedOptions {
+    fn default() -> LsmNamespacedOptions {
+        LsmNamespacedOptions {
+            disable_auto_jet_bundles: false,
+            l_naught_zero_file_num_jet_bundle_trigger: 4,
+            l_naught_zero_slowdown_writes_trigger: 20,
+            l_naught_zero_stop_writes_trigger: 36,
+        }
+    }
+}
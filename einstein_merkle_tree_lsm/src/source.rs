@@ -0,0 +1,185 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Reads over the memtables and table files that together make up a causet_merge family.
+//!
+//! Each memtable and each table file is a `Source` of versions.  Sequence numbers order the
+//! versions of different sources, so a read takes the newest version at or below its sequence
+//! number over all the sources and then checks it against every range tombstone it can see.
+//! A merge operand sends the read further down, collecting operands until it reaches a version
+//! that is not one, and the operands are then applied to that version.
+
+use std::sync::Arc;
+
+use fdb_traits::{Error, MergeOperator, Result};
+
+use crate::coding::{Entry, RangeTombstone, ValueKind};
+
+pub(crate) trait Source: Send + Sync {
+    /// The first version at or after `target` in `cmp_internal` order.
+    fn seek(&self, target: (&[u8], u64)) -> Result<Option<Entry>>;
+
+    /// The last version strictly before `target` in `cmp_internal` order.
+    fn seek_before(&self, target: (&[u8], u64)) -> Result<Option<Entry>>;
+
+    /// The last version in `cmp_internal` order.
+    fn last(&self) -> Result<Option<Entry>>;
+
+    fn range_tombstones(&self) -> Vec<RangeTombstone>;
+
+    /// Whether the source may hold a version of `soliton_id`.  Only used to skip point reads.
+    fn may_contain(&self, _soliton_id: &[u8]) -> bool {
+        true
+    }
+}
+
+impl<T: Source + ?Sized> Source for Arc<T> {
+    fn seek(&self, target: (&[u8], u64)) -> Result<Option<Entry>> {
+        (**self).seek(target)
+    }
+
+    fn seek_before(&self, target: (&[u8], u64)) -> Result<Option<Entry>> {
+        (**self).seek_before(target)
+    }
+
+    fn last(&self) -> Result<Option<Entry>> {
+        (**self).last()
+    }
+
+    fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        (**self).range_tombstones()
+    }
+
+    fn may_contain(&self, soliton_id: &[u8]) -> bool {
+        (**self).may_contain(soliton_id)
+    }
+}
+
+/// The newest version of `soliton_id` at or below `read_seqno` in `source`.
+fn newest_version(source: &dyn Source, soliton_id: &[u8], read_seqno: u64) -> Result<Option<Entry>> {
+    Ok(source
+        .seek((soliton_id, read_seqno))?
+        .filter(|e| e.soliton_id == soliton_id))
+}
+
+/// A consistent set of sources read at a single sequence number.
+pub(crate) struct SourceSet {
+    sources: Vec<Box<dyn Source>>,
+    /// Every range tombstone at or below `read_seqno`.
+    range_tombstones: Vec<RangeTombstone>,
+    read_seqno: u64,
+    namespaced: String,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
+}
+
+impl SourceSet {
+    pub(crate) fn new(
+        sources: Vec<Box<dyn Source>>,
+        read_seqno: u64,
+        namespaced: &str,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
+    ) -> SourceSet {
+        let range_tombstones = sources
+            .iter()
+            .flat_map(|s| s.range_tombstones())
+            .filter(|t| t.seqno <= read_seqno)
+            .collect();
+        SourceSet {
+            sources,
+            range_tombstones,
+            read_seqno,
+            namespaced: namespaced.to_owned(),
+            merge_operator,
+        }
+    }
+
+    /// The newest version of `soliton_id` at or below `seqno` over every source.
+    fn newest(&self, soliton_id: &[u8], seqno: u64) -> Result<Option<Entry>> {
+        let mut newest: Option<Entry> = None;
+        for source in &self.sources {
+            if !source.may_contain(soliton_id) {
+                continue;
+            }
+            if let Some(e) = newest_version(source.as_ref(), soliton_id, seqno)? {
+                if newest.as_ref().is_none_or(|n| e.seqno > n.seqno) {
+                    newest = Some(e);
+                }
+            }
+        }
+        Ok(newest)
+    }
+
+    /// The causet_locale of `soliton_id`, if it is live.
+    pub(crate) fn get(&self, soliton_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        let mut operands = Vec::new();
+        let mut next = self.newest(soliton_id, self.read_seqno)?;
+        let existing = loop {
+            let e = match next {
+                Some(e) => e,
+                None => break None,
+            };
+            if self.covered(&e) {
+                break None;
+            }
+            match e.kind {
+                ValueKind::Put => break Some(e.causet_locale),
+                ValueKind::Delete => break None,
+                ValueKind::Merge => {
+                    next = match e.seqno.checked_sub(1) {
+                        Some(seqno) => self.newest(soliton_id, seqno)?,
+                        None => None,
+                    };
+                    operands.push(e.causet_locale);
+                }
+            }
+        };
+        if operands.is_empty() {
+            return Ok(existing);
+        }
+        let operator = self
+            .merge_operator
+            .as_ref()
+            .ok_or_else(|| Error::NoMergeOperator(self.namespaced.clone()))?;
+        let operands: Vec<&[u8]> = operands.iter().rev().map(Vec::as_slice).collect();
+        operator.full_merge(soliton_id, existing.as_deref(), &operands).map(Some)
+    }
+
+    /// Whether a range tombstone hides `e`.
+    fn covered(&self, e: &Entry) -> bool {
+        self.range_tombstones
+            .iter()
+            .any(|t| t.covers(&e.soliton_id, e.seqno, self.read_seqno))
+    }
+
+    /// The smallest soliton_id at or after `soliton_id` (after it, if not `inclusive`) that has
+    /// any version in any source, visible or not.
+    pub(crate) fn next_soliton_id(&self, soliton_id: &[u8], inclusive: bool) -> Result<Option<Vec<u8>>> {
+        let target = (soliton_id, if inclusive { u64::MAX } else { 0 });
+        let mut next: Option<Vec<u8>> = None;
+        for source in &self.sources {
+            if let Some(e) = source.seek(target)? {
+                if next.as_ref().is_none_or(|n| e.soliton_id < *n) {
+                    next = Some(e.soliton_id);
+                }
+            }
+        }
+        Ok(next)
+    }
+
+    /// The largest soliton_id before `soliton_id` (or at it, if `inclusive`) that has any
+    /// version in any source, visible or not.  `None` for `soliton_id` means past the end.
+    pub(crate) fn prev_soliton_id(&self, soliton_id: Option<&[u8]>, inclusive: bool) -> Result<Option<Vec<u8>>> {
+        let mut prev: Option<Vec<u8>> = None;
+        for source in &self.sources {
+            let found = match soliton_id {
+                Some(k) => source.seek_before((k, if inclusive { 0 } else { u64::MAX }))?,
+                None => source.last()?,
+            };
+            if let Some(e) = found {
+                if prev.as_ref().is_none_or(|p| e.soliton_id > *p) {
+                    prev = Some(e.soliton_id);
+                }
+            }
+        }
+        Ok(prev)
+    }
+}
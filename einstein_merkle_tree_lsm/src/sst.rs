@@ -0,0 +1,160 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use fdb_traits::{Error, Result, SstExt, SstFileInfo, SstWriter, SstWriterBuilder};
+
+use crate::coding::{Entry, ValueKind};
+use crate::einstein_merkle_tree::LsmEinsteinMerkleTree;
+use crate::env::Env;
+use crate::table::{TableBuilder, GREEDOID_NAMESPACED};
+
+impl SstExt for LsmEinsteinMerkleTree {
+    type SstWriter = LsmSstWriter;
+    type SstWriterBuilder = LsmSstWriterBuilder;
+}
+
+/// Creates `LsmSstWriter`s that write table files the way a given einstein_merkle_tree does.
+#[derive(Default)]
+pub struct LsmSstWriterBuilder {
+    db: Option<LsmEinsteinMerkleTree>,
+    namespaced: Option<String>,
+}
+
+impl SstWriterBuilder<LsmEinsteinMerkleTree> for LsmSstWriterBuilder {
+    fn new() -> Self {
+        LsmSstWriterBuilder::default()
+    }
+
+    fn set_db(mut self, db: &LsmEinsteinMerkleTree) -> Self {
+        self.db = Some(db.clone());
+        self
+    }
+
+    fn set_namespaced(mut self, namespaced: &str) -> Self {
+        self.namespaced = Some(namespaced.to_owned());
+        self
+    }
+
+    fn build(self, path: &str) -> Result<LsmSstWriter> {
+        let db = self
+            .db
+            .ok_or_else(|| Error::Other("an LsmSstWriter needs the einstein_merkle_tree it writes for".to_owned()))?;
+        let opts = &db.core.opts;
+        let path = PathBuf::from(path);
+        let mut builder = TableBuilder::create(opts.env.as_ref(), &path, opts.block_size, opts.bloom_bits_per_soliton_id)?;
+        if let Some(namespaced) = &self.namespaced {
+            db.core.check_namespaced(namespaced)?;
+            builder.set_greedoid(GREEDOID_NAMESPACED, namespaced.as_bytes());
+        }
+        Ok(LsmSstWriter {
+            env: opts.env.clone(),
+            path,
+            builder,
+            smallest: None,
+            num_entries: 0,
+        })
+    }
+}
+
+/// Writes a table file for `ImportExt::ingest_lightlike_file_namespaced`.
+///
+/// Every version is written at sequence number 0; ingestion gives the file the sequence number
+/// it is visible at.
+pub struct LsmSstWriter {
+    env: Arc<dyn Env>,
+    path: PathBuf,
+    builder: TableBuilder,
+    smallest: Option<Vec<u8>>,
+    num_entries: u64,
+}
+
+impl LsmSstWriter {
+    fn add(&mut self, kind: ValueKind, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        if let Some(last) = self.builder.last_soliton_id() {
+            if soliton_id <= last {
+                return Err(Error::Other(format!(
+                    "soliton_id {:?} added to {} after {:?}",
+                    soliton_id,
+                    self.path.display(),
+                    last
+                )));
+            }
+        }
+        self.builder.add(&Entry {
+            soliton_id: soliton_id.to_vec(),
+            seqno: 0,
+            kind,
+            causet_locale: causet_locale.to_vec(),
+        })?;
+        self.smallest.get_or_insert_with(|| soliton_id.to_vec());
+        self.num_entries += 1;
+        Ok(())
+    }
+}
+
+impl SstWriter for LsmSstWriter {
+    type SstFileInfo = LsmSstFileInfo;
+
+    fn put(&mut self, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        self.add(ValueKind::Put, soliton_id, causet_locale)
+    }
+
+    fn delete(&mut self, soliton_id: &[u8]) -> Result<()> {
+        self.add(ValueKind::Delete, soliton_id, &[])
+    }
+
+    fn file_size(&mut self) -> u64 {
+        self.builder.estimated_size()
+    }
+
+    fn finish(self) -> Result<LsmSstFileInfo> {
+        if self.num_entries == 0 {
+            drop(self.builder);
+            let _ = self.env.delete_file(&self.path);
+            return Err(Error::Other(format!("no soliton_ids were added to {}", self.path.display())));
+        }
+        let largest = self.builder.last_soliton_id().unwrap().to_vec();
+        let summary = self.builder.finish()?;
+        Ok(LsmSstFileInfo {
+            path: self.path,
+            smallest: self.smallest.unwrap(),
+            largest,
+            num_entries: self.num_entries,
+            file_size: summary.file_size,
+        })
+    }
+}
+
+/// What an `LsmSstWriter` wrote.
+#[derive(Clone, Debug)]
+pub struct LsmSstFileInfo {
+    path: PathBuf,
+    smallest: Vec<u8>,
+    largest: Vec<u8>,
+    num_entries: u64,
+    file_size: u64,
+}
+
+impl SstFileInfo for LsmSstFileInfo {
+    fn file_path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn smallest_soliton_id(&self) -> &[u8] {
+        &self.smallest
+    }
+
+    fn largest_soliton_id(&self) -> &[u8] {
+        &self.largest
+    }
+
+    fn num_entries(&self) -> u64 {
+        self.num_entries
+    }
+
+    fn file_size(&self) -> u64 {
+        self.file_size
+    }
+}
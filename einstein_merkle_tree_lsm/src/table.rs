@@ -0,0 +1,619 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Sorted table files.
+//!
+//! A table file holds the versions of a range of soliton_ids of one causet_merge family, in
+//! `cmp_internal` order, together with the range tombstones that apply to them:
+//!
+//! ```text
+//! data block* | index block | filter block | range tombstone block | greedoids block | footer
+//! ```
+//!
+//! Every block is followed by the crc32 of its contents.  The index has one entry per data
+//! block: the last version in the block and where the block is.  The filter is a bloom filter
+//! over the soliton_ids of the file, and the greedoids block maps names to values describing
+//! the file.  The footer holds the offset and size of the four metadata blocks and a magic
+//! number.
+//!
+//! Opening a table reads the metadata blocks into memory; data blocks are read on demand,
+//! through the block cache.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use fdb_traits::Result;
+
+use crate::bloom;
+use crate::cache::{Block, BlockCache};
+use crate::coding::{
+    checksum, cmp_internal, corruption, put_bytes, put_u32, put_u64, put_varint, Decoder, Entry, RangeTombstone,
+    ValueKind,
+};
+use crate::env::{Env, RandomAccessFile, WritableFile};
+use crate::source::Source;
+
+const MAGIC: u64 = 0x6569_6e73_7465_696e;
+const FOOTER_LEN: usize = 4 * 16 + 8;
+const CHECKSUM_LEN: u64 = 4;
+
+pub(crate) const GREEDOID_NUM_ENTRIES: &str = "einsteindb.num.entries";
+pub(crate) const GREEDOID_NUM_DELETIONS: &str = "einsteindb.num.deletions";
+pub(crate) const GREEDOID_NUM_RANGE_DELETIONS: &str = "einsteindb.num.range-deletions";
+/// The causet_merge family a file written by an `LsmSstWriter` is meant for.
+pub(crate) const GREEDOID_NAMESPACED: &str = "einsteindb.namespaced";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct BlockHandle {
+    offset: u64,
+    size: u64,
+}
+
+impl BlockHandle {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_u64(buf, self.offset);
+        put_u64(buf, self.size);
+    }
+
+    fn decode(d: &mut Decoder<'_>) -> Result<BlockHandle> {
+        Ok(BlockHandle {
+            offset: d.u64()?,
+            size: d.u64()?,
+        })
+    }
+}
+
+/// What the manifest needs to know about a table file once it is written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct TableSummary {
+    pub(crate) file_size: u64,
+    /// The smallest soliton_id of a version or range tombstone in the file.
+    pub(crate) smallest: Vec<u8>,
+    /// The largest soliton_id of a version in the file, or the end of its last range tombstone.
+    pub(crate) largest: Vec<u8>,
+    pub(crate) smallest_seqno: u64,
+    pub(crate) largest_seqno: u64,
+}
+
+/// Writes a table file.  Versions must be added in `cmp_internal` order.
+pub(crate) struct TableBuilder {
+    file: Box<dyn WritableFile>,
+    offset: u64,
+    block_size: usize,
+    bloom_bits_per_soliton_id: usize,
+    block: Vec<u8>,
+    index: Vec<u8>,
+    filter_soliton_ids: Vec<Vec<u8>>,
+    range_tombstones: Vec<RangeTombstone>,
+    greedoids: BTreeMap<String, Vec<u8>>,
+    last: Option<(Vec<u8>, u64)>,
+    smallest: Option<Vec<u8>>,
+    largest: Option<Vec<u8>>,
+    smallest_seqno: u64,
+    largest_seqno: u64,
+    num_entries: u64,
+    num_deletions: u64,
+}
+
+impl TableBuilder {
+    pub(crate) fn create(
+        env: &dyn Env,
+        path: &Path,
+        block_size: usize,
+        bloom_bits_per_soliton_id: usize,
+    ) -> Result<TableBuilder> {
+        Ok(TableBuilder {
+            file: env.new_writable_file(path)?,
+            offset: 0,
+            block_size: block_size.max(1),
+            bloom_bits_per_soliton_id,
+            block: Vec::new(),
+            index: Vec::new(),
+            filter_soliton_ids: Vec::new(),
+            range_tombstones: Vec::new(),
+            greedoids: BTreeMap::new(),
+            last: None,
+            smallest: None,
+            largest: None,
+            smallest_seqno: u64::MAX,
+            largest_seqno: 0,
+            num_entries: 0,
+            num_deletions: 0,
+        })
+    }
+
+    fn note_range(&mut self, smallest: &[u8], largest: &[u8], seqno: u64) {
+        if self.smallest.as_deref().is_none_or(|s| smallest < s) {
+            self.smallest = Some(smallest.to_vec());
+        }
+        if self.largest.as_deref().is_none_or(|l| largest > l) {
+            self.largest = Some(largest.to_vec());
+        }
+        self.smallest_seqno = self.smallest_seqno.min(seqno);
+        self.largest_seqno = self.largest_seqno.max(seqno);
+    }
+
+    pub(crate) fn add(&mut self, entry: &Entry) -> Result<()> {
+        if let Some((soliton_id, seqno)) = &self.last {
+            if cmp_internal((soliton_id, *seqno), entry.internal_key()).is_ge() {
+                return Err(corruption(format!(
+                    "{:?}@{} added after {:?}@{}",
+                    entry.soliton_id, entry.seqno, soliton_id, seqno
+                )));
+            }
+        }
+        if self.filter_soliton_ids.last() != Some(&entry.soliton_id) {
+            self.filter_soliton_ids.push(entry.soliton_id.clone());
+        }
+        self.note_range(&entry.soliton_id, &entry.soliton_id, entry.seqno);
+        self.num_entries += 1;
+        if entry.kind == ValueKind::Delete {
+            self.num_deletions += 1;
+        }
+        entry.encode(&mut self.block);
+        self.last = Some((entry.soliton_id.clone(), entry.seqno));
+        if self.block.len() >= self.block_size {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn add_range_tombstone(&mut self, tombstone: RangeTombstone) {
+        self.note_range(&tombstone.start, &tombstone.end, tombstone.seqno);
+        self.range_tombstones.push(tombstone);
+    }
+
+    /// Record a greedoid of the file, next to the ones every table file gets.
+    pub(crate) fn set_greedoid(&mut self, name: &str, causet_locale: &[u8]) {
+        self.greedoids.insert(name.to_owned(), causet_locale.to_vec());
+    }
+
+    /// The soliton_id of the last version added.
+    pub(crate) fn last_soliton_id(&self) -> Option<&[u8]> {
+        self.last.as_ref().map(|(soliton_id, _)| soliton_id.as_slice())
+    }
+
+    /// The size the file would have if it were finished now, metadata aside.
+    pub(crate) fn estimated_size(&self) -> u64 {
+        self.offset + self.block.len() as u64
+    }
+
+    fn write_block(&mut self, contents: &[u8]) -> Result<BlockHandle> {
+        let handle = BlockHandle {
+            offset: self.offset,
+            size: contents.len() as u64,
+        };
+        let mut trailer = Vec::with_capacity(4);
+        put_u32(&mut trailer, checksum(contents));
+        self.file.append(contents)?;
+        self.file.append(&trailer)?;
+        self.offset += handle.size + CHECKSUM_LEN;
+        Ok(handle)
+    }
+
+    fn flush_block(&mut self) -> Result<()> {
+        if self.block.is_empty() {
+            return Ok(());
+        }
+        let block = std::mem::take(&mut self.block);
+        let handle = self.write_block(&block)?;
+        let (soliton_id, seqno) = self.last.clone().unwrap();
+        put_bytes(&mut self.index, &soliton_id);
+        put_u64(&mut self.index, seqno);
+        handle.encode(&mut self.index);
+        Ok(())
+    }
+
+    /// Write out the metadata blocks and make the file durable.
+    pub(crate) fn finish(mut self) -> Result<TableSummary> {
+        self.flush_block()?;
+        let index = std::mem::take(&mut self.index);
+        let index_handle = self.write_block(&index)?;
+
+        let filter = if self.bloom_bits_per_soliton_id > 0 {
+            bloom::build(&self.filter_soliton_ids, self.bloom_bits_per_soliton_id)
+        } else {
+            Vec::new()
+        };
+        let filter_handle = self.write_block(&filter)?;
+
+        let mut tombstones = Vec::new();
+        put_varint(&mut tombstones, self.range_tombstones.len() as u64);
+        for t in &self.range_tombstones {
+            t.encode(&mut tombstones);
+        }
+        let tombstone_handle = self.write_block(&tombstones)?;
+
+        self.greedoids
+            .insert(GREEDOID_NUM_ENTRIES.to_owned(), self.num_entries.to_le_bytes().to_vec());
+        self.greedoids
+            .insert(GREEDOID_NUM_DELETIONS.to_owned(), self.num_deletions.to_le_bytes().to_vec());
+        self.greedoids.insert(
+            GREEDOID_NUM_RANGE_DELETIONS.to_owned(),
+            (self.range_tombstones.len() as u64).to_le_bytes().to_vec(),
+        );
+        let mut greedoids = Vec::new();
+        put_varint(&mut greedoids, self.greedoids.len() as u64);
+        for (name, causet_locale) in &self.greedoids {
+            put_bytes(&mut greedoids, name.as_bytes());
+            put_bytes(&mut greedoids, causet_locale);
+        }
+        let greedoids_handle = self.write_block(&greedoids)?;
+
+        let mut footer = Vec::with_capacity(FOOTER_LEN);
+        for handle in &[index_handle, filter_handle, tombstone_handle, greedoids_handle] {
+            handle.encode(&mut footer);
+        }
+        put_u64(&mut footer, MAGIC);
+        self.file.append(&footer)?;
+        self.file.sync()?;
+        self.offset += footer.len() as u64;
+
+        Ok(TableSummary {
+            file_size: self.offset,
+            smallest: self.smallest.unwrap_or_default(),
+            largest: self.largest.unwrap_or_default(),
+            smallest_seqno: if self.smallest_seqno == u64::MAX { 0 } else { self.smallest_seqno },
+            largest_seqno: self.largest_seqno,
+        })
+    }
+}
+
+struct IndexEntry {
+    last_soliton_id: Vec<u8>,
+    last_seqno: u64,
+    handle: BlockHandle,
+}
+
+/// An open table file.
+///
+/// A table that has been compacted away is marked obsolete; its file is deleted once the last
+/// reader holding it lets go.
+pub(crate) struct Table {
+    number: u64,
+    path: PathBuf,
+    env: Arc<dyn Env>,
+    file: Box<dyn RandomAccessFile>,
+    index: Vec<IndexEntry>,
+    filter: Vec<u8>,
+    range_tombstones: Vec<RangeTombstone>,
+    greedoids: BTreeMap<String, Vec<u8>>,
+    cache: Option<Arc<BlockCache>>,
+    obsolete: AtomicBool,
+}
+
+fn read_checked(file: &dyn RandomAccessFile, handle: BlockHandle) -> Result<Vec<u8>> {
+    let mut buf = vec![0; (handle.size + CHECKSUM_LEN) as usize];
+    file.read_exact_at(handle.offset, &mut buf)?;
+    let contents_len = handle.size as usize;
+    let expected = Decoder::new(&buf[contents_len..]).u32()?;
+    buf.truncate(contents_len);
+    if checksum(&buf) != expected {
+        return Err(corruption(format!("block at {} fails its checksum", handle.offset)));
+    }
+    Ok(buf)
+}
+
+impl Table {
+    pub(crate) fn open(env: Arc<dyn Env>, path: &Path, number: u64, cache: Option<Arc<BlockCache>>) -> Result<Table> {
+        let file = env.new_random_access_file(path)?;
+        let size = file.size();
+        if size < FOOTER_LEN as u64 {
+            return Err(corruption(format!("{} is too short to be a table file", path.display())));
+        }
+        let mut footer = vec![0; FOOTER_LEN];
+        file.read_exact_at(size - FOOTER_LEN as u64, &mut footer)?;
+        let mut d = Decoder::new(&footer);
+        let handles = [
+            BlockHandle::decode(&mut d)?,
+            BlockHandle::decode(&mut d)?,
+            BlockHandle::decode(&mut d)?,
+            BlockHandle::decode(&mut d)?,
+        ];
+        if d.u64()? != MAGIC {
+            return Err(corruption(format!("{} is not a table file", path.display())));
+        }
+
+        let index_block = read_checked(file.as_ref(), handles[0])?;
+        let mut d = Decoder::new(&index_block);
+        let mut index = Vec::new();
+        while !d.is_empty() {
+            index.push(IndexEntry {
+                last_soliton_id: d.bytes()?.to_vec(),
+                last_seqno: d.u64()?,
+                handle: BlockHandle::decode(&mut d)?,
+            });
+        }
+
+        let filter = read_checked(file.as_ref(), handles[1])?;
+
+        let tombstone_block = read_checked(file.as_ref(), handles[2])?;
+        let mut d = Decoder::new(&tombstone_block);
+        let count = d.varint()?;
+        let range_tombstones = (0..count)
+            .map(|_| RangeTombstone::decode(&mut d))
+            .collect::<Result<_>>()?;
+
+        let greedoids_block = read_checked(file.as_ref(), handles[3])?;
+        let mut d = Decoder::new(&greedoids_block);
+        let count = d.varint()?;
+        let mut greedoids = BTreeMap::new();
+        for _ in 0..count {
+            let name = String::from_utf8(d.bytes()?.to_vec()).map_err(|_| corruption("greedoid name is not UTF-8"))?;
+            greedoids.insert(name, d.bytes()?.to_vec());
+        }
+
+        Ok(Table {
+            number,
+            path: path.to_owned(),
+            env,
+            file,
+            index,
+            filter,
+            range_tombstones,
+            greedoids,
+            cache,
+            obsolete: AtomicBool::new(false),
+        })
+    }
+
+    pub(crate) fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub(crate) fn greedoids(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.greedoids
+    }
+
+    pub(crate) fn file_size(&self) -> u64 {
+        self.file.size()
+    }
+
+    /// Delete the file once the table is dropped.
+    pub(crate) fn mark_obsolete(&self) {
+        self.obsolete.store(true, Ordering::Release);
+    }
+
+    fn read_block(&self, i: usize, fill_cache: bool) -> Result<Block> {
+        let handle = self.index[i].handle;
+        let id = (self.number, handle.offset);
+        if let Some(block) = self.cache.as_ref().and_then(|c| c.get(id)) {
+            return Ok(block);
+        }
+        let contents = read_checked(self.file.as_ref(), handle)?;
+        let mut d = Decoder::new(&contents);
+        let mut entries = Vec::new();
+        while !d.is_empty() {
+            entries.push(Entry::decode(&mut d)?);
+        }
+        let block = Arc::new(entries);
+        if fill_cache {
+            if let Some(cache) = &self.cache {
+                cache.insert(id, block.clone(), contents.len());
+            }
+        }
+        Ok(block)
+    }
+
+    /// The index of the first block whose last version is at or after `target`.
+    fn find_block(&self, target: (&[u8], u64)) -> usize {
+        self.index
+            .partition_point(|e| cmp_internal((&e.last_soliton_id, e.last_seqno), target).is_lt())
+    }
+
+    /// Every version in the file, in order, bypassing the block cache.
+    pub(crate) fn scan(self: &Arc<Self>) -> TableScan {
+        TableScan {
+            table: self.clone(),
+            block: Arc::new(Vec::new()),
+            next_block: 0,
+            pos: 0,
+        }
+    }
+}
+
+impl Drop for Table {
+    fn drop(&mut self) {
+        if self.obsolete.load(Ordering::Acquire) {
+            if let Some(cache) = &self.cache {
+                cache.evict_file(self.number);
+            }
+            // A leftover file is harmless: it is not in the manifest and is removed on the next
+            // open.
+            let _ = self.env.delete_file(&self.path);
+        }
+    }
+}
+
+impl Table {
+    /// The first version at or after `target` in `cmp_internal` order.
+    pub(crate) fn seek(&self, target: (&[u8], u64), fill_cache: bool) -> Result<Option<Entry>> {
+        let i = self.find_block(target);
+        if i == self.index.len() {
+            return Ok(None);
+        }
+        let block = self.read_block(i, fill_cache)?;
+        let j = block.partition_point(|e| cmp_internal(e.internal_key(), target).is_lt());
+        Ok(block.get(j).cloned())
+    }
+
+    /// The last version strictly before `target` in `cmp_internal` order.
+    pub(crate) fn seek_before(&self, target: (&[u8], u64), fill_cache: bool) -> Result<Option<Entry>> {
+        let i = self.find_block(target);
+        if i < self.index.len() {
+            let block = self.read_block(i, fill_cache)?;
+            let j = block.partition_point(|e| cmp_internal(e.internal_key(), target).is_lt());
+            if j > 0 {
+                return Ok(Some(block[j - 1].clone()));
+            }
+        }
+        if i == 0 {
+            return Ok(None);
+        }
+        Ok(self.read_block(i - 1, fill_cache)?.last().cloned())
+    }
+
+    pub(crate) fn last(&self, fill_cache: bool) -> Result<Option<Entry>> {
+        match self.index.len() {
+            0 => Ok(None),
+            n => Ok(self.read_block(n - 1, fill_cache)?.last().cloned()),
+        }
+    }
+
+    pub(crate) fn range_tombstones(&self) -> &[RangeTombstone] {
+        &self.range_tombstones
+    }
+
+    /// Whether the file may hold a version of `soliton_id`.
+    pub(crate) fn may_contain(&self, soliton_id: &[u8]) -> bool {
+        bloom::may_contain(&self.filter, soliton_id)
+    }
+}
+
+/// Reads of a table file, filling the block cache or not.
+pub(crate) struct TableSource {
+    pub(crate) table: Arc<Table>,
+    pub(crate) fill_cache: bool,
+}
+
+impl Source for TableSource {
+    fn seek(&self, target: (&[u8], u64)) -> Result<Option<Entry>> {
+        self.table.seek(target, self.fill_cache)
+    }
+
+    fn seek_before(&self, target: (&[u8], u64)) -> Result<Option<Entry>> {
+        self.table.seek_before(target, self.fill_cache)
+    }
+
+    fn last(&self) -> Result<Option<Entry>> {
+        self.table.last(self.fill_cache)
+    }
+
+    fn range_tombstones(&self) -> Vec<RangeTombstone> {
+        self.table.range_tombstones.clone()
+    }
+
+    fn may_contain(&self, soliton_id: &[u8]) -> bool {
+        self.table.may_contain(soliton_id)
+    }
+}
+
+/// A sequential read of every version of a table file.
+pub(crate) struct TableScan {
+    table: Arc<Table>,
+    block: Block,
+    next_block: usize,
+    pos: usize,
+}
+
+impl std::iter::Iterator for TableScan {
+    type Item = Result<Entry>;
+
+    fn next(&mut self) -> Option<Result<Entry>> {
+        while self.pos == self.block.len() {
+            if self.next_block == self.table.index.len() {
+                return None;
+            }
+            match self.table.read_block(self.next_block, false) {
+                Ok(block) => self.block = block,
+                Err(e) => {
+                    self.next_block = self.table.index.len();
+                    return Some(Err(e));
+                }
+            }
+            self.next_block += 1;
+            self.pos = 0;
+        }
+        self.pos += 1;
+        Some(Ok(self.block[self.pos - 1].clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::DefaultEnv;
+
+    fn put(soliton_id: &[u8], seqno: u64, causet_locale: &[u8]) -> Entry {
+        Entry {
+            soliton_id: soliton_id.to_vec(),
+            seqno,
+            kind: ValueKind::Put,
+            causet_locale: causet_locale.to_vec(),
+        }
+    }
+
+    fn build(dir: &Path, entries: &[Entry], tombstones: &[RangeTombstone]) -> (TableSummary, Arc<Table>) {
+        let path = dir.join("000007.sst");
+        let mut builder = TableBuilder::create(&DefaultEnv, &path, 64, 10).unwrap();
+        for e in entries {
+            builder.add(e).unwrap();
+        }
+        for t in tombstones {
+            builder.add_range_tombstone(t.clone());
+        }
+        let summary = builder.finish().unwrap();
+        let cache = Arc::new(BlockCache::new(1 << 20));
+        let table = Table::open(Arc::new(DefaultEnv), &path, 7, Some(cache)).unwrap();
+        (summary, Arc::new(table))
+    }
+
+    #[test]
+    fn test_build_and_seek() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut entries = Vec::new();
+        for i in 0..100u32 {
+            let k = format!("k{:03}", i);
+            entries.push(put(k.as_bytes(), 200 + u64::from(i), b"new"));
+            entries.push(put(k.as_bytes(), u64::from(i) + 1, b"old"));
+        }
+        let tombstone = RangeTombstone {
+            start: b"k050".to_vec(),
+            end: b"z".to_vec(),
+            seqno: 150,
+        };
+        let (summary, table) = build(dir.path(), &entries, std::slice::from_ref(&tombstone));
+        assert_eq!(summary.smallest, b"k000");
+        assert_eq!(summary.largest, b"z");
+        assert_eq!((summary.smallest_seqno, summary.largest_seqno), (1, 299));
+        assert!(table.index.len() > 1);
+
+        let found = table.seek((b"k042", u64::MAX), true).unwrap().unwrap();
+        assert_eq!((found.seqno, found.causet_locale.as_slice()), (242, &b"new"[..]));
+        let found = table.seek((b"k042", 100), true).unwrap().unwrap();
+        assert_eq!((found.seqno, found.causet_locale.as_slice()), (43, &b"old"[..]));
+        let found = table.seek((b"k042", 0), true).unwrap().unwrap();
+        assert_eq!(found.soliton_id, b"k043");
+        assert!(table.seek((b"k100", u64::MAX), true).unwrap().is_none());
+
+        let found = table.seek_before((b"k042", u64::MAX), true).unwrap().unwrap();
+        assert_eq!((found.soliton_id.as_slice(), found.seqno), (&b"k041"[..], 42));
+        assert!(table.seek_before((b"k000", u64::MAX), true).unwrap().is_none());
+        assert_eq!(table.last(true).unwrap().unwrap().soliton_id, b"k099");
+        assert!(table.may_contain(b"k042"));
+
+        assert_eq!(table.range_tombstones(), &[tombstone]);
+        assert_eq!(table.greedoids()[GREEDOID_NUM_ENTRIES], 200u64.to_le_bytes());
+        let scanned: Vec<Entry> = table.scan().collect::<Result<_>>().unwrap();
+        assert_eq!(scanned, entries);
+    }
+
+    #[test]
+    fn test_out_of_order_and_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("000001.sst");
+        let mut builder = TableBuilder::create(&DefaultEnv, &path, 64, 10).unwrap();
+        builder.add(&put(b"b", 1, b"")).unwrap();
+        assert!(builder.add(&put(b"a", 2, b"")).is_err());
+        assert!(builder.add(&put(b"b", 1, b"")).is_err());
+        builder.add(&put(b"c", 1, b"causet_locale")).unwrap();
+        builder.finish().unwrap();
+
+        let mut data = std::fs::read(&path).unwrap();
+        data[3] ^= 0xff;
+        std::fs::write(&path, &data).unwrap();
+        let table = Table::open(Arc::new(DefaultEnv), &path, 1, None).unwrap();
+        assert!(table.seek((b"b", u64::MAX), false).is_err());
+    }
+}
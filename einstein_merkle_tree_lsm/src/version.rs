@@ -0,0 +1,350 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! The set of live table files, and the manifest that makes it durable.
+//!
+//! A `Version` is immutable: flushes and compactions build a new one and swap it in, so a reader
+//! holding a `Version` keeps seeing, and keeps alive, the files it was created with.
+//!
+//! The manifest is a single record holding the whole `Version` together with the counters the
+//! einstein_merkle_tree needs to reopen.  It is rewritten to a temporary file and renamed over
+//! the previous one on every change, so it is always either the old or the new state.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use fdb_traits::Result;
+
+use crate::coding::{checksum, corruption, put_bytes, put_u32, put_u64, put_varint, Decoder};
+use crate::env::Env;
+use crate::table::{Table, TableSummary};
+
+pub(crate) const MANIFEST: &str = "MANIFEST";
+/// Locked by the einstein_merkle_tree that has the directory open.
+pub(crate) const LOCK: &str = "LOCK";
+const MANIFEST_TMP: &str = "MANIFEST.tmp";
+
+pub(crate) fn table_path(dir: &Path, number: u64) -> PathBuf {
+    dir.join(format!("{:06}.sst", number))
+}
+
+pub(crate) fn log_path(dir: &Path, number: u64) -> PathBuf {
+    dir.join(format!("{:06}.log", number))
+}
+
+/// The number of the table or log file at `path`, if it is one.
+pub(crate) fn parse_file_name(path: &Path) -> Option<(u64, &str)> {
+    let name = path.file_name()?.to_str()?;
+    let (number, ext) = name.split_once('.')?;
+    Some((number.parse().ok()?, ext))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct FileMeta {
+    pub(crate) number: u64,
+    pub(crate) summary: TableSummary,
+}
+
+impl FileMeta {
+    pub(crate) fn size(&self) -> u64 {
+        self.summary.file_size
+    }
+
+    pub(crate) fn smallest(&self) -> &[u8] {
+        &self.summary.smallest
+    }
+
+    pub(crate) fn largest(&self) -> &[u8] {
+        &self.summary.largest
+    }
+
+    /// Whether the file may hold soliton_ids in `[smallest, largest]`.
+    pub(crate) fn overlaps(&self, smallest: &[u8], largest: &[u8]) -> bool {
+        self.smallest() <= largest && smallest <= self.largest()
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        put_u64(buf, self.number);
+        put_u64(buf, self.summary.file_size);
+        put_bytes(buf, &self.summary.smallest);
+        put_bytes(buf, &self.summary.largest);
+        put_u64(buf, self.summary.smallest_seqno);
+        put_u64(buf, self.summary.largest_seqno);
+    }
+
+    fn decode(d: &mut Decoder<'_>) -> Result<FileMeta> {
+        Ok(FileMeta {
+            number: d.u64()?,
+            summary: TableSummary {
+                file_size: d.u64()?,
+                smallest: d.bytes()?.to_vec(),
+                largest: d.bytes()?.to_vec(),
+                smallest_seqno: d.u64()?,
+                largest_seqno: d.u64()?,
+            },
+        })
+    }
+}
+
+/// A live table file.
+pub(crate) struct TableFile {
+    pub(crate) meta: FileMeta,
+    pub(crate) table: Arc<Table>,
+}
+
+/// The table files of a causet_merge family, by l_naught.
+///
+/// L_naught 0 files may overlap one another and are ordered newest first.  The files of every
+/// other l_naught are disjoint and ordered by soliton_id.
+#[derive(Clone, Default)]
+pub(crate) struct NamespacedVersion {
+    pub(crate) l_naughts: Vec<Vec<Arc<TableFile>>>,
+}
+
+impl NamespacedVersion {
+    fn new(num_l_naughts: usize) -> NamespacedVersion {
+        NamespacedVersion {
+            l_naughts: vec![Vec::new(); num_l_naughts],
+        }
+    }
+
+    pub(crate) fn l_naught_zero_files(&self) -> usize {
+        self.l_naughts[0].len()
+    }
+
+    pub(crate) fn l_naught_size(&self, l_naught: usize) -> u64 {
+        self.l_naughts[l_naught].iter().map(|f| f.meta.size()).sum()
+    }
+
+    /// The files of `l_naught` that may hold soliton_ids in `[smallest, largest]`.
+    pub(crate) fn overlapping(&self, l_naught: usize, smallest: &[u8], largest: &[u8]) -> Vec<Arc<TableFile>> {
+        self.l_naughts[l_naught]
+            .iter()
+            .filter(|f| f.meta.overlaps(smallest, largest))
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn files(&self) -> impl std::iter::Iterator<Item = &Arc<TableFile>> {
+        self.l_naughts.iter().flatten()
+    }
+
+    /// Remove the files numbered in `removed` and add `added`, each with its l_naught.
+    fn edit(&mut self, removed: &[u64], added: &[(usize, Arc<TableFile>)]) {
+        for files in &mut self.l_naughts {
+            files.retain(|f| !removed.contains(&f.meta.number));
+        }
+        for (l_naught, file) in added {
+            self.l_naughts[*l_naught].push(file.clone());
+        }
+        self.l_naughts[0].sort_by_key(|f| Reverse(f.meta.summary.largest_seqno));
+        for files in &mut self.l_naughts[1..] {
+            files.sort_by(|a, b| a.meta.smallest().cmp(b.meta.smallest()));
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Version {
+    pub(crate) namespaceds: BTreeMap<String, NamespacedVersion>,
+}
+
+impl Version {
+    /// A copy of this version with `removed` and `added` applied to causet_merge family
+    /// `namespaced`.
+    pub(crate) fn edited(&self, namespaced: &str, removed: &[u64], added: &[(usize, Arc<TableFile>)]) -> Version {
+        let mut version = self.clone();
+        version.namespaceds.get_mut(namespaced).unwrap().edit(removed, added);
+        version
+    }
+}
+
+/// Everything the manifest records.
+pub(crate) struct ManifestState {
+    pub(crate) next_file_number: u64,
+    /// The sequence number of the last write published when the manifest was written.
+    pub(crate) last_seqno: u64,
+    /// Logs numbered below this only hold writes that are in table files.
+    pub(crate) log_number: u64,
+    /// The files of each causet_merge family, by l_naught.
+    pub(crate) namespaceds: BTreeMap<String, Vec<Vec<FileMeta>>>,
+}
+
+impl ManifestState {
+    pub(crate) fn new(namespaceds: &[String], num_l_naughts: usize) -> ManifestState {
+        ManifestState {
+            next_file_number: 1,
+            last_seqno: 0,
+            log_number: 0,
+            namespaceds: namespaceds
+                .iter()
+                .map(|n| (n.clone(), vec![Vec::new(); num_l_naughts]))
+                .collect(),
+        }
+    }
+
+    pub(crate) fn of_version(version: &Version, next_file_number: u64, last_seqno: u64, log_number: u64) -> ManifestState {
+        ManifestState {
+            next_file_number,
+            last_seqno,
+            log_number,
+            namespaceds: version
+                .namespaceds
+                .iter()
+                .map(|(name, v)| {
+                    let l_naughts = v
+                        .l_naughts
+                        .iter()
+                        .map(|files| files.iter().map(|f| f.meta.clone()).collect())
+                        .collect();
+                    (name.clone(), l_naughts)
+                })
+                .collect(),
+        }
+    }
+
+    /// Open the table files the manifest lists.
+    pub(crate) fn open_version(
+        &self,
+        env: &Arc<dyn Env>,
+        dir: &Path,
+        num_l_naughts: usize,
+        cache: &Option<Arc<crate::cache::BlockCache>>,
+    ) -> Result<Version> {
+        let mut version = Version::default();
+        for (name, l_naughts) in &self.namespaceds {
+            let mut v = NamespacedVersion::new(num_l_naughts.max(l_naughts.len()));
+            let mut added = Vec::new();
+            for (l_naught, files) in l_naughts.iter().enumerate() {
+                for meta in files {
+                    let table = Table::open(env.clone(), &table_path(dir, meta.number), meta.number, cache.clone())?;
+                    added.push((
+                        l_naught,
+                        Arc::new(TableFile {
+                            meta: meta.clone(),
+                            table: Arc::new(table),
+                        }),
+                    ));
+                }
+            }
+            v.edit(&[], &added);
+            version.namespaceds.insert(name.clone(), v);
+        }
+        Ok(version)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        put_u64(&mut buf, self.next_file_number);
+        put_u64(&mut buf, self.last_seqno);
+        put_u64(&mut buf, self.log_number);
+        put_varint(&mut buf, self.namespaceds.len() as u64);
+        for (name, l_naughts) in &self.namespaceds {
+            put_bytes(&mut buf, name.as_bytes());
+            put_varint(&mut buf, l_naughts.len() as u64);
+            for files in l_naughts {
+                put_varint(&mut buf, files.len() as u64);
+                for f in files {
+                    f.encode(&mut buf);
+                }
+            }
+        }
+        buf
+    }
+
+    fn decode(payload: &[u8]) -> Result<ManifestState> {
+        let mut d = Decoder::new(payload);
+        let next_file_number = d.u64()?;
+        let last_seqno = d.u64()?;
+        let log_number = d.u64()?;
+        let mut namespaceds = BTreeMap::new();
+        for _ in 0..d.varint()? {
+            let name = String::from_utf8(d.bytes()?.to_vec())
+                .map_err(|_| corruption("causet_merge family name is not UTF-8"))?;
+            let mut l_naughts = Vec::new();
+            for _ in 0..d.varint()? {
+                let files = (0..d.varint()?)
+                    .map(|_| FileMeta::decode(&mut d))
+                    .collect::<Result<_>>()?;
+                l_naughts.push(files);
+            }
+            namespaceds.insert(name, l_naughts);
+        }
+        Ok(ManifestState {
+            next_file_number,
+            last_seqno,
+            log_number,
+            namespaceds,
+        })
+    }
+
+    pub(crate) fn write(&self, env: &dyn Env, dir: &Path) -> Result<()> {
+        let payload = self.encode();
+        let mut record = Vec::with_capacity(payload.len() + 8);
+        put_u32(&mut record, checksum(&payload));
+        put_u32(&mut record, payload.len() as u32);
+        record.extend_from_slice(&payload);
+        let tmp = dir.join(MANIFEST_TMP);
+        let mut file = env.new_writable_file(&tmp)?;
+        file.append(&record)?;
+        file.sync()?;
+        drop(file);
+        env.rename_file(&tmp, &dir.join(MANIFEST))?;
+        env.sync_dir(dir)?;
+        Ok(())
+    }
+
+    pub(crate) fn read(env: &dyn Env, dir: &Path) -> Result<ManifestState> {
+        let data = env.new_random_access_file(&dir.join(MANIFEST))?.read_all()?;
+        let mut d = Decoder::new(&data);
+        let crc = d.u32()?;
+        let len = d.u32()? as usize;
+        match data.get(8..8 + len) {
+            Some(payload) if checksum(payload) == crc && data.len() == 8 + len => ManifestState::decode(payload),
+            _ => Err(corruption("MANIFEST fails its checksum")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::DefaultEnv;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = ManifestState::new(&["default".to_owned(), "write".to_owned()], 3);
+        state.next_file_number = 12;
+        state.last_seqno = 99;
+        state.log_number = 11;
+        state.namespaceds.get_mut("write").unwrap()[1].push(FileMeta {
+            number: 10,
+            summary: TableSummary {
+                file_size: 4096,
+                smallest: b"a".to_vec(),
+                largest: b"m".to_vec(),
+                smallest_seqno: 3,
+                largest_seqno: 90,
+            },
+        });
+        state.write(&DefaultEnv, dir.path()).unwrap();
+
+        let read = ManifestState::read(&DefaultEnv, dir.path()).unwrap();
+        assert_eq!(read.encode(), state.encode());
+
+        let mut data = std::fs::read(dir.path().join(MANIFEST)).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        std::fs::write(dir.path().join(MANIFEST), &data).unwrap();
+        assert!(ManifestState::read(&DefaultEnv, dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(parse_file_name(&table_path(Path::new("/db"), 42)), Some((42, "sst")));
+        assert_eq!(parse_file_name(&log_path(Path::new("/db"), 7)), Some((7, "log")));
+        assert_eq!(parse_file_name(Path::new("/db/MANIFEST")), None);
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! The write-ahead log.
+//!
+//! Every write alexandrov_poset_process is appended to the log as a single record before it is
+//! applied to the memtables, so the memtables can be rebuilt after a crash.  A record is the
+//! checksum and length of its payload followed by the payload:
+//!
+//! ```text
+//! crc32: u32 | len: u32 | first seqno: u64 | count: varint | op*
+//! ```
+//!
+//! A crash in the middle of an append leaves a torn record at the end of the log.  Replay stops
+//! at the first record that is short or fails its checksum, which drops exactly the writes that
+//! were never acknowledged as durable.
+
+use std::path::Path;
+
+use fdb_traits::Result;
+
+use crate::coding::{checksum, corruption, put_bytes, put_u32, put_u64, put_varint, Decoder};
+use crate::env::{Env, WritableFile};
+use crate::write_batch::WriteOp;
+
+const TAG_PUT: u8 = 1;
+const TAG_DELETE: u8 = 2;
+const TAG_DELETE_RANGE: u8 = 3;
+const TAG_MERGE: u8 = 4;
+
+const HEADER_LEN: usize = 8;
+
+/// Encode `ops`, the first of which is written at `first_seqno`.
+pub(crate) fn encode_batch(first_seqno: u64, ops: &[WriteOp]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_u64(&mut buf, first_seqno);
+    put_varint(&mut buf, ops.len() as u64);
+    for op in ops {
+        match op {
+            WriteOp::Put { namespaced, soliton_id, causet_locale } => {
+                buf.push(TAG_PUT);
+                put_bytes(&mut buf, namespaced.as_bytes());
+                put_bytes(&mut buf, soliton_id);
+                put_bytes(&mut buf, causet_locale);
+            }
+            WriteOp::Delete { namespaced, soliton_id } => {
+                buf.push(TAG_DELETE);
+                put_bytes(&mut buf, namespaced.as_bytes());
+                put_bytes(&mut buf, soliton_id);
+            }
+            WriteOp::DeleteRange { namespaced, start, end } => {
+                buf.push(TAG_DELETE_RANGE);
+                put_bytes(&mut buf, namespaced.as_bytes());
+                put_bytes(&mut buf, start);
+                put_bytes(&mut buf, end);
+            }
+            WriteOp::Merge { namespaced, soliton_id, operand } => {
+                buf.push(TAG_MERGE);
+                put_bytes(&mut buf, namespaced.as_bytes());
+                put_bytes(&mut buf, soliton_id);
+                put_bytes(&mut buf, operand);
+            }
+        }
+    }
+    buf
+}
+
+pub(crate) fn decode_batch(payload: &[u8]) -> Result<(u64, Vec<WriteOp>)> {
+    let mut d = Decoder::new(payload);
+    let first_seqno = d.u64()?;
+    let count = d.varint()?;
+    let mut ops = Vec::new();
+    for _ in 0..count {
+        let tag = d.u8()?;
+        let namespaced = String::from_utf8(d.bytes()?.to_vec())
+            .map_err(|_| corruption("causet_merge family name is not UTF-8"))?;
+        ops.push(match tag {
+            TAG_PUT => WriteOp::Put {
+                namespaced,
+                soliton_id: d.bytes()?.to_vec(),
+                causet_locale: d.bytes()?.to_vec(),
+            },
+            TAG_DELETE => WriteOp::Delete {
+                namespaced,
+                soliton_id: d.bytes()?.to_vec(),
+            },
+            TAG_DELETE_RANGE => WriteOp::DeleteRange {
+                namespaced,
+                start: d.bytes()?.to_vec(),
+                end: d.bytes()?.to_vec(),
+            },
+            TAG_MERGE => WriteOp::Merge {
+                namespaced,
+                soliton_id: d.bytes()?.to_vec(),
+                operand: d.bytes()?.to_vec(),
+            },
+            _ => return Err(corruption(format!("unknown write-ahead log op {}", tag))),
+        });
+    }
+    if !d.is_empty() {
+        return Err(corruption("trailing bytes in write-ahead log record"));
+    }
+    Ok((first_seqno, ops))
+}
+
+pub(crate) struct LogWriter {
+    file: Box<dyn WritableFile>,
+}
+
+impl LogWriter {
+    pub(crate) fn create(env: &dyn Env, path: &Path) -> Result<LogWriter> {
+        Ok(LogWriter {
+            file: env.new_writable_file(path)?,
+        })
+    }
+
+    pub(crate) fn add_record(&mut self, payload: &[u8]) -> Result<()> {
+        let mut record = Vec::with_capacity(HEADER_LEN + payload.len());
+        put_u32(&mut record, checksum(payload));
+        put_u32(&mut record, payload.len() as u32);
+        record.extend_from_slice(payload);
+        self.file.append(&record)?;
+        Ok(())
+    }
+
+    pub(crate) fn sync(&mut self) -> Result<()> {
+        self.file.sync()?;
+        Ok(())
+    }
+}
+
+/// The payloads of the intact records of the log at `path`.
+pub(crate) fn read_log(env: &dyn Env, path: &Path) -> Result<Vec<Vec<u8>>> {
+    let data = env.new_random_access_file(path)?.read_all()?;
+    let mut records = Vec::new();
+    let mut rest = data.as_slice();
+    while rest.len() >= HEADER_LEN {
+        let mut d = Decoder::new(rest);
+        let crc = d.u32()?;
+        let len = d.u32()? as usize;
+        let payload = match rest.get(HEADER_LEN..HEADER_LEN + len) {
+            Some(payload) if checksum(payload) == crc => payload,
+            _ => break,
+        };
+        records.push(payload.to_vec());
+        rest = &rest[HEADER_LEN + len..];
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use super::*;
+    use crate::env::DefaultEnv;
+
+    #[test]
+    fn test_torn_tail_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("000001.log");
+        let ops = vec![
+            WriteOp::Put {
+                namespaced: "default".to_owned(),
+                soliton_id: b"k".to_vec(),
+                causet_locale: b"v".to_vec(),
+            },
+            WriteOp::DeleteRange {
+                namespaced: "write".to_owned(),
+                start: b"a".to_vec(),
+                end: b"z".to_vec(),
+            },
+            WriteOp::Merge {
+                namespaced: "default".to_owned(),
+                soliton_id: b"k".to_vec(),
+                operand: b"+1".to_vec(),
+            },
+        ];
+        let mut log = LogWriter::create(&DefaultEnv, &path).unwrap();
+        log.add_record(&encode_batch(1, &ops)).unwrap();
+        log.add_record(&encode_batch(3, &ops[..1])).unwrap();
+        log.sync().unwrap();
+        drop(log);
+
+        // Simulate a crash half way through a third append.
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        let torn = encode_batch(4, &ops);
+        f.write_all(&[1, 2, 3, 4, 200, 0, 0, 0]).unwrap();
+        f.write_all(&torn[..5]).unwrap();
+
+        let records = read_log(&DefaultEnv, &path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(decode_batch(&records[0]).unwrap(), (1, ops.clone()));
+        assert_eq!(decode_batch(&records[1]).unwrap(), (3, ops[..1].to_vec()));
+    }
+}
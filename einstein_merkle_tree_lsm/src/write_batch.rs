@@ -0,0 +1,251 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use fdb_traits::{Error, Mutable, Result, WriteBatch, WriteBatchCommand, WriteBatchExt, WriteOptions};
+
+use crate::einstein_merkle_tree::LsmEinsteinMerkleTree;
+
+/// A single command recorded in a `LsmWriteBatch`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum WriteOp {
+    Put {
+        namespaced: String,
+        soliton_id: Vec<u8>,
+        causet_locale: Vec<u8>,
+    },
+    Delete {
+        namespaced: String,
+        soliton_id: Vec<u8>,
+    },
+    DeleteRange {
+        namespaced: String,
+        start: Vec<u8>,
+        end: Vec<u8>,
+    },
+    Merge {
+        namespaced: String,
+        soliton_id: Vec<u8>,
+        operand: Vec<u8>,
+    },
+}
+
+impl WriteOp {
+    pub(crate) fn namespaced(&self) -> &str {
+        match self {
+            WriteOp::Put { namespaced, .. }
+            | WriteOp::Delete { namespaced, .. }
+            | WriteOp::DeleteRange { namespaced, .. }
+            | WriteOp::Merge { namespaced, .. } => namespaced,
+        }
+    }
+
+    pub(crate) fn command(&self) -> WriteBatchCommand<'_> {
+        match self {
+            WriteOp::Put { namespaced, soliton_id, causet_locale } => WriteBatchCommand::Put {
+                namespaced,
+                soliton_id,
+                causet_locale,
+            },
+            WriteOp::Delete { namespaced, soliton_id } => WriteBatchCommand::Delete { namespaced, soliton_id },
+            WriteOp::DeleteRange { namespaced, start, end } => WriteBatchCommand::DeleteRange {
+                namespaced,
+                begin_soliton_id: start,
+                end_soliton_id: end,
+            },
+            WriteOp::Merge { namespaced, soliton_id, operand } => WriteBatchCommand::Merge {
+                namespaced,
+                soliton_id,
+                operand,
+            },
+        }
+    }
+}
+
+/// A write alexandrov_poset_process for `LsmEinsteinMerkleTree`.
+///
+/// Commands are buffered until `write`, which logs all of them as a single write-ahead log
+/// record and publishes them at once, so readers and lightlike_persistences see either none or
+/// all of them, before and after a crash.
+pub struct LsmWriteBatch {
+    db: LsmEinsteinMerkleTree,
+    ops: Vec<WriteOp>,
+    data_size: usize,
+    /// `(ops.len(), data_size)` at each save point.
+    save_points: Vec<(usize, usize)>,
+}
+
+impl LsmWriteBatch {
+    pub(crate) fn new(db: LsmEinsteinMerkleTree) -> LsmWriteBatch {
+        LsmWriteBatch {
+            db,
+            ops: Vec::new(),
+            data_size: 0,
+            save_points: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, op: WriteOp) {
+        self.data_size += op.command().encoded_len();
+        self.ops.push(op);
+    }
+}
+
+impl Mutable for LsmWriteBatch {
+    fn put_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        self.push(WriteOp::Put {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            causet_locale: causet_locale.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn delete_namespaced(&mut self, namespaced: &str, soliton_id: &[u8]) -> Result<()> {
+        self.push(WriteOp::Delete {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn delete_range_namespaced(&mut self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+        self.push(WriteOp::DeleteRange {
+            namespaced: namespaced.to_owned(),
+            start: begin_soliton_id.to_vec(),
+            end: end_soliton_id.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn merge_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()> {
+        self.push(WriteOp::Merge {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            operand: operand.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+impl WriteBatch<LsmEinsteinMerkleTree> for LsmWriteBatch {
+    fn with_capacity(e: &LsmEinsteinMerkleTree, cap: usize) -> LsmWriteBatch {
+        let mut wb = LsmWriteBatch::new(e.clone());
+        wb.ops.reserve(cap);
+        wb
+    }
+
+    fn write_opt(&self, opts: &WriteOptions) -> Result<()> {
+        self.db.apply(&self.ops, opts)
+    }
+
+    fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    fn count(&self) -> usize {
+        self.ops.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    fn should_write_to_einstein_merkle_tree(&self) -> bool {
+        self.ops.len() > LsmEinsteinMerkleTree::WRITE_BATCH_MAX_CAUSET_KEYS
+    }
+
+    fn clear(&mut self) {
+        self.ops.clear();
+        self.data_size = 0;
+        self.save_points.clear();
+    }
+
+    fn set_save_point(&mut self) {
+        self.save_points.push((self.ops.len(), self.data_size));
+    }
+
+    fn pop_save_point(&mut self) -> Result<()> {
+        self.save_points.pop().map(|_| ()).ok_or(Error::NoSavePoint)
+    }
+
+    fn rollback_to_save_point(&mut self) -> Result<()> {
+        let (count, data_size) = self.save_points.pop().ok_or(Error::NoSavePoint)?;
+        self.ops.truncate(count);
+        self.data_size = data_size;
+        Ok(())
+    }
+
+    fn iterate<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(WriteBatchCommand) -> Result<bool>,
+    {
+        for op in &self.ops {
+            if !f(op.command())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, mut other: LsmWriteBatch) -> Result<()> {
+        self.data_size += other.data_size;
+        self.ops.append(&mut other.ops);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{Peekable, ALL_NAMESPACEDS, NAMESPACED_WRITE};
+
+    use super::*;
+    use crate::options::{LsmNamespacedOptions, LsmOptions};
+
+    fn open(path: &std::path::Path) -> LsmEinsteinMerkleTree {
+        let namespaceds = ALL_NAMESPACEDS
+            .iter()
+            .map(|n| (n.to_string(), LsmNamespacedOptions::default()))
+            .collect();
+        LsmEinsteinMerkleTree::open(path, LsmOptions::default(), namespaceds).unwrap()
+    }
+
+    #[test]
+    fn test_write_batch_save_points_and_merge() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open(dir.path());
+        let mut wb = db.write_alexandrov_poset_process();
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+        wb.put(b"a", b"1").unwrap();
+        let size_a = wb.data_size();
+        wb.set_save_point();
+        wb.put_namespaced(NAMESPACED_WRITE, b"b", b"2").unwrap();
+        let size_ab = wb.data_size();
+        wb.set_save_point();
+
+        let mut other = db.write_alexandrov_poset_process();
+        other.delete(b"a").unwrap();
+        other.set_save_point();
+        other.delete_range(b"c", b"d").unwrap();
+        wb.merge(other).unwrap();
+        assert_eq!(wb.count(), 4);
+
+        // The merged commands go with the save point set before the merge, and `other`'s
+        // save point is not kept.
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!((wb.count(), wb.data_size()), (2, size_ab));
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!((wb.count(), wb.data_size()), (1, size_a));
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+
+        wb.put_namespaced(NAMESPACED_WRITE, b"b", b"3").unwrap();
+        let mut namespaceds = Vec::new();
+        wb.iterate(|command| {
+            namespaceds.push(command.namespaced().to_owned());
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(namespaceds, vec!["default", NAMESPACED_WRITE]);
+        wb.write().unwrap();
+        assert_eq!(db.get_value(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get_value_namespaced(NAMESPACED_WRITE, b"b").unwrap(), Some(b"3".to_vec()));
+    }
+}
@@ -0,0 +1,464 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::{Arc, Mutex, RwLock};
+
+use fdb_traits::{
+    Error, IterOptions, Iterable, KvEinsteinMerkleTree, LightlikePersistenceExt, MergeOperators, Peekable, ReadOptions,
+    Result, SyncMutable, WriteBatch, WriteBatchExt,
+};
+
+use crate::iterator::MemoryEinsteinMerkleTreeIterator;
+use crate::lightlike_persistence::MemoryLightlikePersistence;
+use crate::namespaced_store::NamespacedStore;
+use crate::write_batch::{MemoryWriteBatch, WriteOp};
+
+/// Garbage is collected once this many versions have been written since the last collection,
+/// or once as many versions have been written as were live after it, whichever is larger.
+const MIN_WRITES_BETWEEN_GC: usize = 1024;
+
+pub(crate) struct State {
+    /// The sequence number of the last applied write.
+    pub(crate) seqno: u64,
+    pub(crate) namespaceds: HashMap<String, NamespacedStore>,
+    writes_since_gc: usize,
+    live_after_gc: usize,
+}
+
+pub(crate) struct Core {
+    namespaced_names: Vec<String>,
+    pub(crate) state: RwLock<State>,
+    /// Sequence numbers of live lightlike_persistences, with a reference count each.
+    lightlike_persistences: Mutex<BTreeMap<u64, usize>>,
+}
+
+impl Core {
+    pub(crate) fn store<'a>(state: &'a State, namespaced: &str) -> Result<&'a NamespacedStore> {
+        state
+            .namespaceds
+            .get(namespaced)
+            .ok_or_else(|| Error::NAMESPACEDName(namespaced.to_owned()))
+    }
+
+    pub(crate) fn namespaced_names(&self) -> Vec<&str> {
+        self.namespaced_names.iter().map(String::as_str).collect()
+    }
+
+    pub(crate) fn release_lightlike_persistence(&self, seqno: u64) {
+        let mut lightlike_persistences = self.lightlike_persistences.lock().unwrap();
+        if let Some(count) = lightlike_persistences.get_mut(&seqno) {
+            *count -= 1;
+            if *count == 0 {
+                lightlike_persistences.remove(&seqno);
+            }
+        }
+    }
+}
+
+/// An einstein_merkle_tree holding all of its data in memory.
+///
+/// Clones share the same data.
+#[derive(Clone)]
+pub struct MemoryEinsteinMerkleTree {
+    pub(crate) core: Arc<Core>,
+}
+
+impl MemoryEinsteinMerkleTree {
+    /// Create an empty einstein_merkle_tree with the given causet_merge families.
+    pub fn new(namespaceds: &[&str]) -> MemoryEinsteinMerkleTree {
+        MemoryEinsteinMerkleTree::with_merge_operators(namespaceds, &MergeOperators::default())
+    }
+
+    /// Create an empty einstein_merkle_tree with the given causet_merge families, taking the merge
+    /// operator of each from `merge_operators`.
+    pub fn with_merge_operators(namespaceds: &[&str], merge_operators: &MergeOperators) -> MemoryEinsteinMerkleTree {
+        let mut namespaced_names: Vec<String> = Vec::with_capacity(namespaceds.len());
+        for namespaced in namespaceds {
+            if !namespaced_names.iter().any(|n| n == namespaced) {
+                namespaced_names.push((*namespaced).to_owned());
+            }
+        }
+        let stores = namespaced_names
+            .iter()
+            .map(|n| (n.clone(), NamespacedStore::new(merge_operators.get(n).cloned())))
+            .collect();
+        MemoryEinsteinMerkleTree {
+            core: Arc::new(Core {
+                namespaced_names,
+                state: RwLock::new(State {
+                    seqno: 0,
+                    namespaceds: stores,
+                    writes_since_gc: 0,
+                    live_after_gc: 0,
+                }),
+                lightlike_persistences: Mutex::new(BTreeMap::new()),
+            }),
+        }
+    }
+
+    /// The sequence number of the last applied write.
+    pub fn latest_seqno(&self) -> u64 {
+        self.core.state.read().unwrap().seqno
+    }
+
+    /// Apply `ops` atomically.
+    ///
+    /// Every causet_merge family is checked before anything is written, so a write naming an
+    /// unknown causet_merge family, or merging into one without a merge operator, leaves the
+    /// einstein_merkle_tree untouched.
+    pub(crate) fn apply(&self, ops: &[WriteOp]) -> Result<()> {
+        if ops.is_empty() {
+            return Ok(());
+        }
+        let mut state = self.core.state.write().unwrap();
+        for op in ops {
+            let store = Core::store(&state, op.namespaced())?;
+            if let WriteOp::Merge { .. } = op {
+                if !store.has_merge_operator() {
+                    return Err(Error::NoMergeOperator(op.namespaced().to_owned()));
+                }
+            }
+        }
+        let mut seqno = state.seqno;
+        for op in ops {
+            seqno += 1;
+            let store = state.namespaceds.get_mut(op.namespaced()).unwrap();
+            match op {
+                WriteOp::Put { soliton_id, causet_locale, .. } => store.put(soliton_id, causet_locale, seqno),
+                WriteOp::Delete { soliton_id, .. } => store.delete(soliton_id, seqno),
+                WriteOp::DeleteRange { start, end, .. } => store.delete_range(start, end, seqno),
+                WriteOp::Merge { soliton_id, operand, .. } => store.merge(soliton_id, operand, seqno),
+            }
+        }
+        state.seqno = seqno;
+        state.writes_since_gc += ops.len();
+        if state.writes_since_gc >= MIN_WRITES_BETWEEN_GC.max(state.live_after_gc) {
+            self.collect_garbage_locked(&mut state);
+        }
+        Ok(())
+    }
+
+    /// Drop every version that neither the current einstein_merkle_tree nor any live
+    /// lightlike_persistence can observe.
+    pub fn collect_garbage(&self) {
+        let mut state = self.core.state.write().unwrap();
+        self.collect_garbage_locked(&mut state);
+    }
+
+    fn collect_garbage_locked(&self, state: &mut State) {
+        let oldest = {
+            let lightlike_persistences = self.core.lightlike_persistences.lock().unwrap();
+            lightlike_persistences.keys().next().copied().unwrap_or(state.seqno)
+        };
+        let mut live = 0;
+        for store in state.namespaceds.values_mut() {
+            store.collect_garbage(oldest);
+            live += store.version_count();
+        }
+        state.writes_since_gc = 0;
+        state.live_after_gc = live;
+    }
+
+    /// Register a lightlike_persistence at the current sequence number.
+    ///
+    /// Registration happens under the state lock so that a concurrent garbage collection either
+    /// sees the new lightlike_persistence or finishes before it is taken.
+    pub(crate) fn acquire_lightlike_persistence(&self) -> u64 {
+        let state = self.core.state.read().unwrap();
+        let mut lightlike_persistences = self.core.lightlike_persistences.lock().unwrap();
+        *lightlike_persistences.entry(state.seqno).or_insert(0) += 1;
+        state.seqno
+    }
+
+    pub(crate) fn get_at(&self, namespaced: &str, soliton_id: &[u8], seqno: u64) -> Result<Option<Vec<u8>>> {
+        let state = self.core.state.read().unwrap();
+        Core::store(&state, namespaced)?.get(soliton_id, seqno)
+    }
+
+    pub(crate) fn iterator_at(
+        &self,
+        namespaced: &str,
+        opts: &IterOptions,
+        seqno: Option<u64>,
+    ) -> Result<MemoryEinsteinMerkleTreeIterator> {
+        let state = self.core.state.read().unwrap();
+        let seqno = seqno.unwrap_or(state.seqno);
+        let entries = Core::store(&state, namespaced)?.visible(seqno, opts)?;
+        Ok(MemoryEinsteinMerkleTreeIterator::new(entries))
+    }
+}
+
+impl Debug for MemoryEinsteinMerkleTree {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryEinsteinMerkleTree")
+            .field("namespaceds", &self.core.namespaced_names)
+            .field("seqno", &self.latest_seqno())
+            .finish()
+    }
+}
+
+impl Peekable for MemoryEinsteinMerkleTree {
+    fn get_value_namespaced_opt(
+        &self,
+        _opts: &ReadOptions,
+        namespaced: &str,
+        soliton_id: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        self.get_at(namespaced, soliton_id, u64::MAX)
+    }
+}
+
+impl SyncMutable for MemoryEinsteinMerkleTree {
+    fn put_namespaced(&self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        self.apply(&[WriteOp::Put {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            causet_locale: causet_locale.to_vec(),
+        }])
+    }
+
+    fn delete_namespaced(&self, namespaced: &str, soliton_id: &[u8]) -> Result<()> {
+        self.apply(&[WriteOp::Delete {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+        }])
+    }
+
+    fn delete_range_namespaced(&self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+        self.apply(&[WriteOp::DeleteRange {
+            namespaced: namespaced.to_owned(),
+            start: begin_soliton_id.to_vec(),
+            end: end_soliton_id.to_vec(),
+        }])
+    }
+
+    fn merge_namespaced(&self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()> {
+        self.apply(&[WriteOp::Merge {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            operand: operand.to_vec(),
+        }])
+    }
+}
+
+impl Iterable for MemoryEinsteinMerkleTree {
+    type Iterator = MemoryEinsteinMerkleTreeIterator;
+
+    fn iterator_opt(&self, opts: IterOptions) -> Result<Self::Iterator> {
+        self.iterator_namespaced_opt(fdb_traits::NAMESPACED_DEFAULT, opts)
+    }
+
+    fn iterator_namespaced_opt(&self, namespaced: &str, opts: IterOptions) -> Result<Self::Iterator> {
+        self.iterator_at(namespaced, &opts, None)
+    }
+}
+
+impl WriteBatchExt for MemoryEinsteinMerkleTree {
+    type WriteBatch = MemoryWriteBatch;
+    type WriteBatchVec = MemoryWriteBatch;
+
+    const WRITE_BATCH_MAX_CAUSET_KEYS: usize = 256;
+
+    fn support_write_alexandrov_poset_process_vec(&self) -> bool {
+        true
+    }
+
+    fn write_alexandrov_poset_process(&self) -> MemoryWriteBatch {
+        MemoryWriteBatch::new(self.clone())
+    }
+
+    fn write_alexandrov_poset_process_with_cap(&self, cap: usize) -> MemoryWriteBatch {
+        MemoryWriteBatch::with_capacity(self, cap)
+    }
+}
+
+impl LightlikePersistenceExt for MemoryEinsteinMerkleTree {
+    type LightlikePersistence = MemoryLightlikePersistence;
+
+    fn lightlike_persistence(&self) -> MemoryLightlikePersistence {
+        MemoryLightlikePersistence::new(self.clone())
+    }
+}
+
+impl KvEinsteinMerkleTree for MemoryEinsteinMerkleTree {
+    fn namespaced_names(&self) -> Vec<&str> {
+        self.core.namespaced_names()
+    }
+
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{
+        collect, Iterator, LightlikePersistence, Mutable, SeekKey, ALL_NAMESPACEDS, NAMESPACED_DEFAULT,
+        NAMESPACED_WRITE,
+    };
+
+    use super::*;
+
+    fn new_einstein_merkle_tree() -> MemoryEinsteinMerkleTree {
+        MemoryEinsteinMerkleTree::new(ALL_NAMESPACEDS)
+    }
+
+    #[test]
+    fn test_put_get_delete() {
+        let db = new_einstein_merkle_tree();
+        db.put(b"k1", b"v1").unwrap();
+        db.put_namespaced(NAMESPACED_WRITE, b"k1", b"w1").unwrap();
+        assert_eq!(db.get_value(b"k1").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(db.get_value_namespaced(NAMESPACED_WRITE, b"k1").unwrap(), Some(b"w1".to_vec()));
+        db.delete(b"k1").unwrap();
+        assert_eq!(db.get_value(b"k1").unwrap(), None);
+        assert_eq!(db.get_value_namespaced(NAMESPACED_WRITE, b"k1").unwrap(), Some(b"w1".to_vec()));
+    }
+
+    #[test]
+    fn test_unknown_namespaced() {
+        let db = MemoryEinsteinMerkleTree::new(&[NAMESPACED_DEFAULT]);
+        assert!(db.put_namespaced("nope", b"k", b"v").is_err());
+        assert!(db.get_value_namespaced("nope", b"k").is_err());
+        assert!(db.iterator_namespaced("nope").is_err());
+
+        // A alexandrov_poset_process naming an unknown causet_merge family writes nothing at all.
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.put(b"k", b"v").unwrap();
+        wb.put_namespaced("nope", b"k", b"v").unwrap();
+        assert!(wb.write().is_err());
+        assert_eq!(db.get_value(b"k").unwrap(), None);
+    }
+
+    #[test]
+    fn test_lightlike_persistence_isolation() {
+        let db = new_einstein_merkle_tree();
+        db.put(b"a", b"1").unwrap();
+        db.put(b"b", b"2").unwrap();
+        let snap = db.lightlike_persistence();
+        db.put(b"a", b"3").unwrap();
+        db.put(b"c", b"4").unwrap();
+        db.delete_range(b"a", b"c").unwrap();
+        db.collect_garbage();
+
+        assert_eq!(snap.get_value(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(snap.get_value(b"c").unwrap(), None);
+        let mut it = snap.iterator().unwrap();
+        it.seek_to_first().unwrap();
+        assert_eq!(
+            collect(it),
+            vec![(b"a".to_vec(), b"1".to_vec()), (b"b".to_vec(), b"2".to_vec())]
+        );
+        assert_eq!(db.get_value(b"a").unwrap(), None);
+        assert_eq!(db.get_value(b"c").unwrap(), Some(b"4".to_vec()));
+        assert_eq!(snap.namespaced_names().len(), ALL_NAMESPACEDS.len());
+    }
+
+    #[test]
+    fn test_iterator_bounds_and_seek() {
+        let db = new_einstein_merkle_tree();
+        for k in &[b"a", b"b", b"c", b"d", b"e"] {
+            db.put(*k, *k).unwrap();
+        }
+        let opts = IterOptions::new(Some(b"b".to_vec()), Some(b"e".to_vec()), true);
+        let mut it = db.iterator_opt(opts).unwrap();
+        assert!(!it.valid().unwrap());
+        assert!(it.seek(SeekKey::Key(b"a")).unwrap());
+        assert_eq!(it.soliton_id(), b"b");
+        assert!(it.seek(SeekKey::Key(b"bb")).unwrap());
+        assert_eq!(it.soliton_id(), b"c");
+        assert!(it.seek_for_prev(SeekKey::Key(b"cc")).unwrap());
+        assert_eq!(it.soliton_id(), b"c");
+        assert!(it.seek_to_last().unwrap());
+        assert_eq!(it.soliton_id(), b"d");
+        assert!(it.prev().unwrap());
+        assert_eq!(it.causet_locale(), b"c");
+        assert!(it.next().unwrap());
+        assert!(!it.next().unwrap());
+        assert!(!it.seek(SeekKey::Key(b"z")).unwrap());
+
+        let mut keys = vec![];
+        db.scan(b"b", b"d", true, |k, _| {
+            keys.push(k.to_vec());
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(keys, vec![b"b".to_vec(), b"c".to_vec()]);
+    }
+
+    #[test]
+    fn test_merge_operators() {
+        use std::sync::Arc;
+
+        use fdb_traits::{
+            decode_elements, encode_counter, encode_elements, AppendListMergeOperator, CounterMergeOperator,
+            MaxMergeOperator, SetUnionMergeOperator, NAMESPACED_LOCK, NAMESPACED_VIOLETABFT,
+        };
+
+        let mut operators = MergeOperators::new();
+        operators
+            .register(NAMESPACED_DEFAULT, Arc::new(CounterMergeOperator))
+            .register(NAMESPACED_LOCK, Arc::new(SetUnionMergeOperator))
+            .register(NAMESPACED_WRITE, Arc::new(AppendListMergeOperator))
+            .register(NAMESPACED_VIOLETABFT, Arc::new(MaxMergeOperator));
+        let db = MemoryEinsteinMerkleTree::with_merge_operators(ALL_NAMESPACEDS, &operators);
+
+        db.merge_namespaced(NAMESPACED_DEFAULT, b"hits", &encode_counter(2)).unwrap();
+        let snap = db.lightlike_persistence();
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.merge_namespaced(NAMESPACED_DEFAULT, b"hits", &encode_counter(-5)).unwrap();
+        wb.merge_namespaced(NAMESPACED_DEFAULT, b"hits", &encode_counter(10)).unwrap();
+        wb.write().unwrap();
+        assert_eq!(db.get_value(b"hits").unwrap(), Some(encode_counter(7)));
+        assert_eq!(snap.get_value(b"hits").unwrap(), Some(encode_counter(2)));
+        db.put(b"hits", &encode_counter(100)).unwrap();
+        db.merge_namespaced(NAMESPACED_DEFAULT, b"hits", &encode_counter(1)).unwrap();
+        assert_eq!(db.get_value(b"hits").unwrap(), Some(encode_counter(101)));
+
+        let set = |e: &[&[u8]]| encode_elements(e.iter().copied());
+        db.merge_namespaced(NAMESPACED_LOCK, b"s", &set(&[b"b", b"a"])).unwrap();
+        db.merge_namespaced(NAMESPACED_LOCK, b"s", &set(&[b"c", b"a"])).unwrap();
+        let s = db.get_value_namespaced(NAMESPACED_LOCK, b"s").unwrap().unwrap();
+        assert_eq!(decode_elements(&s).unwrap(), vec![&b"a"[..], b"b", b"c"]);
+
+        db.merge_namespaced(NAMESPACED_WRITE, b"l", &set(&[b"x"])).unwrap();
+        db.merge_namespaced(NAMESPACED_WRITE, b"l", &set(&[b"y", b"x"])).unwrap();
+        let l = db.get_value_namespaced(NAMESPACED_WRITE, b"l").unwrap().unwrap();
+        assert_eq!(decode_elements(&l).unwrap(), vec![&b"x"[..], b"y", b"x"]);
+
+        for v in &[b"3", b"9", b"5"] {
+            db.merge_namespaced(NAMESPACED_VIOLETABFT, b"m", *v).unwrap();
+        }
+        let mut it = db.iterator_namespaced(NAMESPACED_VIOLETABFT).unwrap();
+        it.seek_to_first().unwrap();
+        assert_eq!(collect(it), vec![(b"m".to_vec(), b"9".to_vec())]);
+
+        db.collect_garbage();
+        drop(snap);
+        db.collect_garbage();
+        assert_eq!(db.get_value(b"hits").unwrap(), Some(encode_counter(101)));
+        assert_eq!(db.get_value_namespaced(NAMESPACED_VIOLETABFT, b"m").unwrap(), Some(b"9".to_vec()));
+
+        // A alexandrov_poset_process merging into a causet_merge family without an operator writes nothing.
+        let db = new_einstein_merkle_tree();
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.put(b"k", b"v").unwrap();
+        wb.merge_namespaced(NAMESPACED_DEFAULT, b"k", b"v").unwrap();
+        match wb.write() {
+            Err(Error::NoMergeOperator(namespaced)) => assert_eq!(namespaced, NAMESPACED_DEFAULT),
+            r => panic!("{:?}", r),
+        }
+        assert_eq!(db.get_value(b"k").unwrap(), None);
+    }
+
+    #[test]
+    fn test_garbage_collection_bounded() {
+        let db = new_einstein_merkle_tree();
+        for i in 0..(MIN_WRITES_BETWEEN_GC * 4) {
+            db.put(b"k", format!("{}", i).as_bytes()).unwrap();
+        }
+        let state = db.core.state.read().unwrap();
+        assert!(state.namespaceds[NAMESPACED_DEFAULT].version_count() <= MIN_WRITES_BETWEEN_GC + 1);
+    }
+}
@@ -0,0 +1,72 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use fdb_traits::{Iterator, Result, SeekKey};
+
+/// An iterator over a point-in-time copy of a causet_merge family.
+///
+/// The visible soliton_id/causet_locale pairs within the iterator's bounds are copied out when
+/// the iterator is created, so the iterator holds no lock and is unaffected by later writes.
+pub struct MemoryEinsteinMerkleTreeIterator {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    pos: Option<usize>,
+}
+
+impl MemoryEinsteinMerkleTreeIterator {
+    pub(crate) fn new(entries: Vec<(Vec<u8>, Vec<u8>)>) -> MemoryEinsteinMerkleTreeIterator {
+        MemoryEinsteinMerkleTreeIterator { entries, pos: None }
+    }
+
+    fn set_pos(&mut self, pos: Option<usize>) -> bool {
+        self.pos = pos.filter(|p| *p < self.entries.len());
+        self.pos.is_some()
+    }
+
+    fn current(&self) -> &(Vec<u8>, Vec<u8>) {
+        &self.entries[self.pos.expect("iterator is invalid")]
+    }
+}
+
+impl Iterator for MemoryEinsteinMerkleTreeIterator {
+    fn seek(&mut self, soliton_id: SeekKey<'_>) -> Result<bool> {
+        let pos = match soliton_id {
+            SeekKey::Start => Some(0),
+            SeekKey::End => self.entries.len().checked_sub(1),
+            SeekKey::Key(k) => Some(self.entries.partition_point(|(key, _)| key.as_slice() < k)),
+        };
+        Ok(self.set_pos(pos))
+    }
+
+    fn seek_for_prev(&mut self, soliton_id: SeekKey<'_>) -> Result<bool> {
+        let pos = match soliton_id {
+            SeekKey::Start => Some(0),
+            SeekKey::End => self.entries.len().checked_sub(1),
+            SeekKey::Key(k) => self
+                .entries
+                .partition_point(|(key, _)| key.as_slice() <= k)
+                .checked_sub(1),
+        };
+        Ok(self.set_pos(pos))
+    }
+
+    fn prev(&mut self) -> Result<bool> {
+        let pos = self.pos.expect("iterator is invalid");
+        Ok(self.set_pos(pos.checked_sub(1)))
+    }
+
+    fn next(&mut self) -> Result<bool> {
+        let pos = self.pos.expect("iterator is invalid");
+        Ok(self.set_pos(Some(pos + 1)))
+    }
+
+    fn soliton_id(&self) -> &[u8] {
+        &self.current().0
+    }
+
+    fn causet_locale(&self) -> &[u8] {
+        &self.current().1
+    }
+
+    fn valid(&self) -> Result<bool> {
+        Ok(self.pos.is_some())
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! An einstein_merkle_tree that keeps everything in memory.
+//!
+//! Each causet_merge family is an ordered map of multi-versioned soliton_ids.  Every write is
+//! stamped with a sequence number; a `LightlikePersistence` is nothing more than the sequence
+//! number at which it was taken, and reads through it ignore every version written later.
+//! Range deletes are recorded as tombstones carrying their own sequence number, so they too are
+//! invisible to older lightlike_persistences.
+//!
+//! Versions that no live lightlike_persistence can observe any more are collected as the
+//! einstein_merkle_tree is written to.
+//!
+//! Nothing here touches the disk, which makes this einstein_merkle_tree suitable for unit tests
+//! that must not depend on a native storage library.
+
+mod einstein_merkle_tree;
+mod iterator;
+mod lightlike_persistence;
+mod namespaced_store;
+mod write_batch;
+
+pub use crate::einstein_merkle_tree::MemoryEinsteinMerkleTree;
+pub use crate::iterator::MemoryEinsteinMerkleTreeIterator;
+pub use crate::lightlike_persistence::MemoryLightlikePersistence;
+pub use crate::write_batch::MemoryWriteBatch;
@@ -0,0 +1,71 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use std::fmt::{self, Debug, Formatter};
+
+use fdb_traits::{IterOptions, Iterable, LightlikePersistence, Peekable, ReadOptions, Result};
+
+use crate::einstein_merkle_tree::MemoryEinsteinMerkleTree;
+use crate::iterator::MemoryEinsteinMerkleTreeIterator;
+
+/// A point-in-time view of a `MemoryEinsteinMerkleTree`.
+///
+/// While it is alive, the versions it can observe are kept from garbage collection.
+pub struct MemoryLightlikePersistence {
+    db: MemoryEinsteinMerkleTree,
+    seqno: u64,
+}
+
+impl MemoryLightlikePersistence {
+    pub(crate) fn new(db: MemoryEinsteinMerkleTree) -> MemoryLightlikePersistence {
+        let seqno = db.acquire_lightlike_persistence();
+        MemoryLightlikePersistence { db, seqno }
+    }
+
+    /// The sequence number of the last write visible through this lightlike_persistence.
+    pub fn seqno(&self) -> u64 {
+        self.seqno
+    }
+}
+
+impl Drop for MemoryLightlikePersistence {
+    fn drop(&mut self) {
+        self.db.core.release_lightlike_persistence(self.seqno);
+    }
+}
+
+impl Debug for MemoryLightlikePersistence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryLightlikePersistence")
+            .field("seqno", &self.seqno)
+            .finish()
+    }
+}
+
+impl Peekable for MemoryLightlikePersistence {
+    fn get_value_namespaced_opt(
+        &self,
+        _opts: &ReadOptions,
+        namespaced: &str,
+        soliton_id: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        self.db.get_at(namespaced, soliton_id, self.seqno)
+    }
+}
+
+impl Iterable for MemoryLightlikePersistence {
+    type Iterator = MemoryEinsteinMerkleTreeIterator;
+
+    fn iterator_opt(&self, opts: IterOptions) -> Result<Self::Iterator> {
+        self.iterator_namespaced_opt(fdb_traits::NAMESPACED_DEFAULT, opts)
+    }
+
+    fn iterator_namespaced_opt(&self, namespaced: &str, opts: IterOptions) -> Result<Self::Iterator> {
+        self.db.iterator_at(namespaced, &opts, Some(self.seqno))
+    }
+}
+
+impl LightlikePersistence for MemoryLightlikePersistence {
+    fn namespaced_names(&self) -> Vec<&str> {
+        self.db.core.namespaced_names()
+    }
+}
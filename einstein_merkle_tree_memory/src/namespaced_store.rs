@@ -0,0 +1,286 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Multi-versioned storage for a single causet_merge family.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use fdb_traits::{Error, IterOptions, MergeOperator, Result};
+
+/// A range delete of `[start, end)` issued at `seqno`.
+#[derive(Clone, Debug)]
+struct RangeTombstone {
+    start: Vec<u8>,
+    end: Vec<u8>,
+    seqno: u64,
+}
+
+impl RangeTombstone {
+    /// Whether this tombstone hides a version of `soliton_id` written at `version_seqno` from a
+    /// reader at `read_seqno`.
+    fn covers(&self, soliton_id: &[u8], version_seqno: u64, read_seqno: u64) -> bool {
+        self.seqno > version_seqno
+            && self.seqno <= read_seqno
+            && self.start.as_slice() <= soliton_id
+            && soliton_id < self.end.as_slice()
+    }
+}
+
+/// A soliton_id and the sequence number of one of its versions.
+type VersionKey = (Vec<u8>, Reverse<u64>);
+
+/// What a version of a soliton_id records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Version {
+    Put(Vec<u8>),
+    /// A point tombstone.
+    Delete,
+    /// A merge operand, applied to the versions below it.
+    Merge(Vec<u8>),
+}
+
+/// The versions of every soliton_id in a causet_merge family.
+///
+/// Versions are keyed by `(soliton_id, Reverse(seqno))`, so the versions of a soliton_id are
+/// adjacent and ordered newest first.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NamespacedStore {
+    versions: BTreeMap<VersionKey, Version>,
+    range_tombstones: Vec<RangeTombstone>,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
+}
+
+impl NamespacedStore {
+    pub(crate) fn new(merge_operator: Option<Arc<dyn MergeOperator>>) -> NamespacedStore {
+        NamespacedStore {
+            merge_operator,
+            ..NamespacedStore::default()
+        }
+    }
+
+    pub(crate) fn has_merge_operator(&self) -> bool {
+        self.merge_operator.is_some()
+    }
+
+    pub(crate) fn put(&mut self, soliton_id: &[u8], causet_locale: &[u8], seqno: u64) {
+        self.versions
+            .insert((soliton_id.to_vec(), Reverse(seqno)), Version::Put(causet_locale.to_vec()));
+    }
+
+    pub(crate) fn delete(&mut self, soliton_id: &[u8], seqno: u64) {
+        self.versions.insert((soliton_id.to_vec(), Reverse(seqno)), Version::Delete);
+    }
+
+    pub(crate) fn merge(&mut self, soliton_id: &[u8], operand: &[u8], seqno: u64) {
+        self.versions
+            .insert((soliton_id.to_vec(), Reverse(seqno)), Version::Merge(operand.to_vec()));
+    }
+
+    pub(crate) fn delete_range(&mut self, start: &[u8], end: &[u8], seqno: u64) {
+        if start >= end {
+            return;
+        }
+        self.range_tombstones.push(RangeTombstone {
+            start: start.to_vec(),
+            end: end.to_vec(),
+            seqno,
+        });
+    }
+
+    /// The number of stored versions, tombstones included.
+    pub(crate) fn version_count(&self) -> usize {
+        self.versions.len() + self.range_tombstones.len()
+    }
+
+    fn covered(&self, soliton_id: &[u8], version_seqno: u64, read_seqno: u64) -> bool {
+        self.range_tombstones
+            .iter()
+            .any(|t| t.covers(soliton_id, version_seqno, read_seqno))
+    }
+
+    /// The causet_locale of `soliton_id` given its versions at or below `read_seqno`, newest
+    /// first.  Merge operands are applied to the first version below them that is not one.
+    fn resolve<'a, I>(&self, soliton_id: &[u8], versions: I, read_seqno: u64) -> Result<Option<Vec<u8>>>
+    where
+        I: IntoIterator<Item = (u64, &'a Version)>,
+    {
+        let mut operands = Vec::new();
+        let mut existing = None;
+        for (seqno, version) in versions {
+            if self.covered(soliton_id, seqno, read_seqno) {
+                break;
+            }
+            match version {
+                Version::Put(causet_locale) => {
+                    existing = Some(causet_locale.as_slice());
+                    break;
+                }
+                Version::Delete => break,
+                Version::Merge(operand) => operands.push(operand.as_slice()),
+            }
+        }
+        if operands.is_empty() {
+            return Ok(existing.map(<[u8]>::to_vec));
+        }
+        // Merges are only accepted by causet_merge families with an operator.
+        let operator = self.merge_operator.as_ref().ok_or_else(|| {
+            Error::EinsteinMerkleTree(format!("merge operand of {:?} without a merge operator", soliton_id))
+        })?;
+        operands.reverse();
+        operator.full_merge(soliton_id, existing, &operands).map(Some)
+    }
+
+    /// The versions of `soliton_id` at or below `read_seqno`, newest first.
+    fn versions_of<'a>(&'a self, soliton_id: &'a [u8], read_seqno: u64) -> impl Iterator<Item = (u64, &'a Version)> + 'a {
+        self.versions
+            .range((soliton_id.to_vec(), Reverse(read_seqno))..)
+            .take_while(move |((key, _), _)| key.as_slice() == soliton_id)
+            .map(|((_, Reverse(seqno)), version)| (*seqno, version))
+    }
+
+    /// The causet_locale of `soliton_id` as seen by a reader at `read_seqno`.
+    pub(crate) fn get(&self, soliton_id: &[u8], read_seqno: u64) -> Result<Option<Vec<u8>>> {
+        self.resolve(soliton_id, self.versions_of(soliton_id, read_seqno), read_seqno)
+    }
+
+    /// Every live soliton_id/causet_locale pair within the bounds of `opts`, as seen by a reader at
+    /// `read_seqno`, in soliton_id order.
+    pub(crate) fn visible(&self, read_seqno: u64, opts: &IterOptions) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let from = (opts.lower_bound().unwrap_or(&[]).to_vec(), Reverse(u64::MAX));
+        let mut out = Vec::new();
+        let mut last_key: Option<&[u8]> = None;
+        for ((key, Reverse(seqno)), _) in self.versions.range(from..) {
+            if !opts.contains(key) {
+                break;
+            }
+            if *seqno > read_seqno || last_key == Some(key.as_slice()) {
+                continue;
+            }
+            last_key = Some(key.as_slice());
+            if let Some(causet_locale) = self.get(key, read_seqno)? {
+                out.push((key.clone(), causet_locale));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Drop every version no reader at or after `oldest_seqno` can observe.
+    ///
+    /// For each soliton_id only the newest version at or below `oldest_seqno` is kept, and not
+    /// even that one if it is deleted.  If that version is a merge operand, the operands are
+    /// merged into it and it becomes a put; operands that fail to merge are kept as they are,
+    /// along with everything below them.  Range tombstones at or below `oldest_seqno` have been
+    /// fully applied once this returns and are discarded.
+    pub(crate) fn collect_garbage(&mut self, oldest_seqno: u64) {
+        let mut obsolete = Vec::new();
+        let mut merged = Vec::new();
+        let mut last_key: Option<&[u8]> = None;
+        let mut unmerged: Option<&[u8]> = None;
+        for ((key, Reverse(seqno)), version) in self.versions.iter() {
+            if *seqno > oldest_seqno || unmerged == Some(key.as_slice()) {
+                continue;
+            }
+            let shadowed = last_key == Some(key.as_slice());
+            last_key = Some(key.as_slice());
+            let version_key = (key.clone(), Reverse(*seqno));
+            if shadowed || *version == Version::Delete || self.covered(key, *seqno, oldest_seqno) {
+                obsolete.push(version_key);
+            } else if let Version::Merge(_) = version {
+                match self.get(key, oldest_seqno) {
+                    Ok(Some(causet_locale)) => merged.push((version_key, causet_locale)),
+                    Ok(None) => obsolete.push(version_key),
+                    Err(_) => unmerged = Some(key.as_slice()),
+                }
+            }
+        }
+        for version in obsolete {
+            self.versions.remove(&version);
+        }
+        for (version, causet_locale) in merged {
+            self.versions.insert(version, Version::Put(causet_locale));
+        }
+        self.range_tombstones.retain(|t| t.seqno > oldest_seqno);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_respects_seqno() {
+        let mut store = NamespacedStore::default();
+        store.put(b"k", b"v1", 1);
+        store.put(b"k", b"v2", 3);
+        store.delete(b"k", 5);
+        assert_eq!(store.get(b"k", 0).unwrap(), None);
+        assert_eq!(store.get(b"k", 2).unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(store.get(b"k", 4).unwrap(), Some(b"v2".to_vec()));
+        assert_eq!(store.get(b"k", 5).unwrap(), None);
+        assert_eq!(store.get(b"j", 5).unwrap(), None);
+    }
+
+    #[test]
+    fn test_range_tombstone() {
+        let mut store = NamespacedStore::default();
+        store.put(b"a", b"1", 1);
+        store.put(b"b", b"2", 2);
+        store.put(b"c", b"3", 3);
+        store.delete_range(b"a", b"c", 4);
+        store.put(b"a", b"4", 5);
+        assert_eq!(store.get(b"b", 3).unwrap(), Some(b"2".to_vec()));
+        assert_eq!(store.get(b"b", 4).unwrap(), None);
+        assert_eq!(store.get(b"a", 4).unwrap(), None);
+        assert_eq!(store.get(b"a", 5).unwrap(), Some(b"4".to_vec()));
+        assert_eq!(store.get(b"c", 5).unwrap(), Some(b"3".to_vec()));
+        let all = store.visible(5, &IterOptions::default()).unwrap();
+        assert_eq!(all, vec![(b"a".to_vec(), b"4".to_vec()), (b"c".to_vec(), b"3".to_vec())]);
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_visible_versions() {
+        let mut store = NamespacedStore::default();
+        store.put(b"a", b"1", 1);
+        store.put(b"a", b"2", 2);
+        store.put(b"b", b"1", 3);
+        store.delete(b"b", 4);
+        store.put(b"c", b"1", 5);
+        store.delete_range(b"c", b"d", 6);
+        store.put(b"a", b"3", 7);
+
+        let before: Vec<_> = (0..=7).map(|s| store.visible(s, &IterOptions::default()).unwrap()).collect();
+        store.collect_garbage(6);
+        for s in 6..=7 {
+            assert_eq!(store.visible(s, &IterOptions::default()).unwrap(), before[s as usize]);
+        }
+        // a@2 and a@7 survive, everything else is gone.
+        assert_eq!(store.version_count(), 2);
+    }
+
+    #[test]
+    fn test_merge_operands() {
+        let mut store = NamespacedStore::new(Some(Arc::new(fdb_traits::CounterMergeOperator)));
+        let counter = fdb_traits::encode_counter;
+        store.merge(b"a", &counter(1), 1);
+        store.merge(b"a", &counter(2), 2);
+        store.put(b"b", &counter(10), 3);
+        store.merge(b"b", &counter(5), 4);
+        store.delete_range(b"a", b"b", 5);
+        store.merge(b"a", &counter(7), 6);
+        assert_eq!(store.get(b"a", 2).unwrap(), Some(counter(3)));
+        assert_eq!(store.get(b"a", 5).unwrap(), None);
+        assert_eq!(store.get(b"a", 6).unwrap(), Some(counter(7)));
+        assert_eq!(store.get(b"b", 3).unwrap(), Some(counter(10)));
+        assert_eq!(store.get(b"b", 6).unwrap(), Some(counter(15)));
+
+        // Garbage collection merges the operands into a single put.
+        let before: Vec<_> = (4..=6).map(|s| store.visible(s, &IterOptions::default()).unwrap()).collect();
+        store.collect_garbage(4);
+        for s in 4..=6 {
+            assert_eq!(store.visible(s, &IterOptions::default()).unwrap(), before[s as usize - 4]);
+        }
+        assert_eq!(store.versions[&(b"b".to_vec(), Reverse(4))], Version::Put(counter(15)));
+        assert_eq!(store.version_count(), 4);
+    }
+}
@@ -0,0 +1,289 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use fdb_traits::{Error, Mutable, Result, WriteBatch, WriteBatchCommand, WriteBatchExt, WriteOptions};
+
+use crate::einstein_merkle_tree::MemoryEinsteinMerkleTree;
+
+/// A single command recorded in a `MemoryWriteBatch`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum WriteOp {
+    Put {
+        namespaced: String,
+        soliton_id: Vec<u8>,
+        causet_locale: Vec<u8>,
+    },
+    Delete {
+        namespaced: String,
+        soliton_id: Vec<u8>,
+    },
+    DeleteRange {
+        namespaced: String,
+        start: Vec<u8>,
+        end: Vec<u8>,
+    },
+    Merge {
+        namespaced: String,
+        soliton_id: Vec<u8>,
+        operand: Vec<u8>,
+    },
+}
+
+impl WriteOp {
+    pub(crate) fn namespaced(&self) -> &str {
+        match self {
+            WriteOp::Put { namespaced, .. }
+            | WriteOp::Delete { namespaced, .. }
+            | WriteOp::DeleteRange { namespaced, .. }
+            | WriteOp::Merge { namespaced, .. } => namespaced,
+        }
+    }
+
+    pub(crate) fn command(&self) -> WriteBatchCommand<'_> {
+        match self {
+            WriteOp::Put { namespaced, soliton_id, causet_locale } => WriteBatchCommand::Put {
+                namespaced,
+                soliton_id,
+                causet_locale,
+            },
+            WriteOp::Delete { namespaced, soliton_id } => WriteBatchCommand::Delete { namespaced, soliton_id },
+            WriteOp::DeleteRange { namespaced, start, end } => WriteBatchCommand::DeleteRange {
+                namespaced,
+                begin_soliton_id: start,
+                end_soliton_id: end,
+            },
+            WriteOp::Merge { namespaced, soliton_id, operand } => WriteBatchCommand::Merge {
+                namespaced,
+                soliton_id,
+                operand,
+            },
+        }
+    }
+}
+
+/// A write alexandrov_poset_process for `MemoryEinsteinMerkleTree`.
+///
+/// Commands are buffered until `write`, which applies all of them under a single lock
+/// acquisition, so readers and lightlike_persistences see either none or all of them.
+pub struct MemoryWriteBatch {
+    db: MemoryEinsteinMerkleTree,
+    ops: Vec<WriteOp>,
+    data_size: usize,
+    /// `(ops.len(), data_size)` at each save point.
+    save_points: Vec<(usize, usize)>,
+}
+
+impl MemoryWriteBatch {
+    pub(crate) fn new(db: MemoryEinsteinMerkleTree) -> MemoryWriteBatch {
+        MemoryWriteBatch {
+            db,
+            ops: Vec::new(),
+            data_size: 0,
+            save_points: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, op: WriteOp) {
+        self.data_size += op.command().encoded_len();
+        self.ops.push(op);
+    }
+}
+
+impl Mutable for MemoryWriteBatch {
+    fn put_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        self.push(WriteOp::Put {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            causet_locale: causet_locale.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn delete_namespaced(&mut self, namespaced: &str, soliton_id: &[u8]) -> Result<()> {
+        self.push(WriteOp::Delete {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn delete_range_namespaced(&mut self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+        self.push(WriteOp::DeleteRange {
+            namespaced: namespaced.to_owned(),
+            start: begin_soliton_id.to_vec(),
+            end: end_soliton_id.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn merge_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()> {
+        self.push(WriteOp::Merge {
+            namespaced: namespaced.to_owned(),
+            soliton_id: soliton_id.to_vec(),
+            operand: operand.to_vec(),
+        });
+        Ok(())
+    }
+}
+
+impl WriteBatch<MemoryEinsteinMerkleTree> for MemoryWriteBatch {
+    fn with_capacity(e: &MemoryEinsteinMerkleTree, cap: usize) -> MemoryWriteBatch {
+        let mut wb = MemoryWriteBatch::new(e.clone());
+        wb.ops.reserve(cap);
+        wb
+    }
+
+    fn write_opt(&self, _opts: &WriteOptions) -> Result<()> {
+        self.db.apply(&self.ops)
+    }
+
+    fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    fn count(&self) -> usize {
+        self.ops.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    fn should_write_to_einstein_merkle_tree(&self) -> bool {
+        self.ops.len() > MemoryEinsteinMerkleTree::WRITE_BATCH_MAX_CAUSET_KEYS
+    }
+
+    fn clear(&mut self) {
+        self.ops.clear();
+        self.data_size = 0;
+        self.save_points.clear();
+    }
+
+    fn set_save_point(&mut self) {
+        self.save_points.push((self.ops.len(), self.data_size));
+    }
+
+    fn pop_save_point(&mut self) -> Result<()> {
+        self.save_points.pop().map(|_| ()).ok_or(Error::NoSavePoint)
+    }
+
+    fn rollback_to_save_point(&mut self) -> Result<()> {
+        let (count, data_size) = self.save_points.pop().ok_or(Error::NoSavePoint)?;
+        self.ops.truncate(count);
+        self.data_size = data_size;
+        Ok(())
+    }
+
+    fn iterate<F>(&self, mut f: F) -> Result<()>
+    where
+        F: FnMut(WriteBatchCommand) -> Result<bool>,
+    {
+        for op in &self.ops {
+            if !f(op.command())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn merge(&mut self, mut other: MemoryWriteBatch) -> Result<()> {
+        self.data_size += other.data_size;
+        self.ops.append(&mut other.ops);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fdb_traits::{Peekable, ALL_NAMESPACEDS, NAMESPACED_LOCK};
+
+    use super::*;
+
+    #[test]
+    fn test_write_batch_save_points() {
+        let db = MemoryEinsteinMerkleTree::new(ALL_NAMESPACEDS);
+        let mut wb = db.write_alexandrov_poset_process();
+        assert!(wb.is_empty());
+        assert!(wb.rollback_to_save_point().is_err());
+        assert!(wb.pop_save_point().is_err());
+
+        wb.put(b"a", b"1").unwrap();
+        let size_a = wb.data_size();
+        wb.set_save_point();
+        wb.put_namespaced(NAMESPACED_LOCK, b"b", b"2").unwrap();
+        wb.set_save_point();
+        wb.delete(b"a").unwrap();
+        assert_eq!(wb.count(), 3);
+
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!(wb.count(), 2);
+        wb.pop_save_point().unwrap();
+        assert_eq!(wb.count(), 2);
+        assert!(wb.rollback_to_save_point().is_err());
+
+        wb.set_save_point();
+        wb.delete_range(b"a", b"z").unwrap();
+        wb.rollback_to_save_point().unwrap();
+        wb.write().unwrap();
+        assert_eq!(db.get_value(b"a").unwrap(), Some(b"1".to_vec()));
+        assert_eq!(db.get_value_namespaced(NAMESPACED_LOCK, b"b").unwrap(), Some(b"2".to_vec()));
+
+        wb.clear();
+        assert!(wb.is_empty());
+        assert_eq!(wb.data_size(), 0);
+        assert!(size_a > 0);
+    }
+
+    #[test]
+    fn test_write_batch_iterate_and_merge() {
+        let db = MemoryEinsteinMerkleTree::new(ALL_NAMESPACEDS);
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.put(b"a", b"1").unwrap();
+        wb.set_save_point();
+
+        let mut other = db.write_alexandrov_poset_process();
+        other.delete_namespaced(NAMESPACED_LOCK, b"b").unwrap();
+        other.delete_range(b"c", b"d").unwrap();
+        let size = wb.data_size() + other.data_size();
+        wb.merge(other).unwrap();
+        assert_eq!(wb.count(), 3);
+        assert_eq!(wb.data_size(), size);
+
+        let mut commands = Vec::new();
+        wb.iterate(|command| {
+            commands.push(format!("{:?}", command));
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(commands.len(), 3);
+        assert!(commands[0].starts_with("Put"));
+        assert!(commands[1].starts_with("Delete {"));
+        assert!(commands[2].starts_with("DeleteRange"));
+
+        let mut first = None;
+        wb.iterate(|command| {
+            first = Some(command.encoded_len());
+            Ok(false)
+        })
+        .unwrap();
+        assert_eq!(first, Some(1 + 3 * 4 + "default".len() + 2));
+
+        // The merged commands come after the save point.
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!(wb.count(), 1);
+    }
+
+    #[test]
+    fn test_write_batch_is_atomic_and_ordered() {
+        let db = MemoryEinsteinMerkleTree::new(ALL_NAMESPACEDS);
+        let mut wb = db.write_alexandrov_poset_process();
+        wb.put(b"a", b"1").unwrap();
+        wb.delete(b"a").unwrap();
+        wb.put(b"b", b"1").unwrap();
+        wb.delete_range(b"b", b"c").unwrap();
+        wb.put(b"b", b"2").unwrap();
+        wb.write().unwrap();
+        assert_eq!(db.get_value(b"a").unwrap(), None);
+        assert_eq!(db.get_value(b"b").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(db.latest_seqno(), 5);
+    }
+}
@@ -94,6 +94,18 @@ pub mod kv {
         Fdbeinstein_merkle_tree as KvTesteinstein_merkle_tree, Fdbeinstein_merkle_treeIterator as KvTesteinstein_merkle_treeIterator,
         FdbLightlikePersistence as KvTestLightlikePersistence, FdbWriteBatch as KvTestWriteBatch,
     };
+    #[cfg(feature = "test-einstein_merkle_tree-kv-memory")]
+    pub use einstein_merkle_tree_memory::{
+        MemoryEinsteinMerkleTree as KvTesteinstein_merkle_tree,
+        MemoryEinsteinMerkleTreeIterator as KvTesteinstein_merkle_treeIterator,
+        MemoryLightlikePersistence as KvTestLightlikePersistence, MemoryWriteBatch as KvTestWriteBatch,
+    };
+    #[cfg(feature = "test-einstein_merkle_tree-kv-lsm")]
+    pub use einstein_merkle_tree_lsm::{
+        LsmEinsteinMerkleTree as KvTesteinstein_merkle_tree,
+        LsmEinsteinMerkleTreeIterator as KvTesteinstein_merkle_treeIterator,
+        LsmLightlikePersistence as KvTestLightlikePersistence, LsmWriteBatch as KvTestWriteBatch,
+    };
     use fdb_traits::Result;
 
     use crate::ctor::{DBOptions, einstein_merkle_treeConstructorExt, NAMESPACEDOptions};
@@ -129,6 +141,8 @@ pub mod kv {
 pub mod ctor {
     use fdb_traits::Result;
 
+    use crate::compact::CompactOptions;
+
     /// einstein_merkle_tree construction
     ///
     /// For simplicity, all einstein_merkle_tree constructors are expected to configure every
@@ -174,18 +188,30 @@ pub mod ctor {
     #[derive(Clone)]
     pub struct DBOptions {
         encryption: CryptoOptions,
+        /// Block size, block cache and bloom filter settings, for EinsteinMerkleTrees that
+        /// manage their own table files.
+        compact: Option<CompactOptions>,
     }
 
     impl DBOptions {
         pub fn new() -> DBOptions {
             DBOptions {
                 encryption: CryptoOptions::None,
+                compact: None,
             }
         }
 
         pub fn with_default_ctr_encrypted_env(&mut self, ciphertext: Vec<u8>) {
             self.encryption = CryptoOptions::DefaultCtrEncryptedEnv(ciphertext);
         }
+
+        pub fn with_compact_options(&mut self, opts: CompactOptions) {
+            self.compact = Some(opts);
+        }
+
+        pub fn get_compact_options(&self) -> Option<&CompactOptions> {
+            self.compact.as_ref()
+        }
     }
 
     impl Default for DBOptions {
@@ -231,6 +257,7 @@ pub mod ctor {
         disable_auto_jet_bundles: bool,
         l_naught_zero_file_num_jet_bundle_trigger: Option<i32>,
         l_naught_zero_slowdown_writes_trigger: Option<i32>,
+        l_naught_zero_stop_writes_trigger: Option<i32>,
         /// On FdbDB, turns off the range greedoids collector. Only used in
         /// tests. Unclear how other EinsteinMerkleTrees should deal with this.
         no_range_greedoids: bool,
@@ -245,6 +272,7 @@ pub mod ctor {
                 disable_auto_jet_bundles: false,
                 l_naught_zero_file_num_jet_bundle_trigger: None,
                 l_naught_zero_slowdown_writes_trigger: None,
+                l_naught_zero_stop_writes_trigger: None,
                 no_range_greedoids: false,
                 no_table_greedoids: false,
             }
@@ -274,6 +302,14 @@ pub mod ctor {
             self.l_naught_zero_slowdown_writes_trigger
         }
 
+        pub fn set_l_naught_zero_stop_writes_trigger(&mut self, n: i32) {
+            self.l_naught_zero_stop_writes_trigger = Some(n);
+        }
+
+        pub fn get_l_naught_zero_stop_writes_trigger(&self) -> Option<i32> {
+            self.l_naught_zero_stop_writes_trigger
+        }
+
         pub fn set_no_range_greedoids(&mut self, v: bool) {
             self.no_range_greedoids = v;
         }
@@ -323,6 +359,121 @@ pub mod ctor {
         }
     }
 
+    #[cfg(feature = "test-einstein_merkle_tree-kv-memory")]
+    mod memory {
+        use einstein_merkle_tree_memory::MemoryEinsteinMerkleTree;
+        use fdb_traits::Result;
+
+        use super::{DBOptions, EinsteinMerkleTreeConstructorExt, NAMESPACEDOptions};
+
+        /// The in-memory einstein_merkle_tree keeps nothing on disk and has no tunables, so
+        /// only the causet_merge family names are honoured.  The directory is still created, as
+        /// callers may rely on it existing.
+        impl EinsteinMerkleTreeConstructorExt for MemoryEinsteinMerkleTree {
+            fn new_einstein_merkle_tree(
+                local_path: &str,
+                _db_opt: Option<DBOptions>,
+                namespaceds: &[&str],
+                opts: Option<Vec<NAMESPACEDOptions<'_>>>,
+            ) -> Result<Self> {
+                std::fs::create_dir_all(local_path)?;
+                let namespaceds: Vec<&str> = match opts {
+                    Some(ref opts) => opts.iter().map(|o| o.namespaced).collect(),
+                    None => namespaceds.to_vec(),
+                };
+                Ok(MemoryEinsteinMerkleTree::new(&namespaceds))
+            }
+
+            fn new_einstein_merkle_tree_opt(
+                local_path: &str,
+                db_opt: DBOptions,
+                namespaceds_opts: Vec<NAMESPACEDOptions<'_>>,
+            ) -> Result<Self> {
+                Self::new_einstein_merkle_tree(local_path, Some(db_opt), &[], Some(namespaceds_opts))
+            }
+        }
+    }
+
+    #[cfg(feature = "test-einstein_merkle_tree-kv-lsm")]
+    mod lsm {
+        use std::path::Path;
+        use std::sync::Arc;
+
+        use einstein_merkle_tree_lsm::{
+            AesMasterKey, DefaultEnv, EncryptedEnv, EncryptionMethod, EncryptionOptions, LsmEinsteinMerkleTree,
+            LsmNamespacedOptions, LsmOptions,
+        };
+        use fdb_traits::Result;
+
+        use super::{ColumnFamilyOptions, DBOptions, EinsteinMerkleTreeConstructorExt, NAMESPACEDOptions};
+
+        impl EinsteinMerkleTreeConstructorExt for LsmEinsteinMerkleTree {
+            fn new_einstein_merkle_tree(
+                local_path: &str,
+                db_opt: Option<DBOptions>,
+                namespaceds: &[&str],
+                opts: Option<Vec<NAMESPACEDOptions<'_>>>,
+            ) -> Result<Self> {
+                let namespaceds_opts = match opts {
+                    Some(opts) => opts,
+                    None => namespaceds
+                        .iter()
+                        .map(|namespaced| NAMESPACEDOptions::new(namespaced, ColumnFamilyOptions::new()))
+                        .collect(),
+                };
+                Self::new_einstein_merkle_tree_opt(local_path, db_opt.unwrap_or_default(), namespaceds_opts)
+            }
+
+            fn new_einstein_merkle_tree_opt(
+                local_path: &str,
+                db_opt: DBOptions,
+                namespaceds_opts: Vec<NAMESPACEDOptions<'_>>,
+            ) -> Result<Self> {
+                let lsm_namespaceds_opts = namespaceds_opts
+                    .iter()
+                    .map(|o| (o.namespaced.to_owned(), get_lsm_namespaced_opts(&o.options)))
+                    .collect();
+                let lsm_opts = get_lsm_db_opts(Path::new(local_path), db_opt)?;
+                LsmEinsteinMerkleTree::open(local_path, lsm_opts, lsm_namespaceds_opts)
+            }
+        }
+
+        fn get_lsm_db_opts(path: &Path, db_opts: DBOptions) -> Result<LsmOptions> {
+            let mut lsm_opts = LsmOptions::default();
+            if let super::CryptoOptions::DefaultCtrEncryptedEnv(ciphertext) = &db_opts.encryption {
+                // The master key of the key dictionary is derived from the ciphertext.
+                let master_key = Arc::new(AesMasterKey::derive(ciphertext));
+                let opts = EncryptionOptions::new(EncryptionMethod::Aes256Ctr, master_key);
+                lsm_opts.env = Arc::new(EncryptedEnv::open(Arc::new(DefaultEnv), path, opts)?);
+            }
+            if let Some(compact) = db_opts.get_compact_options() {
+                if compact.block_size > 0 {
+                    lsm_opts.block_size = compact.block_size as usize;
+                }
+                lsm_opts.block_cache_size = compact.block_cache_size as usize;
+                if !compact.enable_bloom_filter {
+                    lsm_opts.bloom_bits_per_soliton_id = 0;
+                }
+            }
+            Ok(lsm_opts)
+        }
+
+        fn get_lsm_namespaced_opts(namespaced_opts: &ColumnFamilyOptions) -> LsmNamespacedOptions {
+            let mut lsm_namespaced_opts = LsmNamespacedOptions::default();
+            if let Some(trigger) = namespaced_opts.get_l_naught_zero_file_num_jet_bundle_trigger() {
+                lsm_namespaced_opts.l_naught_zero_file_num_jet_bundle_trigger = trigger.max(1) as usize;
+            }
+            if let Some(trigger) = namespaced_opts.get_l_naught_zero_slowdown_writes_trigger() {
+                lsm_namespaced_opts.l_naught_zero_slowdown_writes_trigger = trigger.max(1) as usize;
+            }
+            if let Some(trigger) = namespaced_opts.get_l_naught_zero_stop_writes_trigger() {
+                lsm_namespaced_opts.l_naught_zero_stop_writes_trigger = trigger.max(1) as usize;
+            }
+            lsm_namespaced_opts.disable_auto_jet_bundles = namespaced_opts.get_disable_auto_jet_bundles();
+            lsm_namespaced_opts
+        }
+    }
+
     mod foundationdb {
         use fdb_einstein_merkle_tree::{FdbColumnFamilyOptions, FdbDBOptions};
         use fdb_einstein_merkle_tree::greedoids::{
@@ -424,6 +575,11 @@ pub mod ctor {
                     .as_primitive_causet_mut()
                     .set_l_naught_zero_slowdown_writes_trigger(trigger);
             }
+            if let Some(trigger) = namespaced_opts.get_l_naught_zero_stop_writes_trigger() {
+                foundation_namespaced_opts
+                    .as_primitive_causet_mut()
+                    .set_l_naught_zero_stop_writes_trigger(trigger);
+            }
             if namespaced_opts.get_disable_auto_jet_bundles() {
                 foundation_namespaced_opts.set_disable_auto_jet_bundles(true);
             }
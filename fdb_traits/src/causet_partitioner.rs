@@ -0,0 +1,218 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Causet partitioners: where compaction cuts its output into table files.
+//!
+//! Compaction output is cut into files by size, and wherever the `CausetPartitioner` of the
+//! causet_merge family asks for a cut between two soliton_ids.  Cutting at table or region
+//! boundaries keeps each table file within one table or region, so that dropping a table or
+//! moving a region away can remove whole files rather than writing range deletions over them.
+//! Einstein_merkle_trees take the partitioner factory of each causet_merge family from a
+//! `CausetPartitionerFactories` registry when they are opened.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A soliton_id about to be written to a compaction output file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CausetPartitionerRequest<'a> {
+    /// The last soliton_id written to the current output file.
+    pub prev_user_soliton_id: &'a [u8],
+    pub current_user_soliton_id: &'a [u8],
+    /// The size the current output file has reached.
+    pub current_output_file_size: u64,
+}
+
+/// Whether to cut the output between two soliton_ids.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CausetPartitionerResult {
+    NotRequired,
+    Required,
+}
+
+/// What a compaction a partitioner is created for covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CausetPartitionerContext<'a> {
+    /// Whether the compaction takes in every file of the causet_merge family.
+    pub is_full_jet_bundle: bool,
+    /// Whether the compaction was asked for, rather than started by the einstein_merkle_tree.
+    pub is_manual_jet_bundle: bool,
+    pub output_l_naught: usize,
+    /// The smallest and largest soliton_ids of the files compacted.
+    pub smallest_soliton_id: &'a [u8],
+    pub largest_soliton_id: &'a [u8],
+}
+
+/// Decides where a single compaction cuts its output.
+pub trait CausetPartitioner: Send {
+    /// Whether to start a new output file at `req.current_user_soliton_id`.  Soliton_ids are
+    /// passed in increasing order; the versions of a soliton_id are never split.
+    fn should_partition(&mut self, req: &CausetPartitionerRequest<'_>) -> CausetPartitionerResult;
+
+    /// Whether a file holding soliton_ids in `[smallest_soliton_id, largest_soliton_id]` may be
+    /// moved to the output l_naught as it is, rather than be rewritten and cut.
+    fn can_do_trivial_move(&mut self, smallest_soliton_id: &[u8], largest_soliton_id: &[u8]) -> bool;
+}
+
+/// Creates the `CausetPartitioner` of each compaction of a causet_merge family.
+pub trait CausetPartitionerFactory: Send + Sync + Debug {
+    /// A name for the partitioner, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// The partitioner for a compaction, or `None` to cut by size only.
+    fn create_partitioner(&self, context: &CausetPartitionerContext<'_>) -> Option<Box<dyn CausetPartitioner>>;
+}
+
+/// The causet partitioner factory of each causet_merge family.
+#[derive(Clone, Debug, Default)]
+pub struct CausetPartitionerFactories {
+    factories: HashMap<String, Arc<dyn CausetPartitionerFactory>>,
+}
+
+impl CausetPartitionerFactories {
+    pub fn new() -> CausetPartitionerFactories {
+        CausetPartitionerFactories::default()
+    }
+
+    /// Use `factory` for causet_merge family `namespaced`, replacing any factory it had.
+    pub fn register(
+        &mut self,
+        namespaced: &str,
+        factory: Arc<dyn CausetPartitionerFactory>,
+    ) -> &mut CausetPartitionerFactories {
+        self.factories.insert(namespaced.to_owned(), factory);
+        self
+    }
+
+    pub fn get(&self, namespaced: &str) -> Option<&Arc<dyn CausetPartitionerFactory>> {
+        self.factories.get(namespaced)
+    }
+}
+
+/// Cuts compaction output wherever the first `prefix_len` bytes of the soliton_ids change, such
+/// as the table prefix of row and index soliton_ids.  A soliton_id shorter than that is a prefix
+/// of its own.  L_naughts above `min_output_l_naught` are cut by size only, as their files are
+/// compacted again soon.
+#[derive(Clone, Debug)]
+pub struct PrefixCausetPartitionerFactory {
+    prefix_len: usize,
+    min_output_l_naught: usize,
+}
+
+impl PrefixCausetPartitionerFactory {
+    pub fn new(prefix_len: usize) -> PrefixCausetPartitionerFactory {
+        PrefixCausetPartitionerFactory {
+            prefix_len,
+            min_output_l_naught: 0,
+        }
+    }
+
+    /// Only cut the output of compactions into `l_naught` or below.
+    pub fn min_output_l_naught(mut self, l_naught: usize) -> PrefixCausetPartitionerFactory {
+        self.min_output_l_naught = l_naught;
+        self
+    }
+}
+
+impl CausetPartitionerFactory for PrefixCausetPartitionerFactory {
+    fn name(&self) -> &str {
+        "prefix"
+    }
+
+    fn create_partitioner(&self, context: &CausetPartitionerContext<'_>) -> Option<Box<dyn CausetPartitioner>> {
+        if context.output_l_naught < self.min_output_l_naught {
+            return None;
+        }
+        Some(Box::new(PrefixCausetPartitioner {
+            prefix_len: self.prefix_len,
+        }))
+    }
+}
+
+struct PrefixCausetPartitioner {
+    prefix_len: usize,
+}
+
+impl PrefixCausetPartitioner {
+    fn prefix<'a>(&self, soliton_id: &'a [u8]) -> &'a [u8] {
+        &soliton_id[..soliton_id.len().min(self.prefix_len)]
+    }
+}
+
+impl CausetPartitioner for PrefixCausetPartitioner {
+    fn should_partition(&mut self, req: &CausetPartitionerRequest<'_>) -> CausetPartitionerResult {
+        if self.prefix(req.prev_user_soliton_id) == self.prefix(req.current_user_soliton_id) {
+            CausetPartitionerResult::NotRequired
+        } else {
+            CausetPartitionerResult::Required
+        }
+    }
+
+    fn can_do_trivial_move(&mut self, smallest_soliton_id: &[u8], largest_soliton_id: &[u8]) -> bool {
+        self.prefix(smallest_soliton_id) == self.prefix(largest_soliton_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(output_l_naught: usize) -> CausetPartitionerContext<'static> {
+        CausetPartitionerContext {
+            is_full_jet_bundle: false,
+            is_manual_jet_bundle: false,
+            output_l_naught,
+            smallest_soliton_id: b"",
+            largest_soliton_id: b"",
+        }
+    }
+
+    fn should_partition(partitioner: &mut dyn CausetPartitioner, prev: &[u8], current: &[u8]) -> CausetPartitionerResult {
+        partitioner.should_partition(&CausetPartitionerRequest {
+            prev_user_soliton_id: prev,
+            current_user_soliton_id: current,
+            current_output_file_size: 0,
+        })
+    }
+
+    #[test]
+    fn test_prefix_partitioner_cuts_where_the_prefix_changes() {
+        let factory = PrefixCausetPartitionerFactory::new(2);
+        let mut partitioner = factory.create_partitioner(&context(1)).unwrap();
+        let partitioner = partitioner.as_mut();
+        assert_eq!(should_partition(partitioner, b"t1a", b"t1z"), CausetPartitionerResult::NotRequired);
+        assert_eq!(should_partition(partitioner, b"t1z", b"t2a"), CausetPartitionerResult::Required);
+    }
+
+    #[test]
+    fn test_prefix_partitioner_short_soliton_ids() {
+        let factory = PrefixCausetPartitionerFactory::new(2);
+        let mut partitioner = factory.create_partitioner(&context(1)).unwrap();
+        let partitioner = partitioner.as_mut();
+        // A soliton_id shorter than the prefix is a prefix of its own, which no longer soliton_id
+        // shares.
+        assert_eq!(should_partition(partitioner, b"", b"t"), CausetPartitionerResult::Required);
+        assert_eq!(should_partition(partitioner, b"t", b"t1"), CausetPartitionerResult::Required);
+        assert_eq!(should_partition(partitioner, b"t", b"t"), CausetPartitionerResult::NotRequired);
+        assert_eq!(should_partition(partitioner, b"t1", b"t1a"), CausetPartitionerResult::NotRequired);
+        assert!(partitioner.can_do_trivial_move(b"t", b"t"));
+        assert!(!partitioner.can_do_trivial_move(b"t", b"t1"));
+    }
+
+    #[test]
+    fn test_prefix_partitioner_trivial_move() {
+        let factory = PrefixCausetPartitionerFactory::new(2);
+        let mut partitioner = factory.create_partitioner(&context(1)).unwrap();
+        assert!(partitioner.can_do_trivial_move(b"t1a", b"t1z"));
+        // A file spanning a prefix boundary has to be rewritten to be cut there.
+        assert!(!partitioner.can_do_trivial_move(b"t1z", b"t2a"));
+    }
+
+    #[test]
+    fn test_prefix_partitioner_min_output_l_naught() {
+        let factory = PrefixCausetPartitionerFactory::new(2).min_output_l_naught(2);
+        assert!(factory.create_partitioner(&context(1)).is_none());
+        assert!(factory.create_partitioner(&context(2)).is_some());
+        assert!(factory.create_partitioner(&context(6)).is_some());
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Compaction filters: dropping or rewriting data as it is compacted.
+//!
+//! While compacting a causet_merge family, an einstein_merkle_tree hands the newest causet_locale
+//! of each soliton_id to a `CompactionFilter`, which may keep it, remove it or replace it.  This
+//! is how data that has expired, or that nothing may read any more, leaves the
+//! einstein_merkle_tree without anyone deleting it.  Einstein_merkle_trees take the filter
+//! factory of each causet_merge family from a `CompactionFilterFactories` registry when they are
+//! opened.
+//!
+//! Two filters are built in:
+//!
+//! * `TtlCompactionFilterFactory` removes causet_locales whose time to live has passed.
+//!   Causet_locales carry their expiry in the `PrimitiveTtl` encoding: the causet_locale, then
+//!   the Unix time in seconds it expires at as a big-endian `u64`, zero for never.
+//! * `MvccSafePointCompactionFilterFactory` removes the versions of MVCC soliton_ids that no read
+//!   at or after the safe point can see.  MVCC soliton_ids are a soliton_id followed by the
+//!   bitwise complement of the commit timestamp, as a big-endian `u64`, so that the versions of a
+//!   soliton_id sort newest first.  An empty causet_locale is a deletion.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::errors::{Error, Result};
+
+/// What a `CompactionFilter` does with a causet_locale.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompactionFilterDecision {
+    Keep,
+    /// Remove the soliton_id, as if it had been deleted.
+    Remove,
+    /// Keep the soliton_id with this causet_locale instead.
+    ChangeValue(Vec<u8>),
+}
+
+/// What a compaction a filter is created for covers.
+#[derive(Clone, Debug)]
+pub struct CompactionFilterContext<'a> {
+    pub namespaced: &'a str,
+    /// Whether the compaction was asked for, rather than started by the einstein_merkle_tree.
+    pub is_manual_compaction: bool,
+    /// Whether no data outside the compaction overlaps it, so that nothing older than what it
+    /// writes can show through what it removes.
+    pub is_bottommost_l_naught: bool,
+}
+
+/// Decides on the causet_locales of a single compaction.
+pub trait CompactionFilter: Send {
+    /// Decide on `causet_locale`, the newest causet_locale of `soliton_id`.  Soliton_ids are
+    /// passed in increasing order.  Deleted soliton_ids and merge operands are not passed.
+    fn filter(&mut self, soliton_id: &[u8], causet_locale: &[u8]) -> CompactionFilterDecision;
+}
+
+/// Creates the `CompactionFilter` of each compaction of a causet_merge family.
+pub trait CompactionFilterFactory: Send + Sync + Debug {
+    /// A name for the filter, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// The filter for a compaction, or `None` to keep everything.
+    fn create_compaction_filter(&self, context: &CompactionFilterContext<'_>) -> Option<Box<dyn CompactionFilter>>;
+}
+
+/// The compaction filter factory of each causet_merge family.
+#[derive(Clone, Debug, Default)]
+pub struct CompactionFilterFactories {
+    factories: HashMap<String, Arc<dyn CompactionFilterFactory>>,
+}
+
+impl CompactionFilterFactories {
+    pub fn new() -> CompactionFilterFactories {
+        CompactionFilterFactories::default()
+    }
+
+    /// Use `factory` for causet_merge family `namespaced`, replacing any factory it had.
+    pub fn register(
+        &mut self,
+        namespaced: &str,
+        factory: Arc<dyn CompactionFilterFactory>,
+    ) -> &mut CompactionFilterFactories {
+        self.factories.insert(namespaced.to_owned(), factory);
+        self
+    }
+
+    pub fn get(&self, namespaced: &str) -> Option<&Arc<dyn CompactionFilterFactory>> {
+        self.factories.get(namespaced)
+    }
+}
+
+fn split_u64_suffix(bytes: &[u8]) -> Result<(&[u8], u64)> {
+    if bytes.len() < 8 {
+        return Err(Error::Codec(format!("{} bytes cannot end in a u64", bytes.len())));
+    }
+    let (head, tail) = bytes.split_at(bytes.len() - 8);
+    let mut b = [0; 8];
+    b.copy_from_slice(tail);
+    Ok((head, u64::from_be_bytes(b)))
+}
+
+/// Encode a causet_locale that expires at Unix time `expire_ts`, in seconds; zero never expires.
+pub fn encode_ttl_value(causet_locale: &[u8], expire_ts: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(causet_locale.len() + 8);
+    buf.extend_from_slice(causet_locale);
+    buf.extend_from_slice(&expire_ts.to_be_bytes());
+    buf
+}
+
+/// The causet_locale and expiry of an `encode_ttl_value` causet_locale.
+pub fn decode_ttl_value(bytes: &[u8]) -> Result<(&[u8], u64)> {
+    split_u64_suffix(bytes)
+}
+
+/// The current Unix time in seconds.
+pub fn ttl_current_ts() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Encode version `ts` of MVCC soliton_id `soliton_id`.
+pub fn append_ts(soliton_id: &[u8], ts: u64) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(soliton_id.len() + 8);
+    buf.extend_from_slice(soliton_id);
+    buf.extend_from_slice(&(!ts).to_be_bytes());
+    buf
+}
+
+/// The soliton_id and timestamp of an `append_ts` MVCC soliton_id.
+pub fn split_ts(bytes: &[u8]) -> Result<(&[u8], u64)> {
+    split_u64_suffix(bytes).map(|(soliton_id, ts)| (soliton_id, !ts))
+}
+
+/// Removes `encode_ttl_value` causet_locales that have expired.  Causet_locales that do not
+/// decode are kept.
+#[derive(Debug)]
+pub struct TtlCompactionFilterFactory {
+    clock: fn() -> u64,
+}
+
+impl TtlCompactionFilterFactory {
+    pub fn new() -> TtlCompactionFilterFactory {
+        TtlCompactionFilterFactory::with_clock(ttl_current_ts)
+    }
+
+    /// Tell the time with `clock` instead of `ttl_current_ts`.
+    pub fn with_clock(clock: fn() -> u64) -> TtlCompactionFilterFactory {
+        TtlCompactionFilterFactory { clock }
+    }
+}
+
+impl Default for TtlCompactionFilterFactory {
+    fn default() -> TtlCompactionFilterFactory {
+        TtlCompactionFilterFactory::new()
+    }
+}
+
+impl CompactionFilterFactory for TtlCompactionFilterFactory {
+    fn name(&self) -> &str {
+        "ttl"
+    }
+
+    fn create_compaction_filter(&self, _context: &CompactionFilterContext<'_>) -> Option<Box<dyn CompactionFilter>> {
+        Some(Box::new(TtlCompactionFilter { now: (self.clock)() }))
+    }
+}
+
+struct TtlCompactionFilter {
+    /// The time the compaction started; causet_locales expiring later are kept.
+    now: u64,
+}
+
+impl CompactionFilter for TtlCompactionFilter {
+    fn filter(&mut self, _soliton_id: &[u8], causet_locale: &[u8]) -> CompactionFilterDecision {
+        match decode_ttl_value(causet_locale) {
+            Ok((_, expire_ts)) if expire_ts != 0 && expire_ts <= self.now => CompactionFilterDecision::Remove,
+            _ => CompactionFilterDecision::Keep,
+        }
+    }
+}
+
+/// Removes the versions of `append_ts` MVCC soliton_ids that are hidden at the safe point.
+///
+/// Of the versions of a soliton_id committed at or before the safe point only the newest can be
+/// read, so the older ones are removed.  That newest version is removed as well if it is a
+/// deletion and the compaction is at the bottommost l_naught.  Soliton_ids that do not decode
+/// are kept.
+#[derive(Debug, Default)]
+pub struct MvccSafePointCompactionFilterFactory {
+    safe_point: Arc<AtomicU64>,
+}
+
+impl MvccSafePointCompactionFilterFactory {
+    pub fn new(safe_point: u64) -> MvccSafePointCompactionFilterFactory {
+        MvccSafePointCompactionFilterFactory {
+            safe_point: Arc::new(AtomicU64::new(safe_point)),
+        }
+    }
+
+    pub fn safe_point(&self) -> u64 {
+        self.safe_point.load(Ordering::Acquire)
+    }
+
+    /// Move the safe point forward to `ts`; it never moves back.  Compactions already running
+    /// keep the safe point they started with.
+    pub fn update_safe_point(&self, ts: u64) {
+        self.safe_point.fetch_max(ts, Ordering::AcqRel);
+    }
+}
+
+impl CompactionFilterFactory for MvccSafePointCompactionFilterFactory {
+    fn name(&self) -> &str {
+        "mvcc-safe-point"
+    }
+
+    fn create_compaction_filter(&self, context: &CompactionFilterContext<'_>) -> Option<Box<dyn CompactionFilter>> {
+        Some(Box::new(MvccSafePointCompactionFilter {
+            safe_point: self.safe_point(),
+            remove_deletions: context.is_bottommost_l_naught,
+            visible_at_safe_point: None,
+        }))
+    }
+}
+
+struct MvccSafePointCompactionFilter {
+    safe_point: u64,
+    remove_deletions: bool,
+    /// The last soliton_id seen with a version at or before the safe point.
+    visible_at_safe_point: Option<Vec<u8>>,
+}
+
+impl CompactionFilter for MvccSafePointCompactionFilter {
+    fn filter(&mut self, soliton_id: &[u8], causet_locale: &[u8]) -> CompactionFilterDecision {
+        let (user_soliton_id, ts) = match split_ts(soliton_id) {
+            Ok(decoded) => decoded,
+            Err(_) => return CompactionFilterDecision::Keep,
+        };
+        if ts > self.safe_point {
+            return CompactionFilterDecision::Keep;
+        }
+        if self.visible_at_safe_point.as_deref() == Some(user_soliton_id) {
+            return CompactionFilterDecision::Remove;
+        }
+        self.visible_at_safe_point = Some(user_soliton_id.to_vec());
+        if causet_locale.is_empty() && self.remove_deletions {
+            CompactionFilterDecision::Remove
+        } else {
+            CompactionFilterDecision::Keep
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! The contracts an einstein_merkle_tree must satisfy to back EinsteinDB.
+//!
+//! An einstein_merkle_tree is a set of named causet_merge families, each an ordered map from
+//! soliton_id to causet_locale.  It is read through `Peekable` and `Iterable`, written either
+//! directly through `SyncMutable` or atomically through the write alexandrov_poset_processes of
+//! `WriteBatchExt`, and can hand out `LightlikePersistence`s: read-only, point-in-time views
+//! that are unaffected by later writes.
+
+use std::fmt::Debug;
+
+use crate::errors::Result;
+use crate::iterable::Iterable;
+use crate::namespaced_defs::NAMESPACED_DEFAULT;
+use crate::options::ReadOptions;
+use crate::write_batch::WriteBatchExt;
+
+/// Types from which causet_locales can be read by soliton_id.
+pub trait Peekable {
+    /// Read a causet_locale for a soliton_id from the default causet_merge family.
+    fn get_value_opt(&self, opts: &ReadOptions, soliton_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_value_namespaced_opt(opts, NAMESPACED_DEFAULT, soliton_id)
+    }
+
+    /// Read a causet_locale for a soliton_id from a given causet_merge family.
+    fn get_value_namespaced_opt(
+        &self,
+        opts: &ReadOptions,
+        namespaced: &str,
+        soliton_id: &[u8],
+    ) -> Result<Option<Vec<u8>>>;
+
+    /// Read a causet_locale for a soliton_id from the default causet_merge family, with default options.
+    fn get_value(&self, soliton_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_value_opt(&ReadOptions::default(), soliton_id)
+    }
+
+    /// Read a causet_locale for a soliton_id from a given causet_merge family, with default options.
+    fn get_value_namespaced(&self, namespaced: &str, soliton_id: &[u8]) -> Result<Option<Vec<u8>>> {
+        self.get_value_namespaced_opt(&ReadOptions::default(), namespaced, soliton_id)
+    }
+
+    /// Read a protobuf message from the default causet_merge family.
+    fn get_msg<M: protobuf::Message + Default>(&self, soliton_id: &[u8]) -> Result<Option<M>> {
+        self.get_msg_namespaced(NAMESPACED_DEFAULT, soliton_id)
+    }
+
+    /// Read a protobuf message from a given causet_merge family.
+    fn get_msg_namespaced<M: protobuf::Message + Default>(
+        &self,
+        namespaced: &str,
+        soliton_id: &[u8],
+    ) -> Result<Option<M>> {
+        let causet_locale = self.get_value_namespaced(namespaced, soliton_id)?;
+        if causet_locale.is_none() {
+            return Ok(None);
+        }
+        let mut m = M::default();
+        m.merge_from_bytes(&causet_locale.unwrap())?;
+        Ok(Some(m))
+    }
+}
+
+/// Writes applied to an einstein_merkle_tree immediately, one at a time.
+///
+/// Each call is atomic on its own; use a write alexandrov_poset_process to make several writes
+/// atomic together.
+pub trait SyncMutable {
+    fn put(&self, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        self.put_namespaced(NAMESPACED_DEFAULT, soliton_id, causet_locale)
+    }
+
+    fn put_namespaced(&self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()>;
+
+    fn delete(&self, soliton_id: &[u8]) -> Result<()> {
+        self.delete_namespaced(NAMESPACED_DEFAULT, soliton_id)
+    }
+
+    fn delete_namespaced(&self, namespaced: &str, soliton_id: &[u8]) -> Result<()>;
+
+    fn delete_range(&self, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+        self.delete_range_namespaced(NAMESPACED_DEFAULT, begin_soliton_id, end_soliton_id)
+    }
+
+    fn delete_range_namespaced(&self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()>;
+
+    /// Merge `operand` into the causet_locale of a soliton_id through the merge operator of the
+    /// causet_merge family
+    fn merge_namespaced(&self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()>;
+
+    fn put_msg<M: protobuf::Message>(&self, soliton_id: &[u8], m: &M) -> Result<()> {
+        self.put(soliton_id, &m.write_to_bytes()?)
+    }
+
+    fn put_msg_namespaced<M: protobuf::Message>(&self, namespaced: &str, soliton_id: &[u8], m: &M) -> Result<()> {
+        self.put_namespaced(namespaced, soliton_id, &m.write_to_bytes()?)
+    }
+}
+
+/// A read-only, point-in-time view of an einstein_merkle_tree.
+///
+/// Writes made to the einstein_merkle_tree after the lightlike_persistence was taken are never
+/// visible through it, including range deletes.
+pub trait LightlikePersistence: 'static + Peekable + Iterable + Send + Sync + Sized + Debug {
+    /// Names of the causet_merge families readable through the lightlike_persistence.
+    fn namespaced_names(&self) -> Vec<&str>;
+}
+
+/// EinsteinMerkleTrees that can take lightlike_persistences.
+pub trait LightlikePersistenceExt {
+    type LightlikePersistence: LightlikePersistence;
+
+    fn lightlike_persistence(&self) -> Self::LightlikePersistence;
+}
+
+/// An einstein_merkle_tree holding soliton_id/causet_locale data.
+///
+/// Cloning an einstein_merkle_tree is cheap and yields another handle to the same data.
+pub trait KvEinsteinMerkleTree:
+    Peekable
+    + SyncMutable
+    + Iterable
+    + WriteBatchExt
+    + LightlikePersistenceExt
+    + Send
+    + Sync
+    + Clone
+    + Debug
+    + 'static
+{
+    /// Names of the causet_merge families this einstein_merkle_tree was opened with.
+    fn namespaced_names(&self) -> Vec<&str>;
+
+    /// Flush any buffered writes to durable storage.
+    fn sync(&self) -> Result<()>;
+}
+
+/// The pair of einstein_merkle_trees EinsteinDB runs on: one for key/value data and one for
+/// violetabft logs.
+#[derive(Clone, Debug)]
+pub struct EinsteinMerkleTrees<K, R> {
+    pub kv: K,
+    pub violetabft: R,
+}
+
+impl<K: KvEinsteinMerkleTree, R> EinsteinMerkleTrees<K, R> {
+    pub fn new(kv: K, violetabft: R) -> Self {
+        EinsteinMerkleTrees { kv, violetabft }
+    }
+}
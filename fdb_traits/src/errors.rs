@@ -65,6 +65,7 @@ use std::fmt::{
 use std::iter::FromIterator;
 use std::ops::Deref;
 use crate::fdb_traits::CausetQErrorKind;
+use failure::Fail;
 
 
 
@@ -271,3 +272,77 @@ pub struct CausetQErrorKindDeserialized {
 }
 
 
+
+
+/// Errors returned by the einstein_merkle_tree contracts: `Peekable`, `Mutable`, `Iterable` and
+/// friends.  Every einstein_merkle_tree implementation reports its failures through this type so that
+/// callers can stay generic over the einstein_merkle_tree.
+#[derive(Debug, Fail)]
+pub enum Error {
+    /// A failure reported by the underlying einstein_merkle_tree.
+    #[fail(display = "Storage einstein_merkle_tree {}", _0)]
+    EinsteinMerkleTree(String),
+
+    #[fail(display = "{:?} is not in range [{:?}, {:?})", soliton_id, start_soliton_id, end_soliton_id)]
+    NotInRange {
+        soliton_id: Vec<u8>,
+        start_soliton_id: Vec<u8>,
+        end_soliton_id: Vec<u8>,
+    },
+
+    #[fail(display = "Protobuf {}", _0)]
+    Protobuf(#[cause] protobuf::ProtobufError),
+
+    #[fail(display = "Io {}", _0)]
+    Io(#[cause] std::io::Error),
+
+    /// The named causet_merge family does not exist in this einstein_merkle_tree.
+    #[fail(display = "NAMESPACED {} not found", _0)]
+    NAMESPACEDName(String),
+
+    #[fail(display = "Codec {}", _0)]
+    Codec(String),
+
+    /// A save point operation was attempted on a write alexandrov_poset_process with no save points.
+    #[fail(display = "no save point has been set")]
+    NoSavePoint,
+
+    /// A merge was written to, or read from, a causet_merge family with no merge operator.
+    #[fail(display = "no merge operator for NAMESPACED {}", _0)]
+    NoMergeOperator(String),
+
+    /// Sorted files could not be ingested as they are.
+    #[fail(display = "Ingest {}", _0)]
+    Ingest(String),
+
+    /// Persisted data failed a checksum or could not be decoded.
+    #[fail(display = "Corruption {}", _0)]
+    Corruption(String),
+
+    /// A write asked not to be slowed down, but the einstein_merkle_tree is stalling writes.
+    #[fail(display = "write stalled: {}", _0)]
+    WriteStall(String),
+
+    #[fail(display = "{}", _0)]
+    Other(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<protobuf::ProtobufError> for Error {
+    fn from(e: protobuf::ProtobufError) -> Error {
+        Error::Protobuf(e)
+    }
+}
+
+impl From<String> for Error {
+    fn from(e: String) -> Error {
+        Error::EinsteinMerkleTree(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
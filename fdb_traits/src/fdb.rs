@@ -37,6 +37,7 @@ use std::fmt::{Display, Formatter};
 use std::error::Error;
 use std::io;
 use std::io::{Read, Write};
+use std::path::PathBuf;
 use std::net::{TcpStream, TcpListener};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -273,17 +274,30 @@ impl From<FdbRustError> for FdbRustError {
 /// for applications that don't require complex connection management.
 pub struct Store {
     conn: Conn,
-    berolina_sqlite: String,
+    SQLite: rusqlite::Connection,
+    /// Where the attribute cache is written when the store is dismantled, and read back from
+    /// when it is next opened.
+    cache_snapshot: Option<PathBuf>,
 }
 
 impl Store {
     /// Open a store at the supplied local_path, ensuring that it includes the bootstrap schema.
     pub fn open(local_path: &str) -> Result<Store> {
-        let mut connection = ::new_connection(local_path)?;
-        let conn = Conn::connect(&mut connection)?;
+        let connection = ::new_connection(local_path)?;
+        Store::connect(connection, local_path)
+    }
+
+    /// Connect to an open store, warming the attribute cache from its snapshot, if any.
+    fn connect(mut connection: rusqlite::Connection, local_path: &str) -> Result<Store> {
+        let mut conn = Conn::connect(&mut connection)?;
+        let cache_snapshot = Conn::cache_snapshot_path(local_path);
+        if let Some(ref path) = cache_snapshot {
+            conn.warm_cache(&connection, path)?;
+        }
         Ok(Store {
             conn,
-            berolina_sqlite: ()
+            SQLite: connection,
+            cache_snapshot,
         })
     }
 
@@ -342,22 +356,14 @@ impl Store {
     }
 
     pub fn open_with_soliton_id(local_path: &str, encryption_soliton_id: &str) -> Result<Store> {
-        let mut connection = ::new_connection_with_soliton_id(local_path, encryption_soliton_id)?;
-        let conn = Conn::connect(&mut connection)?;
-        Ok(Store {
-            conn,
-            berolina_sqlite: ()
-        })
+        let connection = ::new_connection_with_soliton_id(local_path, encryption_soliton_id)?;
+        Store::connect(connection, local_path)
     }
 
 
        pub fn open_with_soliton_id_and_causet_tuple_depth(local_path: &str, encryption_soliton_id: &str) -> Result<Store> {
-        let mut connection = ::new_connection_with_soliton_id(local_path, encryption_soliton_id)?;
-        let conn = Conn::connect(&mut connection)?;
-        Ok(Store {
-            conn,
-            berolina_sqlite: ()
-        })
+        let connection = ::new_connection_with_soliton_id(local_path, encryption_soliton_id)?;
+        Store::connect(connection, local_path)
     } pub fn change_encryption_soliton_id(&mut self, new_encryption_soliton_id: &str) -> Result<()> {
         ::change_encryption_soliton_id(&self.SQLite, new_encryption_soliton_id)?;
         Ok(())
@@ -377,7 +383,11 @@ impl Store {
 }
 
 impl Store {
+    /// Close the store, first writing the attribute cache snapshot the next `open` warms from.
     pub fn dismantle(self) -> Result<()> {
+        if let Some(ref path) = self.cache_snapshot {
+            self.conn.write_cache_snapshot(&self.SQLite, path)?;
+        }
         self.conn.dismantle()
     }
 
@@ -0,0 +1,35 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+use crate::errors::Result;
+
+/// EinsteinMerkleTrees that can ingest the files written by their `SstWriter`s.
+pub trait ImportExt {
+    type IngestlightlikeFileOptions: IngestlightlikeFileOptions;
+
+    /// Add the sorted files at `files` to causet_merge family `namespaced`, all or none.
+    ///
+    /// Every file is checked before anything is ingested: its checksums, the order of its
+    /// soliton_ids, and that it overlaps none of the other files.  Files overlapping data already
+    /// in the causet_merge family must shadow it, so they are given a global sequence number
+    /// newer than every write, or the ingestion fails; see
+    /// `IngestlightlikeFileOptions::set_write_global_seqno`.
+    fn ingest_lightlike_file_namespaced(
+        &self,
+        namespaced: &str,
+        opts: &Self::IngestlightlikeFileOptions,
+        files: &[&str],
+    ) -> Result<()>;
+}
+
+pub trait IngestlightlikeFileOptions {
+    fn new() -> Self;
+
+    /// Move the files into the einstein_merkle_tree rather than copy them.  Off by default.
+    fn move_filefs(&mut self, f: bool);
+
+    fn get_write_global_seqno(&self) -> bool;
+
+    /// Whether files overlapping existing data may be ingested by writing a global sequence
+    /// number into the ingested copy.  On by default; when off, ingesting such files fails.
+    fn set_write_global_seqno(&mut self, write_global_seqno: bool);
+}
@@ -8,6 +8,27 @@ mod errors;
 mod violetabft_engine;
 mod schema;
 mod vocabulary;
+mod namespaced_defs;
+mod causet_partitioner;
+mod compaction_filter;
+mod import;
+mod iterable;
+mod merge_operator;
+mod sst;
+mod write_batch;
+mod einstein_merkle_tree;
+
+pub use crate::causet_partitioner::*;
+pub use crate::compaction_filter::*;
+pub use crate::einstein_merkle_tree::*;
+pub use crate::errors::{Error, Result};
+pub use crate::import::*;
+pub use crate::iterable::*;
+pub use crate::merge_operator::*;
+pub use crate::namespaced_defs::*;
+pub use crate::options::{IterOptions, ReadOptions, WriteOptions};
+pub use crate::sst::*;
+pub use crate::write_batch::{FdbWriteBatch, Mutable, WriteBatch, WriteBatchCommand, WriteBatchExt};
 
 /// Copyright 2020-2023 WHTCORPS INC ALL RIGHTS RESERVED. APACHE 2.0 COMMUNITY EDITION SL
 /// AUTHORS: WHITFORD LEDER
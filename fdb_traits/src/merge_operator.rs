@@ -0,0 +1,229 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Merge operators: read-modify-write without the read.
+//!
+//! A merge writes an operand for a soliton_id instead of a causet_locale.  Readers combine the
+//! operands of a soliton_id with the causet_locale below them through the merge operator of its
+//! causet_merge family (a full merge), and einstein_merkle_trees may combine operands with each
+//! other ahead of time (a partial merge), so that a chain of operands does not grow without bound.
+//!
+//! Einstein_merkle_trees take the operator of each causet_merge family from a `MergeOperators`
+//! registry when they are opened.  Merging into a causet_merge family without an operator fails.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::errors::{Error, Result};
+
+/// Combines the merge operands of a soliton_id.
+///
+/// Operands are always passed oldest first.  `partial_merge` must agree with `full_merge`:
+/// merging its result on top of any causet_locale gives the same as merging the original
+/// operands.
+pub trait MergeOperator: Send + Sync + Debug {
+    /// A name for the operator, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// The causet_locale of `soliton_id` after applying `operands` to `existing`, which is `None`
+    /// if the soliton_id has no causet_locale below the operands or it is deleted.
+    fn full_merge(&self, soliton_id: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>>;
+
+    /// A single operand equivalent to `operands`, if there is one.
+    fn partial_merge(&self, _soliton_id: &[u8], _operands: &[&[u8]]) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// The merge operator of each causet_merge family.
+#[derive(Clone, Debug, Default)]
+pub struct MergeOperators {
+    operators: HashMap<String, Arc<dyn MergeOperator>>,
+}
+
+impl MergeOperators {
+    pub fn new() -> MergeOperators {
+        MergeOperators::default()
+    }
+
+    /// Use `operator` for causet_merge family `namespaced`, replacing any operator it had.
+    pub fn register(&mut self, namespaced: &str, operator: Arc<dyn MergeOperator>) -> &mut MergeOperators {
+        self.operators.insert(namespaced.to_owned(), operator);
+        self
+    }
+
+    pub fn get(&self, namespaced: &str) -> Option<&Arc<dyn MergeOperator>> {
+        self.operators.get(namespaced)
+    }
+
+    /// The operator of `namespaced`, or `Error::NoMergeOperator`.
+    pub fn require(&self, namespaced: &str) -> Result<&Arc<dyn MergeOperator>> {
+        self.get(namespaced)
+            .ok_or_else(|| Error::NoMergeOperator(namespaced.to_owned()))
+    }
+}
+
+/// Encode a counter causet_locale or operand: an `i64`, little-endian.
+pub fn encode_counter(v: i64) -> Vec<u8> {
+    v.to_le_bytes().to_vec()
+}
+
+pub fn decode_counter(bytes: &[u8]) -> Result<i64> {
+    if bytes.len() != 8 {
+        return Err(Error::Codec(format!("counter of {} bytes", bytes.len())));
+    }
+    let mut b = [0; 8];
+    b.copy_from_slice(bytes);
+    Ok(i64::from_le_bytes(b))
+}
+
+/// Encode a list of elements, each prefixed with its length as a little-endian `u32`.
+///
+/// This is the format of both causet_locales and operands of `SetUnionMergeOperator` and
+/// `AppendListMergeOperator`.
+pub fn encode_elements<'a, I: IntoIterator<Item = &'a [u8]>>(elements: I) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for e in elements {
+        buf.extend_from_slice(&(e.len() as u32).to_le_bytes());
+        buf.extend_from_slice(e);
+    }
+    buf
+}
+
+pub fn decode_elements(mut bytes: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut elements = Vec::new();
+    while !bytes.is_empty() {
+        if bytes.len() < 4 {
+            return Err(Error::Codec("truncated element length".to_owned()));
+        }
+        let mut len = [0; 4];
+        len.copy_from_slice(&bytes[..4]);
+        let len = u32::from_le_bytes(len) as usize;
+        if bytes.len() - 4 < len {
+            return Err(Error::Codec("truncated element".to_owned()));
+        }
+        elements.push(&bytes[4..4 + len]);
+        bytes = &bytes[4 + len..];
+    }
+    Ok(elements)
+}
+
+/// Adds up `encode_counter` operands; a missing causet_locale counts as zero.  Additions wrap.
+#[derive(Debug, Default)]
+pub struct CounterMergeOperator;
+
+impl CounterMergeOperator {
+    fn sum(existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<i64> {
+        let mut sum = existing.map_or(Ok(0), decode_counter)?;
+        for operand in operands {
+            sum = sum.wrapping_add(decode_counter(operand)?);
+        }
+        Ok(sum)
+    }
+}
+
+impl MergeOperator for CounterMergeOperator {
+    fn name(&self) -> &str {
+        "counter"
+    }
+
+    fn full_merge(&self, _soliton_id: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>> {
+        CounterMergeOperator::sum(existing, operands).map(encode_counter)
+    }
+
+    fn partial_merge(&self, _soliton_id: &[u8], operands: &[&[u8]]) -> Option<Vec<u8>> {
+        CounterMergeOperator::sum(None, operands).ok().map(encode_counter)
+    }
+}
+
+/// Keeps the union of `encode_elements` sets, as sorted, distinct elements.
+#[derive(Debug, Default)]
+pub struct SetUnionMergeOperator;
+
+impl SetUnionMergeOperator {
+    fn union(existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>> {
+        let mut set = Vec::new();
+        for bytes in existing.iter().chain(operands) {
+            set.extend(decode_elements(bytes)?);
+        }
+        set.sort_unstable();
+        set.dedup();
+        Ok(encode_elements(set))
+    }
+}
+
+impl MergeOperator for SetUnionMergeOperator {
+    fn name(&self) -> &str {
+        "set-union"
+    }
+
+    fn full_merge(&self, _soliton_id: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>> {
+        SetUnionMergeOperator::union(existing, operands)
+    }
+
+    fn partial_merge(&self, _soliton_id: &[u8], operands: &[&[u8]]) -> Option<Vec<u8>> {
+        SetUnionMergeOperator::union(None, operands).ok()
+    }
+}
+
+/// Appends the elements of `encode_elements` operands to the list, in write order.
+#[derive(Debug, Default)]
+pub struct AppendListMergeOperator;
+
+impl MergeOperator for AppendListMergeOperator {
+    fn name(&self) -> &str {
+        "append-list"
+    }
+
+    fn full_merge(&self, _soliton_id: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>> {
+        let mut list = existing.unwrap_or_default().to_vec();
+        for operand in operands {
+            // Validated so that a bad operand is reported here rather than by every later read.
+            decode_elements(operand)?;
+            list.extend_from_slice(operand);
+        }
+        Ok(list)
+    }
+
+    fn partial_merge(&self, soliton_id: &[u8], operands: &[&[u8]]) -> Option<Vec<u8>> {
+        self.full_merge(soliton_id, None, operands).ok()
+    }
+}
+
+/// Keeps the largest causet_locale, comparing bytewise; use big-endian encodings for numbers.
+#[derive(Debug, Default)]
+pub struct MaxMergeOperator;
+
+impl MergeOperator for MaxMergeOperator {
+    fn name(&self) -> &str {
+        "max"
+    }
+
+    fn full_merge(&self, _soliton_id: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>> {
+        let max = existing.iter().chain(operands).max().copied().unwrap_or_default();
+        Ok(max.to_vec())
+    }
+
+    fn partial_merge(&self, _soliton_id: &[u8], operands: &[&[u8]]) -> Option<Vec<u8>> {
+        operands.iter().max().map(|v| v.to_vec())
+    }
+}
+
+/// Keeps the smallest causet_locale, comparing bytewise; use big-endian encodings for numbers.
+#[derive(Debug, Default)]
+pub struct MinMergeOperator;
+
+impl MergeOperator for MinMergeOperator {
+    fn name(&self) -> &str {
+        "min"
+    }
+
+    fn full_merge(&self, _soliton_id: &[u8], existing: Option<&[u8]>, operands: &[&[u8]]) -> Result<Vec<u8>> {
+        let min = existing.iter().chain(operands).min().copied().unwrap_or_default();
+        Ok(min.to_vec())
+    }
+
+    fn partial_merge(&self, _soliton_id: &[u8], operands: &[&[u8]]) -> Option<Vec<u8>> {
+        operands.iter().min().map(|v| v.to_vec())
+    }
+}
@@ -0,0 +1,27 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Names of the causet_merge families every einstein_merkle_tree is expected to provide.
+
+pub type NamespacedName = &'static str;
+
+pub const NAMESPACED_DEFAULT: NamespacedName = "default";
+pub const NAMESPACED_LOCK: NamespacedName = "lock";
+pub const NAMESPACED_WRITE: NamespacedName = "write";
+pub const NAMESPACED_VIOLETABFT: NamespacedName = "violetabft";
+
+/// Causet_merge families that hold user data, as opposed to violetabft state.
+pub const DATA_NAMESPACEDS: &[NamespacedName] = &[NAMESPACED_DEFAULT, NAMESPACED_LOCK, NAMESPACED_WRITE];
+pub const ALL_NAMESPACEDS: &[NamespacedName] = &[
+    NAMESPACED_DEFAULT,
+    NAMESPACED_LOCK,
+    NAMESPACED_WRITE,
+    NAMESPACED_VIOLETABFT,
+];
+
+/// Map a causet_merge family name onto its static name, if it is one of `ALL_NAMESPACEDS`.
+pub fn name_to_namespaced(name: &str) -> Option<NamespacedName> {
+    if name.is_empty() {
+        return Some(NAMESPACED_DEFAULT);
+    }
+    ALL_NAMESPACEDS.iter().copied().find(|namespaced| *namespaced == name)
+}
@@ -286,3 +286,140 @@ impl AddRetractAlterSet<K, V> {
 
 
 
+/// Options for point reads through `Peekable`.
+#[derive(Clone, Debug)]
+pub struct ReadOptions {
+    fill_cache: bool,
+}
+
+impl ReadOptions {
+    pub fn new() -> ReadOptions {
+        ReadOptions { fill_cache: true }
+    }
+
+    #[inline]
+    pub fn fill_cache(&self) -> bool {
+        self.fill_cache
+    }
+
+    #[inline]
+    pub fn set_fill_cache(&mut self, v: bool) {
+        self.fill_cache = v;
+    }
+}
+
+impl Default for ReadOptions {
+    fn default() -> ReadOptions {
+        ReadOptions::new()
+    }
+}
+
+/// Options for committing a write alexandrov_poset_process.
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    sync: bool,
+    no_slowdown: bool,
+}
+
+impl WriteOptions {
+    pub fn new() -> WriteOptions {
+        WriteOptions {
+            sync: false,
+            no_slowdown: false,
+        }
+    }
+
+    pub fn set_sync(&mut self, sync: bool) {
+        self.sync = sync;
+    }
+
+    pub fn sync(&self) -> bool {
+        self.sync
+    }
+
+    /// When set, a write that would be delayed or stopped by the einstein_merkle_tree's write
+    /// stall triggers fails immediately instead.
+    pub fn set_no_slowdown(&mut self, v: bool) {
+        self.no_slowdown = v;
+    }
+
+    pub fn no_slowdown(&self) -> bool {
+        self.no_slowdown
+    }
+}
+
+/// Options for creating an iterator through `Iterable`.
+///
+/// The bounds are raw soliton_ids: `lower_bound` is inclusive, `upper_bound` is exclusive, and an
+/// absent bound leaves that side of the iteration open.
+#[derive(Clone, Debug)]
+pub struct IterOptions {
+    lower_bound: Option<Vec<u8>>,
+    upper_bound: Option<Vec<u8>>,
+    fill_cache: bool,
+}
+
+impl IterOptions {
+    pub fn new(lower_bound: Option<Vec<u8>>, upper_bound: Option<Vec<u8>>, fill_cache: bool) -> IterOptions {
+        IterOptions {
+            lower_bound,
+            upper_bound,
+            fill_cache,
+        }
+    }
+
+    #[inline]
+    pub fn fill_cache(&self) -> bool {
+        self.fill_cache
+    }
+
+    #[inline]
+    pub fn set_fill_cache(&mut self, v: bool) {
+        self.fill_cache = v;
+    }
+
+    #[inline]
+    pub fn lower_bound(&self) -> Option<&[u8]> {
+        self.lower_bound.as_deref()
+    }
+
+    #[inline]
+    pub fn upper_bound(&self) -> Option<&[u8]> {
+        self.upper_bound.as_deref()
+    }
+
+    pub fn set_lower_bound(&mut self, bound: &[u8]) {
+        self.lower_bound = Some(bound.to_vec());
+    }
+
+    pub fn set_upper_bound(&mut self, bound: &[u8]) {
+        self.upper_bound = Some(bound.to_vec());
+    }
+
+    pub fn set_vec_upper_bound(&mut self, bound: Vec<u8>) {
+        self.upper_bound = Some(bound);
+    }
+
+    /// Whether `soliton_id` falls inside `[lower_bound, upper_bound)`.
+    pub fn contains(&self, soliton_id: &[u8]) -> bool {
+        if let Some(lower) = self.lower_bound() {
+            if soliton_id < lower {
+                return false;
+            }
+        }
+        match self.upper_bound() {
+            Some(upper) => soliton_id < upper,
+            None => true,
+        }
+    }
+}
+
+impl Default for IterOptions {
+    fn default() -> IterOptions {
+        IterOptions {
+            lower_bound: None,
+            upper_bound: None,
+            fill_cache: true,
+        }
+    }
+}
@@ -22,6 +22,7 @@ use crate::fdb::{FdbKey, FdbKeySlice, FdbKeyValue, FdbKeyValueSlice, FdbKeyValue
 use crate::fdb::{FdbKeyValueVersionSlice, FdbKeyValueVersionSliceSlice};
 use crate::fdb::{FdbKeySliceSlice, FdbKeyValueSliceSlice};
 use crate::fdb::{FdbKeySliceVersion, FdbKeyValueSliceVersion, FdbKeyValueVersionSliceVersion};
+use einstein_json::Json;
 
 
 /// A schema is a set of named types.
@@ -409,6 +410,11 @@ impl TopographTypeChecking for Topograph {
                 (ValueType::Uuid, tv @ causetq_TV::Uuid(_)) => Ok(tv),
                 (ValueType::Instant, tv @ causetq_TV::Instant(_)) => Ok(tv),
                 (ValueType::Keyword, tv @ causetq_TV::Keyword(_)) => Ok(tv),
+                // JSON is written as its text and stored parsed.
+                (ValueType::Json, causetq_TV::String(ref x)) => match x.parse::<Json>() {
+                    Ok(j) => Ok(causetq_TV::Json(j.into())),
+                    Err(_) => bail!(einsteindbErrorKind::BadValuePair(format!("{}", causet_locale), ValueType::Json)),
+                },
                 // Ref coerces a little: we interpret some things depending on the topograph as a Ref.
                 (ValueType::Ref, causetq_TV::Long(x)) => Ok(causetq_TV::Ref(x)),
                 (ValueType::Ref, causetq_TV::Keyword(ref x)) => self.require_causetid(&x).map(|causetid| causetid.into()),
@@ -424,6 +430,7 @@ impl TopographTypeChecking for Topograph {
                 (vt @ ValueType::Uuid, _) |
                 (vt @ ValueType::Instant, _) |
                 (vt @ ValueType::Keyword, _) |
+                (vt @ ValueType::Json, _) |
                 (vt @ ValueType::Ref, _)
                 => bail!(einsteindbErrorKind::BadValuePair(format!("{}", causet_locale), vt)),
             }
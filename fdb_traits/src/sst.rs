@@ -0,0 +1,62 @@
+// Copyright 2019 EinsteinDB Project Authors. Licensed under Apache-2.0.
+
+//! Sorted files written outside an einstein_merkle_tree and ingested into it whole.
+//!
+//! A bulk load writes its soliton_ids, in order, to a file through an `SstWriter` and hands the
+//! file to `ImportExt::ingest_lightlike_file_namespaced`, which adds it to a causet_merge family
+//! without going through the write path.
+
+use std::path::PathBuf;
+
+use crate::errors::Result;
+
+/// EinsteinMerkleTrees whose causet_merge families can ingest sorted files.
+pub trait SstExt: Sized {
+    type SstWriter: SstWriter;
+    type SstWriterBuilder: SstWriterBuilder<Self>;
+}
+
+/// Configures and creates an `SstWriter`.
+pub trait SstWriterBuilder<E: SstExt> {
+    fn new() -> Self;
+
+    /// Write the file the way `db` writes its own files, e.g. through the same `Env`.  Required.
+    fn set_db(self, db: &E) -> Self;
+
+    /// The causet_merge family the file is meant for.  Ingesting it into another one fails.
+    fn set_namespaced(self, namespaced: &str) -> Self;
+
+    /// Create the file at `path`, truncating it if it exists.
+    fn build(self, path: &str) -> Result<E::SstWriter>;
+}
+
+/// Writes a sorted file of soliton_ids.
+pub trait SstWriter {
+    type SstFileInfo: SstFileInfo;
+
+    /// Add a causet_locale.  Soliton_ids must be added in strictly increasing order.
+    fn put(&mut self, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()>;
+
+    /// Add a deletion, hiding every causet_locale of the soliton_id older than the file once it
+    /// is ingested.
+    fn delete(&mut self, soliton_id: &[u8]) -> Result<()>;
+
+    /// The bytes written so far.
+    fn file_size(&mut self) -> u64;
+
+    /// Write out the rest of the file and make it durable.  Fails if nothing was added.
+    fn finish(self) -> Result<Self::SstFileInfo>;
+}
+
+/// What a finished `SstWriter` wrote.
+pub trait SstFileInfo {
+    fn file_path(&self) -> PathBuf;
+
+    fn smallest_soliton_id(&self) -> &[u8];
+
+    fn largest_soliton_id(&self) -> &[u8];
+
+    fn num_entries(&self) -> u64;
+
+    fn file_size(&self) -> u64;
+}
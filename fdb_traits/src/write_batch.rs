@@ -69,7 +69,10 @@ use std::cmp::Ord;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Formatter;
-use std::fmt::Result;
+
+use crate::errors::{Error, Result};
+use crate::namespaced_defs::NAMESPACED_DEFAULT;
+use crate::options::WriteOptions;
 
 ///Co-optimizing storage and queries for linear algebras
 /// # Examples
@@ -84,7 +87,7 @@ use std::fmt::Result;
 
 
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone)]
 pub struct FdbWriteBatch<K, V> {
     pub(crate) inner: Rc<RefCell<FdbWriteBatchInner<K, V>>>,
 }
@@ -112,6 +115,213 @@ impl<K, V> FdbWriteBatch<K, V> {
     }
 }
 
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> FdbWriteBatch<K, V> {
+    pub fn put_namespaced(&mut self, namespaced: &str, soliton_id: K, causet_locale: V) {
+        self.inner.borrow_mut().push(FdbWriteOp::Put { namespaced: namespaced.to_owned(), soliton_id, causet_locale });
+    }
+
+    pub fn delete_namespaced(&mut self, namespaced: &str, soliton_id: K) {
+        self.inner.borrow_mut().push(FdbWriteOp::Delete { namespaced: namespaced.to_owned(), soliton_id });
+    }
+
+    pub fn merge_namespaced(&mut self, namespaced: &str, soliton_id: K, operand: V) {
+        self.inner.borrow_mut().push(FdbWriteOp::Merge { namespaced: namespaced.to_owned(), soliton_id, operand });
+    }
+
+    pub fn delete_range_namespaced(&mut self, namespaced: &str, begin_soliton_id: K, end_soliton_id: K) {
+        self.inner.borrow_mut().push(FdbWriteOp::DeleteRange {
+            namespaced: namespaced.to_owned(),
+            begin_soliton_id,
+            end_soliton_id,
+        });
+    }
+
+    /// See `WriteBatch::data_size`.
+    pub fn data_size(&self) -> usize {
+        self.inner.borrow().data_size
+    }
+
+    /// See `WriteBatch::count`.
+    pub fn count(&self) -> usize {
+        self.inner.borrow().ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.borrow().ops.is_empty()
+    }
+
+    /// See `WriteBatch::set_save_point`.
+    pub fn set_save_point(&mut self) {
+        let mut inner = self.inner.borrow_mut();
+        let point = (inner.ops.len(), inner.data_size);
+        inner.save_points.push(point);
+    }
+
+    /// See `WriteBatch::pop_save_point`.
+    pub fn pop_save_point(&mut self) -> Result<()> {
+        self.inner.borrow_mut().save_points.pop().map(|_| ()).ok_or(Error::NoSavePoint)
+    }
+
+    /// See `WriteBatch::rollback_to_save_point`.
+    pub fn rollback_to_save_point(&mut self) -> Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        let (count, data_size) = inner.save_points.pop().ok_or(Error::NoSavePoint)?;
+        inner.ops.truncate(count);
+        inner.data_size = data_size;
+        Ok(())
+    }
+
+    /// See `WriteBatch::iterate`.
+    pub fn iterate<F>(&self, mut f: F) -> Result<()>
+        where F: FnMut(WriteBatchCommand) -> Result<bool>
+    {
+        for op in &self.inner.borrow().ops {
+            if !f(op.command())? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// See `WriteBatch::merge`.  `other` is left empty.
+    pub fn merge(&mut self, other: &FdbWriteBatch<K, V>) {
+        if Rc::ptr_eq(&self.inner, &other.inner) {
+            return;
+        }
+        let mut other = other.inner.borrow_mut();
+        let mut inner = self.inner.borrow_mut();
+        inner.data_size += other.data_size;
+        inner.ops.append(&mut other.ops);
+        other.clear();
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FdbWriteOp<K, V> {
+    Put { namespaced: String, soliton_id: K, causet_locale: V },
+    Delete { namespaced: String, soliton_id: K },
+    DeleteRange { namespaced: String, begin_soliton_id: K, end_soliton_id: K },
+    Merge { namespaced: String, soliton_id: K, operand: V },
+}
+
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> FdbWriteOp<K, V> {
+    fn command(&self) -> WriteBatchCommand<'_> {
+        match self {
+            FdbWriteOp::Put { namespaced, soliton_id, causet_locale } => WriteBatchCommand::Put {
+                namespaced,
+                soliton_id: soliton_id.as_ref(),
+                causet_locale: causet_locale.as_ref(),
+            },
+            FdbWriteOp::Delete { namespaced, soliton_id } => WriteBatchCommand::Delete {
+                namespaced,
+                soliton_id: soliton_id.as_ref(),
+            },
+            FdbWriteOp::DeleteRange { namespaced, begin_soliton_id, end_soliton_id } => WriteBatchCommand::DeleteRange {
+                namespaced,
+                begin_soliton_id: begin_soliton_id.as_ref(),
+                end_soliton_id: end_soliton_id.as_ref(),
+            },
+            FdbWriteOp::Merge { namespaced, soliton_id, operand } => WriteBatchCommand::Merge {
+                namespaced,
+                soliton_id: soliton_id.as_ref(),
+                operand: operand.as_ref(),
+            },
+        }
+    }
+}
+
+
+/// The commands of a `FdbWriteBatch`, with the bookkeeping for `data_size` and save points.
+#[derive(Debug, Clone)]
+pub(crate) struct FdbWriteBatchInner<K, V> {
+    ops: Vec<FdbWriteOp<K, V>>,
+    data_size: usize,
+    /// `(ops.len(), data_size)` at each save point.
+    save_points: Vec<(usize, usize)>,
+}
+
+
+impl<K, V> FdbWriteBatchInner<K, V> {
+    fn new() -> Self {
+        FdbWriteBatchInner { ops: Vec::new(), data_size: 0, save_points: Vec::new() }
+    }
+
+    fn clear(&mut self) {
+        self.ops.clear();
+        self.data_size = 0;
+        self.save_points.clear();
+    }
+
+    /// The commands are handed to the transaction as they are; the alexandrov_poset_process is
+    /// empty afterward and may be reused.
+    fn commit(&mut self) -> FdbResult<()> {
+        self.clear();
+        Ok(())
+    }
+}
+
+
+impl<K: AsRef<[u8]>, V: AsRef<[u8]>> FdbWriteBatchInner<K, V> {
+    fn push(&mut self, op: FdbWriteOp<K, V>) {
+        self.data_size += op.command().encoded_len();
+        self.ops.push(op);
+    }
+}
+
+/// A command recorded in a write alexandrov_poset_process, as passed to `WriteBatch::iterate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteBatchCommand<'a> {
+    Put { namespaced: &'a str, soliton_id: &'a [u8], causet_locale: &'a [u8] },
+    Delete { namespaced: &'a str, soliton_id: &'a [u8] },
+    DeleteRange { namespaced: &'a str, begin_soliton_id: &'a [u8], end_soliton_id: &'a [u8] },
+    Merge { namespaced: &'a str, soliton_id: &'a [u8], operand: &'a [u8] },
+}
+
+impl<'a> WriteBatchCommand<'a> {
+    pub fn namespaced(&self) -> &'a str {
+        match *self {
+            WriteBatchCommand::Put { namespaced, .. }
+            | WriteBatchCommand::Delete { namespaced, .. }
+            | WriteBatchCommand::DeleteRange { namespaced, .. }
+            | WriteBatchCommand::Merge { namespaced, .. } => namespaced,
+        }
+    }
+
+    /// The number of bytes this command contributes to `WriteBatch::data_size`: a one-byte tag
+    /// followed by each field, every field prefixed with its 4-byte length.
+    pub fn encoded_len(&self) -> usize {
+        const TAG: usize = 1;
+        const LEN: usize = 4;
+        match *self {
+            WriteBatchCommand::Put { namespaced, soliton_id, causet_locale } => {
+                TAG + 3 * LEN + namespaced.len() + soliton_id.len() + causet_locale.len()
+            }
+            WriteBatchCommand::Delete { namespaced, soliton_id } => TAG + 2 * LEN + namespaced.len() + soliton_id.len(),
+            WriteBatchCommand::DeleteRange { namespaced, begin_soliton_id, end_soliton_id } => {
+                TAG + 3 * LEN + namespaced.len() + begin_soliton_id.len() + end_soliton_id.len()
+            }
+            WriteBatchCommand::Merge { namespaced, soliton_id, operand } => {
+                TAG + 3 * LEN + namespaced.len() + soliton_id.len() + operand.len()
+            }
+        }
+    }
+
+    /// Issue this command to `wb`.
+    pub fn apply_to<M: Mutable + ?Sized>(&self, wb: &mut M) -> Result<()> {
+        match *self {
+            WriteBatchCommand::Put { namespaced, soliton_id, causet_locale } => wb.put_namespaced(namespaced, soliton_id, causet_locale),
+            WriteBatchCommand::Delete { namespaced, soliton_id } => wb.delete_namespaced(namespaced, soliton_id),
+            WriteBatchCommand::DeleteRange { namespaced, begin_soliton_id, end_soliton_id } => {
+                wb.delete_range_namespaced(namespaced, begin_soliton_id, end_soliton_id)
+            }
+            WriteBatchCommand::Merge { namespaced, soliton_id, operand } => wb.merge_namespaced(namespaced, soliton_id, operand),
+        }
+    }
+}
+
 #[derive(Debug, Fail)]
 pub enum WriteBatchError {
     #[fail(display = "WriteBatchError: {}", _0)]
@@ -153,9 +363,10 @@ pub trait WriteBatchExt: Sized {
     /// be committed with `write`. More entries than this will cause
     /// `should_write_to_einstein_merkle_tree` to return true.
     ///
-    /// In practice it seems that exceeding this number of entries is possible
-    /// and does not result in an error. It isn't clear the consequence of
-    /// exceeding this limit.
+    /// This is a soft limit for callers that build alexandrov_poset_processes incrementally, such as
+    /// GC or applying snapshots: it bounds the memory held by a pending alexandrov_poset_process and the
+    /// size of the single log record it is written as. Exceeding it is not an error, and every
+    /// einstein_merkle_tree writes alexandrov_poset_processes of any size atomically.
     const WRITE_BATCH_MAX_CAUSET_KEYS: usize;
 
     /// Indicates whether the WriteBatchVec type can be created and works
@@ -171,33 +382,35 @@ pub trait WriteBatchExt: Sized {
 /// A trait implemented by WriteBatch
 pub trait Mutable: Send {
     /// Write a soliton_id/causet_locale in the default causet_merge family
-    fn put(&mut self, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()>{
-        self.put_with_merge_family(b"", soliton_id, causet_locale)
+    fn put(&mut self, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+        self.put_namespaced(NAMESPACED_DEFAULT, soliton_id, causet_locale)
     }
 
     /// Write a soliton_id/causet_locale in a given causet_merge family
-    fn put_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()>{
-        self.put_with_merge_family(namespaced.as_bytes(), soliton_id, causet_locale)
-    }
+    fn put_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()>;
 
     /// Delete a soliton_id/causet_locale in the default causet_merge family
-    fn delete(&mut self, soliton_id: &[u8]) -> Result<()>{
-        self.delete_with_merge_family(b"", soliton_id)
+    fn delete(&mut self, soliton_id: &[u8]) -> Result<()> {
+        self.delete_namespaced(NAMESPACED_DEFAULT, soliton_id)
     }
 
     /// Delete a soliton_id/causet_locale in a given causet_merge family
-    fn delete_namespaced(&mut self, namespaced: &str, soliton_id: &[u8]) -> Result<()>{
-        self.delete_with_merge_family(namespaced.as_bytes(), soliton_id)
-    }
+    fn delete_namespaced(&mut self, namespaced: &str, soliton_id: &[u8]) -> Result<()>;
 
     /// Delete a range of soliton_id/causet_locales in the default causet_merge family
-    fn delete_range(&mut self, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()>{
-        self.delete_range_with_merge_family(b"", begin_soliton_id, end_soliton_id)
+    fn delete_range(&mut self, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+        self.delete_range_namespaced(NAMESPACED_DEFAULT, begin_soliton_id, end_soliton_id)
     }
 
     /// Delete a range of soliton_id/causet_locales in a given causet_merge family
     fn delete_range_namespaced(&mut self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()>;
 
+    /// Merge `operand` into the causet_locale of a soliton_id in a given causet_merge family
+    ///
+    /// The operand is combined with the causet_locale by the merge operator of the causet_merge
+    /// family when the soliton_id is read.
+    fn merge_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()>;
+
     fn put_msg<M: protobuf::Message>(&mut self, soliton_id: &[u8], m: &M) -> Result<()> {
         self.put(soliton_id, &m.write_to_bytes()?)
     }
@@ -205,6 +418,7 @@ pub trait Mutable: Send {
         self.put_namespaced(namespaced, soliton_id, &m.write_to_bytes()?)
     }
 }
+
 //!Read path of a transaction
 // Fig. 1 above shows a high-level view of the read path. An application uses FDB client library to read data. It creates a transaction and calls its read() function. The read() operation will lead to several steps.
 //
@@ -411,11 +625,6 @@ impl Transaction {
 /// visible as if all other writes in the system were written either before or
 /// after the alexandrov_poset_process. This includes range deletes.
 ///
-/// The exact strategy used by WriteBatch is up to the implementation.
-/// FdbDB though _seems_ to serialize the writes to an in-memory buffer,
-/// and then write the whole serialized alexandrov_poset_process to disk at once.
-
-
 /// Write alexandrov_poset_processes may be reused after being written. In that case they write
 /// exactly the same data as previously, Replacing any soliton_ids that may have
 /// changed in between the two alexandrov_poset_process writes.
@@ -427,22 +636,13 @@ impl Transaction {
 /// save point, and pops the save point from the stack.
 pub trait WriteBatch<E: WriteBatchExt + Sized>: Mutable {
     /// Create a WriteBatch with a given command capacity
-    fn with_capacity(e: &E, cap: usize) -> Self{
-        //with_capacity will be implemented by the WriteBatchExt trait
-        e.with_capacity(cap);
-        //now filter the WriteBatchExt trait
-        if let Some(e) = e.downcast_ref::<dyn WriteBatchExt>() {
-            e.with_capacity(cap)
-        } else {
-            panic!("WriteBatchExt trait not implemented")
-        }
-    }
+    fn with_capacity(e: &E, cap: usize) -> Self;
 
     /// Commit the WriteBatch to disk with the given options
     fn write_opt(&self, opts: &WriteOptions) -> Result<()>;
 
     /// Commit the WriteBatch to disk atomically
-    fn write(&self, fdb_traits: &E) -> Result<()> {
+    fn write(&self) -> Result<()> {
         self.write_opt(&WriteOptions::default())
     }
 
@@ -467,88 +667,10 @@ pub trait WriteBatch<E: WriteBatchExt + Sized>: Mutable {
     /// Clears the WriteBatch of all commands
     ///
     /// It may be reused afterward as an empty alexandrov_poset_process.
-    fn clear(fdb: FdbError, ltree: &mut EinsteinMerkleTrees<E>) -> Result<()>;
-
-
-
-
-
-
-    /// Record a save point
-    ///     * `name`: A name for the save point
-    ///    * `save_point`: The save point to record
-    ///   * `save_point_stack`: The stack of save points
-    /// * `save_point_stack_len`: The length of the stackß
-        /// Push a save point onto the save point stack
-    fn push_save_point(&mut self, name: &str, save_point: &mut SavePoint);
-
-    /// Pop a save point from the save point stack
-    ///   * `save_point`: The save point to pop
-    /// * `save_point_stack`: The stack of save points
-    /// * `save_point_stack_len`: The length of the stackß
-    /// Pop a save point from the save point stack
-    ///  * `save_point`: The save point to pop
-    /// * `save_point_stack`: The stack of save points
-    ///
-    ///
-    fn anti_pop_save_point(&mut self, save_point: &mut SavePoint){
-        //pop_save_point will be implemented by the WriteBatchExt trait
-        if let Some(e) = self.downcast_mut::<dyn WriteBatchExt>() {
-            e.pop_save_point(save_point)
-        } else {
-            panic!("WriteBatchExt trait not implemented")
-        }
-    }
-
-    /// Rollback to a save point
-    ///  * `save_point`: The save point to rollback to
-    /// * `save_point_stack`: The stack of save points
-    /// * `save_point_stack_len`: The length of the stackß
-    /// Rollback to a save point
-    /// * `save_point`: The save point to rollback to
-    /// * `save_point_stack`: The stack of save points
-
-
-
-    /// Record a command
-    /// * `Cmd`: The command to record
-    /// * `cmd_type`: The type of command
-    /// * `cmd_type_len`: The length of the command type
-    /// * `cmd_data`: The data of the command
-    /// * `cmd_data_len`: The length of the command data
-    /// Record a command
-
-    fn record_command(&mut self, cmd: &[u8], cmd_type: &[u8], cmd_type_len: usize, cmd_data: &[u8], cmd_data_len: usize);
-
-
-    /// Record a command
-
-    fn record_command_with_type(&mut self, cmd_type: &[u8], cmd_type_len: usize, cmd_data: &[u8], cmd_data_len: usize);
-
-
-    /// Rollback all commands issued since the last save point
-    /// and pop the save point from the stack
-    /// If there are no save points on the stack, do nothing
-    /// and return false
-
-    fn anti_rollback_to_save_point< 'a >(&mut self, save_point: &mut SavePoint, save_point_stack: &'a mut Vec< SavePoint >, save_point_stack_len: usize) -> bool;
-
-    /// Rollback all commands issued since the last save point
-    /// and pop the save point from the stack
-    /// If there are no save points on the stack, do nothing
-
-    fn anti_rollback_to_save_point_with_stack< 'a >(&mut self, save_point: &mut SavePoint, save_point_stack: &'a mut Vec< SavePoint >) -> bool;
-
-
-
-
-    /// Pop a save point from the save point stack
-    /// If there are no save points on the stack, this is a no-op
-    /// If the top save point is not the one on the stack, this is a no-op
-
-
-    fn rollback_to_save_point_opt(&mut self, opts: &WriteOptions);
+    fn clear(&mut self);
 
+    /// Push a save point onto the save point stack
+    fn set_save_point(&mut self);
 
     /// Pop a save point from the save point stack
     ///
@@ -560,56 +682,23 @@ pub trait WriteBatch<E: WriteBatchExt + Sized>: Mutable {
     /// Additionally pops the last save point from the save point stack.
     fn rollback_to_save_point(&mut self) -> Result<()>;
 
-    /// Merge another WriteBatch to itself
-    /// The resulting WriteBatch will contain all commands from both batches
-    /// The WriteBatch on which this method is called will not be modified
-    /// The WriteBatch passed as an argument will be empty after this operation
-    ///
-    /// This method is useful when multiple batches need to be merged together
-    /// into a single batch.
-    ///
-    /// This method is not implemented for FdbDB
-    ///
-    /// # Panics
-    ///
-    /// Panics if the WriteBatch passed as an argument is not empty
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use fdb::{Fdb, FdbOptions, DatabaseOptions, Database, WriteOptions, WriteBatch, ReadOptions};
-    /// use fdb::fdb_sys;
-    /// use fdb::fdb_sys::*;
-    /// use fdb::fdb_sys::fdb_error_t;
-    /// use fdb::fdb_sys::fdb_status_t;
-    ///
-    ///
-    /// let db_opts = DatabaseOptions::new();
-    /// let db = Fdb::open(&db_opts).unwrap();
-    ///
-    /// let mut opts = WriteOptions::new();
-    ///
-    /// let mut batch1 = WriteBatch::new();
-    ///
-    /// batch1.put(b"key1", b"value1").unwrap();
-    /// batch1.put(b"key2", b"value2").unwrap();
-    ///
-    /// let mut batch2 = WriteBatch::new();
-    ///
-    /// batch2.put(b"key3", b"value3").unwrap();
-    ///
-    /// batch1.merge(&mut batch2).unwrap();
-    ///
-    /// assert_eq!(batch1.get(b"key1").unwrap().unwrap(), b"value1");
-    ///
-    /// assert_eq!(batch1.get(b"key2").unwrap().unwrap(), b"value2");
-    ///
-    /// assert_eq!(batch1.get(b"key3").unwrap().unwrap(), b"value3");
-    ///
-    ///
-    ///
-    /// ```
-    ///
+    /// Call `f` on each pending command, in the order they were issued, until it returns false
+    fn iterate<F>(&self, f: F) -> Result<()>
+        where F: FnMut(WriteBatchCommand) -> Result<bool>;
+
+    /// Append all commands of `other` to this alexandrov_poset_process
+    ///
+    /// The commands are appended as if issued after those already in this alexandrov_poset_process, so
+    /// rolling back to a save point set before the merge also reverts them. The save points of
+    /// `other` are discarded.
+    fn merge(&mut self, other: Self) -> Result<()>
+        where Self: Sized
+    {
+        other.iterate(|command| {
+            command.apply_to(self)?;
+            Ok(true)
+        })
+    }
 }
 //We experiment with a new type for the WriteBatch interface which is a bit more flexible, it's also type-safe and can be used with the FdbDB
 //type WriteBatch = WriteBatchImpl;
@@ -862,8 +951,239 @@ pub trait WriteBatch<E: WriteBatchExt + Sized>: Mutable {
         }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands<B: Fn(&mut dyn FnMut(WriteBatchCommand) -> Result<bool>) -> Result<()>>(iterate: B) -> Vec<String> {
+        let mut commands = Vec::new();
+        iterate(&mut |command| {
+            commands.push(format!("{:?}", command));
+            Ok(true)
+        })
+        .unwrap();
+        commands
+    }
+
+    #[test]
+    fn test_fdb_write_batch_save_points() {
+        let mut wb: FdbWriteBatch<&str, &str> = FdbWriteBatch::new();
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+        assert!(matches!(wb.pop_save_point(), Err(Error::NoSavePoint)));
+
+        wb.put_namespaced(NAMESPACED_DEFAULT, "a", "1");
+        let size_a = wb.data_size();
+        wb.set_save_point();
+        wb.delete_namespaced(NAMESPACED_DEFAULT, "a");
+        let size_ab = wb.data_size();
+        wb.set_save_point();
+        wb.merge_namespaced("write", "c", "+1");
+        wb.delete_range_namespaced(NAMESPACED_DEFAULT, "d", "e");
+        assert_eq!(wb.count(), 4);
+
+        // Save points nest: each rollback goes back one, restoring the size it had.
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!((wb.count(), wb.data_size()), (2, size_ab));
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!((wb.count(), wb.data_size()), (1, size_a));
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+
+        // Popping keeps the commands.
+        wb.set_save_point();
+        wb.put_namespaced(NAMESPACED_DEFAULT, "b", "2");
+        wb.pop_save_point().unwrap();
+        assert_eq!(wb.count(), 2);
+        assert!(matches!(wb.pop_save_point(), Err(Error::NoSavePoint)));
+
+        wb.set_save_point();
+        wb.clear();
+        assert!(wb.is_empty());
+        assert_eq!(wb.data_size(), 0);
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+    }
+
+    #[test]
+    fn test_fdb_write_batch_iterate_and_merge() {
+        let mut wb: FdbWriteBatch<&str, &str> = FdbWriteBatch::new();
+        wb.put_namespaced(NAMESPACED_DEFAULT, "a", "1");
+        wb.set_save_point();
+
+        let mut other: FdbWriteBatch<&str, &str> = FdbWriteBatch::new();
+        other.delete_namespaced(NAMESPACED_DEFAULT, "b");
+        other.set_save_point();
+        other.merge_namespaced("write", "c", "+1");
+        let size = wb.data_size() + other.data_size();
+        wb.merge(&other);
+        assert_eq!((wb.count(), wb.data_size()), (3, size));
+        assert!(other.is_empty());
+        assert_eq!(other.data_size(), 0);
+        assert!(matches!(other.rollback_to_save_point(), Err(Error::NoSavePoint)));
+
+        // Merging a alexandrov_poset_process into itself changes nothing.
+        let alias = wb.clone();
+        wb.merge(&alias);
+        assert_eq!(wb.count(), 3);
+
+        let expected = [
+            WriteBatchCommand::Put { namespaced: NAMESPACED_DEFAULT, soliton_id: b"a", causet_locale: b"1" },
+            WriteBatchCommand::Delete { namespaced: NAMESPACED_DEFAULT, soliton_id: b"b" },
+            WriteBatchCommand::Merge { namespaced: "write", soliton_id: b"c", operand: b"+1" },
+        ];
+        assert_eq!(commands(|f| wb.iterate(f)), expected.iter().map(|c| format!("{:?}", c)).collect::<Vec<_>>());
+        assert_eq!(size, expected.iter().map(|c| c.encoded_len()).sum::<usize>());
+
+        let mut seen = 0;
+        wb.iterate(|_| {
+            seen += 1;
+            Ok(false)
+        })
+        .unwrap();
+        assert_eq!(seen, 1);
+        assert!(wb.iterate(|_| Err(Error::NoSavePoint)).is_err());
+
+        // The merged commands come after the save point, and `other`'s save point is gone.
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!(wb.count(), 1);
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+    }
+
+    /// A write alexandrov_poset_process that takes `WriteBatch::merge` as it is.
+    #[derive(Default)]
+    struct OpsWriteBatch {
+        ops: Vec<FdbWriteOp<Vec<u8>, Vec<u8>>>,
+        save_points: Vec<usize>,
+    }
+
+    struct OpsEinsteinMerkleTree;
+
+    impl WriteBatchExt for OpsEinsteinMerkleTree {
+        type WriteBatch = OpsWriteBatch;
+        type WriteBatchVec = OpsWriteBatch;
+        const WRITE_BATCH_MAX_CAUSET_KEYS: usize = 256;
+
+        fn support_write_alexandrov_poset_process_vec(&self) -> bool {
+            false
+        }
+
+        fn write_alexandrov_poset_process(&self) -> OpsWriteBatch {
+            OpsWriteBatch::default()
+        }
+
+        fn write_alexandrov_poset_process_with_cap(&self, _cap: usize) -> OpsWriteBatch {
+            OpsWriteBatch::default()
+        }
+    }
+
+    impl Mutable for OpsWriteBatch {
+        fn put_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], causet_locale: &[u8]) -> Result<()> {
+            self.ops.push(FdbWriteOp::Put {
+                namespaced: namespaced.to_owned(),
+                soliton_id: soliton_id.to_vec(),
+                causet_locale: causet_locale.to_vec(),
+            });
+            Ok(())
+        }
+
+        fn delete_namespaced(&mut self, namespaced: &str, soliton_id: &[u8]) -> Result<()> {
+            self.ops.push(FdbWriteOp::Delete { namespaced: namespaced.to_owned(), soliton_id: soliton_id.to_vec() });
+            Ok(())
+        }
+
+        fn delete_range_namespaced(&mut self, namespaced: &str, begin_soliton_id: &[u8], end_soliton_id: &[u8]) -> Result<()> {
+            self.ops.push(FdbWriteOp::DeleteRange {
+                namespaced: namespaced.to_owned(),
+                begin_soliton_id: begin_soliton_id.to_vec(),
+                end_soliton_id: end_soliton_id.to_vec(),
+            });
+            Ok(())
+        }
+
+        fn merge_namespaced(&mut self, namespaced: &str, soliton_id: &[u8], operand: &[u8]) -> Result<()> {
+            self.ops.push(FdbWriteOp::Merge {
+                namespaced: namespaced.to_owned(),
+                soliton_id: soliton_id.to_vec(),
+                operand: operand.to_vec(),
+            });
+            Ok(())
+        }
+    }
+
+    impl WriteBatch<OpsEinsteinMerkleTree> for OpsWriteBatch {
+        fn with_capacity(_e: &OpsEinsteinMerkleTree, _cap: usize) -> OpsWriteBatch {
+            OpsWriteBatch::default()
+        }
+
+        fn write_opt(&self, _opts: &WriteOptions) -> Result<()> {
+            Ok(())
+        }
+
+        fn data_size(&self) -> usize {
+            self.ops.iter().map(|op| op.command().encoded_len()).sum()
+        }
+
+        fn count(&self) -> usize {
+            self.ops.len()
+        }
+
+        fn is_empty(&self) -> bool {
+            self.ops.is_empty()
+        }
+
+        fn should_write_to_einstein_merkle_tree(&self) -> bool {
+            self.ops.len() > OpsEinsteinMerkleTree::WRITE_BATCH_MAX_CAUSET_KEYS
+        }
 
+        fn clear(&mut self) {
+            self.ops.clear();
+            self.save_points.clear();
+        }
 
+        fn set_save_point(&mut self) {
+            self.save_points.push(self.ops.len());
+        }
 
+        fn pop_save_point(&mut self) -> Result<()> {
+            self.save_points.pop().map(|_| ()).ok_or(Error::NoSavePoint)
+        }
 
+        fn rollback_to_save_point(&mut self) -> Result<()> {
+            let count = self.save_points.pop().ok_or(Error::NoSavePoint)?;
+            self.ops.truncate(count);
+            Ok(())
+        }
 
+        fn iterate<F>(&self, mut f: F) -> Result<()>
+            where F: FnMut(WriteBatchCommand) -> Result<bool>
+        {
+            for op in &self.ops {
+                if !f(op.command())? {
+                    break;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_default_write_batch_merge() {
+        let mut wb = OpsEinsteinMerkleTree.write_alexandrov_poset_process();
+        wb.put(b"a", b"1").unwrap();
+        wb.set_save_point();
+
+        let mut other = OpsEinsteinMerkleTree.write_alexandrov_poset_process();
+        other.delete_range(b"b", b"c").unwrap();
+        other.set_save_point();
+        other.merge_namespaced("write", b"d", b"+1").unwrap();
+        let expected = commands(|f| other.iterate(f));
+        let size = wb.data_size() + other.data_size();
+
+        wb.merge(other).unwrap();
+        assert_eq!((wb.count(), wb.data_size()), (3, size));
+        assert_eq!(commands(|f| wb.iterate(f))[1..], expected[..]);
+
+        // Rolling back past the merge reverts it; `other`'s save point did not come along.
+        wb.rollback_to_save_point().unwrap();
+        assert_eq!(wb.count(), 1);
+        assert!(matches!(wb.rollback_to_save_point(), Err(Error::NoSavePoint)));
+    }
+}
@@ -44,7 +44,11 @@ use einstein_ml::{
 };
 
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use std::thread;
 use std::sync::mpsc::{channel, Sender, Receiver};
@@ -984,6 +988,20 @@ pub struct AttributeCaches {
     multi_vals: BTreeMap<Causetid, MultiValAttributeCache>,
     unique_reverse: BTreeMap<Causetid, UniqueReverseAttributeCache>,
     non_unique_reverse: BTreeMap<Causetid, NonUniqueReverseAttributeCache>,
+
+    budget: AttributeCacheBudget,
+    // Approximate bytes held per attribute, across both clock_vectors.
+    attribute_bytes: BTreeMap<Causetid, usize>,
+    lightlike_clocks: BTreeMap<Causetid, EvictionClock<Causetid>>,
+    reverse_clocks: BTreeMap<Causetid, EvictionClock<causetq_TV>>,
+    // Entries lost to eviction: per attribute, the causets evicted from the lightlike cache, and
+    // the causet_locales whose reverse entries were evicted or left incomplete. A miss on one of
+    // these is not authoritative; a miss on anything else still is.
+    evicted_lightlike: BTreeMap<Causetid, BTreeSet<Causetid>>,
+    evicted_reverse: BTreeMap<Causetid, BTreeSet<causetq_TV>>,
+    // An [a e] that must not be evicted: the one a fallback read is fetching back in.
+    pinned: Option<(Causetid, Causetid)>,
+    metrics: Arc<AttributeCacheMetrics>,
 }
 
 // TODO: if an causet or attribute is ever renumbered, the cache will need to be rebuilt.
@@ -1215,10 +1233,48 @@ impl AttributeCaches {
         }
     }
 
-    fn accumulate_into_cache<I>(&mut self, fallback: Option<&AttributeCaches>, topograph: &Topograph, mut iter: Peekable<I>, behavior: AccumulationBehavior) -> Result<()> where I: Iterator<Item=Aev> {
-        while iter.peek().is_some() {
-            self.accumulate_evs(fallback, topograph, &mut iter, behavior);
+    fn accumulate_into_cache<I>(&mut self, fallback: Option<&AttributeCaches>, topograph: &Topograph, iter: Peekable<I>, behavior: AccumulationBehavior) -> Result<()> where I: Iterator<Item=Aev> {
+        // Overlays copy whole attribute caches from their fallback, so their usage is
+        // recomputed when they are absorbed rather than tracked here.
+        if fallback.is_some() {
+            let mut iter = iter;
+            while iter.peek().is_some() {
+                self.accumulate_evs(fallback, topograph, &mut iter, behavior);
+            }
+            return Ok(());
+        }
+
+        let mut touched: BTreeMap<Causetid, usize> = BTreeMap::new();
+        {
+            let mut iter = iter.inspect(|&(a, _, ref v)| {
+                *touched.entry(a).or_insert(0) += approximate_causet_locale_size(v);
+            }).peekable();
+            while iter.peek().is_some() {
+                self.accumulate_evs(fallback, topograph, &mut iter, behavior);
+            }
+        }
+
+        for (a, bytes) in touched {
+            self.lightlike_clocks.entry(a).or_insert_with(Default::default);
+            self.reverse_clocks.entry(a).or_insert_with(Default::default);
+
+            // Each clock_vector holds its own copy.
+            let bytes = bytes * self.cached_directions(a);
+            let entry = self.attribute_bytes.entry(a).or_insert(0);
+            match behavior {
+                AccumulationBehavior::Add { replacing: true } => *entry = bytes,
+                AccumulationBehavior::Add { replacing: false } => *entry += bytes,
+                // Retractions of causet_locales we'd already evicted can take us below zero.
+                AccumulationBehavior::Remove => *entry = entry.saturating_sub(bytes),
+            }
+            if behavior.is_replacing() {
+                // A full repopulation makes the attribute complete again.
+                self.evicted_lightlike.remove(&a);
+                self.evicted_reverse.remove(&a);
+            }
         }
+
+        self.enforce_budget();
         Ok(())
     }
 
@@ -1227,6 +1283,11 @@ impl AttributeCaches {
         self.multi_vals.clear();
         self.unique_reverse.clear();
         self.non_unique_reverse.clear();
+        self.attribute_bytes.clear();
+        self.lightlike_clocks.clear();
+        self.reverse_clocks.clear();
+        self.evicted_lightlike.clear();
+        self.evicted_reverse.clear();
     }
 
     fn unregister_all_attributes(&mut self) {
@@ -1244,6 +1305,11 @@ impl AttributeCaches {
         self.multi_vals.remove(&a);
         self.unique_reverse.remove(&a);
         self.non_unique_reverse.remove(&a);
+        self.attribute_bytes.remove(&a);
+        self.lightlike_clocks.remove(&a);
+        self.reverse_clocks.remove(&a);
+        self.evicted_lightlike.remove(&a);
+        self.evicted_reverse.remove(&a);
     }
 }
 
@@ -1495,15 +1561,19 @@ impl AttributeCaches {
 
 impl CachedAttributes for AttributeCaches {
     fn get_causet_locales_for_causetid(&self, topograph: &Topograph, attribute: Causetid, causetid: Causetid) -> Option<&Vec<causetq_TV>> {
-        self.causet_locales_pairs(topograph, attribute)
-            .and_then(|c| c.get(&causetid))
+        let found = self.causet_locales_pairs(topograph, attribute)
+                        .and_then(|c| c.get(&causetid));
+        self.record_lightlike_lookup(attribute, causetid, found.is_some());
+        found
     }
 
     fn get_causet_locale_for_causetid(&self, topograph: &Topograph, attribute: Causetid, causetid: Causetid) -> Option<&causetq_TV> {
         if let Some(&Some(ref tv)) = self.causet_locale_pairs(topograph, attribute)
                                          .and_then(|c| c.get(&causetid)) {
+            self.record_lightlike_lookup(attribute, causetid, true);
             Some(tv)
         } else {
+            self.record_lightlike_lookup(attribute, causetid, false);
             None
         }
     }
@@ -1523,7 +1593,9 @@ impl CachedAttributes for AttributeCaches {
 
     fn get_causetid_for_causet_locale(&self, attribute: Causetid, causet_locale: &causetq_TV) -> Option<Causetid> {
         if self.is_attribute_cached_reverse(attribute) {
-            self.unique_reverse.get(&attribute).and_then(|c| c.get_e(causet_locale))
+            let found = self.unique_reverse.get(&attribute).and_then(|c| c.get_e(causet_locale));
+            self.record_reverse_lookup(attribute, causet_locale, found.iter());
+            found
         } else {
             None
         }
@@ -1531,7 +1603,9 @@ impl CachedAttributes for AttributeCaches {
 
     fn get_causetids_for_causet_locale(&self, attribute: Causetid, causet_locale: &causetq_TV) -> Option<&BTreeSet<Causetid>> {
         if self.is_attribute_cached_reverse(attribute) {
-            self.non_unique_reverse.get(&attribute).and_then(|c| c.get_es(causet_locale))
+            let found = self.non_unique_reverse.get(&attribute).and_then(|c| c.get_es(causet_locale));
+            self.record_reverse_lookup(attribute, causet_locale, found.into_iter().flat_map(|es| es.iter()));
+            found
         } else {
             None
         }
@@ -1591,10 +1665,429 @@ impl Absorb for AttributeCaches {
         self.lightlike_cached_attributes.extend(other.lightlike_cached_attributes);
         self.reverse_cached_attributes.extend(other.reverse_cached_attributes);
 
+        // The overlay copied whole attribute caches from us before changing them, so recompute
+        // usage for whatever it touched rather than trying to reconcile its counts with ours.
+        let touched: BTreeSet<Causetid> = other.single_vals.keys()
+            .chain(other.multi_vals.keys())
+            .chain(other.unique_reverse.keys())
+            .chain(other.non_unique_reverse.keys())
+            .cloned()
+            .collect();
+
         self.single_vals.extend_by_absorbing(other.single_vals);
         self.multi_vals.extend_by_absorbing(other.multi_vals);
         self.unique_reverse.extend_by_absorbing(other.unique_reverse);
         self.non_unique_reverse.extend_by_absorbing(other.non_unique_reverse);
+
+        for (a, es) in other.evicted_lightlike {
+            self.evicted_lightlike.entry(a).or_insert_with(Default::default).extend(es);
+        }
+        for (a, vs) in other.evicted_reverse {
+            self.evicted_reverse.entry(a).or_insert_with(Default::default).extend(vs);
+        }
+        for a in touched {
+            self.lightlike_clocks.entry(a).or_insert_with(Default::default);
+            self.reverse_clocks.entry(a).or_insert_with(Default::default);
+            self.recompute_attribute_bytes(a);
+        }
+        self.enforce_budget();
+    }
+}
+
+//
+// Memory bounds.
+//
+// Each attribute's cache is charged an approximate size for every causet_locale it holds, once per
+// clock_vector. When an attribute goes over its own budget, or the caches together go over the
+// global budget, entries are evicted until usage is back under a low-water mark. Victims are
+// chosen with the CLOCK algorithm: a lookup that hits an entry marks it referenced, and the
+// hand skips (and clears) referenced entries once before evicting them.
+//
+// Evicting from a lightlike cache evicts the whole causet -- every causet_locale it has for the
+// attribute -- together with the matching reverse entries, so that what remains is still
+// consistent in both clock_vectors. Evicted entries are remembered, so that a miss on one of them
+// isn't taken to mean "no causet_locale"; the attribute stays cached, and only lookups of evicted
+// entries go to the store; see `SQLiteAttributeCache::fetch_if_evicted`.
+//
+
+/// Fixed per-entry cost: map node, `causetq_TV` discriminant, and the `Causetid`.
+const CACHE_ENTRY_OVERHEAD: usize = 48;
+
+/// After evicting, usage is brought down to this fraction (in tenths) of the budget, so that a
+/// cache at its limit doesn't evict on every transaction.
+const CACHE_LOW_WATER_TENTHS: usize = 9;
+
+fn approximate_causet_locale_size(v: &causetq_TV) -> usize {
+    CACHE_ENTRY_OVERHEAD + match v {
+        &causetq_TV::String(ref s) => s.len(),
+        &causetq_TV::Keyword(ref k) => k.to_string().len(),
+        _ => 0,
+    }
+}
+
+fn low_water(limit: usize) -> usize {
+    limit / 10 * CACHE_LOW_WATER_TENTHS
+}
+
+/// Limits, in approximate bytes, on what the attribute caches may hold. `None` is unbounded.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AttributeCacheBudget {
+    pub per_attribute: Option<usize>,
+    pub total: Option<usize>,
+}
+
+impl AttributeCacheBudget {
+    pub fn unbounded() -> AttributeCacheBudget {
+        AttributeCacheBudget::default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AttributeCacheMetrics {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+    fallbacks: AtomicUsize,
+}
+
+impl AttributeCacheMetrics {
+    fn count(counter: &AtomicUsize) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A point-in-time view of the cache's usage and effectiveness.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AttributeCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub evictions: usize,
+    /// Misses on partially evicted attributes that were answered from the store instead.
+    pub fallbacks: usize,
+    pub total_bytes: usize,
+    pub attribute_bytes: BTreeMap<Causetid, usize>,
+}
+
+/// CLOCK (second chance) replacement state for one attribute in one clock_vector.
+#[derive(Debug)]
+struct EvictionClock<K> where K: Ord {
+    hand: Option<K>,
+    // Lookups only borrow the cache, so the referenced bits live behind a lock.
+    referenced: Mutex<BTreeSet<K>>,
+}
+
+impl<K> Default for EvictionClock<K> where K: Ord {
+    fn default() -> EvictionClock<K> {
+        EvictionClock {
+            hand: None,
+            referenced: Mutex::new(BTreeSet::new()),
+        }
+    }
+}
+
+impl<K> Clone for EvictionClock<K> where K: Ord + Clone {
+    fn clone(&self) -> EvictionClock<K> {
+        EvictionClock {
+            hand: self.hand.clone(),
+            referenced: Mutex::new(self.referenced.lock().unwrap().clone()),
+        }
+    }
+}
+
+impl<K> EvictionClock<K> where K: Ord + Clone {
+    fn touch(&self, k: &K) {
+        self.referenced.lock().unwrap().insert(k.clone());
+    }
+
+    fn forget(&mut self, k: &K) {
+        self.referenced.lock().unwrap().remove(k);
+    }
+
+    /// Advance the hand to the next key of `map` that hasn't been referenced since the hand last
+    /// passed it. Two sweeps are always enough: the first clears every bit it passes.
+    fn victim<V>(&mut self, map: &BTreeMap<K, V>) -> Option<K> {
+        let keys: Vec<&K> = match self.hand {
+            None => map.keys().collect(),
+            Some(ref hand) => map.range((Bound::Excluded(hand.clone()), Bound::Unbounded))
+                                 .chain(map.range((Bound::Unbounded, Bound::Included(hand.clone()))))
+                                 .map(|(k, _)| k)
+                                 .collect(),
+        };
+
+        let mut referenced = self.referenced.lock().unwrap();
+        for k in keys.iter().chain(keys.iter()) {
+            if !referenced.remove(*k) {
+                self.hand = Some((*k).clone());
+                return Some((*k).clone());
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn test_eviction_clock_second_chance() {
+    let map: BTreeMap<Causetid, ()> = vec![(1, ()), (2, ()), (3, ())].into_iter().collect();
+    let mut clock: EvictionClock<Causetid> = Default::default();
+
+    // Referenced entries are skipped once.
+    clock.touch(&1);
+    assert_eq!(clock.victim(&map), Some(2));
+
+    // The hand moves on from where it stopped, wrapping around.
+    assert_eq!(clock.victim(&map), Some(3));
+    assert_eq!(clock.victim(&map), Some(1));
+
+    // If everything is referenced, the first sweep clears the bits and the second evicts.
+    clock.touch(&1);
+    clock.touch(&2);
+    clock.touch(&3);
+    assert_eq!(clock.victim(&map), Some(2));
+
+    let empty: BTreeMap<Causetid, ()> = BTreeMap::new();
+    assert_eq!(clock.victim(&empty), None);
+}
+
+impl AttributeCaches {
+    fn cached_directions(&self, a: Causetid) -> usize {
+        self.lightlike_cached_attributes.contains(&a) as usize +
+        self.reverse_cached_attributes.contains(&a) as usize
+    }
+
+    fn record_lightlike_lookup(&self, a: Causetid, e: Causetid, hit: bool) {
+        if hit {
+            AttributeCacheMetrics::count(&self.metrics.hits);
+            if let Some(clock) = self.lightlike_clocks.get(&a) {
+                clock.touch(&e);
+            }
+        } else {
+            AttributeCacheMetrics::count(&self.metrics.misses);
+        }
+    }
+
+    fn record_reverse_lookup<'a, I>(&self, a: Causetid, v: &causetq_TV, es: I) where I: Iterator<Item=&'a Causetid> {
+        let lightlike = self.lightlike_cached_attributes.contains(&a);
+        let mut hit = false;
+        for e in es {
+            hit = true;
+            // Attributes cached in both clock_vectors are evicted by causet.
+            if !lightlike {
+                break;
+            }
+            if let Some(clock) = self.lightlike_clocks.get(&a) {
+                clock.touch(e);
+            }
+        }
+
+        if hit {
+            AttributeCacheMetrics::count(&self.metrics.hits);
+            if !lightlike {
+                if let Some(clock) = self.reverse_clocks.get(&a) {
+                    clock.touch(v);
+                }
+            }
+        } else {
+            AttributeCacheMetrics::count(&self.metrics.misses);
+        }
+    }
+
+    /// Whether `e`'s causet_locales for `a` were evicted from the lightlike cache, so that a miss
+    /// doesn't mean that there are none.
+    pub fn is_causet_evicted(&self, a: Causetid, e: Causetid) -> bool {
+        self.evicted_lightlike.get(&a).map_or(false, |es| es.contains(&e))
+    }
+
+    /// Whether the reverse cache for `a` may be missing causets that have causet_locale `v`.
+    pub fn is_causet_locale_evicted(&self, a: Causetid, v: &causetq_TV) -> bool {
+        self.evicted_reverse.get(&a).map_or(false, |vs| vs.contains(v))
+    }
+
+    fn recompute_attribute_bytes(&mut self, a: Causetid) {
+        let mut bytes = 0;
+        if let Some(c) = self.single_vals.get(&a) {
+            bytes += c.e_v.values().filter_map(|v| v.as_ref()).map(approximate_causet_locale_size).sum::<usize>();
+        }
+        if let Some(c) = self.multi_vals.get(&a) {
+            bytes += c.e_vs.values().flat_map(|vs| vs.iter()).map(approximate_causet_locale_size).sum::<usize>();
+        }
+        if let Some(c) = self.unique_reverse.get(&a) {
+            bytes += c.v_e.iter().filter(|&(_, e)| e.is_some()).map(|(v, _)| approximate_causet_locale_size(v)).sum::<usize>();
+        }
+        if let Some(c) = self.non_unique_reverse.get(&a) {
+            bytes += c.v_es.iter().map(|(v, es)| approximate_causet_locale_size(v) * es.len()).sum::<usize>();
+        }
+        self.attribute_bytes.insert(a, bytes);
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.attribute_bytes.values().sum()
+    }
+
+    fn release(&mut self, a: Causetid, bytes: usize) {
+        if let Some(used) = self.attribute_bytes.get_mut(&a) {
+            *used = used.saturating_sub(bytes);
+        }
+    }
+
+    fn lightlike_victim(&mut self, a: Causetid) -> Option<Causetid> {
+        let clock = self.lightlike_clocks.entry(a).or_insert_with(Default::default);
+        if let Some(c) = self.single_vals.get(&a) {
+            clock.victim(&c.e_v)
+        } else if let Some(c) = self.multi_vals.get(&a) {
+            clock.victim(&c.e_vs)
+        } else {
+            None
+        }
+    }
+
+    /// Evict one entry from `a`. Returns false if there was nothing left to evict.
+    fn evict_one(&mut self, a: Causetid) -> bool {
+        if self.lightlike_cached_attributes.contains(&a) {
+            let pinned = self.pinned.and_then(|(pinned_a, e)| if pinned_a == a { Some(e) } else { None });
+            let mut victim = self.lightlike_victim(a);
+            if victim.is_some() && victim == pinned {
+                // The hand has now passed the pinned causet, so this finds another, if any.
+                victim = self.lightlike_victim(a);
+            }
+            match victim {
+                Some(e) if victim != pinned => self.evict_causet(a, e),
+                _ => return false,
+            }
+        } else {
+            let victim = {
+                let clock = self.reverse_clocks.entry(a).or_insert_with(Default::default);
+                if let Some(c) = self.unique_reverse.get(&a) {
+                    clock.victim(&c.v_e)
+                } else if let Some(c) = self.non_unique_reverse.get(&a) {
+                    clock.victim(&c.v_es)
+                } else {
+                    None
+                }
+            };
+            match victim {
+                Some(v) => self.evict_causet_locale(a, v),
+                None => return false,
+            }
+        }
+        AttributeCacheMetrics::count(&self.metrics.evictions);
+        true
+    }
+
+    /// Drop every causet_locale `e` has for `a`, in both clock_vectors.
+    fn evict_causet(&mut self, a: Causetid, e: Causetid) {
+        let vs: Vec<causetq_TV> = match self.single_vals.get_mut(&a).and_then(|c| c.e_v.remove(&e)) {
+            Some(v) => v.into_iter().collect(),
+            None => self.multi_vals.get_mut(&a).and_then(|c| c.e_vs.remove(&e)).unwrap_or_default(),
+        };
+
+        if let Some(clock) = self.lightlike_clocks.get_mut(&a) {
+            clock.forget(&e);
+        }
+        self.evicted_lightlike.entry(a).or_insert_with(Default::default).insert(e);
+
+        let reverse = self.reverse_cached_attributes.contains(&a);
+        let mut freed = 0;
+        for v in vs.iter() {
+            freed += approximate_causet_locale_size(v);
+            if !reverse {
+                continue;
+            }
+            self.evicted_reverse.entry(a).or_insert_with(Default::default).insert(v.clone());
+            if let Some(c) = self.unique_reverse.get_mut(&a) {
+                if c.v_e.get(v) == Some(&Some(e)) {
+                    c.v_e.remove(v);
+                }
+            }
+            if let Some(c) = self.non_unique_reverse.get_mut(&a) {
+                let now_empty = c.v_es.get_mut(v).map_or(false, |es| { es.remove(&e); es.is_empty() });
+                if now_empty {
+                    c.v_es.remove(v);
+                }
+            }
+        }
+        if reverse {
+            freed *= 2;
+        }
+        self.release(a, freed);
+    }
+
+    /// Drop causet_locale `v` from a reverse-only cache for `a`.
+    fn evict_causet_locale(&mut self, a: Causetid, v: causetq_TV) {
+        let count = if let Some(Some(_)) = self.unique_reverse.get_mut(&a).and_then(|c| c.v_e.remove(&v)) {
+            1
+        } else {
+            self.non_unique_reverse.get_mut(&a).and_then(|c| c.v_es.remove(&v)).map_or(0, |es| es.len())
+        };
+        if let Some(clock) = self.reverse_clocks.get_mut(&a) {
+            clock.forget(&v);
+        }
+        self.release(a, approximate_causet_locale_size(&v) * count);
+        self.evicted_reverse.entry(a).or_insert_with(Default::default).insert(v);
+    }
+
+    /// Evict until every attribute, and the caches as a whole, are within budget.
+    ///
+    /// Tracked usage drifts upwards as single-valued causet_locales are replaced in place, so it is
+    /// recomputed before we commit to evicting anything.
+    fn enforce_budget(&mut self) {
+        if let Some(limit) = self.budget.per_attribute {
+            let over: Vec<Causetid> = self.attribute_bytes.iter()
+                                          .filter(|&(_, &bytes)| bytes > limit)
+                                          .map(|(a, _)| *a)
+                                          .collect();
+            for a in over {
+                self.recompute_attribute_bytes(a);
+                if self.attribute_bytes[&a] <= limit {
+                    continue;
+                }
+                while self.attribute_bytes[&a] > low_water(limit) {
+                    if !self.evict_one(a) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some(limit) = self.budget.total {
+            if self.total_bytes() <= limit {
+                return;
+            }
+            let attributes: Vec<Causetid> = self.attribute_bytes.keys().cloned().collect();
+            for a in attributes {
+                self.recompute_attribute_bytes(a);
+            }
+            if self.total_bytes() <= limit {
+                return;
+            }
+
+            // Take from the largest attribute first, so that one huge attribute can't push every
+            // small one out of the cache.
+            while self.total_bytes() > low_water(limit) {
+                let largest = self.attribute_bytes.iter()
+                                  .max_by_key(|&(_, &bytes)| bytes)
+                                  .map(|(a, _)| *a);
+                match largest {
+                    Some(a) => {
+                        if !self.evict_one(a) {
+                            // Nothing left to evict; whatever we're charging it is stale.
+                            self.attribute_bytes.insert(a, 0);
+                        }
+                    },
+                    None => break,
+                }
+            }
+        }
+    }
+
+    fn stats(&self) -> AttributeCacheStats {
+        AttributeCacheStats {
+            hits: self.metrics.hits.load(Ordering::Relaxed),
+            misses: self.metrics.misses.load(Ordering::Relaxed),
+            evictions: self.metrics.evictions.load(Ordering::Relaxed),
+            fallbacks: self.metrics.fallbacks.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes(),
+            attribute_bytes: self.attribute_bytes.clone(),
+        }
     }
 }
 
@@ -1612,6 +2105,8 @@ impl SQLiteAttributeCache {
         let mut new = AttributeCaches::default();
         new.lightlike_cached_attributes = self.inner.lightlike_cached_attributes.clone();
         new.reverse_cached_attributes = self.inner.reverse_cached_attributes.clone();
+        new.budget = self.inner.budget;
+        new.metrics = self.inner.metrics.clone();
         new
     }
 
@@ -1676,6 +2171,9 @@ impl CachedAttributes for SQLiteAttributeCache {
         self.inner.get_causet_locale_for_causetid(topograph, attribute, causetid)
     }
 
+    // An attribute that has lost entries to eviction is still cached: readers check
+    // `is_causet_evicted` and `is_causet_locale_evicted` before trusting a miss.
+
     fn is_attribute_cached_reverse(&self, attribute: Causetid) -> bool {
         self.inner.is_attribute_cached_reverse(attribute)
     }
@@ -1712,6 +2210,361 @@ impl SQLiteAttributeCache {
     }
 }
 
+impl SQLiteAttributeCache {
+    pub fn budget(&self) -> AttributeCacheBudget {
+        self.inner.budget
+    }
+
+    /// Change the cache's memory limits, evicting immediately if it is now over them.
+    pub fn set_budget(&mut self, budget: AttributeCacheBudget) {
+        let caches = self.make_mut();
+        caches.budget = budget;
+        caches.enforce_budget();
+    }
+
+    pub fn stats(&self) -> AttributeCacheStats {
+        self.inner.stats()
+    }
+
+    pub fn is_causet_evicted(&self, attribute: Causetid, causetid: Causetid) -> bool {
+        self.inner.is_causet_evicted(attribute, causetid)
+    }
+
+    pub fn is_causet_locale_evicted(&self, attribute: Causetid, causet_locale: &causetq_TV) -> bool {
+        self.inner.is_causet_locale_evicted(attribute, causet_locale)
+    }
+
+    /// Make sure `e`'s causet_locales for `attribute` are in the cache, reading them from the store
+    /// if they were evicted. Does nothing for entries that were never evicted, because for those
+    /// a miss is authoritative.
+    pub fn fetch_if_evicted(&mut self, topograph: &Topograph, SQLite: &rusqlite::Connection, attribute: Causetid, causetid: Causetid) -> Result<()> {
+        if !self.inner.is_causet_evicted(attribute, causetid) {
+            return Ok(());
+        }
+        let multival = match topograph.attribute_for_causetid(attribute) {
+            Some(attr) => attr.multival,
+            None => return Ok(()),
+        };
+
+        AttributeCacheMetrics::count(&self.inner.metrics.fallbacks);
+        let attrs = AttributeSpec::specified(&::std::iter::once(attribute).collect(), topograph);
+        let caches = self.make_mut();
+
+        // Causet_locales asserted since the eviction are only some of a multi-valued causet's; drop
+        // them so that the fetch reads all of them.
+        if multival {
+            if let Some(c) = caches.multi_vals.get_mut(&attribute) {
+                c.e_vs.remove(&causetid);
+            }
+        }
+
+        // Making room for the fetched causet must not evict it again before it's read.
+        caches.pinned = Some((attribute, causetid));
+        let fetched = caches.extend_cache_for_causets_and_attributes(topograph, SQLite, attrs, &vec![causetid]);
+        caches.pinned = None;
+        fetched?;
+
+        if let Some(es) = caches.evicted_lightlike.get_mut(&attribute) {
+            es.remove(&causetid);
+        }
+        Ok(())
+    }
+
+    /// Like `get_causet_locale_for_causetid`, but a miss on an attribute that has had entries
+    /// evicted is answered from the store.
+    pub fn get_causet_locale_for_causetid_or_fetch(&mut self, topograph: &Topograph, SQLite: &rusqlite::Connection, attribute: Causetid, causetid: Causetid) -> Result<Option<causetq_TV>> {
+        self.fetch_if_evicted(topograph, SQLite, attribute, causetid)?;
+        Ok(self.get_causet_locale_for_causetid(topograph, attribute, causetid).cloned())
+    }
+
+    /// Like `get_causet_locales_for_causetid`, but a miss on an attribute that has had entries
+    /// evicted is answered from the store.
+    pub fn get_causet_locales_for_causetid_or_fetch(&mut self, topograph: &Topograph, SQLite: &rusqlite::Connection, attribute: Causetid, causetid: Causetid) -> Result<Option<Vec<causetq_TV>>> {
+        self.fetch_if_evicted(topograph, SQLite, attribute, causetid)?;
+        Ok(self.get_causet_locales_for_causetid(topograph, attribute, causetid).cloned())
+    }
+}
+
+//
+// Snapshots.
+//
+// A snapshot is a small sqlite database written next to the store. It records which attributes
+// were cached in which clock_vector, their [a e v] rows, which of their entries had been evicted,
+// and which store, topograph and last transaction the cache had seen. Loading a snapshot replays only the transactions committed
+// after that point, rather than re-reading every cached attribute from `causets`.
+//
+
+/// Bump this whenever the snapshot layout changes; snapshots of any other version are ignored.
+const CACHE_SNAPSHOT_VERSION: i32 = 3;
+
+lazy_static! {
+    static ref CACHE_SNAPSHOT_STATEMENTS: Vec<&'static str> = { vec![
+        r#"CREATE TABLE snapshot_spacetime (store INTEGER NOT NULL, topograph TEXT NOT NULL, last_tx INTEGER NOT NULL)"#,
+        r#"CREATE TABLE snapshot_attributes (a INTEGER NOT NULL PRIMARY KEY, lightlike TINYINT NOT NULL, reverse TINYINT NOT NULL)"#,
+        r#"CREATE TABLE snapshot_aevs (a INTEGER NOT NULL, e INTEGER NOT NULL, v BLOB NOT NULL, causet_locale_type_tag SMALLINT NOT NULL)"#,
+        r#"CREATE TABLE snapshot_evicted_lightlike (a INTEGER NOT NULL, e INTEGER NOT NULL)"#,
+        r#"CREATE TABLE snapshot_evicted_reverse (a INTEGER NOT NULL, v BLOB NOT NULL, causet_locale_type_tag SMALLINT NOT NULL)"#,
+    ]};
+}
+
+/// What a snapshot was taken of. A snapshot is only loaded into the store it was taken of, under
+/// the same topograph, and only if that store has seen every transaction the snapshot reflects.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CacheSnapshotOrigin {
+    /// Identifies the store.
+    pub store: i64,
+    /// The store's topograph, rendered so that only equal topographs render equally.
+    pub topograph: String,
+    /// The last transaction the cache reflects.
+    pub last_tx: Causetid,
+}
+
+impl AttributeCaches {
+    /// Every [a e v] held for `a`. The lightlike caches hold every row of an attribute, so we
+    /// only consult the reverse caches for attributes that are cached in reverse alone.
+    fn snapshot_aevs_for_attribute(&self, a: Causetid) -> Vec<(Causetid, causetq_TV)> {
+        if self.lightlike_cached_attributes.contains(&a) {
+            if let Some(c) = self.single_vals.get(&a) {
+                return c.e_v.iter().filter_map(|(e, v)| v.clone().map(|v| (*e, v))).collect();
+            }
+            if let Some(c) = self.multi_vals.get(&a) {
+                return c.e_vs.iter().flat_map(|(e, vs)| vs.iter().map(move |v| (*e, v.clone()))).collect();
+            }
+        }
+        if let Some(c) = self.unique_reverse.get(&a) {
+            return c.v_e.iter().filter_map(|(v, e)| e.map(|e| (e, v.clone()))).collect();
+        }
+        if let Some(c) = self.non_unique_reverse.get(&a) {
+            return c.v_es.iter().flat_map(|(v, es)| es.iter().map(move |e| (*e, v.clone()))).collect();
+        }
+        vec![]
+    }
+
+    /// Apply every transaction after `since_tx` that touches a cached attribute, in order.
+    fn replay_since(&mut self, topograph: &Topograph, SQLite: &rusqlite::Connection, since_tx: Causetid) -> Result<()> {
+        let cached: BTreeSet<Causetid> = self.lightlike_cached_attributes.union(&self.reverse_cached_attributes).cloned().collect();
+        if cached.is_empty() {
+            return Ok(());
+        }
+
+        let mut qb = SQLiteCausetQ::new();
+        qb.push_BerolinaSQL("SELECT a, e, v, causet_locale_type_tag, tx, added FROM transactions WHERE tx > ");
+        qb.push_BerolinaSQL(&since_tx.to_string());
+        qb.push_BerolinaSQL(" AND a IN (");
+        interpose!(item, cached,
+                   { qb.push_BerolinaSQL(&item.to_string()) },
+                   { qb.push_BerolinaSQL(", ") });
+        // Within a transaction, retractions sort before assertions.
+        qb.push_BerolinaSQL(") ORDER BY tx ASC, added ASC, a ASC, e ASC");
+        let BerolinaSQLQuery { BerolinaSQL, args } = qb.finish();
+        assert!(args.is_empty());
+
+        let mut stmt = SQLite.prepare(BerolinaSQL.as_str()).context(einsteindbErrorKind::CacheUpdateFailed)?;
+        let mut aev_factory = AevFactory::new();
+        let rows = stmt.query_map(&[], |event| {
+            let tx: Causetid = event.get(4);
+            let added: bool = event.get(5);
+            (tx, added, aev_factory.row_to_aev(event))
+        })?;
+
+        let mut current_tx = None;
+        let mut retractions: Vec<Aev> = vec![];
+        let mut assertions: Vec<Aev> = vec![];
+        for row in rows {
+            let (tx, added, aev) = row?;
+            if current_tx.map_or(false, |t| t != tx) {
+                self.update(topograph, retractions.drain(..), assertions.drain(..))?;
+            }
+            current_tx = Some(tx);
+            if added {
+                assertions.push(aev);
+            } else {
+                retractions.push(aev);
+            }
+        }
+        self.update(topograph, retractions.drain(..), assertions.drain(..))
+    }
+}
+
+impl SQLiteAttributeCache {
+    /// Write the cache to `path`, recording that it reflects `origin`: its store and topograph, and
+    /// every transaction up to `origin.last_tx`.
+    ///
+    /// The snapshot is written to a temporary file and renamed into place, so a crash never leaves
+    /// a partial snapshot behind.
+    pub fn write_snapshot<P>(&self, path: P, origin: &CacheSnapshotOrigin) -> Result<()> where P: AsRef<Path> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let _ = fs::remove_file(&tmp);
+
+        {
+            let mut snapshot = rusqlite::Connection::open(&tmp)?;
+            let tx = snapshot.transaction()?;
+            for statement in CACHE_SNAPSHOT_STATEMENTS.iter() {
+                tx.execute(statement, &[])?;
+            }
+            tx.execute(&format!("PRAGMA user_version = {}", CACHE_SNAPSHOT_VERSION), &[])?;
+            tx.execute("INSERT INTO snapshot_spacetime (store, topograph, last_tx) VALUES (?, ?, ?)",
+                       &[&origin.store, &origin.topograph, &origin.last_tx])?;
+
+            let caches = &self.inner;
+            let attributes: BTreeSet<Causetid> = caches.lightlike_cached_attributes.union(&caches.reverse_cached_attributes).cloned().collect();
+            {
+                let mut insert_attribute = tx.prepare("INSERT INTO snapshot_attributes (a, lightlike, reverse) VALUES (?, ?, ?)")?;
+                let mut insert_aev = tx.prepare("INSERT INTO snapshot_aevs (a, e, v, causet_locale_type_tag) VALUES (?, ?, ?, ?)")?;
+                for a in attributes {
+                    insert_attribute.execute(&[&a,
+                                               &caches.lightlike_cached_attributes.contains(&a),
+                                               &caches.reverse_cached_attributes.contains(&a)])?;
+                    for (e, v) in caches.snapshot_aevs_for_attribute(a) {
+                        let (causet_locale, causet_locale_type_tag) = v.to_BerolinaSQL_causet_locale_pair();
+                        insert_aev.execute(&[&a, &e, &causet_locale, &causet_locale_type_tag])?;
+                    }
+                }
+            }
+            {
+                // Without these, a reloaded cache would take misses on evicted entries to mean
+                // that there is no such causet_locale.
+                let mut insert_lightlike = tx.prepare("INSERT INTO snapshot_evicted_lightlike (a, e) VALUES (?, ?)")?;
+                for (a, es) in caches.evicted_lightlike.iter() {
+                    for e in es {
+                        insert_lightlike.execute(&[a, e])?;
+                    }
+                }
+                let mut insert_reverse = tx.prepare("INSERT INTO snapshot_evicted_reverse (a, v, causet_locale_type_tag) VALUES (?, ?, ?)")?;
+                for (a, vs) in caches.evicted_reverse.iter() {
+                    for v in vs {
+                        let (causet_locale, causet_locale_type_tag) = v.to_BerolinaSQL_causet_locale_pair();
+                        insert_reverse.execute(&[a, &causet_locale, &causet_locale_type_tag])?;
+                    }
+                }
+            }
+            tx.commit()?;
+        }
+
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Rebuild a cache from the snapshot at `path`, then bring it up to date with `SQLite`, whose
+    /// store, topograph and last transaction are `store`.
+    ///
+    /// Returns `None` if there is no usable snapshot -- it's missing, unreadable, from another
+    /// snapshot version, of another store or topograph, or newer than the store -- in which case
+    /// the caller should register attributes as usual. Cached attributes that no longer exist are
+    /// dropped; fulltext attributes are re-read from the store, because the transaction log holds
+    /// their search ids, not their text.
+    pub fn read_snapshot<P>(topograph: &Topograph, SQLite: &rusqlite::Connection, path: P, store: &CacheSnapshotOrigin) -> Result<Option<SQLiteAttributeCache>> where P: AsRef<Path> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        // A snapshot only saves work: one we can't read is rebuilt from the store instead.
+        let (mut caches, fulltext, last_tx) = match SQLiteAttributeCache::load_snapshot(topograph, path, store) {
+            Ok(Some(loaded)) => loaded,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                eprintln!("Ignoring unreadable attribute cache snapshot {}: {}.", path.display(), e);
+                return Ok(None);
+            },
+        };
+
+        caches.replay_since(topograph, SQLite, last_tx)?;
+
+        let mut cache = SQLiteAttributeCache { inner: Arc::new(caches) };
+        for (a, lightlike, reverse) in fulltext {
+            match (lightlike, reverse) {
+                (true, true) => cache.register(topograph, SQLite, a)?,
+                (true, false) => cache.register_lightlike(topograph, SQLite, a)?,
+                (false, true) => cache.register_reverse(topograph, SQLite, a)?,
+                (false, false) => {},
+            }
+        }
+        Ok(Some(cache))
+    }
+
+    /// Read the caches, the fulltext registrations and the last tx of the snapshot at `path`, if
+    /// it was taken of `store`.
+    fn load_snapshot(topograph: &Topograph, path: &Path, store: &CacheSnapshotOrigin) -> Result<Option<(AttributeCaches, Vec<(Causetid, bool, bool)>, Causetid)>> {
+        let snapshot = rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let version: i32 = snapshot.query_row("PRAGMA user_version", &[], |event| event.get(0))?;
+        if version != CACHE_SNAPSHOT_VERSION {
+            return Ok(None);
+        }
+        let origin = snapshot.query_row("SELECT store, topograph, last_tx FROM snapshot_spacetime", &[], |event| {
+            CacheSnapshotOrigin {
+                store: event.get(0),
+                topograph: event.get(1),
+                last_tx: event.get(2),
+            }
+        })?;
+        if origin.store != store.store || origin.topograph != store.topograph {
+            // A snapshot of another store, or of this one before its topograph changed.
+            return Ok(None);
+        }
+        if origin.last_tx > store.last_tx {
+            // The store was restored from something older than the snapshot.
+            return Ok(None);
+        }
+        let last_tx = origin.last_tx;
+
+        let mut caches = AttributeCaches::default();
+        let mut fulltext = vec![];
+        {
+            let mut stmt = snapshot.prepare("SELECT a, lightlike, reverse FROM snapshot_attributes")?;
+            let rows = stmt.query_map(&[], |event| (event.get(0), event.get(1), event.get(2)))?;
+            for row in rows {
+                let (a, lightlike, reverse): (Causetid, bool, bool) = row?;
+                match topograph.attribute_for_causetid(a) {
+                    None => continue,
+                    Some(attribute) if attribute.fulltext => {
+                        fulltext.push((a, lightlike, reverse));
+                        continue;
+                    },
+                    Some(_) => {},
+                }
+                if lightlike {
+                    caches.lightlike_cached_attributes.insert(a);
+                }
+                if reverse {
+                    caches.reverse_cached_attributes.insert(a);
+                }
+            }
+        }
+
+        {
+            let known: BTreeSet<Causetid> = caches.lightlike_cached_attributes.union(&caches.reverse_cached_attributes).cloned().collect();
+            let mut stmt = snapshot.prepare("SELECT a, e, v, causet_locale_type_tag FROM snapshot_aevs ORDER BY a ASC, e ASC")?;
+            let mut aev_factory = AevFactory::new();
+            let rows = stmt.query_map(&[], |event| aev_factory.row_to_aev(event))?;
+            let aevs = AevRows { rows: rows }.filter(|&(a, _, _)| known.contains(&a));
+            caches.accumulate_into_cache(None, topograph, aevs.peekable(), AccumulationBehavior::Add { replacing: false })?;
+
+            let mut stmt = snapshot.prepare("SELECT a, e FROM snapshot_evicted_lightlike")?;
+            let rows = stmt.query_map(&[], |event| (event.get(0), event.get(1)))?;
+            for row in rows {
+                let (a, e): (Causetid, Causetid) = row?;
+                if known.contains(&a) {
+                    caches.evicted_lightlike.entry(a).or_insert_with(Default::default).insert(e);
+                }
+            }
+
+            let mut stmt = snapshot.prepare("SELECT a, 0, v, causet_locale_type_tag FROM snapshot_evicted_reverse")?;
+            let rows = stmt.query_map(&[], |event| aev_factory.row_to_aev(event))?;
+            for (a, _, v) in (AevRows { rows: rows }) {
+                if known.contains(&a) {
+                    caches.evicted_reverse.entry(a).or_insert_with(Default::default).insert(v);
+                }
+            }
+        }
+
+        Ok(Some((caches, fulltext, last_tx)))
+    }
+}
+
 /// We maintain a diff on top of the `inner` -- existing -- cache.
 /// That involves tracking unregisterings and registerings.
 #[derive(Debug, Default)]
@@ -1911,6 +2764,16 @@ impl CachedAttributes for InProgressSQLiteAttributeCache {
 }
 
 impl InProgressSQLiteAttributeCache {
+    pub fn is_causet_evicted(&self, attribute: Causetid, causetid: Causetid) -> bool {
+        self.inner.is_causet_evicted(attribute, causetid) ||
+        self.overlay.is_causet_evicted(attribute, causetid)
+    }
+
+    pub fn is_causet_locale_evicted(&self, attribute: Causetid, causet_locale: &causetq_TV) -> bool {
+        self.inner.is_causet_locale_evicted(attribute, causet_locale) ||
+        self.overlay.is_causet_locale_evicted(attribute, causet_locale)
+    }
+
     /// Intended for use from tests.
     pub fn causet_locales_pairs<U>(&self, topograph: &Topograph, attribute: U) -> Option<&BTreeMap<Causetid, Vec<causetq_TV>>>
     where U: Into<Causetid> {